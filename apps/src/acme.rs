@@ -0,0 +1,660 @@
+// Copyright (C) 2025, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Minimal ACME (RFC 8555) client using the TLS-ALPN-01 challenge (RFC 8737).
+//!
+//! The QUIC listener can't answer TLS-ALPN-01 challenges itself, as they are
+//! validated over TLS on top of TCP, so a companion TCP listener is started
+//! for the duration of the challenge. The account key, certificate and
+//! certificate key are all stored in a single directory on disk.
+
+use std::io::Write;
+
+use std::net;
+
+use std::path;
+
+use std::sync::Arc;
+
+use std::time;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+
+use ring::rand::SystemRandom;
+use ring::signature::EcdsaKeyPair;
+use ring::signature::KeyPair;
+use ring::signature::ECDSA_P256_SHA256_FIXED_SIGNING;
+
+use serde_json::json;
+
+/// The ALPN protocol identifier used by TLS-ALPN-01 validation.
+const ACME_TLS_ALPN: &[u8] = b"acme-tls/1";
+
+/// File names used inside the ACME state directory.
+const ACCOUNT_KEY_FILE: &str = "account.key";
+const CERT_FILE: &str = "cert.pem";
+const KEY_FILE: &str = "key.pem";
+
+/// How many times order and authorization resources are polled before giving
+/// up.
+const MAX_POLL_ATTEMPTS: usize = 30;
+
+const POLL_INTERVAL: time::Duration = time::Duration::from_secs(2);
+
+#[derive(Debug)]
+pub enum AcmeError {
+    /// Reading or writing the state directory failed.
+    Io(std::io::Error),
+
+    /// The ACME server returned an error or an unexpected response.
+    Protocol(String),
+
+    /// A challenge or order ended up in the `invalid` state.
+    Invalid(String),
+
+    Other(String),
+}
+
+impl std::fmt::Display for AcmeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            AcmeError::Io(e) => write!(f, "I/O error: {e}"),
+            AcmeError::Protocol(e) => write!(f, "ACME protocol error: {e}"),
+            AcmeError::Invalid(e) => write!(f, "ACME validation failed: {e}"),
+            AcmeError::Other(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl From<std::io::Error> for AcmeError {
+    fn from(err: std::io::Error) -> Self {
+        AcmeError::Io(err)
+    }
+}
+
+impl From<rcgen::Error> for AcmeError {
+    fn from(err: rcgen::Error) -> Self {
+        AcmeError::Other(format!("certificate generation failed: {err}"))
+    }
+}
+
+pub type Result<T> = std::result::Result<T, AcmeError>;
+
+/// ACME configuration.
+#[derive(Clone, Debug)]
+pub struct AcmeConfig {
+    /// The ACME directory URL.
+    pub directory_url: String,
+
+    /// The domain names to request a certificate for.
+    pub domains: Vec<String>,
+
+    /// Optional contact email for the ACME account.
+    pub contact: Option<String>,
+
+    /// Directory where the account key, certificate and key are stored.
+    pub state_dir: path::PathBuf,
+
+    /// The TCP address the TLS-ALPN-01 responder listens on.
+    pub challenge_listen: net::SocketAddr,
+
+    /// Certificates older than this are renewed.
+    pub renew_after: time::Duration,
+}
+
+impl AcmeConfig {
+    /// Path of the PEM-encoded certificate chain.
+    pub fn cert_path(&self) -> path::PathBuf {
+        self.state_dir.join(CERT_FILE)
+    }
+
+    /// Path of the PEM-encoded certificate private key.
+    pub fn key_path(&self) -> path::PathBuf {
+        self.state_dir.join(KEY_FILE)
+    }
+
+    /// Returns true if there is no stored certificate, or if it is older than
+    /// `renew_after`.
+    pub fn needs_renewal(&self) -> bool {
+        let modified = std::fs::metadata(self.cert_path())
+            .and_then(|m| m.modified())
+            .ok();
+
+        match modified.and_then(|m| m.elapsed().ok()) {
+            Some(age) => age >= self.renew_after,
+
+            None => true,
+        }
+    }
+}
+
+/// Obtains a new certificate if the stored one is missing or due for renewal.
+///
+/// Returns `true` if a new certificate was written to the state directory.
+pub fn ensure_certificate(config: &AcmeConfig) -> Result<bool> {
+    if !config.needs_renewal() {
+        debug!("ACME certificate at {:?} is fresh", config.cert_path());
+        return Ok(false);
+    }
+
+    info!("requesting ACME certificate for {:?}", config.domains);
+
+    std::fs::create_dir_all(&config.state_dir)?;
+
+    let account_key = load_or_create_account_key(&config.state_dir)?;
+    let mut client = AcmeClient::new(&config.directory_url, account_key)?;
+
+    client.register(config.contact.as_deref())?;
+
+    let (cert_pem, key_pem) =
+        client.order(&config.domains, config.challenge_listen)?;
+
+    write_atomic(&config.key_path(), key_pem.as_bytes())?;
+    write_atomic(&config.cert_path(), cert_pem.as_bytes())?;
+
+    info!("ACME certificate stored at {:?}", config.cert_path());
+
+    Ok(true)
+}
+
+/// Spawns a thread that periodically calls [`ensure_certificate()`], and
+/// invokes `on_renewed` every time a new certificate is stored.
+pub fn spawn_renewal<F>(
+    config: AcmeConfig, check_interval: time::Duration, on_renewed: F,
+) -> std::thread::JoinHandle<()>
+where
+    F: Fn() + Send + 'static,
+{
+    std::thread::spawn(move || loop {
+        std::thread::sleep(check_interval);
+
+        match ensure_certificate(&config) {
+            Ok(true) => on_renewed(),
+
+            Ok(false) => (),
+
+            Err(e) => error!("ACME renewal failed: {e}"),
+        }
+    })
+}
+
+fn write_atomic(path: &path::Path, data: &[u8]) -> Result<()> {
+    let tmp = path.with_extension("tmp");
+
+    let mut f = std::fs::File::create(&tmp)?;
+    f.write_all(data)?;
+    f.sync_all()?;
+
+    std::fs::rename(&tmp, path)?;
+
+    Ok(())
+}
+
+fn load_or_create_account_key(dir: &path::Path) -> Result<EcdsaKeyPair> {
+    let rng = SystemRandom::new();
+    let path = dir.join(ACCOUNT_KEY_FILE);
+
+    let pkcs8 = match std::fs::read(&path) {
+        Ok(v) => v,
+
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            let doc = EcdsaKeyPair::generate_pkcs8(
+                &ECDSA_P256_SHA256_FIXED_SIGNING,
+                &rng,
+            )
+            .map_err(|_| AcmeError::Other("account key generation".into()))?;
+
+            write_atomic(&path, doc.as_ref())?;
+
+            doc.as_ref().to_vec()
+        },
+
+        Err(e) => return Err(e.into()),
+    };
+
+    EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &pkcs8, &rng)
+        .map_err(|_| AcmeError::Other(format!("invalid account key {path:?}")))
+}
+
+fn b64(data: &[u8]) -> String {
+    URL_SAFE_NO_PAD.encode(data)
+}
+
+struct AcmeClient {
+    key: EcdsaKeyPair,
+    rng: SystemRandom,
+
+    /// The account URL, used as `kid` once registered.
+    kid: Option<String>,
+
+    nonce: Option<String>,
+
+    new_nonce_url: String,
+    new_account_url: String,
+    new_order_url: String,
+}
+
+impl AcmeClient {
+    fn new(directory_url: &str, key: EcdsaKeyPair) -> Result<Self> {
+        let dir = get_json(directory_url)?;
+
+        let url_of = |name: &str| -> Result<String> {
+            dir[name].as_str().map(str::to_string).ok_or_else(|| {
+                AcmeError::Protocol(format!("directory is missing {name}"))
+            })
+        };
+
+        Ok(AcmeClient {
+            key,
+            rng: SystemRandom::new(),
+            kid: None,
+            nonce: None,
+            new_nonce_url: url_of("newNonce")?,
+            new_account_url: url_of("newAccount")?,
+            new_order_url: url_of("newOrder")?,
+        })
+    }
+
+    /// The JWK representation of the account public key, with members in
+    /// lexicographic order as required for the thumbprint (RFC 7638).
+    fn jwk(&self) -> serde_json::Value {
+        // Uncompressed point: 0x04 || x || y.
+        let public = self.key.public_key().as_ref();
+
+        json!({
+            "crv": "P-256",
+            "kty": "EC",
+            "x": b64(&public[1..33]),
+            "y": b64(&public[33..65]),
+        })
+    }
+
+    fn thumbprint(&self) -> String {
+        let jwk = serde_json::to_string(&self.jwk()).unwrap();
+
+        b64(ring::digest::digest(&ring::digest::SHA256, jwk.as_bytes()).as_ref())
+    }
+
+    fn key_authorization(&self, token: &str) -> String {
+        format!("{token}.{}", self.thumbprint())
+    }
+
+    fn nonce(&mut self) -> Result<String> {
+        if let Some(nonce) = self.nonce.take() {
+            return Ok(nonce);
+        }
+
+        let resp = ureq::head(&self.new_nonce_url)
+            .call()
+            .map_err(|e| AcmeError::Protocol(e.to_string()))?;
+
+        resp.header("Replay-Nonce")
+            .map(str::to_string)
+            .ok_or_else(|| AcmeError::Protocol("missing Replay-Nonce".into()))
+    }
+
+    /// Sends a JWS-signed POST request. A `None` payload sends a POST-as-GET.
+    fn post(
+        &mut self, url: &str, payload: Option<&serde_json::Value>,
+    ) -> Result<(Option<String>, String)> {
+        let nonce = self.nonce()?;
+
+        let mut protected = json!({
+            "alg": "ES256",
+            "nonce": nonce,
+            "url": url,
+        });
+
+        match &self.kid {
+            Some(kid) => protected["kid"] = json!(kid),
+
+            None => protected["jwk"] = self.jwk(),
+        }
+
+        let protected = b64(protected.to_string().as_bytes());
+
+        let payload = match payload {
+            Some(v) => b64(v.to_string().as_bytes()),
+
+            None => String::new(),
+        };
+
+        let signing_input = format!("{protected}.{payload}");
+
+        let signature = self
+            .key
+            .sign(&self.rng, signing_input.as_bytes())
+            .map_err(|_| AcmeError::Other("JWS signing failed".into()))?;
+
+        let body = json!({
+            "protected": protected,
+            "payload": payload,
+            "signature": b64(signature.as_ref()),
+        });
+
+        let resp = match ureq::post(url)
+            .set("Content-Type", "application/jose+json")
+            .send_string(&body.to_string())
+        {
+            Ok(v) => v,
+
+            Err(ureq::Error::Status(code, resp)) => {
+                let problem = resp.into_string().unwrap_or_default();
+
+                return Err(AcmeError::Protocol(format!(
+                    "{url} returned {code}: {problem}"
+                )));
+            },
+
+            Err(e) => return Err(AcmeError::Protocol(e.to_string())),
+        };
+
+        self.nonce = resp.header("Replay-Nonce").map(str::to_string);
+
+        let location = resp.header("Location").map(str::to_string);
+
+        let body = resp.into_string()?;
+
+        Ok((location, body))
+    }
+
+    fn post_json(
+        &mut self, url: &str, payload: Option<&serde_json::Value>,
+    ) -> Result<(Option<String>, serde_json::Value)> {
+        let (location, body) = self.post(url, payload)?;
+
+        let value = serde_json::from_str(&body)
+            .map_err(|e| AcmeError::Protocol(format!("invalid JSON: {e}")))?;
+
+        Ok((location, value))
+    }
+
+    fn register(&mut self, contact: Option<&str>) -> Result<()> {
+        let mut payload = json!({ "termsOfServiceAgreed": true });
+
+        if let Some(contact) = contact {
+            payload["contact"] = json!([format!("mailto:{contact}")]);
+        }
+
+        let url = self.new_account_url.clone();
+        let (location, _) = self.post_json(&url, Some(&payload))?;
+
+        let kid = location.ok_or_else(|| {
+            AcmeError::Protocol("account response without Location".into())
+        })?;
+
+        debug!("using ACME account {kid}");
+
+        self.kid = Some(kid);
+
+        Ok(())
+    }
+
+    /// Places an order for `domains` and drives it to completion, returning
+    /// the PEM-encoded certificate chain and private key.
+    fn order(
+        &mut self, domains: &[String], challenge_listen: net::SocketAddr,
+    ) -> Result<(String, String)> {
+        let identifiers: Vec<_> = domains
+            .iter()
+            .map(|d| json!({ "type": "dns", "value": d }))
+            .collect();
+
+        let url = self.new_order_url.clone();
+        let (order_url, order) =
+            self.post_json(&url, Some(&json!({ "identifiers": identifiers })))?;
+
+        let order_url = order_url.ok_or_else(|| {
+            AcmeError::Protocol("order response without Location".into())
+        })?;
+
+        let authorizations: Vec<String> = order["authorizations"]
+            .as_array()
+            .map(|a| {
+                a.iter()
+                    .filter_map(|v| v.as_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        for authz_url in authorizations {
+            self.authorize(&authz_url, challenge_listen)?;
+        }
+
+        // Generate the certificate key and CSR, and finalize the order.
+        let cert_key = rcgen::KeyPair::generate()?;
+        let csr = rcgen::CertificateParams::new(domains.to_vec())?
+            .serialize_request(&cert_key)?;
+
+        let finalize_url = order["finalize"]
+            .as_str()
+            .ok_or_else(|| AcmeError::Protocol("order without finalize".into()))?
+            .to_string();
+
+        self.post_json(
+            &finalize_url,
+            Some(&json!({ "csr": b64(csr.der().as_ref()) })),
+        )?;
+
+        let order = self.poll_until(&order_url, "valid")?;
+
+        let cert_url = order["certificate"]
+            .as_str()
+            .ok_or_else(|| {
+                AcmeError::Protocol("order without certificate".into())
+            })?
+            .to_string();
+
+        let (_, cert_pem) = self.post(&cert_url, None)?;
+
+        Ok((cert_pem, cert_key.serialize_pem()))
+    }
+
+    fn authorize(
+        &mut self, authz_url: &str, challenge_listen: net::SocketAddr,
+    ) -> Result<()> {
+        let (_, authz) = self.post_json(authz_url, None)?;
+
+        if authz["status"] == "valid" {
+            return Ok(());
+        }
+
+        let domain = authz["identifier"]["value"]
+            .as_str()
+            .ok_or_else(|| {
+                AcmeError::Protocol("authorization without identifier".into())
+            })?
+            .to_string();
+
+        let challenge = authz["challenges"]
+            .as_array()
+            .and_then(|c| c.iter().find(|c| c["type"] == "tls-alpn-01"))
+            .ok_or_else(|| {
+                AcmeError::Protocol(format!(
+                    "no tls-alpn-01 challenge for {domain}"
+                ))
+            })?;
+
+        let token = challenge["token"].as_str().unwrap_or_default();
+        let challenge_url = challenge["url"].as_str().unwrap_or_default();
+
+        let key_auth = self.key_authorization(token);
+
+        let responder =
+            ChallengeResponder::start(challenge_listen, &domain, &key_auth)?;
+
+        // Tell the server we are ready to be validated.
+        self.post_json(challenge_url, Some(&json!({})))?;
+
+        let res = self.poll_until(authz_url, "valid");
+
+        responder.stop();
+
+        res.map(|_| ())
+    }
+
+    /// Polls the resource at `url` until its status becomes `status`.
+    fn poll_until(
+        &mut self, url: &str, status: &str,
+    ) -> Result<serde_json::Value> {
+        for _ in 0..MAX_POLL_ATTEMPTS {
+            let (_, res) = self.post_json(url, None)?;
+
+            if res["status"] == status {
+                return Ok(res);
+            }
+
+            if res["status"] == "invalid" {
+                return Err(AcmeError::Invalid(res.to_string()));
+            }
+
+            std::thread::sleep(POLL_INTERVAL);
+        }
+
+        Err(AcmeError::Protocol(format!("timed out waiting for {url}")))
+    }
+}
+
+/// TCP listener answering TLS-ALPN-01 validation handshakes.
+struct ChallengeResponder {
+    addr: net::SocketAddr,
+    stop: Arc<std::sync::atomic::AtomicBool>,
+    thread: std::thread::JoinHandle<()>,
+}
+
+impl ChallengeResponder {
+    fn start(
+        listen: net::SocketAddr, domain: &str, key_auth: &str,
+    ) -> Result<Self> {
+        let tls_config = Arc::new(challenge_tls_config(domain, key_auth)?);
+
+        let listener = net::TcpListener::bind(listen)?;
+        let addr = listener.local_addr()?;
+
+        info!("TLS-ALPN-01 responder for {domain} listening on {addr}");
+
+        let stop = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let stop_thread = stop.clone();
+
+        let thread = std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                if stop_thread.load(std::sync::atomic::Ordering::Relaxed) {
+                    break;
+                }
+
+                let mut stream = match stream {
+                    Ok(v) => v,
+
+                    Err(e) => {
+                        error!("TLS-ALPN-01 accept failed: {e}");
+                        continue;
+                    },
+                };
+
+                stream
+                    .set_read_timeout(Some(time::Duration::from_secs(10)))
+                    .ok();
+
+                let mut conn =
+                    match rustls::ServerConnection::new(tls_config.clone()) {
+                        Ok(v) => v,
+
+                        Err(e) => {
+                            error!("TLS-ALPN-01 session setup failed: {e}");
+                            continue;
+                        },
+                    };
+
+                while conn.is_handshaking() {
+                    if let Err(e) = conn.complete_io(&mut stream) {
+                        debug!("TLS-ALPN-01 handshake failed: {e}");
+                        break;
+                    }
+                }
+
+                conn.send_close_notify();
+                conn.complete_io(&mut stream).ok();
+            }
+        });
+
+        Ok(ChallengeResponder { addr, stop, thread })
+    }
+
+    fn stop(self) {
+        self.stop.store(true, std::sync::atomic::Ordering::Relaxed);
+
+        // Wake up the accept loop.
+        net::TcpStream::connect(self.addr).ok();
+
+        self.thread.join().ok();
+    }
+}
+
+/// Builds a TLS server config presenting the self-signed TLS-ALPN-01
+/// validation certificate for `domain`.
+fn challenge_tls_config(
+    domain: &str, key_auth: &str,
+) -> Result<rustls::ServerConfig> {
+    let digest = ring::digest::digest(&ring::digest::SHA256, key_auth.as_bytes());
+
+    let key = rcgen::KeyPair::generate()?;
+
+    let mut params = rcgen::CertificateParams::new(vec![domain.to_string()])?;
+    params.custom_extensions =
+        vec![rcgen::CustomExtension::new_acme_identifier(digest.as_ref())];
+
+    let cert = params.self_signed(&key)?;
+
+    let key = rustls::pki_types::PrivateKeyDer::Pkcs8(
+        rustls::pki_types::PrivatePkcs8KeyDer::from(key.serialize_der()),
+    );
+
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+
+    let mut config = rustls::ServerConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .and_then(|b| {
+            b.with_no_client_auth()
+                .with_single_cert(vec![cert.der().clone()], key)
+        })
+        .map_err(|e| AcmeError::Other(format!("TLS config failed: {e}")))?;
+
+    config.alpn_protocols = vec![ACME_TLS_ALPN.to_vec()];
+
+    Ok(config)
+}
+
+fn get_json(url: &str) -> Result<serde_json::Value> {
+    let resp = ureq::get(url)
+        .call()
+        .map_err(|e| AcmeError::Protocol(e.to_string()))?;
+
+    let body = resp.into_string()?;
+
+    serde_json::from_str(&body)
+        .map_err(|e| AcmeError::Protocol(format!("invalid JSON: {e}")))
+}
@@ -0,0 +1,563 @@
+// Copyright (C) 2025, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! HTTP Alternative Services (RFC 7838) discovery.
+//!
+//! Browsers usually learn that an origin supports HTTP/3 from the `Alt-Svc`
+//! header of a response received over TCP. This module implements that first
+//! step with a minimal HTTP/1.1 over TLS client, and selects the advertised
+//! alternative that the QUIC connection should then be made to.
+
+use std::io::Read;
+use std::io::Write;
+
+use std::net;
+
+use std::net::ToSocketAddrs;
+
+use std::sync::Arc;
+
+use std::time;
+
+use rustls::client::danger::HandshakeSignatureValid;
+use rustls::client::danger::ServerCertVerified;
+use rustls::client::danger::ServerCertVerifier;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::CertificateDer;
+use rustls::pki_types::ServerName;
+use rustls::pki_types::UnixTime;
+
+/// The largest response header section that is read before giving up.
+const MAX_HEADER_SIZE: usize = 64 * 1024;
+
+const IO_TIMEOUT: time::Duration = time::Duration::from_secs(10);
+
+#[derive(Debug)]
+pub enum AltSvcError {
+    /// The TCP connection or TLS handshake failed.
+    Io(std::io::Error),
+
+    /// The origin's response couldn't be parsed.
+    Http(String),
+
+    /// The `Alt-Svc` header is malformed.
+    InvalidHeader(String),
+
+    /// None of the advertised alternatives can be used.
+    NoAlternative(String),
+
+    Other(String),
+}
+
+impl std::fmt::Display for AltSvcError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            AltSvcError::Io(e) => write!(f, "I/O error: {e}"),
+            AltSvcError::Http(e) => write!(f, "invalid HTTP response: {e}"),
+            AltSvcError::InvalidHeader(e) =>
+                write!(f, "invalid Alt-Svc header: {e}"),
+            AltSvcError::NoAlternative(e) =>
+                write!(f, "no usable alternative service: {e}"),
+            AltSvcError::Other(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl From<std::io::Error> for AltSvcError {
+    fn from(err: std::io::Error) -> Self {
+        AltSvcError::Io(err)
+    }
+}
+
+impl From<rustls::Error> for AltSvcError {
+    fn from(err: rustls::Error) -> Self {
+        AltSvcError::Io(std::io::Error::other(err))
+    }
+}
+
+pub type Result<T> = std::result::Result<T, AltSvcError>;
+
+/// A single alternative advertised in an `Alt-Svc` header.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AltSvc {
+    /// The ALPN protocol identifier of the alternative, e.g. `h3`.
+    pub protocol: Vec<u8>,
+
+    /// The alternative host, or `None` if it is the origin's host.
+    pub host: Option<String>,
+
+    /// The alternative port.
+    pub port: u16,
+
+    /// How long the alternative is valid for, in seconds.
+    pub max_age: u64,
+
+    /// Whether the alternative survives network changes.
+    pub persist: bool,
+}
+
+/// The default freshness lifetime of an alternative, in seconds.
+const DEFAULT_MAX_AGE: u64 = 24 * 60 * 60;
+
+/// Configuration of the TCP request used for discovery.
+#[derive(Clone, Debug, Default)]
+pub struct DiscoveryConfig {
+    /// Don't verify the origin's certificate.
+    pub no_verify: bool,
+
+    /// PEM file with the origin's CA, if not publicly trusted.
+    pub trust_origin_ca_pem: Option<String>,
+}
+
+/// The QUIC endpoint selected from the origin's `Alt-Svc` header.
+#[derive(Clone, Debug)]
+pub struct Discovered {
+    /// The advertised alternative.
+    pub alt_svc: AltSvc,
+
+    /// The address the alternative resolved to.
+    pub peer_addr: net::SocketAddr,
+}
+
+/// Fetches `url` over TCP and returns the first alternative advertised by the
+/// origin whose protocol is in `alpns`, together with its resolved address.
+pub fn discover(
+    url: &url::Url, alpns: &[&[u8]], config: &DiscoveryConfig,
+) -> Result<Discovered> {
+    let header = fetch_alt_svc(url, config)?.ok_or_else(|| {
+        AltSvcError::NoAlternative(format!("{url} has no Alt-Svc header"))
+    })?;
+
+    info!("{url} advertised Alt-Svc: {header}");
+
+    let services = parse(&header)?;
+
+    let alt_svc = select(&services, alpns)?;
+
+    let origin_host = url
+        .host_str()
+        .ok_or_else(|| AltSvcError::Other(format!("{url} has no host")))?;
+
+    let host = alt_svc.host.as_deref().unwrap_or(origin_host);
+
+    // IPv6 literals are bracketed in the authority but not when resolving.
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+
+    let peer_addr =
+        (host, alt_svc.port)
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| {
+                AltSvcError::NoAlternative(format!("{host} did not resolve"))
+            })?;
+
+    Ok(Discovered { alt_svc, peer_addr })
+}
+
+/// Parses the value of an `Alt-Svc` header field.
+///
+/// The special value `clear` results in an empty list.
+pub fn parse(value: &str) -> Result<Vec<AltSvc>> {
+    let value = value.trim();
+
+    if value == "clear" {
+        return Ok(Vec::new());
+    }
+
+    split_unquoted(value, ',')
+        .into_iter()
+        .filter(|v| !v.trim().is_empty())
+        .map(|v| parse_alternative(&v))
+        .collect()
+}
+
+/// Returns the first alternative that can be used with one of `alpns`.
+///
+/// Alternatives are listed in the origin's order of preference, so the first
+/// valid one wins.
+pub fn select(services: &[AltSvc], alpns: &[&[u8]]) -> Result<AltSvc> {
+    if services.is_empty() {
+        return Err(AltSvcError::NoAlternative(
+            "all alternatives were cleared".to_string(),
+        ));
+    }
+
+    for alt_svc in services {
+        if !alpns.contains(&alt_svc.protocol.as_slice()) {
+            debug!(
+                "skipping alternative with unsupported protocol {}",
+                String::from_utf8_lossy(&alt_svc.protocol)
+            );
+            continue;
+        }
+
+        if alt_svc.max_age == 0 {
+            debug!("skipping expired alternative {alt_svc:?}");
+            continue;
+        }
+
+        if alt_svc.port == 0 {
+            debug!("skipping alternative with port 0 {alt_svc:?}");
+            continue;
+        }
+
+        return Ok(alt_svc.clone());
+    }
+
+    Err(AltSvcError::NoAlternative(
+        "no advertised protocol is supported".to_string(),
+    ))
+}
+
+fn parse_alternative(value: &str) -> Result<AltSvc> {
+    let mut params = split_unquoted(value, ';').into_iter();
+
+    // The first element is always present, even if empty.
+    let alternative = params.next().unwrap_or_default();
+
+    let (protocol, authority) = alternative.split_once('=').ok_or_else(|| {
+        AltSvcError::InvalidHeader(format!("missing authority in {alternative}"))
+    })?;
+
+    let protocol = percent_decode(protocol.trim())?;
+
+    let authority = unquote(authority.trim()).ok_or_else(|| {
+        AltSvcError::InvalidHeader(format!("unquoted authority {authority}"))
+    })?;
+
+    let (host, port) = authority.rsplit_once(':').ok_or_else(|| {
+        AltSvcError::InvalidHeader(format!("missing port in {authority}"))
+    })?;
+
+    let port = port.parse::<u16>().map_err(|_| {
+        AltSvcError::InvalidHeader(format!("invalid port in {authority}"))
+    })?;
+
+    if host.chars().any(|c| c.is_whitespace() || c == '/') {
+        return Err(AltSvcError::InvalidHeader(format!(
+            "invalid host in {authority}"
+        )));
+    }
+
+    let host = if host.is_empty() {
+        None
+    } else {
+        Some(host.to_string())
+    };
+
+    let mut alt_svc = AltSvc {
+        protocol,
+        host,
+        port,
+        max_age: DEFAULT_MAX_AGE,
+        persist: false,
+    };
+
+    for param in params {
+        let (name, value) = match param.split_once('=') {
+            Some((name, value)) => (name.trim(), value.trim()),
+
+            None => continue,
+        };
+
+        let value = unquote(value).unwrap_or_else(|| value.to_string());
+
+        match name.to_ascii_lowercase().as_str() {
+            "ma" =>
+                alt_svc.max_age = value.parse().map_err(|_| {
+                    AltSvcError::InvalidHeader(format!("invalid ma={value}"))
+                })?,
+
+            "persist" => alt_svc.persist = value == "1",
+
+            // Unknown parameters must be ignored.
+            _ => (),
+        }
+    }
+
+    Ok(alt_svc)
+}
+
+/// Splits `value` on `delim`, ignoring delimiters inside quoted strings.
+fn split_unquoted(value: &str, delim: char) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut escaped = false;
+
+    for c in value.chars() {
+        match c {
+            _ if escaped => escaped = false,
+
+            '\\' if quoted => escaped = true,
+
+            '"' => quoted = !quoted,
+
+            c if c == delim && !quoted => {
+                parts.push(std::mem::take(&mut current));
+                continue;
+            },
+
+            _ => (),
+        }
+
+        current.push(c);
+    }
+
+    parts.push(current);
+
+    parts
+}
+
+/// Returns the content of a quoted string, or `None` if `value` isn't quoted.
+fn unquote(value: &str) -> Option<String> {
+    let value = value.strip_prefix('"')?.strip_suffix('"')?;
+
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => out.extend(chars.next()),
+
+            c => out.push(c),
+        }
+    }
+
+    Some(out)
+}
+
+fn percent_decode(value: &str) -> Result<Vec<u8>> {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] != b'%' {
+            out.push(bytes[i]);
+            i += 1;
+            continue;
+        }
+
+        let byte = value
+            .get(i + 1..i + 3)
+            .and_then(|v| u8::from_str_radix(v, 16).ok())
+            .ok_or_else(|| {
+                AltSvcError::InvalidHeader(format!("invalid protocol {value}"))
+            })?;
+
+        out.push(byte);
+        i += 3;
+    }
+
+    Ok(out)
+}
+
+/// Sends a GET request for `url` over HTTP/1.1 and returns the `Alt-Svc`
+/// header of the response, if any. Multiple header lines are combined.
+fn fetch_alt_svc(
+    url: &url::Url, config: &DiscoveryConfig,
+) -> Result<Option<String>> {
+    if url.scheme() != "https" {
+        return Err(AltSvcError::Other(format!(
+            "Alt-Svc discovery requires an https URL, got {url}"
+        )));
+    }
+
+    let host = url
+        .host_str()
+        .ok_or_else(|| AltSvcError::Other(format!("{url} has no host")))?;
+
+    let port = url.port_or_known_default().unwrap_or(443);
+
+    let server_name =
+        ServerName::try_from(host.trim_start_matches('[').trim_end_matches(']'))
+            .map_err(|e| AltSvcError::Other(format!("invalid server name: {e}")))?
+            .to_owned();
+
+    let tls_config = Arc::new(tls_config(config)?);
+
+    let mut conn = rustls::ClientConnection::new(tls_config, server_name)?;
+
+    let addr = url
+        .socket_addrs(|| Some(port))?
+        .into_iter()
+        .next()
+        .ok_or_else(|| AltSvcError::Other(format!("{host} did not resolve")))?;
+
+    debug!("fetching {url} over TCP from {addr}");
+
+    let mut sock = net::TcpStream::connect_timeout(&addr, IO_TIMEOUT)?;
+    sock.set_read_timeout(Some(IO_TIMEOUT))?;
+    sock.set_write_timeout(Some(IO_TIMEOUT))?;
+
+    let mut stream = rustls::Stream::new(&mut conn, &mut sock);
+
+    let authority = match url.port() {
+        Some(port) => format!("{host}:{port}"),
+
+        None => host.to_string(),
+    };
+
+    let path = &url[url::Position::BeforePath..url::Position::AfterQuery];
+
+    let req = format!(
+        "GET {path} HTTP/1.1\r\n\
+         Host: {authority}\r\n\
+         User-Agent: quiche\r\n\
+         Connection: close\r\n\
+         \r\n"
+    );
+
+    stream.write_all(req.as_bytes())?;
+
+    // Only the header section is needed, the body is never read.
+    let mut resp = Vec::new();
+    let mut buf = [0; 4096];
+
+    let header_len = loop {
+        if let Some(pos) = resp.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos;
+        }
+
+        if resp.len() > MAX_HEADER_SIZE {
+            return Err(AltSvcError::Http("header section too large".into()));
+        }
+
+        let len = stream.read(&mut buf)?;
+
+        if len == 0 {
+            return Err(AltSvcError::Http("truncated header section".into()));
+        }
+
+        resp.extend_from_slice(&buf[..len]);
+    };
+
+    let header = std::str::from_utf8(&resp[..header_len])
+        .map_err(|_| AltSvcError::Http("non UTF-8 header section".into()))?;
+
+    let mut lines = header.split("\r\n");
+
+    let status = lines.next().unwrap_or_default();
+
+    if !status.starts_with("HTTP/1.") {
+        return Err(AltSvcError::Http(format!("invalid status line {status}")));
+    }
+
+    info!("{url} responded over TCP with {status}");
+
+    let values: Vec<&str> = lines
+        .filter_map(|line| line.split_once(':'))
+        .filter(|(name, _)| name.trim().eq_ignore_ascii_case("alt-svc"))
+        .map(|(_, value)| value.trim())
+        .collect();
+
+    if values.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(values.join(", ")))
+}
+
+fn tls_config(config: &DiscoveryConfig) -> Result<rustls::ClientConfig> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+
+    let builder = rustls::ClientConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()?;
+
+    let mut tls_config = if config.no_verify {
+        builder
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(NoVerifier(provider)))
+            .with_no_client_auth()
+    } else {
+        let mut roots = rustls::RootCertStore::empty();
+
+        match &config.trust_origin_ca_pem {
+            Some(path) =>
+                for cert in CertificateDer::pem_file_iter(path).map_err(|e| {
+                    AltSvcError::Other(format!("error loading {path}: {e}"))
+                })? {
+                    let cert = cert.map_err(|e| {
+                        AltSvcError::Other(format!("error loading {path}: {e}"))
+                    })?;
+
+                    roots.add(cert)?;
+                },
+
+            None => roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned()),
+        }
+
+        builder.with_root_certificates(roots).with_no_client_auth()
+    };
+
+    tls_config.alpn_protocols = vec![b"http/1.1".to_vec()];
+
+    Ok(tls_config)
+}
+
+/// Certificate verifier used with `--no-verify`, which accepts any
+/// certificate.
+#[derive(Debug)]
+struct NoVerifier(Arc<rustls::crypto::CryptoProvider>);
+
+impl ServerCertVerifier for NoVerifier {
+    fn verify_server_cert(
+        &self, _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>], _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8], _now: UnixTime,
+    ) -> std::result::Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self, message: &[u8], cert: &CertificateDer<'_>,
+        dss: &rustls::DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self, message: &[u8], cert: &CertificateDer<'_>,
+        dss: &rustls::DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<rustls::SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
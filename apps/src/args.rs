@@ -26,16 +26,34 @@
 
 use super::common::alpns;
 
+use super::socket::IoBackend;
+
 pub trait Args {
     fn with_docopt(docopt: &docopt::Docopt) -> Self;
 }
 
+const UNSUPPORTED_PROTOCOLS: &str =
+    "Unsupported HTTP version and DATAGRAM protocol.";
+
+/// Parses the value of the argument `name`.
+fn parse_arg<T: std::str::FromStr>(
+    args: &docopt::ArgvMap, name: &str,
+) -> Result<T, String> {
+    let v = args.get_str(name);
+
+    v.parse::<T>()
+        .map_err(|_| format!("invalid value {v:?} for {name}"))
+}
+
 /// Contains commons arguments for creating a quiche QUIC connection.
+#[derive(Clone)]
 pub struct CommonArgs {
     pub alpns: Vec<&'static [u8]>,
     pub max_data: u64,
     pub max_window: u64,
-    pub max_stream_data: u64,
+    pub max_stream_data_bidi_local: u64,
+    pub max_stream_data_bidi_remote: u64,
+    pub max_stream_data_uni: u64,
     pub max_stream_window: u64,
     pub max_streams_bidi: u64,
     pub max_streams_uni: u64,
@@ -54,6 +72,7 @@ pub struct CommonArgs {
     pub qpack_max_table_capacity: Option<u64>,
     pub qpack_blocked_streams: Option<u64>,
     pub initial_cwnd_packets: u64,
+    pub io_backend: IoBackend,
 }
 
 /// Creates a new `CommonArgs` structure using the provided [`Docopt`].
@@ -64,6 +83,9 @@ pub struct CommonArgs {
 /// --max-data BYTES            Connection-wide flow control limit.
 /// --max-window BYTES          Connection-wide max receiver window.
 /// --max-stream-data BYTES     Per-stream flow control limit.
+/// --max-stream-data-bidi-local BYTES  Limit for local bidi streams.
+/// --max-stream-data-bidi-remote BYTES  Limit for remote bidi streams.
+/// --max-stream-data-uni BYTES  Limit for unidirectional streams.
 /// --max-stream-window BYTES   Per-stream max receiver window.
 /// --max-streams-bidi STREAMS  Number of allowed concurrent streams.
 /// --max-streams-uni STREAMS   Number of allowed concurrent streams.
@@ -80,19 +102,29 @@ pub struct CommonArgs {
 /// --qpack-max-table-capacity BYTES  Max capacity of dynamic QPACK decoding.
 /// --qpack-blocked-streams STREAMS  Limit of blocked streams while decoding.
 /// --initial-cwnd-packets      Size of initial congestion window, in packets.
+/// --io-backend NAME           UDP I/O backend.
 ///
 /// [`Docopt`]: https://docs.rs/docopt/1.1.0/docopt/
 impl Args for CommonArgs {
     fn with_docopt(docopt: &docopt::Docopt) -> Self {
-        let args = docopt.parse().unwrap_or_else(|e| e.exit());
+        docopt.parse().unwrap_or_else(|e| e.exit());
+
+        CommonArgs::try_with_docopt(docopt).unwrap_or_else(|e| panic!("{}", e))
+    }
+}
+
+impl CommonArgs {
+    /// Like [`Args::with_docopt()`], but returns an error instead of
+    /// terminating the process when an argument is invalid.
+    pub fn try_with_docopt(docopt: &docopt::Docopt) -> Result<Self, String> {
+        let args = docopt.parse().map_err(|e| e.to_string())?;
 
         let http_version = args.get_str("--http-version");
         let dgram_proto = args.get_str("--dgram-proto");
         let (alpns, dgrams_enabled) = match (http_version, dgram_proto) {
             ("HTTP/0.9", "none") => (alpns::HTTP_09.to_vec(), false),
 
-            ("HTTP/0.9", _) =>
-                panic!("Unsupported HTTP version and DATAGRAM protocol."),
+            ("HTTP/0.9", _) => return Err(UNSUPPORTED_PROTOCOLS.to_string()),
 
             ("HTTP/3", "none") => (alpns::HTTP_3.to_vec(), false),
 
@@ -105,34 +137,45 @@ impl Args for CommonArgs {
                 false,
             ),
 
-            (..) => panic!("Unsupported HTTP version and DATAGRAM protocol."),
+            (..) => return Err(UNSUPPORTED_PROTOCOLS.to_string()),
         };
 
-        let dgram_count = args.get_str("--dgram-count");
-        let dgram_count = dgram_count.parse::<u64>().unwrap();
+        let dgram_count = parse_arg::<u64>(&args, "--dgram-count")?;
 
         let dgram_data = args.get_str("--dgram-data").to_string();
 
-        let max_data = args.get_str("--max-data");
-        let max_data = max_data.parse::<u64>().unwrap();
+        let max_data = parse_arg::<u64>(&args, "--max-data")?;
 
-        let max_window = args.get_str("--max-window");
-        let max_window = max_window.parse::<u64>().unwrap();
+        let max_window = parse_arg::<u64>(&args, "--max-window")?;
 
-        let max_stream_data = args.get_str("--max-stream-data");
-        let max_stream_data = max_stream_data.parse::<u64>().unwrap();
+        let max_stream_data = parse_arg::<u64>(&args, "--max-stream-data")?;
 
-        let max_stream_window = args.get_str("--max-stream-window");
-        let max_stream_window = max_stream_window.parse::<u64>().unwrap();
+        // Each stream class defaults to --max-stream-data, so that e.g. the
+        // HTTP/3 control streams can be sized independently of the request
+        // streams.
+        let stream_class_limit = |name| {
+            let v = args.get_str(name);
 
-        let max_streams_bidi = args.get_str("--max-streams-bidi");
-        let max_streams_bidi = max_streams_bidi.parse::<u64>().unwrap();
+            if v.is_empty() {
+                return Ok(max_stream_data);
+            }
 
-        let max_streams_uni = args.get_str("--max-streams-uni");
-        let max_streams_uni = max_streams_uni.parse::<u64>().unwrap();
+            parse_arg::<u64>(&args, name)
+        };
 
-        let idle_timeout = args.get_str("--idle-timeout");
-        let idle_timeout = idle_timeout.parse::<u64>().unwrap();
+        let max_stream_data_bidi_local =
+            stream_class_limit("--max-stream-data-bidi-local")?;
+        let max_stream_data_bidi_remote =
+            stream_class_limit("--max-stream-data-bidi-remote")?;
+        let max_stream_data_uni = stream_class_limit("--max-stream-data-uni")?;
+
+        let max_stream_window = parse_arg::<u64>(&args, "--max-stream-window")?;
+
+        let max_streams_bidi = parse_arg::<u64>(&args, "--max-streams-bidi")?;
+
+        let max_streams_uni = parse_arg::<u64>(&args, "--max-streams-uni")?;
+
+        let idle_timeout = parse_arg::<u64>(&args, "--idle-timeout")?;
 
         let early_data = args.get_bool("--early-data");
 
@@ -148,54 +191,46 @@ impl Args for CommonArgs {
 
         let disable_hystart = args.get_bool("--disable-hystart");
 
-        let max_active_cids = args.get_str("--max-active-cids");
-        let max_active_cids = max_active_cids.parse::<u64>().unwrap();
+        let max_active_cids = parse_arg::<u64>(&args, "--max-active-cids")?;
 
         let enable_active_migration = args.get_bool("--enable-active-migration");
 
         let max_field_section_size =
             if !args.get_str("--max-field-section-size").is_empty() {
-                Some(
-                    args.get_str("--max-field-section-size")
-                        .parse::<u64>()
-                        .unwrap(),
-                )
+                Some(parse_arg::<u64>(&args, "--max-field-section-size")?)
             } else {
                 None
             };
 
         let qpack_max_table_capacity =
             if !args.get_str("--qpack-max-table-capacity").is_empty() {
-                Some(
-                    args.get_str("--qpack-max-table-capacity")
-                        .parse::<u64>()
-                        .unwrap(),
-                )
+                Some(parse_arg::<u64>(&args, "--qpack-max-table-capacity")?)
             } else {
                 None
             };
 
         let qpack_blocked_streams =
             if !args.get_str("--qpack-blocked-streams").is_empty() {
-                Some(
-                    args.get_str("--qpack-blocked-streams")
-                        .parse::<u64>()
-                        .unwrap(),
-                )
+                Some(parse_arg::<u64>(&args, "--qpack-blocked-streams")?)
             } else {
                 None
             };
 
-        let initial_cwnd_packets = args
-            .get_str("--initial-cwnd-packets")
-            .parse::<u64>()
-            .unwrap();
+        let initial_cwnd_packets =
+            parse_arg::<u64>(&args, "--initial-cwnd-packets")?;
 
-        CommonArgs {
+        let io_backend = args
+            .get_str("--io-backend")
+            .parse::<IoBackend>()
+            .map_err(|e| e.to_string())?;
+
+        Ok(CommonArgs {
             alpns,
             max_data,
             max_window,
-            max_stream_data,
+            max_stream_data_bidi_local,
+            max_stream_data_bidi_remote,
+            max_stream_data_uni,
             max_stream_window,
             max_streams_bidi,
             max_streams_uni,
@@ -214,7 +249,8 @@ impl Args for CommonArgs {
             qpack_max_table_capacity,
             qpack_blocked_streams,
             initial_cwnd_packets,
-        }
+            io_backend,
+        })
     }
 }
 
@@ -224,7 +260,9 @@ impl Default for CommonArgs {
             alpns: alpns::HTTP_3.to_vec(),
             max_data: 10000000,
             max_window: 25165824,
-            max_stream_data: 1000000,
+            max_stream_data_bidi_local: 1000000,
+            max_stream_data_bidi_remote: 1000000,
+            max_stream_data_uni: 1000000,
             max_stream_window: 16777216,
             max_streams_bidi: 100,
             max_streams_uni: 100,
@@ -243,6 +281,7 @@ impl Default for CommonArgs {
             qpack_max_table_capacity: None,
             qpack_blocked_streams: None,
             initial_cwnd_packets: 10,
+            io_backend: IoBackend::Mio,
         }
     }
 }
@@ -252,11 +291,15 @@ pub const CLIENT_USAGE: &str = "Usage:
   quiche-client -h | --help
 
 Options:
+  --config FILE            Read options from the given TOML file.
   --method METHOD          Use the given HTTP request method [default: GET].
   --body FILE              Send the given file as request body.
   --max-data BYTES         Connection-wide flow control limit [default: 10000000].
   --max-window BYTES       Connection-wide max receiver window [default: 25165824].
   --max-stream-data BYTES  Per-stream flow control limit [default: 1000000].
+  --max-stream-data-bidi-local BYTES   Flow control limit for locally-initiated bidirectional streams, overrides --max-stream-data.
+  --max-stream-data-bidi-remote BYTES  Flow control limit for remotely-initiated bidirectional streams, overrides --max-stream-data.
+  --max-stream-data-uni BYTES  Flow control limit for unidirectional streams, overrides --max-stream-data.
   --max-stream-window BYTES   Per-stream max receiver window [default: 16777216].
   --max-streams-bidi STREAMS  Number of allowed concurrent streams [default: 100].
   --max-streams-uni STREAMS   Number of allowed concurrent streams [default: 100].
@@ -272,6 +315,7 @@ Options:
   --dump-json              Dump response headers and payload to stdout in JSON format.
   --max-json-payload BYTES  Per-response payload limit when dumping JSON [default: 10000].
   --connect-to ADDRESS     Override the server's address.
+  --alt-svc                Fetch the URL over TCP first and connect to the HTTP/3 endpoint advertised in its Alt-Svc header (requires the alt-svc feature).
   --no-verify              Don't verify server's certificate.
   --trust-origin-ca-pem <file>  Path to the pem file of the origin's CA, if not publicly trusted.
   --no-grease              Don't send GREASE.
@@ -289,6 +333,7 @@ Options:
   --session-file PATH      File used to cache a TLS session for resumption.
   --source-port PORT       Source port to use when connecting to the server [default: 0].
   --initial-cwnd-packets PACKETS   The initial congestion window size in terms of packet count [default: 10].
+  --io-backend NAME        UDP I/O backend, either mio or io-uring (requires the io-uring feature) [default: mio].
   -h --help                Show this screen.
 ";
 
@@ -305,6 +350,7 @@ pub struct ClientArgs {
     pub body: Option<Vec<u8>>,
     pub method: String,
     pub connect_to: Option<String>,
+    pub alt_svc: bool,
     pub session_file: Option<String>,
     pub source_port: u16,
     pub perform_migration: bool,
@@ -373,6 +419,8 @@ impl Args for ClientArgs {
             None
         };
 
+        let alt_svc = args.get_bool("--alt-svc");
+
         let session_file = if args.get_bool("--session-file") {
             Some(args.get_str("--session-file").to_string())
         } else {
@@ -398,6 +446,7 @@ impl Args for ClientArgs {
             body,
             method,
             connect_to,
+            alt_svc,
             session_file,
             source_port,
             perform_migration,
@@ -420,6 +469,7 @@ impl Default for ClientArgs {
             body: None,
             method: "GET".to_string(),
             connect_to: None,
+            alt_svc: false,
             session_file: None,
             source_port: 0,
             perform_migration: false,
@@ -433,6 +483,7 @@ pub const SERVER_USAGE: &str = "Usage:
   quiche-server -h | --help
 
 Options:
+  --config FILE               Read options from the given TOML file, reloaded on SIGHUP.
   --listen <addr>             Listen on the given IP:port [default: 127.0.0.1:4433]
   --cert <file>               TLS certificate path [default: src/bin/cert.crt]
   --key <file>                TLS certificate key path [default: src/bin/cert.key]
@@ -442,12 +493,16 @@ Options:
   --max-data BYTES            Connection-wide flow control limit [default: 10000000].
   --max-window BYTES          Connection-wide max receiver window [default: 25165824].
   --max-stream-data BYTES     Per-stream flow control limit [default: 1000000].
+  --max-stream-data-bidi-local BYTES   Flow control limit for locally-initiated bidirectional streams, overrides --max-stream-data.
+  --max-stream-data-bidi-remote BYTES  Flow control limit for remotely-initiated bidirectional streams, overrides --max-stream-data.
+  --max-stream-data-uni BYTES  Flow control limit for unidirectional streams, overrides --max-stream-data.
   --max-stream-window BYTES   Per-stream max receiver window [default: 16777216].
   --max-streams-bidi STREAMS  Number of allowed concurrent streams [default: 100].
   --max-streams-uni STREAMS   Number of allowed concurrent streams [default: 100].
   --idle-timeout TIMEOUT      Idle timeout in milliseconds [default: 30000].
   --dump-packets PATH         Dump the incoming packets as files in the given directory.
   --early-data                Enable receiving early data.
+  --reject-early-data         Advertise early data support, but reject 0-RTT by refusing to resume sessions.
   --no-retry                  Disable stateless retry.
   --no-grease                 Don't send GREASE.
   --http-version VERSION      HTTP version to use [default: all].
@@ -464,13 +519,24 @@ Options:
   --disable-gso               Disable GSO (linux only).
   --disable-pacing            Disable pacing (linux only).
   --initial-cwnd-packets PACKETS      The initial congestion window size in terms of packet count [default: 10].
+  --io-backend NAME           UDP I/O backend, either mio or io-uring (requires the io-uring feature) [default: mio].
+  --acme-domain DOMAIN ...    Obtain and renew the certificate for DOMAIN via ACME TLS-ALPN-01 (requires the acme feature).
+  --acme-directory URL        ACME directory URL [default: https://acme-v02.api.letsencrypt.org/directory].
+  --acme-contact EMAIL        Contact email for the ACME account.
+  --acme-dir <dir>            Directory storing the ACME account key, certificate and key [default: acme/].
+  --acme-listen <addr>        TCP address of the TLS-ALPN-01 challenge responder [default: 0.0.0.0:443].
+  --xdp-iface IFACE           Receive and send through AF_XDP sockets on IFACE instead of a UDP socket (requires the af-xdp feature).
+  --xdp-queues NUM            Number of receive queues of the --xdp-iface interface to serve, each by a thread pinned to its own CPU [default: 1].
+  --shutdown-timeout TIMEOUT  Time in milliseconds given to in-flight requests to complete after SIGTERM [default: 10000].
   -h --help                   Show this screen.
 ";
 
 // Application-specific arguments that compliment the `CommonArgs`.
+#[derive(Clone)]
 pub struct ServerArgs {
     pub listen: String,
     pub no_retry: bool,
+    pub reject_early_data: bool,
     pub root: String,
     pub index: String,
     pub cert: String,
@@ -478,14 +544,33 @@ pub struct ServerArgs {
     pub disable_gso: bool,
     pub disable_pacing: bool,
     pub enable_pmtud: bool,
+    pub acme_domains: Vec<String>,
+    pub acme_directory: String,
+    pub acme_contact: Option<String>,
+    pub acme_dir: String,
+    pub acme_listen: String,
+    pub xdp_iface: Option<String>,
+    pub xdp_queues: u32,
+    pub shutdown_timeout: std::time::Duration,
 }
 
 impl Args for ServerArgs {
     fn with_docopt(docopt: &docopt::Docopt) -> Self {
-        let args = docopt.parse().unwrap_or_else(|e| e.exit());
+        docopt.parse().unwrap_or_else(|e| e.exit());
+
+        ServerArgs::try_with_docopt(docopt).unwrap_or_else(|e| panic!("{}", e))
+    }
+}
+
+impl ServerArgs {
+    /// Like [`Args::with_docopt()`], but returns an error instead of
+    /// terminating the process when an argument is invalid.
+    pub fn try_with_docopt(docopt: &docopt::Docopt) -> Result<Self, String> {
+        let args = docopt.parse().map_err(|e| e.to_string())?;
 
         let listen = args.get_str("--listen").to_string();
         let no_retry = args.get_bool("--no-retry");
+        let reject_early_data = args.get_bool("--reject-early-data");
         let root = args.get_str("--root").to_string();
         let index = args.get_str("--index").to_string();
         let cert = args.get_str("--cert").to_string();
@@ -494,9 +579,40 @@ impl Args for ServerArgs {
         let disable_pacing = args.get_bool("--disable-pacing");
         let enable_pmtud = args.get_bool("--enable-pmtud");
 
-        ServerArgs {
+        // ACME domains (can be multiple).
+        let acme_domains = args
+            .get_vec("--acme-domain")
+            .into_iter()
+            .map(|x| x.to_string())
+            .collect();
+
+        let acme_directory = args.get_str("--acme-directory").to_string();
+
+        let acme_contact = if args.get_bool("--acme-contact") {
+            Some(args.get_str("--acme-contact").to_string())
+        } else {
+            None
+        };
+
+        let acme_dir = args.get_str("--acme-dir").to_string();
+        let acme_listen = args.get_str("--acme-listen").to_string();
+
+        let xdp_iface = if !args.get_str("--xdp-iface").is_empty() {
+            Some(args.get_str("--xdp-iface").to_string())
+        } else {
+            None
+        };
+
+        let xdp_queues = parse_arg::<u32>(&args, "--xdp-queues")?;
+
+        let shutdown_timeout = std::time::Duration::from_millis(
+            parse_arg::<u64>(&args, "--shutdown-timeout")?,
+        );
+
+        Ok(ServerArgs {
             listen,
             no_retry,
+            reject_early_data,
             root,
             index,
             cert,
@@ -504,6 +620,14 @@ impl Args for ServerArgs {
             disable_gso,
             disable_pacing,
             enable_pmtud,
-        }
+            acme_domains,
+            acme_directory,
+            acme_contact,
+            acme_dir,
+            acme_listen,
+            xdp_iface,
+            xdp_queues,
+            shutdown_timeout,
+        })
     }
 }
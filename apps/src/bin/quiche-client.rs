@@ -30,11 +30,14 @@ use quiche_apps::common::*;
 
 use quiche_apps::client::*;
 
+use quiche_apps::config::*;
+
 fn main() {
     env_logger::builder().format_timestamp_nanos().init();
 
     // Parse CLI parameters.
-    let docopt = docopt::Docopt::new(CLIENT_USAGE).unwrap();
+    let argv = args_with_config_file().unwrap_or_else(|e| panic!("{}", e));
+    let docopt = docopt::Docopt::new(CLIENT_USAGE).unwrap().argv(argv);
     let conn_args = CommonArgs::with_docopt(&docopt);
     let args = ClientArgs::with_docopt(&docopt);
 
@@ -39,6 +39,8 @@ use std::convert::TryFrom;
 
 use std::rc::Rc;
 
+use std::sync::Arc;
+
 use std::cell::RefCell;
 
 use ring::rand::*;
@@ -47,32 +49,128 @@ use quiche_apps::args::*;
 
 use quiche_apps::common::*;
 
+use quiche_apps::config::*;
+
 use quiche_apps::sendto::*;
 
+use quiche_apps::socket::*;
+
 const MAX_BUF_SIZE: usize = 65507;
 
 const MAX_DATAGRAM_SIZE: usize = 1350;
 
+/// How often the ACME certificate is checked for renewal.
+#[cfg(feature = "acme")]
+const ACME_RENEWAL_CHECK_INTERVAL: std::time::Duration =
+    std::time::Duration::from_secs(12 * 60 * 60);
+
+/// Certificates older than this are renewed via ACME.
+#[cfg(feature = "acme")]
+const ACME_RENEW_AFTER: std::time::Duration =
+    std::time::Duration::from_secs(60 * 24 * 60 * 60);
+
 fn main() {
+    env_logger::builder().format_timestamp_nanos().init();
+
+    // Parse CLI parameters.
+    let (conn_args, args) = parse_args();
+
+    // Obtain the certificate via ACME if requested, otherwise use the
+    // provided one.
+    let (cert_path, key_path) = if !args.acme_domains.is_empty() {
+        setup_acme(&args)
+    } else {
+        (args.cert.clone(), args.key.clone())
+    };
+
+    // Every worker needs to be told about a renewed certificate.
+    let workers = match args.xdp_iface {
+        Some(_) => args.xdp_queues,
+
+        None => 1,
+    };
+
+    let cert_renewed: Vec<_> = (0..workers)
+        .map(|_| Arc::new(std::sync::atomic::AtomicBool::new(false)))
+        .collect();
+
+    #[cfg(feature = "acme")]
+    if !args.acme_domains.is_empty() {
+        let cert_renewed = cert_renewed.clone();
+
+        quiche_apps::acme::spawn_renewal(
+            acme_config(&args),
+            ACME_RENEWAL_CHECK_INTERVAL,
+            move || {
+                for renewed in &cert_renewed {
+                    renewed.store(true, std::sync::atomic::Ordering::Release);
+                }
+            },
+        );
+    }
+
+    if let Some(iface) = &args.xdp_iface {
+        serve_xdp(
+            iface,
+            &conn_args,
+            &args,
+            &cert_path,
+            &key_path,
+            cert_renewed,
+        );
+        return;
+    }
+
+    // Create the UDP listening socket.
+    let socket =
+        Socket::bind(args.listen.parse().unwrap(), conn_args.io_backend).unwrap();
+
+    serve(
+        conn_args,
+        args,
+        socket,
+        &cert_path,
+        &key_path,
+        &cert_renewed[0],
+    );
+}
+
+/// Runs the server's event loop on the given socket.
+fn serve(
+    mut conn_args: CommonArgs, mut args: ServerArgs, mut socket: Socket,
+    cert_path: &str, key_path: &str,
+    cert_renewed: &std::sync::atomic::AtomicBool,
+) {
     let mut buf = [0; MAX_BUF_SIZE];
     let mut out = [0; MAX_BUF_SIZE];
     let mut pacing = false;
 
-    env_logger::builder().format_timestamp_nanos().init();
+    // Reload the configuration file on SIGHUP.
+    let reload_requested =
+        std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
 
-    // Parse CLI parameters.
-    let docopt = docopt::Docopt::new(SERVER_USAGE).unwrap();
-    let conn_args = CommonArgs::with_docopt(&docopt);
-    let args = ServerArgs::with_docopt(&docopt);
+    signal_hook::flag::register(
+        signal_hook::consts::SIGHUP,
+        reload_requested.clone(),
+    )
+    .unwrap();
+
+    // Drain the connections and exit on SIGTERM.
+    let shutdown_requested =
+        std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+
+    signal_hook::flag::register(
+        signal_hook::consts::SIGTERM,
+        shutdown_requested.clone(),
+    )
+    .unwrap();
+
+    let mut shutdown_deadline = None;
 
     // Setup the event loop.
     let mut poll = mio::Poll::new().unwrap();
     let mut events = mio::Events::with_capacity(1024);
 
-    // Create the UDP listening socket, and register it with the event loop.
-    let mut socket =
-        mio::net::UdpSocket::bind(args.listen.parse().unwrap()).unwrap();
-
     // Set SO_TXTIME socket option on the listening UDP socket for pacing
     // outgoing packets.
     if !args.disable_pacing {
@@ -87,6 +185,7 @@ fn main() {
 
     info!("listening on {:}", socket.local_addr().unwrap());
 
+    // Register the socket with the event loop.
     poll.registry()
         .register(&mut socket, mio::Token(0), mio::Interest::READABLE)
         .unwrap();
@@ -103,29 +202,16 @@ fn main() {
     // Create the configuration for the QUIC connections.
     let mut config = quiche::Config::new(quiche::PROTOCOL_VERSION).unwrap();
 
-    config.load_cert_chain_from_pem_file(&args.cert).unwrap();
-    config.load_priv_key_from_pem_file(&args.key).unwrap();
+    config.load_cert_chain_from_pem_file(cert_path).unwrap();
+    config.load_priv_key_from_pem_file(key_path).unwrap();
 
     config.set_application_protos(&conn_args.alpns).unwrap();
 
     config.discover_pmtu(args.enable_pmtud);
-    config.set_max_idle_timeout(conn_args.idle_timeout);
     config.set_max_recv_udp_payload_size(max_datagram_size);
     config.set_max_send_udp_payload_size(max_datagram_size);
-    config.set_initial_max_data(conn_args.max_data);
-    config.set_initial_max_stream_data_bidi_local(conn_args.max_stream_data);
-    config.set_initial_max_stream_data_bidi_remote(conn_args.max_stream_data);
-    config.set_initial_max_stream_data_uni(conn_args.max_stream_data);
-    config.set_initial_max_streams_bidi(conn_args.max_streams_bidi);
-    config.set_initial_max_streams_uni(conn_args.max_streams_uni);
-    config.set_disable_active_migration(!conn_args.enable_active_migration);
-    config.set_active_connection_id_limit(conn_args.max_active_cids);
-    config.set_initial_congestion_window_packets(
-        usize::try_from(conn_args.initial_cwnd_packets).unwrap(),
-    );
 
-    config.set_max_connection_window(conn_args.max_window);
-    config.set_max_stream_window(conn_args.max_stream_window);
+    configure_reloadable(&mut config, &conn_args);
 
     config.enable_pacing(pacing);
 
@@ -143,7 +229,9 @@ fn main() {
         config.log_keys();
     }
 
-    if conn_args.early_data {
+    // Early data needs to be enabled even when rejecting 0-RTT, so that the
+    // session tickets still tell clients to attempt it.
+    if conn_args.early_data || args.reject_early_data {
         config.enable_early_data();
     }
 
@@ -151,14 +239,6 @@ fn main() {
         config.grease(false);
     }
 
-    config
-        .set_cc_algorithm_name(&conn_args.cc_algorithm)
-        .unwrap();
-
-    if conn_args.disable_hystart {
-        config.enable_hystart(false);
-    }
-
     if conn_args.dgrams_enabled {
         config.enable_dgram(true, 1000, 1000);
     }
@@ -178,18 +258,92 @@ fn main() {
     let local_addr = socket.local_addr().unwrap();
 
     loop {
+        if reload_requested.swap(false, std::sync::atomic::Ordering::Relaxed) {
+            reload_args(&mut config, &mut conn_args, &mut args);
+        }
+
+        if shutdown_deadline.is_none() &&
+            shutdown_requested.load(std::sync::atomic::Ordering::Relaxed)
+        {
+            info!("shutting down, draining {} connections", clients.len());
+
+            let now = std::time::Instant::now();
+
+            shutdown_deadline = Some(now + args.shutdown_timeout);
+
+            for client in clients.values_mut() {
+                if let Some(http_conn) = client.http_conn.as_mut() {
+                    http_conn.send_goaway(&mut client.conn);
+                }
+
+                // Requests sent before the client got the GOAWAY can still
+                // arrive for about a round trip.
+                let rtt = client
+                    .conn
+                    .path_stats()
+                    .map(|s| s.rtt)
+                    .max()
+                    .unwrap_or_default();
+
+                client.drain_at = Some(now + rtt * 2);
+                client.goaway_flushed = false;
+            }
+        }
+
+        if shutdown_deadline.is_some() && clients.is_empty() {
+            info!("all connections drained, exiting");
+            return;
+        }
+
+        // Pick up a renewed certificate for new connections. Existing ones
+        // keep using the certificate they were established with.
+        if cert_renewed.swap(false, std::sync::atomic::Ordering::Acquire) {
+            info!("reloading renewed certificate {cert_path}");
+
+            if let Err(e) = config
+                .load_cert_chain_from_pem_file(cert_path)
+                .and_then(|_| config.load_priv_key_from_pem_file(key_path))
+            {
+                error!("failed to reload certificate: {:?}", e);
+            }
+        }
+
         // Find the shorter timeout from all the active connections.
         //
         // TODO: use event loop that properly supports timers
+        let now = std::time::Instant::now();
+
         let timeout = match continue_write {
             true => Some(std::time::Duration::from_secs(0)),
 
-            false => clients.values().filter_map(|c| c.conn.timeout()).min(),
+            false => clients
+                .values()
+                .filter_map(|c| c.conn.timeout())
+                .chain(
+                    clients
+                        .values()
+                        .filter_map(|c| c.drain_at)
+                        .filter(|&t| t > now)
+                        .map(|t| t - now),
+                )
+                .chain(
+                    shutdown_deadline
+                        .map(|deadline| deadline.saturating_duration_since(now)),
+                )
+                .min(),
         };
 
         let mut poll_res = poll.poll(&mut events, timeout);
         while let Err(e) = poll_res.as_ref() {
             if e.kind() == std::io::ErrorKind::Interrupted {
+                // Start draining right away rather than on the next event.
+                if shutdown_deadline.is_none() &&
+                    shutdown_requested
+                        .load(std::sync::atomic::Ordering::Relaxed)
+                {
+                    break;
+                }
+
                 trace!("mio poll() call failed, retrying: {:?}", e);
                 poll_res = poll.poll(&mut events, timeout);
             } else {
@@ -275,6 +429,11 @@ fn main() {
                     continue 'read;
                 }
 
+                if shutdown_deadline.is_some() {
+                    debug!("Shutting down, ignoring new connection");
+                    continue 'read;
+                }
+
                 if !quiche::version_is_supported(hdr.version) {
                     warn!("Doing version negotiation");
 
@@ -357,6 +516,15 @@ fn main() {
 
                 debug!("New connection: dcid={:?} scid={:?}", hdr.dcid, scid);
 
+                // Rotate the session ticket key for every connection, so that
+                // none of the tickets issued so far can be used to resume and
+                // 0-RTT is always rejected.
+                if args.reject_early_data {
+                    let mut ticket_key = [0; 48];
+                    rng.fill(&mut ticket_key).unwrap();
+                    config.set_ticket_key(&ticket_key).unwrap();
+                }
+
                 #[allow(unused_mut)]
                 let mut conn = quiche::accept(
                     &scid,
@@ -400,6 +568,8 @@ fn main() {
                     max_datagram_size,
                     loss_rate: 0.0,
                     max_send_burst: MAX_BUF_SIZE,
+                    drain_at: None,
+                    goaway_flushed: false,
                 };
 
                 clients.insert(client_id, client);
@@ -421,6 +591,7 @@ fn main() {
             let recv_info = quiche::RecvInfo {
                 to: local_addr,
                 from,
+                ecn: quiche::Ecn::NotEct,
             };
 
             // Process potentially coalesced packets.
@@ -535,6 +706,19 @@ fn main() {
             }
         }
 
+        // While shutting down, close the connections that have no requests
+        // left to serve, or all of them once the deadline passed.
+        if let Some(deadline) = shutdown_deadline {
+            let now = std::time::Instant::now();
+            let expired = deadline <= now;
+
+            for client in clients.values_mut() {
+                if expired || is_drained(client, now) {
+                    close_drained(client);
+                }
+            }
+        }
+
         // Generate outgoing QUIC packets for all active connections and send
         // them on the UDP socket, until quiche reports that there are no more
         // packets to be sent.
@@ -555,49 +739,44 @@ fn main() {
                 client.conn.send_quantum().min(client.max_send_burst) /
                     client.max_datagram_size *
                     client.max_datagram_size;
-            let mut total_write = 0;
-            let mut dst_info = None;
 
-            while total_write < max_send_burst {
-                let (write, send_info) = match client
-                    .conn
-                    .send(&mut out[total_write..max_send_burst])
-                {
+            if max_send_burst == 0 {
+                continue;
+            }
+
+            let (total_write, segment_size, dst_info) =
+                match client.conn.send_multiple(&mut out[..max_send_burst]) {
                     Ok(v) => v,
 
                     Err(quiche::Error::Done) => {
                         trace!("{} done writing", client.conn.trace_id());
-                        break;
+
+                        if client.drain_at.is_some() {
+                            client.goaway_flushed = true;
+                        }
+
+                        continue;
                     },
 
                     Err(e) => {
                         error!("{} send failed: {:?}", client.conn.trace_id(), e);
 
                         client.conn.close(false, 0x1, b"fail").ok();
-                        break;
+                        continue;
                     },
                 };
 
-                total_write += write;
-
-                // Use the first packet time to send, not the last.
-                let _ = dst_info.get_or_insert(send_info);
-
-                if write < client.max_datagram_size {
-                    continue_write = true;
-                    break;
-                }
-            }
-
-            if total_write == 0 || dst_info.is_none() {
-                continue;
+            // The batch may have ended early, e.g. on a short datagram, so
+            // check for more packets once it's sent.
+            if total_write < max_send_burst {
+                continue_write = true;
             }
 
             if let Err(e) = send_to(
                 &socket,
                 &out[..total_write],
-                &dst_info.unwrap(),
-                client.max_datagram_size,
+                &dst_info,
+                segment_size,
                 pacing,
                 enable_gso,
             ) {
@@ -644,6 +823,252 @@ fn main() {
     }
 }
 
+/// Returns whether the client has no request left to serve, and can't send
+/// new ones anymore.
+///
+/// The GOAWAY must have been sent, as closing the connection discards any
+/// stream data not sent yet, and requests sent before the client got it must
+/// have had time to arrive.
+fn is_drained(client: &Client, now: std::time::Instant) -> bool {
+    client.goaway_flushed &&
+        client.drain_at.is_some_and(|t| t <= now) &&
+        client.partial_requests.is_empty() &&
+        client.partial_responses.is_empty()
+}
+
+/// Closes the client's connection with NO_ERROR.
+fn close_drained(client: &mut Client) {
+    if client.conn.is_closed() || client.conn.is_draining() {
+        return;
+    }
+
+    let res = if alpns::HTTP_3.contains(&client.conn.application_proto()) {
+        // H3_NO_ERROR.
+        client.conn.close(true, 0x100, b"")
+    } else {
+        client.conn.close(false, 0x0, b"")
+    };
+
+    match res {
+        Ok(_) => debug!("{} closed after draining", client.conn.trace_id()),
+
+        Err(quiche::Error::Done) => (),
+
+        Err(e) => error!("{} close failed {:?}", client.conn.trace_id(), e),
+    }
+}
+
+fn parse_args() -> (CommonArgs, ServerArgs) {
+    let argv = args_with_config_file().unwrap_or_else(|e| panic!("{}", e));
+    let docopt = docopt::Docopt::new(SERVER_USAGE).unwrap().argv(argv);
+
+    (
+        CommonArgs::with_docopt(&docopt),
+        ServerArgs::with_docopt(&docopt),
+    )
+}
+
+/// Applies the arguments that can be changed at runtime. They only affect
+/// connections accepted after they are applied.
+fn configure_reloadable(config: &mut quiche::Config, conn_args: &CommonArgs) {
+    config.set_max_idle_timeout(conn_args.idle_timeout);
+    config.set_initial_max_data(conn_args.max_data);
+    config.set_initial_max_stream_data_bidi_local(
+        conn_args.max_stream_data_bidi_local,
+    );
+    config.set_initial_max_stream_data_bidi_remote(
+        conn_args.max_stream_data_bidi_remote,
+    );
+    config.set_initial_max_stream_data_uni(conn_args.max_stream_data_uni);
+    config.set_initial_max_streams_bidi(conn_args.max_streams_bidi);
+    config.set_initial_max_streams_uni(conn_args.max_streams_uni);
+    config.set_disable_active_migration(!conn_args.enable_active_migration);
+    config.set_active_connection_id_limit(conn_args.max_active_cids);
+    config.set_initial_congestion_window_packets(
+        usize::try_from(conn_args.initial_cwnd_packets).unwrap(),
+    );
+
+    config.set_max_connection_window(conn_args.max_window);
+    config.set_max_stream_window(conn_args.max_stream_window);
+
+    config
+        .set_cc_algorithm_name(&conn_args.cc_algorithm)
+        .unwrap();
+
+    config.enable_hystart(!conn_args.disable_hystart);
+}
+
+/// Re-reads the configuration file and applies the subset of arguments that
+/// is safe to change while running.
+///
+/// Transport parameters, congestion control, retry and the served content
+/// take effect for new connections. Everything else, such as the listening
+/// address, certificates or the HTTP version, requires a restart.
+fn reload_args(
+    config: &mut quiche::Config, conn_args: &mut CommonArgs,
+    args: &mut ServerArgs,
+) {
+    let argv = match args_with_config_file() {
+        Ok(v) => v,
+
+        Err(e) => {
+            error!("configuration reload failed: {}", e);
+            return;
+        },
+    };
+
+    let docopt = docopt::Docopt::new(SERVER_USAGE).unwrap().argv(argv);
+
+    // Don't let an invalid configuration file terminate the server.
+    let new_args = CommonArgs::try_with_docopt(&docopt)
+        .and_then(|c| ServerArgs::try_with_docopt(&docopt).map(|s| (c, s)));
+
+    let (new_conn_args, new_args) = match new_args {
+        Ok(v) => v,
+
+        Err(e) => {
+            error!("configuration reload failed: {}", e);
+            return;
+        },
+    };
+
+    if let Err(e) = quiche::Config::new(quiche::PROTOCOL_VERSION)
+        .and_then(|mut c| c.set_cc_algorithm_name(&new_conn_args.cc_algorithm))
+    {
+        error!(
+            "configuration reload failed: invalid cc algorithm {}: {:?}",
+            new_conn_args.cc_algorithm, e
+        );
+        return;
+    }
+
+    conn_args.max_data = new_conn_args.max_data;
+    conn_args.max_window = new_conn_args.max_window;
+    conn_args.max_stream_data_bidi_local =
+        new_conn_args.max_stream_data_bidi_local;
+    conn_args.max_stream_data_bidi_remote =
+        new_conn_args.max_stream_data_bidi_remote;
+    conn_args.max_stream_data_uni = new_conn_args.max_stream_data_uni;
+    conn_args.max_stream_window = new_conn_args.max_stream_window;
+    conn_args.max_streams_bidi = new_conn_args.max_streams_bidi;
+    conn_args.max_streams_uni = new_conn_args.max_streams_uni;
+    conn_args.idle_timeout = new_conn_args.idle_timeout;
+    conn_args.cc_algorithm = new_conn_args.cc_algorithm;
+    conn_args.disable_hystart = new_conn_args.disable_hystart;
+    conn_args.max_active_cids = new_conn_args.max_active_cids;
+    conn_args.enable_active_migration = new_conn_args.enable_active_migration;
+    conn_args.initial_cwnd_packets = new_conn_args.initial_cwnd_packets;
+
+    args.no_retry = new_args.no_retry;
+    args.root = new_args.root;
+    args.index = new_args.index;
+
+    configure_reloadable(config, conn_args);
+
+    info!("configuration reloaded");
+}
+
+#[cfg(feature = "acme")]
+fn acme_config(args: &ServerArgs) -> quiche_apps::acme::AcmeConfig {
+    quiche_apps::acme::AcmeConfig {
+        directory_url: args.acme_directory.clone(),
+        domains: args.acme_domains.clone(),
+        contact: args.acme_contact.clone(),
+        state_dir: std::path::PathBuf::from(&args.acme_dir),
+        challenge_listen: args.acme_listen.parse().unwrap(),
+        renew_after: ACME_RENEW_AFTER,
+    }
+}
+
+/// Obtains a certificate via ACME, if the stored one is missing or expired,
+/// and returns the paths of the certificate and key files.
+#[cfg(feature = "acme")]
+fn setup_acme(args: &ServerArgs) -> (String, String) {
+    let acme_config = acme_config(args);
+
+    if let Err(e) = quiche_apps::acme::ensure_certificate(&acme_config) {
+        panic!("ACME certificate provisioning failed: {e}");
+    }
+
+    (
+        acme_config.cert_path().to_string_lossy().into_owned(),
+        acme_config.key_path().to_string_lossy().into_owned(),
+    )
+}
+
+#[cfg(not(feature = "acme"))]
+fn setup_acme(_: &ServerArgs) -> (String, String) {
+    panic!("--acme-domain requires building with the acme feature");
+}
+
+/// Serves through AF_XDP sockets bound to the first `--xdp-queues` receive
+/// queues of the interface, each from its own thread pinned to a CPU.
+#[cfg(all(target_os = "linux", feature = "af-xdp"))]
+fn serve_xdp(
+    iface: &str, conn_args: &CommonArgs, args: &ServerArgs, cert_path: &str,
+    key_path: &str, cert_renewed: Vec<Arc<std::sync::atomic::AtomicBool>>,
+) {
+    use quiche_apps::xdp;
+
+    let listen: net::SocketAddr = args.listen.parse().unwrap();
+
+    let ifindex = xdp::ifindex(iface).unwrap();
+
+    let program = Arc::new(
+        xdp::XdpProgram::attach(ifindex, listen.port(), args.xdp_queues).unwrap(),
+    );
+
+    let cpus = std::thread::available_parallelism().map_or(1, |v| v.get());
+
+    let workers: Vec<_> = cert_renewed
+        .into_iter()
+        .enumerate()
+        .map(|(queue, cert_renewed)| {
+            let conn_args = conn_args.clone();
+            let args = args.clone();
+            let cert_path = cert_path.to_string();
+            let key_path = key_path.to_string();
+            let program = program.clone();
+
+            std::thread::spawn(move || {
+                if let Err(e) = xdp::pin_to_cpu(queue % cpus) {
+                    error!("failed to pin queue {queue} to a CPU: {e}");
+                }
+
+                // The socket is created by the pinned thread, so that its
+                // frames are allocated close to the CPU using them.
+                let socket =
+                    xdp::XdpSocket::new(ifindex, queue as u32, listen).unwrap();
+
+                program.insert(queue as u32, &socket).unwrap();
+
+                info!("serving receive queue {queue}");
+
+                serve(
+                    conn_args,
+                    args,
+                    Socket::Xdp(Box::new(socket)),
+                    &cert_path,
+                    &key_path,
+                    &cert_renewed,
+                );
+            })
+        })
+        .collect();
+
+    for worker in workers {
+        worker.join().unwrap();
+    }
+}
+
+#[cfg(not(all(target_os = "linux", feature = "af-xdp")))]
+fn serve_xdp(
+    _: &str, _: &CommonArgs, _: &ServerArgs, _: &str, _: &str,
+    _: Vec<Arc<std::sync::atomic::AtomicBool>>,
+) {
+    panic!("--xdp-iface requires building with the af-xdp feature on Linux");
+}
+
 /// Generate a stateless retry token.
 ///
 /// The token includes the static string `"quiche"` followed by the IP address
@@ -718,6 +1143,15 @@ fn handle_path_events(client: &mut Client) {
                     .expect("cannot probe");
             },
 
+            quiche::PathEvent::ValidationStarted(local_addr, peer_addr) => {
+                info!(
+                    "{} Path ({}, {}) validation started",
+                    client.conn.trace_id(),
+                    local_addr,
+                    peer_addr
+                );
+            },
+
             quiche::PathEvent::Validated(local_addr, peer_addr) => {
                 info!(
                     "{} Path ({}, {}) is now validated",
@@ -774,7 +1208,7 @@ fn handle_path_events(client: &mut Client) {
 ///
 /// Note that this socket option is set only on linux platforms.
 #[cfg(target_os = "linux")]
-fn set_txtime_sockopt(sock: &mio::net::UdpSocket) -> io::Result<()> {
+fn set_txtime_sockopt(sock: &Socket) -> io::Result<()> {
     use nix::sys::socket::setsockopt;
     use nix::sys::socket::sockopt::TxTime;
     use std::os::unix::io::AsRawFd;
@@ -790,7 +1224,7 @@ fn set_txtime_sockopt(sock: &mio::net::UdpSocket) -> io::Result<()> {
 }
 
 #[cfg(not(target_os = "linux"))]
-fn set_txtime_sockopt(_: &mio::net::UdpSocket) -> io::Result<()> {
+fn set_txtime_sockopt(_: &Socket) -> io::Result<()> {
     use std::io::Error;
 
     Err(Error::other("Not supported on this platform"))
@@ -26,6 +26,7 @@
 
 use crate::args::*;
 use crate::common::*;
+use crate::socket::Socket;
 
 use std::io::prelude::*;
 
@@ -61,9 +62,22 @@ pub fn connect(
     // We'll only connect to the first server provided in URL list.
     let connect_url = &args.urls[0];
 
+    // Discover the HTTP/3 endpoint from the origin's Alt-Svc header. Only the
+    // advertised protocol is offered, so that the handshake fails if the
+    // endpoint doesn't actually support it.
+    let (alt_svc_addr, alpns) = if args.alt_svc {
+        let (addr, alpn) = discover_alt_svc(&args, &conn_args.alpns)?;
+
+        (Some(addr), vec![alpn])
+    } else {
+        (None, conn_args.alpns.clone())
+    };
+
     // Resolve server address.
     let peer_addr = if let Some(addr) = &args.connect_to {
         addr.parse().expect("--connect-to is expected to be a string containing an IPv4 or IPv6 address with a port. E.g. 192.0.2.0:443")
+    } else if let Some(addr) = alt_svc_addr {
+        addr
     } else {
         *connect_url.socket_addrs(|| None).unwrap().first().unwrap()
     };
@@ -79,14 +93,15 @@ pub fn connect(
     // Create the UDP socket backing the QUIC connection, and register it with
     // the event loop.
     let mut socket =
-        mio::net::UdpSocket::bind(bind_addr.parse().unwrap()).unwrap();
+        Socket::bind(bind_addr.parse().unwrap(), conn_args.io_backend).unwrap();
     poll.registry()
         .register(&mut socket, mio::Token(0), mio::Interest::READABLE)
         .unwrap();
 
     let migrate_socket = if args.perform_migration {
         let mut socket =
-            mio::net::UdpSocket::bind(bind_addr.parse().unwrap()).unwrap();
+            Socket::bind(bind_addr.parse().unwrap(), conn_args.io_backend)
+                .unwrap();
         poll.registry()
             .register(&mut socket, mio::Token(1), mio::Interest::READABLE)
             .unwrap();
@@ -112,15 +127,19 @@ pub fn connect(
         config.verify_peer(!args.no_verify);
     }
 
-    config.set_application_protos(&conn_args.alpns).unwrap();
+    config.set_application_protos(&alpns).unwrap();
 
     config.set_max_idle_timeout(conn_args.idle_timeout);
     config.set_max_recv_udp_payload_size(MAX_DATAGRAM_SIZE);
     config.set_max_send_udp_payload_size(MAX_DATAGRAM_SIZE);
     config.set_initial_max_data(conn_args.max_data);
-    config.set_initial_max_stream_data_bidi_local(conn_args.max_stream_data);
-    config.set_initial_max_stream_data_bidi_remote(conn_args.max_stream_data);
-    config.set_initial_max_stream_data_uni(conn_args.max_stream_data);
+    config.set_initial_max_stream_data_bidi_local(
+        conn_args.max_stream_data_bidi_local,
+    );
+    config.set_initial_max_stream_data_bidi_remote(
+        conn_args.max_stream_data_bidi_remote,
+    );
+    config.set_initial_max_stream_data_uni(conn_args.max_stream_data_uni);
     config.set_initial_max_streams_bidi(conn_args.max_streams_bidi);
     config.set_initial_max_streams_uni(conn_args.max_streams_uni);
     config.set_disable_active_migration(!conn_args.enable_active_migration);
@@ -313,6 +332,7 @@ pub fn connect(
                 let recv_info = quiche::RecvInfo {
                     to: local_addr,
                     from,
+                    ecn: quiche::Ecn::NotEct,
                 };
 
                 // Process potentially coalesced packets.
@@ -429,6 +449,13 @@ pub fn connect(
             match qe {
                 quiche::PathEvent::New(..) => unreachable!(),
 
+                quiche::PathEvent::ValidationStarted(local_addr, peer_addr) => {
+                    info!(
+                        "Path ({}, {}) validation started",
+                        local_addr, peer_addr
+                    );
+                },
+
                 quiche::PathEvent::Validated(local_addr, peer_addr) => {
                     info!(
                         "Path ({}, {}) is now validated",
@@ -592,3 +619,43 @@ pub fn connect(
 
     Ok(())
 }
+
+/// Fetches the first URL over TCP and returns the address and protocol of the
+/// HTTP/3 alternative advertised by the origin.
+#[cfg(feature = "alt-svc")]
+fn discover_alt_svc(
+    args: &ClientArgs, alpns: &[&'static [u8]],
+) -> Result<(std::net::SocketAddr, &'static [u8]), ClientError> {
+    let config = crate::altsvc::DiscoveryConfig {
+        no_verify: args.no_verify,
+        trust_origin_ca_pem: args.trust_origin_ca_pem.clone(),
+    };
+
+    let discovered = crate::altsvc::discover(&args.urls[0], alpns, &config)
+        .map_err(|e| {
+            ClientError::Other(format!("Alt-Svc discovery failed: {e}"))
+        })?;
+
+    info!(
+        "using alternative service {:?} at {}",
+        discovered.alt_svc, discovered.peer_addr
+    );
+
+    // The selected protocol is always one of the offered ones.
+    let alpn = alpns
+        .iter()
+        .find(|alpn| **alpn == discovered.alt_svc.protocol.as_slice())
+        .copied()
+        .unwrap();
+
+    Ok((discovered.peer_addr, alpn))
+}
+
+#[cfg(not(feature = "alt-svc"))]
+fn discover_alt_svc(
+    _: &ClientArgs, _: &[&'static [u8]],
+) -> Result<(std::net::SocketAddr, &'static [u8]), ClientError> {
+    Err(ClientError::Other(
+        "--alt-svc requires building with the alt-svc feature".to_string(),
+    ))
+}
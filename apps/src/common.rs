@@ -98,6 +98,14 @@ pub struct Client {
     pub loss_rate: f64,
 
     pub max_send_burst: usize,
+
+    /// While shutting down, the time after which the connection can be closed
+    /// once it has no request left to serve.
+    pub drain_at: Option<std::time::Instant>,
+
+    /// Whether all the packets queued since the shutdown started, including
+    /// the GOAWAY, were sent.
+    pub goaway_flushed: bool,
 }
 
 pub type ClientIdMap = HashMap<ConnectionId<'static>, ClientId>;
@@ -356,6 +364,10 @@ pub trait HttpConn {
         &mut self, conn: &mut quiche::Connection,
         partial_responses: &mut HashMap<u64, PartialResponse>, stream_id: u64,
     );
+
+    /// Tells the peer that no new request will be processed, while the ones it
+    /// already sent are still served.
+    fn send_goaway(&mut self, conn: &mut quiche::Connection);
 }
 
 pub fn writable_response_streams(
@@ -629,10 +641,11 @@ impl HttpConn for Http09Conn {
                     path = autoindex(path, index);
 
                     info!(
-                        "{} got GET request for {:?} on stream {}",
+                        "{} got GET request for {:?} on stream {} (early data: {})",
                         conn.trace_id(),
                         path,
-                        s
+                        s,
+                        conn.is_in_early_data()
                     );
 
                     let body = std::fs::read(path.as_path())
@@ -677,6 +690,11 @@ impl HttpConn for Http09Conn {
         Ok(())
     }
 
+    fn send_goaway(&mut self, _conn: &mut quiche::Connection) {
+        // HTTP/0.9 has no way of announcing a shutdown, the connection is
+        // simply closed once the pending responses were sent.
+    }
+
     fn handle_writable(
         &mut self, conn: &mut quiche::Connection,
         partial_responses: &mut HashMap<u64, PartialResponse>, stream_id: u64,
@@ -1333,6 +1351,19 @@ impl HttpConn for Http3Conn {
                     break;
                 },
 
+                Ok((_stream_id, quiche::h3::Event::Cancelled(c))) => {
+                    error!("request was cancelled by peer ({:?}), closing...", c);
+
+                    match conn.close(true, 0x100, b"kthxbye") {
+                        // Already closed.
+                        Ok(_) | Err(quiche::Error::Done) => (),
+
+                        Err(e) => panic!("error closing conn: {:?}", e),
+                    }
+
+                    break;
+                },
+
                 Ok((
                     prioritized_element_id,
                     quiche::h3::Event::PriorityUpdate,
@@ -1352,6 +1383,8 @@ impl HttpConn for Http3Conn {
                     );
                 },
 
+                Ok((_stream_id, quiche::h3::Event::Metadata { .. })) => (),
+
                 Err(quiche::h3::Error::Done) => {
                     break;
                 },
@@ -1411,10 +1444,11 @@ impl HttpConn for Http3Conn {
             match self.h3_conn.poll(conn) {
                 Ok((stream_id, quiche::h3::Event::Headers { list, .. })) => {
                     info!(
-                        "{} got request {:?} on stream id {}",
+                        "{} got request {:?} on stream id {} (early data: {})",
                         conn.trace_id(),
                         hdrs_to_strings(&list),
-                        stream_id
+                        stream_id,
+                        conn.is_in_early_data()
                     );
 
                     self.largest_processed_request =
@@ -1528,6 +1562,8 @@ impl HttpConn for Http3Conn {
 
                 Ok((_stream_id, quiche::h3::Event::Reset { .. })) => (),
 
+                Ok((_stream_id, quiche::h3::Event::Cancelled { .. })) => (),
+
                 Ok((
                     prioritized_element_id,
                     quiche::h3::Event::PriorityUpdate,
@@ -1549,6 +1585,8 @@ impl HttpConn for Http3Conn {
                         .send_goaway(conn, self.largest_processed_request)?;
                 },
 
+                Ok((_stream_id, quiche::h3::Event::Metadata { .. })) => (),
+
                 Err(quiche::h3::Error::Done) => {
                     break;
                 },
@@ -1602,6 +1640,17 @@ impl HttpConn for Http3Conn {
         Ok(())
     }
 
+    fn send_goaway(&mut self, conn: &mut quiche::Connection) {
+        // Requests still in flight may have any ID, so allow all of them, as
+        // described in RFC 9114 Section 5.2. The client stops sending new
+        // ones once it gets the GOAWAY.
+        const MAX_REQUEST_ID: u64 = (1 << 62) - 4;
+
+        if let Err(e) = self.h3_conn.send_goaway(conn, MAX_REQUEST_ID) {
+            error!("{} failed to send GOAWAY {:?}", conn.trace_id(), e);
+        }
+    }
+
     fn handle_writable(
         &mut self, conn: &mut quiche::Connection,
         partial_responses: &mut HashMap<u64, PartialResponse>, stream_id: u64,
@@ -0,0 +1,160 @@
+// Copyright (C) 2025, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! TOML configuration file support.
+//!
+//! A configuration file passed with `--config FILE` is a flat TOML table whose
+//! keys are the long CLI option names without the leading dashes, e.g.:
+//!
+//! ```toml
+//! max-data = 10000000
+//! cc-algorithm = "bbr2"
+//! early-data = true
+//! header = ["user-agent: lab", "x-run: 42"]
+//! ```
+//!
+//! Boolean values toggle flags, arrays repeat the option once per element and
+//! every other value is passed as the option's argument. Options given on the
+//! command line take precedence over the ones in the file.
+
+/// The CLI option used to pass a configuration file.
+pub const CONFIG_OPTION: &str = "--config";
+
+/// Returns the process arguments merged with the options from the
+/// configuration file, if one was passed with `--config`.
+///
+/// The result is meant to be passed to [`Docopt::argv()`].
+///
+/// [`Docopt::argv()`]: https://docs.rs/docopt/1.1.0/docopt/struct.Docopt.html#method.argv
+pub fn args_with_config_file() -> Result<Vec<String>, String> {
+    merge_config_file(std::env::args().collect())
+}
+
+/// Merges the options from the configuration file referenced by `args`, if
+/// any, into `args`.
+pub fn merge_config_file(args: Vec<String>) -> Result<Vec<String>, String> {
+    let path = match config_file_path(&args) {
+        Some(v) => v,
+
+        None => return Ok(args),
+    };
+
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| format!("failed to read config file {path}: {e}"))?;
+
+    let table = content
+        .parse::<toml::Table>()
+        .map_err(|e| format!("failed to parse config file {path}: {e}"))?;
+
+    let config_args = config_to_args(&table, &args)?;
+
+    // Keep the program name first, so the file options come before the ones
+    // given on the command line.
+    let mut merged = Vec::with_capacity(args.len() + config_args.len());
+    let mut args = args.into_iter();
+
+    merged.extend(args.next());
+    merged.extend(config_args);
+    merged.extend(args);
+
+    Ok(merged)
+}
+
+/// Returns the configuration file path passed on the command line.
+pub fn config_file_path(args: &[String]) -> Option<String> {
+    let prefix = format!("{CONFIG_OPTION}=");
+
+    let mut iter = args.iter();
+
+    while let Some(arg) = iter.next() {
+        if arg == CONFIG_OPTION {
+            return iter.next().cloned();
+        }
+
+        if let Some(path) = arg.strip_prefix(&prefix) {
+            return Some(path.to_string());
+        }
+    }
+
+    None
+}
+
+/// Converts a configuration table into CLI arguments, skipping scalar options
+/// already present in `cli_args`.
+fn config_to_args(
+    table: &toml::Table, cli_args: &[String],
+) -> Result<Vec<String>, String> {
+    let mut args = Vec::new();
+
+    for (key, value) in table {
+        let option = format!("--{key}");
+
+        if option == CONFIG_OPTION {
+            return Err("config files can't be nested".to_string());
+        }
+
+        match value {
+            toml::Value::Array(values) =>
+                for v in values {
+                    args.push(option.clone());
+                    args.push(value_to_arg(key, v)?);
+                },
+
+            _ if is_present(&option, cli_args) => (),
+
+            toml::Value::Boolean(true) => args.push(option),
+
+            toml::Value::Boolean(false) => (),
+
+            v => {
+                args.push(option);
+                args.push(value_to_arg(key, v)?);
+            },
+        }
+    }
+
+    Ok(args)
+}
+
+fn value_to_arg(key: &str, value: &toml::Value) -> Result<String, String> {
+    match value {
+        toml::Value::String(v) => Ok(v.clone()),
+
+        toml::Value::Integer(v) => Ok(v.to_string()),
+
+        toml::Value::Float(v) => Ok(v.to_string()),
+
+        _ => Err(format!("unsupported value for config key {key}")),
+    }
+}
+
+fn is_present(option: &str, args: &[String]) -> bool {
+    args.iter().any(|arg| {
+        arg == option ||
+            arg.strip_prefix(option)
+                .is_some_and(|rest| rest.starts_with('='))
+    })
+}
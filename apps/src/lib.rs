@@ -27,7 +27,17 @@
 #[macro_use]
 extern crate log;
 
+#[cfg(feature = "acme")]
+pub mod acme;
+#[cfg(feature = "alt-svc")]
+pub mod altsvc;
 pub mod args;
 pub mod client;
 pub mod common;
+pub mod config;
 pub mod sendto;
+pub mod socket;
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+pub mod uring;
+#[cfg(all(target_os = "linux", feature = "af-xdp"))]
+pub mod xdp;
@@ -28,9 +28,11 @@ use std::cmp;
 
 use std::io;
 
+use crate::socket::Socket;
+
 /// For Linux, try to detect GSO is available.
 #[cfg(target_os = "linux")]
-pub fn detect_gso(socket: &mio::net::UdpSocket, segment_size: usize) -> bool {
+pub fn detect_gso(socket: &Socket, segment_size: usize) -> bool {
     use nix::sys::socket::setsockopt;
     use nix::sys::socket::sockopt::UdpGsoSegment;
     use std::os::unix::io::AsRawFd;
@@ -40,14 +42,14 @@ pub fn detect_gso(socket: &mio::net::UdpSocket, segment_size: usize) -> bool {
 
 /// For non-Linux, there is no GSO support.
 #[cfg(not(target_os = "linux"))]
-pub fn detect_gso(_socket: &mio::net::UdpSocket, _segment_size: usize) -> bool {
+pub fn detect_gso(_socket: &Socket, _segment_size: usize) -> bool {
     false
 }
 
 /// Send packets using sendmsg() with GSO.
 #[cfg(target_os = "linux")]
 fn send_to_gso_pacing(
-    socket: &mio::net::UdpSocket, buf: &[u8], send_info: &quiche::SendInfo,
+    socket: &Socket, buf: &[u8], send_info: &quiche::SendInfo,
     segment_size: usize,
 ) -> io::Result<usize> {
     use nix::sys::socket::sendmsg;
@@ -62,11 +64,22 @@ fn send_to_gso_pacing(
     let dst = SockaddrStorage::from(send_info.to);
     let sockfd = socket.as_raw_fd();
 
+    let send_time = std_time_to_u64(&send_info.at);
+
+    #[cfg(feature = "io-uring")]
+    if let Socket::IoUring(socket) = socket {
+        return socket.send_msg(
+            buf,
+            send_info.to,
+            Some(segment_size),
+            Some(send_time),
+        );
+    }
+
     // GSO option.
     let cmsg_gso = ControlMessage::UdpGsoSegments(&segment_size);
 
     // Pacing option.
-    let send_time = std_time_to_u64(&send_info.at);
     let cmsg_txtime = ControlMessage::TxTime(&send_time);
 
     match sendmsg(
@@ -84,7 +97,7 @@ fn send_to_gso_pacing(
 /// For non-Linux platforms.
 #[cfg(not(target_os = "linux"))]
 fn send_to_gso_pacing(
-    _socket: &mio::net::UdpSocket, _buf: &[u8], _send_info: &quiche::SendInfo,
+    _socket: &Socket, _buf: &[u8], _send_info: &quiche::SendInfo,
     _segment_size: usize,
 ) -> io::Result<usize> {
     panic!("send_to_gso() should not be called on non-linux platforms");
@@ -95,7 +108,7 @@ fn send_to_gso_pacing(
 /// When GSO and SO_TXTIME are enabled, send packets using send_to_gso().
 /// Otherwise, send packets using socket.send_to().
 pub fn send_to(
-    socket: &mio::net::UdpSocket, buf: &[u8], send_info: &quiche::SendInfo,
+    socket: &Socket, buf: &[u8], send_info: &quiche::SendInfo,
     segment_size: usize, pacing: bool, enable_gso: bool,
 ) -> io::Result<usize> {
     if pacing && enable_gso {
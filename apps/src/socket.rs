@@ -0,0 +1,200 @@
+// Copyright (C) 2025, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! UDP sockets with a runtime selectable I/O backend.
+
+use std::io;
+
+use std::net;
+
+use std::os::unix::io::AsRawFd;
+use std::os::unix::io::RawFd;
+
+/// The I/O backend used for UDP sockets.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum IoBackend {
+    /// Non-blocking system calls driven by mio.
+    #[default]
+    Mio,
+
+    /// io_uring, see the `uring` module. Requires the `io-uring` feature.
+    IoUring,
+}
+
+impl std::str::FromStr for IoBackend {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "mio" => Ok(IoBackend::Mio),
+
+            "io-uring" => Ok(IoBackend::IoUring),
+
+            _ => Err(format!("unknown I/O backend {name}")),
+        }
+    }
+}
+
+/// A non-blocking UDP socket.
+///
+/// It can be registered with a mio event loop regardless of the backend.
+pub enum Socket {
+    Mio(mio::net::UdpSocket),
+
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    IoUring(Box<crate::uring::UringSocket>),
+
+    #[cfg(all(target_os = "linux", feature = "af-xdp"))]
+    Xdp(Box<crate::xdp::XdpSocket>),
+}
+
+impl Socket {
+    /// Creates a socket bound to `addr`, using the given I/O backend.
+    pub fn bind(addr: net::SocketAddr, backend: IoBackend) -> io::Result<Self> {
+        match backend {
+            IoBackend::Mio => Ok(Socket::Mio(mio::net::UdpSocket::bind(addr)?)),
+
+            #[cfg(all(target_os = "linux", feature = "io-uring"))]
+            IoBackend::IoUring => {
+                let socket = net::UdpSocket::bind(addr)?;
+                socket.set_nonblocking(true)?;
+
+                let socket = crate::uring::UringSocket::new(socket)?;
+
+                Ok(Socket::IoUring(Box::new(socket)))
+            },
+
+            #[cfg(not(all(target_os = "linux", feature = "io-uring")))]
+            IoBackend::IoUring => Err(io::Error::other(
+                "the io-uring backend requires Linux and the io-uring feature",
+            )),
+        }
+    }
+
+    pub fn local_addr(&self) -> io::Result<net::SocketAddr> {
+        match self {
+            Socket::Mio(s) => s.local_addr(),
+
+            #[cfg(all(target_os = "linux", feature = "io-uring"))]
+            Socket::IoUring(s) => s.local_addr(),
+
+            #[cfg(all(target_os = "linux", feature = "af-xdp"))]
+            Socket::Xdp(s) => s.local_addr(),
+        }
+    }
+
+    pub fn recv_from(
+        &self, buf: &mut [u8],
+    ) -> io::Result<(usize, net::SocketAddr)> {
+        match self {
+            Socket::Mio(s) => s.recv_from(buf),
+
+            #[cfg(all(target_os = "linux", feature = "io-uring"))]
+            Socket::IoUring(s) => s.recv_from(buf),
+
+            #[cfg(all(target_os = "linux", feature = "af-xdp"))]
+            Socket::Xdp(s) => s.recv_from(buf),
+        }
+    }
+
+    pub fn send_to(&self, buf: &[u8], to: net::SocketAddr) -> io::Result<usize> {
+        match self {
+            Socket::Mio(s) => s.send_to(buf, to),
+
+            #[cfg(all(target_os = "linux", feature = "io-uring"))]
+            Socket::IoUring(s) => s.send_msg(buf, to, None, None),
+
+            #[cfg(all(target_os = "linux", feature = "af-xdp"))]
+            Socket::Xdp(s) => s.send_to(buf, to),
+        }
+    }
+}
+
+/// Returns the file descriptor of the UDP socket itself, e.g. for setting
+/// socket options.
+impl AsRawFd for Socket {
+    fn as_raw_fd(&self) -> RawFd {
+        match self {
+            Socket::Mio(s) => s.as_raw_fd(),
+
+            #[cfg(all(target_os = "linux", feature = "io-uring"))]
+            Socket::IoUring(s) => s.socket().as_raw_fd(),
+
+            #[cfg(all(target_os = "linux", feature = "af-xdp"))]
+            Socket::Xdp(s) => s.as_raw_fd(),
+        }
+    }
+}
+
+impl mio::event::Source for Socket {
+    fn register(
+        &mut self, registry: &mio::Registry, token: mio::Token,
+        interests: mio::Interest,
+    ) -> io::Result<()> {
+        match self {
+            Socket::Mio(s) => s.register(registry, token, interests),
+
+            #[cfg(all(target_os = "linux", feature = "io-uring"))]
+            Socket::IoUring(s) => mio::unix::SourceFd(&s.as_raw_fd())
+                .register(registry, token, interests),
+
+            #[cfg(all(target_os = "linux", feature = "af-xdp"))]
+            Socket::Xdp(s) => mio::unix::SourceFd(&s.as_raw_fd())
+                .register(registry, token, interests),
+        }
+    }
+
+    fn reregister(
+        &mut self, registry: &mio::Registry, token: mio::Token,
+        interests: mio::Interest,
+    ) -> io::Result<()> {
+        match self {
+            Socket::Mio(s) => s.reregister(registry, token, interests),
+
+            #[cfg(all(target_os = "linux", feature = "io-uring"))]
+            Socket::IoUring(s) => mio::unix::SourceFd(&s.as_raw_fd())
+                .reregister(registry, token, interests),
+
+            #[cfg(all(target_os = "linux", feature = "af-xdp"))]
+            Socket::Xdp(s) => mio::unix::SourceFd(&s.as_raw_fd())
+                .reregister(registry, token, interests),
+        }
+    }
+
+    fn deregister(&mut self, registry: &mio::Registry) -> io::Result<()> {
+        match self {
+            Socket::Mio(s) => s.deregister(registry),
+
+            #[cfg(all(target_os = "linux", feature = "io-uring"))]
+            Socket::IoUring(s) =>
+                mio::unix::SourceFd(&s.as_raw_fd()).deregister(registry),
+
+            #[cfg(all(target_os = "linux", feature = "af-xdp"))]
+            Socket::Xdp(s) =>
+                mio::unix::SourceFd(&s.as_raw_fd()).deregister(registry),
+        }
+    }
+}
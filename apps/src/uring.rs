@@ -0,0 +1,937 @@
+// Copyright (C) 2025, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! io_uring based UDP I/O.
+//!
+//! Datagrams are received by a single multishot `recvmsg` request that picks
+//! its buffers from a ring of provided buffers, so that the kernel keeps
+//! delivering packets without any further submission, and all the packets
+//! that arrived since the last wakeup are reaped in one batch. Datagrams are
+//! sent with zero-copy `sendmsg`, falling back to a regular `sendmsg` when the
+//! kernel doesn't support it.
+//!
+//! The ring is driven directly through the io_uring system calls, and requires
+//! Linux 6.0 or newer.
+
+use std::cell::RefCell;
+
+use std::collections::VecDeque;
+
+use std::io;
+
+use std::mem;
+
+use std::net;
+
+use std::os::fd::AsRawFd;
+use std::os::fd::FromRawFd;
+use std::os::fd::OwnedFd;
+use std::os::fd::RawFd;
+
+use std::ptr;
+
+use std::sync::atomic::AtomicU16;
+use std::sync::atomic::AtomicU32;
+use std::sync::atomic::Ordering;
+
+/// Number of submission queue entries.
+const RING_ENTRIES: u32 = 256;
+
+/// Number of provided receive buffers. Must be a power of two.
+const RECV_BUFFERS: u16 = 256;
+
+/// Size of each receive buffer. This includes the `recvmsg` header and the
+/// source address, which precede the datagram.
+const RECV_BUFFER_SIZE: usize = 4096;
+
+/// Maximum number of in-flight sends.
+const SEND_SLOTS: usize = 64;
+
+/// Buffer group ID of the receive buffers.
+const RECV_BUFFER_GROUP: u16 = 0;
+
+/// `user_data` of the multishot receive request. Sends use the index of their
+/// slot instead.
+const RECV_USER_DATA: u64 = u64::MAX;
+
+const IORING_OFF_SQ_RING: libc::off_t = 0;
+const IORING_OFF_CQ_RING: libc::off_t = 0x8000000;
+const IORING_OFF_SQES: libc::off_t = 0x10000000;
+
+const IORING_FEAT_SINGLE_MMAP: u32 = 1 << 0;
+
+const IORING_ENTER_GETEVENTS: u32 = 1 << 0;
+
+const IORING_REGISTER_PBUF_RING: u32 = 22;
+
+const IORING_OP_SENDMSG: u8 = 9;
+const IORING_OP_RECVMSG: u8 = 10;
+const IORING_OP_SENDMSG_ZC: u8 = 48;
+
+const IOSQE_BUFFER_SELECT: u8 = 1 << 5;
+
+const IORING_RECV_MULTISHOT: u16 = 1 << 1;
+
+const IORING_CQE_F_BUFFER: u32 = 1 << 0;
+const IORING_CQE_F_MORE: u32 = 1 << 1;
+const IORING_CQE_F_NOTIF: u32 = 1 << 3;
+const IORING_CQE_BUFFER_SHIFT: u32 = 16;
+
+const UDP_SEGMENT: libc::c_int = 103;
+const SCM_TXTIME: libc::c_int = 61;
+
+#[repr(C)]
+#[derive(Default)]
+struct SqringOffsets {
+    head: u32,
+    tail: u32,
+    ring_mask: u32,
+    ring_entries: u32,
+    flags: u32,
+    dropped: u32,
+    array: u32,
+    resv1: u32,
+    user_addr: u64,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct CqringOffsets {
+    head: u32,
+    tail: u32,
+    ring_mask: u32,
+    ring_entries: u32,
+    overflow: u32,
+    cqes: u32,
+    flags: u32,
+    resv1: u32,
+    user_addr: u64,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct Params {
+    sq_entries: u32,
+    cq_entries: u32,
+    flags: u32,
+    sq_thread_cpu: u32,
+    sq_thread_idle: u32,
+    features: u32,
+    wq_fd: u32,
+    resv: [u32; 3],
+    sq_off: SqringOffsets,
+    cq_off: CqringOffsets,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct Sqe {
+    opcode: u8,
+    flags: u8,
+    ioprio: u16,
+    fd: i32,
+    off: u64,
+    addr: u64,
+    len: u32,
+    op_flags: u32,
+    user_data: u64,
+    buf_group: u16,
+    personality: u16,
+    file_index: i32,
+    addr3: u64,
+    pad: u64,
+}
+
+#[repr(C)]
+struct Cqe {
+    user_data: u64,
+    res: i32,
+    flags: u32,
+}
+
+#[repr(C)]
+struct BufReg {
+    ring_addr: u64,
+    ring_entries: u32,
+    bgid: u16,
+    flags: u16,
+    resv: [u64; 3],
+}
+
+#[repr(C)]
+struct Buf {
+    addr: u64,
+    len: u32,
+    bid: u16,
+    resv: u16,
+}
+
+#[repr(C)]
+struct RecvmsgOut {
+    namelen: u32,
+    controllen: u32,
+    payloadlen: u32,
+    flags: u32,
+}
+
+/// A shared memory mapping.
+struct Mmap {
+    ptr: *mut libc::c_void,
+    len: usize,
+}
+
+impl Mmap {
+    /// Maps one of the ring's regions.
+    fn ring(fd: RawFd, len: usize, offset: libc::off_t) -> io::Result<Self> {
+        Self::new(fd, len, offset, libc::MAP_SHARED | libc::MAP_POPULATE)
+    }
+
+    /// Maps page aligned anonymous memory.
+    fn anonymous(len: usize) -> io::Result<Self> {
+        Self::new(-1, len, 0, libc::MAP_PRIVATE | libc::MAP_ANONYMOUS)
+    }
+
+    fn new(
+        fd: RawFd, len: usize, offset: libc::off_t, flags: libc::c_int,
+    ) -> io::Result<Self> {
+        let ptr = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                flags,
+                fd,
+                offset,
+            )
+        };
+
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(Mmap { ptr, len })
+    }
+
+    /// Returns a pointer to the value at `offset` bytes into the mapping.
+    fn at<T>(&self, offset: u32) -> *mut T {
+        unsafe { self.ptr.cast::<u8>().add(offset as usize).cast() }
+    }
+}
+
+impl Drop for Mmap {
+    fn drop(&mut self) {
+        unsafe { libc::munmap(self.ptr, self.len) };
+    }
+}
+
+/// The submission and completion queues.
+struct Ring {
+    fd: OwnedFd,
+
+    sq_head: *const AtomicU32,
+    sq_tail: *const AtomicU32,
+    sq_mask: u32,
+    sq_entries: u32,
+    sq_array: *mut u32,
+    sqes: *mut Sqe,
+
+    cq_head: *const AtomicU32,
+    cq_tail: *const AtomicU32,
+    cq_mask: u32,
+    cqes: *const Cqe,
+
+    /// Entries pushed but not submitted yet.
+    to_submit: u32,
+
+    _sq_ring: Mmap,
+    _cq_ring: Option<Mmap>,
+    _sqes: Mmap,
+}
+
+impl Ring {
+    fn new(entries: u32) -> io::Result<Self> {
+        let mut params = Params::default();
+
+        let fd = unsafe {
+            libc::syscall(
+                libc::SYS_io_uring_setup,
+                entries,
+                &mut params as *mut Params,
+            )
+        };
+
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let fd = unsafe { OwnedFd::from_raw_fd(fd as RawFd) };
+
+        let sq_len = params.sq_off.array as usize +
+            params.sq_entries as usize * mem::size_of::<u32>();
+        let cq_len = params.cq_off.cqes as usize +
+            params.cq_entries as usize * mem::size_of::<Cqe>();
+
+        // Both rings share the same mapping on kernels that support it.
+        let single_mmap = params.features & IORING_FEAT_SINGLE_MMAP != 0;
+
+        let sq_ring = Mmap::ring(
+            fd.as_raw_fd(),
+            if single_mmap {
+                sq_len.max(cq_len)
+            } else {
+                sq_len
+            },
+            IORING_OFF_SQ_RING,
+        )?;
+
+        let cq_ring = if single_mmap {
+            None
+        } else {
+            Some(Mmap::ring(fd.as_raw_fd(), cq_len, IORING_OFF_CQ_RING)?)
+        };
+
+        let sqes = Mmap::ring(
+            fd.as_raw_fd(),
+            params.sq_entries as usize * mem::size_of::<Sqe>(),
+            IORING_OFF_SQES,
+        )?;
+
+        let cq = cq_ring.as_ref().unwrap_or(&sq_ring);
+
+        Ok(Ring {
+            sq_head: sq_ring.at(params.sq_off.head),
+            sq_tail: sq_ring.at(params.sq_off.tail),
+            sq_mask: unsafe { *sq_ring.at::<u32>(params.sq_off.ring_mask) },
+            sq_entries: params.sq_entries,
+            sq_array: sq_ring.at(params.sq_off.array),
+            sqes: sqes.at(0),
+
+            cq_head: cq.at(params.cq_off.head),
+            cq_tail: cq.at(params.cq_off.tail),
+            cq_mask: unsafe { *cq.at::<u32>(params.cq_off.ring_mask) },
+            cqes: cq.at(params.cq_off.cqes),
+
+            to_submit: 0,
+
+            fd,
+            _sq_ring: sq_ring,
+            _cq_ring: cq_ring,
+            _sqes: sqes,
+        })
+    }
+
+    /// Queues a submission entry. It is only passed to the kernel on the next
+    /// call to `submit()`.
+    fn push(&mut self, sqe: Sqe) -> io::Result<()> {
+        let head = unsafe { (*self.sq_head).load(Ordering::Acquire) };
+        let tail = unsafe { (*self.sq_tail).load(Ordering::Relaxed) };
+
+        if tail.wrapping_sub(head) == self.sq_entries {
+            self.submit(0)?;
+        }
+
+        let idx = tail & self.sq_mask;
+
+        unsafe {
+            self.sqes.add(idx as usize).write(sqe);
+            self.sq_array.add(idx as usize).write(idx);
+
+            (*self.sq_tail).store(tail.wrapping_add(1), Ordering::Release);
+        }
+
+        self.to_submit += 1;
+
+        Ok(())
+    }
+
+    /// Submits the queued entries and waits for at least `wait` completions.
+    ///
+    /// This also runs any pending completion work, so it's worth calling with
+    /// nothing to submit before reaping.
+    fn submit(&mut self, wait: u32) -> io::Result<()> {
+        loop {
+            let ret = unsafe {
+                libc::syscall(
+                    libc::SYS_io_uring_enter,
+                    self.fd.as_raw_fd(),
+                    self.to_submit,
+                    wait,
+                    IORING_ENTER_GETEVENTS,
+                    ptr::null::<libc::sigset_t>(),
+                    0,
+                )
+            };
+
+            if ret < 0 {
+                let err = io::Error::last_os_error();
+
+                if err.kind() == io::ErrorKind::Interrupted {
+                    continue;
+                }
+
+                return Err(err);
+            }
+
+            self.to_submit -= ret as u32;
+
+            return Ok(());
+        }
+    }
+
+    /// Returns the next completion, if any.
+    fn pop(&mut self) -> Option<Cqe> {
+        unsafe {
+            let head = (*self.cq_head).load(Ordering::Relaxed);
+            let tail = (*self.cq_tail).load(Ordering::Acquire);
+
+            if head == tail {
+                return None;
+            }
+
+            let cqe = self.cqes.add((head & self.cq_mask) as usize).read();
+
+            (*self.cq_head).store(head.wrapping_add(1), Ordering::Release);
+
+            Some(cqe)
+        }
+    }
+
+    fn register<T>(&self, opcode: u32, arg: &T) -> io::Result<()> {
+        let ret = unsafe {
+            libc::syscall(
+                libc::SYS_io_uring_register,
+                self.fd.as_raw_fd(),
+                opcode,
+                arg as *const T,
+                1,
+            )
+        };
+
+        if ret < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(())
+    }
+}
+
+/// Ring of buffers provided to the kernel for receiving.
+struct BufRing {
+    ring: Mmap,
+    bufs: Vec<u8>,
+    tail: u16,
+}
+
+impl BufRing {
+    fn new() -> io::Result<Self> {
+        let ring =
+            Mmap::anonymous(RECV_BUFFERS as usize * mem::size_of::<Buf>())?;
+
+        let mut buf_ring = BufRing {
+            ring,
+            bufs: vec![0; RECV_BUFFERS as usize * RECV_BUFFER_SIZE],
+            tail: 0,
+        };
+
+        for bid in 0..RECV_BUFFERS {
+            buf_ring.add(bid);
+        }
+
+        buf_ring.publish();
+
+        Ok(buf_ring)
+    }
+
+    fn buf(&self, bid: u16) -> &[u8] {
+        let off = bid as usize * RECV_BUFFER_SIZE;
+        &self.bufs[off..off + RECV_BUFFER_SIZE]
+    }
+
+    /// Hands a buffer back to the kernel. It's only visible to the kernel
+    /// after `publish()`.
+    fn add(&mut self, bid: u16) {
+        let idx = self.tail & (RECV_BUFFERS - 1);
+        let entry = self.ring.at::<Buf>(0).wrapping_add(idx as usize);
+
+        // The `resv` field of the first entry holds the ring's tail, so only
+        // the other fields can be written.
+        unsafe {
+            ptr::addr_of_mut!((*entry).addr)
+                .write(self.bufs.as_ptr().add(bid as usize * RECV_BUFFER_SIZE)
+                    as u64);
+            ptr::addr_of_mut!((*entry).len).write(RECV_BUFFER_SIZE as u32);
+            ptr::addr_of_mut!((*entry).bid).write(bid);
+        }
+
+        self.tail = self.tail.wrapping_add(1);
+    }
+
+    fn publish(&self) {
+        let tail = self.ring.at::<AtomicU16>(mem::offset_of!(Buf, resv) as u32);
+
+        unsafe { (*tail).store(self.tail, Ordering::Release) };
+    }
+}
+
+/// State of an in-flight send. Slots are allocated once, so the message passed
+/// to the kernel doesn't move until the send completes.
+struct SendSlot {
+    buf: Vec<u8>,
+    addr: libc::sockaddr_storage,
+    iov: libc::iovec,
+    control: [u64; 8],
+    msg: libc::msghdr,
+}
+
+struct State {
+    ring: Ring,
+
+    buf_ring: BufRing,
+
+    /// The multishot receive request. It needs to stay alive as long as the
+    /// request is active.
+    recv_msg: Box<libc::msghdr>,
+
+    /// Whether the multishot receive request is active.
+    recv_armed: bool,
+
+    /// Completed receives, as buffer ID and length.
+    received: VecDeque<(u16, usize)>,
+
+    send_slots: Vec<SendSlot>,
+
+    free_send_slots: Vec<usize>,
+
+    zero_copy: bool,
+}
+
+/// A UDP socket whose I/O goes through io_uring.
+///
+/// Register it with an event loop through the file descriptor returned by
+/// `as_raw_fd()`, which is the ring's. It becomes readable when completions
+/// are available.
+pub struct UringSocket {
+    socket: net::UdpSocket,
+    state: RefCell<State>,
+}
+
+impl UringSocket {
+    pub fn new(socket: net::UdpSocket) -> io::Result<Self> {
+        let ring = Ring::new(RING_ENTRIES)?;
+        let buf_ring = BufRing::new()?;
+
+        let reg = BufReg {
+            ring_addr: buf_ring.ring.ptr as u64,
+            ring_entries: RECV_BUFFERS as u32,
+            bgid: RECV_BUFFER_GROUP,
+            flags: 0,
+            resv: [0; 3],
+        };
+
+        ring.register(IORING_REGISTER_PBUF_RING, &reg)?;
+
+        // Only the size of the source address matters, the kernel lays out
+        // each received buffer based on it.
+        let mut recv_msg: Box<libc::msghdr> = Box::new(unsafe { mem::zeroed() });
+        recv_msg.msg_namelen = mem::size_of::<libc::sockaddr_storage>() as u32;
+
+        let send_slots = (0..SEND_SLOTS)
+            .map(|_| SendSlot {
+                buf: Vec::new(),
+                addr: unsafe { mem::zeroed() },
+                iov: unsafe { mem::zeroed() },
+                control: [0; 8],
+                msg: unsafe { mem::zeroed() },
+            })
+            .collect();
+
+        let mut state = State {
+            ring,
+            buf_ring,
+            recv_msg,
+            recv_armed: false,
+            received: VecDeque::new(),
+            send_slots,
+            free_send_slots: (0..SEND_SLOTS).rev().collect(),
+            zero_copy: true,
+        };
+
+        state.arm_recv(socket.as_raw_fd())?;
+
+        Ok(UringSocket {
+            socket,
+            state: RefCell::new(state),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<net::SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    /// Returns the underlying socket, e.g. to set socket options.
+    pub fn socket(&self) -> &net::UdpSocket {
+        &self.socket
+    }
+
+    /// Returns the next received datagram, or `WouldBlock` if there is none.
+    pub fn recv_from(
+        &self, buf: &mut [u8],
+    ) -> io::Result<(usize, net::SocketAddr)> {
+        let mut state = self.state.borrow_mut();
+
+        if state.received.is_empty() {
+            state.reap()?;
+        }
+
+        let (bid, len) = match state.received.pop_front() {
+            Some(v) => v,
+
+            None => {
+                // The request stops when it runs out of buffers. All of them
+                // have been given back by now, so start it again.
+                if !state.recv_armed {
+                    state.arm_recv(self.socket.as_raw_fd())?;
+                }
+
+                return Err(io::ErrorKind::WouldBlock.into());
+            },
+        };
+
+        let res = state.parse_recv(bid, len, buf);
+
+        state.buf_ring.add(bid);
+        state.buf_ring.publish();
+
+        res
+    }
+
+    /// Queues a datagram for sending to `to`.
+    ///
+    /// When `segment_size` is set, `buf` is split into multiple datagrams by
+    /// the kernel (GSO). When `txtime` is set, the datagrams are sent at the
+    /// given `CLOCK_MONOTONIC` time in nanoseconds, which requires `SO_TXTIME`
+    /// to be set on the socket.
+    ///
+    /// The send completes asynchronously, so this returns as soon as the
+    /// datagram is queued. Errors are only logged.
+    pub fn send_msg(
+        &self, buf: &[u8], to: net::SocketAddr, segment_size: Option<u16>,
+        txtime: Option<u64>,
+    ) -> io::Result<usize> {
+        let mut state = self.state.borrow_mut();
+
+        let slot = state.free_send_slot()?;
+
+        let zero_copy = state.zero_copy;
+
+        let s = &mut state.send_slots[slot];
+
+        s.buf.clear();
+        s.buf.extend_from_slice(buf);
+
+        s.iov = libc::iovec {
+            iov_base: s.buf.as_mut_ptr().cast(),
+            iov_len: s.buf.len(),
+        };
+
+        s.msg = unsafe { mem::zeroed() };
+        s.msg.msg_name = ptr::addr_of_mut!(s.addr).cast();
+        s.msg.msg_namelen = write_sockaddr(&to, &mut s.addr);
+        s.msg.msg_iov = &mut s.iov;
+        s.msg.msg_iovlen = 1;
+
+        if segment_size.is_some() || txtime.is_some() {
+            s.msg.msg_control = s.control.as_mut_ptr().cast();
+            s.msg.msg_controllen = mem::size_of_val(&s.control) as _;
+
+            let mut controllen = 0;
+
+            unsafe {
+                let mut cmsg = libc::CMSG_FIRSTHDR(&s.msg);
+
+                if let Some(segment_size) = segment_size {
+                    write_cmsg(cmsg, libc::SOL_UDP, UDP_SEGMENT, segment_size);
+                    controllen += libc::CMSG_SPACE(mem::size_of::<u16>() as u32);
+                    cmsg = libc::CMSG_NXTHDR(&s.msg, cmsg);
+                }
+
+                if let Some(txtime) = txtime {
+                    write_cmsg(cmsg, libc::SOL_SOCKET, SCM_TXTIME, txtime);
+                    controllen += libc::CMSG_SPACE(mem::size_of::<u64>() as u32);
+                }
+            }
+
+            s.msg.msg_controllen = controllen as _;
+        }
+
+        let sqe = Sqe {
+            opcode: if zero_copy {
+                IORING_OP_SENDMSG_ZC
+            } else {
+                IORING_OP_SENDMSG
+            },
+            fd: self.socket.as_raw_fd(),
+            addr: ptr::addr_of!(s.msg) as u64,
+            len: 1,
+            user_data: slot as u64,
+            ..Default::default()
+        };
+
+        state.ring.push(sqe)?;
+        state.ring.submit(0)?;
+
+        Ok(buf.len())
+    }
+}
+
+impl AsRawFd for UringSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.state.borrow().ring.fd.as_raw_fd()
+    }
+}
+
+impl State {
+    fn arm_recv(&mut self, fd: RawFd) -> io::Result<()> {
+        let sqe = Sqe {
+            opcode: IORING_OP_RECVMSG,
+            flags: IOSQE_BUFFER_SELECT,
+            ioprio: IORING_RECV_MULTISHOT,
+            fd,
+            addr: &*self.recv_msg as *const libc::msghdr as u64,
+            len: 1,
+            user_data: RECV_USER_DATA,
+            buf_group: RECV_BUFFER_GROUP,
+            ..Default::default()
+        };
+
+        self.ring.push(sqe)?;
+        self.ring.submit(0)?;
+
+        self.recv_armed = true;
+
+        Ok(())
+    }
+
+    /// Processes all the available completions.
+    fn reap(&mut self) -> io::Result<()> {
+        // Flush completions that are still pending in the kernel.
+        self.ring.submit(0)?;
+
+        while let Some(cqe) = self.ring.pop() {
+            if cqe.user_data == RECV_USER_DATA {
+                if cqe.flags & IORING_CQE_F_MORE == 0 {
+                    self.recv_armed = false;
+                }
+
+                if cqe.res >= 0 && cqe.flags & IORING_CQE_F_BUFFER != 0 {
+                    let bid = (cqe.flags >> IORING_CQE_BUFFER_SHIFT) as u16;
+                    self.received.push_back((bid, cqe.res as usize));
+                } else if cqe.res != -libc::ENOBUFS {
+                    error!(
+                        "io_uring recvmsg failed: {}",
+                        io::Error::from_raw_os_error(-cqe.res)
+                    );
+                }
+
+                continue;
+            }
+
+            let slot = cqe.user_data as usize;
+
+            // Zero-copy sends complete twice, the buffer can only be reused
+            // after the notification.
+            if cqe.flags & IORING_CQE_F_NOTIF == 0 && cqe.res < 0 {
+                if cqe.res == -libc::EINVAL && self.zero_copy {
+                    warn!("io_uring zero-copy send unsupported, disabling");
+                    self.zero_copy = false;
+                } else {
+                    debug!(
+                        "io_uring sendmsg failed: {}",
+                        io::Error::from_raw_os_error(-cqe.res)
+                    );
+                }
+            }
+
+            if cqe.flags & IORING_CQE_F_MORE == 0 {
+                self.free_send_slots.push(slot);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns a free send slot, waiting for in-flight sends to complete if
+    /// there is none.
+    fn free_send_slot(&mut self) -> io::Result<usize> {
+        loop {
+            if let Some(slot) = self.free_send_slots.pop() {
+                return Ok(slot);
+            }
+
+            self.reap()?;
+
+            if self.free_send_slots.is_empty() {
+                self.ring.submit(1)?;
+            }
+        }
+    }
+
+    /// Copies the datagram received in buffer `bid` into `out`.
+    fn parse_recv(
+        &self, bid: u16, len: usize, out: &mut [u8],
+    ) -> io::Result<(usize, net::SocketAddr)> {
+        let buf = &self.buf_ring.buf(bid)[..len];
+
+        // No control messages are requested, so the datagram directly
+        // follows the source address.
+        let hdr_len = mem::size_of::<RecvmsgOut>();
+        let name_len = self.recv_msg.msg_namelen as usize;
+
+        if buf.len() < hdr_len + name_len {
+            return Err(io::Error::other("short io_uring recvmsg buffer"));
+        }
+
+        let hdr = unsafe { buf.as_ptr().cast::<RecvmsgOut>().read_unaligned() };
+
+        if hdr.flags & libc::MSG_TRUNC as u32 != 0 {
+            return Err(io::Error::other("datagram truncated"));
+        }
+
+        let mut addr: libc::sockaddr_storage = unsafe { mem::zeroed() };
+
+        let addr_len = (hdr.namelen as usize).min(name_len);
+
+        unsafe {
+            ptr::copy_nonoverlapping(
+                buf.as_ptr().add(hdr_len),
+                ptr::addr_of_mut!(addr).cast::<u8>(),
+                addr_len,
+            );
+        }
+
+        let from = read_sockaddr(&addr)?;
+
+        let payload = &buf[hdr_len + name_len..];
+        let payload = &payload[..(hdr.payloadlen as usize).min(payload.len())];
+
+        if payload.len() > out.len() {
+            return Err(io::Error::other("receive buffer too small"));
+        }
+
+        out[..payload.len()].copy_from_slice(payload);
+
+        Ok((payload.len(), from))
+    }
+}
+
+unsafe fn write_cmsg<T>(
+    cmsg: *mut libc::cmsghdr, level: libc::c_int, ty: libc::c_int, value: T,
+) {
+    (*cmsg).cmsg_level = level;
+    (*cmsg).cmsg_type = ty;
+    (*cmsg).cmsg_len = libc::CMSG_LEN(mem::size_of::<T>() as u32) as _;
+
+    ptr::write_unaligned(libc::CMSG_DATA(cmsg).cast::<T>(), value);
+}
+
+fn write_sockaddr(
+    addr: &net::SocketAddr, storage: &mut libc::sockaddr_storage,
+) -> libc::socklen_t {
+    *storage = unsafe { mem::zeroed() };
+
+    match addr {
+        net::SocketAddr::V4(addr) => {
+            let sin = libc::sockaddr_in {
+                sin_family: libc::AF_INET as libc::sa_family_t,
+                sin_port: addr.port().to_be(),
+                sin_addr: libc::in_addr {
+                    s_addr: u32::from_ne_bytes(addr.ip().octets()),
+                },
+                sin_zero: [0; 8],
+            };
+
+            unsafe {
+                ptr::write(ptr::addr_of_mut!(*storage).cast(), sin);
+            }
+
+            mem::size_of::<libc::sockaddr_in>() as libc::socklen_t
+        },
+
+        net::SocketAddr::V6(addr) => {
+            let sin6 = libc::sockaddr_in6 {
+                sin6_family: libc::AF_INET6 as libc::sa_family_t,
+                sin6_port: addr.port().to_be(),
+                sin6_flowinfo: addr.flowinfo(),
+                sin6_addr: libc::in6_addr {
+                    s6_addr: addr.ip().octets(),
+                },
+                sin6_scope_id: addr.scope_id(),
+            };
+
+            unsafe {
+                ptr::write(ptr::addr_of_mut!(*storage).cast(), sin6);
+            }
+
+            mem::size_of::<libc::sockaddr_in6>() as libc::socklen_t
+        },
+    }
+}
+
+fn read_sockaddr(
+    storage: &libc::sockaddr_storage,
+) -> io::Result<net::SocketAddr> {
+    match storage.ss_family as libc::c_int {
+        libc::AF_INET => {
+            let sin = unsafe {
+                &*(storage as *const libc::sockaddr_storage)
+                    .cast::<libc::sockaddr_in>()
+            };
+
+            Ok(net::SocketAddr::from((
+                sin.sin_addr.s_addr.to_ne_bytes(),
+                u16::from_be(sin.sin_port),
+            )))
+        },
+
+        libc::AF_INET6 => {
+            let sin6 = unsafe {
+                &*(storage as *const libc::sockaddr_storage)
+                    .cast::<libc::sockaddr_in6>()
+            };
+
+            Ok(net::SocketAddr::V6(net::SocketAddrV6::new(
+                sin6.sin6_addr.s6_addr.into(),
+                u16::from_be(sin6.sin6_port),
+                sin6.sin6_flowinfo,
+                sin6.sin6_scope_id,
+            )))
+        },
+
+        family => Err(io::Error::other(format!(
+            "unsupported address family {family}"
+        ))),
+    }
+}
@@ -0,0 +1,1032 @@
+// Copyright (C) 2025, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Experimental AF_XDP based UDP I/O.
+//!
+//! An XDP program attached to the network interface redirects the UDP
+//! packets destined to the server's port to an AF_XDP socket bound to the
+//! receive queue they arrived on, bypassing the kernel's network stack. All
+//! other traffic is passed to the kernel as usual.
+//!
+//! Each socket owns its UMEM, the memory area holding the packet frames, and
+//! is meant to be driven by a single thread. Ethernet, IP and UDP headers are
+//! parsed and built here; replies are sent back to the MAC address that the
+//! peer's packets were received from, so there is no ARP or neighbor
+//! discovery, and only plain IPv4 and IPv6 packets without VLAN tags,
+//! extension headers or fragmentation are supported.
+//!
+//! The XDP program is attached with a BPF link, which requires Linux 5.9 or
+//! newer and `CAP_NET_ADMIN` and `CAP_BPF` (or `CAP_SYS_ADMIN`).
+
+use std::cell::RefCell;
+
+use std::collections::HashMap;
+
+use std::ffi::CString;
+
+use std::io;
+
+use std::mem;
+
+use std::net;
+
+use std::os::fd::AsRawFd;
+use std::os::fd::FromRawFd;
+use std::os::fd::OwnedFd;
+use std::os::fd::RawFd;
+
+use std::ptr;
+
+use std::sync::atomic::AtomicU32;
+use std::sync::atomic::Ordering;
+
+/// Size of each UMEM frame. A frame holds a single packet.
+const FRAME_SIZE: usize = 2048;
+
+/// Number of frames in the UMEM of each socket.
+const FRAMES: u32 = 4096;
+
+/// Number of entries of each ring. Half of the frames are used to receive,
+/// the other half to send.
+const RING_SIZE: u32 = FRAMES / 2;
+
+const ETH_HDR_LEN: usize = 14;
+const IPV4_HDR_LEN: usize = 20;
+const IPV6_HDR_LEN: usize = 40;
+const UDP_HDR_LEN: usize = 8;
+
+const ETH_P_IPV4: u16 = 0x0800;
+const ETH_P_IPV6: u16 = 0x86dd;
+
+const IPPROTO_UDP: u8 = 17;
+
+const BPF_MAP_CREATE: libc::c_long = 0;
+const BPF_MAP_UPDATE_ELEM: libc::c_long = 2;
+const BPF_PROG_LOAD: libc::c_long = 5;
+const BPF_LINK_CREATE: libc::c_long = 28;
+
+const BPF_MAP_TYPE_XSKMAP: u32 = 17;
+const BPF_PROG_TYPE_XDP: u32 = 6;
+const BPF_XDP: u32 = 37;
+
+const XDP_FLAGS_SKB_MODE: u32 = 1 << 1;
+
+const XDP_PASS: i32 = 2;
+
+const BPF_FUNC_REDIRECT_MAP: i32 = 51;
+
+#[repr(C)]
+struct MapCreateAttr {
+    map_type: u32,
+    key_size: u32,
+    value_size: u32,
+    max_entries: u32,
+}
+
+#[repr(C)]
+struct MapUpdateAttr {
+    map_fd: u32,
+    pad: u32,
+    key: u64,
+    value: u64,
+    flags: u64,
+}
+
+#[repr(C)]
+struct ProgLoadAttr {
+    prog_type: u32,
+    insn_cnt: u32,
+    insns: u64,
+    license: u64,
+    log_level: u32,
+    log_size: u32,
+    log_buf: u64,
+    kern_version: u32,
+    prog_flags: u32,
+    prog_name: [u8; 16],
+    prog_ifindex: u32,
+    expected_attach_type: u32,
+}
+
+#[repr(C)]
+struct LinkCreateAttr {
+    prog_fd: u32,
+    target_ifindex: u32,
+    attach_type: u32,
+    flags: u32,
+}
+
+/// Performs a `bpf(2)` system call that returns a file descriptor.
+fn bpf<T>(cmd: libc::c_long, attr: &T) -> io::Result<libc::c_long> {
+    let res = unsafe {
+        libc::syscall(libc::SYS_bpf, cmd, attr as *const T, mem::size_of::<T>())
+    };
+
+    if res < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(res)
+}
+
+/// A BPF instruction.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Insn {
+    code: u8,
+    regs: u8,
+    off: i16,
+    imm: i32,
+}
+
+const BPF_LDX_W: u8 = 0x61;
+const BPF_LDX_H: u8 = 0x69;
+const BPF_LDX_B: u8 = 0x71;
+const BPF_LD_IMM64: u8 = 0x18;
+const BPF_MOV_X: u8 = 0xbf;
+const BPF_MOV_K: u8 = 0xb7;
+const BPF_ADD_K: u8 = 0x07;
+const BPF_AND_K: u8 = 0x57;
+const BPF_JA: u8 = 0x05;
+const BPF_JEQ_K: u8 = 0x15;
+const BPF_JNE_K: u8 = 0x55;
+const BPF_JGT_X: u8 = 0x2d;
+const BPF_CALL: u8 = 0x85;
+const BPF_EXIT: u8 = 0x95;
+
+const BPF_PSEUDO_MAP_FD: u8 = 1;
+
+/// A BPF program whose jumps refer to labels.
+#[derive(Default)]
+struct Asm {
+    insns: Vec<Insn>,
+    labels: HashMap<&'static str, usize>,
+    jumps: Vec<(usize, &'static str)>,
+}
+
+impl Asm {
+    fn insn(&mut self, code: u8, dst: u8, src: u8, off: i16, imm: i32) {
+        self.insns.push(Insn {
+            code,
+            regs: dst | (src << 4),
+            off,
+            imm,
+        });
+    }
+
+    fn jump(&mut self, code: u8, dst: u8, src: u8, imm: i32, to: &'static str) {
+        self.jumps.push((self.insns.len(), to));
+        self.insn(code, dst, src, 0, imm);
+    }
+
+    fn label(&mut self, name: &'static str) {
+        self.labels.insert(name, self.insns.len());
+    }
+
+    fn finish(mut self) -> Vec<Insn> {
+        for (pc, to) in self.jumps {
+            let target = self.labels[to];
+
+            self.insns[pc].off = (target as isize - pc as isize - 1) as i16;
+        }
+
+        self.insns
+    }
+}
+
+/// Returns the XDP program redirecting UDP packets destined to `port` to the
+/// socket in `xsk_map` bound to the receive queue of the packet.
+fn redirect_program(xsk_map: RawFd, port: u16) -> Vec<Insn> {
+    // Header fields are loaded in host (little endian) byte order.
+    let port = i32::from(u16::from_le_bytes(port.to_be_bytes()));
+    let eth_ipv4 = i32::from(u16::from_le_bytes(ETH_P_IPV4.to_be_bytes()));
+    let eth_ipv6 = i32::from(u16::from_le_bytes(ETH_P_IPV6.to_be_bytes()));
+
+    let ipv4_udp = ETH_HDR_LEN + IPV4_HDR_LEN;
+    let ipv6_udp = ETH_HDR_LEN + IPV6_HDR_LEN;
+
+    let mut asm = Asm::default();
+
+    // r2 = ctx->data, r3 = ctx->data_end
+    asm.insn(BPF_LDX_W, 2, 1, 0, 0);
+    asm.insn(BPF_LDX_W, 3, 1, 4, 0);
+
+    asm.insn(BPF_MOV_X, 4, 2, 0, 0);
+    asm.insn(BPF_ADD_K, 4, 0, 0, ETH_HDR_LEN as i32);
+    asm.jump(BPF_JGT_X, 4, 3, 0, "pass");
+
+    // EtherType.
+    asm.insn(BPF_LDX_H, 5, 2, 12, 0);
+    asm.jump(BPF_JEQ_K, 5, 0, eth_ipv4, "ipv4");
+    asm.jump(BPF_JEQ_K, 5, 0, eth_ipv6, "ipv6");
+    asm.jump(BPF_JA, 0, 0, 0, "pass");
+
+    asm.label("ipv4");
+    asm.insn(BPF_MOV_X, 4, 2, 0, 0);
+    asm.insn(BPF_ADD_K, 4, 0, 0, (ipv4_udp + UDP_HDR_LEN) as i32);
+    asm.jump(BPF_JGT_X, 4, 3, 0, "pass");
+
+    // No IPv4 options.
+    asm.insn(BPF_LDX_B, 5, 2, ETH_HDR_LEN as i16, 0);
+    asm.insn(BPF_AND_K, 5, 0, 0, 0x0f);
+    asm.jump(BPF_JNE_K, 5, 0, 5, "pass");
+
+    // Protocol.
+    asm.insn(BPF_LDX_B, 5, 2, (ETH_HDR_LEN + 9) as i16, 0);
+    asm.jump(BPF_JNE_K, 5, 0, IPPROTO_UDP.into(), "pass");
+
+    // UDP destination port.
+    asm.insn(BPF_LDX_H, 5, 2, (ipv4_udp + 2) as i16, 0);
+    asm.jump(BPF_JNE_K, 5, 0, port, "pass");
+    asm.jump(BPF_JA, 0, 0, 0, "redirect");
+
+    asm.label("ipv6");
+    asm.insn(BPF_MOV_X, 4, 2, 0, 0);
+    asm.insn(BPF_ADD_K, 4, 0, 0, (ipv6_udp + UDP_HDR_LEN) as i32);
+    asm.jump(BPF_JGT_X, 4, 3, 0, "pass");
+
+    // Next header.
+    asm.insn(BPF_LDX_B, 5, 2, (ETH_HDR_LEN + 6) as i16, 0);
+    asm.jump(BPF_JNE_K, 5, 0, IPPROTO_UDP.into(), "pass");
+
+    // UDP destination port.
+    asm.insn(BPF_LDX_H, 5, 2, (ipv6_udp + 2) as i16, 0);
+    asm.jump(BPF_JNE_K, 5, 0, port, "pass");
+
+    // return bpf_redirect_map(xsk_map, ctx->rx_queue_index, XDP_PASS)
+    asm.label("redirect");
+    asm.insn(BPF_LDX_W, 2, 1, 16, 0);
+    asm.insn(BPF_LD_IMM64, 1, BPF_PSEUDO_MAP_FD, 0, xsk_map);
+    asm.insn(0, 0, 0, 0, 0);
+    asm.insn(BPF_MOV_K, 3, 0, 0, XDP_PASS);
+    asm.insn(BPF_CALL, 0, 0, 0, BPF_FUNC_REDIRECT_MAP);
+    asm.insn(BPF_EXIT, 0, 0, 0, 0);
+
+    asm.label("pass");
+    asm.insn(BPF_MOV_K, 0, 0, 0, XDP_PASS);
+    asm.insn(BPF_EXIT, 0, 0, 0, 0);
+
+    asm.finish()
+}
+
+/// Returns the index of the network interface called `name`.
+pub fn ifindex(name: &str) -> io::Result<u32> {
+    let name = CString::new(name)
+        .map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?;
+
+    match unsafe { libc::if_nametoindex(name.as_ptr()) } {
+        0 => Err(io::Error::last_os_error()),
+
+        index => Ok(index),
+    }
+}
+
+/// Pins the calling thread to the given CPU.
+pub fn pin_to_cpu(cpu: usize) -> io::Result<()> {
+    let mut set: libc::cpu_set_t = unsafe { mem::zeroed() };
+
+    unsafe { libc::CPU_SET(cpu, &mut set) };
+
+    let res = unsafe {
+        libc::sched_setaffinity(0, mem::size_of::<libc::cpu_set_t>(), &set)
+    };
+
+    if res < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+/// An XDP program attached to a network interface, redirecting the UDP
+/// packets destined to a port to AF_XDP sockets.
+///
+/// The program is detached when this is dropped.
+pub struct XdpProgram {
+    xsk_map: OwnedFd,
+
+    _link: OwnedFd,
+}
+
+impl XdpProgram {
+    /// Attaches the program to the interface with index `ifindex`, for
+    /// sockets bound to the first `queues` receive queues.
+    ///
+    /// Native XDP is used when the driver supports it, generic XDP otherwise.
+    pub fn attach(ifindex: u32, port: u16, queues: u32) -> io::Result<Self> {
+        let attr = MapCreateAttr {
+            map_type: BPF_MAP_TYPE_XSKMAP,
+            key_size: 4,
+            value_size: 4,
+            max_entries: queues,
+        };
+
+        let xsk_map = bpf(BPF_MAP_CREATE, &attr)?;
+        let xsk_map = unsafe { OwnedFd::from_raw_fd(xsk_map as RawFd) };
+
+        let prog = load_program(&redirect_program(xsk_map.as_raw_fd(), port))?;
+
+        let mut attr = LinkCreateAttr {
+            prog_fd: prog.as_raw_fd() as u32,
+            target_ifindex: ifindex,
+            attach_type: BPF_XDP,
+            flags: 0,
+        };
+
+        let link = match bpf(BPF_LINK_CREATE, &attr) {
+            Ok(v) => v,
+
+            Err(e) => {
+                debug!("native XDP unavailable, using generic XDP: {e}");
+
+                attr.flags = XDP_FLAGS_SKB_MODE;
+
+                bpf(BPF_LINK_CREATE, &attr)?
+            },
+        };
+
+        Ok(XdpProgram {
+            xsk_map,
+            _link: unsafe { OwnedFd::from_raw_fd(link as RawFd) },
+        })
+    }
+
+    /// Starts redirecting packets received on `queue` to `socket`.
+    pub fn insert(&self, queue: u32, socket: &XdpSocket) -> io::Result<()> {
+        let fd = socket.as_raw_fd() as u32;
+
+        let attr = MapUpdateAttr {
+            map_fd: self.xsk_map.as_raw_fd() as u32,
+            pad: 0,
+            key: &queue as *const u32 as u64,
+            value: &fd as *const u32 as u64,
+            flags: 0,
+        };
+
+        bpf(BPF_MAP_UPDATE_ELEM, &attr)?;
+
+        Ok(())
+    }
+}
+
+/// Loads an XDP program, including the verifier's log in the error.
+fn load_program(insns: &[Insn]) -> io::Result<OwnedFd> {
+    let license = b"Dual BSD/GPL\0";
+
+    let mut log = vec![0u8; 64 * 1024];
+
+    let mut attr = ProgLoadAttr {
+        prog_type: BPF_PROG_TYPE_XDP,
+        insn_cnt: insns.len() as u32,
+        insns: insns.as_ptr() as u64,
+        license: license.as_ptr() as u64,
+        log_level: 0,
+        log_size: 0,
+        log_buf: 0,
+        kern_version: 0,
+        prog_flags: 0,
+        prog_name: *b"quiche_xsk\0\0\0\0\0\0",
+        prog_ifindex: 0,
+        expected_attach_type: BPF_XDP,
+    };
+
+    if let Ok(fd) = bpf(BPF_PROG_LOAD, &attr) {
+        return Ok(unsafe { OwnedFd::from_raw_fd(fd as RawFd) });
+    }
+
+    // Load again, just to get the verifier's log.
+    attr.log_level = 1;
+    attr.log_size = log.len() as u32;
+    attr.log_buf = log.as_mut_ptr() as u64;
+
+    let e = match bpf(BPF_PROG_LOAD, &attr) {
+        Ok(fd) => return Ok(unsafe { OwnedFd::from_raw_fd(fd as RawFd) }),
+
+        Err(e) => e,
+    };
+
+    let len = log.iter().position(|&b| b == 0).unwrap_or(log.len());
+
+    Err(io::Error::new(
+        e.kind(),
+        format!(
+            "failed to load XDP program: {e}\n{}",
+            String::from_utf8_lossy(&log[..len])
+        ),
+    ))
+}
+
+/// A shared memory mapping.
+struct Mmap {
+    ptr: *mut libc::c_void,
+    len: usize,
+}
+
+impl Mmap {
+    fn new(fd: RawFd, len: usize, offset: libc::off_t) -> io::Result<Self> {
+        let flags = if fd < 0 {
+            libc::MAP_PRIVATE | libc::MAP_ANONYMOUS
+        } else {
+            libc::MAP_SHARED | libc::MAP_POPULATE
+        };
+
+        let ptr = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                flags,
+                fd,
+                offset,
+            )
+        };
+
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(Mmap { ptr, len })
+    }
+
+    /// Returns a pointer to the value at `offset` bytes into the mapping.
+    fn at<T>(&self, offset: u64) -> *mut T {
+        unsafe { self.ptr.cast::<u8>().add(offset as usize).cast() }
+    }
+}
+
+impl Drop for Mmap {
+    fn drop(&mut self) {
+        unsafe { libc::munmap(self.ptr, self.len) };
+    }
+}
+
+/// One of the four rings shared with the kernel: the fill and completion
+/// rings hold UMEM frame addresses, the RX and TX rings packet descriptors.
+struct Ring<T> {
+    _map: Mmap,
+    producer: *const AtomicU32,
+    consumer: *const AtomicU32,
+    flags: *const AtomicU32,
+    entries: *mut T,
+}
+
+impl<T: Copy> Ring<T> {
+    fn new(
+        fd: RawFd, offsets: &libc::xdp_ring_offset, pgoff: libc::off_t,
+    ) -> io::Result<Self> {
+        let len =
+            offsets.desc as usize + RING_SIZE as usize * mem::size_of::<T>();
+
+        let map = Mmap::new(fd, len, pgoff)?;
+
+        Ok(Ring {
+            producer: map.at(offsets.producer),
+            consumer: map.at(offsets.consumer),
+            flags: map.at(offsets.flags),
+            entries: map.at(offsets.desc),
+            _map: map,
+        })
+    }
+
+    fn producer(&self) -> &AtomicU32 {
+        unsafe { &*self.producer }
+    }
+
+    fn consumer(&self) -> &AtomicU32 {
+        unsafe { &*self.consumer }
+    }
+
+    /// Whether the kernel needs to be woken up to process the ring.
+    fn needs_wakeup(&self) -> bool {
+        let flags = unsafe { &*self.flags };
+
+        flags.load(Ordering::Relaxed) & libc::XDP_RING_NEED_WAKEUP != 0
+    }
+
+    /// Adds an entry to a ring produced by userspace. Returns false if the
+    /// ring is full.
+    fn push(&self, entry: T) -> bool {
+        let prod = self.producer().load(Ordering::Relaxed);
+        let cons = self.consumer().load(Ordering::Acquire);
+
+        if prod.wrapping_sub(cons) == RING_SIZE {
+            return false;
+        }
+
+        unsafe {
+            self.entries
+                .add((prod & (RING_SIZE - 1)) as usize)
+                .write(entry)
+        };
+
+        self.producer()
+            .store(prod.wrapping_add(1), Ordering::Release);
+
+        true
+    }
+
+    /// Takes an entry from a ring produced by the kernel.
+    fn pop(&self) -> Option<T> {
+        let cons = self.consumer().load(Ordering::Relaxed);
+        let prod = self.producer().load(Ordering::Acquire);
+
+        if cons == prod {
+            return None;
+        }
+
+        let entry =
+            unsafe { self.entries.add((cons & (RING_SIZE - 1)) as usize).read() };
+
+        self.consumer()
+            .store(cons.wrapping_add(1), Ordering::Release);
+
+        Some(entry)
+    }
+}
+
+/// Link and network addresses of a peer, learnt from its packets.
+#[derive(Clone, Copy)]
+struct Route {
+    local_mac: [u8; 6],
+    peer_mac: [u8; 6],
+    local_ip: net::IpAddr,
+}
+
+struct State {
+    umem: Mmap,
+
+    fill: Ring<u64>,
+    completion: Ring<u64>,
+    rx: Ring<libc::xdp_desc>,
+    tx: Ring<libc::xdp_desc>,
+
+    /// Frames that can be used to send.
+    free_frames: Vec<u64>,
+
+    routes: HashMap<net::SocketAddr, Route>,
+}
+
+/// An AF_XDP socket receiving and sending the UDP datagrams of one port on
+/// one receive queue of a network interface.
+///
+/// Packets are only delivered to it once it's been inserted in an
+/// [`XdpProgram`].
+pub struct XdpSocket {
+    fd: OwnedFd,
+    local: net::SocketAddr,
+    state: RefCell<State>,
+}
+
+// The rings are only ever accessed through the socket, which isn't `Sync`.
+unsafe impl Send for XdpSocket {}
+
+impl XdpSocket {
+    /// Creates a socket bound to `queue` of the interface with index
+    /// `ifindex`, for the UDP datagrams destined to the port of `local`.
+    pub fn new(
+        ifindex: u32, queue: u32, local: net::SocketAddr,
+    ) -> io::Result<Self> {
+        let fd = unsafe {
+            libc::socket(libc::AF_XDP, libc::SOCK_RAW | libc::SOCK_CLOEXEC, 0)
+        };
+
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+        let raw = fd.as_raw_fd();
+
+        let umem = Mmap::new(-1, FRAMES as usize * FRAME_SIZE, 0)?;
+
+        let reg = libc::xdp_umem_reg {
+            addr: umem.ptr as u64,
+            len: umem.len as u64,
+            chunk_size: FRAME_SIZE as u32,
+            headroom: 0,
+            flags: 0,
+            tx_metadata_len: 0,
+        };
+
+        setsockopt(raw, libc::XDP_UMEM_REG, &reg)?;
+
+        setsockopt(raw, libc::XDP_UMEM_FILL_RING, &RING_SIZE)?;
+        setsockopt(raw, libc::XDP_UMEM_COMPLETION_RING, &RING_SIZE)?;
+        setsockopt(raw, libc::XDP_RX_RING, &RING_SIZE)?;
+        setsockopt(raw, libc::XDP_TX_RING, &RING_SIZE)?;
+
+        let mut offsets: libc::xdp_mmap_offsets = unsafe { mem::zeroed() };
+        let mut len = mem::size_of::<libc::xdp_mmap_offsets>() as u32;
+
+        let res = unsafe {
+            libc::getsockopt(
+                raw,
+                libc::SOL_XDP,
+                libc::XDP_MMAP_OFFSETS,
+                &mut offsets as *mut _ as *mut libc::c_void,
+                &mut len,
+            )
+        };
+
+        if res < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let state = State {
+            fill: Ring::new(
+                raw,
+                &offsets.fr,
+                libc::XDP_UMEM_PGOFF_FILL_RING as libc::off_t,
+            )?,
+            completion: Ring::new(
+                raw,
+                &offsets.cr,
+                libc::XDP_UMEM_PGOFF_COMPLETION_RING as libc::off_t,
+            )?,
+            rx: Ring::new(raw, &offsets.rx, libc::XDP_PGOFF_RX_RING)?,
+            tx: Ring::new(raw, &offsets.tx, libc::XDP_PGOFF_TX_RING)?,
+
+            free_frames: (RING_SIZE..FRAMES)
+                .map(|i| u64::from(i) * FRAME_SIZE as u64)
+                .collect(),
+
+            routes: HashMap::new(),
+
+            umem,
+        };
+
+        for i in 0..RING_SIZE {
+            state.fill.push(u64::from(i) * FRAME_SIZE as u64);
+        }
+
+        let mut addr: libc::sockaddr_xdp = unsafe { mem::zeroed() };
+        addr.sxdp_family = libc::AF_XDP as u16;
+        addr.sxdp_flags = libc::XDP_USE_NEED_WAKEUP;
+        addr.sxdp_ifindex = ifindex;
+        addr.sxdp_queue_id = queue;
+
+        let res = unsafe {
+            libc::bind(
+                raw,
+                &addr as *const _ as *const libc::sockaddr,
+                mem::size_of::<libc::sockaddr_xdp>() as libc::socklen_t,
+            )
+        };
+
+        if res < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(XdpSocket {
+            fd,
+            local,
+            state: RefCell::new(state),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<net::SocketAddr> {
+        Ok(self.local)
+    }
+
+    /// Receives a datagram, or fails with `WouldBlock` if none is pending.
+    pub fn recv_from(
+        &self, buf: &mut [u8],
+    ) -> io::Result<(usize, net::SocketAddr)> {
+        let mut state = self.state.borrow_mut();
+
+        while let Some(desc) = state.rx.pop() {
+            let frame = desc.addr - desc.addr % FRAME_SIZE as u64;
+
+            let res = {
+                let pkt = unsafe {
+                    std::slice::from_raw_parts(
+                        state.umem.at::<u8>(desc.addr),
+                        desc.len as usize,
+                    )
+                };
+
+                parse_packet(pkt, self.local.port()).map(|(from, route, data)| {
+                    let len = data.len().min(buf.len());
+                    buf[..len].copy_from_slice(&data[..len]);
+
+                    (from, route, len)
+                })
+            };
+
+            state.fill.push(frame);
+
+            if state.fill.needs_wakeup() {
+                self.wakeup();
+            }
+
+            if let Some((from, route, len)) = res {
+                state.routes.insert(from, route);
+
+                return Ok((len, from));
+            }
+        }
+
+        Err(io::Error::from(io::ErrorKind::WouldBlock))
+    }
+
+    /// Sends a datagram to a peer that a datagram was previously received
+    /// from.
+    pub fn send_to(&self, buf: &[u8], to: net::SocketAddr) -> io::Result<usize> {
+        let mut state = self.state.borrow_mut();
+
+        while let Some(frame) = state.completion.pop() {
+            state.free_frames.push(frame);
+        }
+
+        let route = match state.routes.get(&to) {
+            Some(v) => *v,
+
+            None =>
+                return Err(io::Error::new(
+                    io::ErrorKind::AddrNotAvailable,
+                    format!("no route to {to}"),
+                )),
+        };
+
+        let frame = match state.free_frames.pop() {
+            Some(v) => v,
+
+            None => return Err(io::Error::from(io::ErrorKind::WouldBlock)),
+        };
+
+        let pkt = unsafe {
+            std::slice::from_raw_parts_mut(state.umem.at::<u8>(frame), FRAME_SIZE)
+        };
+
+        let len = match build_packet(pkt, &route, self.local.port(), to, buf) {
+            Some(v) => v,
+
+            None => {
+                state.free_frames.push(frame);
+
+                return Err(io::Error::from(io::ErrorKind::InvalidInput));
+            },
+        };
+
+        let desc = libc::xdp_desc {
+            addr: frame,
+            len: len as u32,
+            options: 0,
+        };
+
+        if !state.tx.push(desc) {
+            state.free_frames.push(frame);
+
+            return Err(io::Error::from(io::ErrorKind::WouldBlock));
+        }
+
+        if state.tx.needs_wakeup() {
+            self.wakeup();
+        }
+
+        Ok(buf.len())
+    }
+
+    /// Asks the kernel to process the rings.
+    fn wakeup(&self) {
+        unsafe {
+            libc::sendto(
+                self.fd.as_raw_fd(),
+                ptr::null(),
+                0,
+                libc::MSG_DONTWAIT,
+                ptr::null(),
+                0,
+            )
+        };
+    }
+}
+
+impl AsRawFd for XdpSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+fn setsockopt<T>(fd: RawFd, opt: libc::c_int, val: &T) -> io::Result<()> {
+    let res = unsafe {
+        libc::setsockopt(
+            fd,
+            libc::SOL_XDP,
+            opt,
+            val as *const T as *const libc::c_void,
+            mem::size_of::<T>() as libc::socklen_t,
+        )
+    };
+
+    if res < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+/// Parses an Ethernet frame carrying a UDP datagram destined to `port`,
+/// returning the source address, the route back to it and the payload.
+fn parse_packet(
+    pkt: &[u8], port: u16,
+) -> Option<(net::SocketAddr, Route, &[u8])> {
+    let eth = pkt.get(..ETH_HDR_LEN)?;
+
+    let mut local_mac = [0; 6];
+    let mut peer_mac = [0; 6];
+    local_mac.copy_from_slice(&eth[0..6]);
+    peer_mac.copy_from_slice(&eth[6..12]);
+
+    let ip = &pkt[ETH_HDR_LEN..];
+
+    let (src, dst, udp) = match u16::from_be_bytes([eth[12], eth[13]]) {
+        ETH_P_IPV4 => {
+            let hdr = ip.get(..IPV4_HDR_LEN)?;
+            let hdr_len = usize::from(hdr[0] & 0x0f) * 4;
+            let total_len = usize::from(u16::from_be_bytes([hdr[2], hdr[3]]));
+
+            // Fragments aren't reassembled.
+            let frag = u16::from_be_bytes([hdr[6], hdr[7]]) & 0x3fff;
+
+            if hdr[9] != IPPROTO_UDP || frag != 0 || hdr_len < IPV4_HDR_LEN {
+                return None;
+            }
+
+            let src: [u8; 4] = hdr[12..16].try_into().ok()?;
+            let dst: [u8; 4] = hdr[16..20].try_into().ok()?;
+
+            (
+                net::IpAddr::from(src),
+                net::IpAddr::from(dst),
+                ip.get(hdr_len..total_len)?,
+            )
+        },
+
+        ETH_P_IPV6 => {
+            let hdr = ip.get(..IPV6_HDR_LEN)?;
+            let payload_len = usize::from(u16::from_be_bytes([hdr[4], hdr[5]]));
+
+            if hdr[6] != IPPROTO_UDP {
+                return None;
+            }
+
+            let src: [u8; 16] = hdr[8..24].try_into().ok()?;
+            let dst: [u8; 16] = hdr[24..40].try_into().ok()?;
+
+            (
+                net::IpAddr::from(src),
+                net::IpAddr::from(dst),
+                ip.get(IPV6_HDR_LEN..IPV6_HDR_LEN + payload_len)?,
+            )
+        },
+
+        _ => return None,
+    };
+
+    let hdr = udp.get(..UDP_HDR_LEN)?;
+    let src_port = u16::from_be_bytes([hdr[0], hdr[1]]);
+    let dst_port = u16::from_be_bytes([hdr[2], hdr[3]]);
+    let len = usize::from(u16::from_be_bytes([hdr[4], hdr[5]]));
+
+    if dst_port != port || len < UDP_HDR_LEN {
+        return None;
+    }
+
+    let route = Route {
+        local_mac,
+        peer_mac,
+        local_ip: dst,
+    };
+
+    Some((
+        net::SocketAddr::new(src, src_port),
+        route,
+        udp.get(UDP_HDR_LEN..len)?,
+    ))
+}
+
+/// Writes an Ethernet frame carrying `payload` from `port` to `to` into
+/// `pkt`, returning its length.
+fn build_packet(
+    pkt: &mut [u8], route: &Route, port: u16, to: net::SocketAddr, payload: &[u8],
+) -> Option<usize> {
+    let udp_len = UDP_HDR_LEN + payload.len();
+
+    let (ethertype, ip_len) = match to {
+        net::SocketAddr::V4(_) => (ETH_P_IPV4, IPV4_HDR_LEN),
+
+        net::SocketAddr::V6(_) => (ETH_P_IPV6, IPV6_HDR_LEN),
+    };
+
+    let len = ETH_HDR_LEN + ip_len + udp_len;
+
+    if len > pkt.len() {
+        return None;
+    }
+
+    pkt[0..6].copy_from_slice(&route.peer_mac);
+    pkt[6..12].copy_from_slice(&route.local_mac);
+    pkt[12..14].copy_from_slice(&ethertype.to_be_bytes());
+
+    let (ip, udp) = pkt[ETH_HDR_LEN..len].split_at_mut(ip_len);
+
+    // Checksum of the pseudo-header.
+    let mut sum = match (route.local_ip, to.ip()) {
+        (net::IpAddr::V4(src), net::IpAddr::V4(dst)) => {
+            ip.fill(0);
+            ip[0] = 0x45;
+            ip[2..4].copy_from_slice(&((ip_len + udp_len) as u16).to_be_bytes());
+            // Don't fragment.
+            ip[6] = 0x40;
+            ip[8] = 64;
+            ip[9] = IPPROTO_UDP;
+            ip[12..16].copy_from_slice(&src.octets());
+            ip[16..20].copy_from_slice(&dst.octets());
+
+            let csum = !fold(checksum(ip, 0));
+            ip[10..12].copy_from_slice(&csum.to_be_bytes());
+
+            checksum(&ip[12..20], 0)
+        },
+
+        (net::IpAddr::V6(src), net::IpAddr::V6(dst)) => {
+            ip.fill(0);
+            ip[0] = 0x60;
+            ip[4..6].copy_from_slice(&(udp_len as u16).to_be_bytes());
+            ip[6] = IPPROTO_UDP;
+            ip[7] = 64;
+            ip[8..24].copy_from_slice(&src.octets());
+            ip[24..40].copy_from_slice(&dst.octets());
+
+            checksum(&ip[8..40], 0)
+        },
+
+        _ => return None,
+    };
+
+    sum += u32::from(IPPROTO_UDP) + udp_len as u32;
+
+    udp[0..2].copy_from_slice(&port.to_be_bytes());
+    udp[2..4].copy_from_slice(&to.port().to_be_bytes());
+    udp[4..6].copy_from_slice(&(udp_len as u16).to_be_bytes());
+    udp[6..8].fill(0);
+    udp[UDP_HDR_LEN..].copy_from_slice(payload);
+
+    let csum = match !fold(checksum(udp, sum)) {
+        0 => 0xffff,
+
+        v => v,
+    };
+
+    udp[6..8].copy_from_slice(&csum.to_be_bytes());
+
+    Some(len)
+}
+
+/// Adds `data` to a ones' complement sum.
+fn checksum(data: &[u8], mut sum: u32) -> u32 {
+    let mut chunks = data.chunks_exact(2);
+
+    for c in &mut chunks {
+        sum += u32::from(u16::from_be_bytes([c[0], c[1]]));
+    }
+
+    if let [b] = chunks.remainder() {
+        sum += u32::from(*b) << 8;
+    }
+
+    fold(sum).into()
+}
+
+/// Folds a ones' complement sum into 16 bits.
+fn fold(mut sum: u32) -> u16 {
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+
+    sum as u16
+}
@@ -0,0 +1,312 @@
+// Copyright (C) 2025, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Seeded, structured mutations of a sequence of [Action]s, to fuzz how a
+//! server handles slightly malformed HTTP/3 traffic.
+//!
+//! Mutations are derived from a seed only, so that a run that made the server
+//! misbehave can be reproduced with the same seed and baseline actions.
+
+use quiche::Rng;
+use serde::Serialize;
+
+use crate::actions::h3::stream_bytes;
+use crate::actions::h3::Action;
+
+/// The maximum number of mutations applied to a sequence of actions.
+const MAX_MUTATIONS: u64 = 3;
+
+/// A mutation applied by [`mutate`]. Indices refer to the mutated actions.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Mutation {
+    /// A bit of the frame type or length varint of the frame at `index` was
+    /// flipped, keeping the varint's length.
+    FlipVarint { index: usize },
+    /// The frame at `index` was truncated to `len` bytes.
+    TruncatePayload { index: usize, len: usize },
+    /// The frames at `first` and `second` were swapped.
+    ReorderFrames { first: usize, second: usize },
+}
+
+/// Applies up to three random mutations to the frames sent by the top-level
+/// actions in `actions`, using `seed` as the only source of randomness.
+///
+/// Frames are only mutated in [Action::SendFrame],
+/// [Action::SendHeadersFrame] and [Action::StreamBytes] actions. Mutated
+/// frames are sent as [Action::StreamBytes], so that they are not re-encoded.
+///
+/// Returns the mutated actions, with the mutations that were applied.
+pub fn mutate(actions: &[Action], seed: u64) -> (Vec<Action>, Vec<Mutation>) {
+    let mut rng = quiche::SeededRng::new(seed);
+    let mut actions = actions.to_vec();
+    let mut mutations = Vec::new();
+
+    let candidates: Vec<usize> = actions
+        .iter()
+        .enumerate()
+        .filter(|(_, a)| stream_bytes(a).is_some())
+        .map(|(i, _)| i)
+        .collect();
+
+    if candidates.is_empty() {
+        return (actions, mutations);
+    }
+
+    let count = 1 + next_u64(&mut rng) % MAX_MUTATIONS;
+
+    for _ in 0..count {
+        let index = candidates[pick(&mut rng, candidates.len())];
+
+        let mutation = match next_u64(&mut rng) % 3 {
+            0 => flip_varint(&mut actions, index, &mut rng),
+
+            1 => truncate_payload(&mut actions, index, &mut rng),
+
+            _ => reorder_frames(&mut actions, index, &candidates, &mut rng),
+        };
+
+        mutations.extend(mutation);
+    }
+
+    (actions, mutations)
+}
+
+fn next_u64(rng: &mut quiche::SeededRng) -> u64 {
+    let mut buf = [0; 8];
+    rng.fill_bytes(&mut buf);
+
+    u64::from_le_bytes(buf)
+}
+
+/// Picks a random index lower than `len`, which must not be 0.
+fn pick(rng: &mut quiche::SeededRng, len: usize) -> usize {
+    (next_u64(rng) % len as u64) as usize
+}
+
+fn flip_varint(
+    actions: &mut [Action], index: usize, rng: &mut quiche::SeededRng,
+) -> Option<Mutation> {
+    let (stream_id, fin_stream, mut bytes) = stream_bytes(&actions[index])?;
+
+    // Find the frame type and length varints, then flip one of them.
+    let mut b = octets::Octets::with_slice(&bytes);
+    let mut varints = Vec::with_capacity(2);
+
+    for _ in 0..2 {
+        let off = b.off();
+        let v = b.get_varint().ok()?;
+        varints.push((off, b.off() - off, v));
+    }
+
+    let (off, len, v) = varints[pick(rng, varints.len())];
+
+    // The two most significant bits encode the varint's length.
+    let bit = pick(rng, len * 8 - 2);
+    let flipped = v ^ (1 << bit);
+
+    let mut b = octets::OctetsMut::with_slice(&mut bytes[off..off + len]);
+    b.put_varint_with_len(flipped, len).ok()?;
+
+    actions[index] = Action::StreamBytes {
+        stream_id,
+        fin_stream,
+        bytes,
+    };
+
+    Some(Mutation::FlipVarint { index })
+}
+
+fn truncate_payload(
+    actions: &mut [Action], index: usize, rng: &mut quiche::SeededRng,
+) -> Option<Mutation> {
+    let (stream_id, fin_stream, mut bytes) = stream_bytes(&actions[index])?;
+
+    if bytes.is_empty() {
+        return None;
+    }
+
+    let len = pick(rng, bytes.len());
+    bytes.truncate(len);
+
+    actions[index] = Action::StreamBytes {
+        stream_id,
+        fin_stream,
+        bytes,
+    };
+
+    Some(Mutation::TruncatePayload { index, len })
+}
+
+/// Swaps the frame at `index` with another one of `candidates`, preferably
+/// on the same stream.
+fn reorder_frames(
+    actions: &mut [Action], index: usize, candidates: &[usize],
+    rng: &mut quiche::SeededRng,
+) -> Option<Mutation> {
+    let stream_id = stream_bytes(&actions[index])?.0;
+
+    let others: Vec<usize> =
+        candidates.iter().copied().filter(|&i| i != index).collect();
+
+    let same_stream: Vec<usize> = others
+        .iter()
+        .copied()
+        .filter(|&i| {
+            stream_bytes(&actions[i]).is_some_and(|(id, ..)| id == stream_id)
+        })
+        .collect();
+
+    let pool = if same_stream.is_empty() {
+        &others
+    } else {
+        &same_stream
+    };
+
+    if pool.is_empty() {
+        return None;
+    }
+
+    let other = pool[pick(rng, pool.len())];
+    actions.swap(index, other);
+
+    Some(Mutation::ReorderFrames {
+        first: index.min(other),
+        second: index.max(other),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actions::h3::send_headers_frame;
+    use quiche::h3::frame::Frame;
+    use quiche::h3::Header;
+
+    fn baseline() -> Vec<Action> {
+        vec![
+            send_headers_frame(0, false, vec![
+                Header::new(b":method", b"POST"),
+                Header::new(b":scheme", b"https"),
+                Header::new(b":authority", b"example.com"),
+                Header::new(b":path", b"/"),
+            ]),
+            Action::SendFrame {
+                stream_id: 0,
+                fin_stream: true,
+                frame: Frame::Data {
+                    payload: b"hello".to_vec(),
+                },
+            },
+            Action::FlushPackets,
+        ]
+    }
+
+    #[test]
+    fn deterministic() {
+        let actions = baseline();
+
+        for seed in 0..32 {
+            assert_eq!(mutate(&actions, seed), mutate(&actions, seed));
+        }
+
+        let outcomes: Vec<_> =
+            (0..32).map(|seed| mutate(&actions, seed).0).collect();
+        assert!(outcomes.iter().any(|a| a != &outcomes[0]));
+    }
+
+    #[test]
+    fn mutations() {
+        let actions = baseline();
+
+        for seed in 0..64 {
+            let (mutated, mutations) = mutate(&actions, seed);
+
+            assert!(!mutations.is_empty());
+            assert!(mutations.len() <= MAX_MUTATIONS as usize);
+            assert_eq!(mutated.len(), actions.len());
+
+            // Actions that don't send frames are left alone.
+            assert!(mutated.contains(&Action::FlushPackets));
+        }
+    }
+
+    #[test]
+    fn flip_varint_keeps_length() {
+        let mut actions = baseline();
+        let (_, _, original) = stream_bytes(&actions[1]).unwrap();
+
+        let mut rng = quiche::SeededRng::new(7);
+        assert_eq!(
+            flip_varint(&mut actions, 1, &mut rng),
+            Some(Mutation::FlipVarint { index: 1 })
+        );
+
+        let Action::StreamBytes { bytes, .. } = &actions[1] else {
+            panic!("unexpected action {:?}", actions[1]);
+        };
+
+        assert_eq!(bytes.len(), original.len());
+        assert_eq!(
+            bytes.iter().zip(&original).filter(|(a, b)| a != b).count(),
+            1
+        );
+    }
+
+    #[test]
+    fn truncate() {
+        let mut actions = baseline();
+
+        let mut rng = quiche::SeededRng::new(1);
+        let Some(Mutation::TruncatePayload { index: 1, len }) =
+            truncate_payload(&mut actions, 1, &mut rng)
+        else {
+            panic!("payload not truncated");
+        };
+
+        assert!(matches!(
+            &actions[1],
+            Action::StreamBytes { bytes, fin_stream: true, .. } if bytes.len() == len
+        ));
+    }
+
+    #[test]
+    fn reorder() {
+        let mut actions = baseline();
+        let expected =
+            vec![actions[1].clone(), actions[0].clone(), actions[2].clone()];
+
+        let mut rng = quiche::SeededRng::new(3);
+        assert_eq!(
+            reorder_frames(&mut actions, 0, &[0, 1], &mut rng),
+            Some(Mutation::ReorderFrames {
+                first: 0,
+                second: 1
+            })
+        );
+        assert_eq!(actions, expected);
+    }
+}
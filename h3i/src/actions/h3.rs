@@ -30,17 +30,22 @@
 //! streams. Each independent use case for h3i requires its own collection of
 //! Actions, that h3i iterates over in sequence and executes.
 
+use std::cmp;
 use std::collections::HashMap;
+use std::net::SocketAddr;
 use std::time::Duration;
 
 use quiche;
 use quiche::h3::frame::Frame;
+use quiche::h3::frame::DATA_FRAME_TYPE_ID;
 use quiche::h3::Header;
+use quiche::h3::NameValue;
 use quiche::ConnectionError;
 use serde::Deserialize;
 use serde::Serialize;
 use serde_with::serde_as;
 
+use crate::client::expectations::Expectation;
 use crate::encode_header_block;
 use crate::encode_header_block_literal;
 
@@ -69,6 +74,10 @@ pub enum Action {
     },
 
     /// Send arbitrary bytes over a stream.
+    ///
+    /// The bytes are written as is, without any framing, so they can be e.g.
+    /// truncated frames or invalid varints on a control, QPACK or request
+    /// stream.
     StreamBytes {
         stream_id: u64,
         fin_stream: bool,
@@ -82,13 +91,37 @@ pub enum Action {
         stream_type: u64,
     },
 
+    /// Open a new WebTransport stream associated with the session established
+    /// by an extended CONNECT request on stream `session_id`.
+    ///
+    /// Only the stream header is sent, use [Action::StreamBytes] to send data
+    /// on the stream afterwards. Data received on a bidirectional WebTransport
+    /// stream is not HTTP/3 framed, so it is captured as is and reported as
+    /// DATA frames in the [`StreamMap`].
+    ///
+    /// [`StreamMap`]: crate::client::connection_summary::StreamMap
+    OpenWebTransportStream {
+        stream_id: u64,
+        session_id: u64,
+        bidi: bool,
+    },
+
     /// Send a RESET_STREAM frame with the given error code.
+    ///
+    /// Data written to the stream but not sent yet is discarded, so an
+    /// [Action::FlushPackets] is needed between the data and the reset to
+    /// cancel a request mid-body.
     ResetStream {
         stream_id: u64,
         error_code: u64,
     },
 
     /// Send a STOP_SENDING frame with the given error code.
+    ///
+    /// The peer is expected to reset the stream in response, which is
+    /// recorded in the [`StreamMap`] like any other RESET_STREAM.
+    ///
+    /// [`StreamMap`]: crate::client::connection_summary::StreamMap
     StopSending {
         stream_id: u64,
         error_code: u64,
@@ -99,6 +132,205 @@ pub enum Action {
         error: ConnectionError,
     },
 
+    /// Send a MAX_DATA frame with the given limit.
+    ///
+    /// The limit is sent as is, even if it is lower than the one previously
+    /// advertised, and doesn't change how much data h3i accepts.
+    MaxData {
+        max: u64,
+    },
+
+    /// Send a MAX_STREAM_DATA frame with the given limit, for any stream.
+    ///
+    /// The limit is sent as is, even if it is lower than the one previously
+    /// advertised, and doesn't change how much data h3i accepts.
+    MaxStreamData {
+        stream_id: u64,
+        max: u64,
+    },
+
+    /// Send a MAX_STREAMS frame with the given limit, for either bidirectional
+    /// or unidirectional streams.
+    ///
+    /// The limit is sent as is, even if it is lower than the one previously
+    /// advertised, and doesn't change how many streams h3i accepts.
+    MaxStreams {
+        bidi: bool,
+        max: u64,
+    },
+
+    /// Start or stop withholding the MAX_DATA, MAX_STREAM_DATA and MAX_STREAMS
+    /// updates h3i would otherwise send as it reads data from the peer.
+    ///
+    /// Withholding updates eventually blocks the peer, which should then send
+    /// DATA_BLOCKED, STREAM_DATA_BLOCKED or STREAMS_BLOCKED frames. These are
+    /// counted in the [`ConnectionSummary`] stats.
+    ///
+    /// [`ConnectionSummary`]: crate::client::connection_summary::ConnectionSummary
+    WithholdFlowControlUpdates {
+        withhold: bool,
+    },
+
+    /// Send a PING frame.
+    Ping,
+
+    /// Send `len` bytes of PADDING frames.
+    Padding {
+        len: usize,
+    },
+
+    /// Send `packets` full-sized packets, each carrying a PING frame padded
+    /// with PADDING frames, back-to-back.
+    ///
+    /// The packets are subject to the congestion window, so they are only all
+    /// sent at once in burst mode, see [`Config::burst_mode`].
+    ///
+    /// [`Config::burst_mode`]: crate::config::Config::burst_mode
+    Burst {
+        packets: usize,
+    },
+
+    /// Send arbitrary bytes in the payload of a 1-RTT packet, as if they were
+    /// QUIC frames.
+    ///
+    /// The bytes are not validated in any way, so they can encode frames h3i
+    /// would never send, or malformed ones, to test how the peer handles
+    /// them. They are never retransmitted.
+    RawQuicFrame {
+        bytes: Vec<u8>,
+    },
+
+    /// Initiate a TLS key update, so that the following 1-RTT packets are
+    /// protected with the next generation of keys and the flipped key phase.
+    ///
+    /// The key update fails, and is logged, if the handshake isn't confirmed
+    /// yet or the previous key update is too recent.
+    UpdateKeys,
+
+    /// Move the connection to a new UDP socket, bound to `local_addr`, or to a
+    /// new ephemeral port if `None`.
+    ///
+    /// By default the client migrates to the new path, which quiche validates
+    /// with a PATH_CHALLENGE frame. This requires a spare connection ID from
+    /// the server. If `nat_rebinding` is set, the client instead carries on as
+    /// if its address hadn't changed, like behind a NAT that rebound the
+    /// connection, so it's up to the server to notice and validate the new
+    /// path.
+    Migrate {
+        local_addr: Option<SocketAddr>,
+        nat_rebinding: bool,
+    },
+
+    /// Open a new UDP socket, bound to `local_addr`, or to a new ephemeral
+    /// port if `None`, and probe a path from it to the server with a
+    /// PATH_CHALLENGE frame. The connection keeps using its current path.
+    ///
+    /// Paths are numbered in the order they are probed, starting at 1, the
+    /// initial path being 0. Probing requires a spare connection ID from the
+    /// server. Wait for [ConnectionEvent::PathValidated] to know the path was
+    /// validated.
+    ProbePath {
+        local_addr: Option<SocketAddr>,
+    },
+
+    /// Make `path`, the initial path or one opened with [Action::ProbePath],
+    /// the active path.
+    ///
+    /// quiche only sends stream data on the active path, so the streams
+    /// written to after this action are steered onto `path`. See
+    /// [`on_path()`].
+    SwitchPath {
+        path: usize,
+    },
+
+    /// Send an HTTP/3 datagram, i.e. a QUIC DATAGRAM frame carrying
+    /// `quarter_stream_id` as a varint followed by `payload`. See [RFC 9297].
+    ///
+    /// The quarter stream ID of a request is its stream ID divided by 4. The
+    /// connection must be configured to support datagrams, see
+    /// [`Config::enable_dgram`].
+    ///
+    /// [RFC 9297]: https://www.rfc-editor.org/rfc/rfc9297#section-2.1
+    /// [`Config::enable_dgram`]: crate::config::Config::enable_dgram
+    SendDatagram {
+        quarter_stream_id: u64,
+        payload: Vec<u8>,
+    },
+
+    /// Write a QPACK instruction on a stream, usually the QPACK encoder or
+    /// decoder stream previously opened with [Action::OpenUniStream].
+    ///
+    /// The instruction is encoded as is, without checking it against the
+    /// state of the peer's dynamic table, so it can be used to exercise the
+    /// peer's QPACK error handling.
+    SendQpackInstruction {
+        stream_id: u64,
+        instruction: QpackInstruction,
+    },
+
+    /// Open `count` requests with the given `headers`, on consecutive
+    /// client-initiated bidirectional streams starting at `first_stream_id`,
+    /// as fast as the peer's stream credit allows.
+    ///
+    /// The pace at which requests are opened is controlled by `ramp`. Later
+    /// actions are only executed once all the requests have been opened. How
+    /// the peer's MAX_STREAMS limit evolved, and which requests were refused,
+    /// is reported in the [`ConnectionSummary`].
+    ///
+    /// [`ConnectionSummary`]: crate::client::connection_summary::ConnectionSummary
+    OpenConcurrentRequests {
+        first_stream_id: u64,
+        count: u64,
+        headers: Vec<Header>,
+        ramp: RampPolicy,
+    },
+
+    /// Check the body received so far on a stream, i.e. the concatenation of
+    /// its DATA frame payloads, against a [BodyAssertion].
+    ///
+    /// The assertion is evaluated when the action is reached, so it usually
+    /// follows a wait for [StreamEventType::Finished] on the same stream. The
+    /// results are reported in the [`ConnectionSummary`].
+    ///
+    /// [`ConnectionSummary`]: crate::client::connection_summary::ConnectionSummary
+    AssertBody {
+        stream_id: u64,
+        assertion: BodyAssertion,
+    },
+
+    /// Record the connection's statistics and the statistics of its paths,
+    /// under `label`. The snapshots are reported in the
+    /// [`ConnectionSummary`], to measure how the actions between two of them
+    /// affect loss, RTT and the bytes on the wire.
+    ///
+    /// [`ConnectionSummary`]: crate::client::connection_summary::ConnectionSummary
+    SnapshotStats {
+        label: String,
+    },
+
+    /// Execute `actions` `count` times in a row.
+    ///
+    /// The actions are expanded as they are executed, so long sequences, such
+    /// as thousands of HEADERS frames, don't need to be generated up front.
+    /// Repeats can be nested, and can contain waits like any other action.
+    Repeat {
+        count: u64,
+        actions: Vec<Action>,
+    },
+
+    /// Execute `then` if `matcher` is met by what was received so far, and
+    /// `otherwise` if it isn't.
+    ///
+    /// Like [Action::AssertBody], the matcher is evaluated when the action is
+    /// reached, so it usually follows a wait, e.g. for the response HEADERS on
+    /// a stream. The chosen actions are executed in place, before the actions
+    /// following this one.
+    OnFrame {
+        matcher: Expectation,
+        then: Vec<Action>,
+        otherwise: Vec<Action>,
+    },
+
     FlushPackets,
 
     /// Wait for an event. See [WaitType] for the events.
@@ -107,6 +339,134 @@ pub enum Action {
     },
 }
 
+impl Action {
+    /// The name of the action, as used in scripts.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::SendFrame { .. } => "send_frame",
+            Action::SendHeadersFrame { .. } => "send_headers",
+            Action::StreamBytes { .. } => "stream_bytes",
+            Action::OpenUniStream { .. } => "open_uni_stream",
+            Action::OpenWebTransportStream { .. } => "open_webtransport_stream",
+            Action::ResetStream { .. } => "reset_stream",
+            Action::StopSending { .. } => "stop_sending",
+            Action::ConnectionClose { .. } => "connection_close",
+            Action::MaxData { .. } => "max_data",
+            Action::MaxStreamData { .. } => "max_stream_data",
+            Action::MaxStreams { .. } => "max_streams",
+            Action::WithholdFlowControlUpdates { .. } =>
+                "withhold_flow_control_updates",
+            Action::Ping => "ping",
+            Action::Padding { .. } => "padding",
+            Action::Burst { .. } => "burst",
+            Action::RawQuicFrame { .. } => "raw_quic_frame",
+            Action::UpdateKeys => "update_keys",
+            Action::Migrate { .. } => "migrate",
+            Action::ProbePath { .. } => "probe_path",
+            Action::SwitchPath { .. } => "switch_path",
+            Action::SendDatagram { .. } => "send_datagram",
+            Action::SendQpackInstruction { .. } => "send_qpack_instruction",
+            Action::OpenConcurrentRequests { .. } => "open_concurrent_requests",
+            Action::AssertBody { .. } => "assert_body",
+            Action::SnapshotStats { .. } => "snapshot_stats",
+            Action::Repeat { .. } => "repeat",
+            Action::OnFrame { .. } => "on_frame",
+            Action::FlushPackets => "flush_packets",
+            Action::Wait { .. } => "wait",
+        }
+    }
+
+    /// The stream the action applies to, if any.
+    pub fn stream_id(&self) -> Option<u64> {
+        match self {
+            Action::SendFrame { stream_id, .. } |
+            Action::SendHeadersFrame { stream_id, .. } |
+            Action::StreamBytes { stream_id, .. } |
+            Action::OpenUniStream { stream_id, .. } |
+            Action::OpenWebTransportStream { stream_id, .. } |
+            Action::ResetStream { stream_id, .. } |
+            Action::StopSending { stream_id, .. } |
+            Action::MaxStreamData { stream_id, .. } |
+            Action::SendQpackInstruction { stream_id, .. } |
+            Action::AssertBody { stream_id, .. } => Some(*stream_id),
+
+            Action::Wait {
+                wait_type: WaitType::StreamEvent(event),
+            } => Some(event.stream_id),
+
+            _ => None,
+        }
+    }
+}
+
+/// Iterates over a list of actions, expanding [Action::Repeat] as it goes.
+#[derive(Clone, Debug)]
+pub(crate) struct ActionIter<'a> {
+    // The action lists being executed, innermost last, with the number of
+    // times they still need to be restarted.
+    stack: Vec<(std::slice::Iter<'a, Action>, &'a [Action], u64)>,
+    yielded: usize,
+}
+
+impl<'a> ActionIter<'a> {
+    pub(crate) fn new(actions: &'a [Action]) -> Self {
+        Self {
+            stack: vec![(actions.iter(), actions, 0)],
+            yielded: 0,
+        }
+    }
+
+    /// Executes `actions` before the remaining ones.
+    pub(crate) fn push(&mut self, actions: &'a [Action]) {
+        if !actions.is_empty() {
+            self.stack.push((actions.iter(), actions, 0));
+        }
+    }
+
+    /// Executes `actions` after the remaining ones.
+    pub(crate) fn append(&mut self, actions: &'a [Action]) {
+        if !actions.is_empty() {
+            self.stack.insert(0, (actions.iter(), actions, 0));
+        }
+    }
+
+    /// The number of actions returned so far.
+    pub(crate) fn yielded(&self) -> usize {
+        self.yielded
+    }
+}
+
+impl<'a> Iterator for ActionIter<'a> {
+    type Item = &'a Action;
+
+    fn next(&mut self) -> Option<&'a Action> {
+        loop {
+            let (iter, actions, remaining) = self.stack.last_mut()?;
+
+            match iter.next() {
+                Some(Action::Repeat { count, actions }) =>
+                    if *count > 0 && !actions.is_empty() {
+                        self.stack.push((actions.iter(), actions, count - 1));
+                    },
+
+                Some(action) => {
+                    self.yielded += 1;
+                    return Some(action);
+                },
+
+                None if *remaining > 0 => {
+                    *remaining -= 1;
+                    *iter = actions.iter();
+                },
+
+                None => {
+                    self.stack.pop();
+                },
+            }
+        }
+    }
+}
+
 /// Configure the wait behavior for a connection.
 #[serde_as]
 #[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -122,6 +482,10 @@ pub enum WaitType {
     /// 1. The peer resets the specified stream.
     /// 2. The peer sends a `fin` over the specified stream
     StreamEvent(StreamEvent),
+    /// Wait for an event concerning the whole connection before firing the
+    /// next action. Events that already occurred don't block.
+    #[serde(rename = "connection_event")]
+    ConnectionEvent(ConnectionEvent),
 }
 
 impl From<WaitType> for Action {
@@ -130,6 +494,26 @@ impl From<WaitType> for Action {
     }
 }
 
+/// How fast [Action::OpenConcurrentRequests] opens requests.
+#[serde_as]
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum RampPolicy {
+    /// Open as many requests as the stream credit allows, as soon as it is
+    /// granted.
+    #[default]
+    Immediate,
+    /// Open at most `step` requests, within the stream credit, every
+    /// `interval`. A `step` of 0 is treated as 1.
+    Step {
+        step: u64,
+        #[serde_as(as = "serde_with::DurationMilliSecondsWithFrac<f64>")]
+        interval: Duration,
+    },
+}
+
 /// A response event, received over a stream, which will terminate the wait
 /// period.
 ///
@@ -153,25 +537,264 @@ pub enum StreamEventType {
     /// The stream was somehow finished, either by a RESET_STREAM frame or via
     /// the `fin` bit being set.
     Finished,
+    /// At least the given number of bytes were received in DATA frame
+    /// payloads, in total.
+    #[serde(rename = "data_bytes")]
+    DataBytes(u64),
+}
+
+/// A connection-level event that can terminate a wait period. See
+/// [WaitType::ConnectionEvent].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionEvent {
+    /// A GOAWAY frame was received.
+    GoAway,
+    /// The peer's SETTINGS frame was received.
+    Settings,
+    /// The connection is closing, e.g. because the peer sent a
+    /// CONNECTION_CLOSE frame.
+    ConnectionClose,
+    /// The handshake was confirmed, i.e. a HANDSHAKE_DONE frame was received.
+    HandshakeConfirmed,
+    /// A path other than the initial one was validated, e.g. after an
+    /// [Action::Migrate] or [Action::ProbePath].
+    PathValidated,
+}
+
+/// An assertion on a response body. See [Action::AssertBody].
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BodyAssertion {
+    /// The body is exactly the given bytes.
+    Bytes(Vec<u8>),
+    /// The body is exactly the given number of bytes long.
+    Length(u64),
+    /// The SHA-256 digest of the body is the given hex string.
+    Sha256(String),
+    /// The body is valid UTF-8 and matches the given regular expression.
+    Regex(String),
+}
+
+impl BodyAssertion {
+    /// Checks `body` against the assertion, returning a description of the
+    /// mismatch on failure.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use h3i::actions::h3::BodyAssertion;
+    ///
+    /// let assertion = BodyAssertion::Regex("^hello".to_string());
+    /// assert!(assertion.check(b"hello world").is_ok());
+    /// assert!(assertion.check(b"goodbye world").is_err());
+    /// ```
+    pub fn check(&self, body: &[u8]) -> Result<(), String> {
+        match self {
+            BodyAssertion::Bytes(expected) => {
+                if body == expected.as_slice() {
+                    return Ok(());
+                }
+
+                let offset = body
+                    .iter()
+                    .zip(expected)
+                    .position(|(a, b)| a != b)
+                    .unwrap_or(cmp::min(body.len(), expected.len()));
+
+                Err(format!(
+                    "body differs from the expected bytes at offset {offset}"
+                ))
+            },
+
+            BodyAssertion::Length(expected) => {
+                if body.len() as u64 == *expected {
+                    return Ok(());
+                }
+
+                Err(format!(
+                    "body is {} bytes long, expected {expected}",
+                    body.len()
+                ))
+            },
+
+            BodyAssertion::Sha256(expected) => {
+                let digest = ring::digest::digest(&ring::digest::SHA256, body);
+                let actual: String =
+                    digest.as_ref().iter().map(|b| format!("{b:02x}")).collect();
+
+                if actual.eq_ignore_ascii_case(expected) {
+                    return Ok(());
+                }
+
+                Err(format!("body SHA-256 is {actual}, expected {expected}"))
+            },
+
+            BodyAssertion::Regex(pattern) => {
+                let re = regex::Regex::new(pattern)
+                    .map_err(|e| format!("invalid regex {pattern}: {e}"))?;
+
+                let text = std::str::from_utf8(body)
+                    .map_err(|e| format!("body isn't valid UTF-8: {e}"))?;
+
+                if re.is_match(text) {
+                    return Ok(());
+                }
+
+                Err(format!("body doesn't match regex {pattern}"))
+            },
+        }
+    }
+}
+
+/// A QPACK encoder or decoder stream instruction. See
+/// [Action::SendQpackInstruction].
+///
+/// See <https://datatracker.ietf.org/doc/html/rfc9204#section-4.3> and
+/// <https://datatracker.ietf.org/doc/html/rfc9204#section-4.4>. Strings are
+/// never Huffman-encoded.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QpackInstruction {
+    /// An encoder stream Set Dynamic Table Capacity instruction.
+    SetDynamicTableCapacity { capacity: u64 },
+    /// An encoder stream Insert with Name Reference instruction, referring to
+    /// either the static or the dynamic table.
+    InsertWithNameReference {
+        is_static: bool,
+        name_index: u64,
+        value: Vec<u8>,
+    },
+    /// An encoder stream Insert with Literal Name instruction.
+    InsertWithLiteralName { name: Vec<u8>, value: Vec<u8> },
+    /// An encoder stream Duplicate instruction.
+    Duplicate { index: u64 },
+    /// A decoder stream Section Acknowledgment instruction.
+    SectionAcknowledgment { stream_id: u64 },
+    /// A decoder stream Stream Cancellation instruction.
+    StreamCancellation { stream_id: u64 },
+    /// A decoder stream Insert Count Increment instruction.
+    InsertCountIncrement { increment: u64 },
+    /// Arbitrary bytes, e.g. a truncated or otherwise invalid instruction.
+    Raw(Vec<u8>),
+}
+
+impl QpackInstruction {
+    /// Encodes the instruction.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use h3i::actions::h3::QpackInstruction;
+    ///
+    /// let instruction = QpackInstruction::SetDynamicTableCapacity { capacity: 220 };
+    /// assert_eq!(instruction.to_bytes(), vec![0x3f, 0xbd, 0x01]);
+    /// ```
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut b = vec![];
+
+        match self {
+            QpackInstruction::SetDynamicTableCapacity { capacity } =>
+                encode_qpack_int(*capacity, 0x20, 5, &mut b),
+
+            QpackInstruction::InsertWithNameReference {
+                is_static,
+                name_index,
+                value,
+            } => {
+                let first = if *is_static { 0xc0 } else { 0x80 };
+
+                encode_qpack_int(*name_index, first, 6, &mut b);
+                encode_qpack_str(value, 0x00, 7, &mut b);
+            },
+
+            QpackInstruction::InsertWithLiteralName { name, value } => {
+                encode_qpack_str(name, 0x40, 5, &mut b);
+                encode_qpack_str(value, 0x00, 7, &mut b);
+            },
+
+            QpackInstruction::Duplicate { index } =>
+                encode_qpack_int(*index, 0x00, 5, &mut b),
+
+            QpackInstruction::SectionAcknowledgment { stream_id } =>
+                encode_qpack_int(*stream_id, 0x80, 7, &mut b),
+
+            QpackInstruction::StreamCancellation { stream_id } =>
+                encode_qpack_int(*stream_id, 0x40, 6, &mut b),
+
+            QpackInstruction::InsertCountIncrement { increment } =>
+                encode_qpack_int(*increment, 0x00, 6, &mut b),
+
+            QpackInstruction::Raw(bytes) => b.extend_from_slice(bytes),
+        }
+
+        b
+    }
+}
+
+/// Encodes a QPACK prefixed integer, whose first byte starts with the bits of
+/// `first` that are outside of the `prefix` bits.
+fn encode_qpack_int(mut v: u64, first: u8, prefix: u8, b: &mut Vec<u8>) {
+    let mask = (1u64 << prefix) - 1;
+
+    if v < mask {
+        b.push(first | v as u8);
+        return;
+    }
+
+    b.push(first | mask as u8);
+    v -= mask;
+
+    while v >= 128 {
+        b.push((v % 128) as u8 | 0x80);
+        v >>= 7;
+    }
+
+    b.push(v as u8);
+}
+
+/// Encodes a QPACK string literal, without Huffman encoding. The length uses
+/// a `prefix` bit prefix, and the Huffman bit sits right before it.
+fn encode_qpack_str(v: &[u8], first: u8, prefix: u8, b: &mut Vec<u8>) {
+    encode_qpack_int(v.len() as u64, first, prefix, b);
+    b.extend_from_slice(v);
+}
+
+/// Encodes an HTTP/3 datagram, i.e. the payload of a QUIC DATAGRAM frame.
+pub(crate) fn encode_h3_datagram(
+    quarter_stream_id: u64, payload: &[u8],
+) -> Vec<u8> {
+    let mut dgram =
+        vec![0; octets::varint_len(quarter_stream_id) + payload.len()];
+    let mut b = octets::OctetsMut::with_slice(&mut dgram);
+
+    // Both always fit, as the buffer was sized for them.
+    b.put_varint(quarter_stream_id).unwrap();
+    b.put_bytes(payload).unwrap();
+
+    dgram
 }
 
 #[derive(Debug, Default)]
-pub(crate) struct WaitingFor(HashMap<u64, Vec<StreamEvent>>);
+pub(crate) struct WaitingFor {
+    streams: HashMap<u64, Vec<StreamEvent>>,
+    connection: Vec<ConnectionEvent>,
+}
 
 impl WaitingFor {
     pub(crate) fn is_empty(&self) -> bool {
-        self.0.values().all(|v| v.is_empty())
+        self.streams.values().all(|v| v.is_empty()) && self.connection.is_empty()
     }
 
     pub(crate) fn add_wait(&mut self, stream_event: &StreamEvent) {
-        self.0
+        self.streams
             .entry(stream_event.stream_id)
             .or_default()
             .push(*stream_event);
     }
 
     pub(crate) fn remove_wait(&mut self, stream_event: StreamEvent) {
-        if let Some(waits) = self.0.get_mut(&stream_event.stream_id) {
+        if let Some(waits) = self.streams.get_mut(&stream_event.stream_id) {
             let old_len = waits.len();
             waits.retain(|wait| wait != &stream_event);
             let new_len = waits.len();
@@ -182,8 +805,49 @@ impl WaitingFor {
         }
     }
 
+    /// Removes the [StreamEventType::DataBytes] waits on `stream_id` that are
+    /// satisfied by `data_bytes` bytes of DATA frame payloads.
+    pub(crate) fn remove_data_bytes_waits(
+        &mut self, stream_id: u64, data_bytes: u64,
+    ) {
+        if let Some(waits) = self.streams.get_mut(&stream_id) {
+            waits.retain(|wait| match wait.event_type {
+                StreamEventType::DataBytes(n) if n <= data_bytes => {
+                    log::info!("No longer waiting for {:?}", wait);
+                    false
+                },
+
+                _ => true,
+            });
+        }
+    }
+
+    pub(crate) fn add_connection_wait(&mut self, event: ConnectionEvent) {
+        self.connection.push(event);
+    }
+
+    /// Removes the connection waits for which `occurred` returns true.
+    ///
+    /// Returns whether any wait was removed.
+    pub(crate) fn remove_connection_waits(
+        &mut self, occurred: impl Fn(&ConnectionEvent) -> bool,
+    ) -> bool {
+        let old_len = self.connection.len();
+
+        self.connection.retain(|event| {
+            if occurred(event) {
+                log::info!("No longer waiting for {:?}", event);
+                return false;
+            }
+
+            true
+        });
+
+        self.connection.len() != old_len
+    }
+
     pub(crate) fn clear_waits_on_stream(&mut self, stream_id: u64) {
-        if let Some(waits) = self.0.get_mut(&stream_id) {
+        if let Some(waits) = self.streams.get_mut(&stream_id) {
             if !waits.is_empty() {
                 log::info!("Clearing all waits for stream {}", stream_id);
                 waits.clear();
@@ -225,3 +889,1240 @@ pub fn send_headers_frame_literal(
         frame: Frame::Headers { header_block },
     }
 }
+
+/// Convenience to build an [Action::SendHeadersFrame] carrying the trailers of
+/// a request, to send after its DATA frames. Trailers are a HEADERS frame
+/// without pseudo-header fields; it usually ends the stream.
+///
+/// ```
+/// use h3i::actions::h3::send_headers_frame;
+/// use h3i::actions::h3::send_trailers_frame;
+/// use h3i::actions::h3::Action;
+/// use quiche::h3::frame::Frame;
+/// use quiche::h3::Header;
+///
+/// let actions = vec![
+///     send_headers_frame(0, false, vec![
+///         Header::new(b":method", b"POST"),
+///         Header::new(b":scheme", b"https"),
+///         Header::new(b":authority", b"example.com"),
+///         Header::new(b":path", b"/upload"),
+///     ]),
+///     Action::SendFrame {
+///         stream_id: 0,
+///         fin_stream: false,
+///         frame: Frame::Data {
+///             payload: b"hello".to_vec(),
+///         },
+///     },
+///     send_trailers_frame(0, true, vec![Header::new(b"checksum", b"abc")]),
+/// ];
+/// ```
+pub fn send_trailers_frame(
+    stream_id: u64, fin_stream: bool, trailers: Vec<Header>,
+) -> Action {
+    send_headers_frame(stream_id, fin_stream, trailers)
+}
+
+/// The `:protocol` pseudo-header value of WebTransport extended CONNECT
+/// requests.
+pub const WEBTRANSPORT_PROTOCOL: &str = "webtransport";
+
+/// The stream type of unidirectional WebTransport streams.
+pub const WEBTRANSPORT_UNI_STREAM_TYPE: u64 = 0x54;
+
+/// The signal value that starts bidirectional WebTransport streams.
+pub const WEBTRANSPORT_BIDI_SIGNAL: u64 = 0x41;
+
+/// Convenience to build an [Action::SendHeadersFrame] carrying an [extended
+/// CONNECT] request for `protocol`.
+///
+/// The stream is left open, as it carries the session once the server accepts
+/// it. Note that servers only accept extended CONNECT requests when they have
+/// advertised SETTINGS_ENABLE_CONNECT_PROTOCOL, and WebTransport servers also
+/// expect the client to advertise its own WebTransport settings.
+///
+/// [extended CONNECT]: https://www.rfc-editor.org/rfc/rfc9220.html
+pub fn send_extended_connect(
+    stream_id: u64, protocol: &str, authority: &str, path: &str,
+) -> Action {
+    send_headers_frame(stream_id, false, vec![
+        Header::new(b":method", b"CONNECT"),
+        Header::new(b":protocol", protocol.as_bytes()),
+        Header::new(b":scheme", b"https"),
+        Header::new(b":authority", authority.as_bytes()),
+        Header::new(b":path", path.as_bytes()),
+    ])
+}
+
+/// The payload of GREASE frames sent by [`send_grease_frame`].
+pub const GREASE_FRAME_PAYLOAD: &[u8] = b"GREASE is the word";
+
+/// Returns whether `v` is a reserved identifier of the form `0x1f * N + 0x21`.
+///
+/// HTTP/3 reserves these values for frame types, settings identifiers, stream
+/// types and error codes so that endpoints exercise the requirement to ignore
+/// unknown values. See
+/// <https://datatracker.ietf.org/doc/html/rfc9114#section-7.2.8>.
+pub fn is_grease_value(v: u64) -> bool {
+    v >= 0x21 && (v - 0x21) % 0x1f == 0
+}
+
+/// Convenience to build an [Action::SendFrame] carrying a frame with a random
+/// reserved frame type.
+///
+/// Peers are required to ignore it, on any stream.
+pub fn send_grease_frame(stream_id: u64, fin_stream: bool) -> Action {
+    send_grease_frame_with_payload(
+        stream_id,
+        fin_stream,
+        GREASE_FRAME_PAYLOAD.to_vec(),
+    )
+}
+
+/// Like [`send_grease_frame`], but with the given `payload`.
+pub fn send_grease_frame_with_payload(
+    stream_id: u64, fin_stream: bool, payload: Vec<u8>,
+) -> Action {
+    Action::SendFrame {
+        stream_id,
+        fin_stream,
+        frame: Frame::Unknown {
+            raw_type: quiche::h3::grease_value(),
+            payload,
+        },
+    }
+}
+
+/// Convenience to build an [Action::SendFrame] carrying a SETTINGS frame with
+/// the given `settings`, followed by a random reserved setting identifier.
+///
+/// Peers are required to ignore the reserved setting.
+pub fn send_settings_frame_with_grease(
+    stream_id: u64, fin_stream: bool, settings: Vec<(u64, u64)>,
+) -> Action {
+    send_settings_frame_with_grease_value(
+        stream_id,
+        fin_stream,
+        settings,
+        quiche::h3::grease_value(),
+    )
+}
+
+/// Like [`send_settings_frame_with_grease`], but the reserved setting has the
+/// given `value`.
+pub fn send_settings_frame_with_grease_value(
+    stream_id: u64, fin_stream: bool, settings: Vec<(u64, u64)>, value: u64,
+) -> Action {
+    Action::SendFrame {
+        stream_id,
+        fin_stream,
+        frame: Frame::Settings {
+            max_field_section_size: None,
+            qpack_max_table_capacity: None,
+            qpack_blocked_streams: None,
+            connect_protocol_enabled: None,
+            h3_datagram: None,
+            grease: Some((quiche::h3::grease_value(), value)),
+            raw: None,
+            additional_settings: Some(settings),
+        },
+    }
+}
+
+/// Convenience to build an [Action::OpenUniStream] with a random reserved
+/// stream type.
+///
+/// Peers are required to either ignore or abort reading such streams, but not
+/// to treat them as a connection error. A payload can be sent on the stream
+/// afterwards with [Action::StreamBytes].
+pub fn open_grease_uni_stream(stream_id: u64, fin_stream: bool) -> Action {
+    Action::OpenUniStream {
+        stream_id,
+        fin_stream,
+        stream_type: quiche::h3::grease_value(),
+    }
+}
+
+/// Convenience to build an [Action::SendFrame] carrying a PRIORITY_UPDATE
+/// frame for a request stream, to be sent on the control stream `stream_id`.
+///
+/// `priority_field_value` is sent as is, so it doesn't need to be a valid
+/// [Priority Parameter] Structured Field. The effect of the update on the
+/// response can be checked with [`StreamMap::priority_updates`].
+///
+/// [Priority Parameter]: https://datatracker.ietf.org/doc/html/rfc9218#section-4
+/// [`StreamMap::priority_updates`]: crate::client::connection_summary::StreamMap::priority_updates
+pub fn send_priority_update_request(
+    stream_id: u64, prioritized_element_id: u64, priority_field_value: &[u8],
+) -> Action {
+    Action::SendFrame {
+        stream_id,
+        fin_stream: false,
+        frame: Frame::PriorityUpdateRequest {
+            prioritized_element_id,
+            priority_field_value: priority_field_value.to_vec(),
+        },
+    }
+}
+
+/// Like [`send_priority_update_request`], but for the push stream with the
+/// push ID `prioritized_element_id`.
+pub fn send_priority_update_push(
+    stream_id: u64, prioritized_element_id: u64, priority_field_value: &[u8],
+) -> Action {
+    Action::SendFrame {
+        stream_id,
+        fin_stream: false,
+        frame: Frame::PriorityUpdatePush {
+            prioritized_element_id,
+            priority_field_value: priority_field_value.to_vec(),
+        },
+    }
+}
+
+/// Convenience to build an [Action::SendFrame] carrying a MAX_PUSH_ID frame,
+/// to be sent on the control stream `stream_id`.
+///
+/// This allows the peer to push responses with push IDs up to `push_id`,
+/// which are recorded in the [`StreamMap`] as they are received, see
+/// [`StreamMap::push_promises`].
+///
+/// [`StreamMap`]: crate::client::connection_summary::StreamMap
+/// [`StreamMap::push_promises`]: crate::client::connection_summary::StreamMap::push_promises
+pub fn send_max_push_id_frame(stream_id: u64, push_id: u64) -> Action {
+    Action::SendFrame {
+        stream_id,
+        fin_stream: false,
+        frame: Frame::MaxPushId { push_id },
+    }
+}
+
+/// Convenience to build an [Action::SendFrame] carrying a CANCEL_PUSH frame
+/// for the push ID `push_id`, to be sent on the control stream `stream_id`.
+pub fn send_cancel_push_frame(stream_id: u64, push_id: u64) -> Action {
+    Action::SendFrame {
+        stream_id,
+        fin_stream: false,
+        frame: Frame::CancelPush { push_id },
+    }
+}
+
+/// Convenience to build an [Action::SendFrame] carrying a METADATA frame with
+/// the given `fields`.
+///
+/// The frame is sent as is, so it can be sent on any stream and regardless of
+/// whether the peer enabled the METADATA extension. The extension is
+/// negotiated with the `SETTINGS_ENABLE_METADATA` setting, which can be sent
+/// with [`send_settings_frame_with_grease`].
+pub fn send_metadata_frame(
+    stream_id: u64, fin_stream: bool, fields: Vec<Header>,
+) -> Action {
+    let header_block = encode_header_block(&fields).unwrap();
+
+    Action::SendFrame {
+        stream_id,
+        fin_stream,
+        frame: Frame::Metadata { header_block },
+    }
+}
+
+/// The name of the field [`send_oversized_headers_frame`] pads the field
+/// section with.
+pub const OVERSIZED_HEADERS_PADDING_FIELD: &[u8] = b"x-h3i-padding";
+
+/// Like [`send_headers_frame`], but a padding field is added to `headers` so
+/// that the size of the field section exceeds `max_field_section_size`.
+///
+/// The size is computed as described in [RFC 9114], i.e. the length of each
+/// name and value plus 32 bytes per field. The peer's limit is the
+/// `SETTINGS_MAX_FIELD_SECTION_SIZE` setting it sent, if any.
+///
+/// [RFC 9114]: https://datatracker.ietf.org/doc/html/rfc9114#section-4.2.2
+pub fn send_oversized_headers_frame(
+    stream_id: u64, fin_stream: bool, mut headers: Vec<Header>,
+    max_field_section_size: u64,
+) -> Action {
+    let field_size = |name: &[u8], value: &[u8]| name.len() + value.len() + 32;
+
+    let size: usize = headers
+        .iter()
+        .map(|h| field_size(h.name(), h.value()))
+        .sum::<usize>() +
+        field_size(OVERSIZED_HEADERS_PADDING_FIELD, b"");
+
+    let padding_len = (max_field_section_size as usize + 1).saturating_sub(size);
+
+    headers.push(Header::new(
+        OVERSIZED_HEADERS_PADDING_FIELD,
+        &vec![b'a'; padding_len],
+    ));
+
+    send_headers_frame(stream_id, fin_stream, headers)
+}
+
+/// Convenience to build an [Action::StreamBytes] carrying a frame of type
+/// `frame_type`, whose Length field is `declared_len` regardless of the
+/// actual length of `payload`.
+///
+/// A `declared_len` shorter than the payload makes the peer parse the rest of
+/// the payload as the next frame, and a longer one makes it wait for more
+/// bytes, or treat the frame as truncated if the stream ends.
+pub fn send_frame_with_length_mismatch(
+    stream_id: u64, fin_stream: bool, frame_type: u64, declared_len: u64,
+    payload: &[u8],
+) -> Action {
+    let mut bytes = vec![
+        0;
+        octets::varint_len(frame_type) +
+            octets::varint_len(declared_len) +
+            payload.len()
+    ];
+    let mut b = octets::OctetsMut::with_slice(&mut bytes);
+
+    // All always fit, as the buffer was sized for them.
+    b.put_varint(frame_type).unwrap();
+    b.put_varint(declared_len).unwrap();
+    b.put_bytes(payload).unwrap();
+
+    Action::StreamBytes {
+        stream_id,
+        fin_stream,
+        bytes,
+    }
+}
+
+/// Convenience to build an [Action::RawQuicFrame] carrying a STREAM frame
+/// with a DATA frame of `len` bytes, at offset `max_stream_data` of stream
+/// `stream_id`.
+///
+/// `max_stream_data` is meant to be the flow control limit of the stream, as
+/// advertised by the peer, so that the data exceeds it. The peer should then
+/// close the connection with a FLOW_CONTROL_ERROR. The STREAM frame is
+/// written as is, bypassing quiche's flow control and stream state, so the
+/// stream should not be written to otherwise. The frame must fit in a single
+/// packet, so `len` is limited to about a thousand bytes.
+pub fn send_data_exceeding_flow_control(
+    stream_id: u64, max_stream_data: u64, len: usize,
+) -> Action {
+    let mut data = vec![
+        0;
+        octets::varint_len(DATA_FRAME_TYPE_ID) +
+            octets::varint_len(len as u64) +
+            len
+    ];
+    let mut b = octets::OctetsMut::with_slice(&mut data);
+
+    // All always fit, as the buffer was sized for them.
+    b.put_varint(DATA_FRAME_TYPE_ID).unwrap();
+    b.put_varint(len as u64).unwrap();
+
+    // A STREAM frame with the OFF and LEN bits set.
+    let frame_type = 0x08 | 0x04 | 0x02;
+
+    let mut bytes = vec![
+        0;
+        1 + octets::varint_len(stream_id) +
+            octets::varint_len(max_stream_data) +
+            octets::varint_len(data.len() as u64) +
+            data.len()
+    ];
+    let mut b = octets::OctetsMut::with_slice(&mut bytes);
+
+    b.put_u8(frame_type).unwrap();
+    b.put_varint(stream_id).unwrap();
+    b.put_varint(max_stream_data).unwrap();
+    b.put_varint(data.len() as u64).unwrap();
+    b.put_bytes(&data).unwrap();
+
+    Action::RawQuicFrame { bytes }
+}
+
+/// Convenience to build actions that starve the peer of flow control credit
+/// on the stream `stream_id`.
+///
+/// The actions withhold the flow control updates h3i would otherwise send,
+/// then grant `increment` more bytes of credit, to both the stream and the
+/// connection, every `interval`, `count` times. `max_stream_data` and
+/// `max_data` are meant to be the limits advertised so far, e.g. the initial
+/// ones from the [`Config`], which the increments are added to. With a
+/// `count` of 0, flow control is simply frozen.
+///
+/// Updates stay withheld after the last increment, until an
+/// [Action::WithholdFlowControlUpdates] resumes them.
+///
+/// [`Config`]: crate::config::Config
+pub fn send_flow_control_increments(
+    stream_id: u64, max_stream_data: u64, max_data: u64, increment: u64,
+    interval: Duration, count: u64,
+) -> Vec<Action> {
+    let mut actions = vec![Action::WithholdFlowControlUpdates { withhold: true }];
+
+    for i in 1..=count {
+        actions.push(Action::Wait {
+            wait_type: WaitType::WaitDuration(interval),
+        });
+        actions.push(Action::MaxData {
+            max: max_data.saturating_add(increment.saturating_mul(i)),
+        });
+        actions.push(Action::MaxStreamData {
+            stream_id,
+            max: max_stream_data.saturating_add(increment.saturating_mul(i)),
+        });
+    }
+
+    actions
+}
+
+/// Convenience to steer `actions` onto `path`, a path opened with
+/// [Action::ProbePath], then carry on on the initial path.
+///
+/// ```
+/// use h3i::actions::h3::on_path;
+/// use h3i::actions::h3::send_headers_frame;
+/// use h3i::actions::h3::Action;
+/// use h3i::actions::h3::ConnectionEvent;
+/// use h3i::actions::h3::WaitType;
+/// use quiche::h3::Header;
+///
+/// let headers = vec![Header::new(b":method", b"GET")];
+///
+/// // Probe a second path, then send the request on stream 4 over it.
+/// let mut actions =
+///     vec![Action::ProbePath { local_addr: None }, Action::Wait {
+///         wait_type: WaitType::ConnectionEvent(ConnectionEvent::PathValidated),
+///     }];
+/// actions.extend(on_path(1, vec![send_headers_frame(4, true, headers)]));
+/// ```
+pub fn on_path(path: usize, actions: Vec<Action>) -> Vec<Action> {
+    let mut steered = vec![Action::SwitchPath { path }];
+    steered.extend(actions);
+    steered.push(Action::SwitchPath { path: 0 });
+
+    steered
+}
+
+/// Convenience to split the frame sent by `action` across several QUIC
+/// packets, to test how the peer handles frames straddling packet
+/// boundaries.
+///
+/// The serialized frame is cut at each of the byte `offsets`, and each piece
+/// is sent as an [Action::StreamBytes], in its own STREAM frame and packet
+/// thanks to an [Action::FlushPackets] between pieces. The FIN, if any, is
+/// sent with the last piece. Offsets past the end of the frame are ignored.
+///
+/// `action` is returned as is if it doesn't send a frame, i.e. isn't an
+/// [Action::SendFrame], [Action::SendHeadersFrame] or [Action::StreamBytes].
+///
+/// ```
+/// use h3i::actions::h3::send_headers_frame;
+/// use h3i::actions::h3::split_frame;
+/// use quiche::h3::Header;
+///
+/// let headers = vec![Header::new(b":method", b"GET")];
+///
+/// // Send the frame type alone, then the length, then the header block.
+/// let actions = split_frame(&send_headers_frame(0, true, headers), &[1, 2]);
+/// ```
+pub fn split_frame(action: &Action, offsets: &[usize]) -> Vec<Action> {
+    let Some((stream_id, fin_stream, bytes)) = stream_bytes(action) else {
+        return vec![action.clone()];
+    };
+
+    let mut offsets: Vec<usize> = offsets
+        .iter()
+        .copied()
+        .filter(|off| *off > 0 && *off < bytes.len())
+        .collect();
+    offsets.sort_unstable();
+    offsets.dedup();
+    offsets.push(bytes.len());
+
+    let mut actions = vec![];
+    let mut start = 0;
+
+    for end in offsets {
+        if start > 0 {
+            actions.push(Action::FlushPackets);
+        }
+
+        actions.push(Action::StreamBytes {
+            stream_id,
+            fin_stream: fin_stream && end == bytes.len(),
+            bytes: bytes[start..end].to_vec(),
+        });
+
+        start = end;
+    }
+
+    actions
+}
+
+/// Returns the stream, FIN flag and bytes sent by `action`, if it sends a
+/// frame or raw stream bytes.
+pub(crate) fn stream_bytes(action: &Action) -> Option<(u64, bool, Vec<u8>)> {
+    let (stream_id, fin_stream, frame) = match action {
+        Action::SendFrame {
+            stream_id,
+            fin_stream,
+            frame,
+        } |
+        Action::SendHeadersFrame {
+            stream_id,
+            fin_stream,
+            frame,
+            ..
+        } => (*stream_id, *fin_stream, frame),
+
+        Action::StreamBytes {
+            stream_id,
+            fin_stream,
+            bytes,
+        } => return Some((*stream_id, *fin_stream, bytes.clone())),
+
+        _ => return None,
+    };
+
+    let mut d = vec![0; 65535];
+    let mut b = octets::OctetsMut::with_slice(&mut d);
+    let len = frame.to_bytes(&mut b).ok()?;
+    d.truncate(len);
+
+    Some((stream_id, fin_stream, d))
+}
+
+/// A misuse of the HTTP/3 control stream, which the peer must treat as a
+/// connection error.
+///
+/// ```
+/// use h3i::actions::h3::ControlStreamMisuse;
+///
+/// // Open the control stream 2, then send a second SETTINGS frame on it. The
+/// // expectation is met if the server closes the connection with
+/// // H3_FRAME_UNEXPECTED.
+/// let misuse = ControlStreamMisuse::SecondSettings;
+/// let actions = misuse.actions(2);
+/// let expectation = misuse.expectation();
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ControlStreamMisuse {
+    /// Open a second control stream, with the next client-initiated
+    /// unidirectional stream ID.
+    DuplicateControlStream,
+    /// Close the control stream with a FIN.
+    CloseControlStream,
+    /// Send a second SETTINGS frame on the control stream.
+    SecondSettings,
+    /// Send a request's HEADERS frame on the control stream.
+    RequestFrame,
+}
+
+impl ControlStreamMisuse {
+    /// Builds the actions that open the control stream `stream_id` with an
+    /// empty SETTINGS frame, then misuse it.
+    pub fn actions(self, stream_id: u64) -> Vec<Action> {
+        let settings = |stream_id| Action::SendFrame {
+            stream_id,
+            fin_stream: false,
+            frame: Frame::Settings {
+                max_field_section_size: None,
+                qpack_max_table_capacity: None,
+                qpack_blocked_streams: None,
+                connect_protocol_enabled: None,
+                h3_datagram: None,
+                grease: None,
+                raw: None,
+                additional_settings: None,
+            },
+        };
+
+        let open = |stream_id| Action::OpenUniStream {
+            stream_id,
+            fin_stream: false,
+            stream_type: crate::HTTP3_CONTROL_STREAM_TYPE_ID,
+        };
+
+        let mut actions = vec![open(stream_id), settings(stream_id)];
+
+        match self {
+            ControlStreamMisuse::DuplicateControlStream => {
+                actions.push(open(stream_id + 4));
+                actions.push(settings(stream_id + 4));
+            },
+
+            ControlStreamMisuse::CloseControlStream =>
+                actions.push(Action::StreamBytes {
+                    stream_id,
+                    fin_stream: true,
+                    bytes: vec![],
+                }),
+
+            ControlStreamMisuse::SecondSettings =>
+                actions.push(settings(stream_id)),
+
+            ControlStreamMisuse::RequestFrame =>
+                actions.push(send_headers_frame(stream_id, false, vec![
+                    Header::new(b":method", b"GET"),
+                    Header::new(b":scheme", b"https"),
+                    Header::new(b":path", b"/"),
+                ])),
+        }
+
+        actions
+    }
+
+    /// The error the peer must close the connection with.
+    pub fn expected_error(self) -> quiche::h3::WireErrorCode {
+        match self {
+            ControlStreamMisuse::DuplicateControlStream =>
+                quiche::h3::WireErrorCode::StreamCreationError,
+            ControlStreamMisuse::CloseControlStream =>
+                quiche::h3::WireErrorCode::ClosedCriticalStream,
+            ControlStreamMisuse::SecondSettings |
+            ControlStreamMisuse::RequestFrame =>
+                quiche::h3::WireErrorCode::FrameUnexpected,
+        }
+    }
+
+    /// An [Expectation] met if the peer closed the connection with the
+    /// [expected error](Self::expected_error).
+    pub fn expectation(self) -> Expectation {
+        Expectation::CloseError {
+            is_app: true,
+            error_code: self.expected_error() as u64,
+        }
+    }
+}
+
+/// A builder for [Action::SendHeadersFrame]s with precise control over the
+/// field section.
+///
+/// Unlike [`send_headers_frame`], the builder encodes fields literally by
+/// default: names keep their exact bytes (including upper-case characters),
+/// duplicate fields are sent as many times as they are added, and fields are
+/// sent in insertion order, so pseudo-headers can be reordered, repeated or
+/// interleaved with regular fields. This makes it possible to probe the field
+/// validation of servers, which should reject most of these requests as
+/// [malformed].
+///
+/// ```
+/// use h3i::actions::h3::HeadersFrameBuilder;
+///
+/// // Send `:path` first, an upper-case `:Method` and a duplicated
+/// // `:authority`.
+/// let action = HeadersFrameBuilder::new(0)
+///     .with_pseudo_header(b"path", b"/")
+///     .with_pseudo_header(b"Method", b"GET")
+///     .with_pseudo_header(b"scheme", b"https")
+///     .with_pseudo_header(b"authority", b"example.com")
+///     .with_pseudo_header(b"authority", b"example.org")
+///     .with_header(b"User-Agent", b"h3i")
+///     .with_fin_stream(true)
+///     .build();
+/// ```
+///
+/// [malformed]: https://datatracker.ietf.org/doc/html/rfc9114#section-4.1.2
+#[derive(Clone, Debug)]
+pub struct HeadersFrameBuilder {
+    stream_id: u64,
+    fin_stream: bool,
+    literal_headers: bool,
+    pseudo_headers_last: bool,
+    headers: Vec<Header>,
+}
+
+impl HeadersFrameBuilder {
+    /// Creates a builder for a HEADERS frame sent on `stream_id`.
+    pub fn new(stream_id: u64) -> Self {
+        Self {
+            stream_id,
+            fin_stream: false,
+            literal_headers: true,
+            pseudo_headers_last: false,
+            headers: vec![],
+        }
+    }
+
+    /// Appends a field with the exact `name` and `value` bytes.
+    pub fn with_header(mut self, name: &[u8], value: &[u8]) -> Self {
+        self.headers.push(Header::new(name, value));
+        self
+    }
+
+    /// Appends a pseudo-header field. The leading `:` is added to `name` if
+    /// it is missing, the rest of the name is kept as is.
+    pub fn with_pseudo_header(self, name: &[u8], value: &[u8]) -> Self {
+        if name.starts_with(b":") {
+            return self.with_header(name, value);
+        }
+
+        let name = [b":", name].concat();
+        self.with_header(&name, value)
+    }
+
+    /// Appends all the fields in `headers`, in order.
+    pub fn with_headers(mut self, headers: &[Header]) -> Self {
+        self.headers.extend_from_slice(headers);
+        self
+    }
+
+    /// Whether to set the `fin` bit on the stream after the frame.
+    pub fn with_fin_stream(mut self, fin_stream: bool) -> Self {
+        self.fin_stream = fin_stream;
+        self
+    }
+
+    /// Whether to encode the fields literally. When `false`, the fields are
+    /// encoded like [`send_headers_frame`] does, which lower-cases names.
+    pub fn with_literal_headers(mut self, literal_headers: bool) -> Self {
+        self.literal_headers = literal_headers;
+        self
+    }
+
+    /// Whether to move all pseudo-headers after the regular fields. The
+    /// relative order within each group is preserved.
+    pub fn with_pseudo_headers_last(mut self, pseudo_headers_last: bool) -> Self {
+        self.pseudo_headers_last = pseudo_headers_last;
+        self
+    }
+
+    /// Builds the [Action::SendHeadersFrame].
+    pub fn build(self) -> Action {
+        let mut headers = self.headers;
+
+        if self.pseudo_headers_last {
+            // `sort_by_key` is stable, so this only moves pseudo-headers
+            // behind the regular fields.
+            headers.sort_by_key(|h| h.name().starts_with(b":"));
+        }
+
+        if self.literal_headers {
+            send_headers_frame_literal(self.stream_id, self.fin_stream, headers)
+        } else {
+            send_headers_frame(self.stream_id, self.fin_stream, headers)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn built_headers(action: &Action) -> (&[Header], bool) {
+        match action {
+            Action::SendHeadersFrame {
+                headers,
+                literal_headers,
+                ..
+            } => (headers, *literal_headers),
+
+            _ => panic!("unexpected action {action:?}"),
+        }
+    }
+
+    #[test]
+    fn empty_headers_frame() {
+        for action in [
+            send_headers_frame(0, true, vec![]),
+            send_headers_frame_literal(0, true, vec![]),
+        ] {
+            let Action::SendHeadersFrame { frame, .. } = action else {
+                panic!("unexpected action {action:?}");
+            };
+
+            assert_eq!(frame, Frame::Headers {
+                header_block: vec![0, 0]
+            });
+        }
+    }
+
+    #[test]
+    fn qpack_instructions() {
+        let encoded = |instruction: QpackInstruction| instruction.to_bytes();
+
+        assert_eq!(
+            encoded(QpackInstruction::SetDynamicTableCapacity { capacity: 30 }),
+            vec![0x3e]
+        );
+        assert_eq!(
+            encoded(QpackInstruction::InsertWithNameReference {
+                is_static: true,
+                name_index: 0,
+                value: b"a".to_vec(),
+            }),
+            vec![0xc0, 0x01, b'a']
+        );
+        assert_eq!(
+            encoded(QpackInstruction::InsertWithNameReference {
+                is_static: false,
+                name_index: 70,
+                value: vec![],
+            }),
+            vec![0xbf, 0x07, 0x00]
+        );
+        assert_eq!(
+            encoded(QpackInstruction::InsertWithLiteralName {
+                name: b"n".to_vec(),
+                value: b"v".to_vec(),
+            }),
+            vec![0x41, b'n', 0x01, b'v']
+        );
+        assert_eq!(encoded(QpackInstruction::Duplicate { index: 2 }), vec![
+            0x02
+        ]);
+        assert_eq!(
+            encoded(QpackInstruction::SectionAcknowledgment { stream_id: 4 }),
+            vec![0x84]
+        );
+        assert_eq!(
+            encoded(QpackInstruction::StreamCancellation { stream_id: 100 }),
+            vec![0x7f, 0x25]
+        );
+        assert_eq!(
+            encoded(QpackInstruction::InsertCountIncrement { increment: 1 }),
+            vec![0x01]
+        );
+        assert_eq!(encoded(QpackInstruction::Raw(vec![0xff])), vec![0xff]);
+    }
+
+    #[test]
+    fn body_assertions() {
+        let body = b"hello world";
+
+        assert!(BodyAssertion::Bytes(body.to_vec()).check(body).is_ok());
+        assert_eq!(
+            BodyAssertion::Bytes(b"hello there".to_vec()).check(body),
+            Err("body differs from the expected bytes at offset 6".to_string())
+        );
+        assert!(BodyAssertion::Bytes(b"hello".to_vec()).check(body).is_err());
+
+        assert!(BodyAssertion::Length(11).check(body).is_ok());
+        assert!(BodyAssertion::Length(10).check(body).is_err());
+
+        let sha256 =
+            "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9";
+        assert!(BodyAssertion::Sha256(sha256.to_string())
+            .check(body)
+            .is_ok());
+        assert!(BodyAssertion::Sha256(sha256.to_uppercase())
+            .check(body)
+            .is_ok());
+        assert!(BodyAssertion::Sha256(sha256.to_string())
+            .check(b"hello")
+            .is_err());
+
+        assert!(BodyAssertion::Regex("^hello \\w+$".to_string())
+            .check(body)
+            .is_ok());
+        assert!(BodyAssertion::Regex("^world".to_string())
+            .check(body)
+            .is_err());
+        assert!(BodyAssertion::Regex("(".to_string()).check(body).is_err());
+        assert!(BodyAssertion::Regex("".to_string())
+            .check(&[0xff, 0xfe])
+            .is_err());
+    }
+
+    #[test]
+    fn data_bytes_waits() {
+        let mut waiting_for = WaitingFor::default();
+
+        let wait = |bytes| StreamEvent {
+            stream_id: 0,
+            event_type: StreamEventType::DataBytes(bytes),
+        };
+
+        waiting_for.add_wait(&wait(10));
+        waiting_for.add_wait(&wait(20));
+
+        waiting_for.remove_data_bytes_waits(4, 100);
+        waiting_for.remove_data_bytes_waits(0, 5);
+        assert_eq!(waiting_for.streams[&0], vec![wait(10), wait(20)]);
+
+        waiting_for.remove_data_bytes_waits(0, 15);
+        assert_eq!(waiting_for.streams[&0], vec![wait(20)]);
+
+        waiting_for.remove_data_bytes_waits(0, 20);
+        assert!(waiting_for.is_empty());
+    }
+
+    #[test]
+    fn connection_waits() {
+        let mut waiting_for = WaitingFor::default();
+
+        waiting_for.add_connection_wait(ConnectionEvent::Settings);
+        waiting_for.add_connection_wait(ConnectionEvent::GoAway);
+        assert!(!waiting_for.is_empty());
+
+        assert!(!waiting_for
+            .remove_connection_waits(|e| e == &ConnectionEvent::ConnectionClose));
+        assert!(waiting_for
+            .remove_connection_waits(|e| e == &ConnectionEvent::Settings));
+        assert!(!waiting_for.is_empty());
+
+        assert!(waiting_for.remove_connection_waits(|_| true));
+        assert!(waiting_for.is_empty());
+    }
+
+    #[test]
+    fn grease_values() {
+        assert!(is_grease_value(0x21));
+        assert!(is_grease_value(0x21 + 0x1f * 3));
+        assert!(!is_grease_value(0x0));
+        assert!(!is_grease_value(0x20));
+        assert!(!is_grease_value(0x21 + 1));
+
+        for _ in 0..10 {
+            assert!(is_grease_value(quiche::h3::grease_value()));
+        }
+    }
+
+    #[test]
+    fn grease_actions() {
+        match send_grease_frame(2, false) {
+            Action::SendFrame {
+                frame: Frame::Unknown { raw_type, .. },
+                ..
+            } => assert!(is_grease_value(raw_type)),
+
+            a => panic!("unexpected action {a:?}"),
+        }
+
+        match send_settings_frame_with_grease(2, false, vec![(0x6, 100)]) {
+            Action::SendFrame {
+                frame:
+                    Frame::Settings {
+                        grease: Some((id, _)),
+                        additional_settings,
+                        ..
+                    },
+                ..
+            } => {
+                assert!(is_grease_value(id));
+                assert_eq!(additional_settings, Some(vec![(0x6, 100)]));
+            },
+
+            a => panic!("unexpected action {a:?}"),
+        }
+
+        match send_grease_frame_with_payload(0, true, b"hi".to_vec()) {
+            Action::SendFrame {
+                fin_stream: true,
+                frame: Frame::Unknown { raw_type, payload },
+                ..
+            } => {
+                assert!(is_grease_value(raw_type));
+                assert_eq!(payload, b"hi");
+            },
+
+            a => panic!("unexpected action {a:?}"),
+        }
+
+        match send_settings_frame_with_grease_value(2, false, vec![], 7) {
+            Action::SendFrame {
+                frame:
+                    Frame::Settings {
+                        grease: Some((id, 7)),
+                        ..
+                    },
+                ..
+            } => assert!(is_grease_value(id)),
+
+            a => panic!("unexpected action {a:?}"),
+        }
+
+        match open_grease_uni_stream(6, false) {
+            Action::OpenUniStream { stream_type, .. } =>
+                assert!(is_grease_value(stream_type)),
+
+            a => panic!("unexpected action {a:?}"),
+        }
+    }
+
+    #[test]
+    fn extended_connect() {
+        let action = send_extended_connect(0, "webtransport", "test.com", "/wt");
+
+        let (headers, literal) = built_headers(&action);
+        assert_eq!(headers, &[
+            Header::new(b":method", b"CONNECT"),
+            Header::new(b":protocol", b"webtransport"),
+            Header::new(b":scheme", b"https"),
+            Header::new(b":authority", b"test.com"),
+            Header::new(b":path", b"/wt"),
+        ]);
+        assert!(!literal);
+
+        assert!(matches!(action, Action::SendHeadersFrame {
+            fin_stream: false,
+            ..
+        }));
+    }
+
+    #[test]
+    fn oversized_headers() {
+        let action = send_oversized_headers_frame(
+            0,
+            true,
+            vec![Header::new(b":method", b"GET")],
+            1000,
+        );
+
+        let (headers, _) = built_headers(&action);
+        let size: usize = headers
+            .iter()
+            .map(|h| h.name().len() + h.value().len() + 32)
+            .sum();
+        assert_eq!(size, 1001);
+        assert_eq!(headers[1].name(), OVERSIZED_HEADERS_PADDING_FIELD);
+    }
+
+    #[test]
+    fn frame_length_mismatch() {
+        let action = send_frame_with_length_mismatch(0, false, 0x0, 10, b"hi");
+
+        assert_eq!(action, Action::StreamBytes {
+            stream_id: 0,
+            fin_stream: false,
+            bytes: vec![0x00, 0x0a, b'h', b'i'],
+        });
+    }
+
+    #[test]
+    fn data_exceeding_flow_control() {
+        let action = send_data_exceeding_flow_control(4, 100, 3);
+
+        assert_eq!(action, Action::RawQuicFrame {
+            bytes: vec![
+                0x0e, 0x04, 0x40, 0x64, 0x05, 0x00, 0x03, 0x00, 0x00, 0x00
+            ],
+        });
+    }
+
+    #[test]
+    fn flow_control_increments() {
+        let interval = Duration::from_millis(100);
+        let actions =
+            send_flow_control_increments(0, 1000, 5000, 10, interval, 2);
+
+        assert_eq!(actions, vec![
+            Action::WithholdFlowControlUpdates { withhold: true },
+            Action::Wait {
+                wait_type: WaitType::WaitDuration(interval),
+            },
+            Action::MaxData { max: 5010 },
+            Action::MaxStreamData {
+                stream_id: 0,
+                max: 1010,
+            },
+            Action::Wait {
+                wait_type: WaitType::WaitDuration(interval),
+            },
+            Action::MaxData { max: 5020 },
+            Action::MaxStreamData {
+                stream_id: 0,
+                max: 1020,
+            },
+        ]);
+
+        assert_eq!(
+            send_flow_control_increments(0, 1000, 5000, 10, interval, 0),
+            vec![Action::WithholdFlowControlUpdates { withhold: true }]
+        );
+    }
+
+    #[test]
+    fn steer_on_path() {
+        assert_eq!(on_path(2, vec![Action::Ping]), vec![
+            Action::SwitchPath { path: 2 },
+            Action::Ping,
+            Action::SwitchPath { path: 0 },
+        ]);
+    }
+
+    #[test]
+    fn split_frame_at_offsets() {
+        let action = Action::SendFrame {
+            stream_id: 0,
+            fin_stream: true,
+            frame: Frame::Data {
+                payload: vec![1, 2, 3],
+            },
+        };
+
+        let piece = |fin_stream, bytes: &[u8]| Action::StreamBytes {
+            stream_id: 0,
+            fin_stream,
+            bytes: bytes.to_vec(),
+        };
+
+        assert_eq!(split_frame(&action, &[4, 1, 9, 1]), vec![
+            piece(false, &[0x0]),
+            Action::FlushPackets,
+            piece(false, &[0x3, 1, 2]),
+            Action::FlushPackets,
+            piece(true, &[3]),
+        ]);
+
+        assert_eq!(split_frame(&action, &[]), vec![piece(true, &[
+            0x0, 0x3, 1, 2, 3
+        ])]);
+        assert_eq!(split_frame(&Action::Ping, &[1]), vec![Action::Ping]);
+    }
+
+    #[test]
+    fn control_stream_misuse() {
+        let actions = ControlStreamMisuse::DuplicateControlStream.actions(2);
+        assert_eq!(actions.len(), 4);
+        assert_eq!(actions[2], Action::OpenUniStream {
+            stream_id: 6,
+            fin_stream: false,
+            stream_type: 0x0,
+        });
+
+        let actions = ControlStreamMisuse::CloseControlStream.actions(2);
+        assert_eq!(
+            actions.last(),
+            Some(&Action::StreamBytes {
+                stream_id: 2,
+                fin_stream: true,
+                bytes: vec![],
+            })
+        );
+
+        let actions = ControlStreamMisuse::SecondSettings.actions(2);
+        assert_eq!(actions[1], actions[2]);
+
+        let actions = ControlStreamMisuse::RequestFrame.actions(2);
+        assert!(matches!(actions[2], Action::SendHeadersFrame {
+            stream_id: 2,
+            ..
+        }));
+
+        assert_eq!(
+            ControlStreamMisuse::CloseControlStream.expectation(),
+            Expectation::CloseError {
+                is_app: true,
+                error_code: 0x104,
+            }
+        );
+    }
+
+    #[test]
+    fn headers_builder_preserves_fields() {
+        let action = HeadersFrameBuilder::new(4)
+            .with_pseudo_header(b"Path", b"/")
+            .with_header(b"X-Foo", b"1")
+            .with_pseudo_header(b":method", b"GET")
+            .with_header(b"X-Foo", b"2")
+            .with_fin_stream(true)
+            .build();
+
+        let expected = vec![
+            Header::new(b":Path", b"/"),
+            Header::new(b"X-Foo", b"1"),
+            Header::new(b":method", b"GET"),
+            Header::new(b"X-Foo", b"2"),
+        ];
+
+        let (headers, literal) = built_headers(&action);
+        assert_eq!(headers, expected);
+        assert!(literal);
+
+        assert_eq!(action, send_headers_frame_literal(4, true, expected));
+    }
+
+    #[test]
+    fn headers_builder_pseudo_headers_last() {
+        let action = HeadersFrameBuilder::new(0)
+            .with_headers(&[
+                Header::new(b":method", b"GET"),
+                Header::new(b"a", b"1"),
+                Header::new(b":path", b"/"),
+                Header::new(b"b", b"2"),
+            ])
+            .with_pseudo_headers_last(true)
+            .build();
+
+        let (headers, _) = built_headers(&action);
+        assert_eq!(headers, [
+            Header::new(b"a", b"1"),
+            Header::new(b"b", b"2"),
+            Header::new(b":method", b"GET"),
+            Header::new(b":path", b"/"),
+        ]);
+    }
+
+    #[test]
+    fn headers_builder_not_literal() {
+        let headers = vec![Header::new(b":method", b"GET")];
+
+        let action = HeadersFrameBuilder::new(0)
+            .with_headers(&headers)
+            .with_literal_headers(false)
+            .build();
+
+        assert_eq!(action, send_headers_frame(0, false, headers));
+    }
+
+    #[test]
+    fn repeat_is_expanded_lazily() {
+        let actions = vec![
+            Action::Ping,
+            Action::Repeat {
+                count: 2,
+                actions: vec![Action::FlushPackets, Action::Repeat {
+                    count: 3,
+                    actions: vec![Action::UpdateKeys],
+                }],
+            },
+            Action::Repeat {
+                count: 0,
+                actions: vec![Action::Ping],
+            },
+            Action::Repeat {
+                count: u64::MAX,
+                actions: vec![],
+            },
+            Action::Padding { len: 1 },
+        ];
+
+        let mut iter = ActionIter::new(&actions);
+        let expanded: Vec<_> = iter.by_ref().cloned().collect();
+
+        assert_eq!(expanded, vec![
+            Action::Ping,
+            Action::FlushPackets,
+            Action::UpdateKeys,
+            Action::UpdateKeys,
+            Action::UpdateKeys,
+            Action::FlushPackets,
+            Action::UpdateKeys,
+            Action::UpdateKeys,
+            Action::UpdateKeys,
+            Action::Padding { len: 1 },
+        ]);
+        assert_eq!(iter.yielded(), expanded.len());
+    }
+
+    #[test]
+    fn action_iter_append() {
+        let first = vec![Action::Ping, Action::FlushPackets];
+        let second = vec![Action::UpdateKeys];
+        let third = vec![Action::Padding { len: 1 }];
+
+        let mut iter = ActionIter::new(&first);
+        assert_eq!(iter.next(), Some(&Action::Ping));
+
+        // Appended actions run after the remaining ones, even once the
+        // iterator is exhausted.
+        iter.append(&second);
+        assert_eq!(iter.next(), Some(&Action::FlushPackets));
+        assert_eq!(iter.next(), Some(&Action::UpdateKeys));
+        assert_eq!(iter.next(), None);
+
+        iter.append(&third);
+        assert_eq!(iter.next(), Some(&Action::Padding { len: 1 }));
+        assert_eq!(iter.next(), None);
+    }
+}
@@ -27,4 +27,8 @@
 //! Actions are small operations such as sending HTTP/3 frames or managing QUIC
 //! streams. Each independent use case for h3i requires its own collection of
 //! Actions, that h3i iterates over in sequence and executes.
+pub mod fuzz;
 pub mod h3;
+pub mod script;
+
+pub use script::from_file;
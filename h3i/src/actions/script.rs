@@ -0,0 +1,1804 @@
+// Copyright (C) 2025, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Declarative action scripts.
+//!
+//! Scripts describe a scenario, i.e. a sequence of [Action]s and optional
+//! [`CloseTriggerFrames`], in JSON or YAML, so that scenarios can be written
+//! without any Rust code. For example, the following script sends a request
+//! and waits for the response headers, closing the connection once a `200`
+//! response was received:
+//!
+//! ```yaml
+//! actions:
+//!   - send_headers:
+//!       stream_id: 0
+//!       fin_stream: true
+//!       headers:
+//!         - [":method", "GET"]
+//!         - [":scheme", "https"]
+//!         - [":authority", "cloudflare-quic.com"]
+//!         - [":path", "/"]
+//!   - send_frame:
+//!       stream_id: 2
+//!       frame:
+//!         unknown:
+//!           raw_type: 0x21
+//!           payload: { hex: "deadbeef" }
+//!   - wait:
+//!       stream_event: { stream_id: 0, type: headers }
+//!   - wait:
+//!       duration: 100
+//!
+//! close_triggers:
+//!   - stream_id: 0
+//!     headers:
+//!       - [":status", "200"]
+//! ```
+//!
+//! Each action is keyed by its name in snake case, e.g. `send_frame` or
+//! `reset_stream`, and has the same fields as the corresponding [Action].
+//! Payloads are either UTF-8 strings, or `{ hex: "..." }` objects for
+//! arbitrary bytes. Wait durations are in milliseconds, and connection-level
+//! events are waited for with e.g. `- wait: { connection_event: go_away }`.
+//! Waiting for part of a response body is done with e.g.
+//! `- wait: { stream_event: { stream_id: 0, type: { data_bytes: 1024 } } }`.
+//!
+//! GREASE can be sent with `grease` frames, which have a random reserved type
+//! and an optional `payload`, `open_grease_uni_stream` actions and the
+//! `grease` and `grease_value` fields of `settings` frames.
+//!
+//! A `repeat` action executes its nested `actions` `count` times, e.g.
+//! `- repeat: { count: 1000, actions: [{ send_frame: ... }] }`.
+//!
+//! Besides `close_triggers` frames, a `close_on` section lists the
+//! [`CloseTriggerEvent`]s to wait for before closing the connection, e.g.
+//! `close_on: [go_away, { stream_reset: { error_code: 0x10c } }]`.
+//!
+//! An `on_frame` action checks an [Expectation] against the frames received
+//! so far and executes either its `then` or its `otherwise` actions, e.g.
+//! `- on_frame: { matcher: { status: { stream_id: 0, status: 200 } }, then:
+//! [...], otherwise: [...] }`. `otherwise` may be omitted.
+//!
+//! `extended_connect` is a shorthand for a `send_headers` action carrying an
+//! extended CONNECT request, e.g. `- extended_connect: { stream_id: 0,
+//! protocol: webtransport, authority: example.com, path: /wt }`. Scripts
+//! written back from actions use `send_headers` instead.
+//!
+//! A `connections` section runs several connections concurrently instead of a
+//! single one, each with its own `actions`, e.g. `connections: [{ actions:
+//! [...] }, { actions: [...] }]`. It can't be combined with a top-level
+//! `actions` section.
+//!
+//! An `expect` section lists the [Expectations] to evaluate once the
+//! connection is over, keyed by their name in snake case, e.g.
+//! `- status: { stream_id: 0, status: 200 }`.
+//!
+//! Actions can also be written back to scripts, e.g. to save the actions
+//! [recorded] during a session as a regression test.
+//!
+//! [recorded]: crate::recordreplay::script
+
+use std::io;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::time::Duration;
+
+use quiche::h3::frame::Frame;
+use quiche::h3::Header;
+use quiche::h3::NameValue;
+use quiche::ConnectionError;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_with::serde_as;
+
+use crate::actions::h3::open_grease_uni_stream;
+use crate::actions::h3::send_extended_connect;
+use crate::actions::h3::send_headers_frame;
+use crate::actions::h3::send_headers_frame_literal;
+use crate::actions::h3::Action;
+use crate::actions::h3::BodyAssertion;
+use crate::actions::h3::ConnectionEvent;
+use crate::actions::h3::QpackInstruction;
+use crate::actions::h3::RampPolicy;
+use crate::actions::h3::StreamEvent;
+use crate::actions::h3::WaitType;
+use crate::actions::h3::GREASE_FRAME_PAYLOAD;
+use crate::client::connection_summary::CloseTriggerEvent;
+use crate::client::connection_summary::CloseTriggerFrames;
+use crate::client::expectations::Expectation;
+use crate::client::expectations::Expectations;
+use crate::encode_header_block;
+use crate::frame::CloseTriggerFrame;
+use crate::frame::H3iFrame;
+use crate::frame::ResetStream;
+
+/// The actions and close triggers loaded from a script.
+#[derive(Debug, Default)]
+pub struct Scenario {
+    /// The actions to execute, in order.
+    pub actions: Vec<Action>,
+    /// The actions of each connection to run concurrently, if the script has
+    /// a `connections` section. `actions` is empty in that case.
+    pub connections: Vec<Vec<Action>>,
+    /// The frames that close the connection once they were all received, if
+    /// the script has any.
+    pub close_trigger_frames: Option<CloseTriggerFrames>,
+    /// The expectations to evaluate against the connection summary.
+    pub expectations: Expectations,
+}
+
+/// Loads a [Scenario] from a script file.
+///
+/// Files with a `.json` extension are parsed as JSON, and any other file as
+/// YAML.
+pub fn from_file(path: impl AsRef<Path>) -> io::Result<Scenario> {
+    let path = path.as_ref();
+    let contents = std::fs::read_to_string(path)?;
+
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("json") => from_json(&contents),
+
+        _ => from_yaml(&contents),
+    }
+}
+
+/// Loads a [Scenario] from a JSON script.
+pub fn from_json(script: &str) -> io::Result<Scenario> {
+    let script: Script = serde_json::from_str(script).map_err(invalid_data)?;
+
+    script.try_into()
+}
+
+/// Loads a [Scenario] from a YAML script.
+pub fn from_yaml(script: &str) -> io::Result<Scenario> {
+    // Actions are keyed by their name, rather than written as YAML tags.
+    let script: Script =
+        serde_yaml_ng::with::singleton_map_recursive::deserialize(
+            serde_yaml_ng::Deserializer::from_str(script),
+        )
+        .map_err(invalid_data)?;
+
+    script.try_into()
+}
+
+/// Serializes `actions` to a JSON script.
+///
+/// See [`to_yaml()`] for how actions are represented.
+pub fn to_json(actions: &[Action]) -> io::Result<String> {
+    serde_json::to_string_pretty(&Script::from(actions)).map_err(invalid_data)
+}
+
+/// Serializes `actions` to a YAML script.
+///
+/// Header fields and payloads that aren't valid UTF-8 are kept as raw bytes:
+/// payloads are written as hex strings, and HEADERS frames whose fields can't
+/// be written as strings, or whose header block isn't the one h3i would
+/// encode, are written as frames of an unknown type with the raw type of
+/// HEADERS. Loading the script back yields actions that send the same bytes.
+pub fn to_yaml(actions: &[Action]) -> io::Result<String> {
+    let mut script = Vec::new();
+
+    serde_yaml_ng::with::singleton_map_recursive::serialize(
+        &Script::from(actions),
+        &mut serde_yaml_ng::Serializer::new(&mut script),
+    )
+    .map_err(invalid_data)?;
+
+    String::from_utf8(script).map_err(invalid_data)
+}
+
+/// Writes `actions` to a script file.
+///
+/// Files with a `.json` extension are written as JSON, and any other file as
+/// YAML.
+pub fn to_file(path: impl AsRef<Path>, actions: &[Action]) -> io::Result<()> {
+    let path = path.as_ref();
+
+    let script = match path.extension().and_then(|e| e.to_str()) {
+        Some("json") => to_json(actions)?,
+
+        _ => to_yaml(actions)?,
+    };
+
+    std::fs::write(path, script)
+}
+
+fn invalid_data(e: impl ToString) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Script {
+    #[serde(default)]
+    actions: Vec<ScriptAction>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    connections: Vec<ScriptConnection>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    close_triggers: Vec<ScriptCloseTrigger>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    close_on: Vec<CloseTriggerEvent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    close_with: Option<ScriptConnectionError>,
+    #[serde(default, skip_serializing_if = "Expectations::is_empty")]
+    expect: Expectations,
+}
+
+/// The actions of one of the connections of a script.
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ScriptConnection {
+    actions: Vec<ScriptAction>,
+}
+
+/// A list of fields, as `[name, value]` pairs so that their order and
+/// duplicates are preserved.
+type Fields = Vec<(String, String)>;
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+enum ScriptAction {
+    SendHeaders {
+        stream_id: u64,
+        #[serde(default)]
+        fin_stream: bool,
+        #[serde(default)]
+        literal_headers: bool,
+        headers: Fields,
+    },
+    SendFrame {
+        stream_id: u64,
+        #[serde(default)]
+        fin_stream: bool,
+        frame: ScriptFrame,
+    },
+    StreamBytes {
+        stream_id: u64,
+        #[serde(default)]
+        fin_stream: bool,
+        bytes: Payload,
+    },
+    OpenUniStream {
+        stream_id: u64,
+        #[serde(default)]
+        fin_stream: bool,
+        stream_type: u64,
+    },
+    OpenGreaseUniStream {
+        stream_id: u64,
+        #[serde(default)]
+        fin_stream: bool,
+    },
+    ExtendedConnect {
+        stream_id: u64,
+        protocol: String,
+        authority: String,
+        path: String,
+    },
+    OpenWebtransportStream {
+        stream_id: u64,
+        session_id: u64,
+        #[serde(default)]
+        bidi: bool,
+    },
+    ResetStream {
+        stream_id: u64,
+        error_code: u64,
+    },
+    StopSending {
+        stream_id: u64,
+        error_code: u64,
+    },
+    ConnectionClose(ScriptConnectionError),
+    MaxData {
+        max: u64,
+    },
+    MaxStreamData {
+        stream_id: u64,
+        max: u64,
+    },
+    MaxStreams {
+        bidi: bool,
+        max: u64,
+    },
+    WithholdFlowControlUpdates {
+        withhold: bool,
+    },
+    Ping,
+    Padding {
+        len: usize,
+    },
+    Burst {
+        packets: usize,
+    },
+    RawQuicFrame {
+        bytes: Payload,
+    },
+    UpdateKeys,
+    Migrate {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        local_addr: Option<SocketAddr>,
+        #[serde(default)]
+        nat_rebinding: bool,
+    },
+    ProbePath {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        local_addr: Option<SocketAddr>,
+    },
+    SwitchPath {
+        path: usize,
+    },
+    SendDatagram {
+        quarter_stream_id: u64,
+        payload: Payload,
+    },
+    SendQpackInstruction {
+        stream_id: u64,
+        instruction: ScriptQpackInstruction,
+    },
+    OpenConcurrentRequests {
+        first_stream_id: u64,
+        count: u64,
+        headers: Fields,
+        #[serde(default)]
+        ramp: RampPolicy,
+    },
+    AssertBody {
+        stream_id: u64,
+        assertion: BodyAssertion,
+    },
+    SnapshotStats {
+        label: String,
+    },
+    Repeat {
+        count: u64,
+        actions: Vec<ScriptAction>,
+    },
+    OnFrame {
+        matcher: Expectation,
+        then: Vec<ScriptAction>,
+        #[serde(default)]
+        otherwise: Vec<ScriptAction>,
+    },
+    FlushPackets,
+    Wait(ScriptWait),
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ScriptConnectionError {
+    #[serde(default)]
+    is_app: bool,
+    error_code: u64,
+    #[serde(default)]
+    reason: String,
+}
+
+impl From<ScriptConnectionError> for ConnectionError {
+    fn from(e: ScriptConnectionError) -> Self {
+        ConnectionError {
+            is_app: e.is_app,
+            error_code: e.error_code,
+            reason: e.reason.into_bytes(),
+        }
+    }
+}
+
+#[serde_as]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ScriptWait {
+    Duration(
+        #[serde_as(as = "serde_with::DurationMilliSecondsWithFrac<f64>")]
+        Duration,
+    ),
+    StreamEvent(StreamEvent),
+    ConnectionEvent(ConnectionEvent),
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+enum ScriptFrame {
+    Data {
+        payload: Payload,
+    },
+    Headers {
+        headers: Fields,
+    },
+    CancelPush {
+        push_id: u64,
+    },
+    Settings {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        max_field_section_size: Option<u64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        qpack_max_table_capacity: Option<u64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        qpack_blocked_streams: Option<u64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        connect_protocol_enabled: Option<u64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        h3_datagram: Option<u64>,
+        #[serde(default)]
+        settings: Vec<(u64, u64)>,
+        #[serde(default)]
+        grease: bool,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        grease_value: Option<u64>,
+    },
+    PushPromise {
+        push_id: u64,
+        headers: Fields,
+    },
+    GoAway {
+        id: u64,
+    },
+    MaxPushId {
+        push_id: u64,
+    },
+    PriorityUpdateRequest {
+        prioritized_element_id: u64,
+        priority_field_value: String,
+    },
+    PriorityUpdatePush {
+        prioritized_element_id: u64,
+        priority_field_value: String,
+    },
+    Metadata {
+        fields: Fields,
+    },
+    Unknown {
+        raw_type: u64,
+        payload: Payload,
+    },
+    Grease {
+        #[serde(default)]
+        payload: Option<Payload>,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+enum ScriptQpackInstruction {
+    SetDynamicTableCapacity {
+        capacity: u64,
+    },
+    InsertWithNameReference {
+        #[serde(default)]
+        is_static: bool,
+        name_index: u64,
+        value: Payload,
+    },
+    InsertWithLiteralName {
+        name: Payload,
+        value: Payload,
+    },
+    Duplicate {
+        index: u64,
+    },
+    SectionAcknowledgment {
+        stream_id: u64,
+    },
+    StreamCancellation {
+        stream_id: u64,
+    },
+    InsertCountIncrement {
+        increment: u64,
+    },
+    Raw(Payload),
+}
+
+/// Bytes given either as a UTF-8 string or as a hex string.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum Payload {
+    Text(String),
+    Hex { hex: String },
+}
+
+#[derive(Serialize, Deserialize)]
+struct ScriptCloseTrigger {
+    stream_id: u64,
+    #[serde(flatten)]
+    frame: ScriptTriggerFrame,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ScriptTriggerFrame {
+    /// Matches HEADERS frames containing at least these fields.
+    Headers(Fields),
+    ResetStream {
+        error_code: u64,
+    },
+    Frame(ScriptFrame),
+}
+
+impl TryFrom<Script> for Scenario {
+    type Error = io::Error;
+
+    fn try_from(script: Script) -> io::Result<Self> {
+        if !script.actions.is_empty() && !script.connections.is_empty() {
+            return Err(invalid_data(
+                "actions and connections can't be used together",
+            ));
+        }
+
+        let actions = script
+            .actions
+            .into_iter()
+            .map(Action::try_from)
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let connections = script
+            .connections
+            .into_iter()
+            .map(|c| {
+                c.actions
+                    .into_iter()
+                    .map(Action::try_from)
+                    .collect::<io::Result<Vec<_>>>()
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let frames = script
+            .close_triggers
+            .into_iter()
+            .map(|trigger| {
+                let frame = match trigger.frame {
+                    ScriptTriggerFrame::Headers(fields) =>
+                        H3iFrame::from(headers(&fields)),
+
+                    ScriptTriggerFrame::ResetStream { error_code } =>
+                        H3iFrame::ResetStream(ResetStream {
+                            stream_id: trigger.stream_id,
+                            error_code,
+                        }),
+
+                    ScriptTriggerFrame::Frame(frame) =>
+                        H3iFrame::QuicheH3(frame.try_into()?),
+                };
+
+                Ok(CloseTriggerFrame::new(trigger.stream_id, frame))
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let no_triggers = frames.is_empty() && script.close_on.is_empty();
+
+        let close_trigger_frames = match (no_triggers, script.close_with) {
+            (true, _) => None,
+
+            (false, Some(close_with)) =>
+                Some(CloseTriggerFrames::new_with_connection_close(
+                    frames,
+                    close_with.into(),
+                )),
+
+            (false, None) => Some(CloseTriggerFrames::new(frames)),
+        }
+        .map(|triggers| triggers.with_events(script.close_on));
+
+        Ok(Scenario {
+            actions,
+            connections,
+            close_trigger_frames,
+            expectations: script.expect,
+        })
+    }
+}
+
+impl TryFrom<ScriptAction> for Action {
+    type Error = io::Error;
+
+    fn try_from(action: ScriptAction) -> io::Result<Self> {
+        let action = match action {
+            ScriptAction::SendHeaders {
+                stream_id,
+                fin_stream,
+                literal_headers,
+                headers: fields,
+            } =>
+                if literal_headers {
+                    send_headers_frame_literal(
+                        stream_id,
+                        fin_stream,
+                        headers(&fields),
+                    )
+                } else {
+                    send_headers_frame(stream_id, fin_stream, headers(&fields))
+                },
+
+            ScriptAction::SendFrame {
+                stream_id,
+                fin_stream,
+                frame,
+            } => Action::SendFrame {
+                stream_id,
+                fin_stream,
+                frame: frame.try_into()?,
+            },
+
+            ScriptAction::StreamBytes {
+                stream_id,
+                fin_stream,
+                bytes,
+            } => Action::StreamBytes {
+                stream_id,
+                fin_stream,
+                bytes: bytes.into_bytes()?,
+            },
+
+            ScriptAction::OpenUniStream {
+                stream_id,
+                fin_stream,
+                stream_type,
+            } => Action::OpenUniStream {
+                stream_id,
+                fin_stream,
+                stream_type,
+            },
+
+            ScriptAction::OpenGreaseUniStream {
+                stream_id,
+                fin_stream,
+            } => open_grease_uni_stream(stream_id, fin_stream),
+
+            ScriptAction::ExtendedConnect {
+                stream_id,
+                protocol,
+                authority,
+                path,
+            } => send_extended_connect(stream_id, &protocol, &authority, &path),
+
+            ScriptAction::OpenWebtransportStream {
+                stream_id,
+                session_id,
+                bidi,
+            } => Action::OpenWebTransportStream {
+                stream_id,
+                session_id,
+                bidi,
+            },
+
+            ScriptAction::ResetStream {
+                stream_id,
+                error_code,
+            } => Action::ResetStream {
+                stream_id,
+                error_code,
+            },
+
+            ScriptAction::StopSending {
+                stream_id,
+                error_code,
+            } => Action::StopSending {
+                stream_id,
+                error_code,
+            },
+
+            ScriptAction::ConnectionClose(error) => Action::ConnectionClose {
+                error: error.into(),
+            },
+
+            ScriptAction::MaxData { max } => Action::MaxData { max },
+
+            ScriptAction::MaxStreamData { stream_id, max } =>
+                Action::MaxStreamData { stream_id, max },
+
+            ScriptAction::MaxStreams { bidi, max } =>
+                Action::MaxStreams { bidi, max },
+
+            ScriptAction::WithholdFlowControlUpdates { withhold } =>
+                Action::WithholdFlowControlUpdates { withhold },
+
+            ScriptAction::Ping => Action::Ping,
+            ScriptAction::UpdateKeys => Action::UpdateKeys,
+            ScriptAction::Migrate {
+                local_addr,
+                nat_rebinding,
+            } => Action::Migrate {
+                local_addr,
+                nat_rebinding,
+            },
+            ScriptAction::ProbePath { local_addr } =>
+                Action::ProbePath { local_addr },
+            ScriptAction::SwitchPath { path } => Action::SwitchPath { path },
+
+            ScriptAction::SendDatagram {
+                quarter_stream_id,
+                payload,
+            } => Action::SendDatagram {
+                quarter_stream_id,
+                payload: payload.into_bytes()?,
+            },
+
+            ScriptAction::Padding { len } => Action::Padding { len },
+            ScriptAction::Burst { packets } => Action::Burst { packets },
+
+            ScriptAction::RawQuicFrame { bytes } => Action::RawQuicFrame {
+                bytes: bytes.into_bytes()?,
+            },
+
+            ScriptAction::SendQpackInstruction {
+                stream_id,
+                instruction,
+            } => Action::SendQpackInstruction {
+                stream_id,
+                instruction: instruction.try_into()?,
+            },
+
+            ScriptAction::OpenConcurrentRequests {
+                first_stream_id,
+                count,
+                headers: fields,
+                ramp,
+            } => Action::OpenConcurrentRequests {
+                first_stream_id,
+                count,
+                headers: headers(&fields),
+                ramp,
+            },
+
+            ScriptAction::AssertBody {
+                stream_id,
+                assertion,
+            } => Action::AssertBody {
+                stream_id,
+                assertion,
+            },
+            ScriptAction::SnapshotStats { label } =>
+                Action::SnapshotStats { label },
+
+            ScriptAction::Repeat { count, actions } => Action::Repeat {
+                count,
+                actions: actions
+                    .into_iter()
+                    .map(Action::try_from)
+                    .collect::<io::Result<_>>()?,
+            },
+
+            ScriptAction::OnFrame {
+                matcher,
+                then,
+                otherwise,
+            } => Action::OnFrame {
+                matcher,
+                then: then
+                    .into_iter()
+                    .map(Action::try_from)
+                    .collect::<io::Result<_>>()?,
+                otherwise: otherwise
+                    .into_iter()
+                    .map(Action::try_from)
+                    .collect::<io::Result<_>>()?,
+            },
+
+            ScriptAction::FlushPackets => Action::FlushPackets,
+
+            ScriptAction::Wait(ScriptWait::Duration(period)) => Action::Wait {
+                wait_type: WaitType::WaitDuration(period),
+            },
+
+            ScriptAction::Wait(ScriptWait::StreamEvent(event)) => Action::Wait {
+                wait_type: WaitType::StreamEvent(event),
+            },
+
+            ScriptAction::Wait(ScriptWait::ConnectionEvent(event)) =>
+                Action::Wait {
+                    wait_type: WaitType::ConnectionEvent(event),
+                },
+        };
+
+        Ok(action)
+    }
+}
+
+impl TryFrom<ScriptFrame> for Frame {
+    type Error = io::Error;
+
+    fn try_from(frame: ScriptFrame) -> io::Result<Self> {
+        let frame = match frame {
+            ScriptFrame::Data { payload } => Frame::Data {
+                payload: payload.into_bytes()?,
+            },
+
+            ScriptFrame::Headers { headers: fields } => Frame::Headers {
+                header_block: header_block(fields)?,
+            },
+
+            ScriptFrame::CancelPush { push_id } => Frame::CancelPush { push_id },
+
+            ScriptFrame::Settings {
+                max_field_section_size,
+                qpack_max_table_capacity,
+                qpack_blocked_streams,
+                connect_protocol_enabled,
+                h3_datagram,
+                settings,
+                grease,
+                grease_value,
+            } => Frame::Settings {
+                max_field_section_size,
+                qpack_max_table_capacity,
+                qpack_blocked_streams,
+                connect_protocol_enabled,
+                h3_datagram,
+                grease: (grease || grease_value.is_some()).then(|| {
+                    (
+                        quiche::h3::grease_value(),
+                        grease_value.unwrap_or_else(quiche::h3::grease_value),
+                    )
+                }),
+                raw: None,
+                additional_settings: Some(settings),
+            },
+
+            ScriptFrame::PushPromise {
+                push_id,
+                headers: fields,
+            } => Frame::PushPromise {
+                push_id,
+                header_block: header_block(fields)?,
+            },
+
+            ScriptFrame::GoAway { id } => Frame::GoAway { id },
+
+            ScriptFrame::MaxPushId { push_id } => Frame::MaxPushId { push_id },
+
+            ScriptFrame::PriorityUpdateRequest {
+                prioritized_element_id,
+                priority_field_value,
+            } => Frame::PriorityUpdateRequest {
+                prioritized_element_id,
+                priority_field_value: priority_field_value.into_bytes(),
+            },
+
+            ScriptFrame::PriorityUpdatePush {
+                prioritized_element_id,
+                priority_field_value,
+            } => Frame::PriorityUpdatePush {
+                prioritized_element_id,
+                priority_field_value: priority_field_value.into_bytes(),
+            },
+
+            ScriptFrame::Metadata { fields } => Frame::Metadata {
+                header_block: header_block(fields)?,
+            },
+
+            ScriptFrame::Unknown { raw_type, payload } => Frame::Unknown {
+                raw_type,
+                payload: payload.into_bytes()?,
+            },
+
+            ScriptFrame::Grease { payload } => Frame::Unknown {
+                raw_type: quiche::h3::grease_value(),
+                payload: match payload {
+                    Some(payload) => payload.into_bytes()?,
+                    None => GREASE_FRAME_PAYLOAD.to_vec(),
+                },
+            },
+        };
+
+        Ok(frame)
+    }
+}
+
+impl From<&[Action]> for Script {
+    fn from(actions: &[Action]) -> Self {
+        Script {
+            actions: actions.iter().map(ScriptAction::from).collect(),
+            connections: vec![],
+            close_triggers: vec![],
+            close_on: vec![],
+            close_with: None,
+            expect: Expectations::default(),
+        }
+    }
+}
+
+impl From<&Action> for ScriptAction {
+    fn from(action: &Action) -> Self {
+        match action {
+            Action::SendHeadersFrame {
+                stream_id,
+                fin_stream,
+                literal_headers,
+                headers,
+                frame,
+            } => {
+                let fields = fields(headers);
+
+                // Only use the fields if they encode to the exact same header
+                // block, e.g. it wasn't built with custom encoding.
+                let encoded = fields.as_ref().map(|f| {
+                    if *literal_headers {
+                        send_headers_frame_literal(0, false, self::headers(f))
+                    } else {
+                        send_headers_frame(0, false, self::headers(f))
+                    }
+                });
+
+                match (fields, encoded) {
+                    (
+                        Some(fields),
+                        Some(Action::SendHeadersFrame {
+                            frame: encoded_frame,
+                            ..
+                        }),
+                    ) if &encoded_frame == frame => ScriptAction::SendHeaders {
+                        stream_id: *stream_id,
+                        fin_stream: *fin_stream,
+                        literal_headers: *literal_headers,
+                        headers: fields,
+                    },
+
+                    _ => ScriptAction::SendFrame {
+                        stream_id: *stream_id,
+                        fin_stream: *fin_stream,
+                        frame: ScriptFrame::from(frame),
+                    },
+                }
+            },
+
+            Action::SendFrame {
+                stream_id,
+                fin_stream,
+                frame,
+            } => ScriptAction::SendFrame {
+                stream_id: *stream_id,
+                fin_stream: *fin_stream,
+                frame: ScriptFrame::from(frame),
+            },
+
+            Action::StreamBytes {
+                stream_id,
+                fin_stream,
+                bytes,
+            } => ScriptAction::StreamBytes {
+                stream_id: *stream_id,
+                fin_stream: *fin_stream,
+                bytes: Payload::from(bytes.as_slice()),
+            },
+
+            Action::OpenUniStream {
+                stream_id,
+                fin_stream,
+                stream_type,
+            } => ScriptAction::OpenUniStream {
+                stream_id: *stream_id,
+                fin_stream: *fin_stream,
+                stream_type: *stream_type,
+            },
+
+            Action::OpenWebTransportStream {
+                stream_id,
+                session_id,
+                bidi,
+            } => ScriptAction::OpenWebtransportStream {
+                stream_id: *stream_id,
+                session_id: *session_id,
+                bidi: *bidi,
+            },
+
+            Action::ResetStream {
+                stream_id,
+                error_code,
+            } => ScriptAction::ResetStream {
+                stream_id: *stream_id,
+                error_code: *error_code,
+            },
+
+            Action::StopSending {
+                stream_id,
+                error_code,
+            } => ScriptAction::StopSending {
+                stream_id: *stream_id,
+                error_code: *error_code,
+            },
+
+            Action::ConnectionClose { error } =>
+                ScriptAction::ConnectionClose(ScriptConnectionError {
+                    is_app: error.is_app,
+                    error_code: error.error_code,
+                    reason: String::from_utf8_lossy(&error.reason).into_owned(),
+                }),
+
+            Action::MaxData { max } => ScriptAction::MaxData { max: *max },
+
+            Action::MaxStreamData { stream_id, max } =>
+                ScriptAction::MaxStreamData {
+                    stream_id: *stream_id,
+                    max: *max,
+                },
+
+            Action::MaxStreams { bidi, max } => ScriptAction::MaxStreams {
+                bidi: *bidi,
+                max: *max,
+            },
+
+            Action::WithholdFlowControlUpdates { withhold } =>
+                ScriptAction::WithholdFlowControlUpdates {
+                    withhold: *withhold,
+                },
+
+            Action::Ping => ScriptAction::Ping,
+            Action::UpdateKeys => ScriptAction::UpdateKeys,
+            Action::Migrate {
+                local_addr,
+                nat_rebinding,
+            } => ScriptAction::Migrate {
+                local_addr: *local_addr,
+                nat_rebinding: *nat_rebinding,
+            },
+            Action::ProbePath { local_addr } => ScriptAction::ProbePath {
+                local_addr: *local_addr,
+            },
+            Action::SwitchPath { path } =>
+                ScriptAction::SwitchPath { path: *path },
+            Action::SendDatagram {
+                quarter_stream_id,
+                payload,
+            } => ScriptAction::SendDatagram {
+                quarter_stream_id: *quarter_stream_id,
+                payload: Payload::from(payload.as_slice()),
+            },
+
+            Action::Padding { len } => ScriptAction::Padding { len: *len },
+            Action::Burst { packets } =>
+                ScriptAction::Burst { packets: *packets },
+
+            // Raw frames are binary by nature, so always keep them as hex.
+            Action::RawQuicFrame { bytes } => ScriptAction::RawQuicFrame {
+                bytes: Payload::hex(bytes),
+            },
+
+            Action::SendQpackInstruction {
+                stream_id,
+                instruction,
+            } => ScriptAction::SendQpackInstruction {
+                stream_id: *stream_id,
+                instruction: instruction.into(),
+            },
+
+            Action::OpenConcurrentRequests {
+                first_stream_id,
+                count,
+                headers,
+                ramp,
+            } => ScriptAction::OpenConcurrentRequests {
+                first_stream_id: *first_stream_id,
+                count: *count,
+                headers: lossy_fields(headers),
+                ramp: *ramp,
+            },
+
+            Action::AssertBody {
+                stream_id,
+                assertion,
+            } => ScriptAction::AssertBody {
+                stream_id: *stream_id,
+                assertion: assertion.clone(),
+            },
+            Action::SnapshotStats { label } => ScriptAction::SnapshotStats {
+                label: label.clone(),
+            },
+
+            Action::Repeat { count, actions } => ScriptAction::Repeat {
+                count: *count,
+                actions: actions.iter().map(ScriptAction::from).collect(),
+            },
+
+            Action::OnFrame {
+                matcher,
+                then,
+                otherwise,
+            } => ScriptAction::OnFrame {
+                matcher: matcher.clone(),
+                then: then.iter().map(ScriptAction::from).collect(),
+                otherwise: otherwise.iter().map(ScriptAction::from).collect(),
+            },
+
+            Action::FlushPackets => ScriptAction::FlushPackets,
+
+            Action::Wait {
+                wait_type: WaitType::WaitDuration(period),
+            } => ScriptAction::Wait(ScriptWait::Duration(*period)),
+
+            Action::Wait {
+                wait_type: WaitType::StreamEvent(event),
+            } => ScriptAction::Wait(ScriptWait::StreamEvent(*event)),
+
+            Action::Wait {
+                wait_type: WaitType::ConnectionEvent(event),
+            } => ScriptAction::Wait(ScriptWait::ConnectionEvent(*event)),
+        }
+    }
+}
+
+impl TryFrom<ScriptQpackInstruction> for QpackInstruction {
+    type Error = io::Error;
+
+    fn try_from(instruction: ScriptQpackInstruction) -> io::Result<Self> {
+        let instruction = match instruction {
+            ScriptQpackInstruction::SetDynamicTableCapacity { capacity } =>
+                QpackInstruction::SetDynamicTableCapacity { capacity },
+
+            ScriptQpackInstruction::InsertWithNameReference {
+                is_static,
+                name_index,
+                value,
+            } => QpackInstruction::InsertWithNameReference {
+                is_static,
+                name_index,
+                value: value.into_bytes()?,
+            },
+
+            ScriptQpackInstruction::InsertWithLiteralName { name, value } =>
+                QpackInstruction::InsertWithLiteralName {
+                    name: name.into_bytes()?,
+                    value: value.into_bytes()?,
+                },
+
+            ScriptQpackInstruction::Duplicate { index } =>
+                QpackInstruction::Duplicate { index },
+
+            ScriptQpackInstruction::SectionAcknowledgment { stream_id } =>
+                QpackInstruction::SectionAcknowledgment { stream_id },
+
+            ScriptQpackInstruction::StreamCancellation { stream_id } =>
+                QpackInstruction::StreamCancellation { stream_id },
+
+            ScriptQpackInstruction::InsertCountIncrement { increment } =>
+                QpackInstruction::InsertCountIncrement { increment },
+
+            ScriptQpackInstruction::Raw(bytes) =>
+                QpackInstruction::Raw(bytes.into_bytes()?),
+        };
+
+        Ok(instruction)
+    }
+}
+
+impl From<&QpackInstruction> for ScriptQpackInstruction {
+    fn from(instruction: &QpackInstruction) -> Self {
+        match instruction {
+            QpackInstruction::SetDynamicTableCapacity { capacity } =>
+                ScriptQpackInstruction::SetDynamicTableCapacity {
+                    capacity: *capacity,
+                },
+
+            QpackInstruction::InsertWithNameReference {
+                is_static,
+                name_index,
+                value,
+            } => ScriptQpackInstruction::InsertWithNameReference {
+                is_static: *is_static,
+                name_index: *name_index,
+                value: Payload::from(value.as_slice()),
+            },
+
+            QpackInstruction::InsertWithLiteralName { name, value } =>
+                ScriptQpackInstruction::InsertWithLiteralName {
+                    name: Payload::from(name.as_slice()),
+                    value: Payload::from(value.as_slice()),
+                },
+
+            QpackInstruction::Duplicate { index } =>
+                ScriptQpackInstruction::Duplicate { index: *index },
+
+            QpackInstruction::SectionAcknowledgment { stream_id } =>
+                ScriptQpackInstruction::SectionAcknowledgment {
+                    stream_id: *stream_id,
+                },
+
+            QpackInstruction::StreamCancellation { stream_id } =>
+                ScriptQpackInstruction::StreamCancellation {
+                    stream_id: *stream_id,
+                },
+
+            QpackInstruction::InsertCountIncrement { increment } =>
+                ScriptQpackInstruction::InsertCountIncrement {
+                    increment: *increment,
+                },
+
+            QpackInstruction::Raw(bytes) =>
+                ScriptQpackInstruction::Raw(Payload::hex(bytes)),
+        }
+    }
+}
+
+impl From<&Frame> for ScriptFrame {
+    fn from(frame: &Frame) -> Self {
+        match frame {
+            Frame::Data { payload } => ScriptFrame::Data {
+                payload: Payload::from(payload.as_slice()),
+            },
+
+            Frame::Headers { header_block } =>
+                match decode_fields(header_block) {
+                    Some(fields) => ScriptFrame::Headers { headers: fields },
+
+                    None => raw_frame(frame),
+                },
+
+            Frame::CancelPush { push_id } =>
+                ScriptFrame::CancelPush { push_id: *push_id },
+
+            Frame::Settings {
+                max_field_section_size,
+                qpack_max_table_capacity,
+                qpack_blocked_streams,
+                connect_protocol_enabled,
+                h3_datagram,
+                grease,
+                additional_settings,
+                ..
+            } => ScriptFrame::Settings {
+                max_field_section_size: *max_field_section_size,
+                qpack_max_table_capacity: *qpack_max_table_capacity,
+                qpack_blocked_streams: *qpack_blocked_streams,
+                connect_protocol_enabled: *connect_protocol_enabled,
+                h3_datagram: *h3_datagram,
+                settings: additional_settings.clone().unwrap_or_default(),
+                grease: grease.is_some(),
+                grease_value: grease.map(|(_, value)| value),
+            },
+
+            Frame::PushPromise {
+                push_id,
+                header_block,
+            } => match decode_fields(header_block) {
+                Some(fields) => ScriptFrame::PushPromise {
+                    push_id: *push_id,
+                    headers: fields,
+                },
+
+                None => raw_frame(frame),
+            },
+
+            Frame::GoAway { id } => ScriptFrame::GoAway { id: *id },
+
+            Frame::MaxPushId { push_id } =>
+                ScriptFrame::MaxPushId { push_id: *push_id },
+
+            Frame::PriorityUpdateRequest {
+                prioritized_element_id,
+                priority_field_value,
+            } => match std::str::from_utf8(priority_field_value) {
+                Ok(value) => ScriptFrame::PriorityUpdateRequest {
+                    prioritized_element_id: *prioritized_element_id,
+                    priority_field_value: value.to_string(),
+                },
+
+                Err(_) => raw_frame(frame),
+            },
+
+            Frame::PriorityUpdatePush {
+                prioritized_element_id,
+                priority_field_value,
+            } => match std::str::from_utf8(priority_field_value) {
+                Ok(value) => ScriptFrame::PriorityUpdatePush {
+                    prioritized_element_id: *prioritized_element_id,
+                    priority_field_value: value.to_string(),
+                },
+
+                Err(_) => raw_frame(frame),
+            },
+
+            Frame::Metadata { header_block } => match decode_fields(header_block)
+            {
+                Some(fields) => ScriptFrame::Metadata { fields },
+
+                None => raw_frame(frame),
+            },
+
+            Frame::Unknown { raw_type, payload } => ScriptFrame::Unknown {
+                raw_type: *raw_type,
+                payload: Payload::from(payload.as_slice()),
+            },
+        }
+    }
+}
+
+/// Represents `frame` as a frame of unknown type, with the same type and
+/// payload on the wire.
+fn raw_frame(frame: &Frame) -> ScriptFrame {
+    let mut d = vec![0; 65535];
+    let mut b = octets::OctetsMut::with_slice(&mut d);
+
+    let len = frame.to_bytes(&mut b).unwrap_or(0);
+
+    let mut b = octets::Octets::with_slice(&d[..len]);
+
+    let raw_type = b.get_varint().unwrap_or(0);
+    let payload = b.get_bytes_with_varint_length().map(|p| p.to_vec());
+
+    ScriptFrame::Unknown {
+        raw_type,
+        payload: Payload::from(payload.unwrap_or_default().as_slice()),
+    }
+}
+
+impl From<&[u8]> for Payload {
+    fn from(bytes: &[u8]) -> Self {
+        match std::str::from_utf8(bytes) {
+            Ok(text) => Payload::Text(text.to_string()),
+
+            Err(_) => Payload::hex(bytes),
+        }
+    }
+}
+
+impl Payload {
+    fn hex(bytes: &[u8]) -> Self {
+        Payload::Hex {
+            hex: bytes.iter().map(|b| format!("{b:02x}")).collect(),
+        }
+    }
+
+    fn into_bytes(self) -> io::Result<Vec<u8>> {
+        match self {
+            Payload::Text(text) => Ok(text.into_bytes()),
+
+            Payload::Hex { hex } => {
+                if hex.len() % 2 != 0 {
+                    return Err(invalid_data(format!(
+                        "odd number of hex digits in {hex}"
+                    )));
+                }
+
+                (0..hex.len())
+                    .step_by(2)
+                    .map(|i| {
+                        hex.get(i..i + 2)
+                            .and_then(|b| u8::from_str_radix(b, 16).ok())
+                            .ok_or_else(|| {
+                                invalid_data(format!("invalid hex string {hex}"))
+                            })
+                    })
+                    .collect()
+            },
+        }
+    }
+}
+
+fn headers(fields: &[(String, String)]) -> Vec<Header> {
+    fields
+        .iter()
+        .map(|(name, value)| Header::new(name.as_bytes(), value.as_bytes()))
+        .collect()
+}
+
+/// Returns the fields as strings, or `None` if any isn't valid UTF-8.
+fn fields(headers: &[Header]) -> Option<Fields> {
+    headers
+        .iter()
+        .map(|h| {
+            let name = std::str::from_utf8(h.name()).ok()?;
+            let value = std::str::from_utf8(h.value()).ok()?;
+
+            Some((name.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+fn lossy_fields(headers: &[Header]) -> Fields {
+    headers
+        .iter()
+        .map(|h| {
+            (
+                String::from_utf8_lossy(h.name()).into_owned(),
+                String::from_utf8_lossy(h.value()).into_owned(),
+            )
+        })
+        .collect()
+}
+
+/// Decodes a header block into fields that encode back to the same block.
+fn decode_fields(header_block: &[u8]) -> Option<Fields> {
+    if header_block.is_empty() {
+        return Some(vec![]);
+    }
+
+    let mut decoder = quiche::h3::qpack::Decoder::new();
+    let headers = decoder.decode(header_block, u64::MAX).ok()?;
+
+    let fields = fields(&headers)?;
+
+    (encode_header_block(&headers).ok()? == header_block).then_some(fields)
+}
+
+fn header_block(fields: Fields) -> io::Result<Vec<u8>> {
+    let fields = headers(&fields);
+
+    if fields.is_empty() {
+        return Ok(vec![]);
+    }
+
+    encode_header_block(&fields).map_err(invalid_data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::actions::h3::is_grease_value;
+    use crate::actions::h3::StreamEvent;
+    use crate::actions::h3::StreamEventType;
+
+    const YAML: &str = r#"
+actions:
+  - send_headers:
+      stream_id: 0
+      fin_stream: true
+      headers:
+        - [":method", "GET"]
+        - [":path", "/"]
+  - send_frame:
+      stream_id: 2
+      frame:
+        unknown:
+          raw_type: 0x21
+          payload: { hex: "dEAdbeef" }
+  - wait:
+      stream_event: { stream_id: 0, type: headers }
+  - wait:
+      stream_event: { stream_id: 0, type: { data_bytes: 1024 } }
+  - wait:
+      duration: 12.5
+  - wait:
+      connection_event: handshake_confirmed
+  - connection_close:
+      is_app: true
+      error_code: 0x100
+
+close_triggers:
+  - stream_id: 0
+    headers:
+      - [":status", "200"]
+  - stream_id: 4
+    reset_stream: { error_code: 0x10c }
+
+close_on:
+  - go_away
+  - stop_sending: { stream_id: 0 }
+  - peer_close: { is_app: true, error_code: 0x100 }
+
+expect:
+  - status: { stream_id: 0, status: 200 }
+  - forbidden_frame: { frame: GOAWAY }
+"#;
+
+    #[test]
+    fn yaml_script() {
+        let scenario = from_yaml(YAML).unwrap();
+
+        let req =
+            vec![Header::new(b":method", b"GET"), Header::new(b":path", b"/")];
+
+        assert_eq!(scenario.actions, vec![
+            send_headers_frame(0, true, req),
+            Action::SendFrame {
+                stream_id: 2,
+                fin_stream: false,
+                frame: Frame::Unknown {
+                    raw_type: 0x21,
+                    payload: vec![0xde, 0xad, 0xbe, 0xef],
+                },
+            },
+            Action::Wait {
+                wait_type: WaitType::StreamEvent(StreamEvent {
+                    stream_id: 0,
+                    event_type: StreamEventType::Headers,
+                }),
+            },
+            Action::Wait {
+                wait_type: WaitType::StreamEvent(StreamEvent {
+                    stream_id: 0,
+                    event_type: StreamEventType::DataBytes(1024),
+                }),
+            },
+            Action::Wait {
+                wait_type: WaitType::WaitDuration(Duration::from_micros(12500)),
+            },
+            Action::Wait {
+                wait_type: WaitType::ConnectionEvent(
+                    ConnectionEvent::HandshakeConfirmed,
+                ),
+            },
+            Action::ConnectionClose {
+                error: ConnectionError {
+                    is_app: true,
+                    error_code: 0x100,
+                    reason: vec![],
+                },
+            },
+        ]);
+
+        let triggers = scenario.close_trigger_frames.unwrap();
+        assert_eq!(triggers.missing_triggers(), vec![
+            CloseTriggerFrame::new(0, vec![Header::new(b":status", b"200")]),
+            CloseTriggerFrame::new(
+                4,
+                H3iFrame::ResetStream(ResetStream {
+                    stream_id: 4,
+                    error_code: 0x10c,
+                })
+            ),
+        ]);
+        assert_eq!(triggers.missing_events(), &[
+            CloseTriggerEvent::GoAway,
+            CloseTriggerEvent::StopSending {
+                stream_id: Some(0),
+                error_code: None,
+            },
+            CloseTriggerEvent::PeerClose {
+                is_app: true,
+                error_code: 0x100,
+            },
+        ]);
+
+        assert_eq!(
+            scenario.expectations,
+            Expectations::new()
+                .with_status(0, 200)
+                .with_forbidden_frame(None, "GOAWAY")
+        );
+    }
+
+    #[test]
+    fn json_script() {
+        let scenario = from_json(
+            r#"{
+                "actions": [
+                    { "stream_bytes": { "stream_id": 0, "bytes": "hi" } },
+                    "ping",
+                    "update_keys",
+                    { "migrate": { "nat_rebinding": true } },
+                    { "migrate": { "local_addr": "127.0.0.1:4433" } },
+                    { "probe_path": {} },
+                    { "switch_path": { "path": 1 } },
+                    { "snapshot_stats": { "label": "before" } },
+                    { "send_datagram": { "quarter_stream_id": 0, "payload": "ping" } },
+                    { "extended_connect": {
+                        "stream_id": 4,
+                        "protocol": "webtransport",
+                        "authority": "example.com",
+                        "path": "/wt"
+                    } },
+                    { "open_webtransport_stream": { "stream_id": 8, "session_id": 4, "bidi": true } },
+                    { "repeat": { "count": 2, "actions": ["ping", "flush_packets"] } },
+                    { "on_frame": {
+                        "matcher": { "status": { "stream_id": 0, "status": 200 } },
+                        "then": ["ping"]
+                    } },
+                    { "padding": { "len": 10 } },
+                    { "burst": { "packets": 50 } },
+                    { "raw_quic_frame": { "bytes": { "hex": "1f00" } } },
+                    { "send_qpack_instruction": {
+                        "stream_id": 6,
+                        "instruction": {
+                            "insert_with_name_reference": {
+                                "is_static": true,
+                                "name_index": 0,
+                                "value": "example.com"
+                            }
+                        }
+                    } },
+                    { "send_qpack_instruction": {
+                        "stream_id": 6,
+                        "instruction": { "raw": { "hex": "ff" } }
+                    } },
+                    "flush_packets"
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(scenario.actions, vec![
+            Action::StreamBytes {
+                stream_id: 0,
+                fin_stream: false,
+                bytes: b"hi".to_vec(),
+            },
+            Action::Ping,
+            Action::UpdateKeys,
+            Action::Migrate {
+                local_addr: None,
+                nat_rebinding: true,
+            },
+            Action::Migrate {
+                local_addr: Some("127.0.0.1:4433".parse().unwrap()),
+                nat_rebinding: false,
+            },
+            Action::ProbePath { local_addr: None },
+            Action::SwitchPath { path: 1 },
+            Action::SnapshotStats {
+                label: "before".to_string(),
+            },
+            Action::SendDatagram {
+                quarter_stream_id: 0,
+                payload: b"ping".to_vec(),
+            },
+            send_extended_connect(4, "webtransport", "example.com", "/wt"),
+            Action::OpenWebTransportStream {
+                stream_id: 8,
+                session_id: 4,
+                bidi: true,
+            },
+            Action::Repeat {
+                count: 2,
+                actions: vec![Action::Ping, Action::FlushPackets],
+            },
+            Action::OnFrame {
+                matcher: Expectation::Status {
+                    stream_id: 0,
+                    status: 200,
+                },
+                then: vec![Action::Ping],
+                otherwise: vec![],
+            },
+            Action::Padding { len: 10 },
+            Action::Burst { packets: 50 },
+            Action::RawQuicFrame {
+                bytes: vec![0x1f, 0x00],
+            },
+            Action::SendQpackInstruction {
+                stream_id: 6,
+                instruction: QpackInstruction::InsertWithNameReference {
+                    is_static: true,
+                    name_index: 0,
+                    value: b"example.com".to_vec(),
+                },
+            },
+            Action::SendQpackInstruction {
+                stream_id: 6,
+                instruction: QpackInstruction::Raw(vec![0xff]),
+            },
+            Action::FlushPackets,
+        ]);
+        assert!(scenario.close_trigger_frames.is_none());
+    }
+
+    #[test]
+    fn grease_script() {
+        let scenario = from_yaml(
+            r#"
+actions:
+  - open_grease_uni_stream: { stream_id: 6 }
+  - send_frame: { stream_id: 2, frame: { grease: {} } }
+  - send_frame: { stream_id: 0, frame: { grease: { payload: "hi" } } }
+  - send_frame:
+      stream_id: 2
+      frame: { settings: { settings: [[6, 100]], grease_value: 7 } }
+"#,
+        )
+        .unwrap();
+
+        let actions = scenario.actions;
+        assert_eq!(actions.len(), 4);
+
+        assert!(matches!(
+            actions[0],
+            Action::OpenUniStream {
+                stream_id: 6,
+                fin_stream: false,
+                stream_type,
+            } if is_grease_value(stream_type)
+        ));
+
+        for (action, expected) in [
+            (&actions[1], GREASE_FRAME_PAYLOAD),
+            (&actions[2], b"hi".as_slice()),
+        ] {
+            match action {
+                Action::SendFrame {
+                    frame: Frame::Unknown { raw_type, payload },
+                    ..
+                } => {
+                    assert!(is_grease_value(*raw_type));
+                    assert_eq!(payload, expected);
+                },
+
+                a => panic!("unexpected action {a:?}"),
+            }
+        }
+
+        match &actions[3] {
+            Action::SendFrame {
+                frame:
+                    Frame::Settings {
+                        grease: Some((id, 7)),
+                        additional_settings,
+                        ..
+                    },
+                ..
+            } => {
+                assert!(is_grease_value(*id));
+                assert_eq!(additional_settings, &Some(vec![(6, 100)]));
+            },
+
+            a => panic!("unexpected action {a:?}"),
+        }
+
+        // The GREASE setting value is kept when writing back to a script.
+        let yaml = to_yaml(&actions[3..]).unwrap();
+        assert!(matches!(
+            from_yaml(&yaml).unwrap().actions[0],
+            Action::SendFrame {
+                frame: Frame::Settings {
+                    grease: Some((_, 7)),
+                    ..
+                },
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn invalid_script() {
+        // Unknown action.
+        assert!(from_yaml("actions:\n  - teleport: {}\n").is_err());
+
+        // Invalid hex payload.
+        assert!(from_yaml(
+            "actions:\n  - stream_bytes: { stream_id: 0, bytes: { hex: abc } }\n"
+        )
+        .is_err());
+
+        // Both top-level actions and connections.
+        assert!(from_yaml(
+            "actions: [ping]\nconnections:\n  - actions: [ping]\n"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn connections_script() {
+        let scenario = from_yaml(
+            r#"
+connections:
+  - actions:
+      - ping
+  - actions:
+      - stream_bytes: { stream_id: 0, bytes: "hi" }
+      - flush_packets
+"#,
+        )
+        .unwrap();
+
+        assert!(scenario.actions.is_empty());
+        assert_eq!(scenario.connections, vec![vec![Action::Ping], vec![
+            Action::StreamBytes {
+                stream_id: 0,
+                fin_stream: false,
+                bytes: b"hi".to_vec(),
+            },
+            Action::FlushPackets,
+        ],]);
+    }
+}
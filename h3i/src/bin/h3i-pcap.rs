@@ -0,0 +1,143 @@
+// Copyright (C) 2025, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Converts a packet capture of an HTTP/3 client into an h3i action file,
+//! which can be replayed with `h3i --qlog-input`.
+
+use std::env;
+use std::path::Path;
+use std::time::Duration;
+
+use h3i::recordreplay::pcap;
+use h3i::recordreplay::qlog::make_streamer;
+use h3i::recordreplay::qlog::record_actions;
+
+use clap::App;
+use clap::Arg;
+
+fn main() {
+    let mut log_builder = env_logger::builder();
+    if env::var_os("RUST_LOG").is_none() {
+        log_builder.filter_level(log::LevelFilter::Info);
+    }
+
+    log_builder.init();
+
+    let matches = App::new("h3i-pcap")
+        .version("v0.1.0")
+        .about("Converts a packet capture of an HTTP/3 client into h3i actions")
+        .arg(
+            Arg::with_name("capture")
+                .help("The pcap or pcapng capture")
+                .required(true)
+                .index(1),
+        )
+        .arg(
+            Arg::with_name("keylog")
+                .long("keylog")
+                .help("The TLS key log of the client. Optional if the secrets are embedded in a pcapng capture.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("server-port")
+                .long("server-port")
+                .help("Convert the first connection to this UDP port, rather than the first connection in the capture.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("min-wait")
+                .long("min-wait")
+                .help("The shortest gap between packets, in milliseconds, reproduced with a wait.")
+                .takes_value(true)
+                .default_value("1"),
+        )
+        .arg(
+            Arg::with_name("output")
+                .long("output")
+                .short('o')
+                .help("The action file to write. Defaults to the capture's file name, with an .sqlog extension.")
+                .takes_value(true),
+        )
+        .get_matches();
+
+    let capture_path = matches.value_of("capture").unwrap();
+
+    let capture = std::fs::read(capture_path)
+        .unwrap_or_else(|e| fail(&format!("failed to read {capture_path}: {e}")));
+
+    let key_log = matches.value_of("keylog").map(|path| {
+        std::fs::read_to_string(path)
+            .unwrap_or_else(|e| fail(&format!("failed to read {path}: {e}")))
+    });
+
+    let server_port = matches.value_of("server-port").map(|v| {
+        v.parse::<u16>()
+            .unwrap_or_else(|e| fail(&format!("server-port input error {e}")))
+    });
+
+    let min_wait = matches
+        .value_of("min-wait")
+        .unwrap()
+        .parse::<f64>()
+        .ok()
+        .and_then(|v| Duration::try_from_secs_f64(v / 1000.0).ok())
+        .unwrap_or_else(|| fail("min-wait input error"));
+
+    let config = pcap::Config {
+        server_port,
+        min_wait,
+    };
+
+    let actions = pcap::actions_from_pcap(&capture, key_log.as_deref(), &config)
+        .unwrap_or_else(|e| fail(&format!("failed to convert capture: {e}")));
+
+    let output = match matches.value_of("output") {
+        Some(v) => v.to_string(),
+
+        None => Path::new(capture_path)
+            .with_extension("sqlog")
+            .to_string_lossy()
+            .into_owned(),
+    };
+
+    let file = std::fs::File::create(&output)
+        .unwrap_or_else(|e| fail(&format!("failed to create {output}: {e}")));
+
+    let mut streamer = make_streamer(Box::new(std::io::BufWriter::new(file)));
+
+    record_actions(&mut streamer, &actions);
+
+    streamer
+        .finish_log()
+        .unwrap_or_else(|e| fail(&format!("failed to write {output}: {e}")));
+
+    log::info!("wrote {} actions to {}", actions.len(), output);
+}
+
+fn fail(msg: &str) -> ! {
+    log::error!("{msg}");
+    std::process::exit(1);
+}
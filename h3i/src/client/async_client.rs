@@ -0,0 +1,155 @@
+// Copyright (C) 2025, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! An async API to drive long-lived connections.
+//!
+//! The connection runs on its own thread, like the ones started by
+//! [`connect_interactive()`], while [ConnectionHandle] lets async code send
+//! actions to it and consume the events it receives.
+//!
+//! [`connect_interactive()`]: crate::client::sync_client::connect_interactive
+
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use tokio_stream::StreamExt;
+
+use crate::actions::h3::Action;
+use crate::client::connection_summary::ConnectionSummary;
+use crate::client::sync_client::connect_interactive;
+use crate::client::sync_client::ActionSender;
+use crate::client::ClientError;
+use crate::client::ClientEvent;
+use crate::config::Config;
+
+type ConnectionThread =
+    std::thread::JoinHandle<Result<ConnectionSummary, ClientError>>;
+
+/// A handle to a connection started by [`connect()`].
+pub struct ConnectionHandle {
+    sender: ActionSender,
+    events: UnboundedReceiverStream<ClientEvent>,
+    thread: ConnectionThread,
+}
+
+/// Connect to a server, returning a [ConnectionHandle] to drive the
+/// connection.
+///
+/// No actions are executed until they are sent with
+/// [`ConnectionHandle::send()`]. The connection is kept alive until it is
+/// closed with [`ConnectionHandle::close()`], or by the server.
+///
+/// ```no_run
+/// # async fn run() -> Result<(), h3i::client::ClientError> {
+/// use h3i::actions::h3::send_headers_frame;
+/// use h3i::client::async_client;
+/// use h3i::client::ClientEvent;
+/// use h3i::quiche::h3::Header;
+/// use tokio_stream::StreamExt;
+///
+/// let config = h3i::config::Config::new()
+///     .with_host_port("cloudflare-quic.com".to_string())
+///     .build()
+///     .unwrap();
+///
+/// let mut handle = async_client::connect(config)?;
+///
+/// handle.send(vec![send_headers_frame(0, true, vec![
+///     Header::new(b":method", b"GET"),
+///     Header::new(b":scheme", b"https"),
+///     Header::new(b":authority", b"cloudflare-quic.com"),
+///     Header::new(b":path", b"/"),
+/// ])])?;
+///
+/// while let Some(event) = handle.events().next().await {
+///     match event {
+///         ClientEvent::Frame {
+///             stream_id: 0,
+///             frame,
+///         } => println!("{frame:?}"),
+///         ClientEvent::StreamFinished { stream_id: 0 } => break,
+///         _ => (),
+///     }
+/// }
+///
+/// let summary = handle.close().await?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn connect(args: Config) -> Result<ConnectionHandle, ClientError> {
+    let (tx, rx) = mpsc::unbounded_channel();
+
+    let (sender, thread) = connect_interactive(args, move |event| {
+        // The handle may not be interested in events anymore.
+        let _ = tx.send(event);
+    })?;
+
+    Ok(ConnectionHandle {
+        sender,
+        events: UnboundedReceiverStream::new(rx),
+        thread,
+    })
+}
+
+impl ConnectionHandle {
+    /// Queues `actions` to be executed after the ones sent previously.
+    ///
+    /// Returns an error if the connection is already closed.
+    pub fn send(&self, actions: Vec<Action>) -> Result<(), ClientError> {
+        self.sender.send(actions)
+    }
+
+    /// The stream of [ClientEvent]s received on the connection, in order.
+    ///
+    /// The stream ends once the connection is closed.
+    pub fn events(&mut self) -> &mut UnboundedReceiverStream<ClientEvent> {
+        &mut self.events
+    }
+
+    /// Waits for the next [ClientEvent] received on the connection.
+    ///
+    /// Returns `None` once the connection is closed.
+    pub async fn next_event(&mut self) -> Option<ClientEvent> {
+        self.events.next().await
+    }
+
+    /// Gracefully closes the connection, once the actions sent so far are
+    /// executed, and returns its [ConnectionSummary].
+    ///
+    /// Events that were not consumed yet are dropped.
+    pub async fn close(self) -> Result<ConnectionSummary, ClientError> {
+        // Dropping the sender closes the connection.
+        drop(self.sender);
+
+        let thread = self.thread;
+
+        tokio::task::spawn_blocking(move || thread.join())
+            .await
+            .map_err(|e| ClientError::Other(e.to_string()))?
+            .map_err(|_| {
+                ClientError::Other("connection thread panicked".into())
+            })?
+    }
+}
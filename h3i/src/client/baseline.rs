@@ -0,0 +1,574 @@
+// Copyright (C) 2025, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Golden baselines of a connection, used to detect drift in a server's
+//! behavior between runs.
+//!
+//! A [Baseline] is a normalized [ConnectionSummary]: it only keeps the parts
+//! of the summary that are expected to be stable when running the same
+//! actions against the same server, such as the sequence of frame types
+//! received on each stream, response statuses and close codes. Timings are
+//! kept too, but only compared within a [Tolerance].
+
+use std::fmt;
+use std::time::Duration;
+
+use quiche::h3::frame::Frame as QFrame;
+use quiche::ConnectionError;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_with::serde_as;
+
+use super::connection_summary::ConnectionSummary;
+use crate::actions::h3::is_grease_value;
+use crate::frame::H3iFrame;
+
+/// A normalized [ConnectionSummary].
+///
+/// # Example
+///
+/// ```
+/// use h3i::client::baseline::Baseline;
+/// use h3i::client::baseline::Tolerance;
+/// use h3i::client::connection_summary::ConnectionSummary;
+///
+/// let summary = ConnectionSummary::default();
+///
+/// let baseline = Baseline::from(&summary);
+/// assert!(baseline
+///     .compare(&baseline, &Tolerance::default())
+///     .is_empty());
+/// ```
+#[serde_as]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Baseline {
+    /// The streams on which frames were received, ordered by stream ID.
+    pub streams: Vec<StreamBaseline>,
+    /// The error sent by the peer when closing the connection, if any.
+    pub peer_error: Option<CloseBaseline>,
+    /// The error sent by h3i when closing the connection, if any.
+    pub local_error: Option<CloseBaseline>,
+    /// If the connection timed out.
+    pub timed_out: bool,
+    /// The time from the first stream being written to, until the last DATA
+    /// frame was received.
+    #[serde_as(as = "serde_with::DurationMilliSecondsWithFrac<f64>")]
+    pub elapsed: Duration,
+}
+
+/// The normalized frames received on a single stream.
+#[serde_as]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct StreamBaseline {
+    pub stream_id: u64,
+    /// The types of the frames received, in order. Consecutive DATA frames
+    /// are collapsed into one, since how the body is split into frames is
+    /// up to the peer, and GREASE frame types are all reported as `GREASE`.
+    pub frames: Vec<String>,
+    /// The `:status` of each HEADERS frame that had one, in order.
+    pub statuses: Vec<String>,
+    /// The error code of the RESET_STREAM frame received, if any.
+    pub reset_error_code: Option<u64>,
+    /// The time from the first time h3i wrote to the stream, until its last
+    /// DATA frame was received. `None` if no DATA frame was received.
+    #[serde_as(as = "Option<serde_with::DurationMilliSecondsWithFrac<f64>>")]
+    #[serde(default)]
+    pub elapsed: Option<Duration>,
+}
+
+/// The error a connection was closed with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CloseBaseline {
+    /// Whether this is an application error.
+    pub is_app: bool,
+    /// The error code.
+    pub error_code: u64,
+}
+
+impl fmt::Display for CloseBaseline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = if self.is_app {
+            "application"
+        } else {
+            "transport"
+        };
+
+        write!(f, "{kind} error {:#x}", self.error_code)
+    }
+}
+
+impl From<&ConnectionError> for CloseBaseline {
+    fn from(e: &ConnectionError) -> Self {
+        Self {
+            is_app: e.is_app,
+            error_code: e.error_code,
+        }
+    }
+}
+
+/// How much slower than the baseline a run can be before it is reported as a
+/// regression.
+///
+/// A timing is a regression if it exceeds the baseline's by more than
+/// `relative` times the baseline, plus `absolute`. Runs that are faster than
+/// the baseline are never regressions.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Tolerance {
+    /// The allowed slowdown, relative to the baseline, e.g. 0.5 for 50%.
+    pub relative: f64,
+    /// The allowed slowdown on top of the relative one. This keeps short
+    /// timings, which are dominated by jitter, from being reported.
+    pub absolute: Duration,
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Self {
+            relative: 0.5,
+            absolute: Duration::from_millis(100),
+        }
+    }
+}
+
+impl Tolerance {
+    fn exceeded(&self, expected: Duration, actual: Duration) -> bool {
+        let limit = expected.mul_f64(1.0 + self.relative) + self.absolute;
+
+        actual > limit
+    }
+}
+
+/// A difference between a run and its baseline.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Regression {
+    /// What differs, e.g. `stream 0 statuses`.
+    pub field: String,
+    /// The value in the baseline.
+    pub expected: String,
+    /// The value in the current run.
+    pub actual: String,
+}
+
+impl fmt::Display for Regression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: expected {}, got {}",
+            self.field, self.expected, self.actual
+        )
+    }
+}
+
+impl From<&ConnectionSummary> for Baseline {
+    fn from(summary: &ConnectionSummary) -> Self {
+        let throughput = summary.throughput_report();
+
+        let streams = summary
+            .stream_map
+            .stream_ids()
+            .into_iter()
+            .map(|stream_id| {
+                let mut stream = StreamBaseline {
+                    stream_id,
+                    elapsed: throughput
+                        .streams
+                        .iter()
+                        .find(|s| s.stream_id == stream_id)
+                        .map(|s| s.elapsed),
+                    ..Default::default()
+                };
+
+                for frame in summary.stream_map.stream(stream_id) {
+                    if let H3iFrame::Headers(headers) = &frame {
+                        if let Some(status) = headers.status_code() {
+                            stream
+                                .statuses
+                                .push(String::from_utf8_lossy(status).into());
+                        }
+                    }
+
+                    if let H3iFrame::ResetStream(reset) = &frame {
+                        stream.reset_error_code = Some(reset.error_code);
+                    }
+
+                    let name = frame_name(&frame);
+
+                    if name == "DATA" &&
+                        stream.frames.last().is_some_and(|f| f == "DATA")
+                    {
+                        continue;
+                    }
+
+                    stream.frames.push(name);
+                }
+
+                stream
+            })
+            .collect();
+
+        let close = &summary.conn_close_details;
+
+        Baseline {
+            streams,
+            peer_error: close.peer_error().map(CloseBaseline::from),
+            local_error: close.local_error().map(CloseBaseline::from),
+            timed_out: close.timed_out,
+            elapsed: throughput.elapsed,
+        }
+    }
+}
+
+impl Baseline {
+    /// Compares the `current` run against this baseline, returning the
+    /// differences found, if any.
+    pub fn compare(
+        &self, current: &Baseline, tolerance: &Tolerance,
+    ) -> Vec<Regression> {
+        let mut regressions = vec![];
+
+        for expected in &self.streams {
+            let field = format!("stream {}", expected.stream_id);
+
+            let Some(actual) = current
+                .streams
+                .iter()
+                .find(|s| s.stream_id == expected.stream_id)
+            else {
+                regressions.push(Regression {
+                    field,
+                    expected: "frames".to_string(),
+                    actual: "none".to_string(),
+                });
+
+                continue;
+            };
+
+            diff(
+                &mut regressions,
+                format!("{field} frames"),
+                expected.frames.join(","),
+                actual.frames.join(","),
+            );
+
+            diff(
+                &mut regressions,
+                format!("{field} statuses"),
+                expected.statuses.join(","),
+                actual.statuses.join(","),
+            );
+
+            diff(
+                &mut regressions,
+                format!("{field} reset error code"),
+                or_none(expected.reset_error_code.map(|c| format!("{c:#x}"))),
+                or_none(actual.reset_error_code.map(|c| format!("{c:#x}"))),
+            );
+
+            if let (Some(e), Some(a)) = (expected.elapsed, actual.elapsed) {
+                if tolerance.exceeded(e, a) {
+                    regressions.push(Regression {
+                        field: format!("{field} elapsed"),
+                        expected: format!("{e:?}"),
+                        actual: format!("{a:?}"),
+                    });
+                }
+            }
+        }
+
+        for actual in &current.streams {
+            if !self.streams.iter().any(|s| s.stream_id == actual.stream_id) {
+                regressions.push(Regression {
+                    field: format!("stream {}", actual.stream_id),
+                    expected: "none".to_string(),
+                    actual: actual.frames.join(","),
+                });
+            }
+        }
+
+        diff(
+            &mut regressions,
+            "peer error".to_string(),
+            or_none(self.peer_error.map(|e| e.to_string())),
+            or_none(current.peer_error.map(|e| e.to_string())),
+        );
+
+        diff(
+            &mut regressions,
+            "local error".to_string(),
+            or_none(self.local_error.map(|e| e.to_string())),
+            or_none(current.local_error.map(|e| e.to_string())),
+        );
+
+        diff(
+            &mut regressions,
+            "timed out".to_string(),
+            self.timed_out.to_string(),
+            current.timed_out.to_string(),
+        );
+
+        if tolerance.exceeded(self.elapsed, current.elapsed) {
+            regressions.push(Regression {
+                field: "elapsed".to_string(),
+                expected: format!("{:?}", self.elapsed),
+                actual: format!("{:?}", current.elapsed),
+            });
+        }
+
+        regressions
+    }
+}
+
+fn diff(
+    regressions: &mut Vec<Regression>, field: String, expected: String,
+    actual: String,
+) {
+    if expected != actual {
+        regressions.push(Regression {
+            field,
+            expected,
+            actual,
+        });
+    }
+}
+
+fn or_none(v: Option<String>) -> String {
+    v.unwrap_or_else(|| "none".to_string())
+}
+
+/// The name of a frame's type, as reported in baselines and expectations.
+pub(crate) fn frame_name(frame: &H3iFrame) -> String {
+    let frame = match frame {
+        H3iFrame::Headers(_) => return "HEADERS".to_string(),
+
+        H3iFrame::ResetStream(_) => return "RESET_STREAM".to_string(),
+
+        H3iFrame::QuicheH3(frame) => frame,
+    };
+
+    let name = match frame {
+        QFrame::Data { .. } => "DATA",
+        QFrame::Headers { .. } => "HEADERS",
+        QFrame::CancelPush { .. } => "CANCEL_PUSH",
+        QFrame::Settings { .. } => "SETTINGS",
+        QFrame::PushPromise { .. } => "PUSH_PROMISE",
+        QFrame::GoAway { .. } => "GOAWAY",
+        QFrame::MaxPushId { .. } => "MAX_PUSH_ID",
+        QFrame::PriorityUpdateRequest { .. } => "PRIORITY_UPDATE_REQUEST",
+        QFrame::PriorityUpdatePush { .. } => "PRIORITY_UPDATE_PUSH",
+        QFrame::Metadata { .. } => "METADATA",
+
+        QFrame::Unknown { raw_type, .. } if is_grease_value(*raw_type) =>
+            "GREASE",
+
+        QFrame::Unknown { raw_type, .. } =>
+            return format!("UNKNOWN({raw_type:#x})"),
+    };
+
+    name.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::connection_summary::StreamMap;
+    use crate::frame::EnrichedHeaders;
+    use crate::frame::ResetStream;
+    use quiche::h3::Header;
+
+    fn response(status: &[u8]) -> H3iFrame {
+        H3iFrame::Headers(EnrichedHeaders::from(vec![Header::new(
+            b":status", status,
+        )]))
+    }
+
+    fn data() -> H3iFrame {
+        H3iFrame::QuicheH3(QFrame::Data {
+            payload: b"hello".to_vec(),
+        })
+    }
+
+    fn summary(frames: Vec<(u64, Vec<H3iFrame>)>) -> ConnectionSummary {
+        ConnectionSummary {
+            stream_map: StreamMap::from(frames),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn normalize() {
+        let summary = summary(vec![
+            (0, vec![response(b"200"), data(), data(), data()]),
+            (4, vec![
+                response(b"103"),
+                response(b"200"),
+                data(),
+                H3iFrame::ResetStream(ResetStream {
+                    stream_id: 4,
+                    error_code: 0x10c,
+                }),
+            ]),
+            (3, vec![H3iFrame::QuicheH3(QFrame::Unknown {
+                raw_type: 0x21,
+                payload: vec![],
+            })]),
+        ]);
+
+        let baseline = Baseline::from(&summary);
+
+        assert_eq!(baseline.streams, vec![
+            StreamBaseline {
+                stream_id: 0,
+                frames: vec!["HEADERS".into(), "DATA".into()],
+                statuses: vec!["200".into()],
+                ..Default::default()
+            },
+            StreamBaseline {
+                stream_id: 3,
+                frames: vec!["GREASE".into()],
+                ..Default::default()
+            },
+            StreamBaseline {
+                stream_id: 4,
+                frames: vec![
+                    "HEADERS".into(),
+                    "HEADERS".into(),
+                    "DATA".into(),
+                    "RESET_STREAM".into()
+                ],
+                statuses: vec!["103".into(), "200".into()],
+                reset_error_code: Some(0x10c),
+                ..Default::default()
+            },
+        ]);
+    }
+
+    #[test]
+    fn compare() {
+        let expected = Baseline::from(&summary(vec![
+            (0, vec![response(b"200"), data()]),
+            (4, vec![response(b"200"), data()]),
+        ]));
+
+        let actual = Baseline::from(&summary(vec![
+            (0, vec![response(b"404"), data(), data()]),
+            (8, vec![response(b"200")]),
+        ]));
+
+        let regressions = expected.compare(&actual, &Tolerance::default());
+
+        assert_eq!(regressions, vec![
+            Regression {
+                field: "stream 0 statuses".to_string(),
+                expected: "200".to_string(),
+                actual: "404".to_string(),
+            },
+            Regression {
+                field: "stream 4".to_string(),
+                expected: "frames".to_string(),
+                actual: "none".to_string(),
+            },
+            Regression {
+                field: "stream 8".to_string(),
+                expected: "none".to_string(),
+                actual: "HEADERS".to_string(),
+            },
+        ]);
+    }
+
+    #[test]
+    fn compare_close() {
+        let expected = Baseline {
+            peer_error: Some(CloseBaseline {
+                is_app: true,
+                error_code: 0x100,
+            }),
+            ..Default::default()
+        };
+
+        let actual = Baseline {
+            peer_error: Some(CloseBaseline {
+                is_app: true,
+                error_code: 0x101,
+            }),
+            timed_out: true,
+            ..Default::default()
+        };
+
+        let regressions = expected.compare(&actual, &Tolerance::default());
+
+        assert_eq!(regressions.len(), 2);
+        assert_eq!(regressions[0].field, "peer error");
+        assert_eq!(regressions[1].field, "timed out");
+    }
+
+    #[test]
+    fn compare_timing() {
+        let tolerance = Tolerance {
+            relative: 0.5,
+            absolute: Duration::from_millis(10),
+        };
+
+        let expected = Baseline {
+            elapsed: Duration::from_millis(100),
+            ..Default::default()
+        };
+
+        for (elapsed, regressed) in [(20, false), (160, false), (161, true)] {
+            let actual = Baseline {
+                elapsed: Duration::from_millis(elapsed),
+                ..Default::default()
+            };
+
+            assert_eq!(
+                !expected.compare(&actual, &tolerance).is_empty(),
+                regressed
+            );
+        }
+    }
+
+    #[test]
+    fn serde_roundtrip() {
+        let baseline = Baseline {
+            streams: vec![StreamBaseline {
+                stream_id: 0,
+                frames: vec!["HEADERS".into(), "DATA".into()],
+                statuses: vec!["200".into()],
+                reset_error_code: None,
+                elapsed: Some(Duration::from_millis(12)),
+            }],
+            local_error: Some(CloseBaseline {
+                is_app: true,
+                error_code: 0x100,
+            }),
+            elapsed: Duration::from_millis(12),
+            ..Default::default()
+        };
+
+        let json = serde_json::to_string(&baseline).unwrap();
+
+        assert_eq!(serde_json::from_str::<Baseline>(&json).unwrap(), baseline);
+    }
+}
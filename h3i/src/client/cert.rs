@@ -0,0 +1,211 @@
+// Copyright (C) 2025, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Verifies the server's certificate against the pins of the [Config].
+//!
+//! [Config]: crate::config::Config
+
+use crate::client::ClientError;
+use crate::config::CertificatePin;
+
+/// The TLS alerts a client sends when it can't verify the server's
+/// certificate, see
+/// [RFC 8446](https://www.rfc-editor.org/rfc/rfc8446#section-6.2).
+const CERTIFICATE_ALERTS: &[(u64, &str)] = &[
+    (42, "bad_certificate"),
+    (43, "unsupported_certificate"),
+    (44, "certificate_revoked"),
+    (45, "certificate_expired"),
+    (46, "certificate_unknown"),
+    (48, "unknown_ca"),
+];
+
+/// The QUIC error codes of TLS alerts start at this value, see
+/// [RFC 9001](https://www.rfc-editor.org/rfc/rfc9001#section-4.8).
+const CRYPTO_ERROR: u64 = 0x100;
+
+/// The QUIC error code of the bad_certificate TLS alert.
+pub(crate) const BAD_CERTIFICATE: u64 = CRYPTO_ERROR + 42;
+
+/// Checks the server's certificate against `pins`. The certificate must match
+/// at least one of them, unless there are none.
+pub(crate) fn check_pins(
+    conn: &quiche::Connection, pins: &[CertificatePin],
+) -> Result<(), ClientError> {
+    if pins.is_empty() {
+        return Ok(());
+    }
+
+    let Some(cert) = conn.peer_cert() else {
+        return Err(ClientError::CertificateVerificationFailed(
+            "server sent no certificate".to_string(),
+        ));
+    };
+
+    if pins.iter().any(|pin| pin.matches(cert)) {
+        return Ok(());
+    }
+
+    Err(ClientError::CertificateVerificationFailed(
+        "server certificate matches none of the pins".to_string(),
+    ))
+}
+
+/// The reason the connection failed to verify the server's certificate, if
+/// that is why it was closed.
+pub(crate) fn verification_failure(conn: &quiche::Connection) -> Option<String> {
+    let error = conn.local_error().filter(|e| !e.is_app)?;
+    let alert = error.error_code.checked_sub(CRYPTO_ERROR)?;
+
+    CERTIFICATE_ALERTS
+        .iter()
+        .find(|(code, _)| *code == alert)
+        .map(|(_, name)| format!("server certificate rejected with {name} alert"))
+}
+
+/// The DER-encoded SubjectPublicKeyInfo of a DER-encoded X.509 certificate,
+/// see [RFC 5280](https://www.rfc-editor.org/rfc/rfc5280#section-4.1).
+pub(crate) fn subject_public_key_info(cert: &[u8]) -> Option<&[u8]> {
+    let (certificate, _) = der_element(cert, 0x30)?;
+    let (tbs_certificate, _) = der_element(certificate.content, 0x30)?;
+
+    let mut rest = tbs_certificate.content;
+
+    // The version is optional.
+    if let Some((_, after)) = der_element(rest, 0xa0) {
+        rest = after;
+    }
+
+    // Skip the serial number, signature algorithm, issuer, validity and
+    // subject.
+    for tag in [0x02, 0x30, 0x30, 0x30, 0x30] {
+        let (_, after) = der_element(rest, tag)?;
+        rest = after;
+    }
+
+    let (spki, _) = der_element(rest, 0x30)?;
+
+    Some(spki.encoded)
+}
+
+/// A DER element.
+struct DerElement<'a> {
+    /// The whole element, including its tag and length.
+    encoded: &'a [u8],
+    content: &'a [u8],
+}
+
+/// Parses the DER element at the start of `b`, which must have the given
+/// `tag`. Returns it along with the bytes following it.
+fn der_element(b: &[u8], tag: u8) -> Option<(DerElement<'_>, &[u8])> {
+    if *b.first()? != tag {
+        return None;
+    }
+
+    let first = *b.get(1)?;
+
+    let (len, header_len) = if first & 0x80 == 0 {
+        (first as usize, 2)
+    } else {
+        let len_len = (first & 0x7f) as usize;
+
+        if len_len == 0 || len_len > 4 {
+            return None;
+        }
+
+        let len = b
+            .get(2..2 + len_len)?
+            .iter()
+            .fold(0, |len, byte| len << 8 | *byte as usize);
+
+        (len, 2 + len_len)
+    };
+
+    let end = header_len.checked_add(len)?;
+    let encoded = b.get(..end)?;
+
+    Some((
+        DerElement {
+            encoded,
+            content: &encoded[header_len..],
+        },
+        &b[end..],
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A certificate with empty fields, except for the SubjectPublicKeyInfo.
+    fn certificate(spki: &[u8]) -> Vec<u8> {
+        let mut tbs = vec![
+            0xa0, 0x03, 0x02, 0x01, 0x02, // version
+            0x02, 0x01, 0x01, // serial number
+            0x30, 0x00, // signature algorithm
+            0x30, 0x00, // issuer
+            0x30, 0x00, // validity
+            0x30, 0x00, // subject
+        ];
+        tbs.extend_from_slice(spki);
+
+        let mut tbs_certificate = vec![0x30, 0x81, tbs.len() as u8];
+        tbs_certificate.extend(tbs);
+
+        let mut cert = vec![0x30, tbs_certificate.len() as u8];
+        cert.extend(tbs_certificate);
+        cert
+    }
+
+    #[test]
+    fn spki() {
+        let spki = [0x30, 0x03, 0x05, 0x01, 0xff];
+        let cert = certificate(&spki);
+
+        assert_eq!(subject_public_key_info(&cert), Some(&spki[..]));
+
+        // Truncated certificates don't parse.
+        assert_eq!(subject_public_key_info(&cert[..cert.len() - 1]), None);
+        assert_eq!(subject_public_key_info(&[0x02, 0x00]), None);
+    }
+
+    #[test]
+    fn pins() {
+        let spki = [0x30, 0x03, 0x05, 0x01, 0xff];
+        let cert = certificate(&spki);
+
+        let digest = |b: &[u8]| {
+            ring::digest::digest(&ring::digest::SHA256, b)
+                .as_ref()
+                .to_vec()
+        };
+
+        assert!(CertificatePin::Certificate(digest(&cert)).matches(&cert));
+        assert!(CertificatePin::Spki(digest(&spki)).matches(&cert));
+        assert!(!CertificatePin::Spki(digest(&cert)).matches(&cert));
+        assert!(!CertificatePin::Certificate(digest(&spki)).matches(&cert));
+    }
+}
@@ -27,17 +27,35 @@
 //! Summarizes events that occurred during a connection.
 
 use quiche;
+use quiche::h3::frame::Frame as QFrame;
 use quiche::Connection;
 use quiche::ConnectionError;
 use quiche::PathStats;
 use quiche::Stats;
 use serde::ser::SerializeStruct;
 use serde::ser::Serializer;
+use serde::Deserialize;
 use serde::Serialize;
+use serde_with::serde_as;
 use std::cmp;
+use std::collections::BTreeMap;
 use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io::Write;
 use std::iter::FromIterator;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
 
+use crate::actions::h3::is_grease_value;
+use crate::actions::h3::Action;
+use crate::actions::h3::ActionIter;
+use crate::actions::h3::BodyAssertion;
+use crate::client::baseline::frame_name;
+use crate::client::ClientError;
+use crate::config::BodyCapture;
 use crate::frame::CloseTriggerFrame;
 use crate::frame::EnrichedHeaders;
 use crate::frame::H3iFrame;
@@ -64,6 +82,14 @@ pub struct ConnectionSummary {
     pub path_stats: Vec<PathStats>,
     /// Details about why the connection closed.
     pub conn_close_details: ConnectionCloseDetails,
+    /// Whether early data was sent, and if the server accepted it.
+    pub early_data: EarlyData,
+    /// The transport parameters sent by the server, including the ones quiche
+    /// doesn't know about.
+    pub peer_transport_params: Option<quiche::TransportParams>,
+    /// Whether the server sent a Retry or a Version Negotiation packet, and
+    /// the ALPN protocol it selected.
+    pub handshake: HandshakeSummary,
 }
 
 impl Serialize for ConnectionSummary {
@@ -71,7 +97,7 @@ impl Serialize for ConnectionSummary {
     where
         S: Serializer,
     {
-        let mut state = s.serialize_struct("path_stats", 4)?;
+        let mut state = s.serialize_struct("path_stats", 8)?;
         state.serialize_field("stream_map", &self.stream_map)?;
         state.serialize_field(
             "stats",
@@ -81,14 +107,439 @@ impl Serialize for ConnectionSummary {
             self.path_stats.iter().map(SerializablePathStats).collect();
         state.serialize_field("path_stats", &p)?;
         state.serialize_field("error", &self.conn_close_details)?;
+        if self.early_data != EarlyData::NotSent {
+            state.serialize_field("early_data", &self.early_data)?;
+        }
+        if let Some(params) = &self.peer_transport_params {
+            state.serialize_field(
+                "peer_transport_params",
+                &SerializableTransportParams(params),
+            )?;
+        }
+        if self.handshake != HandshakeSummary::default() {
+            state.serialize_field("handshake", &self.handshake)?;
+        }
         state.serialize_field(
             "missed_close_trigger_frames",
             &self.stream_map.missing_close_trigger_frames(),
         )?;
+
+        if let Some(events) = self.stream_map.missing_close_trigger_events() {
+            if !events.is_empty() {
+                state.serialize_field("missed_close_trigger_events", &events)?;
+            }
+        }
+
+        let priority_updates = self.stream_map.priority_updates();
+        if !priority_updates.is_empty() {
+            state.serialize_field("priority_updates", &priority_updates)?;
+        }
+
+        let body_assertions = self.stream_map.body_assertions();
+        if !body_assertions.is_empty() {
+            state.serialize_field("body_assertions", body_assertions)?;
+        }
+
+        let concurrent_requests = self.stream_map.concurrent_requests();
+        if !concurrent_requests.is_empty() {
+            state.serialize_field("concurrent_requests", concurrent_requests)?;
+        }
+
+        let informational = self.stream_map.informational_headers();
+        if !informational.is_empty() {
+            state.serialize_field("informational_headers", &informational)?;
+        }
+
+        let trailers = self.stream_map.trailers();
+        if !trailers.is_empty() {
+            state.serialize_field("trailers", &trailers)?;
+        }
+
+        let stats_snapshots = self.stream_map.stats_snapshots();
+        if !stats_snapshots.is_empty() {
+            state.serialize_field("stats_snapshots", stats_snapshots)?;
+        }
+
+        let datagrams = self.stream_map.datagrams();
+        if !datagrams.is_empty() {
+            state.serialize_field("datagrams", datagrams)?;
+        }
+
+        let captured_bodies = self.stream_map.captured_bodies();
+        if !captured_bodies.is_empty() {
+            state.serialize_field("captured_bodies", &captured_bodies)?;
+        }
+
+        let stop_sending = &self.stream_map.stop_sending;
+        if !stop_sending.is_empty() {
+            state.serialize_field("stop_sending", stop_sending)?;
+        }
+
+        let throughput = self.throughput_report();
+        if throughput.goodput_bytes > 0 {
+            state.serialize_field("throughput", &throughput)?;
+        }
+
+        let timing = self.timing_report();
+        if !timing.is_empty() {
+            state.serialize_field("timing", &timing)?;
+        }
+
+        state.end()
+    }
+}
+
+impl ConnectionSummary {
+    /// Reports when frames were received on each stream and when each action
+    /// was executed, relative to the start of the run.
+    ///
+    /// An action's latency is the time until the first frame received on its
+    /// stream after it was executed, e.g. the time to the response HEADERS for
+    /// a request.
+    pub fn timing_report(&self) -> TimingReport {
+        let stream_map = &self.stream_map;
+
+        let relative =
+            |t: Instant| stream_map.start.map(|s| t.saturating_duration_since(s));
+
+        let mut ids: Vec<u64> = stream_map.frame_times.keys().copied().collect();
+        ids.sort_unstable();
+
+        let streams = ids
+            .into_iter()
+            .map(|stream_id| {
+                let frames = stream_map.stream(stream_id);
+                let times = stream_map.frame_times_on_stream(stream_id);
+
+                let first = |matches: fn(&H3iFrame) -> bool| {
+                    frames
+                        .iter()
+                        .zip(times)
+                        .find(|(f, _)| matches(f))
+                        .map(|(_, t)| *t)
+                };
+
+                let first_sent = stream_map
+                    .stream_timings
+                    .get(&stream_id)
+                    .and_then(|t| t.first_sent)
+                    .and_then(relative);
+                let first_headers = first(|f| matches!(f, H3iFrame::Headers(_)));
+
+                StreamTimingReport {
+                    stream_id,
+                    first_sent,
+                    first_headers,
+                    first_data: first(|f| {
+                        matches!(f, H3iFrame::QuicheH3(QFrame::Data { .. }))
+                    }),
+                    last_frame: times.last().copied(),
+                    time_to_first_headers: first_sent
+                        .zip(first_headers)
+                        .map(|(sent, headers)| headers.saturating_sub(sent)),
+                }
+            })
+            .collect();
+
+        let actions = stream_map
+            .action_times
+            .iter()
+            .enumerate()
+            .map(|(index, action)| {
+                let latency = action.stream_id.and_then(|id| {
+                    stream_map
+                        .frame_times_on_stream(id)
+                        .iter()
+                        .find(|t| **t >= action.executed)
+                        .map(|t| t.saturating_sub(action.executed))
+                });
+
+                ActionTimingReport {
+                    index,
+                    action: action.name,
+                    stream_id: action.stream_id,
+                    executed: action.executed,
+                    latency,
+                }
+            })
+            .collect();
+
+        TimingReport { streams, actions }
+    }
+
+    /// Computes the goodput of the run, per stream and in aggregate, together
+    /// with the wire overhead and retransmission ratio derived from the
+    /// connection's [`Stats`].
+    ///
+    /// Only DATA frames received while the client was running are accounted
+    /// for. A stream's goodput is measured from the first time h3i wrote to it,
+    /// or from its first DATA frame for streams opened by the peer, until its
+    /// last DATA frame.
+    pub fn throughput_report(&self) -> ThroughputReport {
+        let mut report = ThroughputReport::default();
+
+        let mut ids: Vec<u64> = self
+            .stream_map
+            .stream_timings
+            .iter()
+            .filter(|(_, t)| t.last_data.is_some())
+            .map(|(id, _)| *id)
+            .collect();
+        ids.sort_unstable();
+
+        let mut start: Option<Instant> = None;
+        let mut end: Option<Instant> = None;
+
+        for stream_id in ids {
+            let timing = &self.stream_map.stream_timings[&stream_id];
+
+            let (Some(first), Some(last)) =
+                (timing.first_sent.or(timing.first_data), timing.last_data)
+            else {
+                continue;
+            };
+
+            let bytes = self.stream_map.data_bytes_on_stream(stream_id);
+            let elapsed = last.saturating_duration_since(first);
+
+            start = Some(start.map_or(first, |s| s.min(first)));
+            end = Some(end.map_or(last, |e| e.max(last)));
+
+            report.goodput_bytes += bytes;
+            report.streams.push(StreamThroughput {
+                stream_id,
+                bytes,
+                elapsed,
+                goodput_bps: bits_per_second(bytes, elapsed),
+            });
+        }
+
+        if let (Some(start), Some(end)) = (start, end) {
+            report.elapsed = end.saturating_duration_since(start);
+            report.goodput_bps =
+                bits_per_second(report.goodput_bytes, report.elapsed);
+        }
+
+        if let Some(stats) = &self.stats {
+            report.wire_bytes_received = Some(stats.recv_bytes);
+            report.wire_overhead = ratio(
+                stats.recv_bytes.saturating_sub(report.goodput_bytes),
+                stats.recv_bytes,
+            );
+            report.retransmission_ratio =
+                ratio(stats.retrans as u64, stats.sent as u64);
+            report.retransmitted_bytes_ratio =
+                ratio(stats.stream_retrans_bytes, stats.sent_bytes);
+        }
+
+        report
+    }
+
+    /// Reports how the peer reacted to the GREASE sent by `actions`, which
+    /// should be the actions executed on this connection.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use h3i::actions::h3::send_grease_frame;
+    /// use h3i::client::connection_summary::ConnectionSummary;
+    ///
+    /// let actions = vec![send_grease_frame(0, true)];
+    /// let summary = ConnectionSummary::default();
+    ///
+    /// let report = summary.grease_report(&actions);
+    /// assert_eq!(report.frame_streams, vec![0]);
+    /// assert!(!report.peer_choked());
+    /// ```
+    pub fn grease_report(&self, actions: &[Action]) -> GreaseReport {
+        let mut report = GreaseReport::default();
+
+        for action in ActionIter::new(actions) {
+            match action {
+                Action::SendFrame {
+                    stream_id,
+                    frame: quiche::h3::frame::Frame::Unknown { raw_type, .. },
+                    ..
+                } if is_grease_value(*raw_type) =>
+                    report.frame_streams.push(*stream_id),
+
+                Action::SendFrame {
+                    frame:
+                        quiche::h3::frame::Frame::Settings {
+                            grease,
+                            additional_settings,
+                            raw,
+                            ..
+                        },
+                    ..
+                } => {
+                    // Settings replayed from qlog may only carry `raw`.
+                    let settings = additional_settings.as_ref().or(raw.as_ref());
+
+                    report.settings += grease
+                        .iter()
+                        .chain(settings.into_iter().flatten())
+                        .filter(|(id, _)| is_grease_value(*id))
+                        .count();
+                },
+
+                Action::OpenUniStream {
+                    stream_id,
+                    stream_type,
+                    ..
+                } if is_grease_value(*stream_type) =>
+                    report.uni_streams.push(*stream_id),
+
+                _ => (),
+            }
+        }
+
+        report.frame_streams.sort_unstable();
+        report.frame_streams.dedup();
+
+        report.reset_streams = report
+            .frame_streams
+            .iter()
+            .filter(|id| {
+                self.stream_map
+                    .stream(**id)
+                    .iter()
+                    .any(|f| matches!(f, H3iFrame::ResetStream(_)))
+            })
+            .copied()
+            .collect();
+
+        report.peer_error = self
+            .conn_close_details
+            .peer_error()
+            .filter(|e| !is_no_error(e))
+            .cloned();
+
+        report
+    }
+}
+
+/// The summaries of several connections that ran concurrently against the same
+/// server. See [`connect_many`].
+///
+/// Serializes as the list of per-connection results, in the order the
+/// connections were given, together with the number of connections that
+/// succeeded and failed.
+///
+/// [`connect_many`]: crate::client::sync_client::connect_many
+#[derive(Debug, Default)]
+pub struct MultiConnectionSummary {
+    /// The result of each connection, in order.
+    pub connections: Vec<Result<ConnectionSummary, ClientError>>,
+}
+
+impl MultiConnectionSummary {
+    /// The summaries of the connections that completed.
+    pub fn succeeded(&self) -> impl Iterator<Item = &ConnectionSummary> {
+        self.connections.iter().filter_map(|c| c.as_ref().ok())
+    }
+
+    /// The errors of the connections that failed, with their index.
+    pub fn failed(&self) -> impl Iterator<Item = (usize, &ClientError)> {
+        self.connections
+            .iter()
+            .enumerate()
+            .filter_map(|(i, c)| c.as_ref().err().map(|e| (i, e)))
+    }
+}
+
+impl Serialize for MultiConnectionSummary {
+    fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        #[derive(Serialize)]
+        #[serde(rename_all = "snake_case")]
+        enum ConnectionResult<'a> {
+            Summary(&'a ConnectionSummary),
+            Error(&'a ClientError),
+        }
+
+        let connections: Vec<ConnectionResult> = self
+            .connections
+            .iter()
+            .map(|c| match c {
+                Ok(summary) => ConnectionResult::Summary(summary),
+                Err(e) => ConnectionResult::Error(e),
+            })
+            .collect();
+
+        let mut state = s.serialize_struct("multi_connection_summary", 3)?;
+        state.serialize_field("connections", &connections)?;
+        state.serialize_field("succeeded", &self.succeeded().count())?;
+        state.serialize_field("failed", &self.failed().count())?;
+        state.end()
+    }
+}
+
+/// How the peer reacted to the GREASE sent on a connection. See
+/// [`ConnectionSummary::grease_report`].
+#[derive(Clone, Debug, Default)]
+pub struct GreaseReport {
+    /// Streams on which frames with reserved types were sent.
+    pub frame_streams: Vec<u64>,
+    /// The number of reserved settings identifiers sent.
+    pub settings: usize,
+    /// Unidirectional streams opened with a reserved stream type.
+    pub uni_streams: Vec<u64>,
+    /// Streams carrying frames with reserved types that the peer reset.
+    pub reset_streams: Vec<u64>,
+    /// The error the peer closed the connection with, if it wasn't a
+    /// NO_ERROR or H3_NO_ERROR.
+    pub peer_error: Option<ConnectionError>,
+}
+
+impl GreaseReport {
+    /// Whether any GREASE was sent.
+    pub fn sent_grease(&self) -> bool {
+        !self.frame_streams.is_empty() ||
+            self.settings > 0 ||
+            !self.uni_streams.is_empty()
+    }
+
+    /// Whether GREASE was sent and the peer closed the connection with an
+    /// error, or reset a stream that carried it.
+    ///
+    /// Neither is necessarily caused by the GREASE, so the connection's other
+    /// actions should be checked before blaming the peer.
+    pub fn peer_choked(&self) -> bool {
+        self.sent_grease() &&
+            (self.peer_error.is_some() || !self.reset_streams.is_empty())
+    }
+}
+
+impl Serialize for GreaseReport {
+    fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = s.serialize_struct("grease_report", 6)?;
+        state.serialize_field("frame_streams", &self.frame_streams)?;
+        state.serialize_field("settings", &self.settings)?;
+        state.serialize_field("uni_streams", &self.uni_streams)?;
+        state.serialize_field("reset_streams", &self.reset_streams)?;
+        state.serialize_field(
+            "peer_error",
+            &self.peer_error.as_ref().map(SerializableConnectionError),
+        )?;
+        state.serialize_field("peer_choked", &self.peer_choked())?;
         state.end()
     }
 }
 
+fn is_no_error(e: &ConnectionError) -> bool {
+    if e.is_app {
+        e.error_code == quiche::h3::WireErrorCode::NoError as u64
+    } else {
+        e.error_code == 0x0
+    }
+}
+
 /// A read-only aggregation of frames received over a connection, mapped to the
 /// stream ID over which they were received.
 ///
@@ -98,6 +549,54 @@ impl Serialize for ConnectionSummary {
 pub struct StreamMap {
     stream_frame_map: HashMap<u64, Vec<H3iFrame>>,
     close_trigger_frames: Option<CloseTriggerFrames>,
+    /// The stream IDs of all received DATA frames, in order of arrival.
+    #[serde(skip)]
+    data_frame_order: Vec<u64>,
+    /// The PRIORITY_UPDATE frames sent, with the number of DATA frames
+    /// received at the time.
+    #[serde(skip)]
+    priority_updates: Vec<(PriorityUpdate, usize)>,
+    /// The results of the [`Action::AssertBody`] actions, in order.
+    #[serde(skip)]
+    body_assertions: Vec<BodyAssertionResult>,
+    /// The reports of the [`Action::OpenConcurrentRequests`] actions, in
+    /// order.
+    #[serde(skip)]
+    concurrent_requests: Vec<ConcurrentRequestsReport>,
+    /// The statistics recorded by the [`Action::SnapshotStats`] actions, in
+    /// order.
+    #[serde(skip)]
+    stats_snapshots: Vec<StatsSnapshot>,
+    /// When each stream was first written to, and when DATA frames were
+    /// received on it.
+    #[serde(skip)]
+    stream_timings: HashMap<u64, StreamTiming>,
+    /// The HTTP/3 datagrams received, in order of arrival.
+    #[serde(skip)]
+    datagrams: Vec<H3Datagram>,
+    /// The error codes of the STOP_SENDING frames received, by stream ID.
+    #[serde(skip)]
+    stop_sending: BTreeMap<u64, u64>,
+    /// The IDs of the push streams received, by push ID.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    push_streams: BTreeMap<u64, u64>,
+    /// When the run started. Frame and action times are relative to it.
+    #[serde(skip)]
+    start: Option<Instant>,
+    /// When each frame was received, by stream ID, in the same order as in
+    /// `stream_frame_map`.
+    #[serde(skip)]
+    frame_times: HashMap<u64, Vec<Duration>>,
+    /// The actions executed, in order.
+    #[serde(skip)]
+    action_times: Vec<ActionTiming>,
+    /// Where to keep the payloads of DATA frames, if not in
+    /// `stream_frame_map`.
+    #[serde(skip)]
+    body_capture: Option<BodyCapture>,
+    /// The bodies captured, by stream ID.
+    #[serde(skip)]
+    captured_bodies: BTreeMap<u64, BodyCapturer>,
 }
 
 impl<T> From<T> for StreamMap
@@ -109,7 +608,7 @@ where
 
         Self {
             stream_frame_map,
-            close_trigger_frames: None,
+            ..Default::default()
         }
     }
 }
@@ -167,6 +666,70 @@ impl StreamMap {
             .unwrap_or_default()
     }
 
+    /// The IDs of the streams on which frames were received, in ascending
+    /// order.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use h3i::client::connection_summary::StreamMap;
+    /// use h3i::frame::EnrichedHeaders;
+    /// use h3i::frame::H3iFrame;
+    /// use quiche::h3::Header;
+    ///
+    /// let h = Header::new(b"hello", b"world");
+    /// let headers = H3iFrame::Headers(EnrichedHeaders::from(vec![h]));
+    ///
+    /// let stream_map: StreamMap =
+    ///     [(4, vec![headers.clone()]), (0, vec![headers])].into();
+    /// assert_eq!(stream_map.stream_ids(), vec![0, 4]);
+    /// ```
+    pub fn stream_ids(&self) -> Vec<u64> {
+        let mut ids: Vec<u64> = self.stream_frame_map.keys().copied().collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    /// The frames of a given type received, with the ID of the stream they
+    /// were received on, by stream ID. The type is matched case-insensitively
+    /// against the names used by [`Expectations`] and baselines, e.g. `DATA`,
+    /// `GOAWAY` or `RESET_STREAM`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use h3i::client::connection_summary::StreamMap;
+    /// use h3i::frame::H3iFrame;
+    /// use quiche::h3::frame::Frame;
+    ///
+    /// let data = H3iFrame::QuicheH3(Frame::Data {
+    ///     payload: b"hello".to_vec(),
+    /// });
+    /// let goaway = H3iFrame::QuicheH3(Frame::GoAway { id: 4 });
+    ///
+    /// let stream_map: StreamMap = [
+    ///     (4, vec![data.clone()]),
+    ///     (3, vec![goaway]),
+    ///     (0, vec![data.clone()]),
+    /// ]
+    /// .into();
+    ///
+    /// assert_eq!(stream_map.frames_of_type("data"), vec![
+    ///     (0, data.clone()),
+    ///     (4, data)
+    /// ]);
+    /// assert_eq!(stream_map.frames_of_type("GOAWAY").len(), 1);
+    /// ```
+    ///
+    /// [`Expectations`]: crate::client::expectations::Expectations
+    pub fn frames_of_type(&self, frame_type: &str) -> Vec<(u64, H3iFrame)> {
+        self.stream_ids()
+            .into_iter()
+            .flat_map(|id| self.stream(id).into_iter().map(move |f| (id, f)))
+            .filter(|(_, f)| frame_name(f).eq_ignore_ascii_case(frame_type))
+            .collect()
+    }
+
     /// Check if a provided [`H3iFrame`] was received, regardless of what stream
     /// it was received on.
     ///
@@ -267,57 +830,1306 @@ impl StreamMap {
             .collect()
     }
 
-    /// If all [`CloseTriggerFrame`]s were seen. If no triggers were expected,
-    /// this will return `false`.
-    pub fn all_close_trigger_frames_seen(&self) -> bool {
-        if let Some(triggers) = self.close_trigger_frames.as_ref() {
-            triggers.saw_all_trigger_frames()
-        } else {
-            false
-        }
+    /// The interim, informational (1xx), responses received on a given stream
+    /// before the final response, such as 103 Early Hints, in order.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use h3i::client::connection_summary::StreamMap;
+    /// use h3i::frame::EnrichedHeaders;
+    /// use h3i::frame::H3iFrame;
+    /// use quiche::h3::Header;
+    ///
+    /// let status = |s| EnrichedHeaders::from(vec![Header::new(b":status", s)]);
+    /// let early_hints = status(b"103");
+    ///
+    /// let stream_map: StreamMap = [(0, vec![
+    ///     H3iFrame::Headers(early_hints.clone()),
+    ///     H3iFrame::Headers(status(b"200")),
+    /// ])]
+    /// .into();
+    ///
+    /// assert_eq!(stream_map.informational_headers_on_stream(0), vec![
+    ///     early_hints
+    /// ]);
+    /// assert_eq!(stream_map.final_headers_on_stream(0), Some(status(b"200")));
+    /// ```
+    pub fn informational_headers_on_stream(
+        &self, stream_id: u64,
+    ) -> Vec<EnrichedHeaders> {
+        self.headers_on_stream(stream_id)
+            .into_iter()
+            .take_while(|h| h.is_informational())
+            .collect()
     }
 
-    /// The set of all [`CloseTriggerFrame`]s that were _not_ seen on the
-    /// connection. Returns `None` if
-    pub fn missing_close_trigger_frames(&self) -> Option<Vec<CloseTriggerFrame>> {
-        self.close_trigger_frames
-            .as_ref()
-            .map(|e| e.missing_triggers())
+    /// The HEADERS of the final response received on a given stream, i.e. the
+    /// first one that isn't informational (1xx).
+    pub fn final_headers_on_stream(
+        &self, stream_id: u64,
+    ) -> Option<EnrichedHeaders> {
+        self.headers_on_stream(stream_id)
+            .into_iter()
+            .find(|h| !h.is_informational())
     }
 
-    ///  Not `pub` as users aren't expected to build their own [`StreamMap`]s.
-    pub(crate) fn new(close_trigger_frames: Option<CloseTriggerFrames>) -> Self {
-        Self {
-            close_trigger_frames,
-            ..Default::default()
-        }
+    /// The status code of the final response received on a given stream, if
+    /// it is a valid one.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use h3i::client::connection_summary::StreamMap;
+    /// use h3i::frame::EnrichedHeaders;
+    /// use h3i::frame::H3iFrame;
+    /// use quiche::h3::Header;
+    ///
+    /// let status = |s| {
+    ///     H3iFrame::Headers(EnrichedHeaders::from(vec![Header::new(b":status", s)]))
+    /// };
+    ///
+    /// let stream_map: StreamMap = [
+    ///     (0, vec![status(b"103"), status(b"404")]),
+    ///     (4, vec![status(b"bogus")]),
+    /// ]
+    /// .into();
+    ///
+    /// assert_eq!(stream_map.status_code_on_stream(0), Some(404));
+    /// assert_eq!(stream_map.status_code_on_stream(4), None);
+    /// assert_eq!(stream_map.status_code_on_stream(8), None);
+    /// ```
+    pub fn status_code_on_stream(&self, stream_id: u64) -> Option<u16> {
+        let headers = self.final_headers_on_stream(stream_id)?;
+
+        std::str::from_utf8(headers.status_code()?)
+            .ok()?
+            .parse()
+            .ok()
     }
 
-    pub(crate) fn insert(&mut self, stream_id: u64, frame: H3iFrame) {
-        if let Some(expected) = self.close_trigger_frames.as_mut() {
-            expected.receive_frame(stream_id, &frame);
+    /// The informational responses received, by stream ID.
+    pub fn informational_headers(&self) -> BTreeMap<u64, Vec<EnrichedHeaders>> {
+        self.stream_ids()
+            .into_iter()
+            .map(|id| (id, self.informational_headers_on_stream(id)))
+            .filter(|(_, headers)| !headers.is_empty())
+            .collect()
+    }
+
+    /// See the HEADERS received on a given stream, except for the trailers.
+    /// See [`StreamMap::trailers_on_stream()`].
+    pub fn response_headers_on_stream(
+        &self, stream_id: u64,
+    ) -> Vec<EnrichedHeaders> {
+        let mut headers = self.headers_on_stream(stream_id);
+
+        if let Some(trailers) = trailers_index(&headers) {
+            headers.truncate(trailers);
         }
 
-        self.stream_frame_map
-            .entry(stream_id)
-            .or_default()
-            .push(frame);
+        headers
     }
 
-    /// Close a [`quiche::Connection`] with the CONNECTION_CLOSE frame specified
-    /// by [`CloseTriggerFrames`]. If no [`CloseTriggerFrames`] exist, this is a
-    /// no-op.
-    pub(crate) fn close_due_to_trigger_frames(
-        &self, qconn: &mut quiche::Connection,
-    ) {
-        if let Some(ConnectionError {
-            is_app,
-            error_code,
-            reason,
-        }) = self.close_trigger_frames.as_ref().map(|tf| &tf.close_with)
-        {
-            let _ = qconn.close(*is_app, *error_code, reason);
-        }
+    /// The trailers received on a given stream, i.e. the HEADERS frame that
+    /// followed the final response's. Informational (1xx) responses precede
+    /// the final response, so they are never mistaken for trailers.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use h3i::client::connection_summary::StreamMap;
+    /// use h3i::frame::EnrichedHeaders;
+    /// use h3i::frame::H3iFrame;
+    /// use quiche::h3::Header;
+    ///
+    /// let headers = |h| H3iFrame::Headers(EnrichedHeaders::from(vec![h]));
+    /// let response = headers(Header::new(b":status", b"200"));
+    /// let trailers = headers(Header::new(b"grpc-status", b"0"));
+    ///
+    /// let stream_map: StreamMap = [
+    ///     (0, vec![response.clone(), trailers.clone()]),
+    ///     (4, vec![response]),
+    /// ]
+    /// .into();
+    ///
+    /// assert_eq!(
+    ///     stream_map.trailers_on_stream(0).map(H3iFrame::Headers),
+    ///     Some(trailers)
+    /// );
+    /// assert!(stream_map.trailers_on_stream(4).is_none());
+    /// ```
+    pub fn trailers_on_stream(&self, stream_id: u64) -> Option<EnrichedHeaders> {
+        let headers = self.headers_on_stream(stream_id);
+        let trailers = trailers_index(&headers)?;
+
+        headers.into_iter().nth(trailers)
+    }
+
+    /// The trailers received, by stream ID.
+    pub fn trailers(&self) -> BTreeMap<u64, EnrichedHeaders> {
+        self.stream_ids()
+            .into_iter()
+            .filter_map(|id| Some((id, self.trailers_on_stream(id)?)))
+            .collect()
+    }
+
+    /// The total number of bytes received in DATA frame payloads on a given
+    /// stream, including the ones captured outside of the [`StreamMap`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use h3i::client::connection_summary::StreamMap;
+    /// use h3i::frame::H3iFrame;
+    ///
+    /// let data = H3iFrame::QuicheH3(quiche::h3::frame::Frame::Data {
+    ///     payload: b"hello world".to_vec(),
+    /// });
+    ///
+    /// let stream_map: StreamMap = [(0, vec![data.clone(), data])].into();
+    /// assert_eq!(stream_map.data_bytes_on_stream(0), 22);
+    /// assert_eq!(stream_map.data_bytes_on_stream(4), 0);
+    /// ```
+    pub fn data_bytes_on_stream(&self, stream_id: u64) -> u64 {
+        let captured = self
+            .captured_bodies
+            .get(&stream_id)
+            .map_or(0, |capturer| capturer.len);
+
+        self.stream_frame_map
+            .get(&stream_id)
+            .into_iter()
+            .flatten()
+            .map(|frame| match frame {
+                H3iFrame::QuicheH3(QFrame::Data { payload }) =>
+                    payload.len() as u64,
+
+                _ => 0,
+            })
+            .sum::<u64>() +
+            captured
+    }
+
+    /// The HTTP/3 datagrams received on the connection, in order of arrival.
+    pub fn datagrams(&self) -> &[H3Datagram] {
+        &self.datagrams
+    }
+
+    /// The payloads of the HTTP/3 datagrams received for the request on a
+    /// given stream, in order of arrival.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use h3i::client::connection_summary::StreamMap;
+    ///
+    /// let mut stream_map = StreamMap::default();
+    /// stream_map.insert_datagram(&[0x01, b'h', b'i']);
+    /// stream_map.insert_datagram(&[0x02, b'n', b'o']);
+    ///
+    /// assert_eq!(stream_map.datagrams_on_stream(4), vec![b"hi"]);
+    /// assert!(stream_map.datagrams_on_stream(0).is_empty());
+    /// ```
+    pub fn datagrams_on_stream(&self, stream_id: u64) -> Vec<&[u8]> {
+        self.datagrams
+            .iter()
+            .filter(|d| d.quarter_stream_id == Some(stream_id / 4))
+            .map(|d| d.payload.as_slice())
+            .collect()
+    }
+
+    /// Records a received QUIC DATAGRAM frame payload as an HTTP/3 datagram.
+    pub fn insert_datagram(&mut self, dgram: &[u8]) {
+        let mut b = octets::Octets::with_slice(dgram);
+
+        let datagram = match b.get_varint() {
+            Ok(quarter_stream_id) => H3Datagram {
+                quarter_stream_id: Some(quarter_stream_id),
+                payload: b.to_vec(),
+            },
+
+            Err(_) => H3Datagram {
+                quarter_stream_id: None,
+                payload: dgram.to_vec(),
+            },
+        };
+
+        self.datagrams.push(datagram);
+    }
+
+    /// The error code of the STOP_SENDING frame received on a given stream, if
+    /// any.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use h3i::client::connection_summary::StreamMap;
+    ///
+    /// let mut stream_map = StreamMap::default();
+    /// stream_map.insert_stop_sending(0, 0x10c);
+    ///
+    /// assert_eq!(stream_map.stop_sending_on_stream(0), Some(0x10c));
+    /// assert_eq!(stream_map.stop_sending_on_stream(4), None);
+    /// ```
+    pub fn stop_sending_on_stream(&self, stream_id: u64) -> Option<u64> {
+        self.stop_sending.get(&stream_id).copied()
+    }
+
+    /// Records a STOP_SENDING frame received on a stream.
+    pub fn insert_stop_sending(&mut self, stream_id: u64, error_code: u64) {
+        if let Some(expected) = self.close_trigger_frames.as_mut() {
+            expected.receive_stop_sending(stream_id, error_code);
+        }
+
+        self.stop_sending.insert(stream_id, error_code);
+    }
+
+    /// The ID of the push stream received for the push ID `push_id`, if any.
+    ///
+    /// The frames of the push response can be retrieved with
+    /// [`StreamMap::stream`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use h3i::client::connection_summary::StreamMap;
+    ///
+    /// let mut stream_map = StreamMap::default();
+    /// stream_map.insert_push_stream(15, 0);
+    ///
+    /// assert_eq!(stream_map.push_stream(0), Some(15));
+    /// assert_eq!(stream_map.push_stream(1), None);
+    /// ```
+    pub fn push_stream(&self, push_id: u64) -> Option<u64> {
+        self.push_streams.get(&push_id).copied()
+    }
+
+    /// Records a push stream received for the push ID `push_id`.
+    pub fn insert_push_stream(&mut self, stream_id: u64, push_id: u64) {
+        self.push_streams.insert(push_id, stream_id);
+    }
+
+    /// The PUSH_PROMISE frames received, by request stream ID and in order of
+    /// arrival, with the push stream fulfilling each of them, if any.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use h3i::client::connection_summary::StreamMap;
+    /// use h3i::frame::H3iFrame;
+    /// use quiche::h3::frame::Frame;
+    /// use quiche::h3::Header;
+    ///
+    /// let headers = vec![Header::new(b":path", b"/style.css")];
+    /// let mut header_block = vec![0; 64];
+    /// let len = quiche::h3::qpack::Encoder::new()
+    ///     .encode(&headers, &mut header_block)
+    ///     .unwrap();
+    /// header_block.truncate(len);
+    /// let push_promise = H3iFrame::QuicheH3(Frame::PushPromise {
+    ///     push_id: 0,
+    ///     header_block,
+    /// });
+    ///
+    /// let mut stream_map: StreamMap = [(0, vec![push_promise])].into();
+    /// stream_map.insert_push_stream(15, 0);
+    ///
+    /// let promises = stream_map.push_promises();
+    /// assert_eq!(promises[0].headers, headers);
+    /// assert_eq!(promises[0].push_stream_id, Some(15));
+    /// ```
+    pub fn push_promises(&self) -> Vec<PushPromise> {
+        let mut stream_ids: Vec<u64> =
+            self.stream_frame_map.keys().copied().collect();
+        stream_ids.sort_unstable();
+
+        let mut promises = vec![];
+
+        for stream_id in stream_ids {
+            for frame in &self.stream_frame_map[&stream_id] {
+                let H3iFrame::QuicheH3(QFrame::PushPromise {
+                    push_id,
+                    header_block,
+                }) = frame
+                else {
+                    continue;
+                };
+
+                let headers = quiche::h3::qpack::Decoder::new()
+                    .decode(header_block, u64::MAX)
+                    .unwrap_or_default();
+
+                promises.push(PushPromise {
+                    stream_id,
+                    push_id: *push_id,
+                    headers,
+                    push_stream_id: self.push_stream(*push_id),
+                });
+            }
+        }
+
+        promises
+    }
+
+    /// The body received on a given stream, i.e. the concatenation of its DATA
+    /// frame payloads. Bodies captured with a [`BodyCapture`] aren't kept in
+    /// memory, see [`StreamMap::captured_body()`] instead.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use h3i::client::connection_summary::StreamMap;
+    /// use h3i::frame::H3iFrame;
+    ///
+    /// let data = |payload: &[u8]| {
+    ///     H3iFrame::QuicheH3(quiche::h3::frame::Frame::Data {
+    ///         payload: payload.to_vec(),
+    ///     })
+    /// };
+    ///
+    /// let stream_map: StreamMap =
+    ///     [(0, vec![data(b"hello "), data(b"world")])].into();
+    /// assert_eq!(stream_map.body_on_stream(0), b"hello world");
+    /// assert!(stream_map.body_on_stream(4).is_empty());
+    /// ```
+    pub fn body_on_stream(&self, stream_id: u64) -> Vec<u8> {
+        self.stream_frame_map
+            .get(&stream_id)
+            .into_iter()
+            .flatten()
+            .filter_map(|frame| match frame {
+                H3iFrame::QuicheH3(QFrame::Data { payload }) =>
+                    Some(payload.as_slice()),
+
+                _ => None,
+            })
+            .flatten()
+            .copied()
+            .collect()
+    }
+
+    /// The body captured on a given stream, if the connection was configured
+    /// with a [`BodyCapture`] and DATA frames were received on the stream.
+    pub fn captured_body(&self, stream_id: u64) -> Option<CapturedBody> {
+        self.captured_bodies
+            .get(&stream_id)
+            .map(BodyCapturer::captured)
+    }
+
+    /// All the bodies captured, by stream ID.
+    pub fn captured_bodies(&self) -> BTreeMap<u64, CapturedBody> {
+        self.captured_bodies
+            .iter()
+            .map(|(stream_id, capturer)| (*stream_id, capturer.captured()))
+            .collect()
+    }
+
+    /// The results of the [`Action::AssertBody`] actions executed on the
+    /// connection, in order.
+    pub fn body_assertions(&self) -> &[BodyAssertionResult] {
+        &self.body_assertions
+    }
+
+    /// The reports of the [`Action::OpenConcurrentRequests`] actions executed
+    /// on the connection, in order.
+    pub fn concurrent_requests(&self) -> &[ConcurrentRequestsReport] {
+        &self.concurrent_requests
+    }
+
+    /// The statistics recorded by the [`Action::SnapshotStats`] actions
+    /// executed on the connection, in order.
+    pub fn stats_snapshots(&self) -> &[StatsSnapshot] {
+        &self.stats_snapshots
+    }
+
+    /// Returns the snapshot labelled `label`, the latest one if several share
+    /// it.
+    pub fn stats_snapshot(&self, label: &str) -> Option<&StatsSnapshot> {
+        self.stats_snapshots.iter().rev().find(|s| s.label == label)
+    }
+
+    /// If all [`CloseTriggerFrame`]s were seen. If no triggers were expected,
+    /// this will return `false`.
+    pub fn all_close_trigger_frames_seen(&self) -> bool {
+        if let Some(triggers) = self.close_trigger_frames.as_ref() {
+            triggers.saw_all_trigger_frames()
+        } else {
+            false
+        }
+    }
+
+    /// The set of all [`CloseTriggerFrame`]s that were _not_ seen on the
+    /// connection. Returns `None` if
+    pub fn missing_close_trigger_frames(&self) -> Option<Vec<CloseTriggerFrame>> {
+        self.close_trigger_frames
+            .as_ref()
+            .map(|e| e.missing_triggers())
+    }
+
+    /// The [`CloseTriggerEvent`]s that were _not_ seen on the connection.
+    /// Returns `None` if there are no close triggers.
+    pub fn missing_close_trigger_events(&self) -> Option<Vec<CloseTriggerEvent>> {
+        self.close_trigger_frames
+            .as_ref()
+            .map(|e| e.missing_events().to_vec())
+    }
+
+    /// Records that the peer closed the connection with `error`.
+    pub(crate) fn peer_closed(&mut self, error: &ConnectionError) {
+        if let Some(expected) = self.close_trigger_frames.as_mut() {
+            expected.receive_peer_error(error);
+        }
+    }
+
+    /// The PRIORITY_UPDATE frames sent on the connection, in order, along
+    /// with how the DATA frames received around them were scheduled.
+    ///
+    /// Only DATA frames received on request streams are accounted for.
+    pub fn priority_updates(&self) -> Vec<PriorityUpdateOutcome> {
+        let mut outcomes = Vec::with_capacity(self.priority_updates.len());
+
+        for (i, (update, sent_at)) in self.priority_updates.iter().enumerate() {
+            let prev = i
+                .checked_sub(1)
+                .map(|prev| self.priority_updates[prev].1)
+                .unwrap_or(0);
+
+            let next = self
+                .priority_updates
+                .get(i + 1)
+                .map(|next| next.1)
+                .unwrap_or(self.data_frame_order.len());
+
+            let before = &self.data_frame_order[prev..*sent_at];
+            let after = &self.data_frame_order[*sent_at..next];
+
+            let prioritized = |frames: &[u64]| {
+                if update.push {
+                    return 0;
+                }
+
+                frames
+                    .iter()
+                    .filter(|id| **id == update.prioritized_element_id)
+                    .count()
+            };
+
+            outcomes.push(PriorityUpdateOutcome {
+                update: update.clone(),
+                data_frames_before: before.len(),
+                prioritized_data_frames_before: prioritized(before),
+                data_frames_after: after.len(),
+                prioritized_data_frames_after: prioritized(after),
+            });
+        }
+
+        outcomes
+    }
+
+    /// When each frame received on `stream_id` was received, relative to the
+    /// start of the run, in the same order as [`Self::stream`].
+    ///
+    /// Empty for [`StreamMap`]s that weren't filled in by a client.
+    pub fn frame_times_on_stream(&self, stream_id: u64) -> &[Duration] {
+        self.frame_times
+            .get(&stream_id)
+            .map(|v| v.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Records the actions that are accounted for in the [`StreamMap`], right
+    /// before they are executed.
+    pub(crate) fn record_action(&mut self, action: &Action) {
+        match action {
+            Action::SendFrame { stream_id, .. } |
+            Action::SendHeadersFrame { stream_id, .. } |
+            Action::StreamBytes { stream_id, .. } =>
+                self.stream_written(*stream_id),
+
+            _ => (),
+        }
+
+        let update = match action {
+            Action::SendFrame {
+                frame:
+                    QFrame::PriorityUpdateRequest {
+                        prioritized_element_id,
+                        priority_field_value,
+                    },
+                ..
+            } => PriorityUpdate {
+                prioritized_element_id: *prioritized_element_id,
+                push: false,
+                priority_field_value: priority_field_value.clone(),
+            },
+
+            Action::SendFrame {
+                frame:
+                    QFrame::PriorityUpdatePush {
+                        prioritized_element_id,
+                        priority_field_value,
+                    },
+                ..
+            } => PriorityUpdate {
+                prioritized_element_id: *prioritized_element_id,
+                push: true,
+                priority_field_value: priority_field_value.clone(),
+            },
+
+            Action::AssertBody {
+                stream_id,
+                assertion,
+            } => {
+                self.assert_body(*stream_id, assertion);
+                return;
+            },
+
+            _ => return,
+        };
+
+        self.priority_updates
+            .push((update, self.data_frame_order.len()));
+    }
+
+    fn assert_body(&mut self, stream_id: u64, assertion: &BodyAssertion) {
+        let (body_len, failure) = match self.captured_body(stream_id) {
+            Some(captured) =>
+                (captured.len as usize, captured.check(assertion).err()),
+
+            None => {
+                let body = self.body_on_stream(stream_id);
+
+                (body.len(), assertion.check(&body).err())
+            },
+        };
+
+        match &failure {
+            Some(e) => log::error!(
+                "body assertion {:?} on stream {} failed: {}",
+                assertion,
+                stream_id,
+                e
+            ),
+
+            None => log::info!(
+                "body assertion {:?} on stream {} passed",
+                assertion,
+                stream_id
+            ),
+        }
+
+        self.body_assertions.push(BodyAssertionResult {
+            stream_id,
+            assertion: assertion.clone(),
+            body_len,
+            failure,
+        });
+    }
+
+    /// Records the connection's statistics for an [`Action::SnapshotStats`]
+    /// action.
+    pub(crate) fn snapshot_stats(
+        &mut self, label: &str, stats: Stats, path_stats: Vec<PathStats>,
+    ) {
+        let elapsed = self.elapsed();
+
+        log::info!("stats snapshot {:?} at {:?}: {:?}", label, elapsed, stats);
+
+        self.stats_snapshots.push(StatsSnapshot {
+            label: label.to_string(),
+            elapsed,
+            stats,
+            path_stats,
+        });
+    }
+
+    /// Starts reporting on an [`Action::OpenConcurrentRequests`] action, given
+    /// the peer's current bidirectional stream limit.
+    pub(crate) fn start_concurrent_requests(
+        &mut self, first_stream_id: u64, count: u64, max_streams: u64,
+    ) {
+        self.concurrent_requests.push(ConcurrentRequestsReport {
+            first_stream_id,
+            count,
+            opened: vec![],
+            max_streams: vec![StreamsGrant {
+                max_streams,
+                elapsed: Duration::ZERO,
+            }],
+            refused: vec![],
+            start: Instant::now(),
+        });
+    }
+
+    /// Records that a request of the latest [`Action::OpenConcurrentRequests`]
+    /// action was opened.
+    pub(crate) fn concurrent_request_opened(&mut self, stream_id: u64) {
+        self.stream_written(stream_id);
+
+        if let Some(report) = self.concurrent_requests.last_mut() {
+            let elapsed = report.start.elapsed();
+
+            report.opened.push(OpenedRequest { stream_id, elapsed });
+        }
+    }
+
+    /// Records the peer's bidirectional stream limit, if it changed since the
+    /// latest [`Action::OpenConcurrentRequests`] action started.
+    pub(crate) fn update_peer_max_streams(&mut self, max_streams: u64) {
+        let Some(report) = self.concurrent_requests.last_mut() else {
+            return;
+        };
+
+        if report.max_streams.last().map(|g| g.max_streams) == Some(max_streams) {
+            return;
+        }
+
+        let elapsed = report.start.elapsed();
+
+        log::info!("peer max_streams_bidi={} after {:?}", max_streams, elapsed);
+
+        report.max_streams.push(StreamsGrant {
+            max_streams,
+            elapsed,
+        });
+    }
+
+    /// Records the first time h3i wrote to a stream.
+    fn stream_written(&mut self, stream_id: u64) {
+        self.stream_timings
+            .entry(stream_id)
+            .or_default()
+            .first_sent
+            .get_or_insert_with(Instant::now);
+    }
+
+    ///  Not `pub` as users aren't expected to build their own [`StreamMap`]s.
+    pub(crate) fn new(close_trigger_frames: Option<CloseTriggerFrames>) -> Self {
+        Self {
+            close_trigger_frames,
+            start: Some(Instant::now()),
+            ..Default::default()
+        }
+    }
+
+    /// Keeps the payloads of the DATA frames received from now on out of the
+    /// [`StreamMap`].
+    pub(crate) fn capture_bodies(&mut self, body_capture: BodyCapture) {
+        self.body_capture = Some(body_capture);
+    }
+
+    /// The time since the start of the run.
+    fn elapsed(&mut self) -> Duration {
+        self.start.get_or_insert_with(Instant::now).elapsed()
+    }
+
+    /// Records that `action` was executed.
+    pub(crate) fn action_executed(&mut self, action: &Action) {
+        let executed = self.elapsed();
+
+        self.action_times.push(ActionTiming {
+            name: action.name(),
+            stream_id: action.stream_id(),
+            executed,
+        });
+    }
+
+    pub(crate) fn insert(&mut self, stream_id: u64, frame: H3iFrame) {
+        if let Some(expected) = self.close_trigger_frames.as_mut() {
+            expected.receive_frame(stream_id, &frame);
+        }
+
+        let received = self.elapsed();
+        self.frame_times
+            .entry(stream_id)
+            .or_default()
+            .push(received);
+
+        if matches!(frame, H3iFrame::QuicheH3(QFrame::Data { .. })) {
+            self.data_frame_order.push(stream_id);
+
+            let now = Instant::now();
+            let timing = self.stream_timings.entry(stream_id).or_default();
+
+            timing.first_data.get_or_insert(now);
+            timing.last_data = Some(now);
+        }
+
+        if let H3iFrame::ResetStream(reset) = &frame {
+            for report in self.concurrent_requests.iter_mut() {
+                if report.opened.iter().any(|r| r.stream_id == stream_id) {
+                    let elapsed = report.start.elapsed();
+
+                    report.refused.push(RefusedRequest {
+                        stream_id,
+                        error_code: reset.error_code,
+                        elapsed,
+                    });
+                }
+            }
+        }
+
+        let frame = match (frame, &self.body_capture) {
+            (H3iFrame::QuicheH3(QFrame::Data { payload }), Some(capture)) => {
+                self.captured_bodies
+                    .entry(stream_id)
+                    .or_insert_with(|| BodyCapturer::new(capture, stream_id))
+                    .write(&payload);
+
+                H3iFrame::QuicheH3(QFrame::Data { payload: vec![] })
+            },
+
+            (frame, _) => frame,
+        };
+
+        self.stream_frame_map
+            .entry(stream_id)
+            .or_default()
+            .push(frame);
+    }
+
+    /// Close a [`quiche::Connection`] with the CONNECTION_CLOSE frame specified
+    /// by [`CloseTriggerFrames`]. If no [`CloseTriggerFrames`] exist, this is a
+    /// no-op.
+    pub(crate) fn close_due_to_trigger_frames(
+        &self, qconn: &mut quiche::Connection,
+    ) {
+        if let Some(ConnectionError {
+            is_app,
+            error_code,
+            reason,
+        }) = self.close_trigger_frames.as_ref().map(|tf| &tf.close_with)
+        {
+            let _ = qconn.close(*is_app, *error_code, reason);
+        }
+    }
+}
+
+/// Hashes a body and writes it to a file, see [`BodyCapture`].
+#[derive(Clone)]
+struct BodyCapturer {
+    path: Option<String>,
+    file: Option<Arc<File>>,
+    max_size: u64,
+    len: u64,
+    written: u64,
+    digest: ring::digest::Context,
+}
+
+impl BodyCapturer {
+    fn new(capture: &BodyCapture, stream_id: u64) -> Self {
+        let path = capture.dir.as_ref().map(|dir| {
+            Path::new(dir)
+                .join(format!("stream-{stream_id}.body"))
+                .to_string_lossy()
+                .into_owned()
+        });
+
+        let file = path.as_ref().and_then(|path| match File::create(path) {
+            Ok(file) => Some(Arc::new(file)),
+
+            Err(e) => {
+                log::error!("failed to create body file {}: {}", path, e);
+                None
+            },
+        });
+
+        Self {
+            path: path.filter(|_| file.is_some()),
+            file,
+            max_size: capture.max_size.unwrap_or(u64::MAX),
+            len: 0,
+            written: 0,
+            digest: ring::digest::Context::new(&ring::digest::SHA256),
+        }
+    }
+
+    fn write(&mut self, payload: &[u8]) {
+        self.digest.update(payload);
+        self.len += payload.len() as u64;
+
+        let Some(file) = &self.file else {
+            return;
+        };
+
+        let room = self.max_size.saturating_sub(self.written);
+        let n = cmp::min(room, payload.len() as u64) as usize;
+
+        if n == 0 {
+            return;
+        }
+
+        match file.as_ref().write_all(&payload[..n]) {
+            Ok(()) => self.written += n as u64,
+
+            Err(e) => {
+                log::error!("failed to write body file {:?}: {}", self.path, e);
+                self.file = None;
+            },
+        }
+    }
+
+    fn captured(&self) -> CapturedBody {
+        let digest = self.digest.clone().finish();
+
+        CapturedBody {
+            path: self.path.clone(),
+            len: self.len,
+            written: self.written,
+            sha256: digest.as_ref().iter().map(|b| format!("{b:02x}")).collect(),
+        }
+    }
+}
+
+impl fmt::Debug for BodyCapturer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BodyCapturer")
+            .field("path", &self.path)
+            .field("len", &self.len)
+            .field("written", &self.written)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A body captured outside of the [`StreamMap`], see [`BodyCapture`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct CapturedBody {
+    /// The file the body was written to, if any.
+    pub path: Option<String>,
+    /// The length of the body.
+    pub len: u64,
+    /// How many bytes of the body were written to `path`.
+    pub written: u64,
+    /// The SHA-256 digest of the whole body, as a hex string.
+    pub sha256: String,
+}
+
+impl CapturedBody {
+    /// Whether the whole body was written to `path`.
+    pub fn is_complete(&self) -> bool {
+        self.path.is_some() && self.written == self.len
+    }
+
+    /// Checks the body against `assertion`. Assertions on the body's contents
+    /// read it back from `path`, and fail if it wasn't written in full.
+    pub fn check(&self, assertion: &BodyAssertion) -> Result<(), String> {
+        match assertion {
+            BodyAssertion::Length(expected) => {
+                if self.len == *expected {
+                    return Ok(());
+                }
+
+                Err(format!(
+                    "body is {} bytes long, expected {expected}",
+                    self.len
+                ))
+            },
+
+            BodyAssertion::Sha256(expected) => {
+                if self.sha256.eq_ignore_ascii_case(expected) {
+                    return Ok(());
+                }
+
+                Err(format!(
+                    "body SHA-256 is {}, expected {expected}",
+                    self.sha256
+                ))
+            },
+
+            _ => match &self.path {
+                Some(path) if self.is_complete() => {
+                    let body = std::fs::read(path)
+                        .map_err(|e| format!("failed to read {path}: {e}"))?;
+
+                    assertion.check(&body)
+                },
+
+                _ => Err(format!(
+                    "only {} of the {} body bytes were captured",
+                    self.written, self.len
+                )),
+            },
+        }
+    }
+}
+
+/// An action executed by the client, and when it was.
+#[derive(Clone, Copy, Debug)]
+struct ActionTiming {
+    name: &'static str,
+    stream_id: Option<u64>,
+    executed: Duration,
+}
+
+/// When a stream was first written to, and when DATA frames were received on
+/// it.
+#[derive(Clone, Copy, Debug, Default)]
+struct StreamTiming {
+    first_sent: Option<Instant>,
+    first_data: Option<Instant>,
+    last_data: Option<Instant>,
+}
+
+/// When frames were received and actions executed during a run, as computed
+/// by [`ConnectionSummary::timing_report()`]. All times are relative to the
+/// start of the run.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct TimingReport {
+    /// The streams on which frames were received, ordered by stream ID.
+    pub streams: Vec<StreamTimingReport>,
+    /// The actions executed, in order.
+    pub actions: Vec<ActionTimingReport>,
+}
+
+impl TimingReport {
+    pub fn is_empty(&self) -> bool {
+        self.streams.is_empty() && self.actions.is_empty()
+    }
+}
+
+/// When frames were received on a single stream.
+#[serde_as]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct StreamTimingReport {
+    pub stream_id: u64,
+    /// When h3i first wrote to the stream. `None` for streams opened by the
+    /// peer.
+    #[serde_as(as = "Option<serde_with::DurationMilliSecondsWithFrac<f64>>")]
+    pub first_sent: Option<Duration>,
+    /// When the first HEADERS frame was received.
+    #[serde_as(as = "Option<serde_with::DurationMilliSecondsWithFrac<f64>>")]
+    pub first_headers: Option<Duration>,
+    /// When the first DATA frame was received.
+    #[serde_as(as = "Option<serde_with::DurationMilliSecondsWithFrac<f64>>")]
+    pub first_data: Option<Duration>,
+    /// When the last frame was received.
+    #[serde_as(as = "Option<serde_with::DurationMilliSecondsWithFrac<f64>>")]
+    pub last_frame: Option<Duration>,
+    /// The time from h3i first writing to the stream until the first HEADERS
+    /// frame was received.
+    #[serde_as(as = "Option<serde_with::DurationMilliSecondsWithFrac<f64>>")]
+    pub time_to_first_headers: Option<Duration>,
+}
+
+/// When a single action was executed.
+#[serde_as]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct ActionTimingReport {
+    /// The position of the action among the executed actions, with
+    /// [`Action::Repeat`]s expanded.
+    pub index: usize,
+    /// The name of the action, see [`Action::name()`].
+    pub action: &'static str,
+    /// The stream the action applies to, if any.
+    pub stream_id: Option<u64>,
+    #[serde_as(as = "serde_with::DurationMilliSecondsWithFrac<f64>")]
+    pub executed: Duration,
+    /// The time until the first frame received on `stream_id` after the
+    /// action was executed.
+    #[serde_as(as = "Option<serde_with::DurationMilliSecondsWithFrac<f64>>")]
+    pub latency: Option<Duration>,
+}
+
+/// Goodput, wire overhead and retransmissions of a run, as computed by
+/// [`ConnectionSummary::throughput_report()`].
+///
+/// Goodput only accounts for the payload of the DATA frames received.
+#[serde_as]
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct ThroughputReport {
+    /// The streams on which DATA frames were received, ordered by stream ID.
+    pub streams: Vec<StreamThroughput>,
+    /// The total DATA frame payload received, in bytes.
+    pub goodput_bytes: u64,
+    /// The time from the first stream being written to, until the last DATA
+    /// frame was received.
+    #[serde_as(as = "serde_with::DurationMilliSecondsWithFrac<f64>")]
+    pub elapsed: Duration,
+    /// The aggregate goodput, in bits per second.
+    pub goodput_bps: Option<f64>,
+    /// The number of bytes received on the wire, i.e. the connection's
+    /// `recv_bytes`.
+    pub wire_bytes_received: Option<u64>,
+    /// The fraction of the bytes received on the wire that wasn't DATA frame
+    /// payload: QUIC and HTTP/3 framing, headers, control streams and so on.
+    pub wire_overhead: Option<f64>,
+    /// The fraction of the packets sent that were retransmitted.
+    pub retransmission_ratio: Option<f64>,
+    /// The fraction of the bytes sent that were retransmitted stream data.
+    pub retransmitted_bytes_ratio: Option<f64>,
+}
+
+/// The goodput of a single stream.
+#[serde_as]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct StreamThroughput {
+    pub stream_id: u64,
+    /// The DATA frame payload received, in bytes.
+    pub bytes: u64,
+    #[serde_as(as = "serde_with::DurationMilliSecondsWithFrac<f64>")]
+    pub elapsed: Duration,
+    /// The goodput, in bits per second. `None` if all the data was received at
+    /// once.
+    pub goodput_bps: Option<f64>,
+}
+
+fn bits_per_second(bytes: u64, elapsed: Duration) -> Option<f64> {
+    if elapsed.is_zero() {
+        return None;
+    }
+
+    Some(bytes as f64 * 8.0 / elapsed.as_secs_f64())
+}
+
+fn ratio(num: u64, den: u64) -> Option<f64> {
+    if den == 0 {
+        return None;
+    }
+
+    Some(num as f64 / den as f64)
+}
+
+/// An HTTP/3 datagram received from the peer.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct H3Datagram {
+    /// The quarter stream ID the datagram is associated with, or `None` if the
+    /// datagram was too short to contain one.
+    pub quarter_stream_id: Option<u64>,
+    /// The payload following the quarter stream ID, or the whole datagram if
+    /// it couldn't be parsed.
+    pub payload: Vec<u8>,
+}
+
+impl Serialize for H3Datagram {
+    fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = s.serialize_struct("h3_datagram", 3)?;
+        let max = cmp::min(self.payload.len(), MAX_SERIALIZED_BUFFER_LEN);
+        state.serialize_field("quarter_stream_id", &self.quarter_stream_id)?;
+        state.serialize_field("payload_len", &self.payload.len())?;
+        state.serialize_field(
+            "payload",
+            &qlog::HexSlice::maybe_string(Some(&self.payload[..max])),
+        )?;
+        state.end()
+    }
+}
+
+/// The result of an [`Action::AssertBody`] action.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct BodyAssertionResult {
+    /// The stream whose body was checked.
+    pub stream_id: u64,
+    /// The assertion.
+    pub assertion: BodyAssertion,
+    /// The length of the body received on the stream when the assertion was
+    /// evaluated.
+    pub body_len: usize,
+    /// Why the assertion failed, if it did.
+    pub failure: Option<String>,
+}
+
+impl BodyAssertionResult {
+    /// Whether the assertion passed.
+    pub fn passed(&self) -> bool {
+        self.failure.is_none()
+    }
+}
+
+/// How the requests opened by an [`Action::OpenConcurrentRequests`] action
+/// fared.
+///
+/// All times are relative to when the action was executed.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ConcurrentRequestsReport {
+    /// The stream ID of the first request.
+    pub first_stream_id: u64,
+    /// The number of requests to open.
+    pub count: u64,
+    /// The requests that were opened, in order.
+    pub opened: Vec<OpenedRequest>,
+    /// The peer's bidirectional stream limit when the action was executed, and
+    /// every time it changed afterwards.
+    pub max_streams: Vec<StreamsGrant>,
+    /// The requests the peer reset, e.g. with H3_REQUEST_REJECTED.
+    pub refused: Vec<RefusedRequest>,
+    #[serde(skip)]
+    start: Instant,
+}
+
+impl ConcurrentRequestsReport {
+    /// The number of requests that couldn't be opened, for lack of stream
+    /// credit.
+    pub fn pending(&self) -> u64 {
+        self.count.saturating_sub(self.opened.len() as u64)
+    }
+}
+
+/// The position of the trailers among the HEADERS frames received on a
+/// stream, i.e. of the frame following the first non-informational one.
+fn trailers_index(headers: &[EnrichedHeaders]) -> Option<usize> {
+    let final_response = headers.iter().position(|h| !h.is_informational())?;
+
+    (final_response + 1 < headers.len()).then_some(final_response + 1)
+}
+
+/// The statistics of the connection when an [`Action::SnapshotStats`] action
+/// was executed. Comparing consecutive snapshots shows how the actions between
+/// them affected loss, RTT and the bytes on the wire.
+#[derive(Clone, Debug)]
+pub struct StatsSnapshot {
+    /// The label of the action.
+    pub label: String,
+    /// When the action was executed, relative to the start of the run.
+    pub elapsed: Duration,
+    /// The connection's statistics.
+    pub stats: Stats,
+    /// The statistics of all paths of the connection.
+    pub path_stats: Vec<PathStats>,
+}
+
+impl Serialize for StatsSnapshot {
+    fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = s.serialize_struct("stats_snapshot", 4)?;
+        state.serialize_field("label", &self.label)?;
+        state
+            .serialize_field("elapsed", &(self.elapsed.as_secs_f64() * 1000.0))?;
+        state.serialize_field("stats", &SerializableStats(&self.stats))?;
+        let p: Vec<SerializablePathStats> =
+            self.path_stats.iter().map(SerializablePathStats).collect();
+        state.serialize_field("path_stats", &p)?;
+        state.end()
+    }
+}
+
+/// A request opened by an [`Action::OpenConcurrentRequests`] action.
+#[serde_as]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct OpenedRequest {
+    pub stream_id: u64,
+    #[serde_as(as = "serde_with::DurationMilliSecondsWithFrac<f64>")]
+    pub elapsed: Duration,
+}
+
+/// A bidirectional stream limit granted by the peer.
+#[serde_as]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct StreamsGrant {
+    pub max_streams: u64,
+    #[serde_as(as = "serde_with::DurationMilliSecondsWithFrac<f64>")]
+    pub elapsed: Duration,
+}
+
+/// A request reset by the peer.
+#[serde_as]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct RefusedRequest {
+    pub stream_id: u64,
+    pub error_code: u64,
+    #[serde_as(as = "serde_with::DurationMilliSecondsWithFrac<f64>")]
+    pub elapsed: Duration,
+}
+
+/// A PRIORITY_UPDATE frame sent by h3i.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PriorityUpdate {
+    /// The stream ID, or push ID, of the prioritized element.
+    pub prioritized_element_id: u64,
+    /// Whether the update was for a push stream.
+    pub push: bool,
+    /// The priority field value, sent as is.
+    pub priority_field_value: Vec<u8>,
+}
+
+/// A PUSH_PROMISE frame received on a request stream. See
+/// [`StreamMap::push_promises`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PushPromise {
+    /// The request stream the frame was received on.
+    pub stream_id: u64,
+    /// The push ID.
+    pub push_id: u64,
+    /// The headers of the promised request. Empty if they couldn't be
+    /// decoded.
+    pub headers: Vec<quiche::h3::Header>,
+    /// The push stream fulfilling the promise, if it was received.
+    pub push_stream_id: Option<u64>,
+}
+
+/// How the DATA frames received around a PRIORITY_UPDATE frame were
+/// scheduled. See [`StreamMap::priority_updates`].
+///
+/// The frames received before the update are counted from the previous
+/// update, or the start of the connection, and the ones received after it up
+/// to the next update, or the end of the connection.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PriorityUpdateOutcome {
+    /// The PRIORITY_UPDATE frame.
+    pub update: PriorityUpdate,
+    /// The number of DATA frames received before the update.
+    pub data_frames_before: usize,
+    /// The number of DATA frames received on the prioritized stream before
+    /// the update.
+    pub prioritized_data_frames_before: usize,
+    /// The number of DATA frames received after the update.
+    pub data_frames_after: usize,
+    /// The number of DATA frames received on the prioritized stream after the
+    /// update.
+    pub prioritized_data_frames_after: usize,
+}
+
+impl PriorityUpdateOutcome {
+    /// Whether the share of DATA frames received on the prioritized stream
+    /// changed after the update. This is `false` if no DATA frames were
+    /// received either before or after the update.
+    pub fn scheduling_changed(&self) -> bool {
+        if self.data_frames_before == 0 || self.data_frames_after == 0 {
+            return false;
+        }
+
+        self.prioritized_data_frames_before * self.data_frames_after !=
+            self.prioritized_data_frames_after * self.data_frames_before
+    }
+}
+
+impl Serialize for PriorityUpdateOutcome {
+    fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = s.serialize_struct("priority_update", 8)?;
+        state.serialize_field(
+            "prioritized_element_id",
+            &self.update.prioritized_element_id,
+        )?;
+        state.serialize_field("push", &self.update.push)?;
+        let max = cmp::min(
+            self.update.priority_field_value.len(),
+            MAX_SERIALIZED_BUFFER_LEN,
+        );
+        state.serialize_field(
+            "priority_field_value",
+            &String::from_utf8_lossy(&self.update.priority_field_value[..max]),
+        )?;
+        state.serialize_field("data_frames_before", &self.data_frames_before)?;
+        state.serialize_field(
+            "prioritized_data_frames_before",
+            &self.prioritized_data_frames_before,
+        )?;
+        state.serialize_field("data_frames_after", &self.data_frames_after)?;
+        state.serialize_field(
+            "prioritized_data_frames_after",
+            &self.prioritized_data_frames_after,
+        )?;
+        state
+            .serialize_field("scheduling_changed", &self.scheduling_changed())?;
+        state.end()
     }
 }
 
@@ -330,9 +2142,15 @@ impl StreamMap {
 /// [`ConnectionError`] to [`Self::new_with_connection_close`]. h3i will send an
 /// application CONNECTION_CLOSE frame with error code 0x100 if this struct is
 /// constructed with the [`Self::new`] constructor.
+///
+/// Besides frames, h3i can watch for [`CloseTriggerEvent`]s such as a GOAWAY
+/// or a stream reset with a given error code, added with [`Self::with_events`].
+/// The connection is closed once all frames and events were seen.
 #[derive(Clone, Serialize, Debug)]
 pub struct CloseTriggerFrames {
     missing: Vec<CloseTriggerFrame>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    missing_events: Vec<CloseTriggerEvent>,
     #[serde(skip)]
     close_with: ConnectionError,
 }
@@ -357,10 +2175,17 @@ impl CloseTriggerFrames {
     ) -> Self {
         Self {
             missing: frames,
+            missing_events: vec![],
             close_with,
         }
     }
 
+    /// Also waits for `events` before closing the connection.
+    pub fn with_events(mut self, events: Vec<CloseTriggerEvent>) -> Self {
+        self.missing_events.extend(events);
+        self
+    }
+
     fn receive_frame(&mut self, stream_id: u64, frame: &H3iFrame) {
         for (i, trigger) in self.missing.iter_mut().enumerate() {
             if trigger.is_equivalent(frame) && trigger.stream_id() == stream_id {
@@ -368,28 +2193,218 @@ impl CloseTriggerFrames {
                 break;
             }
         }
+
+        match frame {
+            H3iFrame::QuicheH3(QFrame::GoAway { .. }) =>
+                self.receive_event(|e| matches!(e, CloseTriggerEvent::GoAway)),
+
+            H3iFrame::ResetStream(reset) => self.receive_event(|e| {
+                matches!(e, CloseTriggerEvent::StreamReset {
+                    stream_id: id,
+                    error_code: code,
+                } if id.map_or(true, |id| id == stream_id) &&
+                    code.map_or(true, |code| code == reset.error_code))
+            }),
+
+            _ => (),
+        }
+    }
+
+    fn receive_stop_sending(&mut self, stream_id: u64, error_code: u64) {
+        self.receive_event(|e| {
+            matches!(e, CloseTriggerEvent::StopSending {
+                stream_id: id,
+                error_code: code,
+            } if id.map_or(true, |id| id == stream_id) &&
+                code.map_or(true, |code| code == error_code))
+        });
+    }
+
+    fn receive_peer_error(&mut self, error: &ConnectionError) {
+        self.receive_event(|e| {
+            matches!(e, CloseTriggerEvent::PeerClose {
+                is_app,
+                error_code,
+            } if *is_app == error.is_app && *error_code == error.error_code)
+        });
+    }
+
+    /// Removes the first missing event matched by `matches`.
+    fn receive_event(&mut self, matches: impl Fn(&CloseTriggerEvent) -> bool) {
+        if let Some(i) = self.missing_events.iter().position(matches) {
+            let event = self.missing_events.remove(i);
+            log::info!("saw close trigger event {:?}", event);
+        }
     }
 
     fn saw_all_trigger_frames(&self) -> bool {
-        self.missing.is_empty()
+        self.missing.is_empty() && self.missing_events.is_empty()
+    }
+
+    pub(crate) fn missing_events(&self) -> &[CloseTriggerEvent] {
+        &self.missing_events
     }
 
-    fn missing_triggers(&self) -> Vec<CloseTriggerFrame> {
+    pub(crate) fn missing_triggers(&self) -> Vec<CloseTriggerFrame> {
         self.missing.clone()
     }
 }
 
+/// A connection event that h3i can watch for before closing the connection.
+/// See [`CloseTriggerFrames::with_events`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum CloseTriggerEvent {
+    /// A GOAWAY frame was received.
+    GoAway,
+    /// A RESET_STREAM frame was received, on `stream_id` and with `error_code`
+    /// if they are given.
+    StreamReset {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        stream_id: Option<u64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        error_code: Option<u64>,
+    },
+    /// A STOP_SENDING frame was received, on `stream_id` and with `error_code`
+    /// if they are given.
+    StopSending {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        stream_id: Option<u64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        error_code: Option<u64>,
+    },
+    /// The peer closed the connection with the given error.
+    PeerClose { is_app: bool, error_code: u64 },
+}
+
 impl From<Vec<CloseTriggerFrame>> for CloseTriggerFrames {
     fn from(value: Vec<CloseTriggerFrame>) -> Self {
         Self::new(value)
     }
 }
 
+/// The outcome of the 0-RTT early data of a resumed connection.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EarlyData {
+    /// No action was executed before the handshake completed.
+    #[default]
+    NotSent,
+    /// Actions were executed as early data, and the server accepted it.
+    Accepted,
+    /// Actions were executed as early data, but the server rejected it. The
+    /// data was then sent again once the handshake completed.
+    Rejected,
+}
+
+impl EarlyData {
+    pub(crate) fn new(qconn: &Connection, sent: bool) -> Self {
+        match (sent, qconn.is_early_data_accepted()) {
+            (false, _) => EarlyData::NotSent,
+
+            (true, true) => EarlyData::Accepted,
+
+            (true, false) => EarlyData::Rejected,
+        }
+    }
+}
+
+/// The Retry and Version Negotiation packets received from the server during
+/// the handshake, and the ALPN protocol it selected.
+///
+/// The packets are recorded as received from the socket, so they show up even
+/// when [`HandshakeFaults`] drops them before quiche sees them. Only the first
+/// packet of each type is recorded, as quiche ignores the ones after it.
+///
+/// [`HandshakeFaults`]: crate::config::HandshakeFaults
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct HandshakeSummary {
+    /// The token of the Retry packet, if the server sent one.
+    pub retry_token: Option<Vec<u8>>,
+    /// The versions listed by the Version Negotiation packet, if the server
+    /// sent one.
+    pub version_negotiation: Option<Vec<u32>>,
+    /// The ALPN protocol selected by the server, if the handshake got that
+    /// far. See [`Config::alpn`].
+    ///
+    /// [`Config::alpn`]: crate::config::Config::alpn
+    pub alpn: Option<Vec<u8>>,
+}
+
+impl HandshakeSummary {
+    /// Whether the server asked to validate the client's address with a Retry
+    /// packet.
+    pub fn did_retry(&self) -> bool {
+        self.retry_token.is_some()
+    }
+
+    /// Whether the server answered with a Version Negotiation packet.
+    pub fn did_version_negotiation(&self) -> bool {
+        self.version_negotiation.is_some()
+    }
+
+    /// Records the datagram if it carries a Retry or a Version Negotiation
+    /// packet.
+    pub(crate) fn observe(&mut self, buf: &mut [u8]) {
+        // Retry and Version Negotiation packets have a long header.
+        if !matches!(buf.first(), Some(b) if b & 0x80 != 0) {
+            return;
+        }
+
+        let Ok(hdr) = quiche::Header::from_slice(buf, quiche::MAX_CONN_ID_LEN)
+        else {
+            return;
+        };
+
+        match hdr.ty {
+            quiche::Type::Retry if self.retry_token.is_none() => {
+                log::info!("received Retry packet");
+
+                self.retry_token = Some(hdr.token.unwrap_or_default());
+            },
+
+            quiche::Type::VersionNegotiation
+                if self.version_negotiation.is_none() =>
+            {
+                log::info!(
+                    "received Version Negotiation packet versions={:x?}",
+                    hdr.versions
+                );
+
+                self.version_negotiation = Some(hdr.versions.unwrap_or_default());
+            },
+
+            _ => (),
+        }
+    }
+}
+
+impl Serialize for HandshakeSummary {
+    fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = s.serialize_struct("handshake", 3)?;
+        if let Some(token) = &self.retry_token {
+            let token: String =
+                token.iter().map(|b| format!("{b:02x}")).collect();
+            state.serialize_field("retry_token", &token)?;
+        }
+        if let Some(versions) = &self.version_negotiation {
+            state.serialize_field("version_negotiation", versions)?;
+        }
+        if let Some(alpn) = &self.alpn {
+            state.serialize_field("alpn", &String::from_utf8_lossy(alpn))?;
+        }
+        state.end()
+    }
+}
+
 /// Denotes why the connection was closed.
 #[derive(Debug, Default)]
 pub struct ConnectionCloseDetails {
-    peer_error: Option<ConnectionError>,
-    local_error: Option<ConnectionError>,
+    pub(crate) peer_error: Option<ConnectionError>,
+    pub(crate) local_error: Option<ConnectionError>,
     /// If the connection timed out.
     pub timed_out: bool,
 }
@@ -500,7 +2515,7 @@ impl Serialize for SerializableStats<'_> {
     where
         S: Serializer,
     {
-        let mut state = s.serialize_struct("path_stats", 14)?;
+        let mut state = s.serialize_struct("path_stats", 18)?;
         state.serialize_field("recv", &self.0.recv)?;
         state.serialize_field("sent", &self.0.sent)?;
         state.serialize_field("lost", &self.0.lost)?;
@@ -533,6 +2548,84 @@ impl Serialize for SerializableStats<'_> {
             "path_challenge_rx_count",
             &self.0.path_challenge_rx_count,
         )?;
+        state.serialize_field(
+            "data_blocked_rx_count",
+            &self.0.data_blocked_rx_count,
+        )?;
+        state.serialize_field(
+            "stream_data_blocked_rx_count",
+            &self.0.stream_data_blocked_rx_count,
+        )?;
+        state.serialize_field(
+            "streams_blocked_bidi_rx_count",
+            &self.0.streams_blocked_bidi_rx_count,
+        )?;
+        state.serialize_field(
+            "streams_blocked_uni_rx_count",
+            &self.0.streams_blocked_uni_rx_count,
+        )?;
+        state.end()
+    }
+}
+
+/// A wrapper to help serialize [quiche::TransportParams]
+pub struct SerializableTransportParams<'a>(&'a quiche::TransportParams);
+
+impl Serialize for SerializableTransportParams<'_> {
+    fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let tp = self.0;
+
+        let mut state = s.serialize_struct("transport_params", 15)?;
+        state.serialize_field("max_idle_timeout", &tp.max_idle_timeout)?;
+        state
+            .serialize_field("max_udp_payload_size", &tp.max_udp_payload_size)?;
+        state.serialize_field("initial_max_data", &tp.initial_max_data)?;
+        state.serialize_field(
+            "initial_max_stream_data_bidi_local",
+            &tp.initial_max_stream_data_bidi_local,
+        )?;
+        state.serialize_field(
+            "initial_max_stream_data_bidi_remote",
+            &tp.initial_max_stream_data_bidi_remote,
+        )?;
+        state.serialize_field(
+            "initial_max_stream_data_uni",
+            &tp.initial_max_stream_data_uni,
+        )?;
+        state.serialize_field(
+            "initial_max_streams_bidi",
+            &tp.initial_max_streams_bidi,
+        )?;
+        state.serialize_field(
+            "initial_max_streams_uni",
+            &tp.initial_max_streams_uni,
+        )?;
+        state.serialize_field("ack_delay_exponent", &tp.ack_delay_exponent)?;
+        state.serialize_field("max_ack_delay", &tp.max_ack_delay)?;
+        state.serialize_field(
+            "disable_active_migration",
+            &tp.disable_active_migration,
+        )?;
+        state.serialize_field(
+            "active_connection_id_limit",
+            &tp.active_conn_id_limit,
+        )?;
+        state.serialize_field(
+            "max_datagram_frame_size",
+            &tp.max_datagram_frame_size,
+        )?;
+
+        let unknown: Vec<qlog::events::quic::UnknownTransportParameter> = tp
+            .unknown_params
+            .iter()
+            .flatten()
+            .cloned()
+            .map(Into::into)
+            .collect();
+        state.serialize_field("unknown_parameters", &unknown)?;
         state.end()
     }
 }
@@ -562,6 +2655,7 @@ impl Serialize for SerializableConnectionError<'_> {
 mod tests {
     use super::*;
     use crate::frame::EnrichedHeaders;
+    use crate::frame::ResetStream;
     use quiche::h3::Header;
 
     fn h3i_frame() -> H3iFrame {
@@ -597,6 +2691,47 @@ mod tests {
         assert_eq!(expected.missing_triggers(), expected_frames[1..].to_vec());
     }
 
+    #[test]
+    fn close_trigger_events() {
+        let mut triggers = CloseTriggerFrames::new(vec![]).with_events(vec![
+            CloseTriggerEvent::GoAway,
+            CloseTriggerEvent::StreamReset {
+                stream_id: None,
+                error_code: Some(0x10c),
+            },
+            CloseTriggerEvent::PeerClose {
+                is_app: true,
+                error_code: 0x100,
+            },
+        ]);
+
+        triggers.receive_frame(
+            0,
+            &H3iFrame::ResetStream(ResetStream {
+                stream_id: 0,
+                error_code: 0x10b,
+            }),
+        );
+        triggers.receive_frame(3, &QFrame::GoAway { id: 0 }.into());
+        assert_eq!(triggers.missing_events().len(), 2);
+
+        triggers.receive_frame(
+            4,
+            &H3iFrame::ResetStream(ResetStream {
+                stream_id: 4,
+                error_code: 0x10c,
+            }),
+        );
+        assert!(!triggers.saw_all_trigger_frames());
+
+        triggers.receive_peer_error(&ConnectionError {
+            is_app: true,
+            error_code: 0x100,
+            reason: vec![],
+        });
+        assert!(triggers.saw_all_trigger_frames());
+    }
+
     fn stream_map_data() -> Vec<H3iFrame> {
         let headers =
             H3iFrame::Headers(EnrichedHeaders::from(vec![Header::new(
@@ -609,6 +2744,552 @@ mod tests {
         vec![headers, data]
     }
 
+    #[test]
+    fn grease_report() {
+        let actions = vec![
+            crate::actions::h3::send_settings_frame_with_grease(2, false, vec![
+                (0x6, 100),
+            ]),
+            crate::actions::h3::send_grease_frame(0, false),
+            crate::actions::h3::open_grease_uni_stream(6, false),
+            crate::actions::h3::send_grease_frame(4, false),
+        ];
+
+        let reset = H3iFrame::ResetStream(crate::frame::ResetStream {
+            stream_id: 4,
+            error_code: 0x10c,
+        });
+
+        let mut summary = ConnectionSummary {
+            stream_map: vec![(0, stream_map_data())].into(),
+            ..Default::default()
+        };
+
+        let report = summary.grease_report(&actions);
+        assert_eq!(report.frame_streams, vec![0, 4]);
+        assert_eq!(report.settings, 1);
+        assert_eq!(report.uni_streams, vec![6]);
+        assert!(report.reset_streams.is_empty());
+        assert!(!report.peer_choked());
+
+        summary.stream_map.insert(4, reset);
+        summary.conn_close_details.peer_error = Some(ConnectionError {
+            is_app: true,
+            error_code: quiche::h3::WireErrorCode::NoError as u64,
+            reason: vec![],
+        });
+
+        let report = summary.grease_report(&actions);
+        assert_eq!(report.reset_streams, vec![4]);
+        assert!(report.peer_error.is_none());
+        assert!(report.peer_choked());
+    }
+
+    #[test]
+    fn grease_report_peer_error() {
+        let actions = vec![crate::actions::h3::open_grease_uni_stream(6, false)];
+
+        let mut summary = ConnectionSummary::default();
+        summary.conn_close_details.peer_error = Some(ConnectionError {
+            is_app: true,
+            error_code: quiche::h3::WireErrorCode::StreamCreationError as u64,
+            reason: vec![],
+        });
+
+        assert!(summary.grease_report(&actions).peer_choked());
+        assert!(!summary.grease_report(&[]).peer_choked());
+    }
+
+    #[test]
+    fn throughput_report() {
+        let mut summary = ConnectionSummary::default();
+
+        // No DATA received, nothing to report.
+        let report = summary.throughput_report();
+        assert!(report.streams.is_empty());
+        assert_eq!(report.goodput_bytes, 0);
+        assert_eq!(report.goodput_bps, None);
+
+        summary.stream_map.record_action(
+            &crate::actions::h3::send_headers_frame(0, true, vec![Header::new(
+                b":method", b"GET",
+            )]),
+        );
+        std::thread::sleep(Duration::from_millis(5));
+        summary.stream_map.insert(0, stream_map_data()[1].clone());
+        summary.stream_map.insert(0, stream_map_data()[1].clone());
+
+        // DATA on a stream opened by the peer.
+        summary.stream_map.insert(3, data_frame());
+
+        summary.stats = Some(Stats {
+            sent: 10,
+            retrans: 1,
+            sent_bytes: 1000,
+            recv_bytes: 88,
+            stream_retrans_bytes: 100,
+            ..Default::default()
+        });
+
+        let report = summary.throughput_report();
+        assert_eq!(report.goodput_bytes, 23);
+        assert_eq!(report.streams.len(), 2);
+
+        let stream = report.streams[0];
+        assert_eq!(stream.stream_id, 0);
+        assert_eq!(stream.bytes, 22);
+        assert!(stream.elapsed >= Duration::from_millis(5));
+        assert!(stream.goodput_bps.is_some());
+
+        assert_eq!(report.streams[1].stream_id, 3);
+        assert_eq!(report.streams[1].bytes, 1);
+
+        assert!(report.elapsed >= stream.elapsed);
+        assert!(report.goodput_bps.is_some());
+        assert_eq!(report.wire_bytes_received, Some(88));
+        assert_eq!(report.wire_overhead, Some(65.0 / 88.0));
+        assert_eq!(report.retransmission_ratio, Some(0.1));
+        assert_eq!(report.retransmitted_bytes_ratio, Some(0.1));
+
+        let json = serde_json::to_value(&summary).unwrap();
+        assert_eq!(json["throughput"]["goodput_bytes"], 23);
+    }
+
+    #[test]
+    fn timing_report() {
+        let mut summary = ConnectionSummary::default();
+        assert!(summary.timing_report().is_empty());
+
+        let request =
+            crate::actions::h3::send_headers_frame(0, true, vec![Header::new(
+                b":method", b"GET",
+            )]);
+        summary.stream_map.action_executed(&request);
+        summary.stream_map.record_action(&request);
+        summary.stream_map.action_executed(&Action::FlushPackets);
+
+        std::thread::sleep(Duration::from_millis(5));
+        for frame in stream_map_data() {
+            summary.stream_map.insert(0, frame);
+        }
+
+        let report = summary.timing_report();
+
+        assert_eq!(report.actions.len(), 2);
+        assert_eq!(report.actions[0].action, "send_headers");
+        assert_eq!(report.actions[0].stream_id, Some(0));
+        assert!(report.actions[0].latency.unwrap() >= Duration::from_millis(5));
+        assert_eq!(report.actions[1].action, "flush_packets");
+        assert_eq!(report.actions[1].latency, None);
+
+        assert_eq!(report.streams.len(), 1);
+        let stream = report.streams[0];
+        assert_eq!(stream.stream_id, 0);
+        assert!(stream.first_sent.is_some());
+        assert!(stream.first_headers <= stream.first_data);
+        assert_eq!(stream.last_frame, stream.first_data);
+        assert!(
+            stream.time_to_first_headers.unwrap() >= Duration::from_millis(5)
+        );
+        assert_eq!(summary.stream_map.frame_times_on_stream(0).len(), 2);
+
+        let json = serde_json::to_value(&summary).unwrap();
+        assert_eq!(json["timing"]["actions"][0]["action"], "send_headers");
+    }
+
+    fn data_frame() -> H3iFrame {
+        H3iFrame::QuicheH3(quiche::h3::frame::Frame::Data {
+            payload: b"a".to_vec(),
+        })
+    }
+
+    #[test]
+    fn priority_update_outcomes() {
+        let mut stream_map = StreamMap::default();
+
+        // Stream 4 is served after stream 0 completes.
+        for id in [0, 0, 0] {
+            stream_map.insert(id, data_frame());
+        }
+
+        stream_map.record_action(
+            &crate::actions::h3::send_priority_update_request(2, 4, b"u=0"),
+        );
+
+        for id in [4, 4, 0] {
+            stream_map.insert(id, data_frame());
+        }
+
+        // A malformed value that doesn't change anything.
+        stream_map.record_action(
+            &crate::actions::h3::send_priority_update_request(2, 4, b"u=\xff"),
+        );
+
+        for id in [4, 0, 4] {
+            stream_map.insert(id, data_frame());
+        }
+
+        let outcomes = stream_map.priority_updates();
+        assert_eq!(outcomes.len(), 2);
+
+        assert_eq!(outcomes[0].update.priority_field_value, b"u=0");
+        assert_eq!(outcomes[0].data_frames_before, 3);
+        assert_eq!(outcomes[0].prioritized_data_frames_before, 0);
+        assert_eq!(outcomes[0].data_frames_after, 3);
+        assert_eq!(outcomes[0].prioritized_data_frames_after, 2);
+        assert!(outcomes[0].scheduling_changed());
+
+        assert_eq!(outcomes[1].data_frames_before, 3);
+        assert_eq!(outcomes[1].prioritized_data_frames_before, 2);
+        assert_eq!(outcomes[1].data_frames_after, 3);
+        assert_eq!(outcomes[1].prioritized_data_frames_after, 2);
+        assert!(!outcomes[1].scheduling_changed());
+    }
+
+    #[test]
+    fn body_assertion_results() {
+        let mut stream_map = StreamMap::default();
+
+        let assert_body = |assertion| Action::AssertBody {
+            stream_id: 0,
+            assertion,
+        };
+
+        // Evaluated against the body received so far.
+        stream_map.record_action(&assert_body(BodyAssertion::Length(0)));
+
+        stream_map.insert(0, data_frame());
+        stream_map.insert(4, data_frame());
+        stream_map.insert(0, data_frame());
+
+        stream_map.record_action(&assert_body(BodyAssertion::Length(2)));
+        stream_map
+            .record_action(&assert_body(BodyAssertion::Bytes(b"ab".to_vec())));
+
+        let results = stream_map.body_assertions();
+        assert_eq!(results.len(), 3);
+
+        assert!(results[0].passed());
+        assert_eq!(results[0].body_len, 0);
+
+        assert!(results[1].passed());
+        assert_eq!(results[1].body_len, 2);
+
+        assert!(!results[2].passed());
+        assert_eq!(
+            results[2].failure.as_deref(),
+            Some("body differs from the expected bytes at offset 1")
+        );
+
+        let summary = ConnectionSummary {
+            stream_map,
+            ..Default::default()
+        };
+        let json = serde_json::to_value(&summary).unwrap();
+        assert_eq!(
+            json["body_assertions"][1],
+            serde_json::json!({
+                "stream_id": 0,
+                "assertion": { "length": 2 },
+                "body_len": 2,
+                "failure": null,
+            })
+        );
+    }
+
+    #[test]
+    fn captured_bodies() {
+        let dir = std::env::temp_dir()
+            .join(format!("h3i-captured-bodies-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let mut stream_map = StreamMap::default();
+        stream_map.capture_bodies(BodyCapture {
+            dir: Some(dir.to_string_lossy().into_owned()),
+            max_size: Some(1),
+        });
+
+        stream_map.insert(0, data_frame());
+        stream_map.insert(0, data_frame());
+        stream_map.insert(4, data_frame());
+
+        // Payloads are kept out of the frames, but still accounted for.
+        assert!(stream_map.body_on_stream(0).is_empty());
+        assert_eq!(stream_map.data_bytes_on_stream(0), 2);
+
+        let captured = stream_map.captured_body(0).unwrap();
+        assert_eq!(captured.len, 2);
+        assert_eq!(captured.written, 1);
+        assert!(!captured.is_complete());
+        assert_eq!(
+            captured.sha256,
+            "961b6dd3ede3cb8ecbaacbd68de040cd78eb2ed5889130cceb4c49268ea4d506"
+        );
+        assert_eq!(
+            std::fs::read(captured.path.as_ref().unwrap()).unwrap(),
+            b"a"
+        );
+
+        assert!(captured.check(&BodyAssertion::Length(2)).is_ok());
+        assert!(captured
+            .check(&BodyAssertion::Sha256(captured.sha256.clone()))
+            .is_ok());
+        assert_eq!(
+            captured.check(&BodyAssertion::Bytes(b"aa".to_vec())),
+            Err("only 1 of the 2 body bytes were captured".to_string())
+        );
+
+        let captured = stream_map.captured_body(4).unwrap();
+        assert!(captured.is_complete());
+        assert!(captured.check(&BodyAssertion::Bytes(b"a".to_vec())).is_ok());
+
+        assert!(stream_map.captured_body(8).is_none());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn informational_headers_and_trailers() {
+        let headers = |name: &[u8], value: &[u8]| {
+            H3iFrame::Headers(vec![Header::new(name, value)].into())
+        };
+
+        let stream_map: StreamMap = [
+            (0, vec![
+                headers(b":status", b"103"),
+                headers(b":status", b"200"),
+                data_frame(),
+                headers(b"grpc-status", b"0"),
+            ]),
+            (4, vec![
+                headers(b":status", b"100"),
+                headers(b":status", b"200"),
+            ]),
+        ]
+        .into();
+
+        assert_eq!(
+            stream_map.trailers_on_stream(0),
+            Some(vec![Header::new(b"grpc-status", b"0")].into())
+        );
+        assert_eq!(stream_map.response_headers_on_stream(0).len(), 2);
+        assert_eq!(stream_map.trailers_on_stream(4), None);
+        assert_eq!(stream_map.response_headers_on_stream(4).len(), 2);
+        assert_eq!(stream_map.trailers().keys().collect::<Vec<_>>(), vec![&0]);
+
+        assert_eq!(stream_map.informational_headers_on_stream(0), vec![vec![
+            Header::new(b":status", b"103")
+        ]
+        .into()]);
+        assert_eq!(
+            stream_map.final_headers_on_stream(4),
+            Some(vec![Header::new(b":status", b"200")].into())
+        );
+        assert_eq!(stream_map.informational_headers().len(), 2);
+
+        let summary = ConnectionSummary {
+            stream_map,
+            ..Default::default()
+        };
+
+        let json = serde_json::to_value(&summary).unwrap();
+        assert_eq!(json["trailers"]["0"]["headers"][0]["name"], "grpc-status");
+        assert_eq!(
+            json["informational_headers"]["4"][0]["headers"][0]["value"],
+            "100"
+        );
+    }
+
+    #[test]
+    fn stats_snapshots() {
+        let mut stream_map = StreamMap::default();
+        assert!(stream_map.stats_snapshots().is_empty());
+
+        let stats = |sent| Stats {
+            sent,
+            ..Default::default()
+        };
+
+        stream_map.snapshot_stats("before", stats(2), vec![]);
+        stream_map.snapshot_stats("after", stats(10), vec![]);
+        stream_map.snapshot_stats("after", stats(12), vec![]);
+
+        let labels: Vec<_> = stream_map
+            .stats_snapshots()
+            .iter()
+            .map(|s| s.label.as_str())
+            .collect();
+        assert_eq!(labels, vec!["before", "after", "after"]);
+        assert_eq!(stream_map.stats_snapshot("after").unwrap().stats.sent, 12);
+        assert!(stream_map.stats_snapshot("other").is_none());
+
+        let summary = ConnectionSummary {
+            stream_map,
+            stats: None,
+            path_stats: vec![],
+            conn_close_details: ConnectionCloseDetails::default(),
+            early_data: EarlyData::NotSent,
+            peer_transport_params: None,
+            handshake: HandshakeSummary::default(),
+        };
+
+        let json = serde_json::to_value(&summary).unwrap();
+        assert_eq!(json["stats_snapshots"][0]["label"], "before");
+        assert_eq!(json["stats_snapshots"][1]["stats"]["sent"], 10);
+    }
+
+    #[test]
+    fn concurrent_requests_report() {
+        let mut stream_map = StreamMap::default();
+
+        // Ignored until a report is started.
+        stream_map.update_peer_max_streams(100);
+        stream_map.concurrent_request_opened(0);
+        assert!(stream_map.concurrent_requests().is_empty());
+
+        stream_map.start_concurrent_requests(0, 4, 2);
+        stream_map.concurrent_request_opened(0);
+        stream_map.concurrent_request_opened(4);
+        stream_map.update_peer_max_streams(2);
+        stream_map.update_peer_max_streams(3);
+        stream_map.concurrent_request_opened(8);
+
+        let reset = |stream_id| {
+            H3iFrame::ResetStream(ResetStream {
+                stream_id,
+                error_code: 0x10b,
+            })
+        };
+
+        stream_map.insert(4, reset(4));
+        // Not one of the concurrent requests.
+        stream_map.insert(12, reset(12));
+
+        let reports = stream_map.concurrent_requests();
+        assert_eq!(reports.len(), 1);
+
+        let report = &reports[0];
+        assert_eq!(
+            report
+                .opened
+                .iter()
+                .map(|r| r.stream_id)
+                .collect::<Vec<_>>(),
+            vec![0, 4, 8]
+        );
+        assert_eq!(report.pending(), 1);
+        assert_eq!(
+            report
+                .max_streams
+                .iter()
+                .map(|g| g.max_streams)
+                .collect::<Vec<_>>(),
+            vec![2, 3]
+        );
+        assert_eq!(report.refused.len(), 1);
+        assert_eq!(report.refused[0].stream_id, 4);
+        assert_eq!(report.refused[0].error_code, 0x10b);
+
+        let summary = ConnectionSummary {
+            stream_map,
+            ..Default::default()
+        };
+        let json = serde_json::to_value(&summary).unwrap();
+        assert_eq!(json["concurrent_requests"][0]["count"], 4);
+        assert_eq!(
+            json["concurrent_requests"][0]["max_streams"][1]["max_streams"],
+            3
+        );
+    }
+
+    #[test]
+    fn peer_transport_params() {
+        let params = quiche::TransportParams {
+            initial_max_data: 1000,
+            unknown_params: Some(quiche::UnknownTransportParameters {
+                capacity: 0,
+                parameters: vec![quiche::UnknownTransportParameter {
+                    id: 0x2ab2,
+                    value: vec![0x01, 0x02],
+                }],
+            }),
+            ..Default::default()
+        };
+
+        let summary = ConnectionSummary {
+            peer_transport_params: Some(params),
+            ..Default::default()
+        };
+        let json = serde_json::to_value(&summary).unwrap();
+        assert_eq!(json["peer_transport_params"]["initial_max_data"], 1000);
+        assert_eq!(
+            json["peer_transport_params"]["unknown_parameters"],
+            serde_json::json!([{ "id": 0x2ab2, "value": "0102" }])
+        );
+
+        let json = serde_json::to_value(ConnectionSummary::default()).unwrap();
+        assert!(json.get("peer_transport_params").is_none());
+    }
+
+    #[test]
+    fn handshake_summary() {
+        let scid = quiche::ConnectionId::from_ref(&[0xaa; 8]);
+        let dcid = quiche::ConnectionId::from_ref(&[0xbb; 8]);
+        let new_scid = quiche::ConnectionId::from_ref(&[0xcc; 8]);
+
+        let mut retry = [0; 128];
+        let len = quiche::retry(
+            &scid,
+            &dcid,
+            &new_scid,
+            &[0x01, 0x02],
+            quiche::PROTOCOL_VERSION,
+            &mut retry,
+        )
+        .unwrap();
+        let retry = &mut retry[..len];
+
+        let mut vn = [0; 128];
+        let len = quiche::negotiate_version(&scid, &dcid, &mut vn).unwrap();
+        let vn = &mut vn[..len];
+
+        let mut handshake = HandshakeSummary::default();
+        handshake.observe(&mut [0x40, 1, 2, 3, 4]);
+        assert_eq!(handshake, HandshakeSummary::default());
+
+        handshake.observe(retry);
+        handshake.observe(vn);
+        assert!(handshake.did_retry());
+        assert_eq!(handshake.retry_token, Some(vec![0x01, 0x02]));
+        assert!(handshake
+            .version_negotiation
+            .as_ref()
+            .unwrap()
+            .contains(&quiche::PROTOCOL_VERSION));
+
+        let summary = ConnectionSummary {
+            handshake,
+            ..Default::default()
+        };
+        let json = serde_json::to_value(&summary).unwrap();
+        assert_eq!(json["handshake"]["retry_token"], "0102");
+        assert!(json["handshake"]["version_negotiation"].is_array());
+        assert!(json["handshake"].get("alpn").is_none());
+
+        let summary = ConnectionSummary {
+            handshake: HandshakeSummary {
+                alpn: Some(b"h3".to_vec()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let json = serde_json::to_value(&summary).unwrap();
+        assert_eq!(json["handshake"], serde_json::json!({ "alpn": "h3" }));
+
+        let json = serde_json::to_value(ConnectionSummary::default()).unwrap();
+        assert!(json.get("handshake").is_none());
+    }
+
     #[test]
     fn test_stream_map_trigger_frames_with_none() {
         let stream_map: StreamMap = vec![(0, stream_map_data())].into();
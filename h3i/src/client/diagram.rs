@@ -0,0 +1,387 @@
+// Copyright (C) 2025, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Renders a run as a sequence diagram, in Mermaid or PlantUML syntax, to
+//! attach a visual of the interaction to bug reports.
+//!
+//! The diagram shows the actions executed by h3i and the frames received from
+//! the server, in the order they happened, followed by how the connection was
+//! closed.
+
+use std::fmt::Write;
+use std::path::Path;
+use std::time::Duration;
+
+use quiche::h3::frame::Frame as QFrame;
+use quiche::ConnectionError;
+
+use crate::frame::H3iFrame;
+
+use super::baseline::frame_name;
+use super::connection_summary::ConnectionSummary;
+
+/// The syntax of a sequence diagram.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DiagramFormat {
+    /// [Mermaid](https://mermaid.js.org/syntax/sequenceDiagram.html), which
+    /// e.g. GitHub renders in Markdown.
+    #[default]
+    Mermaid,
+    /// [PlantUML](https://plantuml.com/sequence-diagram).
+    PlantUml,
+}
+
+impl DiagramFormat {
+    /// The format of a file, given its extension: PlantUML for `.puml`,
+    /// `.plantuml` and `.pu` files, Mermaid otherwise.
+    pub fn from_path(path: &str) -> Self {
+        match Path::new(path).extension().and_then(|e| e.to_str()) {
+            Some("puml" | "plantuml" | "pu") => DiagramFormat::PlantUml,
+
+            _ => DiagramFormat::Mermaid,
+        }
+    }
+}
+
+/// Something that happened during the run.
+struct Event {
+    time: Duration,
+    kind: EventKind,
+    label: String,
+}
+
+enum EventKind {
+    /// An action that puts something on the wire.
+    Sent,
+    /// An action that doesn't, e.g. a wait.
+    Local,
+    Received,
+}
+
+/// Renders the actions executed and the frames received during the run
+/// summarized by `summary`, as a sequence diagram.
+///
+/// # Example
+///
+/// ```
+/// use h3i::client::connection_summary::ConnectionSummary;
+/// use h3i::client::diagram::sequence_diagram;
+/// use h3i::client::diagram::DiagramFormat;
+///
+/// let diagram =
+///     sequence_diagram(&ConnectionSummary::default(), DiagramFormat::Mermaid);
+/// assert!(diagram.starts_with("sequenceDiagram\n"));
+/// ```
+pub fn sequence_diagram(
+    summary: &ConnectionSummary, format: DiagramFormat,
+) -> String {
+    let stream_map = &summary.stream_map;
+
+    let mut events: Vec<Event> = summary
+        .timing_report()
+        .actions
+        .into_iter()
+        .map(|action| Event {
+            time: action.executed,
+            kind: if is_local_action(action.action) {
+                EventKind::Local
+            } else {
+                EventKind::Sent
+            },
+            label: with_stream(action.action.to_string(), action.stream_id),
+        })
+        .collect();
+
+    for stream_id in stream_map.stream_ids() {
+        let times = stream_map.frame_times_on_stream(stream_id);
+
+        for (i, frame) in stream_map.stream(stream_id).iter().enumerate() {
+            events.push(Event {
+                time: times.get(i).copied().unwrap_or_default(),
+                kind: EventKind::Received,
+                label: with_stream(frame_label(frame), Some(stream_id)),
+            });
+        }
+    }
+
+    // Sort by time, keeping actions before the frames received at the same
+    // time.
+    events.sort_by_key(|e| e.time);
+
+    let mut out = String::new();
+
+    match format {
+        DiagramFormat::Mermaid => {
+            out.push_str("sequenceDiagram\n");
+            out.push_str("    participant C as h3i\n");
+            out.push_str("    participant S as server\n");
+        },
+
+        DiagramFormat::PlantUml => {
+            out.push_str("@startuml\n");
+            out.push_str("participant \"h3i\" as C\n");
+            out.push_str("participant \"server\" as S\n");
+        },
+    }
+
+    let indent = match format {
+        DiagramFormat::Mermaid => "    ",
+
+        DiagramFormat::PlantUml => "",
+    };
+
+    for event in events {
+        let label = format!(
+            "[{:.1}ms] {}",
+            event.time.as_secs_f64() * 1000.0,
+            escape(&event.label)
+        );
+
+        let _ = match (event.kind, format) {
+            (EventKind::Sent, DiagramFormat::Mermaid) =>
+                writeln!(out, "{indent}C->>S: {label}"),
+
+            (EventKind::Sent, DiagramFormat::PlantUml) =>
+                writeln!(out, "{indent}C -> S: {label}"),
+
+            (EventKind::Received, DiagramFormat::Mermaid) =>
+                writeln!(out, "{indent}S->>C: {label}"),
+
+            (EventKind::Received, DiagramFormat::PlantUml) =>
+                writeln!(out, "{indent}S -> C: {label}"),
+
+            (EventKind::Local, _) =>
+                writeln!(out, "{indent}Note over C: {label}"),
+        };
+    }
+
+    let details = &summary.conn_close_details;
+
+    let close = if let Some(e) = details.peer_error() {
+        Some(("S", "C", close_label(e)))
+    } else if let Some(e) = details.local_error() {
+        Some(("C", "S", close_label(e)))
+    } else if details.timed_out {
+        let _ = writeln!(out, "{indent}Note over C,S: idle timeout");
+        None
+    } else {
+        None
+    };
+
+    if let Some((from, to, label)) = close {
+        let _ = match format {
+            DiagramFormat::Mermaid =>
+                writeln!(out, "{indent}{from}-x{to}: {}", escape(&label)),
+
+            DiagramFormat::PlantUml =>
+                writeln!(out, "{indent}{from} ->x {to}: {}", escape(&label)),
+        };
+    }
+
+    if format == DiagramFormat::PlantUml {
+        out.push_str("@enduml\n");
+    }
+
+    out
+}
+
+/// Whether an action only affects h3i, rather than sending something.
+fn is_local_action(name: &str) -> bool {
+    matches!(
+        name,
+        "wait" |
+            "flush_packets" |
+            "assert_body" |
+            "snapshot_stats" |
+            "repeat" |
+            "on_frame" |
+            "withhold_flow_control_updates"
+    )
+}
+
+fn with_stream(label: String, stream_id: Option<u64>) -> String {
+    match stream_id {
+        Some(id) => format!("{label} (stream {id})"),
+
+        None => label,
+    }
+}
+
+fn frame_label(frame: &H3iFrame) -> String {
+    let name = frame_name(frame);
+
+    match frame {
+        H3iFrame::Headers(headers) => match headers.status_code() {
+            Some(status) =>
+                format!("{name} :status {}", String::from_utf8_lossy(status)),
+
+            None => name,
+        },
+
+        H3iFrame::ResetStream(reset) =>
+            format!("{name} error {:#x}", reset.error_code),
+
+        H3iFrame::QuicheH3(QFrame::Data { payload }) =>
+            format!("{name} {} bytes", payload.len()),
+
+        H3iFrame::QuicheH3(QFrame::GoAway { id }) => format!("{name} id {id}"),
+
+        H3iFrame::QuicheH3(_) => name,
+    }
+}
+
+fn close_label(e: &ConnectionError) -> String {
+    let kind = if e.is_app { "application" } else { "transport" };
+    let reason = String::from_utf8_lossy(&e.reason);
+
+    if reason.is_empty() {
+        format!("CONNECTION_CLOSE {kind} error {:#x}", e.error_code)
+    } else {
+        format!(
+            "CONNECTION_CLOSE {kind} error {:#x} {reason:?}",
+            e.error_code
+        )
+    }
+}
+
+/// Removes the characters that end a message, or that Mermaid would
+/// interpret.
+fn escape(label: &str) -> String {
+    label
+        .chars()
+        .map(|c| match c {
+            '\n' | '\r' | ';' | '#' => ' ',
+
+            c => c,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::actions::h3::send_headers_frame;
+    use crate::actions::h3::Action;
+    use crate::actions::h3::WaitType;
+    use crate::frame::EnrichedHeaders;
+    use quiche::h3::Header;
+
+    fn summary() -> ConnectionSummary {
+        let mut summary = ConnectionSummary::default();
+        let stream_map = &mut summary.stream_map;
+
+        stream_map.action_executed(&send_headers_frame(0, true, vec![
+            Header::new(b":method", b"GET"),
+            Header::new(b":scheme", b"https"),
+            Header::new(b":authority", b"example.com"),
+            Header::new(b":path", b"/"),
+        ]));
+        stream_map.action_executed(&Action::Wait {
+            wait_type: WaitType::WaitDuration(Duration::from_millis(10)),
+        });
+        stream_map.insert(
+            0,
+            H3iFrame::Headers(EnrichedHeaders::from(vec![Header::new(
+                b":status", b"200",
+            )])),
+        );
+        stream_map.insert(
+            0,
+            H3iFrame::QuicheH3(QFrame::Data {
+                payload: b"hello".to_vec(),
+            }),
+        );
+
+        summary.conn_close_details.peer_error = Some(ConnectionError {
+            is_app: true,
+            error_code: 0x100,
+            reason: vec![],
+        });
+
+        summary
+    }
+
+    fn messages(diagram: &str) -> Vec<String> {
+        diagram
+            .lines()
+            .filter_map(|l| {
+                let (arrow, label) = l.trim().split_once(": ")?;
+                let label = match label.split_once("] ") {
+                    Some((_, label)) => label,
+
+                    None => label,
+                };
+
+                Some(format!("{arrow}: {label}"))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn mermaid() {
+        let diagram = sequence_diagram(&summary(), DiagramFormat::Mermaid);
+
+        assert!(diagram.starts_with("sequenceDiagram\n"));
+        assert_eq!(messages(&diagram), vec![
+            "C->>S: send_headers (stream 0)",
+            "Note over C: wait",
+            "S->>C: HEADERS :status 200 (stream 0)",
+            "S->>C: DATA 5 bytes (stream 0)",
+            "S-xC: CONNECTION_CLOSE application error 0x100",
+        ]);
+    }
+
+    #[test]
+    fn plantuml() {
+        let diagram = sequence_diagram(&summary(), DiagramFormat::PlantUml);
+
+        assert!(diagram.starts_with("@startuml\n"));
+        assert!(diagram.ends_with("@enduml\n"));
+        assert_eq!(messages(&diagram), vec![
+            "C -> S: send_headers (stream 0)",
+            "Note over C: wait",
+            "S -> C: HEADERS :status 200 (stream 0)",
+            "S -> C: DATA 5 bytes (stream 0)",
+            "S ->x C: CONNECTION_CLOSE application error 0x100",
+        ]);
+    }
+
+    #[test]
+    fn format_from_path() {
+        assert_eq!(
+            DiagramFormat::from_path("run.puml"),
+            DiagramFormat::PlantUml
+        );
+        assert_eq!(DiagramFormat::from_path("run.mmd"), DiagramFormat::Mermaid);
+        assert_eq!(DiagramFormat::from_path("run"), DiagramFormat::Mermaid);
+    }
+
+    #[test]
+    fn escaped_labels() {
+        assert_eq!(escape("a;b#c\nd"), "a b c d");
+    }
+}
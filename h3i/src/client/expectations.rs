@@ -0,0 +1,575 @@
+// Copyright (C) 2025, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Expectations on the outcome of a connection.
+//!
+//! [Expectations] are evaluated against a [ConnectionSummary] once the
+//! connection is over, and produce an [ExpectationsReport] telling which of
+//! them were met.
+
+use quiche::h3::NameValue;
+use quiche::ConnectionError;
+use serde::Deserialize;
+use serde::Serialize;
+
+use super::baseline::frame_name;
+use super::connection_summary::ConnectionSummary;
+use super::connection_summary::StreamMap;
+use crate::actions::h3::BodyAssertion;
+use crate::frame::EnrichedHeaders;
+
+/// A single expectation on the outcome of a connection.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum Expectation {
+    /// The final response received on the stream has the given `:status`.
+    /// Informational (1xx) responses are ignored.
+    Status { stream_id: u64, status: u16 },
+    /// An informational (1xx) response with the given `:status` was received
+    /// on the stream before the final response, e.g. 103 Early Hints.
+    InformationalStatus { stream_id: u64, status: u16 },
+    /// A HEADERS frame received on the stream, other than the trailers,
+    /// contains a field with the given name and, if set, value. Names are
+    /// compared case-insensitively.
+    Header {
+        stream_id: u64,
+        name: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        value: Option<String>,
+    },
+    /// The trailers received on the stream contain a field with the given
+    /// name and, if set, value. Names are compared case-insensitively.
+    Trailer {
+        stream_id: u64,
+        name: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        value: Option<String>,
+    },
+    /// No frame of the given type was received on the stream, or on any
+    /// stream if `stream_id` is `None`. Frame types are named as in
+    /// baselines, e.g. `GOAWAY`, `RESET_STREAM` or `GREASE`.
+    ForbiddenFrame {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        stream_id: Option<u64>,
+        frame: String,
+    },
+    /// The peer closed the connection with the given error.
+    CloseError { is_app: bool, error_code: u64 },
+    /// An HTTP/3 datagram received for the request on the stream satisfies
+    /// the assertion.
+    Datagram {
+        stream_id: u64,
+        assertion: BodyAssertion,
+    },
+}
+
+impl Expectation {
+    /// Evaluates the expectation against `summary`, returning why it wasn't
+    /// met, if it wasn't.
+    fn check(&self, summary: &ConnectionSummary) -> Result<(), String> {
+        self.check_streams(
+            &summary.stream_map,
+            summary.conn_close_details.peer_error(),
+        )
+    }
+
+    /// Evaluates the expectation against the frames received so far and the
+    /// error the peer closed the connection with, if any.
+    pub(crate) fn check_streams(
+        &self, stream_map: &StreamMap, peer_error: Option<&ConnectionError>,
+    ) -> Result<(), String> {
+        match self {
+            Expectation::Status { stream_id, status } => {
+                let headers = stream_map.headers_on_stream(*stream_id);
+
+                let Some(actual) = final_status(&headers) else {
+                    return Err(format!(
+                        "no final response received on stream {stream_id}"
+                    ));
+                };
+
+                if actual != status.to_string() {
+                    return Err(format!(
+                        "expected status {status} on stream {stream_id}, got {actual}"
+                    ));
+                }
+            },
+
+            Expectation::InformationalStatus { stream_id, status } => {
+                let received: Vec<String> = stream_map
+                    .informational_headers_on_stream(*stream_id)
+                    .iter()
+                    .filter_map(|h| h.status_code())
+                    .map(|s| String::from_utf8_lossy(s).into_owned())
+                    .collect();
+
+                if !received.contains(&status.to_string()) {
+                    return Err(format!(
+                        "expected informational status {status} on stream \
+                         {stream_id}, got {received:?}"
+                    ));
+                }
+            },
+
+            Expectation::Header {
+                stream_id,
+                name,
+                value,
+            } => check_field(
+                "header",
+                &stream_map.response_headers_on_stream(*stream_id),
+                *stream_id,
+                name,
+                value.as_deref(),
+            )?,
+
+            Expectation::Trailer {
+                stream_id,
+                name,
+                value,
+            } => check_field(
+                "trailer",
+                stream_map.trailers_on_stream(*stream_id).as_slice(),
+                *stream_id,
+                name,
+                value.as_deref(),
+            )?,
+
+            Expectation::ForbiddenFrame { stream_id, frame } => {
+                let frames = match stream_id {
+                    Some(id) => stream_map.stream(*id),
+
+                    None => stream_map.all_frames(),
+                };
+
+                let count = frames
+                    .iter()
+                    .filter(|f| frame_name(f).eq_ignore_ascii_case(frame))
+                    .count();
+
+                if count > 0 {
+                    let location = match stream_id {
+                        Some(id) => format!("stream {id}"),
+
+                        None => "the connection".to_string(),
+                    };
+
+                    return Err(format!(
+                        "received {count} {frame} frame(s) on {location}"
+                    ));
+                }
+            },
+
+            Expectation::CloseError { is_app, error_code } => match peer_error {
+                Some(e) if e.is_app == *is_app && e.error_code == *error_code =>
+                    (),
+
+                Some(e) =>
+                    return Err(format!(
+                        "expected peer to close with {}, got {}",
+                        describe_error(*is_app, *error_code),
+                        describe_error(e.is_app, e.error_code)
+                    )),
+
+                None =>
+                    return Err(format!(
+                        "expected peer to close with {}, but it didn't close \
+                             with an error",
+                        describe_error(*is_app, *error_code)
+                    )),
+            },
+
+            Expectation::Datagram {
+                stream_id,
+                assertion,
+            } => {
+                let datagrams = stream_map.datagrams_on_stream(*stream_id);
+
+                if datagrams.is_empty() {
+                    return Err(format!(
+                        "no datagram received for stream {stream_id}"
+                    ));
+                }
+
+                if !datagrams.iter().any(|d| assertion.check(d).is_ok()) {
+                    return Err(format!(
+                        "none of the {} datagram(s) received for stream \
+                         {stream_id} matched {assertion:?}",
+                        datagrams.len()
+                    ));
+                }
+            },
+        }
+
+        Ok(())
+    }
+}
+
+/// Checks that `headers`, received on `stream_id`, contain a field called
+/// `name`, with `value` if set. `kind` names the fields in errors.
+fn check_field(
+    kind: &str, headers: &[EnrichedHeaders], stream_id: u64, name: &str,
+    value: Option<&str>,
+) -> Result<(), String> {
+    let values: Vec<String> = headers
+        .iter()
+        .flat_map(|h| h.headers().to_vec())
+        .filter(|h| h.name().eq_ignore_ascii_case(name.as_bytes()))
+        .map(|h| String::from_utf8_lossy(h.value()).into_owned())
+        .collect();
+
+    if values.is_empty() {
+        return Err(format!("{kind} {name} not received on stream {stream_id}"));
+    }
+
+    if let Some(value) = value {
+        if !values.iter().any(|v| v == value) {
+            return Err(format!(
+                "expected {kind} {name}: {value} on stream {stream_id}, got {values:?}"
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// The `:status` of the first non-informational response in `headers`.
+fn final_status(headers: &[EnrichedHeaders]) -> Option<String> {
+    headers
+        .iter()
+        .filter_map(|h| h.status_code())
+        .map(|s| String::from_utf8_lossy(s).into_owned())
+        .find(|s| !s.starts_with('1'))
+}
+
+fn describe_error(is_app: bool, error_code: u64) -> String {
+    let kind = if is_app { "application" } else { "transport" };
+
+    format!("{kind} error {error_code:#x}")
+}
+
+/// A set of [Expectation]s on the outcome of a connection.
+///
+/// # Example
+///
+/// ```
+/// use h3i::client::connection_summary::ConnectionSummary;
+/// use h3i::client::expectations::Expectations;
+///
+/// let expectations = Expectations::new()
+///     .with_status(0, 200)
+///     .with_header(0, "content-type", Some("text/html"))
+///     .with_forbidden_frame(None, "GOAWAY");
+///
+/// let report = expectations.evaluate(&ConnectionSummary::default());
+/// assert!(!report.passed());
+/// assert_eq!(report.failures().count(), 2);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Expectations {
+    expectations: Vec<Expectation>,
+}
+
+impl Expectations {
+    /// Creates an empty set of expectations.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Expects the final response on `stream_id` to have `status`.
+    pub fn with_status(self, stream_id: u64, status: u16) -> Self {
+        self.with(Expectation::Status { stream_id, status })
+    }
+
+    /// Expects an informational response with `status` on `stream_id`, before
+    /// the final response.
+    pub fn with_informational_status(self, stream_id: u64, status: u16) -> Self {
+        self.with(Expectation::InformationalStatus { stream_id, status })
+    }
+
+    /// Expects a response field called `name` on `stream_id`, with `value` if
+    /// set.
+    pub fn with_header(
+        self, stream_id: u64, name: &str, value: Option<&str>,
+    ) -> Self {
+        self.with(Expectation::Header {
+            stream_id,
+            name: name.to_string(),
+            value: value.map(str::to_string),
+        })
+    }
+
+    /// Expects a trailer field called `name` on `stream_id`, with `value` if
+    /// set.
+    pub fn with_trailer(
+        self, stream_id: u64, name: &str, value: Option<&str>,
+    ) -> Self {
+        self.with(Expectation::Trailer {
+            stream_id,
+            name: name.to_string(),
+            value: value.map(str::to_string),
+        })
+    }
+
+    /// Expects no `frame` frame to be received on `stream_id`, or on any
+    /// stream if it's `None`.
+    pub fn with_forbidden_frame(
+        self, stream_id: Option<u64>, frame: &str,
+    ) -> Self {
+        self.with(Expectation::ForbiddenFrame {
+            stream_id,
+            frame: frame.to_string(),
+        })
+    }
+
+    /// Expects the peer to close the connection with the given error.
+    pub fn with_close_error(self, is_app: bool, error_code: u64) -> Self {
+        self.with(Expectation::CloseError { is_app, error_code })
+    }
+
+    /// Expects an HTTP/3 datagram satisfying `assertion` to be received for
+    /// the request on `stream_id`.
+    pub fn with_datagram(self, stream_id: u64, assertion: BodyAssertion) -> Self {
+        self.with(Expectation::Datagram {
+            stream_id,
+            assertion,
+        })
+    }
+
+    /// Adds an arbitrary [Expectation].
+    pub fn with(mut self, expectation: Expectation) -> Self {
+        self.expectations.push(expectation);
+        self
+    }
+
+    /// The expectations, in the order they were added.
+    pub fn expectations(&self) -> &[Expectation] {
+        &self.expectations
+    }
+
+    /// Whether there are no expectations.
+    pub fn is_empty(&self) -> bool {
+        self.expectations.is_empty()
+    }
+
+    /// Evaluates all the expectations against `summary`.
+    pub fn evaluate(&self, summary: &ConnectionSummary) -> ExpectationsReport {
+        let results = self
+            .expectations
+            .iter()
+            .map(|expectation| ExpectationResult {
+                expectation: expectation.clone(),
+                failure: expectation.check(summary).err(),
+            })
+            .collect();
+
+        ExpectationsReport { results }
+    }
+}
+
+impl From<Vec<Expectation>> for Expectations {
+    fn from(expectations: Vec<Expectation>) -> Self {
+        Self { expectations }
+    }
+}
+
+/// The outcome of evaluating [Expectations].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct ExpectationsReport {
+    /// The result of each expectation, in the order they were added.
+    pub results: Vec<ExpectationResult>,
+}
+
+impl ExpectationsReport {
+    /// Whether all expectations were met.
+    pub fn passed(&self) -> bool {
+        self.results.iter().all(ExpectationResult::passed)
+    }
+
+    /// The results of the expectations that weren't met.
+    pub fn failures(&self) -> impl Iterator<Item = &ExpectationResult> {
+        self.results.iter().filter(|r| !r.passed())
+    }
+}
+
+/// The result of evaluating a single [Expectation].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ExpectationResult {
+    /// The expectation.
+    pub expectation: Expectation,
+    /// Why the expectation wasn't met, if it wasn't.
+    pub failure: Option<String>,
+}
+
+impl ExpectationResult {
+    /// Whether the expectation was met.
+    pub fn passed(&self) -> bool {
+        self.failure.is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::frame::H3iFrame;
+    use crate::frame::ResetStream;
+    use quiche::h3::frame::Frame as QFrame;
+    use quiche::h3::Header;
+
+    fn summary() -> ConnectionSummary {
+        let early_hints =
+            H3iFrame::Headers(vec![Header::new(b":status", b"103")].into());
+        let response = H3iFrame::Headers(
+            vec![
+                Header::new(b":status", b"200"),
+                Header::new(b"content-type", b"text/html"),
+            ]
+            .into(),
+        );
+        let data = H3iFrame::QuicheH3(QFrame::Data {
+            payload: b"hello".to_vec(),
+        });
+        let trailers =
+            H3iFrame::Headers(vec![Header::new(b"grpc-status", b"0")].into());
+        let reset = H3iFrame::ResetStream(ResetStream {
+            stream_id: 4,
+            error_code: 0x10c,
+        });
+
+        let mut summary = ConnectionSummary {
+            stream_map: vec![
+                (0, vec![early_hints, response, data, trailers]),
+                (4, vec![reset]),
+            ]
+            .into(),
+            ..Default::default()
+        };
+
+        summary
+            .stream_map
+            .insert_datagram(&[0x00, b'p', b'i', b'n', b'g']);
+
+        summary.conn_close_details.peer_error = Some(ConnectionError {
+            is_app: true,
+            error_code: 0x100,
+            reason: vec![],
+        });
+
+        summary
+    }
+
+    #[test]
+    fn expectations_met() {
+        let report = Expectations::new()
+            .with_status(0, 200)
+            .with_informational_status(0, 103)
+            .with_header(0, "Content-Type", None)
+            .with_header(0, "content-type", Some("text/html"))
+            .with_trailer(0, "grpc-status", Some("0"))
+            .with_forbidden_frame(None, "GOAWAY")
+            .with_forbidden_frame(Some(0), "RESET_STREAM")
+            .with_close_error(true, 0x100)
+            .with_datagram(0, BodyAssertion::Bytes(b"ping".to_vec()))
+            .evaluate(&summary());
+
+        assert_eq!(report.results.len(), 9);
+        assert!(report.passed());
+    }
+
+    #[test]
+    fn expectations_failed() {
+        let report = Expectations::new()
+            .with_status(0, 404)
+            .with_status(8, 200)
+            .with_informational_status(0, 100)
+            .with_header(0, "content-type", Some("text/plain"))
+            .with_header(0, "server", None)
+            .with_header(0, "grpc-status", None)
+            .with_trailer(0, "grpc-status", Some("2"))
+            .with_trailer(4, "grpc-status", None)
+            .with_forbidden_frame(None, "reset_stream")
+            .with_close_error(false, 0x1)
+            .with_datagram(0, BodyAssertion::Length(3))
+            .with_datagram(4, BodyAssertion::Length(4))
+            .evaluate(&summary());
+
+        assert!(!report.passed());
+
+        let failures: Vec<&str> = report
+            .failures()
+            .map(|r| r.failure.as_deref().unwrap())
+            .collect();
+
+        assert_eq!(failures, vec![
+            "expected status 404 on stream 0, got 200",
+            "no final response received on stream 8",
+            "expected informational status 100 on stream 0, got [\"103\"]",
+            "expected header content-type: text/plain on stream 0, got [\"text/html\"]",
+            "header server not received on stream 0",
+            "header grpc-status not received on stream 0",
+            "expected trailer grpc-status: 2 on stream 0, got [\"0\"]",
+            "trailer grpc-status not received on stream 4",
+            "received 1 reset_stream frame(s) on the connection",
+            "expected peer to close with transport error 0x1, got application error 0x100",
+            "none of the 1 datagram(s) received for stream 0 matched Length(3)",
+            "no datagram received for stream 4",
+        ]);
+
+        let report = Expectations::new()
+            .with_close_error(true, 0x100)
+            .evaluate(&ConnectionSummary::default());
+
+        assert_eq!(
+            report.results[0].failure.as_deref(),
+            Some(
+                "expected peer to close with application error 0x100, but it \
+                 didn't close with an error"
+            )
+        );
+    }
+
+    #[test]
+    fn serde() {
+        let expectations = Expectations::new()
+            .with_status(0, 200)
+            .with_header(0, "server", None)
+            .with_forbidden_frame(Some(4), "GREASE")
+            .with_close_error(true, 0x100);
+
+        let json = serde_json::to_string(&expectations).unwrap();
+        assert_eq!(
+            json,
+            r#"[{"status":{"stream_id":0,"status":200}},{"header":{"stream_id":0,"name":"server"}},{"forbidden_frame":{"stream_id":4,"frame":"GREASE"}},{"close_error":{"is_app":true,"error_code":256}}]"#
+        );
+
+        assert_eq!(
+            serde_json::from_str::<Expectations>(&json).unwrap(),
+            expectations
+        );
+    }
+}
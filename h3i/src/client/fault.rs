@@ -0,0 +1,387 @@
+// Copyright (C) 2025, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Injects the [HandshakeFaults] into the datagrams exchanged with the server.
+
+use std::time::Instant;
+
+use crate::config::HandshakeFaults;
+
+/// The QUIC v1 long header packet type of Handshake packets.
+const HANDSHAKE_PACKET_TYPE: u8 = 0x2;
+
+/// The QUIC v1 long header packet type of Retry packets.
+const RETRY_PACKET_TYPE: u8 = 0x3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum FirstFlight {
+    Pending,
+    Discarding,
+    Done,
+}
+
+/// Drops the datagrams, or the packets in them, that the configured faults
+/// call for.
+pub(crate) struct FaultInjector {
+    faults: HandshakeFaults,
+    start: Instant,
+    sent_first: bool,
+    first_flight: FirstFlight,
+    dropped_sent: usize,
+    dropped_received: usize,
+}
+
+impl FaultInjector {
+    pub(crate) fn new(faults: HandshakeFaults) -> Self {
+        let first_flight = if faults.discard_server_first_flight {
+            FirstFlight::Pending
+        } else {
+            FirstFlight::Done
+        };
+
+        Self {
+            faults,
+            start: Instant::now(),
+            sent_first: false,
+            first_flight,
+            dropped_sent: 0,
+            dropped_received: 0,
+        }
+    }
+
+    /// Applies the faults to a datagram about to be sent. Returns the length
+    /// of what remains to be sent, or `None` if it must be dropped.
+    pub(crate) fn outgoing(&mut self, buf: &mut [u8]) -> Option<usize> {
+        if self.faults.stop_after_first_initial && self.sent_first {
+            return self.drop_sent();
+        }
+
+        self.sent_first = true;
+
+        match self.strip_handshake(buf) {
+            0 => self.drop_sent(),
+
+            len => Some(len),
+        }
+    }
+
+    /// Applies the faults to a datagram received from the server. Returns the
+    /// length of what remains to be processed, or `None` if it must be
+    /// dropped.
+    pub(crate) fn incoming(&mut self, buf: &mut [u8]) -> Option<usize> {
+        if self.first_flight != FirstFlight::Done {
+            self.first_flight = FirstFlight::Discarding;
+
+            return self.drop_received();
+        }
+
+        match long_header(buf) {
+            Some((0, _)) if self.faults.drop_version_negotiation =>
+                return self.drop_received(),
+
+            Some((version, RETRY_PACKET_TYPE))
+                if version != 0 && self.faults.drop_retry =>
+                return self.drop_received(),
+
+            _ => (),
+        }
+
+        match self.strip_handshake(buf) {
+            0 => self.drop_received(),
+
+            len => Some(len),
+        }
+    }
+
+    /// Signals that all the datagrams received so far were read, which ends
+    /// the server's first flight.
+    pub(crate) fn end_of_read_burst(&mut self) {
+        if self.first_flight == FirstFlight::Discarding {
+            log::info!("discarded the server's first flight");
+
+            self.first_flight = FirstFlight::Done;
+        }
+    }
+
+    fn drop_sent(&mut self) -> Option<usize> {
+        self.dropped_sent += 1;
+        None
+    }
+
+    fn drop_received(&mut self) -> Option<usize> {
+        self.dropped_received += 1;
+        None
+    }
+
+    fn strip_handshake(&self, buf: &mut [u8]) -> usize {
+        match self.faults.drop_handshake {
+            Some(period) if self.start.elapsed() < period =>
+                strip_handshake_packets(buf),
+
+            _ => buf.len(),
+        }
+    }
+}
+
+impl Drop for FaultInjector {
+    fn drop(&mut self) {
+        if self.dropped_sent > 0 || self.dropped_received > 0 {
+            log::info!(
+                "fault injection dropped {} sent and {} received datagrams",
+                self.dropped_sent,
+                self.dropped_received
+            );
+        }
+    }
+}
+
+/// Removes the Handshake packets from a datagram of possibly coalesced
+/// packets, moving the remaining ones to the front of `buf`. Returns the
+/// length of the remaining packets.
+///
+/// The datagram is left untouched if it can't be parsed.
+fn strip_handshake_packets(buf: &mut [u8]) -> usize {
+    let mut packets = vec![];
+    let mut off = 0;
+
+    while off < buf.len() {
+        let Some(len) = packet_len(&buf[off..]) else {
+            return buf.len();
+        };
+
+        let ty = (buf[off] & 0x30) >> 4;
+        let is_handshake = buf[off] & 0x80 != 0 && ty == HANDSHAKE_PACKET_TYPE;
+
+        if !is_handshake {
+            packets.push(off..off + len);
+        }
+
+        off += len;
+    }
+
+    let mut len = 0;
+
+    for packet in packets {
+        let packet_len = packet.len();
+
+        buf.copy_within(packet, len);
+        len += packet_len;
+    }
+
+    len
+}
+
+/// Returns the version and packet type of the first packet in `buf`, if it
+/// has a long header.
+fn long_header(buf: &[u8]) -> Option<(u32, u8)> {
+    let mut b = octets::Octets::with_slice(buf);
+
+    let first = b.get_u8().ok()?;
+
+    if first & 0x80 == 0 {
+        return None;
+    }
+
+    Some((b.get_u32().ok()?, (first & 0x30) >> 4))
+}
+
+/// Returns the length of the first packet in `buf`.
+///
+/// Short header packets, Version Negotiation and Retry packets always extend
+/// to the end of the datagram.
+fn packet_len(buf: &[u8]) -> Option<usize> {
+    let mut b = octets::Octets::with_slice(buf);
+
+    let first = b.get_u8().ok()?;
+
+    if first & 0x80 == 0 {
+        return Some(buf.len());
+    }
+
+    let version = b.get_u32().ok()?;
+    let ty = (first & 0x30) >> 4;
+
+    // Version Negotiation and Retry.
+    if version == 0 || ty == 0x3 {
+        return Some(buf.len());
+    }
+
+    b.get_bytes_with_u8_length().ok()?;
+    b.get_bytes_with_u8_length().ok()?;
+
+    // Initial packets carry a token.
+    if ty == 0x0 {
+        b.get_bytes_with_varint_length().ok()?;
+    }
+
+    let len = b.get_varint().ok()? as usize;
+    let end = b.off().checked_add(len)?;
+
+    if end > buf.len() {
+        return None;
+    }
+
+    Some(end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::Duration;
+
+    fn long_packet(ty: u8, payload: &[u8]) -> Vec<u8> {
+        let mut p = vec![0xc0 | (ty << 4), 0, 0, 0, 1];
+
+        // DCID and SCID.
+        p.extend([4, 1, 2, 3, 4, 4, 5, 6, 7, 8]);
+
+        if ty == 0x0 {
+            // Empty token.
+            p.push(0);
+        }
+
+        p.extend([0x40, payload.len() as u8]);
+        p.extend(payload);
+        p
+    }
+
+    fn short_packet(payload: &[u8]) -> Vec<u8> {
+        let mut p = vec![0x40, 1, 2, 3, 4];
+        p.extend(payload);
+        p
+    }
+
+    #[test]
+    fn strip_coalesced_handshake() {
+        let initial = long_packet(0x0, &[0xaa; 20]);
+        let handshake = long_packet(HANDSHAKE_PACKET_TYPE, &[0xbb; 30]);
+        let short = short_packet(&[0xcc; 10]);
+
+        let mut buf =
+            [initial.clone(), handshake.clone(), short.clone()].concat();
+        let len = strip_handshake_packets(&mut buf);
+        assert_eq!(&buf[..len], [initial.clone(), short].concat());
+
+        let mut buf = handshake.clone();
+        assert_eq!(strip_handshake_packets(&mut buf), 0);
+
+        // Truncated packets are left alone.
+        let mut buf = [initial, handshake[..10].to_vec()].concat();
+        assert_eq!(strip_handshake_packets(&mut buf), buf.len());
+    }
+
+    #[test]
+    fn drop_handshake() {
+        let mut faults = FaultInjector::new(HandshakeFaults {
+            drop_handshake: Some(Duration::from_secs(60)),
+            ..Default::default()
+        });
+
+        let initial = long_packet(0x0, &[0xaa; 20]);
+        let handshake = long_packet(HANDSHAKE_PACKET_TYPE, &[0xbb; 30]);
+
+        let mut buf = [initial.clone(), handshake.clone()].concat();
+        assert_eq!(faults.outgoing(&mut buf), Some(initial.len()));
+
+        let mut buf = handshake.clone();
+        assert_eq!(faults.incoming(&mut buf), None);
+
+        assert_eq!((faults.dropped_sent, faults.dropped_received), (0, 1));
+
+        let mut faults = FaultInjector::new(HandshakeFaults {
+            drop_handshake: Some(Duration::ZERO),
+            ..Default::default()
+        });
+
+        let mut buf = handshake.clone();
+        assert_eq!(faults.incoming(&mut buf), Some(handshake.len()));
+    }
+
+    #[test]
+    fn stop_after_first_initial() {
+        let mut faults = FaultInjector::new(HandshakeFaults {
+            stop_after_first_initial: true,
+            ..Default::default()
+        });
+
+        let mut buf = long_packet(0x0, &[0xaa; 20]);
+        assert_eq!(faults.outgoing(&mut buf), Some(buf.len()));
+        assert_eq!(faults.outgoing(&mut buf), None);
+        assert_eq!(faults.outgoing(&mut buf), None);
+
+        assert_eq!((faults.dropped_sent, faults.dropped_received), (2, 0));
+    }
+
+    #[test]
+    fn discard_server_first_flight() {
+        let mut faults = FaultInjector::new(HandshakeFaults {
+            discard_server_first_flight: true,
+            ..Default::default()
+        });
+
+        let mut buf = long_packet(0x0, &[0xaa; 20]);
+
+        // Nothing was received yet.
+        faults.end_of_read_burst();
+
+        assert_eq!(faults.incoming(&mut buf), None);
+        assert_eq!(faults.incoming(&mut buf), None);
+
+        faults.end_of_read_burst();
+
+        assert_eq!(faults.incoming(&mut buf), Some(buf.len()));
+        assert_eq!((faults.dropped_sent, faults.dropped_received), (0, 2));
+    }
+
+    #[test]
+    fn drop_retry_and_version_negotiation() {
+        let retry = long_packet(RETRY_PACKET_TYPE, &[0xaa; 20]);
+        let initial = long_packet(0x0, &[0xbb; 20]);
+        let mut vn = vec![0x80, 0, 0, 0, 0, 4, 1, 2, 3, 4, 4, 5, 6, 7, 8];
+        vn.extend([0, 0, 0, 1]);
+
+        let mut faults = FaultInjector::new(HandshakeFaults::default());
+        assert_eq!(faults.incoming(&mut retry.clone()), Some(retry.len()));
+        assert_eq!(faults.incoming(&mut vn.clone()), Some(vn.len()));
+
+        let mut faults = FaultInjector::new(HandshakeFaults {
+            drop_retry: true,
+            ..Default::default()
+        });
+        assert_eq!(faults.incoming(&mut retry.clone()), None);
+        assert_eq!(faults.incoming(&mut vn.clone()), Some(vn.len()));
+        assert_eq!(faults.incoming(&mut initial.clone()), Some(initial.len()));
+
+        let mut faults = FaultInjector::new(HandshakeFaults {
+            drop_version_negotiation: true,
+            ..Default::default()
+        });
+        assert_eq!(faults.incoming(&mut vn.clone()), None);
+        assert_eq!(faults.incoming(&mut retry.clone()), Some(retry.len()));
+        assert_eq!((faults.dropped_sent, faults.dropped_received), (0, 1));
+    }
+}
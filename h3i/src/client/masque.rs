@@ -0,0 +1,655 @@
+// Copyright (C) 2025, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Tunnels the connection through a MASQUE proxy, with HTTP/3 CONNECT-UDP
+//! ([RFC 9298]).
+//!
+//! The tunnel relays the datagrams of the connection between a local UDP
+//! socket, which the connection uses as its peer, and HTTP/3 datagrams on a
+//! separate QUIC connection to the proxy. This leaves the connection itself
+//! untouched, so every action works the same with and without a proxy.
+//!
+//! [RFC 9298]: https://www.rfc-editor.org/rfc/rfc9298
+
+use std::net::Ipv4Addr;
+use std::net::Ipv6Addr;
+use std::net::SocketAddr;
+use std::sync::mpsc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use quiche::h3::NameValue;
+
+use crate::actions::h3::encode_h3_datagram;
+use crate::client::sync_client::generate_cid_and_reset_token;
+use crate::client::ClientError;
+use crate::client::DGRAM_QUEUE_LEN;
+use crate::client::QUIC_VERSION;
+use crate::config::Config;
+use crate::config::MasqueProxy;
+
+/// The maximum UDP payload size of the connection to the proxy. It is larger
+/// than the connection's own, so that its packets fit in HTTP/3 datagrams.
+const MAX_TUNNEL_DATAGRAM_SIZE: usize = 1500;
+
+/// How long to wait for the proxy to accept the CONNECT-UDP request.
+const TUNNEL_SETUP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The context ID of UDP payloads, see
+/// [RFC 9298](https://www.rfc-editor.org/rfc/rfc9298#section-4).
+const UDP_PAYLOAD_CONTEXT_ID: u64 = 0;
+
+const TUNNEL_TOKEN: mio::Token = mio::Token(0);
+const RELAY_TOKEN: mio::Token = mio::Token(1);
+const WAKER_TOKEN: mio::Token = mio::Token(2);
+
+/// A CONNECT-UDP tunnel to the server, through a [`MasqueProxy`].
+///
+/// The tunnel is closed when dropped.
+pub(crate) struct MasqueTunnel {
+    relay_addr: SocketAddr,
+    waker: mio::Waker,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl MasqueTunnel {
+    /// Connects to `proxy` and opens a tunnel to the server of `args`. Returns
+    /// once the proxy has accepted the tunnel.
+    pub(crate) fn open(
+        proxy: &MasqueProxy, args: &Config,
+    ) -> Result<Self, ClientError> {
+        let err = |e: std::io::Error| ClientError::Other(e.to_string());
+
+        let proxy_addr = resolve(&proxy.host_port)?;
+        let (target_host, target_port) = target(args);
+        let path =
+            expand_uri_template(&proxy.uri_template, &target_host, target_port);
+
+        let poll = mio::Poll::new().map_err(err)?;
+        let waker = mio::Waker::new(poll.registry(), WAKER_TOKEN).map_err(err)?;
+
+        let bind_addr: SocketAddr = match proxy_addr {
+            SocketAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+            SocketAddr::V6(_) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+        };
+        let mut socket = mio::net::UdpSocket::bind(bind_addr).map_err(err)?;
+        let mut relay =
+            mio::net::UdpSocket::bind((Ipv4Addr::LOCALHOST, 0).into())
+                .map_err(err)?;
+
+        poll.registry()
+            .register(&mut socket, TUNNEL_TOKEN, mio::Interest::READABLE)
+            .map_err(err)?;
+        poll.registry()
+            .register(&mut relay, RELAY_TOKEN, mio::Interest::READABLE)
+            .map_err(err)?;
+
+        let relay_addr = relay.local_addr().map_err(err)?;
+        let local_addr = socket.local_addr().map_err(err)?;
+
+        let conn = connect_to_proxy(proxy, args, proxy_addr, local_addr)?;
+
+        let request = vec![
+            quiche::h3::Header::new(b":method", b"CONNECT"),
+            quiche::h3::Header::new(b":protocol", b"connect-udp"),
+            quiche::h3::Header::new(b":scheme", b"https"),
+            quiche::h3::Header::new(b":authority", proxy.host_port.as_bytes()),
+            quiche::h3::Header::new(b":path", path.as_bytes()),
+            quiche::h3::Header::new(b"capsule-protocol", b"?1"),
+        ];
+
+        log::info!(
+            "opening CONNECT-UDP tunnel to {}:{} through {} ({})",
+            target_host,
+            target_port,
+            proxy.host_port,
+            path
+        );
+
+        let (ready_tx, ready_rx) = mpsc::channel();
+
+        let mut relay = Relay {
+            poll,
+            socket,
+            relay,
+            local_addr,
+            conn,
+            h3_conn: None,
+            request,
+            stream_id: None,
+            client_addr: None,
+            ready: Some(ready_tx),
+        };
+
+        let thread = std::thread::spawn(move || relay.run());
+
+        match ready_rx.recv_timeout(TUNNEL_SETUP_TIMEOUT) {
+            Ok(Ok(())) => Ok(MasqueTunnel {
+                relay_addr,
+                waker,
+                thread: Some(thread),
+            }),
+
+            Ok(Err(e)) => Err(e),
+
+            Err(_) => {
+                // Stop the relay, which is still waiting for the proxy.
+                let _ = waker.wake();
+
+                Err(ClientError::Other(format!(
+                    "MASQUE proxy {} didn't open the tunnel",
+                    proxy.host_port
+                )))
+            },
+        }
+    }
+
+    /// The address the connection sends its datagrams to, in place of the
+    /// server's.
+    pub(crate) fn relay_addr(&self) -> SocketAddr {
+        self.relay_addr
+    }
+}
+
+impl Drop for MasqueTunnel {
+    fn drop(&mut self) {
+        let _ = self.waker.wake();
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// The state of the relay thread of a [`MasqueTunnel`].
+struct Relay {
+    poll: mio::Poll,
+    /// The socket of the connection to the proxy.
+    socket: mio::net::UdpSocket,
+    /// The socket the tunnelled connection sends its datagrams to.
+    relay: mio::net::UdpSocket,
+    local_addr: SocketAddr,
+    conn: quiche::Connection,
+    h3_conn: Option<quiche::h3::Connection>,
+    request: Vec<quiche::h3::Header>,
+    /// The stream of the CONNECT-UDP request, once accepted by the proxy.
+    stream_id: Option<u64>,
+    /// The address of the tunnelled connection, once it sent a datagram.
+    client_addr: Option<SocketAddr>,
+    /// Notifies [`MasqueTunnel::open()`] of the outcome of the request.
+    ready: Option<mpsc::Sender<Result<(), ClientError>>>,
+}
+
+impl Relay {
+    fn run(&mut self) {
+        let mut buf = [0; 65535];
+        let mut events = mio::Events::with_capacity(1024);
+
+        loop {
+            if let Err(e) = self.poll.poll(&mut events, self.conn.timeout()) {
+                log::error!("MASQUE tunnel: poll failed: {}", e);
+                break;
+            }
+
+            if events.is_empty() {
+                self.conn.on_timeout();
+            }
+
+            for event in &events {
+                match event.token() {
+                    TUNNEL_TOKEN => self.recv_from_proxy(&mut buf),
+
+                    RELAY_TOKEN => self.recv_from_client(&mut buf),
+
+                    // The tunnel was dropped.
+                    _ => {
+                        self.conn
+                            .close(
+                                true,
+                                quiche::h3::WireErrorCode::NoError as u64,
+                                b"",
+                            )
+                            .ok();
+                    },
+                }
+            }
+
+            if self.conn.is_established() && self.h3_conn.is_none() {
+                self.send_request();
+            }
+
+            self.poll_h3(&mut buf);
+            self.relay_datagrams(&mut buf);
+
+            while self.conn.scids_left() > 0 {
+                let (scid, reset_token) = generate_cid_and_reset_token();
+
+                if self.conn.new_scid(&scid, reset_token, false).is_err() {
+                    break;
+                }
+            }
+
+            self.flush(&mut buf);
+
+            if self.conn.is_closed() {
+                log::info!(
+                    "MASQUE tunnel closed with error={:?}",
+                    self.conn.peer_error()
+                );
+
+                fail(
+                    &mut self.ready,
+                    format!(
+                        "connection to the MASQUE proxy closed: {:?}",
+                        self.conn.peer_error()
+                    ),
+                );
+
+                break;
+            }
+        }
+    }
+
+    fn recv_from_proxy(&mut self, buf: &mut [u8]) {
+        loop {
+            let (len, from) = match self.socket.recv_from(buf) {
+                Ok(v) => v,
+
+                Err(e) => {
+                    if e.kind() != std::io::ErrorKind::WouldBlock {
+                        log::error!("MASQUE tunnel: recv() failed: {:?}", e);
+                    }
+
+                    return;
+                },
+            };
+
+            let recv_info = quiche::RecvInfo {
+                to: self.local_addr,
+                from,
+                ecn: quiche::Ecn::NotEct,
+            };
+
+            if let Err(e) = self.conn.recv(&mut buf[..len], recv_info) {
+                log::debug!("MASQUE tunnel: recv failed: {:?}", e);
+            }
+        }
+    }
+
+    /// Forwards the datagrams of the tunnelled connection to the proxy.
+    fn recv_from_client(&mut self, buf: &mut [u8]) {
+        loop {
+            let (len, from) = match self.relay.recv_from(buf) {
+                Ok(v) => v,
+
+                Err(e) => {
+                    if e.kind() != std::io::ErrorKind::WouldBlock {
+                        log::error!("MASQUE relay: recv() failed: {:?}", e);
+                    }
+
+                    return;
+                },
+            };
+
+            self.client_addr = Some(from);
+
+            let Some(stream_id) = self.stream_id else {
+                log::debug!("MASQUE relay: dropped datagram, tunnel not open");
+                continue;
+            };
+
+            let dgram = encode_udp_payload(stream_id, &buf[..len]);
+
+            if let Err(e) = self.conn.dgram_send_vec(dgram) {
+                log::debug!("MASQUE relay: dropped datagram: {:?}", e);
+            }
+        }
+    }
+
+    fn send_request(&mut self) {
+        let h3_config = match quiche::h3::Config::new() {
+            Ok(v) => v,
+
+            Err(e) => {
+                fail(
+                    &mut self.ready,
+                    format!("failed to create HTTP/3 config: {e:?}"),
+                );
+                return;
+            },
+        };
+
+        let mut h3_conn = match quiche::h3::Connection::with_transport(
+            &mut self.conn,
+            &h3_config,
+        ) {
+            Ok(v) => v,
+
+            Err(e) => {
+                fail(
+                    &mut self.ready,
+                    format!("failed to create HTTP/3 connection: {e:?}"),
+                );
+                return;
+            },
+        };
+
+        if let Err(e) = h3_conn.send_request(&mut self.conn, &self.request, false)
+        {
+            fail(
+                &mut self.ready,
+                format!("failed to send CONNECT-UDP request: {e:?}"),
+            );
+            self.conn
+                .close(true, quiche::h3::WireErrorCode::InternalError as u64, b"")
+                .ok();
+        }
+
+        self.h3_conn = Some(h3_conn);
+    }
+
+    fn poll_h3(&mut self, buf: &mut [u8]) {
+        let Some(h3_conn) = &mut self.h3_conn else {
+            return;
+        };
+
+        loop {
+            match h3_conn.poll(&mut self.conn) {
+                Ok((stream_id, quiche::h3::Event::Headers { list, .. })) => {
+                    let status = list
+                        .iter()
+                        .find(|h| h.name() == b":status")
+                        .map(|h| String::from_utf8_lossy(h.value()).into_owned())
+                        .unwrap_or_default();
+
+                    if status.starts_with('2') {
+                        log::info!("MASQUE tunnel open on stream {}", stream_id);
+
+                        self.stream_id = Some(stream_id);
+
+                        if let Some(ready) = self.ready.take() {
+                            let _ = ready.send(Ok(()));
+                        }
+                    } else {
+                        let reason = format!(
+                            "MASQUE proxy refused the tunnel with status {status}"
+                        );
+
+                        fail(&mut self.ready, reason);
+                        self.conn
+                            .close(
+                                true,
+                                quiche::h3::WireErrorCode::NoError as u64,
+                                b"",
+                            )
+                            .ok();
+                    }
+                },
+
+                // Capsules aren't used, just consume them.
+                Ok((stream_id, quiche::h3::Event::Data)) =>
+                    while h3_conn
+                        .recv_body(&mut self.conn, stream_id, buf)
+                        .is_ok()
+                    {},
+
+                Ok((_, quiche::h3::Event::Finished)) |
+                Ok((_, quiche::h3::Event::Reset(_))) |
+                Ok((_, quiche::h3::Event::Cancelled(_))) => {
+                    log::info!("MASQUE tunnel closed by the proxy");
+                    fail(
+                        &mut self.ready,
+                        "MASQUE proxy closed the tunnel".to_string(),
+                    );
+                    self.conn
+                        .close(
+                            true,
+                            quiche::h3::WireErrorCode::NoError as u64,
+                            b"",
+                        )
+                        .ok();
+                },
+
+                Ok(_) => (),
+
+                Err(quiche::h3::Error::Done) => break,
+
+                Err(e) => {
+                    log::error!("MASQUE tunnel: HTTP/3 error: {:?}", e);
+                    fail(&mut self.ready, format!("HTTP/3 error: {e:?}"));
+                    break;
+                },
+            }
+        }
+    }
+
+    /// Forwards the datagrams received from the proxy to the tunnelled
+    /// connection.
+    fn relay_datagrams(&mut self, buf: &mut [u8]) {
+        while let Ok(len) = self.conn.dgram_recv(buf) {
+            let Some(stream_id) = self.stream_id else {
+                continue;
+            };
+
+            let Some(payload) = decode_udp_payload(stream_id, &buf[..len]) else {
+                log::debug!("MASQUE relay: ignored datagram len={}", len);
+                continue;
+            };
+
+            let Some(client_addr) = self.client_addr else {
+                continue;
+            };
+
+            if let Err(e) = self.relay.send_to(payload, client_addr) {
+                log::debug!("MASQUE relay: send() failed: {:?}", e);
+            }
+        }
+    }
+
+    fn flush(&mut self, out: &mut [u8]) {
+        loop {
+            let (write, send_info) = match self.conn.send(out) {
+                Ok(v) => v,
+
+                Err(quiche::Error::Done) => break,
+
+                Err(e) => {
+                    log::error!("MASQUE tunnel: send failed: {:?}", e);
+                    self.conn.close(false, 0x1, b"fail").ok();
+                    break;
+                },
+            };
+
+            if let Err(e) = self.socket.send_to(&out[..write], send_info.to) {
+                if e.kind() == std::io::ErrorKind::WouldBlock {
+                    break;
+                }
+
+                log::error!("MASQUE tunnel: send() failed: {:?}", e);
+            }
+        }
+    }
+}
+
+/// Notifies [`MasqueTunnel::open()`] that the tunnel couldn't be opened, if it
+/// is still waiting.
+fn fail(
+    ready: &mut Option<mpsc::Sender<Result<(), ClientError>>>, reason: String,
+) {
+    if let Some(ready) = ready.take() {
+        let _ = ready.send(Err(ClientError::Other(reason)));
+    }
+}
+
+/// Resolves the `<host>:<port>` of the proxy.
+fn resolve(host_port: &str) -> Result<SocketAddr, ClientError> {
+    url::Url::parse(&format!("https://{host_port}"))
+        .ok()
+        .and_then(|url| url.socket_addrs(|| None).ok())
+        .and_then(|addrs| addrs.into_iter().next())
+        .ok_or_else(|| {
+            ClientError::Other(format!("can't resolve MASQUE proxy {host_port}"))
+        })
+}
+
+/// The host and port the proxy should connect to: the `--connect-to` address
+/// if any, otherwise the server's `<host>:<port>`.
+fn target(args: &Config) -> (String, u16) {
+    let host_port = args.connect_to.as_deref().unwrap_or(&args.host_port);
+
+    match host_port.rsplit_once(':') {
+        Some((host, port)) if !host.ends_with(':') => (
+            host.trim_start_matches('[')
+                .trim_end_matches(']')
+                .to_string(),
+            port.parse().unwrap_or(443),
+        ),
+
+        _ => (host_port.to_string(), 443),
+    }
+}
+
+/// Fills in the `{target_host}` and `{target_port}` variables of a URI
+/// template. IPv6 addresses have their colons percent-encoded, see
+/// [RFC 9298](https://www.rfc-editor.org/rfc/rfc9298#section-2).
+fn expand_uri_template(template: &str, host: &str, port: u16) -> String {
+    template
+        .replace("{target_host}", &host.replace(':', "%3A"))
+        .replace("{target_port}", &port.to_string())
+}
+
+/// Wraps a UDP payload in an HTTP/3 datagram of the request on `stream_id`.
+fn encode_udp_payload(stream_id: u64, payload: &[u8]) -> Vec<u8> {
+    let mut context = [0; 8];
+    let mut b = octets::OctetsMut::with_slice(&mut context);
+
+    // Always fits, as the buffer is large enough for any varint.
+    b.put_varint(UDP_PAYLOAD_CONTEXT_ID).unwrap();
+    let len = b.off();
+
+    encode_h3_datagram(stream_id / 4, &[&context[..len], payload].concat())
+}
+
+/// Extracts the UDP payload of an HTTP/3 datagram, if it belongs to the
+/// request on `stream_id`.
+fn decode_udp_payload(stream_id: u64, dgram: &[u8]) -> Option<&[u8]> {
+    let mut b = octets::Octets::with_slice(dgram);
+
+    if b.get_varint().ok()? != stream_id / 4 ||
+        b.get_varint().ok()? != UDP_PAYLOAD_CONTEXT_ID
+    {
+        return None;
+    }
+
+    let off = b.off();
+
+    Some(&dgram[off..])
+}
+
+/// Creates the QUIC connection to the proxy, which supports HTTP/3 datagrams.
+fn connect_to_proxy(
+    proxy: &MasqueProxy, args: &Config, proxy_addr: SocketAddr,
+    local_addr: SocketAddr,
+) -> Result<quiche::Connection, ClientError> {
+    let err = |e: quiche::Error| ClientError::Other(e.to_string());
+
+    let mut config = quiche::Config::new(QUIC_VERSION).map_err(err)?;
+
+    config.verify_peer(args.verify_peer);
+
+    if let Some(ca_file) = &args.ca_file {
+        config
+            .load_verify_locations_from_file(ca_file)
+            .map_err(err)?;
+    }
+    config
+        .set_application_protos(quiche::h3::APPLICATION_PROTOCOL)
+        .map_err(err)?;
+    config.set_max_idle_timeout(args.idle_timeout);
+    config.set_max_recv_udp_payload_size(MAX_TUNNEL_DATAGRAM_SIZE);
+    config.set_max_send_udp_payload_size(MAX_TUNNEL_DATAGRAM_SIZE);
+    config.set_initial_max_data(args.max_data);
+    config
+        .set_initial_max_stream_data_bidi_local(args.max_stream_data_bidi_local);
+    config.set_initial_max_stream_data_bidi_remote(
+        args.max_stream_data_bidi_remote,
+    );
+    config.set_initial_max_stream_data_uni(args.max_stream_data_uni);
+    config.set_initial_max_streams_bidi(args.max_streams_bidi);
+    config.set_initial_max_streams_uni(args.max_streams_uni);
+    config.set_disable_active_migration(true);
+    config.enable_dgram(true, DGRAM_QUEUE_LEN, DGRAM_QUEUE_LEN);
+
+    let server_name = proxy.host_port.rsplit_once(':').map(|(host, _)| host);
+
+    let mut scid = [0; quiche::MAX_CONN_ID_LEN];
+    quiche::rand_bytes(&mut scid);
+    let scid = quiche::ConnectionId::from_ref(&scid);
+
+    quiche::connect(server_name, &scid, local_addr, proxy_addr, &mut config)
+        .map_err(err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uri_template() {
+        let template = "/.well-known/masque/udp/{target_host}/{target_port}/";
+
+        assert_eq!(
+            expand_uri_template(template, "example.org", 443),
+            "/.well-known/masque/udp/example.org/443/"
+        );
+        assert_eq!(
+            expand_uri_template(template, "2001:db8::42", 8443),
+            "/.well-known/masque/udp/2001%3Adb8%3A%3A42/8443/"
+        );
+    }
+
+    #[test]
+    fn target_host_port() {
+        let args = Config::new().with_host_port("example.org:4433".to_string());
+        assert_eq!(target(&args), ("example.org".to_string(), 4433));
+
+        let args = args.with_connect_to("[2001:db8::42]:443".to_string());
+        assert_eq!(target(&args), ("2001:db8::42".to_string(), 443));
+
+        let args = Config::new().with_host_port("example.org".to_string());
+        assert_eq!(target(&args), ("example.org".to_string(), 443));
+    }
+
+    #[test]
+    fn udp_payload() {
+        let dgram = encode_udp_payload(8, b"quic");
+        assert_eq!(dgram, [&[2, 0][..], b"quic"].concat());
+
+        assert_eq!(decode_udp_payload(8, &dgram), Some(&b"quic"[..]));
+        assert_eq!(decode_udp_payload(4, &dgram), None);
+        assert_eq!(decode_udp_payload(8, &[2, 1, 0]), None);
+    }
+}
@@ -30,7 +30,19 @@
 //! as series of [Action]s, and capturing the results in a
 //! [ConnectionSummary].
 
+#[cfg(feature = "async")]
+pub mod async_client;
+pub mod baseline;
+pub(crate) mod cert;
 pub mod connection_summary;
+pub mod diagram;
+pub mod expectations;
+mod fault;
+mod masque;
+mod resolve;
+mod shaper;
+mod socket;
+pub mod suite;
 pub mod sync_client;
 
 use connection_summary::*;
@@ -41,9 +53,12 @@ use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::time::Instant;
 
+use crate::actions::h3::encode_h3_datagram;
 use crate::actions::h3::Action;
 use crate::actions::h3::StreamEvent;
 use crate::actions::h3::StreamEventType;
+use crate::actions::h3::WEBTRANSPORT_BIDI_SIGNAL;
+use crate::actions::h3::WEBTRANSPORT_UNI_STREAM_TYPE;
 use crate::config::Config;
 use crate::frame::H3iFrame;
 use crate::frame::ResetStream;
@@ -67,24 +82,48 @@ use quiche::{
     self,
 };
 
-const MAX_DATAGRAM_SIZE: usize = 1350;
-const QUIC_VERSION: u32 = 1;
+pub(crate) const MAX_DATAGRAM_SIZE: usize = 1350;
+pub(crate) const DGRAM_QUEUE_LEN: usize = 1000;
+pub(crate) const QUIC_VERSION: u32 = 1;
+const MAX_UNKNOWN_TRANSPORT_PARAMS_SIZE: usize = 4096;
+const BURST_MODE_INITIAL_CWND_PACKETS: usize = 1000;
+
+/// A random number taken from quiche's randomness, so that it follows
+/// [`Config::rng_seed`] while the connection runs.
+pub(crate) fn rand_u64() -> u64 {
+    let mut buf = [0; 8];
+
+    quiche::rand_bytes(&mut buf);
+
+    u64::from_ne_bytes(buf)
+}
 
 pub fn build_quiche_connection(
     args: Config, peer_addr: SocketAddr, local_addr: SocketAddr,
 ) -> Result<Connection> {
     // We'll only connect to one server.
     let connect_url = if !args.omit_sni {
-        args.host_port.split(':').next()
+        args.sni
+            .as_deref()
+            .or_else(|| args.host_port.split(':').next())
     } else {
         None
     };
 
     // Create the configuration for the QUIC connection.
-    let mut config = quiche::Config::new(QUIC_VERSION).unwrap();
+    let mut config =
+        quiche::Config::new(args.initial_version.unwrap_or(QUIC_VERSION))?;
 
     config.verify_peer(args.verify_peer);
-    config.set_application_protos(&[b"h3"]).unwrap();
+    let alpn: Vec<&[u8]> = args.alpn.iter().map(|p| p.as_slice()).collect();
+    config.set_application_protos(&alpn)?;
+
+    if let Some(ca_file) = &args.ca_file {
+        if let Err(e) = config.load_verify_locations_from_file(ca_file) {
+            log::error!("failed to load CA file {}: {:?}", ca_file, e);
+            return Err(e);
+        }
+    }
     config.set_max_idle_timeout(args.idle_timeout);
     config.set_max_recv_udp_payload_size(MAX_DATAGRAM_SIZE);
     config.set_max_send_udp_payload_size(MAX_DATAGRAM_SIZE);
@@ -119,9 +158,34 @@ pub fn build_quiche_connection(
 
     config.grease(false);
 
+    if args.session_file.is_some() {
+        config.enable_early_data();
+    }
+
+    if args.enable_dgram {
+        config.enable_dgram(true, DGRAM_QUEUE_LEN, DGRAM_QUEUE_LEN);
+    }
+
+    if args.burst_mode {
+        config.enable_pacing(false);
+        config.set_initial_congestion_window_packets(
+            BURST_MODE_INITIAL_CWND_PACKETS,
+        );
+    }
+
+    for (id, value) in &args.custom_transport_params {
+        config.add_custom_transport_parameter(*id, value);
+    }
+
+    // Keep the server's unknown transport parameters, so that they show up in
+    // the connection summary.
+    config.enable_track_unknown_transport_parameters(
+        MAX_UNKNOWN_TRANSPORT_PARAMS_SIZE,
+    );
+
     // Generate a random source connection ID for the connection.
     let mut scid = [0; quiche::MAX_CONN_ID_LEN];
-    rand::RngCore::fill_bytes(&mut rand::thread_rng(), &mut scid);
+    quiche::rand_bytes(&mut scid);
 
     let scid = quiche::ConnectionId::from_ref(&scid);
 
@@ -135,6 +199,15 @@ pub fn build_quiche_connection(
         }
     }
 
+    // Resume the cached session, if any, so that early data can be sent.
+    if let Some(session_file) = &args.session_file {
+        if let Ok(session) = std::fs::read(session_file) {
+            if let Err(e) = conn.set_session(&session) {
+                log::warn!("failed to resume session from {session_file}: {e}");
+            }
+        }
+    }
+
     log::info!(
         "connecting to {:} from {:} with scid {:?}",
         peer_addr,
@@ -160,11 +233,29 @@ fn handle_qlog(
     }
 }
 
+/// An event received on a connection, reported as soon as it happens by the
+/// clients that drive long-lived connections, see
+/// [`sync_client::connect_interactive()`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ClientEvent {
+    /// A frame was received on a stream.
+    Frame { stream_id: u64, frame: H3iFrame },
+    /// The peer finished or reset a stream.
+    StreamFinished { stream_id: u64 },
+    /// The peer opened a push stream for the push ID `push_id`.
+    PushStream { stream_id: u64, push_id: u64 },
+    /// An HTTP/3 datagram was received.
+    Datagram(Vec<u8>),
+}
+
 #[derive(Debug, Serialize)]
 /// Represents different errors that can occur when [sync_client] runs.
 pub enum ClientError {
     /// An error during the QUIC handshake.
     HandshakeFail,
+    /// The server's certificate failed verification, or matched none of the
+    /// pins of the [`Config`].
+    CertificateVerificationFailed(String),
     /// An error during HTTP/3 exchanges.
     HttpFail,
     /// Some other type of error.
@@ -178,6 +269,9 @@ pub(crate) trait Client {
     /// Handles a response frame. This allows [`Client`]s to customize how they
     /// construct a [`StreamMap`] from a list of frames.
     fn handle_response_frame(&mut self, stream_id: u64, frame: H3iFrame);
+
+    /// Handles a push stream opened by the peer for the push ID `push_id`.
+    fn handle_push_stream(&mut self, stream_id: u64, push_id: u64);
 }
 
 pub(crate) type StreamParserMap = HashMap<u64, FrameParser>;
@@ -312,6 +406,37 @@ pub(crate) fn execute_action(
                 .or_insert_with(|| FrameParser::new(*stream_id));
         },
 
+        Action::OpenWebTransportStream {
+            stream_id,
+            session_id,
+            bidi,
+        } => {
+            log::info!(
+                "open webtransport stream_id={} session_id={} bidi={}",
+                stream_id,
+                session_id,
+                bidi
+            );
+
+            let ty = if *bidi {
+                WEBTRANSPORT_BIDI_SIGNAL
+            } else {
+                WEBTRANSPORT_UNI_STREAM_TYPE
+            };
+
+            let mut d = [42; 16];
+            let mut b = octets::OctetsMut::with_slice(&mut d);
+            b.put_varint(ty).unwrap();
+            b.put_varint(*session_id).unwrap();
+            let off = b.off();
+
+            conn.stream_send(*stream_id, &d[..off], false).unwrap();
+
+            stream_parsers
+                .entry(*stream_id)
+                .or_insert_with(|| FrameParser::raw(*stream_id));
+        },
+
         Action::StreamBytes {
             stream_id,
             bytes,
@@ -323,13 +448,55 @@ pub(crate) fn execute_action(
                 bytes.len(),
                 fin_stream
             );
-            conn.stream_send(*stream_id, bytes, *fin_stream).unwrap();
+            match conn.stream_send(*stream_id, bytes, *fin_stream) {
+                Ok(written) if written < bytes.len() => log::warn!(
+                    "only {} of {} stream bytes were written on stream {}",
+                    written,
+                    bytes.len(),
+                    stream_id
+                ),
+
+                Ok(_) => (),
+
+                // The bytes are often deliberately invalid, so the stream may
+                // well have been reset or the connection closed in response
+                // to earlier ones.
+                Err(e) => {
+                    log::error!(
+                        "can't send stream bytes on stream {}: {}",
+                        stream_id,
+                        e
+                    );
+                    return;
+                },
+            }
 
             stream_parsers
                 .entry(*stream_id)
                 .or_insert_with(|| FrameParser::new(*stream_id));
         },
 
+        Action::SendQpackInstruction {
+            stream_id,
+            instruction,
+        } => {
+            log::info!(
+                "qpack instruction tx id={} instruction={:?}",
+                stream_id,
+                instruction
+            );
+
+            if let Err(e) =
+                conn.stream_send(*stream_id, &instruction.to_bytes(), false)
+            {
+                log::error!(
+                    "can't send QPACK instruction on stream {}: {}",
+                    stream_id,
+                    e
+                );
+            }
+        },
+
         Action::ResetStream {
             stream_id,
             error_code,
@@ -392,8 +559,126 @@ pub(crate) fn execute_action(
             let _ = conn.close(*is_app, *error_code, reason);
         },
 
-        // Neither of these actions will manipulate the Quiche connection
-        Action::FlushPackets | Action::Wait { .. } => unreachable!(),
+        Action::MaxData { max } => {
+            log::info!("max_data max={}", max);
+
+            send_flow_control_frame(conn, quiche::FlowControlFrame::MaxData {
+                max: *max,
+            });
+        },
+
+        Action::MaxStreamData { stream_id, max } => {
+            log::info!("max_stream_data stream_id={} max={}", stream_id, max);
+
+            send_flow_control_frame(
+                conn,
+                quiche::FlowControlFrame::MaxStreamData {
+                    stream_id: *stream_id,
+                    max: *max,
+                },
+            );
+        },
+
+        Action::MaxStreams { bidi, max } => {
+            log::info!("max_streams bidi={} max={}", bidi, max);
+
+            let frame = if *bidi {
+                quiche::FlowControlFrame::MaxStreamsBidi { max: *max }
+            } else {
+                quiche::FlowControlFrame::MaxStreamsUni { max: *max }
+            };
+
+            send_flow_control_frame(conn, frame);
+        },
+
+        Action::WithholdFlowControlUpdates { withhold } => {
+            log::info!("withhold_flow_control_updates={}", withhold);
+            conn.withhold_flow_control_updates(*withhold);
+        },
+
+        Action::Ping => {
+            log::info!("ping");
+            send_raw_frame(conn, &[0x01]);
+        },
+
+        Action::Padding { len } => {
+            log::info!("padding len={}", len);
+            send_raw_frame(conn, &vec![0; *len]);
+        },
+
+        Action::Burst { packets } => {
+            log::info!("burst packets={}", packets);
+
+            // Fill each packet with a PING frame followed by PADDING, leaving
+            // room for the largest short header and the AEAD tag.
+            let overhead = 1 + quiche::MAX_CONN_ID_LEN + 4 + 16;
+            let mut frame = vec![
+                0;
+                conn.max_send_udp_payload_size()
+                    .saturating_sub(overhead)
+            ];
+            frame[0] = 0x01;
+
+            for _ in 0..*packets {
+                send_raw_frame(conn, &frame);
+            }
+        },
+
+        Action::RawQuicFrame { bytes } => {
+            log::info!("raw_quic_frame len={}", bytes.len());
+            send_raw_frame(conn, bytes);
+        },
+
+        Action::SendDatagram {
+            quarter_stream_id,
+            payload,
+        } => {
+            log::info!(
+                "send_datagram quarter_stream_id={} len={}",
+                quarter_stream_id,
+                payload.len()
+            );
+
+            let dgram = encode_h3_datagram(*quarter_stream_id, payload);
+
+            if let Err(e) = conn.dgram_send(&dgram) {
+                log::error!("can't send datagram: {}", e);
+            }
+        },
+
+        Action::UpdateKeys => {
+            log::info!("update_keys");
+
+            if let Err(e) = conn.update_key() {
+                log::error!("can't update keys: {}", e);
+            }
+        },
+
+        // None of these actions will manipulate the Quiche connection directly
+        Action::FlushPackets |
+        Action::Migrate { .. } |
+        Action::ProbePath { .. } |
+        Action::SwitchPath { .. } |
+        Action::Wait { .. } |
+        Action::AssertBody { .. } |
+        Action::SnapshotStats { .. } |
+        Action::OpenConcurrentRequests { .. } |
+        Action::Repeat { .. } |
+        Action::OnFrame { .. } => unreachable!(),
+    }
+}
+
+fn send_flow_control_frame(
+    conn: &mut quiche::Connection, frame: quiche::FlowControlFrame,
+) {
+    if let Err(e) = conn.send_flow_control_frame(frame) {
+        log::error!("can't send {:?}: {}", frame, e);
+    }
+}
+
+fn send_raw_frame(conn: &mut quiche::Connection, bytes: &[u8]) {
+    if let Err(e) = conn.send_raw_frame(bytes) {
+        log::error!("can't send {} raw frame bytes: {}", bytes.len(), e);
     }
 }
 
@@ -404,9 +689,19 @@ pub(crate) fn parse_streams<C: Client>(
         Vec::with_capacity(conn.readable().len());
 
     for stream in conn.readable() {
-        // TODO: ignoring control streams
-        if stream % 4 != 0 {
-            continue;
+        // Unidirectional streams opened by the peer are identified by their
+        // stream type. Only the control and push streams carry frames.
+        match stream % 4 {
+            0 => (),
+
+            3 => {
+                client
+                    .stream_parsers_mut()
+                    .entry(stream)
+                    .or_insert_with(|| FrameParser::uni(stream));
+            },
+
+            _ => continue,
         }
 
         loop {
@@ -440,6 +735,12 @@ pub(crate) fn parse_streams<C: Client>(
                     }
                 },
                 Ok(FrameParseResult::Retry) => {},
+                Ok(FrameParseResult::PushStream { push_id }) => {
+                    log::info!(
+                        "received push stream {stream} for push ID {push_id}"
+                    );
+                    client.handle_push_stream(stream, push_id);
+                },
                 Ok(FrameParseResult::Interrupted(cause)) => {
                     if let InterruptCause::ResetStream(error_code) = cause {
                         let frame = H3iFrame::ResetStream(ResetStream {
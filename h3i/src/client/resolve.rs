@@ -0,0 +1,199 @@
+// Copyright (C) 2025, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Resolves the server's host name, as configured by [DnsResolution].
+
+use std::net::SocketAddr;
+use std::net::ToSocketAddrs;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use crate::config::AddressSelection;
+use crate::config::DnsResolution;
+use crate::config::IpVersion;
+
+use super::rand_u64;
+use super::ClientError;
+
+/// Resolves `host_port`, a `<host>:<port>` with an optional port, to the
+/// address to connect to.
+///
+/// IP literals are used as is. Other hosts are looked up in the overrides of
+/// `dns`, then in the system resolver. The addresses that don't match
+/// `ip_version` are left out before one is selected.
+pub(crate) fn resolve(
+    host_port: &str, dns: &DnsResolution, ip_version: Option<IpVersion>,
+) -> Result<SocketAddr, ClientError> {
+    let url = url::Url::parse(&format!("https://{host_port}")).map_err(|e| {
+        ClientError::Other(format!("invalid host {host_port}: {e}"))
+    })?;
+
+    let port = url.port_or_known_default().unwrap_or(443);
+
+    let addrs = match url.host() {
+        Some(url::Host::Ipv4(ip)) => vec![SocketAddr::new(ip.into(), port)],
+
+        Some(url::Host::Ipv6(ip)) => vec![SocketAddr::new(ip.into(), port)],
+
+        Some(url::Host::Domain(host)) => match dns.overrides.get(host) {
+            Some(ips) =>
+                ips.iter().map(|ip| SocketAddr::new(*ip, port)).collect(),
+
+            None => lookup(host, port, dns.timeout)?,
+        },
+
+        None =>
+            return Err(ClientError::Other(format!("no host in {host_port}"))),
+    };
+
+    let addrs: Vec<SocketAddr> = addrs
+        .into_iter()
+        .filter(|addr| ip_version.map_or(true, |v| v.matches(&addr.ip())))
+        .collect();
+
+    log::debug!("resolved {} to {:?}", host_port, addrs);
+
+    select(&addrs, dns.selection).ok_or_else(|| {
+        ClientError::Other(format!(
+            "no {:?} address found for {} with {:?}",
+            ip_version, host_port, dns.selection
+        ))
+    })
+}
+
+/// Looks `host` up with the system resolver, giving up after `timeout` if
+/// set.
+///
+/// The lookup runs on its own thread, as the system resolver can't be
+/// interrupted. The thread is left to finish on its own when it times out.
+fn lookup(
+    host: &str, port: u16, timeout: Option<Duration>,
+) -> Result<Vec<SocketAddr>, ClientError> {
+    let (tx, rx) = mpsc::channel();
+    let name = host.to_string();
+
+    std::thread::spawn(move || {
+        let _ = tx.send((name.as_str(), port).to_socket_addrs());
+    });
+
+    let res = match timeout {
+        Some(timeout) => rx.recv_timeout(timeout).map_err(|_| {
+            ClientError::Other(format!(
+                "resolving {host} timed out after {timeout:?}"
+            ))
+        })?,
+
+        None => rx.recv().map_err(|_| {
+            ClientError::Other(format!("resolving {host} failed"))
+        })?,
+    };
+
+    res.map(|addrs| addrs.collect())
+        .map_err(|e| ClientError::Other(format!("can't resolve {host}: {e}")))
+}
+
+/// Selects one of `addrs`.
+fn select(
+    addrs: &[SocketAddr], selection: AddressSelection,
+) -> Option<SocketAddr> {
+    let index = match selection {
+        AddressSelection::First => 0,
+
+        AddressSelection::Index(index) => index,
+
+        AddressSelection::Random if addrs.is_empty() => return None,
+
+        AddressSelection::Random => (rand_u64() % addrs.len() as u64) as usize,
+    };
+
+    addrs.get(index).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashMap;
+
+    fn dns(selection: AddressSelection) -> DnsResolution {
+        DnsResolution {
+            overrides: HashMap::from([("test.com".to_string(), vec![
+                "192.0.2.1".parse().unwrap(),
+                "2001:db8::1".parse().unwrap(),
+                "192.0.2.2".parse().unwrap(),
+            ])]),
+            selection,
+            timeout: None,
+        }
+    }
+
+    #[test]
+    fn overrides() {
+        let addr = resolve("test.com", &dns(AddressSelection::First), None);
+        assert_eq!(addr.unwrap(), "192.0.2.1:443".parse().unwrap());
+
+        let addr =
+            resolve("test.com:4433", &dns(AddressSelection::Index(1)), None);
+        assert_eq!(addr.unwrap(), "[2001:db8::1]:4433".parse().unwrap());
+
+        let addr = resolve(
+            "test.com",
+            &dns(AddressSelection::Index(1)),
+            Some(IpVersion::V4),
+        );
+        assert_eq!(addr.unwrap(), "192.0.2.2:443".parse().unwrap());
+
+        let addr = resolve(
+            "test.com",
+            &dns(AddressSelection::Index(1)),
+            Some(IpVersion::V6),
+        );
+        assert!(addr.is_err());
+
+        let addr = resolve("test.com", &dns(AddressSelection::Random), None);
+        assert!(dns(AddressSelection::Random).overrides["test.com"]
+            .contains(&addr.unwrap().ip()));
+    }
+
+    #[test]
+    fn ip_literals() {
+        let addr = resolve("[::1]:8443", &dns(AddressSelection::First), None);
+        assert_eq!(addr.unwrap(), "[::1]:8443".parse().unwrap());
+
+        let addr = resolve(
+            "127.0.0.1",
+            &dns(AddressSelection::First),
+            Some(IpVersion::V6),
+        );
+        assert!(addr.is_err());
+    }
+
+    #[test]
+    fn select_empty() {
+        assert_eq!(select(&[], AddressSelection::First), None);
+        assert_eq!(select(&[], AddressSelection::Random), None);
+    }
+}
@@ -0,0 +1,320 @@
+// Copyright (C) 2025, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Delays and reorders the datagrams sent to the server, as configured by
+//! [SendShaping].
+
+use std::collections::VecDeque;
+use std::net::SocketAddr;
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::config::SendShaping;
+
+use super::rand_u64;
+
+/// How long a datagram held back for reordering waits for the next one, at
+/// most.
+const REORDER_HOLD: Duration = Duration::from_millis(25);
+
+/// A datagram waiting to be sent.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct Datagram {
+    pub(crate) buf: Vec<u8>,
+    pub(crate) from: SocketAddr,
+    pub(crate) to: SocketAddr,
+    release: Instant,
+}
+
+/// Queues the datagrams produced by quiche until they are due to be written
+/// to the socket.
+pub(crate) struct SendShaper {
+    shaping: SendShaping,
+    // Sorted by release time, datagrams released at the same time in the order
+    // they were queued.
+    queue: VecDeque<Datagram>,
+    // A datagram to send after the next one, and when to give up waiting for
+    // the next one.
+    held_back: Option<(Datagram, Instant)>,
+    delayed: usize,
+    reordered: usize,
+}
+
+impl SendShaper {
+    pub(crate) fn new(shaping: SendShaping) -> Self {
+        Self {
+            shaping,
+            queue: VecDeque::new(),
+            held_back: None,
+            delayed: 0,
+            reordered: 0,
+        }
+    }
+
+    /// Whether any shaping is configured. If not, datagrams can be written to
+    /// the socket right away.
+    pub(crate) fn is_enabled(&self) -> bool {
+        !self.shaping.delay.is_zero() ||
+            !self.shaping.jitter.is_zero() ||
+            self.shaping.reorder_percent > 0
+    }
+
+    /// Queues a datagram sent from `from` to `to`.
+    pub(crate) fn push(
+        &mut self, buf: &[u8], from: SocketAddr, to: SocketAddr, now: Instant,
+    ) {
+        let delay = self.shaping.delay + self.jitter();
+
+        if !delay.is_zero() {
+            self.delayed += 1;
+        }
+
+        let dgram = Datagram {
+            buf: buf.to_vec(),
+            from,
+            to,
+            release: now + delay,
+        };
+
+        if let Some((mut held, _)) = self.held_back.take() {
+            held.release = held.release.max(dgram.release);
+
+            self.insert(dgram);
+            self.insert(held);
+
+            return;
+        }
+
+        if self.reorder() {
+            self.reordered += 1;
+
+            let deadline = dgram.release + REORDER_HOLD;
+            self.held_back = Some((dgram, deadline));
+
+            return;
+        }
+
+        self.insert(dgram);
+    }
+
+    /// Returns how long until the next datagram is due, if any is queued.
+    pub(crate) fn timeout(&self, now: Instant) -> Option<Duration> {
+        let next = self.queue.front().map(|d| d.release);
+        let held = self.held_back.as_ref().map(|(_, deadline)| *deadline);
+
+        let next = match (next, held) {
+            (Some(a), Some(b)) => a.min(b),
+            (a, b) => a.or(b)?,
+        };
+
+        Some(next.saturating_duration_since(now))
+    }
+
+    /// Returns the next datagram that is due at `now`, if any.
+    pub(crate) fn pop_due(&mut self, now: Instant) -> Option<Datagram> {
+        if matches!(self.held_back, Some((_, deadline)) if deadline <= now) {
+            let (held, _) = self.held_back.take()?;
+
+            self.insert(held);
+        }
+
+        if self.queue.front()?.release > now {
+            return None;
+        }
+
+        self.queue.pop_front()
+    }
+
+    /// Returns all the queued datagrams, in order, regardless of when they
+    /// are due.
+    pub(crate) fn flush(&mut self) -> impl Iterator<Item = Datagram> + '_ {
+        if let Some((held, _)) = self.held_back.take() {
+            self.queue.push_back(held);
+        }
+
+        self.queue.drain(..)
+    }
+
+    fn insert(&mut self, dgram: Datagram) {
+        let pos = self.queue.partition_point(|d| d.release <= dgram.release);
+
+        self.queue.insert(pos, dgram);
+    }
+
+    fn jitter(&self) -> Duration {
+        let max = self.shaping.jitter.as_nanos() as u64;
+
+        if max == 0 {
+            return Duration::ZERO;
+        }
+
+        Duration::from_nanos(rand_u64() % (max + 1))
+    }
+
+    fn reorder(&self) -> bool {
+        match self.shaping.reorder_percent {
+            0 => false,
+
+            100.. => true,
+
+            percent => rand_u64() % 100 < u64::from(percent),
+        }
+    }
+}
+
+impl Drop for SendShaper {
+    fn drop(&mut self) {
+        if self.delayed > 0 || self.reordered > 0 {
+            log::info!(
+                "send shaping delayed {} and reordered {} datagrams",
+                self.delayed,
+                self.reordered
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(port: u16) -> SocketAddr {
+        SocketAddr::from(([127, 0, 0, 1], port))
+    }
+
+    fn pop_all(shaper: &mut SendShaper, now: Instant) -> Vec<Vec<u8>> {
+        std::iter::from_fn(|| shaper.pop_due(now))
+            .map(|d| d.buf)
+            .collect()
+    }
+
+    #[test]
+    fn disabled() {
+        let shaper = SendShaper::new(SendShaping::default());
+        assert!(!shaper.is_enabled());
+    }
+
+    #[test]
+    fn delay() {
+        let mut shaper = SendShaper::new(SendShaping {
+            delay: Duration::from_millis(10),
+            ..Default::default()
+        });
+
+        let now = Instant::now();
+
+        shaper.push(&[1], addr(1), addr(2), now);
+        shaper.push(&[2], addr(1), addr(2), now + Duration::from_millis(5));
+
+        assert_eq!(shaper.timeout(now), Some(Duration::from_millis(10)));
+        assert_eq!(shaper.pop_due(now), None);
+
+        let later = now + Duration::from_millis(10);
+        assert_eq!(pop_all(&mut shaper, later), vec![vec![1]]);
+        assert_eq!(shaper.timeout(later), Some(Duration::from_millis(5)));
+
+        let later = now + Duration::from_millis(15);
+        let dgram = shaper.pop_due(later).unwrap();
+        assert_eq!(
+            (dgram.buf, dgram.from, dgram.to),
+            (vec![2], addr(1), addr(2))
+        );
+        assert_eq!(shaper.timeout(later), None);
+    }
+
+    #[test]
+    fn jitter() {
+        quiche::set_thread_rng(Some(Box::new(quiche::SeededRng::new(1))));
+
+        let mut shaper = SendShaper::new(SendShaping {
+            delay: Duration::from_millis(10),
+            jitter: Duration::from_millis(5),
+            ..Default::default()
+        });
+
+        let now = Instant::now();
+
+        for i in 0..10 {
+            shaper.push(&[i], addr(1), addr(2), now);
+        }
+
+        quiche::set_thread_rng(None);
+
+        assert_eq!(
+            pop_all(&mut shaper, now + Duration::from_millis(9)).len(),
+            0
+        );
+        assert_eq!(
+            pop_all(&mut shaper, now + Duration::from_millis(15)).len(),
+            10
+        );
+    }
+
+    #[test]
+    fn reorder() {
+        let mut shaper = SendShaper::new(SendShaping {
+            reorder_percent: 100,
+            ..Default::default()
+        });
+
+        let now = Instant::now();
+
+        for i in 0..5 {
+            shaper.push(&[i], addr(1), addr(2), now);
+        }
+
+        assert_eq!(pop_all(&mut shaper, now), vec![
+            vec![1],
+            vec![0],
+            vec![3],
+            vec![2]
+        ]);
+
+        // The last datagram waits for the next one until it gives up.
+        assert_eq!(shaper.timeout(now), Some(REORDER_HOLD));
+        assert_eq!(pop_all(&mut shaper, now + REORDER_HOLD), vec![vec![4]]);
+        assert_eq!(shaper.reordered, 3);
+    }
+
+    #[test]
+    fn flush() {
+        let mut shaper = SendShaper::new(SendShaping {
+            delay: Duration::from_secs(1),
+            reorder_percent: 100,
+            ..Default::default()
+        });
+
+        let now = Instant::now();
+
+        shaper.push(&[0], addr(1), addr(2), now);
+        shaper.push(&[1], addr(1), addr(2), now);
+        shaper.push(&[2], addr(1), addr(2), now);
+
+        let flushed: Vec<_> = shaper.flush().map(|d| d.buf).collect();
+        assert_eq!(flushed, vec![vec![1], vec![0], vec![2]]);
+        assert_eq!(shaper.timeout(now), None);
+    }
+}
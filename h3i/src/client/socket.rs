@@ -0,0 +1,209 @@
+// Copyright (C) 2025, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Creates the UDP sockets of a connection, with the socket options of the
+//! [Config].
+
+use std::io;
+use std::net::SocketAddr;
+
+use crate::config::Config;
+
+/// The options applied to every socket of a connection, including the ones
+/// it migrates to.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct SocketOptions {
+    interface: Option<String>,
+    tos: Option<u8>,
+    dont_fragment: Option<bool>,
+}
+
+impl SocketOptions {
+    pub(crate) fn new(args: &Config) -> io::Result<Self> {
+        let tos = match (args.dscp, args.ecn) {
+            (None, None) => None,
+
+            (Some(dscp), _) if dscp > 0x3f => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("invalid DSCP {dscp}, must be lower than 64"),
+                ));
+            },
+
+            (dscp, ecn) =>
+                Some(dscp.unwrap_or(0) << 2 | ecn.map_or(0, |ecn| ecn as u8)),
+        };
+
+        Ok(SocketOptions {
+            interface: args.interface.clone(),
+            tos,
+            dont_fragment: args.dont_fragment,
+        })
+    }
+}
+
+/// Creates a non-blocking UDP socket bound to `addr`, with the given
+/// `options`.
+pub(crate) fn bind_socket(
+    addr: SocketAddr, options: &SocketOptions,
+) -> io::Result<mio::net::UdpSocket> {
+    let socket = socket2::Socket::new(
+        socket2::Domain::for_address(addr),
+        socket2::Type::DGRAM,
+        Some(socket2::Protocol::UDP),
+    )?;
+
+    if let Some(interface) = &options.interface {
+        bind_device(&socket, interface)?;
+    }
+
+    if let Some(tos) = options.tos {
+        match addr {
+            SocketAddr::V4(_) => socket.set_tos_v4(tos.into())?,
+
+            SocketAddr::V6(_) => socket.set_tclass_v6(tos.into())?,
+        }
+    }
+
+    if let Some(dont_fragment) = options.dont_fragment {
+        set_dont_fragment(&socket, addr.is_ipv6(), dont_fragment)?;
+    }
+
+    socket.bind(&addr.into())?;
+    socket.set_nonblocking(true)?;
+
+    Ok(mio::net::UdpSocket::from_std(socket.into()))
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn bind_device(socket: &socket2::Socket, interface: &str) -> io::Result<()> {
+    socket.bind_device(Some(interface.as_bytes()))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn bind_device(_socket: &socket2::Socket, _interface: &str) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "binding to an interface is only supported on Linux and Android",
+    ))
+}
+
+/// Sets or clears the don't fragment bit of the packets sent on `socket`,
+/// disabling the kernel's path MTU discovery in either case.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn set_dont_fragment(
+    socket: &socket2::Socket, is_ipv6: bool, dont_fragment: bool,
+) -> io::Result<()> {
+    use std::os::fd::AsRawFd;
+
+    let (level, name, value) = match (is_ipv6, dont_fragment) {
+        (false, true) => (
+            libc::IPPROTO_IP,
+            libc::IP_MTU_DISCOVER,
+            libc::IP_PMTUDISC_DO,
+        ),
+
+        (false, false) => (
+            libc::IPPROTO_IP,
+            libc::IP_MTU_DISCOVER,
+            libc::IP_PMTUDISC_DONT,
+        ),
+
+        (true, true) => (
+            libc::IPPROTO_IPV6,
+            libc::IPV6_MTU_DISCOVER,
+            libc::IPV6_PMTUDISC_DO,
+        ),
+
+        (true, false) => (
+            libc::IPPROTO_IPV6,
+            libc::IPV6_MTU_DISCOVER,
+            libc::IPV6_PMTUDISC_DONT,
+        ),
+    };
+
+    let ret = unsafe {
+        libc::setsockopt(
+            socket.as_raw_fd(),
+            level,
+            name,
+            &value as *const libc::c_int as *const libc::c_void,
+            std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+        )
+    };
+
+    match ret {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn set_dont_fragment(
+    _socket: &socket2::Socket, _is_ipv6: bool, _dont_fragment: bool,
+) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "setting the don't fragment bit is only supported on Linux and Android",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Ecn;
+
+    #[test]
+    fn tos() {
+        let args = Config::new();
+        assert_eq!(SocketOptions::new(&args).unwrap().tos, None);
+
+        let args = Config {
+            dscp: Some(46),
+            ..Config::new()
+        };
+        assert_eq!(SocketOptions::new(&args).unwrap().tos, Some(0xb8));
+
+        let args = Config {
+            ecn: Some(Ecn::Ect0),
+            ..Config::new()
+        };
+        assert_eq!(SocketOptions::new(&args).unwrap().tos, Some(0x02));
+
+        let args = Config {
+            dscp: Some(10),
+            ecn: Some(Ecn::Ce),
+            ..Config::new()
+        };
+        assert_eq!(SocketOptions::new(&args).unwrap().tos, Some(0x2b));
+
+        let args = Config {
+            dscp: Some(64),
+            ..Config::new()
+        };
+        assert!(SocketOptions::new(&args).is_err());
+    }
+}
@@ -0,0 +1,441 @@
+// Copyright (C) 2025, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Runs a directory of scripts as a test suite.
+//!
+//! Each script is a scenario: its connections are run with [`connect()`] or
+//! [`connect_many()`], then its expectations and body assertions are
+//! evaluated. The outcome of all the scenarios is gathered in a
+//! [SuiteReport], which serializes to JSON and renders as JUnit XML for CI.
+//!
+//! [`connect()`]: crate::client::sync_client::connect
+//! [`connect_many()`]: crate::client::sync_client::connect_many
+
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use std::time::Instant;
+
+use serde::Serialize;
+use serde_with::serde_as;
+
+use crate::actions::script::Scenario;
+use crate::client::connection_summary::ConnectionSummary;
+use crate::client::expectations::Expectations;
+use crate::client::sync_client;
+use crate::config::Config;
+
+/// The extensions of the script files run by [`run_suite()`].
+const SCRIPT_EXTENSIONS: [&str; 3] = ["json", "yaml", "yml"];
+
+/// The outcome of a single scenario.
+#[serde_as]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ScenarioReport {
+    /// The file name of the script.
+    pub name: String,
+    /// How long the scenario took to run.
+    #[serde_as(as = "serde_with::DurationMilliSecondsWithFrac<f64>")]
+    pub duration: Duration,
+    /// The expectations that weren't met and body assertions that failed.
+    pub failures: Vec<String>,
+    /// Why the scenario couldn't run to completion, e.g. an invalid script
+    /// or a failed handshake.
+    pub error: Option<String>,
+}
+
+impl ScenarioReport {
+    /// Whether the scenario ran to completion and met all its expectations.
+    pub fn passed(&self) -> bool {
+        self.error.is_none() && self.failures.is_empty()
+    }
+}
+
+/// The outcome of all the scenarios of a suite, as returned by
+/// [`run_suite()`].
+#[serde_as]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct SuiteReport {
+    /// The scenarios, ordered by file name.
+    pub scenarios: Vec<ScenarioReport>,
+    /// How long the whole suite took to run.
+    #[serde_as(as = "serde_with::DurationMilliSecondsWithFrac<f64>")]
+    pub duration: Duration,
+}
+
+impl SuiteReport {
+    /// Whether all the scenarios passed.
+    pub fn passed(&self) -> bool {
+        self.scenarios.iter().all(ScenarioReport::passed)
+    }
+
+    /// The scenarios that didn't pass.
+    pub fn failed(&self) -> impl Iterator<Item = &ScenarioReport> {
+        self.scenarios.iter().filter(|s| !s.passed())
+    }
+
+    /// Renders the report as a JUnit XML document, with one test case per
+    /// scenario. Unmet expectations are reported as failures, and scenarios
+    /// that couldn't run as errors.
+    pub fn to_junit(&self) -> String {
+        let failures = self
+            .scenarios
+            .iter()
+            .filter(|s| !s.failures.is_empty())
+            .count();
+        let errors = self.scenarios.iter().filter(|s| s.error.is_some()).count();
+
+        let mut xml =
+            String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+
+        xml.push_str(&format!(
+            "<testsuite name=\"h3i\" tests=\"{}\" failures=\"{}\" errors=\"{}\" time=\"{:.3}\">\n",
+            self.scenarios.len(),
+            failures,
+            errors,
+            self.duration.as_secs_f64()
+        ));
+
+        for scenario in &self.scenarios {
+            xml.push_str(&format!(
+                "  <testcase classname=\"h3i\" name=\"{}\" time=\"{:.3}\"",
+                xml_escape(&scenario.name),
+                scenario.duration.as_secs_f64()
+            ));
+
+            if scenario.passed() {
+                xml.push_str("/>\n");
+                continue;
+            }
+
+            xml.push_str(">\n");
+
+            if let Some(error) = &scenario.error {
+                xml.push_str(&format!(
+                    "    <error message=\"{}\"/>\n",
+                    xml_escape(error)
+                ));
+            }
+
+            if !scenario.failures.is_empty() {
+                xml.push_str(&format!(
+                    "    <failure message=\"{} expectation(s) not met\">{}</failure>\n",
+                    scenario.failures.len(),
+                    xml_escape(&scenario.failures.join("\n"))
+                ));
+            }
+
+            xml.push_str("  </testcase>\n");
+        }
+
+        xml.push_str("</testsuite>\n");
+        xml
+    }
+}
+
+/// Runs every script in `dir` against the server in `config`, and reports
+/// their outcome.
+///
+/// Scripts are the files with a `.json`, `.yaml` or `.yml` extension, see
+/// [`from_file()`]. Up to `jobs` scenarios run at the same time; each one
+/// still runs its own connections concurrently. Subdirectories are ignored.
+///
+/// [`from_file()`]: crate::actions::script::from_file
+pub fn run_suite(
+    config: &Config, dir: impl AsRef<Path>, jobs: usize,
+) -> io::Result<SuiteReport> {
+    let scripts = find_scripts(dir.as_ref())?;
+    let start = Instant::now();
+
+    let next = AtomicUsize::new(0);
+
+    let mut scenarios: Vec<ScenarioReport> = std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..jobs.clamp(1, scripts.len().max(1)))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut reports = vec![];
+
+                    while let Some(script) =
+                        scripts.get(next.fetch_add(1, Ordering::Relaxed))
+                    {
+                        reports.push(run_scenario(config, script));
+                    }
+
+                    reports
+                })
+            })
+            .collect();
+
+        workers
+            .into_iter()
+            .flat_map(|w| w.join().unwrap_or_default())
+            .collect()
+    });
+
+    scenarios.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(SuiteReport {
+        scenarios,
+        duration: start.elapsed(),
+    })
+}
+
+/// Returns the scripts in `dir`, ordered by file name.
+fn find_scripts(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut scripts = vec![];
+
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+
+        let is_script = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| SCRIPT_EXTENSIONS.contains(&e));
+
+        if is_script && path.is_file() {
+            scripts.push(path);
+        }
+    }
+
+    scripts.sort();
+
+    Ok(scripts)
+}
+
+fn run_scenario(config: &Config, path: &Path) -> ScenarioReport {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    log::info!("scenario {} starting", name);
+
+    let start = Instant::now();
+
+    let mut report = ScenarioReport {
+        name,
+        duration: Duration::ZERO,
+        failures: vec![],
+        error: None,
+    };
+
+    match crate::actions::from_file(path) {
+        Ok(scenario) => run_script(config, scenario, &mut report),
+
+        Err(e) => report.error = Some(format!("invalid script: {e}")),
+    }
+
+    report.duration = start.elapsed();
+
+    if report.passed() {
+        log::info!("scenario {} passed", report.name);
+    } else {
+        log::error!(
+            "scenario {} failed: {}",
+            report.name,
+            report
+                .error
+                .iter()
+                .chain(&report.failures)
+                .cloned()
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+
+    report
+}
+
+fn run_script(config: &Config, scenario: Scenario, report: &mut ScenarioReport) {
+    let results = if scenario.connections.is_empty() {
+        vec![sync_client::connect(
+            config.clone(),
+            &scenario.actions,
+            scenario.close_trigger_frames,
+        )]
+    } else {
+        sync_client::connect_many(
+            config.clone(),
+            &scenario.connections,
+            scenario.close_trigger_frames,
+        )
+        .connections
+    };
+
+    let many = results.len() > 1;
+
+    for (i, result) in results.into_iter().enumerate() {
+        let prefix = if many {
+            format!("connection {i}: ")
+        } else {
+            String::new()
+        };
+
+        match result {
+            Ok(summary) => report.failures.extend(
+                summary_failures(&scenario.expectations, &summary)
+                    .map(|f| format!("{prefix}{f}")),
+            ),
+
+            Err(e) if report.error.is_none() =>
+                report.error = Some(format!("{prefix}{e:?}")),
+
+            Err(_) => (),
+        }
+    }
+}
+
+/// The unmet expectations and failed body assertions of a connection.
+fn summary_failures<'a>(
+    expectations: &'a Expectations, summary: &'a ConnectionSummary,
+) -> impl Iterator<Item = String> + 'a {
+    let expectations = expectations
+        .evaluate(summary)
+        .results
+        .into_iter()
+        .filter_map(|r| r.failure);
+
+    let body_assertions = summary
+        .stream_map
+        .body_assertions()
+        .iter()
+        .filter_map(|r| r.failure.clone())
+        .map(|f| format!("body assertion failed: {f}"));
+
+    expectations.chain(body_assertions)
+}
+
+fn xml_escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        match c {
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '&' => escaped.push_str("&amp;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scenario(
+        name: &str, failures: &[&str], error: Option<&str>,
+    ) -> ScenarioReport {
+        ScenarioReport {
+            name: name.to_string(),
+            duration: Duration::from_millis(1500),
+            failures: failures.iter().map(|f| f.to_string()).collect(),
+            error: error.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn find_scripts_in_dir() {
+        let dir = std::env::temp_dir()
+            .join(format!("h3i-suite-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("nested.yaml")).unwrap();
+
+        for file in ["b.yaml", "a.json", "c.yml", "notes.txt"] {
+            std::fs::write(dir.join(file), "actions: []").unwrap();
+        }
+
+        let names: Vec<_> = find_scripts(&dir)
+            .unwrap()
+            .iter()
+            .map(|p| p.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(names, vec!["a.json", "b.yaml", "c.yml"]);
+    }
+
+    #[test]
+    fn junit_report() {
+        let report = SuiteReport {
+            scenarios: vec![
+                scenario("get.yaml", &[], None),
+                scenario(
+                    "status.yaml",
+                    &["expected status 200, got <404>"],
+                    None,
+                ),
+                scenario("bad.yaml", &[], Some("invalid script")),
+            ],
+            duration: Duration::from_secs(3),
+        };
+
+        assert!(!report.passed());
+        assert_eq!(report.failed().count(), 2);
+
+        assert_eq!(
+            report.to_junit(),
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuite name="h3i" tests="3" failures="1" errors="1" time="3.000">
+  <testcase classname="h3i" name="get.yaml" time="1.500"/>
+  <testcase classname="h3i" name="status.yaml" time="1.500">
+    <failure message="1 expectation(s) not met">expected status 200, got &lt;404&gt;</failure>
+  </testcase>
+  <testcase classname="h3i" name="bad.yaml" time="1.500">
+    <error message="invalid script"/>
+  </testcase>
+</testsuite>
+"#
+        );
+    }
+
+    #[test]
+    fn json_report() {
+        let report = SuiteReport {
+            scenarios: vec![scenario("get.yaml", &[], None)],
+            duration: Duration::from_secs(1),
+        };
+
+        assert_eq!(
+            serde_json::to_value(&report).unwrap(),
+            serde_json::json!({
+                "scenarios": [{
+                    "name": "get.yaml",
+                    "duration": 1500.0,
+                    "failures": [],
+                    "error": null,
+                }],
+                "duration": 1000.0,
+            })
+        );
+    }
+}
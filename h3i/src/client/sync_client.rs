@@ -26,44 +26,220 @@
 
 //! Responsible for creating a [quiche::Connection] and managing I/O.
 
-use std::slice::Iter;
+use std::cmp;
+use std::fs::File;
+use std::io::BufWriter;
+use std::net::Ipv4Addr;
+use std::net::Ipv6Addr;
+use std::net::SocketAddr;
+use std::sync::mpsc;
+use std::thread::JoinHandle;
 use std::time::Duration;
 use std::time::Instant;
+use std::time::SystemTime;
 
 use crate::frame::H3iFrame;
 use crate::quiche;
+use crate::quiche::h3::frame::Frame as QFrame;
+use crate::quiche::h3::Header;
+use crate::recordreplay::pcap::Direction;
+use crate::recordreplay::pcap::PcapngWriter;
 
+use crate::actions::h3::send_headers_frame;
 use crate::actions::h3::Action;
+use crate::actions::h3::ActionIter;
+use crate::actions::h3::ConnectionEvent;
+use crate::actions::h3::RampPolicy;
 use crate::actions::h3::StreamEventType;
 use crate::actions::h3::WaitType;
 use crate::actions::h3::WaitingFor;
 use crate::client::build_quiche_connection;
+use crate::client::cert::check_pins;
+use crate::client::cert::verification_failure;
+use crate::client::cert::BAD_CERTIFICATE;
 use crate::client::execute_action;
+use crate::client::fault::FaultInjector;
+use crate::client::masque::MasqueTunnel;
 use crate::client::parse_streams;
+use crate::client::resolve::resolve;
+use crate::client::shaper::Datagram;
+use crate::client::shaper::SendShaper;
+use crate::client::socket::bind_socket;
+use crate::client::socket::SocketOptions;
 use crate::client::ClientError;
+use crate::client::ClientEvent;
 use crate::client::ConnectionCloseDetails;
+use crate::client::EarlyData;
 use crate::client::MAX_DATAGRAM_SIZE;
 use crate::config::Config;
+use crate::config::IpVersion;
+use crate::recordreplay::script::RecordedTiming;
+use crate::recordreplay::script::Recorder;
 
 use super::Client;
 use super::CloseTriggerFrames;
 use super::ConnectionSummary;
+use super::HandshakeSummary;
+use super::MultiConnectionSummary;
 use super::StreamMap;
 use super::StreamParserMap;
 
 #[derive(Default)]
-struct SyncClient {
+pub(crate) struct SyncClient {
     streams: StreamMap,
     stream_parsers: StreamParserMap,
+    pending_requests: Option<PendingRequests>,
+    recorder: Option<Recorder>,
+    sent_early_data: bool,
+    pending_migration: Option<Migration>,
+    pending_path_changes: Vec<PathChange>,
+    validated_paths: u64,
+    on_event: Option<Box<dyn FnMut(ClientEvent) + Send>>,
+    wait_keepalive: WaitKeepalive,
+    handshake: HandshakeSummary,
+}
+
+/// Sends PINGs during [Action::Wait]s, so that waits longer than the idle
+/// timeout don't close the connection. See [`Config::wait_keepalive`].
+#[derive(Default)]
+struct WaitKeepalive {
+    enabled: bool,
+    last_ping: Option<Instant>,
+}
+
+impl WaitKeepalive {
+    /// How long until the next PING is due, if `waiting`. PINGs are sent
+    /// every half idle timeout.
+    fn timeout(
+        &self, waiting: bool, conn: &quiche::Connection,
+    ) -> Option<Duration> {
+        if !self.enabled || !waiting {
+            return None;
+        }
+
+        let interval = conn.idle_timeout()? / 2;
+
+        Some(
+            self.last_ping
+                .map_or(interval, |last| interval.saturating_sub(last.elapsed())),
+        )
+    }
+
+    /// Makes the connection send a PING if one is due.
+    fn keep_alive(&mut self, waiting: bool, conn: &mut quiche::Connection) {
+        if !waiting {
+            self.last_ping = None;
+            return;
+        }
+
+        match self.timeout(waiting, conn) {
+            Some(Duration::ZERO) => {
+                log::debug!("sending keepalive PING");
+
+                conn.send_ack_eliciting().ok();
+                self.last_ping = Some(Instant::now());
+            },
+
+            // Start counting from the beginning of the wait.
+            Some(_) if self.last_ping.is_none() =>
+                self.last_ping = Some(Instant::now()),
+
+            _ => (),
+        }
+    }
+}
+
+/// An [Action::Migrate] to carry out once the actions that preceded it are
+/// sent.
+struct Migration {
+    local_addr: Option<SocketAddr>,
+    nat_rebinding: bool,
+}
+
+/// An [Action::ProbePath] or [Action::SwitchPath] to carry out once the
+/// actions that preceded it are sent.
+enum PathChange {
+    Probe(Option<SocketAddr>),
+    Switch(usize),
+}
+
+/// A path opened by an [Action::ProbePath], with its own socket.
+struct ProbedPath {
+    socket: mio::net::UdpSocket,
+    local_addr: SocketAddr,
+}
+
+/// The event loop token of the socket of `path`, which is at least 1.
+fn path_token(path: usize) -> mio::Token {
+    mio::Token(WAKER_TOKEN.0 + path)
+}
+
+/// The requests of an [Action::OpenConcurrentRequests] that are yet to be
+/// opened.
+struct PendingRequests {
+    next_stream_id: u64,
+    remaining: u64,
+    headers: Vec<Header>,
+    ramp: RampPolicy,
+}
+
+impl PendingRequests {
+    /// How long to wait before opening more requests, if there is a fixed
+    /// interval. Otherwise requests are opened as soon as there is credit.
+    fn ramp_interval(&self) -> Option<Duration> {
+        match self.ramp {
+            RampPolicy::Immediate => None,
+            RampPolicy::Step { interval, .. } => Some(interval),
+        }
+    }
 }
 
 impl SyncClient {
-    fn new(close_trigger_frames: Option<CloseTriggerFrames>) -> Self {
+    pub(crate) fn new(close_trigger_frames: Option<CloseTriggerFrames>) -> Self {
         Self {
             streams: StreamMap::new(close_trigger_frames),
             ..Default::default()
         }
     }
+
+    /// Records the executed actions, to be written to a script once the
+    /// connection is closed.
+    pub(crate) fn record(&mut self) {
+        self.recorder = Some(Recorder::new());
+    }
+
+    /// Writes the recorded actions, if any, to the script file at `path`.
+    pub(crate) fn write_script(&self, path: &str, timing: RecordedTiming) {
+        let Some(recorder) = &self.recorder else {
+            return;
+        };
+
+        match recorder.write_script(path, timing) {
+            Ok(()) => log::info!("recorded actions to {}", path),
+
+            Err(e) => log::error!("failed to record actions to {}: {}", path, e),
+        }
+    }
+
+    /// Summarizes the connection once it is closed.
+    pub(crate) fn into_summary(
+        self, conn: &quiche::Connection,
+    ) -> ConnectionSummary {
+        ConnectionSummary {
+            stream_map: self.streams,
+            stats: Some(conn.stats()),
+            path_stats: conn.path_stats().collect(),
+            conn_close_details: ConnectionCloseDetails::new(conn),
+            early_data: EarlyData::new(conn, self.sent_early_data),
+            peer_transport_params: conn.peer_transport_params().cloned(),
+            handshake: HandshakeSummary {
+                alpn: Some(conn.application_proto())
+                    .filter(|p| !p.is_empty())
+                    .map(|p| p.to_vec()),
+                ..self.handshake
+            },
+        }
+    }
 }
 
 impl Client for SyncClient {
@@ -72,8 +248,23 @@ impl Client for SyncClient {
     }
 
     fn handle_response_frame(&mut self, stream_id: u64, frame: H3iFrame) {
+        if let Some(on_event) = &mut self.on_event {
+            on_event(ClientEvent::Frame {
+                stream_id,
+                frame: frame.clone(),
+            });
+        }
+
         self.streams.insert(stream_id, frame);
     }
+
+    fn handle_push_stream(&mut self, stream_id: u64, push_id: u64) {
+        if let Some(on_event) = &mut self.on_event {
+            on_event(ClientEvent::PushStream { stream_id, push_id });
+        }
+
+        self.streams.insert_push_stream(stream_id, push_id);
+    }
 }
 
 /// Connect to a server and execute provided actions.
@@ -90,75 +281,301 @@ pub fn connect(
     args: Config, actions: &[Action],
     close_trigger_frames: Option<CloseTriggerFrames>,
 ) -> std::result::Result<ConnectionSummary, ClientError> {
+    // Setup the event loop.
+    let poll = mio::Poll::new().unwrap();
+
+    run_connection(args, actions, close_trigger_frames, poll, None)
+}
+
+/// Connect to a server and execute actions as they are sent on the returned
+/// [ActionSender], calling `on_event` with each [ClientEvent] as it is
+/// received.
+///
+/// The connection is kept alive until the [ActionSender] is dropped, at which
+/// point it is closed gracefully, or until the server closes it. The
+/// connection runs on its own thread, which returns a [ConnectionSummary] on
+/// success, [ClientError] on failure.
+pub fn connect_interactive(
+    args: Config, on_event: impl FnMut(ClientEvent) + Send + 'static,
+) -> std::result::Result<
+    (
+        ActionSender,
+        JoinHandle<std::result::Result<ConnectionSummary, ClientError>>,
+    ),
+    ClientError,
+> {
+    let poll = mio::Poll::new().map_err(|e| ClientError::Other(e.to_string()))?;
+    let waker = mio::Waker::new(poll.registry(), WAKER_TOKEN)
+        .map_err(|e| ClientError::Other(e.to_string()))?;
+    let (sender, receiver) = mpsc::channel();
+
+    // Ping the server well before the connection would idle out.
+    let keepalive = (args.idle_timeout > 0)
+        .then(|| Duration::from_millis(args.idle_timeout) / 2);
+
+    let interactive = Interactive {
+        receiver,
+        keepalive,
+        last_keepalive: Instant::now(),
+        closing: false,
+        on_event: Some(Box::new(on_event)),
+    };
+
+    let handle = std::thread::spawn(move || {
+        run_connection(args, &[], None, poll, Some(interactive))
+    });
+
+    let sender = ActionSender {
+        sender: Some(sender),
+        waker,
+    };
+
+    Ok((sender, handle))
+}
+
+/// The token of the waker notifying an interactive connection of new
+/// actions.
+const WAKER_TOKEN: mio::Token = mio::Token(1);
+
+/// Sends actions to a connection started by [`connect_interactive()`].
+///
+/// The connection is closed once the sender is dropped.
+pub struct ActionSender {
+    sender: Option<mpsc::Sender<Vec<Action>>>,
+    waker: mio::Waker,
+}
+
+impl ActionSender {
+    /// Queues `actions` to be executed after the ones sent previously.
+    ///
+    /// Returns an error if the connection is already closed.
+    pub fn send(
+        &self, actions: Vec<Action>,
+    ) -> std::result::Result<(), ClientError> {
+        let closed = || ClientError::Other("connection closed".into());
+
+        self.sender
+            .as_ref()
+            .ok_or_else(closed)?
+            .send(actions)
+            .map_err(|_| closed())?;
+
+        self.waker
+            .wake()
+            .map_err(|e| ClientError::Other(e.to_string()))
+    }
+}
+
+impl Drop for ActionSender {
+    fn drop(&mut self) {
+        // Disconnect the channel before waking up the connection, so that it
+        // sees it needs to close.
+        self.sender = None;
+        let _ = self.waker.wake();
+    }
+}
+
+/// The state of a connection started by [`connect_interactive()`].
+struct Interactive {
+    receiver: mpsc::Receiver<Vec<Action>>,
+    keepalive: Option<Duration>,
+    last_keepalive: Instant,
+    closing: bool,
+    on_event: Option<Box<dyn FnMut(ClientEvent) + Send>>,
+}
+
+impl Interactive {
+    /// Queues the actions received so far after the remaining ones, and
+    /// closes the connection once the sender is gone.
+    fn queue_actions(
+        &mut self, action_iter: &mut ActionIter, conn: &mut quiche::Connection,
+    ) {
+        loop {
+            match self.receiver.try_recv() {
+                Ok(actions) => {
+                    // The actions need to outlive the iterator. There are only
+                    // as many as the user types, so they are leaked.
+                    action_iter.append(Box::leak(actions.into_boxed_slice()));
+                },
+
+                Err(mpsc::TryRecvError::Empty) => break,
+
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    if !self.closing {
+                        self.closing = true;
+                        conn.close(
+                            true,
+                            quiche::h3::WireErrorCode::NoError as u64,
+                            b"",
+                        )
+                        .ok();
+                    }
+
+                    break;
+                },
+            }
+        }
+    }
+
+    /// How long until the next keepalive is due.
+    fn timeout(&self) -> Option<Duration> {
+        self.keepalive
+            .map(|k| k.saturating_sub(self.last_keepalive.elapsed()))
+    }
+
+    /// Makes the connection send an ack-eliciting packet if a keepalive is
+    /// due.
+    fn keep_alive(&mut self, conn: &mut quiche::Connection) {
+        if self.timeout() == Some(Duration::ZERO) {
+            conn.send_ack_eliciting().ok();
+            self.last_keepalive = Instant::now();
+        }
+    }
+}
+
+/// Runs the connection's event loop, executing `actions`, and the ones sent
+/// on `interactive` if given.
+fn run_connection(
+    args: Config, actions: &[Action],
+    close_trigger_frames: Option<CloseTriggerFrames>, mut poll: mio::Poll,
+    mut interactive: Option<Interactive>,
+) -> std::result::Result<ConnectionSummary, ClientError> {
+    // Make the connection's randomness deterministic, if requested. This
+    // needs to happen before anything random is generated, and is undone once
+    // the connection is over.
+    let _rng_guard = args.rng_seed.map(|seed| {
+        quiche::scoped_thread_rng(Box::new(quiche::SeededRng::new(seed)))
+    });
+
     let mut buf = [0; 65535];
     let mut out = [0; MAX_DATAGRAM_SIZE];
 
-    // Setup the event loop.
-    let mut poll = mio::Poll::new().unwrap();
     let mut events = mio::Events::with_capacity(1024);
 
+    let ip_version = args
+        .ip_version
+        .or_else(|| args.local_ip.as_ref().map(IpVersion::from));
+
+    // Tunnel the connection through the MASQUE proxy, if any. It then sends
+    // its datagrams to the proxy's relay, in place of the server.
+    let tunnel = args
+        .masque_proxy
+        .as_ref()
+        .map(|proxy| MasqueTunnel::open(proxy, &args))
+        .transpose()?;
+
     // Resolve server address.
-    let peer_addr = if let Some(addr) = &args.connect_to {
+    let peer_addr: SocketAddr = if let Some(tunnel) = &tunnel {
+        tunnel.relay_addr()
+    } else if let Some(addr) = &args.connect_to {
         addr.parse().expect("--connect-to is expected to be a string containing an IPv4 or IPv6 address with a port. E.g. 192.0.2.0:443")
     } else {
-        let x = format!("https://{}", args.host_port);
-        *url::Url::parse(&x)
-            .unwrap()
-            .socket_addrs(|| None)
-            .unwrap()
-            .first()
-            .unwrap()
+        resolve(&args.host_port, &args.dns, ip_version)?
     };
 
+    if tunnel.is_none() &&
+        !ip_version.map_or(true, |v| v.matches(&peer_addr.ip()))
+    {
+        return Err(ClientError::Other(format!(
+            "server address {peer_addr} is not {ip_version:?}"
+        )));
+    }
+
     // Bind to INADDR_ANY or IN6ADDR_ANY depending on the IP family of the
-    // server address. This is needed on macOS and BSD variants that don't
-    // support binding to IN6ADDR_ANY for both v4 and v6.
-    let bind_addr = match peer_addr {
-        std::net::SocketAddr::V4(_) => format!("0.0.0.0:{}", args.source_port),
-        std::net::SocketAddr::V6(_) => format!("[::]:{}", args.source_port),
-    };
+    // server address, unless a local IP was given. This is needed on macOS
+    // and BSD variants that don't support binding to IN6ADDR_ANY for both v4
+    // and v6.
+    let bind_ip = args.local_ip.unwrap_or(match peer_addr {
+        SocketAddr::V4(_) => Ipv4Addr::UNSPECIFIED.into(),
+        SocketAddr::V6(_) => Ipv6Addr::UNSPECIFIED.into(),
+    });
+    let bind_addr = SocketAddr::new(bind_ip, args.source_port as u16);
 
     // Create the UDP socket backing the QUIC connection, and register it with
     // the event loop.
-    let mut socket =
-        mio::net::UdpSocket::bind(bind_addr.parse().unwrap()).unwrap();
+    let socket_options = SocketOptions::new(&args)
+        .map_err(|e| ClientError::Other(e.to_string()))?;
+    let mut socket = bind_socket(bind_addr, &socket_options).map_err(|e| {
+        ClientError::Other(format!("can't bind to {bind_addr}: {e}"))
+    })?;
     poll.registry()
         .register(&mut socket, mio::Token(0), mio::Interest::READABLE)
         .unwrap();
 
-    let Ok(local_addr) = socket.local_addr() else {
+    // The local address of the connection's path, as quiche sees it. It can
+    // differ from the socket's after an emulated NAT rebinding.
+    let Ok(mut local_addr) = socket.local_addr() else {
         return Err(ClientError::Other("invalid socket".to_string()));
     };
 
+    let mut faults = FaultInjector::new(args.handshake_faults.clone());
+    let mut capture = args.pcap_file.as_deref().map(open_capture).transpose()?;
+    let mut shaper = SendShaper::new(args.send_shaping.clone());
+    let record_script = args.record_script.clone();
+    let body_capture = args.body_capture.clone();
+    let record_timing = args.record_timing;
+    let session_file = args.session_file.clone();
+    let wait_keepalive = args.wait_keepalive;
+    let cert_pins = args.cert_pins.clone();
+
     let mut conn = build_quiche_connection(args, peer_addr, local_addr)
         .map_err(|_| ClientError::HandshakeFail)?;
 
+    let mut probed_paths: Vec<ProbedPath> = Vec::new();
+
     let mut app_proto_selected = false;
+    let mut cert_checked = false;
+    let mut cert_error = None;
 
     let (write, send_info) = conn.send(&mut out).expect("initial send failed");
 
-    while let Err(e) = socket.send_to(&out[..write], send_info.to) {
-        if e.kind() == std::io::ErrorKind::WouldBlock {
-            log::debug!(
-                "{} -> {}: send() would block",
-                socket.local_addr().unwrap(),
-                send_info.to
-            );
-            continue;
+    let write = faults.outgoing(&mut out[..write]);
+
+    if let Some(write) = write.filter(|_| shaper.is_enabled()) {
+        shaper.push(&out[..write], local_addr, send_info.to, Instant::now());
+    } else if let Some(write) = write {
+        while let Err(e) = socket.send_to(&out[..write], send_info.to) {
+            if e.kind() == std::io::ErrorKind::WouldBlock {
+                log::debug!(
+                    "{} -> {}: send() would block",
+                    socket.local_addr().unwrap(),
+                    send_info.to
+                );
+                continue;
+            }
+
+            return Err(ClientError::Other(format!("send() failed: {e:?}")));
         }
 
-        return Err(ClientError::Other(format!("send() failed: {e:?}")));
+        capture_datagram(
+            &mut capture,
+            local_addr,
+            send_info.to,
+            &out[..write],
+            Direction::Outbound,
+        );
     }
 
     let app_data_start = std::time::Instant::now();
 
-    let mut action_iter = actions.iter();
+    let mut action_iter = ActionIter::new(actions);
     let mut wait_duration = None;
     let mut wait_instant = None;
 
     let mut client = SyncClient::new(close_trigger_frames);
+    if let Some(body_capture) = body_capture {
+        client.streams.capture_bodies(body_capture);
+    }
+    client.on_event = interactive.as_mut().and_then(|i| i.on_event.take());
+    client.wait_keepalive.enabled = wait_keepalive;
     let mut waiting_for = WaitingFor::default();
 
+    if record_script.is_some() {
+        client.record();
+    }
+
+    let mut poll_now = false;
+
     loop {
         let actual_sleep = match (wait_duration, conn.timeout()) {
             (Some(wait), Some(timeout)) => {
@@ -181,6 +598,34 @@ pub fn connect(
             _ => None,
         };
 
+        let actual_sleep = match interactive.as_ref().and_then(|i| i.timeout()) {
+            Some(keepalive) =>
+                Some(actual_sleep.map_or(keepalive, |s| s.min(keepalive))),
+            None => actual_sleep,
+        };
+
+        let actual_sleep = match client
+            .wait_keepalive
+            .timeout(wait_duration.is_some(), &conn)
+        {
+            Some(keepalive) =>
+                Some(actual_sleep.map_or(keepalive, |s| s.min(keepalive))),
+            None => actual_sleep,
+        };
+
+        let actual_sleep = match shaper.timeout(Instant::now()) {
+            Some(release) =>
+                Some(actual_sleep.map_or(release, |s| s.min(release))),
+            None => actual_sleep,
+        };
+
+        // Don't wait before sending on a new socket.
+        let actual_sleep = if std::mem::take(&mut poll_now) {
+            Some(Duration::ZERO)
+        } else {
+            actual_sleep
+        };
+
         log::debug!("actual sleep is {:?}", actual_sleep);
         poll.poll(&mut events, actual_sleep).unwrap();
 
@@ -195,13 +640,19 @@ pub fn connect(
         // Read incoming UDP packets from the socket and feed them to quiche,
         // until there are no more packets to read.
         for event in &events {
-            let socket = match event.token() {
-                mio::Token(0) => &socket,
+            let (socket, local_addr) = match event.token() {
+                mio::Token(0) => (&socket, local_addr),
 
-                _ => unreachable!(),
+                // New interactive actions are handled below.
+                WAKER_TOKEN => continue,
+
+                token => {
+                    let path = &probed_paths[token.0 - path_token(1).0];
+
+                    (&path.socket, path.local_addr)
+                },
             };
 
-            let local_addr = socket.local_addr().unwrap();
             'read: loop {
                 let (len, from) = match socket.recv_from(&mut buf) {
                     Ok(v) => v,
@@ -210,6 +661,7 @@ pub fn connect(
                         // There are no more UDP packets to read on this socket.
                         // Process subsequent events.
                         if e.kind() == std::io::ErrorKind::WouldBlock {
+                            faults.end_of_read_burst();
                             break 'read;
                         }
 
@@ -219,9 +671,25 @@ pub fn connect(
                     },
                 };
 
+                capture_datagram(
+                    &mut capture,
+                    from,
+                    local_addr,
+                    &buf[..len],
+                    Direction::Inbound,
+                );
+
+                client.handshake.observe(&mut buf[..len]);
+
+                let Some(len) = faults.incoming(&mut buf[..len]) else {
+                    log::debug!("{}: dropped datagram from {}", local_addr, from);
+                    continue 'read;
+                };
+
                 let recv_info = quiche::RecvInfo {
                     to: local_addr,
                     from,
+                    ecn: quiche::Ecn::NotEct,
                 };
 
                 // Process potentially coalesced packets.
@@ -238,6 +706,21 @@ pub fn connect(
 
         log::debug!("done reading");
 
+        client
+            .streams
+            .update_peer_max_streams(conn.peer_max_streams_bidi());
+
+        while let Ok(len) = conn.dgram_recv(&mut buf) {
+            log::debug!("received datagram len={}", len);
+            client.streams.insert_datagram(&buf[..len]);
+
+            if let Some(on_event) = &mut client.on_event {
+                on_event(ClientEvent::Datagram(buf[..len].to_vec()));
+            }
+        }
+
+        record_stop_sending(&conn, &mut client.streams);
+
         if conn.is_closed() {
             log::info!(
                 "connection closed with error={:?} did_idle_timeout={}, stats={:?} path_stats={:?}",
@@ -253,7 +736,7 @@ pub fn connect(
                     app_data_start.elapsed(),
                 );
 
-                return Err(ClientError::HandshakeFail);
+                return Err(handshake_failure(&conn));
             }
 
             break;
@@ -267,42 +750,44 @@ pub fn connect(
             app_proto_selected = true;
         }
 
-        if app_proto_selected {
-            check_duration_and_do_actions(
+        // Check the server's certificate against the pins once the handshake
+        // is done, and close the connection without executing any more actions
+        // if it doesn't match.
+        if conn.is_established() && !cert_checked {
+            cert_checked = true;
+
+            if let Err(e) = check_pins(&conn, &cert_pins) {
+                log::error!("{:?}", e);
+
+                conn.close(false, BAD_CERTIFICATE, b"").ok();
+                cert_error = Some(e);
+            }
+        }
+
+        if let Some(interactive) = &mut interactive {
+            interactive.queue_actions(&mut action_iter, &mut conn);
+            interactive.keep_alive(&mut conn);
+        }
+
+        client
+            .wait_keepalive
+            .keep_alive(wait_duration.is_some(), &mut conn);
+
+        if app_proto_selected && cert_error.is_none() {
+            let in_early_data = !conn.is_established();
+            let executed = action_iter.yielded();
+
+            run_actions(
                 &mut wait_duration,
                 &mut wait_instant,
                 &mut action_iter,
                 &mut conn,
                 &mut waiting_for,
-                client.stream_parsers_mut(),
+                &mut client,
             );
 
-            let mut wait_cleared = false;
-            for response in parse_streams(&mut conn, &mut client) {
-                let stream_id = response.stream_id;
-
-                if let StreamEventType::Finished = response.event_type {
-                    waiting_for.clear_waits_on_stream(stream_id);
-                } else {
-                    waiting_for.remove_wait(response);
-                }
-
-                wait_cleared = true;
-            }
-
-            if client.streams.all_close_trigger_frames_seen() {
-                client.streams.close_due_to_trigger_frames(&mut conn);
-            }
-
-            if wait_cleared {
-                check_duration_and_do_actions(
-                    &mut wait_duration,
-                    &mut wait_instant,
-                    &mut action_iter,
-                    &mut conn,
-                    &mut waiting_for,
-                    client.stream_parsers_mut(),
-                );
+            if in_early_data && action_iter.yielded() > executed {
+                client.sent_early_data = true;
             }
         }
 
@@ -317,11 +802,10 @@ pub fn connect(
 
         // Generate outgoing QUIC packets and send them on the UDP socket, until
         // quiche reports that there are no more packets to be sent.
-        let sockets = vec![&socket];
-
-        for socket in sockets {
-            let local_addr = socket.local_addr().unwrap();
+        let sockets = std::iter::once((&socket, local_addr))
+            .chain(probed_paths.iter().map(|p| (&p.socket, p.local_addr)));
 
+        for (socket, local_addr) in sockets {
             for peer_addr in conn.paths_iter(local_addr) {
                 loop {
                     let (write, send_info) = match conn.send_on_path(
@@ -348,6 +832,25 @@ pub fn connect(
                         },
                     };
 
+                    let Some(write) = faults.outgoing(&mut out[..write]) else {
+                        log::debug!(
+                            "{} -> {}: dropped datagram",
+                            local_addr,
+                            send_info.to
+                        );
+                        continue;
+                    };
+
+                    if shaper.is_enabled() {
+                        shaper.push(
+                            &out[..write],
+                            local_addr,
+                            send_info.to,
+                            Instant::now(),
+                        );
+                        continue;
+                    }
+
                     if let Err(e) = socket.send_to(&out[..write], send_info.to) {
                         if e.kind() == std::io::ErrorKind::WouldBlock {
                             log::debug!(
@@ -363,10 +866,65 @@ pub fn connect(
                             local_addr, send_info.to, e
                         )));
                     }
+
+                    capture_datagram(
+                        &mut capture,
+                        local_addr,
+                        send_info.to,
+                        &out[..write],
+                        Direction::Outbound,
+                    );
                 }
             }
         }
 
+        // Write the shaped datagrams that are due, or all of them once the
+        // connection is closed, as nothing will be sent afterwards.
+        if conn.is_closed() {
+            for dgram in shaper.flush() {
+                send_shaped(&dgram, &socket, &probed_paths, &mut capture)?;
+            }
+        }
+
+        let now = Instant::now();
+
+        while let Some(dgram) = shaper.pop_due(now) {
+            send_shaped(&dgram, &socket, &probed_paths, &mut capture)?;
+        }
+
+        // Move to the new socket once the packets preceding the migration
+        // were sent on the current one.
+        if let Some(migration) = client.pending_migration.take() {
+            if let Err(e) = migrate(
+                &migration,
+                &mut conn,
+                poll.registry(),
+                &mut socket,
+                &mut local_addr,
+                &socket_options,
+            ) {
+                log::error!("migration failed: {}", e);
+            }
+
+            poll_now = true;
+        }
+
+        for change in std::mem::take(&mut client.pending_path_changes) {
+            if let Err(e) = change_path(
+                &change,
+                &mut conn,
+                poll.registry(),
+                &mut probed_paths,
+                local_addr,
+                peer_addr,
+                &socket_options,
+            ) {
+                log::error!("path change failed: {}", e);
+            }
+
+            poll_now = true;
+        }
+
         if conn.is_closed() {
             log::info!(
                 "connection closed, {:?} {:?}",
@@ -380,42 +938,397 @@ pub fn connect(
                     app_data_start.elapsed(),
                 );
 
-                return Err(ClientError::HandshakeFail);
+                return Err(handshake_failure(&conn));
             }
 
             break;
         }
     }
 
-    Ok(ConnectionSummary {
-        stream_map: client.streams,
-        stats: Some(conn.stats()),
-        path_stats: conn.path_stats().collect(),
-        conn_close_details: ConnectionCloseDetails::new(&conn),
-    })
+    if let Some(Err(e)) = capture.as_mut().map(|c| c.flush()) {
+        log::error!("failed to write to the pcap file: {}", e);
+    }
+
+    if let Some(e) = cert_error {
+        return Err(e);
+    }
+
+    if let Some(path) = &record_script {
+        client.write_script(path, record_timing);
+    }
+
+    if let Some(path) = &session_file {
+        save_session(&conn, path);
+    }
+
+    let summary = client.into_summary(&conn);
+
+    if summary.early_data != EarlyData::NotSent {
+        log::info!("early data {:?}", summary.early_data);
+    }
+
+    Ok(summary)
+}
+
+/// The error of a connection closed before the handshake completed.
+fn handshake_failure(conn: &quiche::Connection) -> ClientError {
+    match verification_failure(conn) {
+        Some(reason) => ClientError::CertificateVerificationFailed(reason),
+        None => ClientError::HandshakeFail,
+    }
+}
+
+/// Connect to a server several times concurrently, executing a separate list
+/// of actions on each connection.
+///
+/// Each entry of `connections` is run by [`connect()`] on its own thread and
+/// socket, with a copy of `args` and `close_trigger_frames`. Only the first
+/// connection records a script to [`Config::record_script`], so that the
+/// connections don't overwrite each other's, and the `i`-th connection's
+/// randomness is seeded with [`Config::rng_seed`] plus `i`, so that they
+/// don't share connection IDs.
+///
+/// Returns a [MultiConnectionSummary] with the result of each connection, in
+/// the order of `connections`.
+pub fn connect_many(
+    args: Config, connections: &[Vec<Action>],
+    close_trigger_frames: Option<CloseTriggerFrames>,
+) -> MultiConnectionSummary {
+    let connections = std::thread::scope(|scope| {
+        let handles: Vec<_> = connections
+            .iter()
+            .enumerate()
+            .map(|(i, actions)| {
+                let mut args = args.clone();
+                let close_trigger_frames = close_trigger_frames.clone();
+
+                if i > 0 {
+                    args.record_script = None;
+                }
+
+                args.rng_seed =
+                    args.rng_seed.map(|seed| seed.wrapping_add(i as u64));
+
+                scope.spawn(move || {
+                    log::info!(
+                        "connection {} starting with {} action(s)",
+                        i,
+                        actions.len()
+                    );
+
+                    connect(args, actions, close_trigger_frames)
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .map(|h| {
+                h.join().unwrap_or_else(|_| {
+                    Err(ClientError::Other("connection panicked".to_string()))
+                })
+            })
+            .collect()
+    });
+
+    MultiConnectionSummary { connections }
+}
+
+/// Saves the session of the connection, so that the next connection resumes
+/// it and sends its first actions as early data.
+fn save_session(conn: &quiche::Connection, path: &str) {
+    let Some(session) = conn.session() else {
+        log::info!("no session to save to {}", path);
+        return;
+    };
+
+    if let Err(e) = std::fs::write(path, session) {
+        log::error!("failed to save session to {}: {}", path, e);
+    }
+}
+
+/// Records the STOP_SENDING frames received since the last call. quiche
+/// answers them with a RESET_STREAM on its own, and reports the stream as
+/// writable so that the error surfaces.
+fn record_stop_sending(conn: &quiche::Connection, streams: &mut StreamMap) {
+    for stream_id in conn.writable() {
+        let Err(quiche::Error::StreamStopped(error_code)) =
+            conn.stream_capacity(stream_id)
+        else {
+            continue;
+        };
+
+        if streams.stop_sending_on_stream(stream_id).is_none() {
+            log::info!(
+                "received stop_sending stream_id={} error_code={}",
+                stream_id,
+                error_code
+            );
+
+            streams.insert_stop_sending(stream_id, error_code);
+        }
+    }
+}
+
+/// Moves the connection to a new socket, replacing `socket`. Unless the
+/// migration emulates a NAT rebinding, the connection migrates to the new path
+/// and `local_addr` is updated. The new socket has the same `socket_options`
+/// as the connection's.
+fn migrate(
+    migration: &Migration, conn: &mut quiche::Connection,
+    registry: &mio::Registry, socket: &mut mio::net::UdpSocket,
+    local_addr: &mut SocketAddr, socket_options: &SocketOptions,
+) -> std::io::Result<()> {
+    let bind_addr = match migration.local_addr {
+        Some(addr) => addr,
+
+        None => SocketAddr::new(socket.local_addr()?.ip(), 0),
+    };
+
+    let mut new_socket = bind_socket(bind_addr, socket_options)?;
+    let new_addr = new_socket.local_addr()?;
+
+    if !migration.nat_rebinding {
+        conn.migrate_source(new_addr).map_err(|e| {
+            std::io::Error::other(format!("can't migrate to {new_addr}: {e}"))
+        })?;
+
+        *local_addr = new_addr;
+    }
+
+    registry.deregister(socket)?;
+    registry.register(&mut new_socket, mio::Token(0), mio::Interest::READABLE)?;
+
+    log::info!(
+        "moved from {} to {} (nat_rebinding={})",
+        socket.local_addr()?,
+        new_addr,
+        migration.nat_rebinding
+    );
+
+    *socket = new_socket;
+
+    Ok(())
+}
+
+/// Writes a datagram delayed by the [SendShaper] to the socket of the path it
+/// was sent on, or to the connection's main socket if that path is gone.
+fn send_shaped(
+    dgram: &Datagram, socket: &mio::net::UdpSocket, probed_paths: &[ProbedPath],
+    capture: &mut Option<Capture>,
+) -> Result<(), ClientError> {
+    let socket = probed_paths
+        .iter()
+        .find(|p| p.local_addr == dgram.from)
+        .map_or(socket, |p| &p.socket);
+
+    if let Err(e) = socket.send_to(&dgram.buf, dgram.to) {
+        if e.kind() == std::io::ErrorKind::WouldBlock {
+            log::debug!("{} -> {}: send() would block", dgram.from, dgram.to);
+            return Ok(());
+        }
+
+        return Err(ClientError::Other(format!(
+            "{} -> {}: send() failed: {:?}",
+            dgram.from, dgram.to, e
+        )));
+    }
+
+    capture_datagram(
+        capture,
+        dgram.from,
+        dgram.to,
+        &dgram.buf,
+        Direction::Outbound,
+    );
+
+    Ok(())
+}
+
+/// A pcapng capture of the datagrams sent and received, see
+/// [`Config::pcap_file`].
+type Capture = PcapngWriter<BufWriter<File>>;
+
+fn open_capture(path: &str) -> Result<Capture, ClientError> {
+    File::create(path)
+        .map(BufWriter::new)
+        .and_then(PcapngWriter::new)
+        .map_err(|e| {
+            ClientError::Other(format!("can't write pcap file {path}: {e}"))
+        })
+}
+
+/// Writes a datagram to the capture, if any. The capture stops at the first
+/// write error, without failing the connection.
+fn capture_datagram(
+    capture: &mut Option<Capture>, from: SocketAddr, to: SocketAddr, buf: &[u8],
+    direction: Direction,
+) {
+    let Some(writer) = capture else {
+        return;
+    };
+
+    let time = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default();
+
+    if let Err(e) = writer.write_datagram(time, from, to, buf, direction) {
+        log::error!("failed to write to the pcap file: {}", e);
+
+        *capture = None;
+    }
+}
+
+/// Probes a new path from a new socket, or switches the active path. Path 0
+/// is the path of the connection's main socket, from `local_addr`.
+fn change_path(
+    change: &PathChange, conn: &mut quiche::Connection, registry: &mio::Registry,
+    probed_paths: &mut Vec<ProbedPath>, local_addr: SocketAddr,
+    peer_addr: SocketAddr, socket_options: &SocketOptions,
+) -> std::io::Result<()> {
+    match change {
+        PathChange::Probe(addr) => {
+            let bind_addr =
+                addr.unwrap_or_else(|| SocketAddr::new(local_addr.ip(), 0));
+
+            let mut socket = bind_socket(bind_addr, socket_options)?;
+            let new_addr = socket.local_addr()?;
+
+            conn.probe_path(new_addr, peer_addr).map_err(|e| {
+                std::io::Error::other(format!("can't probe from {new_addr}: {e}"))
+            })?;
+
+            let path = probed_paths.len() + 1;
+
+            registry.register(
+                &mut socket,
+                path_token(path),
+                mio::Interest::READABLE,
+            )?;
+
+            log::info!("probing path {} from {}", path, new_addr);
+
+            probed_paths.push(ProbedPath {
+                socket,
+                local_addr: new_addr,
+            });
+        },
+
+        PathChange::Switch(path) => {
+            let addr = match path {
+                0 => local_addr,
+
+                n => probed_paths.get(n - 1).map(|p| p.local_addr).ok_or_else(
+                    || std::io::Error::other(format!("no path {n}")),
+                )?,
+            };
+
+            conn.migrate(addr, peer_addr).map_err(|e| {
+                std::io::Error::other(format!("can't switch to path {path}: {e}"))
+            })?;
+
+            log::info!("switched to path {} from {}", path, addr);
+        },
+    }
+
+    Ok(())
+}
+
+/// Executes the actions that are due, then processes the frames received from
+/// the peer and executes the actions that were waiting for them.
+pub(crate) fn run_actions(
+    wait_duration: &mut Option<Duration>, wait_instant: &mut Option<Instant>,
+    action_iter: &mut ActionIter, conn: &mut quiche::Connection,
+    waiting_for: &mut WaitingFor, client: &mut SyncClient,
+) {
+    check_duration_and_do_actions(
+        wait_duration,
+        wait_instant,
+        action_iter,
+        conn,
+        waiting_for,
+        client,
+    );
+
+    let mut wait_cleared = false;
+    for response in parse_streams(conn, client) {
+        let stream_id = response.stream_id;
+
+        match response.event_type {
+            StreamEventType::Finished => {
+                waiting_for.clear_waits_on_stream(stream_id);
+
+                if let Some(on_event) = &mut client.on_event {
+                    on_event(ClientEvent::StreamFinished { stream_id });
+                }
+            },
+
+            StreamEventType::Data => {
+                waiting_for.remove_data_bytes_waits(
+                    stream_id,
+                    client.streams.data_bytes_on_stream(stream_id),
+                );
+                waiting_for.remove_wait(response);
+            },
+
+            _ => waiting_for.remove_wait(response),
+        }
+
+        wait_cleared = true;
+    }
+
+    while let Some(event) = conn.path_event_next() {
+        if let quiche::PathEvent::Validated(local, peer) = event {
+            log::info!("path {} -> {} validated", local, peer);
+            client.validated_paths += 1;
+        }
+    }
+
+    if waiting_for.remove_connection_waits(|event| {
+        connection_event_occurred(event, conn, client)
+    }) {
+        wait_cleared = true;
+    }
+
+    if let Some(error) = conn.peer_error() {
+        client.streams.peer_closed(error);
+    }
+
+    if client.streams.all_close_trigger_frames_seen() {
+        client.streams.close_due_to_trigger_frames(conn);
+    }
+
+    if wait_cleared {
+        check_duration_and_do_actions(
+            wait_duration,
+            wait_instant,
+            action_iter,
+            conn,
+            waiting_for,
+            client,
+        );
+    }
 }
 
 fn check_duration_and_do_actions(
     wait_duration: &mut Option<Duration>, wait_instant: &mut Option<Instant>,
-    action_iter: &mut Iter<Action>, conn: &mut quiche::Connection,
-    waiting_for: &mut WaitingFor, stream_parsers: &mut StreamParserMap,
+    action_iter: &mut ActionIter, conn: &mut quiche::Connection,
+    waiting_for: &mut WaitingFor, client: &mut SyncClient,
 ) {
     match wait_duration.as_ref() {
         None => {
             if let Some(idle_wait) =
-                handle_actions(action_iter, conn, waiting_for, stream_parsers)
+                handle_actions(action_iter, conn, waiting_for, client)
             {
                 *wait_duration = Some(idle_wait);
                 *wait_instant = Some(Instant::now());
 
-                // TODO: the wait period could still be larger than the
-                // negotiated idle timeout.
-                // We could in theory check quiche's idle_timeout value if
-                // it was public.
                 log::info!(
                     "waiting for {:?} before executing more actions",
                     idle_wait
                 );
+
+                check_wait_against_idle_timeout(idle_wait, conn, client);
             }
         },
 
@@ -432,22 +1345,79 @@ fn check_duration_and_do_actions(
                 *wait_duration = None;
 
                 if let Some(idle_wait) =
-                    handle_actions(action_iter, conn, waiting_for, stream_parsers)
+                    handle_actions(action_iter, conn, waiting_for, client)
                 {
                     *wait_duration = Some(idle_wait);
+
+                    check_wait_against_idle_timeout(idle_wait, conn, client);
                 }
             }
         },
     }
 }
 
+/// Warns if a wait is long enough for the connection to idle out, unless
+/// keepalive PINGs are sent during waits.
+fn check_wait_against_idle_timeout(
+    wait: Duration, conn: &quiche::Connection, client: &SyncClient,
+) {
+    let Some(idle_timeout) = conn.idle_timeout() else {
+        return;
+    };
+
+    if wait < idle_timeout {
+        return;
+    }
+
+    if client.wait_keepalive.enabled {
+        log::info!(
+            "wait of {:?} exceeds the idle timeout of {:?}, sending keepalive PINGs",
+            wait,
+            idle_timeout
+        );
+    } else {
+        log::warn!(
+            "wait of {:?} exceeds the idle timeout of {:?}, the connection will likely time out",
+            wait,
+            idle_timeout
+        );
+    }
+}
+
+/// Whether `event` occurred on the connection at any point so far.
+fn connection_event_occurred(
+    event: &ConnectionEvent, conn: &quiche::Connection, client: &SyncClient,
+) -> bool {
+    let received = |matches: fn(&QFrame) -> bool| {
+        client.streams.all_frames().iter().any(|f| match f {
+            H3iFrame::QuicheH3(frame) => matches(frame),
+            _ => false,
+        })
+    };
+
+    match event {
+        ConnectionEvent::GoAway =>
+            received(|f| matches!(f, QFrame::GoAway { .. })),
+
+        ConnectionEvent::Settings =>
+            received(|f| matches!(f, QFrame::Settings { .. })),
+
+        ConnectionEvent::ConnectionClose =>
+            conn.is_draining() || conn.is_closed(),
+
+        ConnectionEvent::HandshakeConfirmed => conn.is_handshake_confirmed(),
+
+        ConnectionEvent::PathValidated => client.validated_paths > 0,
+    }
+}
+
 /// Generate a new pair of Source Connection ID and reset token.
 pub fn generate_cid_and_reset_token() -> (quiche::ConnectionId<'static>, u128) {
     let mut scid = [0; quiche::MAX_CONN_ID_LEN];
-    rand::RngCore::fill_bytes(&mut rand::thread_rng(), &mut scid);
+    quiche::rand_bytes(&mut scid);
     let scid = scid.to_vec().into();
     let mut reset_token = [0; 16];
-    rand::RngCore::fill_bytes(&mut rand::thread_rng(), &mut reset_token);
+    quiche::rand_bytes(&mut reset_token);
     let reset_token = u128::from_be_bytes(reset_token);
     (scid, reset_token)
 }
@@ -470,13 +1440,10 @@ pub fn make_qlog_writer(
     }
 }
 
-fn handle_actions<'a, I>(
-    iter: &mut I, conn: &mut quiche::Connection, waiting_for: &mut WaitingFor,
-    stream_parsers: &mut StreamParserMap,
-) -> Option<Duration>
-where
-    I: Iterator<Item = &'a Action>,
-{
+fn handle_actions(
+    iter: &mut ActionIter, conn: &mut quiche::Connection,
+    waiting_for: &mut WaitingFor, client: &mut SyncClient,
+) -> Option<Duration> {
     if !waiting_for.is_empty() {
         log::debug!(
             "won't fire an action due to waiting for responses: {:?}",
@@ -485,8 +1452,39 @@ where
         return None;
     }
 
+    if client.pending_requests.is_some() {
+        open_concurrent_requests(conn, client);
+
+        if let Some(pending) = &client.pending_requests {
+            return pending.ramp_interval();
+        }
+    }
+
     // Send actions
-    for action in iter {
+    while let Some(action) = iter.next() {
+        // Only the actions of the chosen branch are recorded.
+        if let Action::OnFrame {
+            matcher,
+            then,
+            otherwise,
+        } = action
+        {
+            let met = matcher
+                .check_streams(&client.streams, conn.peer_error())
+                .is_ok();
+
+            log::info!("matcher {:?} met={}", matcher, met);
+
+            iter.push(if met { then } else { otherwise });
+            continue;
+        }
+
+        if let Some(recorder) = &mut client.recorder {
+            recorder.record(action);
+        }
+
+        client.streams.action_executed(action);
+
         match action {
             Action::FlushPackets => return None,
             Action::Wait { wait_type } => match wait_type {
@@ -497,12 +1495,190 @@ where
                         response
                     );
                     waiting_for.add_wait(response);
+
+                    // The DATA might already have been received.
+                    waiting_for.remove_data_bytes_waits(
+                        response.stream_id,
+                        client.streams.data_bytes_on_stream(response.stream_id),
+                    );
+
+                    if !waiting_for.is_empty() {
+                        return None;
+                    }
+                },
+                WaitType::ConnectionEvent(event) => {
+                    if connection_event_occurred(event, conn, client) {
+                        continue;
+                    }
+
+                    log::info!(
+                        "waiting for {:?} before executing more actions",
+                        event
+                    );
+                    waiting_for.add_connection_wait(*event);
+
                     return None;
                 },
             },
-            action => execute_action(action, conn, stream_parsers),
+            Action::AssertBody { .. } => client.streams.record_action(action),
+            Action::SnapshotStats { label } => client.streams.snapshot_stats(
+                label,
+                conn.stats(),
+                conn.path_stats().collect(),
+            ),
+            Action::Migrate {
+                local_addr,
+                nat_rebinding,
+            } => {
+                client.pending_migration = Some(Migration {
+                    local_addr: *local_addr,
+                    nat_rebinding: *nat_rebinding,
+                });
+
+                return None;
+            },
+            Action::ProbePath { local_addr } => {
+                client
+                    .pending_path_changes
+                    .push(PathChange::Probe(*local_addr));
+
+                return None;
+            },
+            Action::SwitchPath { path } => {
+                client.pending_path_changes.push(PathChange::Switch(*path));
+
+                return None;
+            },
+            Action::OpenConcurrentRequests {
+                first_stream_id,
+                count,
+                headers,
+                ramp,
+            } => {
+                log::info!(
+                    "opening {} concurrent requests from stream {}",
+                    count,
+                    first_stream_id
+                );
+
+                client.streams.start_concurrent_requests(
+                    *first_stream_id,
+                    *count,
+                    conn.peer_max_streams_bidi(),
+                );
+
+                client.pending_requests = Some(PendingRequests {
+                    next_stream_id: *first_stream_id,
+                    remaining: *count,
+                    headers: headers.clone(),
+                    ramp: *ramp,
+                });
+
+                open_concurrent_requests(conn, client);
+
+                if let Some(pending) = &client.pending_requests {
+                    return pending.ramp_interval();
+                }
+            },
+            action => {
+                client.streams.record_action(action);
+                execute_action(action, conn, client.stream_parsers_mut());
+            },
         }
     }
 
     None
 }
+
+/// Opens as many of the pending concurrent requests as the peer's stream
+/// credit, and the ramp policy, allow.
+fn open_concurrent_requests(
+    conn: &mut quiche::Connection, client: &mut SyncClient,
+) {
+    let Some(pending) = client.pending_requests.as_mut() else {
+        return;
+    };
+
+    let mut max = pending.remaining;
+
+    if let RampPolicy::Step { step, .. } = pending.ramp {
+        max = cmp::min(max, cmp::max(step, 1));
+    }
+
+    let mut opened = 0;
+
+    // A request stream can only be opened if the peer's limit covers its ID,
+    // regardless of which streams were opened before.
+    while opened < max &&
+        pending.next_stream_id / 4 < conn.peer_max_streams_bidi()
+    {
+        let action = send_headers_frame(
+            pending.next_stream_id,
+            true,
+            pending.headers.clone(),
+        );
+
+        execute_action(&action, conn, &mut client.stream_parsers);
+        client
+            .streams
+            .concurrent_request_opened(pending.next_stream_id);
+
+        pending.next_stream_id += 4;
+        pending.remaining -= 1;
+        opened += 1;
+    }
+
+    if pending.remaining == 0 {
+        log::info!("opened all concurrent requests");
+        client.pending_requests = None;
+    } else if opened == 0 {
+        log::debug!(
+            "waiting for stream credit to open stream {}",
+            pending.next_stream_id
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashSet;
+    use std::net::UdpSocket;
+
+    #[test]
+    fn connect_many_seeded_distinct_scids() {
+        // Nothing answers on this socket, so the connections only send their
+        // Initial packets to it until they time out.
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = socket.local_addr().unwrap().to_string();
+
+        let args = Config::new()
+            .with_host_port(addr.clone())
+            .with_connect_to(addr)
+            .with_idle_timeout(100)
+            .with_rng_seed(42)
+            .build()
+            .unwrap();
+
+        let summary = connect_many(args, &[vec![], vec![], vec![]], None);
+        assert_eq!(summary.connections.len(), 3);
+
+        socket.set_nonblocking(true).unwrap();
+
+        let mut buf = [0; 65535];
+        let mut scids = HashSet::new();
+
+        while let Ok(len) = socket.recv(&mut buf) {
+            let hdr = quiche::Header::from_slice(
+                &mut buf[..len],
+                quiche::MAX_CONN_ID_LEN,
+            )
+            .unwrap();
+
+            scids.insert(hdr.scid.to_vec());
+        }
+
+        assert_eq!(scids.len(), 3);
+    }
+}
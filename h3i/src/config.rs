@@ -24,8 +24,14 @@
 // NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
 // SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
 
-//! Configuration for the h3i client.
+//! Configuration for the h3i client and server.
+use std::collections::HashMap;
 use std::io;
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::recordreplay::script::RecordedTiming;
 
 /// Server details and QUIC connection properties.
 #[derive(Clone)]
@@ -35,12 +41,46 @@ pub struct Config {
     pub host_port: String,
     /// If the SNI should be omitted during the TLS handshake.
     pub omit_sni: bool,
+    /// The SNI to send during the TLS handshake, and to verify the server
+    /// certificate against, instead of the host of `host_port`. The
+    /// `:authority` of requests is not affected. Ignored if `omit_sni` is set.
+    pub sni: Option<String>,
     /// Set a specific IP address to connect to, rather than use DNS resolution.
     pub connect_to: Option<String>,
+    /// How the server's host name is resolved, if `connect_to` isn't set.
+    pub dns: DnsResolution,
     /// The source port to use when connecting to a server.
     pub source_port: u32,
+    /// The local IP address to bind to. By default, h3i binds to the
+    /// unspecified address of the server address' family.
+    pub local_ip: Option<IpAddr>,
+    /// The network interface to bind to, e.g. `eth1`. Only supported on Linux
+    /// and Android.
+    pub interface: Option<String>,
+    /// The IP version to use when resolving the server's host name. By
+    /// default, the family of `local_ip` is used if it is set, or the first
+    /// address resolved otherwise.
+    pub ip_version: Option<IpVersion>,
+    /// The DSCP to mark the packets sent with, in the upper six bits of the
+    /// IPv4 TOS field or the IPv6 traffic class.
+    pub dscp: Option<u8>,
+    /// The ECN codepoint to mark the packets sent with, in the lower two bits
+    /// of the IPv4 TOS field or the IPv6 traffic class. quiche doesn't react
+    /// to ECN, so this only tests how the server and the network handle it.
+    pub ecn: Option<Ecn>,
+    /// Whether to set the don't fragment bit on the packets sent. Either way,
+    /// the kernel's path MTU discovery is disabled. By default, the kernel's
+    /// setting is kept. Only supported on Linux and Android.
+    pub dont_fragment: Option<bool>,
     /// Whether to verify the server certificate.
     pub verify_peer: bool,
+    /// A PEM file of root CAs to verify the server's certificate against,
+    /// instead of the system's.
+    pub ca_file: Option<String>,
+    /// Pins of the server's certificate. If not empty, the certificate must
+    /// match one of them, even if `verify_peer` is false. This allows trusting
+    /// a self-signed certificate without trusting every certificate.
+    pub cert_pins: Vec<CertificatePin>,
     /// The QUIC idle timeout value in milliseconds.
     pub idle_timeout: u64,
     /// Flow control limit for the connection in bytes
@@ -61,6 +101,293 @@ pub struct Config {
     pub max_window: u64,
     /// Receiver window limit for a stream in bytes.
     pub max_stream_window: u64,
+    /// Seed for the randomness used by h3i and quiche, e.g. for connection
+    /// IDs, reset tokens and GREASE, so that connection traces are
+    /// reproducible. The TLS handshake is still randomized.
+    pub rng_seed: Option<u64>,
+    /// The QUIC version to start the connection with. If the server doesn't
+    /// support it, e.g. a reserved version like `0x1a2a3a4a`, it answers with
+    /// Version Negotiation and the connection moves to QUIC v1. Defaults to
+    /// QUIC v1.
+    pub initial_version: Option<u32>,
+    /// Faults to inject into the QUIC handshake.
+    pub handshake_faults: HandshakeFaults,
+    /// Delays and reordering applied to the datagrams sent.
+    pub send_shaping: SendShaping,
+    /// Path of an action script to record the executed actions to, once the
+    /// connection is closed. See [`Recorder`].
+    ///
+    /// [`Recorder`]: crate::recordreplay::script::Recorder
+    pub record_script: Option<String>,
+    /// How the timing of the session is reproduced in the recorded script.
+    pub record_timing: RecordedTiming,
+    /// Path of a pcapng file to write the UDP datagrams sent and received to,
+    /// as they go through the socket, so that the connection can be inspected
+    /// with e.g. Wireshark. Datagrams dropped by [`HandshakeFaults`] are
+    /// written if they were received, but not if they were to be sent.
+    pub pcap_file: Option<String>,
+    /// Path of a file caching the TLS session and transport parameters of the
+    /// server. If the file exists, the connection resumes the session and
+    /// sends the actions executed before the handshake completes as 0-RTT
+    /// early data. The file is then overwritten with the new session.
+    pub session_file: Option<String>,
+    /// The ALPN protocols to offer in the ClientHello, in order of preference.
+    /// They don't need to be HTTP/3 ones, so the server's handling of bogus
+    /// or older draft protocols can be tested. h3i speaks HTTP/3 whichever
+    /// protocol is selected. Defaults to `h3`.
+    pub alpn: Vec<Vec<u8>>,
+    /// Whether to support QUIC DATAGRAM frames, which carry HTTP/3 datagrams.
+    /// HTTP/3 datagrams also require the `SETTINGS_H3_DATAGRAM` setting, which
+    /// must be sent in the SETTINGS frame.
+    pub enable_dgram: bool,
+    /// Extra transport parameters to send in the ClientHello, as `(id, value)`
+    /// pairs. They are encoded after the ones quiche sends on its own.
+    pub custom_transport_params: Vec<(u64, Vec<u8>)>,
+    /// Whether to disable pacing and start with a very large congestion
+    /// window, so that h3i sends as many packets as quiche produces
+    /// back-to-back. Useful to stress the server's buffering and
+    /// anti-amplification handling, e.g. with [`Action::Burst`].
+    ///
+    /// [`Action::Burst`]: crate::actions::h3::Action::Burst
+    pub burst_mode: bool,
+    /// Whether to send PINGs during [`Action::Wait`]s, every half of the
+    /// effective idle timeout, so that waits longer than the idle timeout
+    /// don't close the connection.
+    ///
+    /// [`Action::Wait`]: crate::actions::h3::Action::Wait
+    pub wait_keepalive: bool,
+    /// Where to keep the payloads of the DATA frames received, instead of the
+    /// [`StreamMap`]. See [`BodyCapture`].
+    ///
+    /// [`StreamMap`]: crate::client::connection_summary::StreamMap
+    pub body_capture: Option<BodyCapture>,
+    /// A MASQUE proxy to tunnel the connection through. The proxy connects to
+    /// `connect_to` if set, otherwise to `host_port`.
+    pub masque_proxy: Option<MasqueProxy>,
+}
+
+/// An IP version, to select which of the server's addresses to connect to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IpVersion {
+    V4,
+    V6,
+}
+
+impl IpVersion {
+    /// Whether `addr` belongs to this IP version.
+    pub fn matches(&self, addr: &IpAddr) -> bool {
+        match self {
+            IpVersion::V4 => addr.is_ipv4(),
+            IpVersion::V6 => addr.is_ipv6(),
+        }
+    }
+}
+
+impl From<&IpAddr> for IpVersion {
+    fn from(addr: &IpAddr) -> Self {
+        match addr {
+            IpAddr::V4(_) => IpVersion::V4,
+            IpAddr::V6(_) => IpVersion::V6,
+        }
+    }
+}
+
+/// How the server's host name is resolved.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DnsResolution {
+    /// Addresses to use for host names instead of resolving them, keyed by
+    /// host name.
+    pub overrides: HashMap<String, Vec<IpAddr>>,
+    /// Which of the addresses of the host to connect to, once the ones not
+    /// matching [`Config::ip_version`] are left out.
+    pub selection: AddressSelection,
+    /// How long to wait for the system resolver. If `None`, h3i waits for as
+    /// long as the resolver takes.
+    pub timeout: Option<Duration>,
+}
+
+/// Which of the addresses of a host to connect to, see
+/// [`DnsResolution::selection`].
+///
+/// Selections parse from `first`, `random` or the index of the address.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AddressSelection {
+    /// The first address, in the order of the resolver or the override.
+    #[default]
+    First,
+    /// The address at the given index. Resolution fails if there are fewer
+    /// addresses.
+    Index(usize),
+    /// An address picked at random.
+    Random,
+}
+
+impl FromStr for AddressSelection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "first" => Ok(AddressSelection::First),
+            "random" => Ok(AddressSelection::Random),
+            _ => s
+                .parse()
+                .map(AddressSelection::Index)
+                .map_err(|_| format!("invalid address selection {s}")),
+        }
+    }
+}
+
+/// An ECN codepoint, see
+/// [RFC 3168](https://www.rfc-editor.org/rfc/rfc3168#section-5).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Ecn {
+    NotEct = 0b00,
+    Ect1   = 0b01,
+    Ect0   = 0b10,
+    Ce     = 0b11,
+}
+
+/// Faults injected into the QUIC handshake, to exercise the server's
+/// handshake timers, retransmissions and anti-amplification limit.
+///
+/// Packets are dropped right before they are sent on, or right after they are
+/// received from, the socket, so quiche behaves as if they were lost by the
+/// network.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct HandshakeFaults {
+    /// Stop sending anything after the first datagram, which carries the
+    /// client's first Initial packet.
+    pub stop_after_first_initial: bool,
+    /// Drop all Handshake packets, sent and received, for this long after the
+    /// connection starts. Other packets coalesced in the same datagrams are
+    /// kept.
+    pub drop_handshake: Option<Duration>,
+    /// Discard the server's first flight once, i.e. the first burst of
+    /// datagrams received from the server.
+    pub discard_server_first_flight: bool,
+    /// Drop the Retry packets received from the server, so the client never
+    /// proves its address.
+    pub drop_retry: bool,
+    /// Drop the Version Negotiation packets received from the server, so the
+    /// client keeps trying its initial version.
+    pub drop_version_negotiation: bool,
+}
+
+/// Delays and reordering applied to the datagrams sent, right before they
+/// are written to the socket, to reproduce network conditions without an
+/// external tool like netem.
+///
+/// quiche sees the datagrams as sent when it produces them, so the delays
+/// show up in its RTT estimate.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SendShaping {
+    /// A delay added to every datagram.
+    pub delay: Duration,
+    /// The maximum of a random delay added on top of `delay`, drawn for each
+    /// datagram. Datagrams can overtake each other when it is larger than the
+    /// time between them.
+    pub jitter: Duration,
+    /// The percentage of datagrams held back and sent right after the next
+    /// one, or after 25ms if no other datagram is sent by then.
+    pub reorder_percent: u8,
+}
+
+/// Keeps response bodies out of memory, to fetch large responses.
+///
+/// The DATA frames received are recorded in the [`StreamMap`] with an empty
+/// payload. Their payload is hashed with SHA-256 and, if `dir` is set, written
+/// to a `stream-<id>.body` file per stream in `dir`. See
+/// [`StreamMap::captured_body()`].
+///
+/// [`StreamMap`]: crate::client::connection_summary::StreamMap
+/// [`StreamMap::captured_body()`]: crate::client::connection_summary::StreamMap::captured_body
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BodyCapture {
+    /// The directory to write the bodies to. If `None`, the bodies are
+    /// discarded once hashed.
+    pub dir: Option<String>,
+    /// The maximum number of bytes written to each file. The bytes past it are
+    /// only hashed. If `None`, bodies are written in full.
+    pub max_size: Option<u64>,
+}
+
+/// The default URI template of a MASQUE proxy, see
+/// [RFC 9298](https://www.rfc-editor.org/rfc/rfc9298#section-3).
+pub const DEFAULT_MASQUE_URI_TEMPLATE: &str =
+    "/.well-known/masque/udp/{target_host}/{target_port}/";
+
+/// A MASQUE proxy, which h3i asks to open a UDP tunnel to the server with an
+/// HTTP/3 CONNECT-UDP request. The connection then runs through the tunnel.
+///
+/// The connection to the proxy shares the TLS verification and transport
+/// limits of the [`Config`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MasqueProxy {
+    /// The `<host>:<port>` of the proxy.
+    pub host_port: String,
+    /// The path of the proxy's URI template, in which `{target_host}` and
+    /// `{target_port}` are replaced with the server's.
+    pub uri_template: String,
+}
+
+impl MasqueProxy {
+    /// A proxy at `host_port`, with the [`DEFAULT_MASQUE_URI_TEMPLATE`].
+    pub fn new(host_port: String) -> Self {
+        Self {
+            host_port,
+            uri_template: DEFAULT_MASQUE_URI_TEMPLATE.to_string(),
+        }
+    }
+}
+
+/// A pin of the server's certificate, see [`Config::cert_pins`].
+///
+/// Pins parse from `cert:<hex>` or `spki:<hex>` strings, with the hex-encoded
+/// SHA-256 digest.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CertificatePin {
+    /// The SHA-256 digest of the DER-encoded certificate.
+    Certificate(Vec<u8>),
+    /// The SHA-256 digest of the DER-encoded SubjectPublicKeyInfo of the
+    /// certificate, which is kept when the certificate is renewed with the
+    /// same key.
+    Spki(Vec<u8>),
+}
+
+impl CertificatePin {
+    /// Whether the DER-encoded `cert` matches the pin.
+    pub fn matches(&self, cert: &[u8]) -> bool {
+        let (pinned, pinned_data) = match self {
+            CertificatePin::Certificate(digest) => (digest, Some(cert)),
+
+            CertificatePin::Spki(digest) =>
+                (digest, crate::client::cert::subject_public_key_info(cert)),
+        };
+
+        pinned_data.is_some_and(|data| {
+            ring::digest::digest(&ring::digest::SHA256, data).as_ref() ==
+                pinned.as_slice()
+        })
+    }
+}
+
+impl FromStr for CertificatePin {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || format!("invalid certificate pin {s}");
+
+        let (kind, hex) = s.split_once(':').ok_or_else(err)?;
+        let digest = crate::recordreplay::hex_decode(hex)
+            .filter(|d| d.len() == ring::digest::SHA256_OUTPUT_LEN)
+            .ok_or_else(err)?;
+
+        match kind {
+            "cert" => Ok(CertificatePin::Certificate(digest)),
+            "spki" => Ok(CertificatePin::Spki(digest)),
+            _ => Err(err()),
+        }
+    }
 }
 
 impl Config {
@@ -79,21 +406,80 @@ impl Config {
         self
     }
 
+    pub fn with_sni(mut self, sni: String) -> Self {
+        self.sni = Some(sni);
+        self
+    }
+
     pub fn with_connect_to(mut self, connect_to: String) -> Self {
         self.connect_to = Some(connect_to);
         self
     }
 
+    pub fn with_dns_resolution(mut self, dns: DnsResolution) -> Self {
+        self.dns = dns;
+        self
+    }
+
+    /// Connects to one of `addrs` instead of resolving `host`, like curl's
+    /// `--resolve`.
+    pub fn with_resolve_override(
+        mut self, host: String, addrs: Vec<IpAddr>,
+    ) -> Self {
+        self.dns.overrides.insert(host, addrs);
+        self
+    }
+
     pub fn with_source_port(mut self, port: u32) -> Self {
         self.source_port = port;
         self
     }
 
+    pub fn with_local_ip(mut self, local_ip: IpAddr) -> Self {
+        self.local_ip = Some(local_ip);
+        self
+    }
+
+    pub fn with_interface(mut self, interface: String) -> Self {
+        self.interface = Some(interface);
+        self
+    }
+
+    pub fn with_ip_version(mut self, ip_version: IpVersion) -> Self {
+        self.ip_version = Some(ip_version);
+        self
+    }
+
+    pub fn with_dscp(mut self, dscp: u8) -> Self {
+        self.dscp = Some(dscp);
+        self
+    }
+
+    pub fn with_ecn(mut self, ecn: Ecn) -> Self {
+        self.ecn = Some(ecn);
+        self
+    }
+
+    pub fn with_dont_fragment(mut self, dont_fragment: bool) -> Self {
+        self.dont_fragment = Some(dont_fragment);
+        self
+    }
+
     pub fn verify_peer(mut self, verify_peer: bool) -> Self {
         self.verify_peer = verify_peer;
         self
     }
 
+    pub fn with_ca_file(mut self, ca_file: String) -> Self {
+        self.ca_file = Some(ca_file);
+        self
+    }
+
+    pub fn with_cert_pin(mut self, cert_pin: CertificatePin) -> Self {
+        self.cert_pins.push(cert_pin);
+        self
+    }
+
     pub fn with_idle_timeout(mut self, idle_timeout: u64) -> Self {
         self.idle_timeout = idle_timeout;
         self
@@ -143,6 +529,85 @@ impl Config {
         self
     }
 
+    pub fn with_rng_seed(mut self, rng_seed: u64) -> Self {
+        self.rng_seed = Some(rng_seed);
+        self
+    }
+
+    pub fn with_record_script(mut self, record_script: String) -> Self {
+        self.record_script = Some(record_script);
+        self
+    }
+
+    pub fn with_pcap_file(mut self, pcap_file: String) -> Self {
+        self.pcap_file = Some(pcap_file);
+        self
+    }
+
+    pub fn with_record_timing(mut self, record_timing: RecordedTiming) -> Self {
+        self.record_timing = record_timing;
+        self
+    }
+
+    pub fn with_alpn(mut self, alpn: Vec<Vec<u8>>) -> Self {
+        self.alpn = alpn;
+        self
+    }
+
+    pub fn enable_dgram(mut self) -> Self {
+        self.enable_dgram = true;
+        self
+    }
+
+    pub fn with_session_file(mut self, session_file: String) -> Self {
+        self.session_file = Some(session_file);
+        self
+    }
+
+    pub fn with_initial_version(mut self, initial_version: u32) -> Self {
+        self.initial_version = Some(initial_version);
+        self
+    }
+
+    pub fn with_handshake_faults(
+        mut self, handshake_faults: HandshakeFaults,
+    ) -> Self {
+        self.handshake_faults = handshake_faults;
+        self
+    }
+
+    pub fn with_send_shaping(mut self, send_shaping: SendShaping) -> Self {
+        self.send_shaping = send_shaping;
+        self
+    }
+
+    pub fn burst_mode(mut self) -> Self {
+        self.burst_mode = true;
+        self
+    }
+
+    pub fn wait_keepalive(mut self) -> Self {
+        self.wait_keepalive = true;
+        self
+    }
+
+    pub fn with_body_capture(mut self, body_capture: BodyCapture) -> Self {
+        self.body_capture = Some(body_capture);
+        self
+    }
+
+    pub fn with_masque_proxy(mut self, masque_proxy: MasqueProxy) -> Self {
+        self.masque_proxy = Some(masque_proxy);
+        self
+    }
+
+    pub fn with_custom_transport_param(
+        mut self, id: u64, value: Vec<u8>,
+    ) -> Self {
+        self.custom_transport_params.push((id, value));
+        self
+    }
+
     pub fn build(self) -> Result<Self, io::Error> {
         if self.host_port.is_empty() {
             return Err(io::Error::new(
@@ -151,12 +616,29 @@ impl Config {
             ));
         }
 
+        if self.dscp.is_some_and(|dscp| dscp > 0x3f) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "DSCP must be lower than 64".to_string(),
+            ));
+        }
+
         Ok(Config {
             host_port: self.host_port,
             omit_sni: self.omit_sni,
+            sni: self.sni,
             connect_to: self.connect_to,
+            dns: self.dns,
             source_port: self.source_port,
+            local_ip: self.local_ip,
+            interface: self.interface,
+            ip_version: self.ip_version,
+            dscp: self.dscp,
+            ecn: self.ecn,
+            dont_fragment: self.dont_fragment,
             verify_peer: self.verify_peer,
+            ca_file: self.ca_file,
+            cert_pins: self.cert_pins,
             idle_timeout: self.idle_timeout,
             max_data: self.max_data,
             max_stream_data_bidi_local: self.max_stream_data_bidi_local,
@@ -166,6 +648,21 @@ impl Config {
             max_streams_uni: self.max_streams_uni,
             max_window: self.max_window,
             max_stream_window: self.max_stream_window,
+            rng_seed: self.rng_seed,
+            initial_version: self.initial_version,
+            handshake_faults: self.handshake_faults,
+            send_shaping: self.send_shaping,
+            record_script: self.record_script,
+            record_timing: self.record_timing,
+            pcap_file: self.pcap_file,
+            session_file: self.session_file,
+            alpn: self.alpn,
+            enable_dgram: self.enable_dgram,
+            custom_transport_params: self.custom_transport_params,
+            burst_mode: self.burst_mode,
+            wait_keepalive: self.wait_keepalive,
+            body_capture: self.body_capture,
+            masque_proxy: self.masque_proxy,
         })
     }
 }
@@ -176,9 +673,193 @@ impl Default for Config {
         Self {
             host_port: "".to_string(),
             omit_sni: false,
+            sni: None,
             connect_to: None,
+            dns: DnsResolution::default(),
             source_port: 0,
+            local_ip: None,
+            interface: None,
+            ip_version: None,
+            dscp: None,
+            ecn: None,
+            dont_fragment: None,
             verify_peer: true,
+            ca_file: None,
+            cert_pins: Vec::new(),
+            idle_timeout: 5000,
+            max_data: 10000000,
+            max_stream_data_bidi_local: 10000000,
+            max_stream_data_bidi_remote: 10000000,
+            max_stream_data_uni: 10000000,
+            max_streams_bidi: 100,
+            max_streams_uni: 100,
+            max_window: 25165824,
+            max_stream_window: 16777216,
+            rng_seed: None,
+            initial_version: None,
+            handshake_faults: HandshakeFaults::default(),
+            send_shaping: SendShaping::default(),
+            record_script: None,
+            record_timing: RecordedTiming::default(),
+            pcap_file: None,
+            session_file: None,
+            alpn: vec![b"h3".to_vec()],
+            enable_dgram: false,
+            custom_transport_params: Vec::new(),
+            burst_mode: false,
+            wait_keepalive: false,
+            body_capture: None,
+            masque_proxy: None,
+        }
+    }
+}
+
+/// Listening address, certificate and QUIC connection properties of the h3i
+/// server.
+#[derive(Clone)]
+pub struct ServerConfig {
+    /// The address to listen on, using the format `<ip>:<port>`.
+    pub listen: String,
+    /// Path to the PEM file containing the certificate chain to present.
+    pub cert_file: String,
+    /// Path to the PEM file containing the certificate's private key.
+    pub key_file: String,
+    /// The QUIC idle timeout value in milliseconds.
+    pub idle_timeout: u64,
+    /// Flow control limit for the connection in bytes
+    pub max_data: u64,
+    /// Flow control limit for locally-initiated bidirectional streams in bytes.
+    pub max_stream_data_bidi_local: u64,
+    /// Flow control limit for remotely-initiated bidirectional streams in
+    /// bytes.
+    pub max_stream_data_bidi_remote: u64,
+    /// Flow control limit for unidirectional streams in bytes.
+    pub max_stream_data_uni: u64,
+    /// Maximum count for concurrent remotely-initiated bidirectional streams.
+    pub max_streams_bidi: u64,
+    /// Maximum count for concurrent remotely-initiated unidirectional
+    /// streams.
+    pub max_streams_uni: u64,
+    /// Receiver window limit for the connection in bytes.
+    pub max_window: u64,
+    /// Receiver window limit for a stream in bytes.
+    pub max_stream_window: u64,
+    /// Seed for the randomness used by h3i and quiche, e.g. for connection
+    /// IDs, reset tokens and GREASE, so that connection traces are
+    /// reproducible. The TLS handshake is still randomized.
+    pub rng_seed: Option<u64>,
+    /// Path of an action script to record the executed actions to, once the
+    /// connection is closed. See [`Recorder`].
+    ///
+    /// [`Recorder`]: crate::recordreplay::script::Recorder
+    pub record_script: Option<String>,
+    /// How the timing of the session is reproduced in the recorded script.
+    pub record_timing: RecordedTiming,
+}
+
+impl ServerConfig {
+    /// Construct a new server config object with default values.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_listen(mut self, listen: String) -> Self {
+        self.listen = listen;
+        self
+    }
+
+    pub fn with_cert_file(mut self, cert_file: String) -> Self {
+        self.cert_file = cert_file;
+        self
+    }
+
+    pub fn with_key_file(mut self, key_file: String) -> Self {
+        self.key_file = key_file;
+        self
+    }
+
+    pub fn with_idle_timeout(mut self, idle_timeout: u64) -> Self {
+        self.idle_timeout = idle_timeout;
+        self
+    }
+
+    pub fn with_max_data(mut self, max_data: u64) -> Self {
+        self.max_data = max_data;
+        self
+    }
+
+    pub fn with_max_stream_data_bidi_local(
+        mut self, max_stream_data_bidi_local: u64,
+    ) -> Self {
+        self.max_stream_data_bidi_local = max_stream_data_bidi_local;
+        self
+    }
+
+    pub fn with_max_stream_data_bidi_remote(
+        mut self, max_stream_data_bidi_remote: u64,
+    ) -> Self {
+        self.max_stream_data_bidi_remote = max_stream_data_bidi_remote;
+        self
+    }
+
+    pub fn with_max_stream_data_uni(mut self, max_stream_data_uni: u64) -> Self {
+        self.max_stream_data_uni = max_stream_data_uni;
+        self
+    }
+
+    pub fn with_max_streams_bidi(mut self, max_streams_bidi: u64) -> Self {
+        self.max_streams_bidi = max_streams_bidi;
+        self
+    }
+
+    pub fn with_max_streams_uni(mut self, max_streams_uni: u64) -> Self {
+        self.max_streams_uni = max_streams_uni;
+        self
+    }
+
+    pub fn with_max_window(mut self, max_window: u64) -> Self {
+        self.max_window = max_window;
+        self
+    }
+
+    pub fn with_max_stream_window(mut self, max_stream_window: u64) -> Self {
+        self.max_stream_window = max_stream_window;
+        self
+    }
+
+    pub fn with_rng_seed(mut self, rng_seed: u64) -> Self {
+        self.rng_seed = Some(rng_seed);
+        self
+    }
+
+    pub fn with_record_script(mut self, record_script: String) -> Self {
+        self.record_script = Some(record_script);
+        self
+    }
+
+    pub fn with_record_timing(mut self, record_timing: RecordedTiming) -> Self {
+        self.record_timing = record_timing;
+        self
+    }
+
+    pub fn build(self) -> Result<Self, io::Error> {
+        if self.cert_file.is_empty() || self.key_file.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Must provide a certificate and private key".to_string(),
+            ));
+        }
+
+        Ok(self)
+    }
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            listen: "127.0.0.1:4433".to_string(),
+            cert_file: "".to_string(),
+            key_file: "".to_string(),
             idle_timeout: 5000,
             max_data: 10000000,
             max_stream_data_bidi_local: 10000000,
@@ -188,6 +869,9 @@ impl Default for Config {
             max_streams_uni: 100,
             max_window: 25165824,
             max_stream_window: 16777216,
+            rng_seed: None,
+            record_script: None,
+            record_timing: RecordedTiming::default(),
         }
     }
 }
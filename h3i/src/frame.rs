@@ -210,6 +210,25 @@ impl EnrichedHeaders {
     pub fn status_code(&self) -> Option<&Vec<u8>> {
         self.header_map.get(b":status".as_slice())
     }
+
+    /// Whether these are the headers of an interim, informational (1xx),
+    /// response, such as 103 Early Hints.
+    ///
+    /// # Examples
+    /// ```
+    /// use h3i::frame::EnrichedHeaders;
+    /// use quiche::h3::Header;
+    ///
+    /// let headers = EnrichedHeaders::from(vec![Header::new(b":status", b"103")]);
+    /// assert!(headers.is_informational());
+    ///
+    /// let headers = EnrichedHeaders::from(vec![Header::new(b":status", b"200")]);
+    /// assert!(!headers.is_informational());
+    /// ```
+    pub fn is_informational(&self) -> bool {
+        self.status_code()
+            .is_some_and(|s| s.len() == 3 && s.first() == Some(&b'1'))
+    }
 }
 
 impl Serialize for EnrichedHeaders {
@@ -289,6 +308,7 @@ fn frame_name(frame: &QFrame) -> &'static str {
         QFrame::MaxPushId { .. } => "MAX_PUSH_ID",
         QFrame::PriorityUpdateRequest { .. } => "PRIORITY_UPDATE(REQUEST)",
         QFrame::PriorityUpdatePush { .. } => "PRIORITY_UPDATE(PUSH)",
+        QFrame::Metadata { .. } => "METADATA",
         QFrame::Unknown { .. } => "UNKNOWN",
     }
 }
@@ -432,6 +452,12 @@ impl Serialize for SerializableQFrame<'_> {
                 state.end()
             },
 
+            QFrame::Metadata { header_block } => {
+                let mut state = s.serialize_struct(name, 1)?;
+                state.serialize_field("header_block_len", &header_block.len())?;
+                state.end()
+            },
+
             QFrame::Unknown { raw_type, payload } => {
                 let mut state = s.serialize_struct(name, 3)?;
                 state.serialize_field("raw_type", &raw_type)?;
@@ -32,16 +32,25 @@ use quiche::h3::Result;
 use quiche::Connection;
 
 use crate::frame::H3iFrame;
+use crate::HTTP3_CONTROL_STREAM_TYPE_ID;
+use crate::HTTP3_PUSH_STREAM_TYPE_ID;
 
 /// Max stream state size in bytes (2MB).
 const MAX_STREAM_STATE_SIZE: usize = 2_000_000;
 
+/// Max number of bytes read at once from streams that aren't HTTP/3 framed.
+const MAX_RAW_READ_SIZE: usize = 65_535;
+
 #[derive(Debug, Default, PartialEq, Eq)]
 enum FrameState {
     #[default]
     Type,
     Len,
     Val,
+    /// The type of a unidirectional stream.
+    StreamType,
+    /// The push ID of a push stream.
+    PushId,
 }
 
 #[derive(Debug, Eq, PartialEq)]
@@ -65,6 +74,10 @@ pub enum FrameParseResult {
     /// A frame is in the middle of being parsed, but either a FIN bit or a
     /// RESET_STREAM was received.
     Interrupted(InterruptCause),
+    /// The stream was identified as a push stream for the push ID `push_id`.
+    /// The caller should keep calling try_parse_frame() to read the frames of
+    /// the push response.
+    PushStream { push_id: u64 },
 }
 
 /// Parses [`H3iFrame`]s from a QUIC stream.
@@ -81,6 +94,12 @@ pub(crate) struct FrameParser {
 
     stream_id: u64,
 
+    // Whether the stream carries unframed data, e.g. a WebTransport stream.
+    raw: bool,
+
+    // Whether the stream's data is discarded, e.g. on a QPACK stream.
+    skip: bool,
+
     curr_state: FrameState,
     state_buf: Vec<u8>,
     state_offset: usize,
@@ -95,6 +114,32 @@ impl FrameParser {
         }
     }
 
+    /// Creates a parser for a stream that doesn't carry HTTP/3 frames, such as
+    /// a bidirectional WebTransport stream.
+    ///
+    /// Every read from the stream is returned as is, in a DATA frame.
+    pub(crate) fn raw(stream_id: u64) -> Self {
+        Self {
+            stream_id,
+            raw: true,
+            ..Default::default()
+        }
+    }
+
+    /// Creates a parser for a unidirectional stream opened by the peer.
+    ///
+    /// The stream type is read first. The frames of control and push streams
+    /// are then parsed, after [FrameParseResult::PushStream] is returned for
+    /// the latter. The data of other streams, such as QPACK streams, is
+    /// discarded.
+    pub(crate) fn uni(stream_id: u64) -> Self {
+        Self {
+            stream_id,
+            curr_state: FrameState::StreamType,
+            ..Default::default()
+        }
+    }
+
     /// Attempt to pull stream data from a [`quiche::Connection`] and build a
     /// complete frame.
     ///
@@ -118,6 +163,14 @@ impl FrameParser {
     pub(crate) fn try_parse_frame(
         &mut self, qconn: &mut Connection,
     ) -> Result<FrameParseResult> {
+        if self.raw {
+            return self.try_read_raw(qconn);
+        }
+
+        if self.skip {
+            return self.try_skip(qconn);
+        }
+
         loop {
             let (len, fin) = match self.try_fill_buffer(qconn, self.stream_id) {
                 Ok((l, f)) => (l, f),
@@ -139,6 +192,39 @@ impl FrameParser {
             };
 
             match self.curr_state {
+                FrameState::StreamType => {
+                    let Ok(stream_type) = self.try_consume_varint() else {
+                        return Ok(FrameParseResult::Retry);
+                    };
+
+                    match stream_type {
+                        HTTP3_CONTROL_STREAM_TYPE_ID =>
+                            self.state_transition(FrameState::Type, 1)?,
+
+                        HTTP3_PUSH_STREAM_TYPE_ID =>
+                            self.state_transition(FrameState::PushId, 1)?,
+
+                        _ => {
+                            log::debug!(
+                                "{} stream={} skipping stream type={}",
+                                qconn.trace_id(),
+                                self.stream_id,
+                                stream_type
+                            );
+
+                            self.skip = true;
+                            return self.try_skip(qconn);
+                        },
+                    }
+                },
+                FrameState::PushId => {
+                    let Ok(push_id) = self.try_consume_varint() else {
+                        return Ok(FrameParseResult::Retry);
+                    };
+
+                    self.state_transition(FrameState::Type, 1)?;
+                    return Ok(FrameParseResult::PushStream { push_id });
+                },
                 FrameState::Type => {
                     let Ok(varint) = self.try_consume_varint() else {
                         // Map Error::Done's to Retry's because state_buf must be
@@ -201,6 +287,57 @@ impl FrameParser {
         }
     }
 
+    fn try_read_raw(
+        &mut self, qconn: &mut Connection,
+    ) -> Result<FrameParseResult> {
+        let mut buf = vec![0; MAX_RAW_READ_SIZE];
+
+        let (len, fin) = match qconn.stream_recv(self.stream_id, &mut buf) {
+            Ok(v) => v,
+            Err(quiche::Error::StreamReset(err)) =>
+                return Ok(FrameParseResult::Interrupted(
+                    InterruptCause::ResetStream(err),
+                )),
+            Err(e) => return Err(H3Error::TransportError(e)),
+        };
+
+        log::trace!(
+            "{} stream={} read raw bytes={len} fin={fin}",
+            qconn.trace_id(),
+            self.stream_id
+        );
+
+        if len == 0 && fin {
+            return Ok(FrameParseResult::Interrupted(InterruptCause::FinBit));
+        }
+
+        buf.truncate(len);
+
+        Ok(FrameParseResult::FrameParsed {
+            h3i_frame: H3iFrame::QuicheH3(QFrame::Data { payload: buf }),
+            fin,
+        })
+    }
+
+    fn try_skip(&mut self, qconn: &mut Connection) -> Result<FrameParseResult> {
+        let mut buf = vec![0; MAX_RAW_READ_SIZE];
+
+        loop {
+            match qconn.stream_recv(self.stream_id, &mut buf) {
+                Ok((_, true)) =>
+                    return Ok(FrameParseResult::Interrupted(
+                        InterruptCause::FinBit,
+                    )),
+                Ok(_) => (),
+                Err(quiche::Error::StreamReset(err)) =>
+                    return Ok(FrameParseResult::Interrupted(
+                        InterruptCause::ResetStream(err),
+                    )),
+                Err(e) => return Err(H3Error::TransportError(e)),
+            }
+        }
+    }
+
     fn frame_incomplete(&self) -> bool {
         !self.state_buf.is_empty() && !self.state_buffer_complete()
     }
@@ -310,6 +447,8 @@ impl Default for FrameParser {
             len: None,
             curr_state: FrameState::default(),
             stream_id: 0,
+            raw: false,
+            skip: false,
             state_buf: vec![0],
             state_offset: 0,
             state_len: 1,
@@ -797,4 +936,81 @@ mod tests {
             )))
         );
     }
+
+    #[test]
+    fn raw_stream() {
+        let mut s = session().unwrap();
+        s.handshake().unwrap();
+
+        let mut parser = FrameParser::raw(0);
+
+        // Looks like a frame header, but must not be parsed as one.
+        s.send_arbitrary_stream_data_client(&[0, 5, 1, 2], 0, false)
+            .expect("first");
+        let res = parser.try_parse_frame(&mut s.pipe.server).unwrap();
+        assert_eq!(res, FrameParseResult::FrameParsed {
+            h3i_frame: H3iFrame::QuicheH3(Frame::Data {
+                payload: vec![0, 5, 1, 2]
+            }),
+            fin: false
+        });
+
+        s.send_arbitrary_stream_data_client(&[], 0, true)
+            .expect("second");
+        assert_eq!(
+            parser.try_parse_frame(&mut s.pipe.server),
+            Ok(FrameParseResult::Interrupted(InterruptCause::FinBit))
+        );
+    }
+
+    #[test]
+    fn push_stream() {
+        let mut s = session().unwrap();
+        s.handshake().unwrap();
+
+        let mut parser = FrameParser::uni(14);
+
+        // Stream type, push ID, then a DATA frame.
+        s.send_arbitrary_stream_data_client(
+            &[0x1, 0x4, 0x0, 0x2, 1, 2],
+            14,
+            true,
+        )
+        .expect("first");
+        assert_eq!(
+            parser.try_parse_frame(&mut s.pipe.server),
+            Ok(FrameParseResult::PushStream { push_id: 4 })
+        );
+
+        let res = parser.try_parse_frame(&mut s.pipe.server).unwrap();
+        assert_eq!(res, FrameParseResult::FrameParsed {
+            h3i_frame: H3iFrame::QuicheH3(Frame::Data {
+                payload: vec![1, 2]
+            }),
+            fin: true
+        });
+    }
+
+    #[test]
+    fn skipped_uni_stream() {
+        let mut s = session().unwrap();
+        s.handshake().unwrap();
+
+        let mut parser = FrameParser::uni(14);
+
+        // A QPACK encoder stream, whose data isn't parsed.
+        s.send_arbitrary_stream_data_client(&[0x2, 0x0, 0x5, 1], 14, false)
+            .expect("first");
+        assert_eq!(
+            parser.try_parse_frame(&mut s.pipe.server),
+            Err(H3Error::TransportError(quiche::Error::Done))
+        );
+
+        s.send_arbitrary_stream_data_client(&[0x0, 0x5], 14, true)
+            .expect("second");
+        assert_eq!(
+            parser.try_parse_frame(&mut s.pipe.server),
+            Ok(FrameParseResult::Interrupted(InterruptCause::FinBit))
+        );
+    }
 }
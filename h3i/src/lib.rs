@@ -38,6 +38,9 @@
 //! stream, in any order, containing user-controlled content (both legal and
 //! illegal).
 //!
+//! The same actions can be executed by h3i acting as a server, see [server],
+//! in order to test the behavior of clients.
+//!
 //! # Example
 //!
 //! The following example sends a request with its Content-Length header set to
@@ -131,9 +134,10 @@
 //! ) -> std::result::Result<Vec<u8>, String> {
 //!     let mut encoder = quiche::h3::qpack::Encoder::new();
 //!
+//!     // Leave room for the 2-byte prefix, even if there are no headers.
 //!     let headers_len = headers
 //!         .iter()
-//!         .fold(0, |acc, h| acc + h.value().len() + h.name().len() + 32);
+//!         .fold(2, |acc, h| acc + h.value().len() + h.name().len() + 32);
 //!
 //!     let mut header_block = vec![0; headers_len];
 //!     let len = encoder
@@ -212,9 +216,10 @@ fn encode_header_block_literal(
 ) -> std::result::Result<Vec<u8>, String> {
     // This is a combination of a modified `quiche::h3::qpack::Encoder::encode`
     // and the [`encode_header_block`] function.
+    // Leave room for the 2-byte prefix, even if there are no headers.
     let headers_len = headers
         .iter()
-        .fold(0, |acc, h| acc + h.value().len() + h.name().len() + 32);
+        .fold(2, |acc, h| acc + h.value().len() + h.name().len() + 32);
 
     let mut header_block = vec![0; headers_len];
 
@@ -244,9 +249,10 @@ fn encode_header_block(
 ) -> std::result::Result<Vec<u8>, String> {
     let mut encoder = quiche::h3::qpack::Encoder::new();
 
+    // Leave room for the 2-byte prefix, even if there are no headers.
     let headers_len = headers
         .iter()
-        .fold(0, |acc, h| acc + h.value().len() + h.name().len() + 32);
+        .fold(2, |acc, h| acc + h.value().len() + h.name().len() + 32);
 
     let mut header_block = vec![0; headers_len];
     let len = encoder
@@ -288,3 +294,4 @@ pub mod frame;
 pub mod frame_parser;
 pub mod prompts;
 pub mod recordreplay;
+pub mod server;
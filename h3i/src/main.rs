@@ -27,16 +27,31 @@
 use std::env;
 use std::io::BufReader;
 use std::result::Result;
+use std::str::FromStr;
 use std::time;
-use std::time::Instant;
 
 use h3i::actions::h3::Action;
+use h3i::client::baseline::Baseline;
+use h3i::client::baseline::Tolerance;
+use h3i::client::connection_summary::CloseTriggerFrames;
 use h3i::client::connection_summary::ConnectionSummary;
+use h3i::client::diagram::sequence_diagram;
+use h3i::client::diagram::DiagramFormat;
+use h3i::client::expectations::Expectations;
 use h3i::client::ClientError;
+use h3i::client::ClientEvent;
+use h3i::config::AddressSelection;
+use h3i::config::BodyCapture;
+use h3i::config::CertificatePin;
+use h3i::config::DnsResolution;
+use h3i::config::Ecn;
+use h3i::config::IpVersion;
+use h3i::config::MasqueProxy;
+use h3i::config::SendShaping;
+use h3i::config::DEFAULT_MASQUE_URI_TEMPLATE;
 use h3i::prompts::h3::Prompter;
-use h3i::recordreplay::qlog::QlogEvent;
 use h3i::recordreplay::qlog::*;
-use qlog::reader::QlogSeqReader;
+use h3i::recordreplay::script::RecordedTiming;
 
 use clap::App;
 use clap::Arg;
@@ -58,22 +73,165 @@ fn main() -> Result<(), ClientError> {
         },
     };
 
-    let actions = match &config.qlog_input {
-        Some(v) => read_qlog(v, config.host_override.as_deref()),
-        None => prompt_frames(&config),
-    };
+    if config.interactive {
+        return run_interactive(config);
+    }
+
+    if let Some(suite) = &config.suite {
+        return run_suite(&config, suite);
+    }
+
+    let (actions, mut connections, close_trigger_frames, expectations) =
+        match (&config.script, &config.qlog_input) {
+            (Some(path), _) => match h3i::actions::from_file(path) {
+                Ok(scenario) => (
+                    scenario.actions,
+                    scenario.connections,
+                    scenario.close_trigger_frames,
+                    scenario.expectations,
+                ),
+
+                Err(e) => {
+                    log::error!("Error loading script {}, exiting: {}", path, e);
+                    return Err(ClientError::Other("Invalid script".into()));
+                },
+            },
+
+            (None, Some(v)) => (
+                read_qlog(v, config.host_override.as_deref()),
+                vec![],
+                None,
+                Expectations::default(),
+            ),
+
+            (None, None) => (
+                prompt_frames(&config),
+                vec![],
+                None,
+                Expectations::default(),
+            ),
+        };
+
+    if connections.is_empty() && config.connections > 1 {
+        connections = vec![actions.clone(); config.connections];
+    }
+
+    if !connections.is_empty() {
+        return run_connections(
+            config,
+            &connections,
+            close_trigger_frames,
+            &expectations,
+        );
+    }
 
-    match sync_client(config, &actions) {
+    if let Some(fuzz) = config.fuzz {
+        return run_fuzz(config, fuzz, &actions, close_trigger_frames);
+    }
+
+    let baseline = config.baseline.clone();
+    let summary_format = config.summary_format;
+    let sequence_diagram = config.sequence_diagram.clone();
+
+    match sync_client(config, &actions, close_trigger_frames) {
         Ok(summary) => {
             log::debug!(
                 "received connection_summary: {}",
                 serde_json::to_string_pretty(&summary)
                     .unwrap_or_else(|e| e.to_string())
             );
+
+            if let Some(format) = summary_format {
+                format.print(&summary);
+            }
+
+            let grease_report = summary.grease_report(&actions);
+
+            if grease_report.sent_grease() {
+                log::info!(
+                    "grease report: {}",
+                    serde_json::to_string(&grease_report)
+                        .unwrap_or_else(|e| e.to_string())
+                );
+
+                if grease_report.peer_choked() {
+                    log::warn!("peer may have choked on GREASE");
+                }
+            }
+
+            for report in summary.stream_map.concurrent_requests() {
+                log::info!(
+                    "concurrent requests from stream {}: opened={}/{} refused={} max_streams={:?}",
+                    report.first_stream_id,
+                    report.opened.len(),
+                    report.count,
+                    report.refused.len(),
+                    report
+                        .max_streams
+                        .iter()
+                        .map(|g| g.max_streams)
+                        .collect::<Vec<_>>()
+                );
+            }
+
+            let throughput = summary.throughput_report();
+
+            if throughput.goodput_bytes > 0 {
+                log::info!(
+                    "throughput report: {}",
+                    serde_json::to_string(&throughput)
+                        .unwrap_or_else(|e| e.to_string())
+                );
+            }
+
+            let failed_body_assertions = summary
+                .stream_map
+                .body_assertions()
+                .iter()
+                .filter(|r| !r.passed())
+                .count();
+
+            if failed_body_assertions > 0 {
+                log::error!(
+                    "{} body assertion(s) failed",
+                    failed_body_assertions
+                );
+            }
+
+            if let Some(baseline) = &baseline {
+                check_baseline(baseline, &summary);
+            }
+
+            if let Some(path) = &sequence_diagram {
+                write_sequence_diagram(path, &summary);
+            }
+
+            if !expectations.is_empty() {
+                let report = expectations.evaluate(&summary);
+
+                for result in report.failures() {
+                    log::error!(
+                        "expectation failed: {}",
+                        result.failure.as_deref().unwrap_or_default()
+                    );
+                }
+
+                if !report.passed() {
+                    return Err(ClientError::Other(
+                        "Expectations not met".into(),
+                    ));
+                }
+
+                log::info!("all {} expectation(s) met", report.results.len());
+            }
         },
 
         Err(e) => {
             log::error!("error: {:?}", e);
+
+            if let Some(format) = summary_format {
+                format.print(&serde_json::json!({ "error": e }));
+            }
         },
     }
 
@@ -83,11 +241,122 @@ fn main() -> Result<(), ClientError> {
 struct Config {
     library_config: h3i::config::Config,
     pub qlog_input: Option<String>,
+    pub script: Option<String>,
     pub qlog_actions_output: bool,
     pub host_override: Option<String>,
+    pub baseline: Option<BaselineArgs>,
+    pub connections: usize,
+    pub summary_format: Option<SummaryFormat>,
+    pub sequence_diagram: Option<String>,
+    pub fuzz: Option<FuzzArgs>,
+    pub suite: Option<SuiteArgs>,
+    pub interactive: bool,
+}
+
+#[derive(Clone, Copy)]
+struct FuzzArgs {
+    seed: u64,
+    iterations: u64,
+}
+
+#[derive(Clone)]
+struct SuiteArgs {
+    dir: String,
+    jobs: usize,
+    report: Option<String>,
+}
+
+/// How the connection summary is written to stdout.
+#[derive(Clone, Copy)]
+enum SummaryFormat {
+    /// A single pretty-printed JSON document.
+    Json,
+    /// One JSON document per line, i.e. one per connection.
+    Ndjson,
+}
+
+impl SummaryFormat {
+    fn print(self, value: &impl serde::Serialize) {
+        let serialized = match self {
+            SummaryFormat::Json => serde_json::to_string_pretty(value),
+            SummaryFormat::Ndjson => serde_json::to_string(value),
+        };
+
+        match serialized {
+            Ok(v) => println!("{v}"),
+
+            Err(e) => log::error!("failed to serialize summary: {}", e),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct BaselineArgs {
+    path: String,
+    tolerance: Tolerance,
+    update: bool,
+}
+
+/// Returns the process arguments with the defaults from the TOML file passed
+/// via `--config` inserted before the ones given on the command line.
+///
+/// Keys are long option names without the leading dashes. Options given on
+/// the command line take precedence over the ones in the file.
+fn args_with_config_file() -> std::result::Result<Vec<String>, String> {
+    let args: Vec<String> = env::args().collect();
+
+    let path = args.iter().enumerate().find_map(|(i, arg)| {
+        if arg == "--config" {
+            return args.get(i + 1).cloned();
+        }
+
+        arg.strip_prefix("--config=").map(|p| p.to_string())
+    });
+
+    let Some(path) = path else {
+        return Ok(args);
+    };
+
+    let table = std::fs::read_to_string(&path)
+        .map_err(|e| format!("failed to read config file {path}: {e}"))?
+        .parse::<toml::Table>()
+        .map_err(|e| format!("failed to parse config file {path}: {e}"))?;
+
+    let mut merged = vec![args[0].clone()];
+
+    for (key, value) in table {
+        let option = format!("--{key}");
+
+        let present = args.iter().any(|a| {
+            a == &option ||
+                a.strip_prefix(&option).is_some_and(|r| r.starts_with('='))
+        });
+
+        if present {
+            continue;
+        }
+
+        match value {
+            toml::Value::Boolean(true) => merged.push(option),
+
+            toml::Value::Boolean(false) => (),
+
+            toml::Value::String(v) => merged.extend([option, v]),
+
+            toml::Value::Integer(v) => merged.extend([option, v.to_string()]),
+
+            _ => return Err(format!("unsupported value for config key {key}")),
+        }
+    }
+
+    merged.extend(args.into_iter().skip(1));
+
+    Ok(merged)
 }
 
 fn config_from_clap() -> std::result::Result<Config, String> {
+    let argv = args_with_config_file()?;
+
     let matches = App::new("h3i")
         .version("v0.1.0")
         .about("Interactive HTTP/3 console debugger")
@@ -97,6 +366,12 @@ fn config_from_clap() -> std::result::Result<Config, String> {
                 .required(true)
                 .index(1),
         )
+        .arg(
+            Arg::with_name("config")
+                .long("config")
+                .help("Read default option values from the given TOML file.")
+                .takes_value(true),
+        )
         .arg(
             Arg::with_name("omit-sni")
                 .long("omit-sni")
@@ -104,17 +379,101 @@ fn config_from_clap() -> std::result::Result<Config, String> {
                 // Requires an OsStr, so we can parse to empty later on
                 .takes_value(false)
         )
+        .arg(
+            Arg::with_name("sni")
+                .long("sni")
+                .help("Send the given SNI in the TLS handshake, and verify the server certificate against it, instead of the host of <host:port>. The :authority of requests is not affected.")
+                .takes_value(true)
+                .conflicts_with("omit-sni"),
+        )
+        .arg(
+            Arg::with_name("local-ip")
+                .long("local-ip")
+                .help("Bind to the given local IP address, rather than the unspecified address.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("interface")
+                .long("interface")
+                .help("Bind to the given network interface, e.g. eth1. Only supported on Linux and Android.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("ipv4")
+                .long("ipv4")
+                .help("Only connect to an IPv4 address of the server.")
+                .conflicts_with("ipv6"),
+        )
+        .arg(
+            Arg::with_name("ipv6")
+                .long("ipv6")
+                .help("Only connect to an IPv6 address of the server."),
+        )
+        .arg(
+            Arg::with_name("dscp")
+                .long("dscp")
+                .help("Mark the packets sent with the given DSCP, from 0 to 63.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("ecn")
+                .long("ecn")
+                .help("Mark the packets sent with the given ECN codepoint.")
+                .takes_value(true)
+                .possible_values(["not-ect", "ect0", "ect1", "ce"]),
+        )
+        .arg(
+            Arg::with_name("dont-fragment")
+                .long("dont-fragment")
+                .help("Set (true) or clear (false) the don't fragment bit of the packets sent, disabling the kernel's path MTU discovery. Only supported on Linux and Android.")
+                .takes_value(true)
+                .possible_values(["true", "false"]),
+        )
         .arg(
             Arg::with_name("connect-to")
                 .long("connect-to")
                 .help("Set a specific IP address to connect to, rather than use DNS resolution")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("resolve")
+                .long("resolve")
+                .help("Use the given addresses for a host instead of resolving it, as <host>=<addr>[,<addr>...]. Can be repeated.")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::with_name("dns-select")
+                .long("dns-select")
+                .help("Which of the server's addresses to connect to: first, random, or the index of the address.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("dns-timeout")
+                .long("dns-timeout")
+                .help("Give up resolving the server's host name after the given number of milliseconds.")
+                .takes_value(true),
+        )
         .arg(
             Arg::with_name("no-verify")
                 .long("no-verify")
                 .help("Don't verify server's certificate."),
         )
+        .arg(
+            Arg::with_name("ca-file")
+                .long("ca-file")
+                .help("PEM file of root CAs to verify the server's certificate against, instead of the system's.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("pin-cert")
+                .long("pin-cert")
+                .help("Require the server's certificate to match a pin, as cert:<hex SHA-256 of the certificate> or spki:<hex SHA-256 of its SubjectPublicKeyInfo>, even with --no-verify. Can be repeated to accept several certificates.")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
         .arg(
             Arg::with_name("no-qlog-actions-output")
                 .long("no-qlog-actions-output")
@@ -126,6 +485,31 @@ fn config_from_clap() -> std::result::Result<Config, String> {
                 .help("Drive connection via qlog rather than cli.")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("record-script")
+                .long("record-script")
+                .help("Record the executed actions to a JSON or YAML script, to replay them with --script.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("record-durations")
+                .long("record-durations")
+                .help("Record waits as the durations they took, rather than as they were executed.")
+                .requires("record-script"),
+        )
+        .arg(
+            Arg::with_name("pcap")
+                .long("pcap")
+                .help("Write the UDP datagrams sent and received to a pcapng file, to inspect the connection in Wireshark.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("script")
+                .long("script")
+                .help("Drive connection via a JSON or YAML script rather than cli.")
+                .conflicts_with("qlog-input")
+                .takes_value(true),
+        )
         .arg(
             Arg::with_name("idle-timeout")
                 .long("idle-timeout")
@@ -189,6 +573,217 @@ fn config_from_clap() -> std::result::Result<Config, String> {
                 .takes_value(true)
                 .default_value("16777216"),
         )
+        .arg(
+            Arg::with_name("rng-seed")
+                .long("rng-seed")
+                .help("Seed the randomness used for connection IDs, reset tokens and GREASE, making connection traces reproducible.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("alpn")
+                .long("alpn")
+                .help("Offer the given ALPN protocol, instead of h3. Can be repeated to offer several, in order of preference.")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::with_name("enable-dgram")
+                .long("enable-dgram")
+                .help("Support QUIC DATAGRAM frames, to send and receive HTTP/3 datagrams."),
+        )
+        .arg(
+            Arg::with_name("burst-mode")
+                .long("burst-mode")
+                .help("Disable pacing and start with a very large congestion window, to send as many packets as possible back-to-back."),
+        )
+        .arg(
+            Arg::with_name("wait-keepalive")
+                .long("wait-keepalive")
+                .help("Send PINGs during waits, so that waits longer than the idle timeout don't close the connection."),
+        )
+        .arg(
+            Arg::with_name("transport-param")
+                .long("transport-param")
+                .help("Send an extra transport parameter in the ClientHello, as <id>=<hex value>, e.g. 0x2ab2=0102. Can be repeated.")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::with_name("session-file")
+                .long("session-file")
+                .help("File to cache the server's TLS session in. If the file exists, the session is resumed and the actions executed before the handshake completes are sent as 0-RTT early data.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("masque-proxy")
+                .long("masque-proxy")
+                .help("Tunnel the connection through the MASQUE proxy at this <host>:<port>, with HTTP/3 CONNECT-UDP. The proxy connects to --connect-to if given, otherwise to the server's <host>:<port>.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("masque-uri-template")
+                .long("masque-uri-template")
+                .help("Path of the MASQUE proxy's URI template, with {target_host} and {target_port} variables.")
+                .takes_value(true)
+                .default_value(DEFAULT_MASQUE_URI_TEMPLATE)
+                .requires("masque-proxy"),
+        )
+        .arg(
+            Arg::with_name("capture-bodies")
+                .long("capture-bodies")
+                .help("Write response bodies to a stream-<id>.body file per stream in this directory, instead of keeping them in memory.")
+                .takes_value(true)
+                .conflicts_with("discard-bodies"),
+        )
+        .arg(
+            Arg::with_name("discard-bodies")
+                .long("discard-bodies")
+                .help("Discard response bodies once hashed, instead of keeping them in memory."),
+        )
+        .arg(
+            Arg::with_name("max-capture-size")
+                .long("max-capture-size")
+                .help("Maximum number of bytes of each response body written by --capture-bodies. The rest of the body is only hashed.")
+                .takes_value(true)
+                .requires("capture-bodies"),
+        )
+        .arg(
+            Arg::with_name("stop-after-first-initial")
+                .long("stop-after-first-initial")
+                .help("Stop sending anything after the first Initial packet."),
+        )
+        .arg(
+            Arg::with_name("drop-handshake-packets")
+                .long("drop-handshake-packets")
+                .help("Drop all Handshake packets, sent and received, for the given number of milliseconds after the connection starts.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("discard-server-first-flight")
+                .long("discard-server-first-flight")
+                .help("Discard the first flight of datagrams received from the server, once."),
+        )
+        .arg(
+            Arg::with_name("drop-retry")
+                .long("drop-retry")
+                .help("Drop the Retry packets received from the server."),
+        )
+        .arg(
+            Arg::with_name("drop-version-negotiation")
+                .long("drop-version-negotiation")
+                .help("Drop the Version Negotiation packets received from the server."),
+        )
+        .arg(
+            Arg::with_name("initial-version")
+                .long("initial-version")
+                .help("Start the connection with the given QUIC version, in hex. Use a reserved version like 0x1a2a3a4a to force Version Negotiation.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("send-delay")
+                .long("send-delay")
+                .help("Delay every datagram sent by the given number of milliseconds.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("send-jitter")
+                .long("send-jitter")
+                .help("Delay every datagram sent by a random number of milliseconds, up to the given one, on top of --send-delay.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("send-reorder")
+                .long("send-reorder")
+                .help("Percentage of the datagrams sent to hold back and send after the next one.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("baseline")
+                .long("baseline")
+                .help("Compare the run against the golden baseline in the given file and report regressions. The file is written if it doesn't exist.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("baseline-tolerance")
+                .long("baseline-tolerance")
+                .help("How much slower than the baseline timings can be, in percent.")
+                .requires("baseline")
+                .takes_value(true)
+                .default_value("50"),
+        )
+        .arg(
+            Arg::with_name("baseline-slack")
+                .long("baseline-slack")
+                .help("How much slower than the baseline timings can be on top of --baseline-tolerance, in milliseconds.")
+                .requires("baseline")
+                .takes_value(true)
+                .default_value("100"),
+        )
+        .arg(
+            Arg::with_name("update-baseline")
+                .long("update-baseline")
+                .help("Overwrite the baseline with the current run instead of comparing against it.")
+                .requires("baseline"),
+        )
+        .arg(
+            Arg::with_name("summary-format")
+                .long("summary-format")
+                .help("Write the connection summary to stdout in the given format: json, or ndjson for one line per connection.")
+                .possible_values(["json", "ndjson"])
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("sequence-diagram")
+                .long("sequence-diagram")
+                .help("Write the frames exchanged as a sequence diagram to the given file, in PlantUML for .puml files, Mermaid otherwise.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("connections")
+                .long("connections")
+                .help("Open the given number of connections concurrently, each executing all of the actions.")
+                .takes_value(true)
+                .default_value("1"),
+        )
+        .arg(
+            Arg::with_name("fuzz-seed")
+                .long("fuzz-seed")
+                .help("Fuzz the server: apply random mutations derived from the given seed to the frames sent, on each iteration.")
+                .takes_value(true)
+                .conflicts_with("connections"),
+        )
+        .arg(
+            Arg::with_name("fuzz-iterations")
+                .long("fuzz-iterations")
+                .help("The number of fuzzing iterations, each using the seed of the previous one plus one.")
+                .takes_value(true)
+                .requires("fuzz-seed")
+                .default_value("1"),
+        )
+        .arg(
+            Arg::with_name("suite")
+                .long("suite")
+                .help("Run every JSON or YAML script in the given directory as a test suite, and report which of them met their expectations.")
+                .takes_value(true)
+                .conflicts_with_all(&["qlog-input", "script", "connections", "fuzz-seed"]),
+        )
+        .arg(
+            Arg::with_name("suite-jobs")
+                .long("suite-jobs")
+                .help("The number of suite scripts to run at the same time.")
+                .takes_value(true)
+                .requires("suite")
+                .default_value("1"),
+        )
+        .arg(
+            Arg::with_name("suite-report")
+                .long("suite-report")
+                .help("Write the suite report to the given file, as JUnit XML if its extension is .xml and as JSON otherwise.")
+                .takes_value(true)
+                .requires("suite"),
+        )
         .arg(
             Arg::with_name("replay-host-override")
                 .long("replay-host-override")
@@ -196,13 +791,75 @@ fn config_from_clap() -> std::result::Result<Config, String> {
                 .requires("qlog-input")
                 .takes_value(true),
         )
-        .get_matches();
+        .arg(
+            Arg::with_name("interactive")
+                .long("interactive")
+                .help("Open the connection right away and send each action as soon as it is entered, printing the frames received.")
+                .conflicts_with_all(&["qlog-input", "script", "connections", "fuzz-seed", "suite"]),
+        )
+        .get_matches_from(argv);
 
     let host_port = matches.value_of("host:port").unwrap().to_string();
     let omit_sni = matches.is_present("omit-sni");
+    let sni = matches.value_of("sni").map(|s| s.to_string());
     let connect_to: Option<String> =
         matches.value_of("connect-to").map(|s| s.to_string());
+    let dns = DnsResolution {
+        overrides: matches
+            .values_of("resolve")
+            .into_iter()
+            .flatten()
+            .map(parse_resolve)
+            .collect::<Result<_, _>>()?,
+        selection: matches
+            .value_of("dns-select")
+            .map(AddressSelection::from_str)
+            .transpose()?
+            .unwrap_or_default(),
+        timeout: matches
+            .value_of("dns-timeout")
+            .map(|v| v.parse::<u64>())
+            .transpose()
+            .map_err(|e| format!("dns-timeout input error {}", e))?
+            .map(time::Duration::from_millis),
+    };
+    let local_ip = matches
+        .value_of("local-ip")
+        .map(|v| v.parse::<std::net::IpAddr>())
+        .transpose()
+        .map_err(|e| format!("local-ip input error {}", e))?;
+    let interface = matches.value_of("interface").map(|s| s.to_string());
+    let dscp = matches
+        .value_of("dscp")
+        .map(|v| v.parse::<u8>())
+        .transpose()
+        .map_err(|e| format!("dscp input error {}", e))?;
+    if dscp.is_some_and(|dscp| dscp > 0x3f) {
+        return Err("dscp input error must be lower than 64".to_string());
+    }
+    let ecn = match matches.value_of("ecn") {
+        Some("not-ect") => Some(Ecn::NotEct),
+        Some("ect0") => Some(Ecn::Ect0),
+        Some("ect1") => Some(Ecn::Ect1),
+        Some("ce") => Some(Ecn::Ce),
+        _ => None,
+    };
+    let dont_fragment = matches.value_of("dont-fragment").map(|v| v == "true");
+    let ip_version = if matches.is_present("ipv4") {
+        Some(IpVersion::V4)
+    } else if matches.is_present("ipv6") {
+        Some(IpVersion::V6)
+    } else {
+        None
+    };
     let verify_peer = !matches.is_present("no-verify");
+    let ca_file = matches.value_of("ca-file").map(|s| s.to_string());
+    let cert_pins = matches
+        .values_of("pin-cert")
+        .into_iter()
+        .flatten()
+        .map(CertificatePin::from_str)
+        .collect::<std::result::Result<Vec<_>, _>>()?;
     let idle_timeout = matches
         .value_of("idle-timeout")
         .unwrap()
@@ -257,6 +914,142 @@ fn config_from_clap() -> std::result::Result<Config, String> {
         .parse::<u64>()
         .map_err(|e| format!("max-stream-window input error {}", e))?;
 
+    let rng_seed = matches
+        .value_of("rng-seed")
+        .map(|v| v.parse::<u64>())
+        .transpose()
+        .map_err(|e| format!("rng-seed input error {}", e))?;
+
+    let session_file = matches.value_of("session-file").map(|s| s.to_string());
+
+    let max_capture_size = matches
+        .value_of("max-capture-size")
+        .map(|v| v.parse::<u64>())
+        .transpose()
+        .map_err(|e| format!("max-capture-size input error {}", e))?;
+
+    let masque_proxy =
+        matches
+            .value_of("masque-proxy")
+            .map(|host_port| MasqueProxy {
+                host_port: host_port.to_string(),
+                uri_template: matches
+                    .value_of("masque-uri-template")
+                    .unwrap()
+                    .to_string(),
+            });
+
+    let body_capture = match matches.value_of("capture-bodies") {
+        Some(dir) => Some(BodyCapture {
+            dir: Some(dir.to_string()),
+            max_size: max_capture_size,
+        }),
+
+        None if matches.is_present("discard-bodies") =>
+            Some(BodyCapture::default()),
+
+        None => None,
+    };
+    let summary_format = match matches.value_of("summary-format") {
+        Some("json") => Some(SummaryFormat::Json),
+        Some("ndjson") => Some(SummaryFormat::Ndjson),
+        _ => None,
+    };
+
+    let connections = matches
+        .value_of("connections")
+        .unwrap()
+        .parse::<usize>()
+        .map_err(|e| format!("connections input error {}", e))?;
+    let enable_dgram = matches.is_present("enable-dgram");
+    let alpn = match matches.values_of("alpn") {
+        Some(v) => v.map(|p| p.as_bytes().to_vec()).collect(),
+
+        None => vec![b"h3".to_vec()],
+    };
+
+    let fuzz = match matches.value_of("fuzz-seed") {
+        Some(seed) => Some(FuzzArgs {
+            seed: seed
+                .parse::<u64>()
+                .map_err(|e| format!("fuzz-seed input error {}", e))?,
+            iterations: matches
+                .value_of("fuzz-iterations")
+                .unwrap()
+                .parse::<u64>()
+                .map_err(|e| format!("fuzz-iterations input error {}", e))?,
+        }),
+
+        None => None,
+    };
+    let suite = match matches.value_of("suite") {
+        Some(dir) => Some(SuiteArgs {
+            dir: dir.to_string(),
+            jobs: matches
+                .value_of("suite-jobs")
+                .unwrap()
+                .parse::<usize>()
+                .map_err(|e| format!("suite-jobs input error {}", e))?,
+            report: matches.value_of("suite-report").map(|s| s.to_string()),
+        }),
+
+        None => None,
+    };
+    let burst_mode = matches.is_present("burst-mode");
+
+    let custom_transport_params = matches
+        .values_of("transport-param")
+        .into_iter()
+        .flatten()
+        .map(parse_transport_param)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let drop_handshake = matches
+        .value_of("drop-handshake-packets")
+        .map(|v| v.parse::<u64>())
+        .transpose()
+        .map_err(|e| format!("drop-handshake-packets input error {}", e))?
+        .map(time::Duration::from_millis);
+
+    let handshake_faults = h3i::config::HandshakeFaults {
+        stop_after_first_initial: matches.is_present("stop-after-first-initial"),
+        drop_handshake,
+        discard_server_first_flight: matches
+            .is_present("discard-server-first-flight"),
+        drop_retry: matches.is_present("drop-retry"),
+        drop_version_negotiation: matches.is_present("drop-version-negotiation"),
+    };
+
+    let initial_version = matches
+        .value_of("initial-version")
+        .map(|v| u32::from_str_radix(v.trim_start_matches("0x"), 16))
+        .transpose()
+        .map_err(|e| format!("initial-version input error {}", e))?;
+
+    let send_delay = |name| {
+        matches
+            .value_of(name)
+            .map(|v| v.parse::<u64>())
+            .transpose()
+            .map_err(|e| format!("{} input error {}", name, e))
+            .map(|v| v.map(time::Duration::from_millis).unwrap_or_default())
+    };
+
+    let send_shaping = SendShaping {
+        delay: send_delay("send-delay")?,
+        jitter: send_delay("send-jitter")?,
+        reorder_percent: matches
+            .value_of("send-reorder")
+            .map(|v| v.parse::<u8>())
+            .transpose()
+            .map_err(|e| format!("send-reorder input error {}", e))?
+            .unwrap_or(0),
+    };
+
+    if send_shaping.reorder_percent > 100 {
+        return Err("send-reorder must be at most 100".to_string());
+    }
+
     let qlog_actions_output = !matches.is_present("no-qlog-actions-output");
     let qlog_input = matches.value_of("qlog-input").and_then(|q| {
         std::path::Path::new(q)
@@ -266,16 +1059,63 @@ fn config_from_clap() -> std::result::Result<Config, String> {
             .map(|s| s.to_string())
     });
 
+    let script = matches.value_of("script").map(|s| s.to_string());
+
+    let record_script = matches.value_of("record-script").map(|s| s.to_string());
+    let pcap_file = matches.value_of("pcap").map(|s| s.to_string());
+    let record_timing = if matches.is_present("record-durations") {
+        RecordedTiming::Durations
+    } else {
+        RecordedTiming::Waits
+    };
+
     let host_override = matches
         .value_of("replay-host-override")
         .map(|s| s.to_string());
 
+    let baseline = match matches.value_of("baseline") {
+        Some(path) => {
+            let relative = matches
+                .value_of("baseline-tolerance")
+                .unwrap()
+                .parse::<f64>()
+                .map_err(|e| format!("baseline-tolerance input error {}", e))?;
+
+            let absolute = matches
+                .value_of("baseline-slack")
+                .unwrap()
+                .parse::<u64>()
+                .map_err(|e| format!("baseline-slack input error {}", e))?;
+
+            Some(BaselineArgs {
+                path: path.to_string(),
+                tolerance: Tolerance {
+                    relative: relative / 100.0,
+                    absolute: time::Duration::from_millis(absolute),
+                },
+                update: matches.is_present("update-baseline"),
+            })
+        },
+
+        None => None,
+    };
+
     let library_config = h3i::config::Config {
         host_port,
         omit_sni,
+        sni,
         connect_to,
+        dns,
         source_port: 0,
+        local_ip,
+        interface,
+        ip_version,
+        dscp,
+        ecn,
+        dont_fragment,
         verify_peer,
+        ca_file,
+        cert_pins,
         idle_timeout,
         max_data,
         max_stream_data_bidi_local,
@@ -285,45 +1125,347 @@ fn config_from_clap() -> std::result::Result<Config, String> {
         max_streams_uni,
         max_window,
         max_stream_window,
+        rng_seed,
+        initial_version,
+        handshake_faults,
+        send_shaping,
+        record_script,
+        record_timing,
+        pcap_file,
+        session_file,
+        alpn,
+        enable_dgram,
+        custom_transport_params,
+        burst_mode,
+        wait_keepalive: matches.is_present("wait-keepalive"),
+        body_capture,
+        masque_proxy,
     };
 
     Ok(Config {
         qlog_input,
+        script,
         qlog_actions_output,
         library_config,
         host_override,
+        baseline,
+        connections,
+        summary_format,
+        sequence_diagram: matches
+            .value_of("sequence-diagram")
+            .map(|s| s.to_string()),
+        fuzz,
+        suite,
+        interactive: matches.is_present("interactive"),
     })
 }
 
+/// Parses a `--transport-param` value of the form `<id>=<hex value>`. The id
+/// can be given in decimal or, prefixed with `0x`, in hex.
+fn parse_transport_param(
+    param: &str,
+) -> std::result::Result<(u64, Vec<u8>), String> {
+    let err = || format!("transport-param input error {}", param);
+
+    let (id, value) = param.split_once('=').ok_or_else(err)?;
+
+    let id = match id.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => id.parse::<u64>(),
+    }
+    .map_err(|_| err())?;
+
+    let value = h3i::recordreplay::hex_decode(value).ok_or_else(err)?;
+
+    Ok((id, value))
+}
+
+fn parse_resolve(
+    resolve: &str,
+) -> std::result::Result<(String, Vec<std::net::IpAddr>), String> {
+    let err = || format!("resolve input error {}", resolve);
+
+    let (host, addrs) = resolve.split_once('=').ok_or_else(err)?;
+
+    let addrs = addrs
+        .split(',')
+        .map(|addr| addr.trim().parse())
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|_| err())?;
+
+    Ok((host.to_string(), addrs))
+}
+
+/// Writes the run as a sequence diagram to `path`.
+fn write_sequence_diagram(path: &str, summary: &ConnectionSummary) {
+    let diagram = sequence_diagram(summary, DiagramFormat::from_path(path));
+
+    match std::fs::write(path, diagram) {
+        Ok(()) => log::info!("wrote sequence diagram to {}", path),
+
+        Err(e) =>
+            log::error!("failed to write sequence diagram to {}: {}", path, e),
+    }
+}
+
+/// Compares the run against the baseline stored in `args.path`, or stores it
+/// there if there is no baseline yet.
+fn check_baseline(args: &BaselineArgs, summary: &ConnectionSummary) {
+    let current = Baseline::from(summary);
+
+    let stored = if args.update {
+        None
+    } else {
+        match std::fs::read_to_string(&args.path) {
+            Ok(v) => Some(v),
+
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+
+            Err(e) => {
+                log::error!("failed to read baseline {}: {}", args.path, e);
+                return;
+            },
+        }
+    };
+
+    let Some(stored) = stored else {
+        let written = serde_json::to_string_pretty(&current)
+            .map_err(|e| e.to_string())
+            .and_then(|v| {
+                std::fs::write(&args.path, v).map_err(|e| e.to_string())
+            });
+
+        match written {
+            Ok(()) => log::info!("wrote baseline to {}", args.path),
+
+            Err(e) =>
+                log::error!("failed to write baseline {}: {}", args.path, e),
+        }
+
+        return;
+    };
+
+    let expected: Baseline = match serde_json::from_str(&stored) {
+        Ok(v) => v,
+
+        Err(e) => {
+            log::error!("failed to parse baseline {}: {}", args.path, e);
+            return;
+        },
+    };
+
+    let regressions = expected.compare(&current, &args.tolerance);
+
+    if regressions.is_empty() {
+        log::info!("no regressions against baseline {}", args.path);
+        return;
+    }
+
+    for regression in &regressions {
+        log::error!("baseline regression: {}", regression);
+    }
+
+    log::error!(
+        "{} regression(s) against baseline {}",
+        regressions.len(),
+        args.path
+    );
+}
+
 fn sync_client(
     config: Config, actions: &[Action],
+    close_trigger_frames: Option<CloseTriggerFrames>,
 ) -> Result<ConnectionSummary, ClientError> {
-    // TODO: CLI/qlog don't support passing close trigger frames at the moment
-    h3i::client::sync_client::connect(config.library_config, actions, None)
+    // TODO: the prompts and qlog input don't support close trigger frames at the
+    // moment, only scripts do
+    h3i::client::sync_client::connect(
+        config.library_config,
+        actions,
+        close_trigger_frames,
+    )
 }
 
-fn read_qlog(filename: &str, host_override: Option<&str>) -> Vec<Action> {
-    let file = std::fs::File::open(filename).expect("failed to open file");
-    let reader = BufReader::new(file);
+/// Runs `connections` concurrently and evaluates `expectations` against each
+/// of them.
+fn run_connections(
+    config: Config, connections: &[Vec<Action>],
+    close_trigger_frames: Option<CloseTriggerFrames>,
+    expectations: &Expectations,
+) -> Result<(), ClientError> {
+    let summary = h3i::client::sync_client::connect_many(
+        config.library_config,
+        connections,
+        close_trigger_frames,
+    );
+
+    log::debug!(
+        "received multi_connection_summary: {}",
+        serde_json::to_string_pretty(&summary).unwrap_or_else(|e| e.to_string())
+    );
+
+    match config.summary_format {
+        Some(SummaryFormat::Json) => SummaryFormat::Json.print(&summary),
+
+        Some(SummaryFormat::Ndjson) =>
+            for result in &summary.connections {
+                match result {
+                    Ok(s) => SummaryFormat::Ndjson.print(s),
+
+                    Err(e) => SummaryFormat::Ndjson
+                        .print(&serde_json::json!({ "error": e })),
+                }
+            },
+
+        None => (),
+    }
+
+    for (i, e) in summary.failed() {
+        log::error!("connection {} error: {:?}", i, e);
+    }
+
+    let mut expectations_met = true;
+
+    for (i, result) in summary.connections.iter().enumerate() {
+        let Ok(summary) = result else {
+            continue;
+        };
+
+        if let Some(e) = summary.conn_close_details.peer_error() {
+            log::info!("connection {} closed by peer: {:?}", i, e);
+        }
+
+        if expectations.is_empty() {
+            continue;
+        }
+
+        let report = expectations.evaluate(summary);
+
+        for result in report.failures() {
+            log::error!(
+                "connection {} expectation failed: {}",
+                i,
+                result.failure.as_deref().unwrap_or_default()
+            );
+        }
+
+        expectations_met &= report.passed();
+    }
+
+    log::info!(
+        "{} connection(s) succeeded, {} failed",
+        summary.succeeded().count(),
+        summary.failed().count()
+    );
+
+    if !expectations_met {
+        return Err(ClientError::Other("Expectations not met".into()));
+    }
+
+    Ok(())
+}
+
+/// Runs the scripts of the suite and writes the report, if asked to.
+fn run_suite(config: &Config, suite: &SuiteArgs) -> Result<(), ClientError> {
+    let report = h3i::client::suite::run_suite(
+        &config.library_config,
+        &suite.dir,
+        suite.jobs,
+    )
+    .map_err(|e| {
+        ClientError::Other(format!("can't read suite {}: {}", suite.dir, e))
+    })?;
+
+    if let Some(path) = &suite.report {
+        let serialized = if path.ends_with(".xml") {
+            Ok(report.to_junit())
+        } else {
+            serde_json::to_string_pretty(&report).map_err(|e| e.to_string())
+        };
+
+        let written = serialized
+            .and_then(|s| std::fs::write(path, s).map_err(|e| e.to_string()));
+
+        if let Err(e) = written {
+            log::error!("failed to write suite report to {}: {}", path, e);
+        }
+    }
+
+    let failed = report.failed().count();
+
+    log::info!(
+        "{} scenario(s) passed, {} failed",
+        report.scenarios.len() - failed,
+        failed
+    );
+
+    if !report.passed() {
+        return Err(ClientError::Other("Suite failed".into()));
+    }
 
-    let qlog_reader = QlogSeqReader::new(Box::new(reader)).unwrap();
-    let mut actions = vec![];
+    Ok(())
+}
+
+/// Runs the fuzzing iterations, each one with the mutations of `actions`
+/// derived from its seed. Iterations that fail or make the server close the
+/// connection with an error are logged with their seed, so that they can be
+/// reproduced.
+fn run_fuzz(
+    config: Config, fuzz: FuzzArgs, actions: &[Action],
+    close_trigger_frames: Option<CloseTriggerFrames>,
+) -> Result<(), ClientError> {
+    let mut failures = 0;
+
+    for i in 0..fuzz.iterations {
+        let seed = fuzz.seed.wrapping_add(i);
+        let (mutated, mutations) = h3i::actions::fuzz::mutate(actions, seed);
+
+        log::info!(
+            "fuzz iteration {} seed={} mutations={}",
+            i,
+            seed,
+            serde_json::to_string(&mutations).unwrap_or_else(|e| e.to_string())
+        );
+
+        let result = h3i::client::sync_client::connect(
+            config.library_config.clone(),
+            &mutated,
+            close_trigger_frames.clone(),
+        );
+
+        match result {
+            Ok(summary) => match summary.conn_close_details.peer_error() {
+                Some(e) => log::info!("seed {} closed by peer: {:?}", seed, e),
 
-    for event in qlog_reader {
-        match event {
-            qlog::reader::Event::Qlog(ev) => {
-                let ac: H3Actions = actions_from_qlog(ev, host_override);
-                actions.extend(ac.0);
+                None => log::info!("seed {} no peer error", seed),
             },
 
-            qlog::reader::Event::Json(ev) => {
-                let ac: H3Actions = (ev).into();
-                actions.extend(ac.0);
+            Err(e) => {
+                failures += 1;
+
+                log::error!(
+                    "seed {} error: {:?}, reproduce with --fuzz-seed {}",
+                    seed,
+                    e,
+                    seed
+                );
             },
         }
     }
 
-    actions
+    log::info!("{} fuzz iteration(s), {} failed", fuzz.iterations, failures);
+
+    if failures > 0 {
+        return Err(ClientError::Other("Fuzzing failures".into()));
+    }
+
+    Ok(())
+}
+
+fn read_qlog(filename: &str, host_override: Option<&str>) -> Vec<Action> {
+    let file = std::fs::File::open(filename).expect("failed to open file");
+    let reader = BufReader::new(file);
+
+    actions_from_qlog_reader(reader, host_override).expect("failed to read qlog")
 }
 
 fn prompt_frames(config: &Config) -> Vec<Action> {
@@ -334,30 +1476,58 @@ fn prompt_frames(config: &Config) -> Vec<Action> {
         let writer = make_qlog_writer();
         let mut streamer = make_streamer(std::boxed::Box::new(writer));
 
-        for action in &actions {
-            let events: QlogEvents = action.into();
-            for event in events {
-                match event {
-                    QlogEvent::Event { data, ex_data } => {
-                        streamer.add_event_data_ex_now(*data, ex_data).ok();
-                    },
-
-                    QlogEvent::JsonEvent(mut ev) => {
-                        // need to rewrite the event time
-                        ev.time = Instant::now()
-                            .duration_since(streamer.start_time())
-                            .as_secs_f32() *
-                            1000.0;
-                        streamer.add_event(ev).ok();
-                    },
-                }
-            }
-        }
+        record_actions(&mut streamer, &actions);
     }
 
     actions
 }
 
+/// Connects to the server right away and sends the actions as they are
+/// entered, until the user is done or the connection is closed.
+fn run_interactive(config: Config) -> Result<(), ClientError> {
+    let mut prompter = Prompter::with_config(&config.library_config);
+    let (sender, handle) = h3i::client::sync_client::connect_interactive(
+        config.library_config,
+        |event| match event {
+            ClientEvent::Frame { stream_id, frame } =>
+                println!("stream {stream_id}: {frame:?}"),
+            ClientEvent::StreamFinished { stream_id } =>
+                println!("stream {stream_id}: finished"),
+            ClientEvent::PushStream { stream_id, push_id } =>
+                println!("stream {stream_id}: push stream for push ID {push_id}"),
+            ClientEvent::Datagram(payload) =>
+                println!("datagram: {} bytes", payload.len()),
+        },
+    )?;
+
+    let actions = prompter.prompt_interactive(&sender);
+
+    // Dropping the sender closes the connection.
+    drop(sender);
+
+    if !actions.is_empty() && config.qlog_actions_output {
+        let writer = make_qlog_writer();
+        let mut streamer = make_streamer(std::boxed::Box::new(writer));
+
+        record_actions(&mut streamer, &actions);
+    }
+
+    let summary = handle.join().map_err(|_| {
+        ClientError::Other("interactive connection panicked".into())
+    })??;
+
+    log::debug!(
+        "received connection_summary: {}",
+        serde_json::to_string_pretty(&summary).unwrap_or_else(|e| e.to_string())
+    );
+
+    if let Some(format) = config.summary_format {
+        format.print(&summary);
+    }
+
+    Ok(())
+}
+
 /// Makes a buffered writer for a qlog.
 pub fn make_qlog_writer() -> std::io::BufWriter<std::fs::File> {
     let mut path = std::env::current_dir().unwrap();
@@ -379,39 +1549,3 @@ pub fn make_qlog_writer() -> std::io::BufWriter<std::fs::File> {
         ),
     }
 }
-
-pub fn make_streamer(
-    writer: Box<dyn std::io::Write + Send + Sync>,
-) -> qlog::streamer::QlogStreamer {
-    let vp = qlog::VantagePointType::Client;
-
-    let trace = qlog::TraceSeq::new(
-        qlog::VantagePoint {
-            name: None,
-            ty: vp,
-            flow: None,
-        },
-        Some("h3i".into()),
-        Some("h3i".into()),
-        Some(qlog::Configuration {
-            time_offset: Some(0.0),
-            original_uris: None,
-        }),
-        None,
-    );
-
-    let mut streamer = qlog::streamer::QlogStreamer::new(
-        qlog::QLOG_VERSION.to_string(),
-        Some("h3i".into()),
-        Some("h3i".into()),
-        None,
-        time::Instant::now(),
-        trace,
-        qlog::events::EventImportance::Extra,
-        writer,
-    );
-
-    streamer.start_log().ok();
-
-    streamer
-}
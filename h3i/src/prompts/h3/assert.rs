@@ -0,0 +1,93 @@
+// Copyright (C) 2025, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Prompts for assertions on response bodies.
+
+use inquire::error::InquireResult;
+use inquire::validator::ErrorMessage;
+use inquire::validator::Validation;
+use inquire::Select;
+use inquire::Text;
+
+use crate::actions::h3::Action;
+use crate::actions::h3::BodyAssertion;
+
+use super::prompt_stream_id;
+use super::prompt_varint;
+use super::SuggestionResult;
+
+const BYTES: &str = "bytes";
+const LENGTH: &str = "length";
+const SHA256: &str = "sha256";
+const REGEX: &str = "regex";
+
+pub fn prompt_assert_body() -> InquireResult<Action> {
+    let stream_id = prompt_stream_id()?;
+
+    let ty = Select::new("assertion type:", vec![BYTES, LENGTH, SHA256, REGEX])
+        .prompt()?;
+
+    let assertion = match ty {
+        BYTES => BodyAssertion::Bytes(
+            Text::new("expected body:").prompt()?.into_bytes(),
+        ),
+        LENGTH => BodyAssertion::Length(prompt_varint("expected length:")?),
+        SHA256 => BodyAssertion::Sha256(
+            Text::new("expected SHA-256 (hex):")
+                .with_validator(validate_sha256)
+                .prompt()?,
+        ),
+        REGEX => BodyAssertion::Regex(
+            Text::new("regex:")
+                .with_validator(validate_regex)
+                .prompt()?,
+        ),
+        _ => unreachable!(),
+    };
+
+    Ok(Action::AssertBody {
+        stream_id,
+        assertion,
+    })
+}
+
+fn validate_sha256(v: &str) -> SuggestionResult<Validation> {
+    if v.len() == 64 && v.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Ok(Validation::Valid);
+    }
+
+    Ok(Validation::Invalid(ErrorMessage::Custom(
+        "expected 64 hex characters".to_string(),
+    )))
+}
+
+fn validate_regex(v: &str) -> SuggestionResult<Validation> {
+    if let Err(e) = regex::Regex::new(v) {
+        return Ok(Validation::Invalid(ErrorMessage::Custom(e.to_string())));
+    }
+
+    Ok(Validation::Valid)
+}
@@ -0,0 +1,63 @@
+// Copyright (C) 2025, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Prompts for QUIC flow control frames.
+
+use inquire::error::InquireResult;
+
+use crate::actions::h3::Action;
+
+use super::prompt_stream_id;
+use super::prompt_varint;
+use super::prompt_yes_no;
+
+const MAX_PROMPT: &str = "maximum:";
+
+pub fn prompt_max_data() -> InquireResult<Action> {
+    let max = prompt_varint(MAX_PROMPT)?;
+
+    Ok(Action::MaxData { max })
+}
+
+pub fn prompt_max_stream_data() -> InquireResult<Action> {
+    let stream_id = prompt_stream_id()?;
+    let max = prompt_varint(MAX_PROMPT)?;
+
+    Ok(Action::MaxStreamData { stream_id, max })
+}
+
+pub fn prompt_max_streams() -> InquireResult<Action> {
+    let bidi = prompt_yes_no("bidirectional streams:")?;
+    let max = prompt_varint(MAX_PROMPT)?;
+
+    Ok(Action::MaxStreams { bidi, max })
+}
+
+pub fn prompt_withhold_flow_control_updates() -> InquireResult<Action> {
+    let withhold = prompt_yes_no("withhold flow control updates:")?;
+
+    Ok(Action::WithholdFlowControlUpdates { withhold })
+}
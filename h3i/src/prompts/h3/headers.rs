@@ -26,15 +26,19 @@
 
 //! Prompts for HTTP/3 header fields.
 
+use std::time::Duration;
+
 use inquire::error::InquireResult;
 use inquire::validator::Validation;
 use inquire::Text;
 use quiche;
 use quiche::h3::frame::Frame;
 
+use crate::actions::h3::HeadersFrameBuilder;
+use crate::actions::h3::RampPolicy;
 use crate::encode_header_block;
-use crate::encode_header_block_literal;
 use crate::prompts::h3;
+use crate::prompts::h3::prompt_yes_no;
 use crate::StreamIdAllocator;
 
 use super::squish_suggester;
@@ -47,6 +51,8 @@ use super::PUSH_ID_PROMPT;
 use super::STREAM_ID_PROMPT;
 use crate::actions::h3::Action;
 
+const DEFAULT_PSEUDO_HEADER_ORDER: &str = "method,authority,path,scheme";
+
 pub fn prompt_headers(
     sid_alloc: &mut StreamIdAllocator, host_port: &str, raw: bool, literal: bool,
 ) -> InquireResult<Action> {
@@ -66,33 +72,66 @@ pub fn prompt_headers(
         _ => stream_id.parse::<u64>().unwrap(),
     };
 
-    let mut headers = vec![];
+    let mut builder =
+        HeadersFrameBuilder::new(stream_id).with_literal_headers(literal);
 
     if !raw {
-        headers.extend_from_slice(&pseudo_headers(host_port)?);
+        let pseudo = if literal {
+            literal_pseudo_headers(host_port)?
+        } else {
+            pseudo_headers(host_port)?
+        };
+
+        builder = builder.with_headers(&pseudo);
     }
 
-    headers.extend_from_slice(&headers_read_loop()?);
+    builder = builder.with_headers(&headers_read_loop()?);
+
+    if literal && !raw {
+        builder = builder.with_pseudo_headers_last(prompt_yes_no(
+            "send pseudo-headers after regular fields:",
+        )?);
+    }
 
     sid_alloc.take_next_id();
 
-    let header_block = if literal {
-        encode_header_block_literal(&headers).unwrap_or_default()
+    let fin_stream = prompt_fin_stream()?;
+
+    Ok(builder.with_fin_stream(fin_stream).build())
+}
+
+pub fn prompt_concurrent_requests(
+    sid_alloc: &mut StreamIdAllocator, host_port: &str,
+) -> InquireResult<Action> {
+    let count = h3::prompt_varint("number of requests:")?;
+
+    let first_stream_id = sid_alloc.peek_next_id();
+    println!("first stream ID={first_stream_id}");
+
+    let mut headers = pseudo_headers(host_port)?;
+    headers.extend(headers_read_loop()?);
+
+    let ramp = if prompt_yes_no("ramp up in steps:")? {
+        RampPolicy::Step {
+            step: h3::prompt_varint("requests per step:")?,
+            interval: Duration::from_millis(h3::prompt_varint(
+                "step interval (ms):",
+            )?),
+        }
     } else {
-        encode_header_block(&headers).unwrap_or_default()
+        RampPolicy::Immediate
     };
 
-    let fin_stream = prompt_fin_stream()?;
+    for _ in 0..count {
+        sid_alloc.take_next_id();
+    }
 
-    let action = Action::SendHeadersFrame {
-        stream_id,
-        fin_stream,
+    Ok(Action::OpenConcurrentRequests {
+        first_stream_id,
+        count,
         headers,
-        literal_headers: literal,
-        frame: Frame::Headers { header_block },
-    };
-
-    Ok(action)
+        ramp,
+    })
 }
 
 pub fn prompt_push_promise() -> InquireResult<Action> {
@@ -120,6 +159,27 @@ pub fn prompt_push_promise() -> InquireResult<Action> {
     Ok(action)
 }
 
+pub fn prompt_metadata() -> InquireResult<Action> {
+    let stream_id = h3::prompt_stream_id()?;
+
+    let fields = headers_read_loop()?;
+    let header_block = if fields.is_empty() {
+        vec![]
+    } else {
+        encode_header_block(&fields).unwrap()
+    };
+
+    let fin_stream = prompt_fin_stream()?;
+
+    let action = Action::SendFrame {
+        stream_id,
+        fin_stream,
+        frame: Frame::Metadata { header_block },
+    };
+
+    Ok(action)
+}
+
 fn pseudo_headers(host_port: &str) -> InquireResult<Vec<quiche::h3::Header>> {
     let method = Text::new("method:")
         .with_autocomplete(&method_suggester)
@@ -148,6 +208,46 @@ fn pseudo_headers(host_port: &str) -> InquireResult<Vec<quiche::h3::Header>> {
     ])
 }
 
+/// Prompts for pseudo-headers in a user-provided order. Names are sent exactly
+/// as typed, and can be omitted or repeated.
+fn literal_pseudo_headers(
+    host_port: &str,
+) -> InquireResult<Vec<quiche::h3::Header>> {
+    let order = Text::new("pseudo-header order:")
+        .with_default(DEFAULT_PSEUDO_HEADER_ORDER)
+        .with_help_message(
+            "comma-separated names, which can be reordered, repeated or \
+             omitted; casing is preserved",
+        )
+        .prompt()?;
+
+    let mut headers = vec![];
+
+    for name in order.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+        let name = name.strip_prefix(':').unwrap_or(name);
+
+        let default = match name.to_ascii_lowercase().as_str() {
+            "method" => "GET",
+            "authority" => host_port,
+            "path" => "/",
+            "scheme" => "https",
+            _ => "",
+        };
+
+        let value = Text::new(&format!(":{name}:"))
+            .with_default(default)
+            .with_help_message(ESC_TO_RET)
+            .prompt()?;
+
+        headers.push(quiche::h3::Header::new(
+            format!(":{name}").as_bytes(),
+            value.as_bytes(),
+        ));
+    }
+
+    Ok(headers)
+}
+
 fn headers_read_loop() -> InquireResult<Vec<quiche::h3::Header>> {
     let mut headers = vec![];
     loop {
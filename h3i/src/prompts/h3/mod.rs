@@ -36,10 +36,15 @@ use inquire::Text;
 use qlog::events::quic::ErrorSpace;
 use quiche::ConnectionError;
 
+use crate::actions::h3::send_cancel_push_frame;
+use crate::actions::h3::send_max_push_id_frame;
 use crate::actions::h3::Action;
+use crate::client::expectations::Expectation;
+use crate::client::sync_client::ActionSender;
 use crate::config::Config;
 use crate::prompts::h3;
 use crate::prompts::h3::headers::prompt_push_promise;
+use crate::recordreplay::hex_decode;
 use crate::StreamIdAllocator;
 
 use std::cell::RefCell;
@@ -89,13 +94,36 @@ const CANCEL_PUSH: &str = "cancel_push";
 const GOAWAY: &str = "goaway";
 const MAX_PUSH_ID: &str = "max_push_id";
 const PRIORITY_UPDATE: &str = "priority_update";
+const METADATA: &str = "metadata";
 const GREASE: &str = "grease";
 const EXTENSION: &str = "extension_frame";
 const OPEN_UNI_STREAM: &str = "open_uni_stream";
+const EXTENDED_CONNECT: &str = "extended_connect";
+const OPEN_WEBTRANSPORT_STREAM: &str = "open_webtransport_stream";
 const RESET_STREAM: &str = "reset_stream";
 const STOP_SENDING: &str = "stop_sending";
 const CONNECTION_CLOSE: &str = "connection_close";
 const STREAM_BYTES: &str = "stream_bytes";
+const MAX_DATA: &str = "max_data";
+const MAX_STREAM_DATA: &str = "max_stream_data";
+const MAX_STREAMS: &str = "max_streams";
+const WITHHOLD_FLOW_CONTROL_UPDATES: &str = "withhold_flow_control_updates";
+const PING: &str = "ping";
+const PADDING: &str = "padding";
+const BURST: &str = "burst";
+const RAW_QUIC_FRAME: &str = "raw_quic_frame";
+const UPDATE_KEYS: &str = "update_keys";
+const MIGRATE: &str = "migrate";
+const PROBE_PATH: &str = "probe_path";
+const SWITCH_PATH: &str = "switch_path";
+const DATAGRAM: &str = "datagram";
+const QPACK_INSTRUCTION: &str = "qpack_instruction";
+const ASSERT_BODY: &str = "assert_body";
+const SNAPSHOT_STATS: &str = "snapshot_stats";
+const CONCURRENT_REQUESTS: &str = "concurrent_requests";
+
+const REPEAT: &str = "repeat";
+const ON_FRAME: &str = "on_frame";
 
 const COMMIT: &str = "commit";
 const FLUSH_PACKETS: &str = "flush_packets";
@@ -105,6 +133,11 @@ const QUIT: &str = "quit";
 const YES: &str = "Yes";
 const NO: &str = "No";
 
+const QUEUE_PROMPT: &str =
+    "Select an action to queue. `Commit` ends selection and flushes queue.";
+const INTERACTIVE_PROMPT: &str =
+    "Select an action to send. `Commit` or ESC closes the connection.";
+
 const ESC_TO_RET: &str = "ESC to return to actions";
 const STREAM_ID_PROMPT: &str = "stream ID:";
 const EMPTY_PICKS: &str = "empty picks next available ID";
@@ -113,6 +146,16 @@ const PUSH_ID_PROMPT: &str = "push ID:";
 
 enum PromptOutcome {
     Action(Action),
+    /// Repeat the last `len` actions `count` times.
+    Loop {
+        len: usize,
+        count: u64,
+    },
+    /// Execute the last `len` actions only if `matcher` is met.
+    Conditional {
+        len: usize,
+        matcher: Expectation,
+    },
     Repeat,
     Commit,
     Clear,
@@ -155,10 +198,23 @@ impl Prompter {
                 )
             },
 
+            CONCURRENT_REQUESTS => headers::prompt_concurrent_requests(
+                &mut self.bidi_sid_alloc,
+                &self.host_port,
+            ),
             DATA => prompt_data(),
             SETTINGS => settings::prompt_settings(),
             OPEN_UNI_STREAM =>
                 stream::prompt_open_uni_stream(&mut self.uni_sid_alloc),
+            EXTENDED_CONNECT => webtransport::prompt_extended_connect(
+                &mut self.bidi_sid_alloc,
+                &self.host_port,
+            ),
+            OPEN_WEBTRANSPORT_STREAM =>
+                webtransport::prompt_open_webtransport_stream(
+                    &mut self.bidi_sid_alloc,
+                    &mut self.uni_sid_alloc,
+                ),
             RESET_STREAM => stream::prompt_reset_stream(),
             STOP_SENDING => stream::prompt_stop_sending(),
             GREASE => prompt_grease(),
@@ -168,9 +224,36 @@ impl Prompter {
             CANCEL_PUSH => prompt_cancel_push(),
             PUSH_PROMISE => prompt_push_promise(),
             PRIORITY_UPDATE => priority::prompt_priority(),
+            METADATA => headers::prompt_metadata(),
             CONNECTION_CLOSE => prompt_connection_close(),
             STREAM_BYTES => prompt_stream_bytes(),
+            MAX_DATA => flow_control::prompt_max_data(),
+            MAX_STREAM_DATA => flow_control::prompt_max_stream_data(),
+            MAX_STREAMS => flow_control::prompt_max_streams(),
+            WITHHOLD_FLOW_CONTROL_UPDATES =>
+                flow_control::prompt_withhold_flow_control_updates(),
+            PING => return PromptOutcome::Action(Action::Ping),
+            PADDING => transport::prompt_padding(),
+            BURST => transport::prompt_burst(),
+            RAW_QUIC_FRAME => transport::prompt_raw_quic_frame(),
+            UPDATE_KEYS => return PromptOutcome::Action(Action::UpdateKeys),
+            MIGRATE => transport::prompt_migrate(),
+            PROBE_PATH => transport::prompt_probe_path(),
+            SWITCH_PATH => transport::prompt_switch_path(),
+            DATAGRAM => transport::prompt_datagram(),
+            QPACK_INSTRUCTION => qpack::prompt_qpack_instruction(),
+            ASSERT_BODY => assert::prompt_assert_body(),
+            SNAPSHOT_STATS => prompt_snapshot_stats(),
             FLUSH_PACKETS => return PromptOutcome::Action(Action::FlushPackets),
+            REPEAT => match prompt_repeat() {
+                Ok((len, count)) => return PromptOutcome::Loop { len, count },
+                Err(e) => Err(e),
+            },
+            ON_FRAME => match prompt_on_frame() {
+                Ok((len, matcher)) =>
+                    return PromptOutcome::Conditional { len, matcher },
+                Err(e) => Err(e),
+            },
             COMMIT => return PromptOutcome::Commit,
             WAIT => prompt_wait(),
             QUIT => return PromptOutcome::Clear,
@@ -204,7 +287,7 @@ impl Prompter {
         loop {
             println!();
 
-            let action = match prompt_action() {
+            let action = match prompt_action(QUEUE_PROMPT) {
                 Ok(v) => v,
                 Err(inquire::InquireError::OperationCanceled) |
                 Err(inquire::InquireError::OperationInterrupted) =>
@@ -217,12 +300,82 @@ impl Prompter {
 
             match self.handle_action(&action) {
                 PromptOutcome::Action(action) => actions.push(action),
+                PromptOutcome::Loop { len, count } => {
+                    let repeated =
+                        actions.split_off(actions.len().saturating_sub(len));
+                    actions.push(Action::Repeat {
+                        count,
+                        actions: repeated,
+                    });
+                },
+                PromptOutcome::Conditional { len, matcher } => {
+                    let then =
+                        actions.split_off(actions.len().saturating_sub(len));
+                    actions.push(Action::OnFrame {
+                        matcher,
+                        then,
+                        otherwise: vec![],
+                    });
+                },
                 PromptOutcome::Repeat => continue,
                 PromptOutcome::Commit => return actions,
                 PromptOutcome::Clear => return vec![],
             }
         }
     }
+
+    /// Start the interactive prompt loop.
+    ///
+    /// Each action is sent to the connection behind `sender` as soon as it is
+    /// entered. `repeat` and `on_frame` apply to the last actions sent, which
+    /// are sent again. This continues until a terminal choice is made or the
+    /// connection is closed.
+    ///
+    /// Returns an ordered list of the [Action]s sent, which may be empty.
+    pub fn prompt_interactive(&mut self, sender: &ActionSender) -> Vec<Action> {
+        let mut actions: Vec<Action> = vec![];
+
+        loop {
+            println!();
+
+            let action = match prompt_action(INTERACTIVE_PROMPT) {
+                Ok(v) => v,
+                Err(inquire::InquireError::OperationCanceled) |
+                Err(inquire::InquireError::OperationInterrupted) =>
+                    return actions,
+                Err(e) => {
+                    println!("Unexpected error while determining action: {}", e);
+                    return actions;
+                },
+            };
+
+            let last = |len: usize| {
+                actions[actions.len().saturating_sub(len)..].to_vec()
+            };
+
+            let action = match self.handle_action(&action) {
+                PromptOutcome::Action(action) => action,
+                PromptOutcome::Loop { len, count } => Action::Repeat {
+                    count,
+                    actions: last(len),
+                },
+                PromptOutcome::Conditional { len, matcher } => Action::OnFrame {
+                    matcher,
+                    then: last(len),
+                    otherwise: vec![],
+                },
+                PromptOutcome::Repeat => continue,
+                PromptOutcome::Commit | PromptOutcome::Clear => return actions,
+            };
+
+            if let Err(e) = sender.send(vec![action.clone()]) {
+                println!("error: {:?}", e);
+                return actions;
+            }
+
+            actions.push(action);
+        }
+    }
 }
 
 fn handle_action_loop_error(err: InquireError) -> bool {
@@ -237,13 +390,11 @@ fn handle_action_loop_error(err: InquireError) -> bool {
     }
 }
 
-fn prompt_action() -> InquireResult<String> {
-    let name = Text::new(
-        "Select an action to queue. `Commit` ends selection and flushes queue.",
-    )
-    .with_autocomplete(&action_suggester)
-    .with_page_size(18)
-    .prompt();
+fn prompt_action(message: &str) -> InquireResult<String> {
+    let name = Text::new(message)
+        .with_autocomplete(&action_suggester)
+        .with_page_size(18)
+        .prompt();
 
     name
 }
@@ -255,20 +406,42 @@ fn action_suggester(val: &str) -> SuggestionResult<Vec<String>> {
         HEADERS_NO_PSEUDO,
         HEADERS_LITERAL,
         HEADERS_NO_PSEUDO_LITERAL,
+        CONCURRENT_REQUESTS,
         DATA,
         SETTINGS,
         GOAWAY,
         PRIORITY_UPDATE,
+        METADATA,
         PUSH_PROMISE,
         CANCEL_PUSH,
         MAX_PUSH_ID,
         GREASE,
         EXTENSION,
         OPEN_UNI_STREAM,
+        EXTENDED_CONNECT,
+        OPEN_WEBTRANSPORT_STREAM,
         RESET_STREAM,
         STOP_SENDING,
         CONNECTION_CLOSE,
         STREAM_BYTES,
+        MAX_DATA,
+        MAX_STREAM_DATA,
+        MAX_STREAMS,
+        WITHHOLD_FLOW_CONTROL_UPDATES,
+        PING,
+        PADDING,
+        BURST,
+        RAW_QUIC_FRAME,
+        UPDATE_KEYS,
+        MIGRATE,
+        PROBE_PATH,
+        SWITCH_PATH,
+        DATAGRAM,
+        QPACK_INSTRUCTION,
+        ASSERT_BODY,
+        SNAPSHOT_STATS,
+        REPEAT,
+        ON_FRAME,
         FLUSH_PACKETS,
         COMMIT,
         WAIT,
@@ -358,31 +531,27 @@ fn prompt_data() -> InquireResult<Action> {
 }
 
 fn prompt_max_push_id() -> InquireResult<Action> {
-    let stream_id = h3::prompt_stream_id()?;
+    let stream_id = h3::prompt_control_stream_id()?;
     let push_id = h3::prompt_varint(PUSH_ID_PROMPT)?;
 
-    let fin_stream = prompt_fin_stream()?;
+    let mut action = send_max_push_id_frame(stream_id, push_id);
 
-    let action = Action::SendFrame {
-        stream_id,
-        fin_stream,
-        frame: quiche::h3::frame::Frame::MaxPushId { push_id },
-    };
+    if let Action::SendFrame { fin_stream, .. } = &mut action {
+        *fin_stream = prompt_fin_stream()?;
+    }
 
     Ok(action)
 }
 
 fn prompt_cancel_push() -> InquireResult<Action> {
-    let stream_id = h3::prompt_stream_id()?;
+    let stream_id = h3::prompt_control_stream_id()?;
     let push_id = h3::prompt_varint(PUSH_ID_PROMPT)?;
 
-    let fin_stream = prompt_fin_stream()?;
+    let mut action = send_cancel_push_frame(stream_id, push_id);
 
-    let action = Action::SendFrame {
-        stream_id,
-        fin_stream,
-        frame: quiche::h3::frame::Frame::CancelPush { push_id },
-    };
+    if let Action::SendFrame { fin_stream, .. } = &mut action {
+        *fin_stream = prompt_fin_stream()?;
+    }
 
     Ok(action)
 }
@@ -461,15 +630,58 @@ pub fn prompt_connection_close() -> InquireResult<Action> {
     })
 }
 
+fn prompt_repeat() -> InquireResult<(usize, u64)> {
+    let len = prompt_varint("number of previous actions to repeat:")?;
+    let count = prompt_varint("repeat count:")?;
+
+    Ok((len as usize, count))
+}
+
+fn prompt_on_frame() -> InquireResult<(usize, Expectation)> {
+    let len = prompt_varint("number of previous actions to make conditional:")?;
+    let stream_id = prompt_stream_id()?;
+    let status = Text::new("expected status code:")
+        .with_validator(validate_status)
+        .prompt()?
+        .parse::<u16>()
+        .unwrap();
+
+    Ok((len as usize, Expectation::Status { stream_id, status }))
+}
+
+fn prompt_snapshot_stats() -> InquireResult<Action> {
+    let label = Text::new("label:").prompt()?;
+
+    Ok(Action::SnapshotStats { label })
+}
+
+fn validate_status(status: &str) -> SuggestionResult<Validation> {
+    match status.parse::<u16>() {
+        Ok(100..=599) => Ok(Validation::Valid),
+        _ => Ok(Validation::Invalid(ErrorMessage::Default)),
+    }
+}
+
 pub fn prompt_stream_bytes() -> InquireResult<Action> {
     let stream_id = h3::prompt_stream_id()?;
-    let bytes = Text::new("bytes:").prompt()?;
+
+    let bytes = if prompt_yes_no("hex bytes:")? {
+        let bytes = Text::new("bytes (hex):")
+            .with_validator(transport::validate_hex)
+            .prompt()?;
+
+        // bytes are already validated so unwrap always succeeds
+        hex_decode(&bytes).unwrap()
+    } else {
+        Text::new("bytes:").prompt()?.into_bytes()
+    };
+
     let fin_stream = prompt_fin_stream()?;
 
     Ok(Action::StreamBytes {
         stream_id,
         fin_stream,
-        bytes: bytes.as_bytes().to_vec(),
+        bytes,
     })
 }
 
@@ -500,9 +712,14 @@ fn prompt_yes_no(msg: &str) -> InquireResult<bool> {
     Ok(res == YES)
 }
 
+mod assert;
 mod errors;
+mod flow_control;
 mod headers;
 mod priority;
+mod qpack;
 mod settings;
 mod stream;
+mod transport;
 mod wait;
+mod webtransport;
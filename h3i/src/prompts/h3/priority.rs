@@ -29,41 +29,44 @@ use inquire::Select;
 use inquire::Text;
 
 use super::stream::prompt_fin_stream;
+use crate::actions::h3::send_priority_update_push;
+use crate::actions::h3::send_priority_update_request;
 use crate::actions::h3::Action;
 use crate::prompts::h3;
 
-use quiche;
-
 const REQUEST: &str = "request";
 const PUSH: &str = "push";
 
 pub fn prompt_priority() -> InquireResult<Action> {
-    let stream_id = h3::prompt_stream_id()?;
+    let stream_id = h3::prompt_control_stream_id()?;
 
     let ty = prompt_request_or_push()?;
     let prioritized_element_id = h3::prompt_varint("Prioritized Element ID:")?;
 
-    let priority_field_value = Text::new("priority field value:").prompt()?;
+    // The value is sent as is, to test how the server parses invalid ones.
+    let priority_field_value = Text::new("priority field value:")
+        .with_help_message(
+            "e.g. u=1, i; doesn't need to be a valid structured field",
+        )
+        .prompt()?;
 
-    let frame = if ty.as_str() == REQUEST {
-        quiche::h3::frame::Frame::PriorityUpdateRequest {
+    let mut action = if ty.as_str() == REQUEST {
+        send_priority_update_request(
+            stream_id,
             prioritized_element_id,
-            priority_field_value: priority_field_value.into(),
-        }
+            priority_field_value.as_bytes(),
+        )
     } else {
-        quiche::h3::frame::Frame::PriorityUpdatePush {
+        send_priority_update_push(
+            stream_id,
             prioritized_element_id,
-            priority_field_value: priority_field_value.into(),
-        }
+            priority_field_value.as_bytes(),
+        )
     };
 
-    let fin_stream = prompt_fin_stream()?;
-
-    let action = Action::SendFrame {
-        stream_id,
-        fin_stream,
-        frame,
-    };
+    if let Action::SendFrame { fin_stream, .. } = &mut action {
+        *fin_stream = prompt_fin_stream()?;
+    }
 
     Ok(action)
 }
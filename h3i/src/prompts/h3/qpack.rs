@@ -0,0 +1,114 @@
+// Copyright (C) 2025, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Prompts for QPACK encoder and decoder stream instructions.
+
+use inquire::error::InquireResult;
+use inquire::Select;
+use inquire::Text;
+
+use crate::actions::h3::Action;
+use crate::actions::h3::QpackInstruction;
+use crate::recordreplay::hex_decode;
+
+use super::prompt_stream_id;
+use super::prompt_varint;
+use super::prompt_yes_no;
+use super::transport::validate_hex;
+
+const SET_DYNAMIC_TABLE_CAPACITY: &str = "set_dynamic_table_capacity";
+const INSERT_WITH_NAME_REFERENCE: &str = "insert_with_name_reference";
+const INSERT_WITH_LITERAL_NAME: &str = "insert_with_literal_name";
+const DUPLICATE: &str = "duplicate";
+const SECTION_ACKNOWLEDGMENT: &str = "section_acknowledgment";
+const STREAM_CANCELLATION: &str = "stream_cancellation";
+const INSERT_COUNT_INCREMENT: &str = "insert_count_increment";
+const RAW: &str = "raw";
+
+pub fn prompt_qpack_instruction() -> InquireResult<Action> {
+    let stream_id = prompt_stream_id()?;
+
+    let ty = Select::new("instruction:", vec![
+        SET_DYNAMIC_TABLE_CAPACITY,
+        INSERT_WITH_NAME_REFERENCE,
+        INSERT_WITH_LITERAL_NAME,
+        DUPLICATE,
+        SECTION_ACKNOWLEDGMENT,
+        STREAM_CANCELLATION,
+        INSERT_COUNT_INCREMENT,
+        RAW,
+    ])
+    .prompt()?;
+
+    let instruction = match ty {
+        SET_DYNAMIC_TABLE_CAPACITY => QpackInstruction::SetDynamicTableCapacity {
+            capacity: prompt_varint("capacity:")?,
+        },
+
+        INSERT_WITH_NAME_REFERENCE => QpackInstruction::InsertWithNameReference {
+            is_static: prompt_yes_no("static table:")?,
+            name_index: prompt_varint("name index:")?,
+            value: Text::new("value:").prompt()?.into_bytes(),
+        },
+
+        INSERT_WITH_LITERAL_NAME => QpackInstruction::InsertWithLiteralName {
+            name: Text::new("name:").prompt()?.into_bytes(),
+            value: Text::new("value:").prompt()?.into_bytes(),
+        },
+
+        DUPLICATE => QpackInstruction::Duplicate {
+            index: prompt_varint("index:")?,
+        },
+
+        SECTION_ACKNOWLEDGMENT => QpackInstruction::SectionAcknowledgment {
+            stream_id: prompt_varint("acknowledged stream ID:")?,
+        },
+
+        STREAM_CANCELLATION => QpackInstruction::StreamCancellation {
+            stream_id: prompt_varint("cancelled stream ID:")?,
+        },
+
+        INSERT_COUNT_INCREMENT => QpackInstruction::InsertCountIncrement {
+            increment: prompt_varint("increment:")?,
+        },
+
+        RAW => {
+            let bytes = Text::new("instruction bytes (hex):")
+                .with_validator(validate_hex)
+                .prompt()?;
+
+            // bytes are already validated so unwrap always succeeds
+            QpackInstruction::Raw(hex_decode(&bytes).unwrap())
+        },
+
+        _ => unreachable!(),
+    };
+
+    Ok(Action::SendQpackInstruction {
+        stream_id,
+        instruction,
+    })
+}
@@ -41,6 +41,7 @@ const MAX_FIELD_SECTION_SIZE: &str = "MAX_FIELD_SECTION_SIZE";
 const QPACK_BLOCKED_STREAMS: &str = "QPACK_BLOCKED_STREAMS";
 const ENABLE_CONNECT_PROTOCOL: &str = "ENABLE_CONNECT_PROTOCOL";
 const H3_DATAGRAM: &str = "H3_DATAGRAM";
+const GREASE: &str = "GREASE";
 
 type RawSettings = Vec<(u64, u64)>;
 
@@ -97,6 +98,11 @@ fn settings_read_loop() -> RawSettings {
             QPACK_BLOCKED_STREAMS => 0x7,
             ENABLE_CONNECT_PROTOCOL => 0x8,
             H3_DATAGRAM => 0x33,
+            GREASE => {
+                let id = quiche::h3::grease_value();
+                println!("GREASE setting type={id}");
+                id
+            },
 
             v => v.parse::<u64>().unwrap(),
         };
@@ -121,7 +127,8 @@ fn validate_setting_type(id: &str) -> SuggestionResult<Validation> {
             MAX_FIELD_SECTION_SIZE |
             QPACK_BLOCKED_STREAMS |
             ENABLE_CONNECT_PROTOCOL |
-            H3_DATAGRAM
+            H3_DATAGRAM |
+            GREASE
     ) {
         return Ok(Validation::Valid);
     }
@@ -136,6 +143,7 @@ fn settings_type_suggestor(val: &str) -> SuggestionResult<Vec<String>> {
         QPACK_BLOCKED_STREAMS,
         ENABLE_CONNECT_PROTOCOL,
         H3_DATAGRAM,
+        GREASE,
     ];
 
     squish_suggester(&suggestions, val)
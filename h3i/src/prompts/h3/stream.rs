@@ -45,6 +45,7 @@ const CONTROL_STREAM: &str = "Control Stream";
 const PUSH_STREAM: &str = "Push Stream";
 const QPACK_ENCODER: &str = "QPACK Encoder Stream";
 const QPACK_DECODER: &str = "QPACK Decoder Stream";
+const GREASE_STREAM: &str = "GREASE Stream";
 
 fn validate_stream_id(id: &str) -> SuggestionResult<Validation> {
     if id.is_empty() {
@@ -88,6 +89,11 @@ pub fn prompt_open_uni_stream(
         PUSH_STREAM => 0x1,
         QPACK_ENCODER => 0x2,
         QPACK_DECODER => 0x3,
+        GREASE_STREAM => {
+            let ty = quiche::h3::grease_value();
+            println!("GREASE stream type={ty}");
+            ty
+        },
         _ => stream_type.parse::<u64>().unwrap(),
     };
 
@@ -103,7 +109,11 @@ pub fn prompt_open_uni_stream(
 fn validate_stream_type(id: &str) -> SuggestionResult<Validation> {
     if matches!(
         id,
-        CONTROL_STREAM | PUSH_STREAM | QPACK_ENCODER | QPACK_DECODER
+        CONTROL_STREAM |
+            PUSH_STREAM |
+            QPACK_ENCODER |
+            QPACK_DECODER |
+            GREASE_STREAM
     ) {
         return Ok(Validation::Valid);
     }
@@ -112,7 +122,13 @@ fn validate_stream_type(id: &str) -> SuggestionResult<Validation> {
 }
 
 fn stream_type_suggestor(val: &str) -> SuggestionResult<Vec<String>> {
-    let suggestions = [CONTROL_STREAM, PUSH_STREAM, QPACK_ENCODER, QPACK_DECODER];
+    let suggestions = [
+        CONTROL_STREAM,
+        PUSH_STREAM,
+        QPACK_ENCODER,
+        QPACK_DECODER,
+        GREASE_STREAM,
+    ];
 
     squish_suggester(&suggestions, val)
 }
@@ -0,0 +1,127 @@
+// Copyright (C) 2025, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Prompts for arbitrary QUIC frames, connection migration and paths.
+
+use inquire::error::InquireResult;
+use inquire::validator::ErrorMessage;
+use inquire::validator::Validation;
+use inquire::Text;
+
+use crate::actions::h3::Action;
+use crate::recordreplay::hex_decode;
+
+use super::prompt_varint;
+use super::prompt_yes_no;
+use super::SuggestionResult;
+
+pub fn prompt_padding() -> InquireResult<Action> {
+    let len = prompt_varint("length:")?;
+
+    Ok(Action::Padding { len: len as usize })
+}
+
+pub fn prompt_burst() -> InquireResult<Action> {
+    let packets = prompt_varint("packets:")?;
+
+    Ok(Action::Burst {
+        packets: packets as usize,
+    })
+}
+
+pub fn prompt_raw_quic_frame() -> InquireResult<Action> {
+    let bytes = Text::new("frame bytes (hex):")
+        .with_validator(validate_hex)
+        .prompt()?;
+
+    // bytes are already validated so unwrap always succeeds
+    Ok(Action::RawQuicFrame {
+        bytes: hex_decode(&bytes).unwrap(),
+    })
+}
+
+pub fn prompt_datagram() -> InquireResult<Action> {
+    let quarter_stream_id = prompt_varint("quarter stream ID:")?;
+    let payload = Text::new("payload:").prompt()?;
+
+    Ok(Action::SendDatagram {
+        quarter_stream_id,
+        payload: payload.into_bytes(),
+    })
+}
+
+pub fn prompt_migrate() -> InquireResult<Action> {
+    let local_addr = Text::new("local address (empty for a new port):")
+        .with_validator(validate_socket_addr)
+        .prompt()?;
+
+    let nat_rebinding = prompt_yes_no("NAT rebinding:")?;
+
+    // local_addr is already validated, so only an empty one fails to parse
+    Ok(Action::Migrate {
+        local_addr: local_addr.parse().ok(),
+        nat_rebinding,
+    })
+}
+
+pub fn prompt_probe_path() -> InquireResult<Action> {
+    let local_addr = Text::new("local address (empty for a new port):")
+        .with_validator(validate_socket_addr)
+        .prompt()?;
+
+    // local_addr is already validated, so only an empty one fails to parse
+    Ok(Action::ProbePath {
+        local_addr: local_addr.parse().ok(),
+    })
+}
+
+pub fn prompt_switch_path() -> InquireResult<Action> {
+    let path = prompt_varint("path (0 for the initial path):")?;
+
+    Ok(Action::SwitchPath {
+        path: path as usize,
+    })
+}
+
+fn validate_socket_addr(v: &str) -> SuggestionResult<Validation> {
+    if v.is_empty() || v.parse::<std::net::SocketAddr>().is_ok() {
+        return Ok(Validation::Valid);
+    }
+
+    Ok(Validation::Invalid(ErrorMessage::Custom(
+        "expected an address and port, e.g. 0.0.0.0:4433".to_string(),
+    )))
+}
+
+pub(super) fn validate_hex(v: &str) -> SuggestionResult<Validation> {
+    if !v.is_empty() && hex_decode(v).is_some() {
+        return Ok(Validation::Valid);
+    }
+
+    Ok(Validation::Invalid(ErrorMessage::Custom(
+        "expected an even number of hex characters".to_string(),
+    )))
+}
@@ -31,11 +31,13 @@ use inquire::validator::Validation;
 use inquire::Text;
 
 use crate::actions::h3::Action;
+use crate::actions::h3::ConnectionEvent;
 use crate::actions::h3::StreamEvent;
 use crate::actions::h3::StreamEventType;
 use crate::actions::h3::WaitType;
 
 use super::prompt_stream_id;
+use super::prompt_varint;
 use super::squish_suggester;
 use super::validate_wait_period;
 use super::SuggestionResult;
@@ -44,6 +46,12 @@ const DURATION: &str = "duration";
 const HEADERS: &str = "headers";
 const DATA: &str = "data";
 const FINISHED: &str = "stream finished";
+const DATA_BYTES: &str = "data bytes";
+const GOAWAY: &str = "goaway";
+const SETTINGS: &str = "settings";
+const CONNECTION_CLOSE: &str = "connection close";
+const HANDSHAKE_CONFIRMED: &str = "handshake confirmed";
+const PATH_VALIDATED: &str = "path validated";
 
 pub fn prompt_wait() -> InquireResult<Action> {
     let wait_type = Text::new("wait type:")
@@ -53,7 +61,16 @@ pub fn prompt_wait() -> InquireResult<Action> {
 
     let actual = match wait_type.as_str() {
         DURATION => Some(prompt_wait_period()),
-        t @ (HEADERS | DATA | FINISHED) => Some(prompt_stream_wait(t)),
+        t @ (HEADERS | DATA | FINISHED | DATA_BYTES) =>
+            Some(prompt_stream_wait(t)),
+        GOAWAY => Some(Ok(connection_wait(ConnectionEvent::GoAway))),
+        SETTINGS => Some(Ok(connection_wait(ConnectionEvent::Settings))),
+        CONNECTION_CLOSE =>
+            Some(Ok(connection_wait(ConnectionEvent::ConnectionClose))),
+        HANDSHAKE_CONFIRMED =>
+            Some(Ok(connection_wait(ConnectionEvent::HandshakeConfirmed))),
+        PATH_VALIDATED =>
+            Some(Ok(connection_wait(ConnectionEvent::PathValidated))),
         _ => None,
     };
 
@@ -66,14 +83,27 @@ pub fn prompt_wait() -> InquireResult<Action> {
 }
 
 fn wait_type_suggestor(val: &str) -> SuggestionResult<Vec<String>> {
-    let suggestions = [DURATION, HEADERS, DATA, FINISHED];
+    let suggestions = [
+        DURATION,
+        HEADERS,
+        DATA,
+        FINISHED,
+        DATA_BYTES,
+        GOAWAY,
+        SETTINGS,
+        CONNECTION_CLOSE,
+        HANDSHAKE_CONFIRMED,
+        PATH_VALIDATED,
+    ];
 
     squish_suggester(&suggestions, val)
 }
 
 fn wait_type_validator(wait_type: &str) -> SuggestionResult<Validation> {
     match wait_type {
-        DURATION | HEADERS | DATA | FINISHED => Ok(Validation::Valid),
+        DURATION | HEADERS | DATA | FINISHED | DATA_BYTES | GOAWAY |
+        SETTINGS | CONNECTION_CLOSE | HANDSHAKE_CONFIRMED | PATH_VALIDATED =>
+            Ok(Validation::Valid),
         _ => Ok(Validation::Invalid(
             inquire::validator::ErrorMessage::Default,
         )),
@@ -89,6 +119,8 @@ fn prompt_stream_wait(stream_wait_type: &str) -> InquireResult<WaitType> {
         Some(StreamEventType::Data)
     } else if let FINISHED = stream_wait_type {
         Some(StreamEventType::Finished)
+    } else if let DATA_BYTES = stream_wait_type {
+        Some(StreamEventType::DataBytes(prompt_varint("bytes:")?))
     } else {
         None
     }
@@ -102,6 +134,10 @@ fn prompt_stream_wait(stream_wait_type: &str) -> InquireResult<WaitType> {
     }))
 }
 
+fn connection_wait(event: ConnectionEvent) -> WaitType {
+    WaitType::ConnectionEvent(event)
+}
+
 pub fn prompt_wait_period() -> InquireResult<WaitType> {
     let period = Text::new("wait period (ms):")
         .with_validator(validate_wait_period)
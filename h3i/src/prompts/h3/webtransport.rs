@@ -0,0 +1,74 @@
+// Copyright (C) 2025, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Prompts for extended CONNECT requests and WebTransport streams.
+
+use inquire::error::InquireResult;
+use inquire::Text;
+
+use crate::actions::h3::send_extended_connect;
+use crate::actions::h3::Action;
+use crate::actions::h3::WEBTRANSPORT_PROTOCOL;
+use crate::prompts::h3;
+use crate::StreamIdAllocator;
+
+use super::prompt_yes_no;
+use super::stream::autopick_stream_id;
+
+pub fn prompt_extended_connect(
+    sid_alloc: &mut StreamIdAllocator, host_port: &str,
+) -> InquireResult<Action> {
+    let stream_id = autopick_stream_id(sid_alloc)?;
+
+    let protocol = Text::new("protocol:")
+        .with_default(WEBTRANSPORT_PROTOCOL)
+        .prompt()?;
+    let authority = Text::new("authority:").with_default(host_port).prompt()?;
+    let path = Text::new("path:").with_default("/").prompt()?;
+
+    Ok(send_extended_connect(
+        stream_id, &protocol, &authority, &path,
+    ))
+}
+
+pub fn prompt_open_webtransport_stream(
+    bidi_sid_alloc: &mut StreamIdAllocator, uni_sid_alloc: &mut StreamIdAllocator,
+) -> InquireResult<Action> {
+    let session_id = h3::prompt_varint("session ID:")?;
+    let bidi = prompt_yes_no("bidirectional:")?;
+
+    let stream_id = if bidi {
+        autopick_stream_id(bidi_sid_alloc)?
+    } else {
+        autopick_stream_id(uni_sid_alloc)?
+    };
+
+    Ok(Action::OpenWebTransportStream {
+        stream_id,
+        session_id,
+        bidi,
+    })
+}
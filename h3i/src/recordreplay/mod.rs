@@ -25,4 +25,19 @@
 // SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
 
 //! Support for recording h3i Actions and replaying them.
+pub mod pcap;
 pub mod qlog;
+pub mod script;
+
+/// Decodes a string of hex digits, e.g. from a key log file, a qlog, a
+/// prompt or the command line.
+pub fn hex_decode(s: &str) -> Option<Vec<u8>> {
+    if s.len() % 2 != 0 {
+        return None;
+    }
+
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
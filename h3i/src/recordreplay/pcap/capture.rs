@@ -0,0 +1,625 @@
+// Copyright (C) 2025, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Extraction of UDP datagrams from pcap and pcapng captures.
+
+use std::net::IpAddr;
+use std::net::Ipv4Addr;
+use std::net::Ipv6Addr;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use super::Error;
+use super::Result;
+
+const PCAP_MAGIC_MICROS: u32 = 0xa1b2_c3d4;
+const PCAP_MAGIC_NANOS: u32 = 0xa1b2_3c4d;
+
+const PCAPNG_SECTION_HEADER: u32 = 0x0a0d_0d0a;
+const PCAPNG_BYTE_ORDER_MAGIC: u32 = 0x1a2b_3c4d;
+const PCAPNG_INTERFACE_DESCRIPTION: u32 = 0x0000_0001;
+const PCAPNG_SIMPLE_PACKET: u32 = 0x0000_0003;
+const PCAPNG_ENHANCED_PACKET: u32 = 0x0000_0006;
+const PCAPNG_DECRYPTION_SECRETS: u32 = 0x0000_000a;
+
+const PCAPNG_OPTION_END: u16 = 0;
+const PCAPNG_OPTION_IF_TSRESOL: u16 = 9;
+
+/// The pcapng secrets type of a TLS key log.
+const PCAPNG_SECRETS_TLS_KEY_LOG: u32 = 0x544c_534b;
+
+const LINKTYPE_NULL: u32 = 0;
+const LINKTYPE_ETHERNET: u32 = 1;
+const LINKTYPE_RAW: u32 = 101;
+const LINKTYPE_LINUX_SLL: u32 = 113;
+const LINKTYPE_IPV4: u32 = 228;
+const LINKTYPE_IPV6: u32 = 229;
+const LINKTYPE_LINUX_SLL2: u32 = 276;
+
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_IPV6: u16 = 0x86dd;
+const ETHERTYPE_VLAN: u16 = 0x8100;
+const ETHERTYPE_QINQ: u16 = 0x88a8;
+
+const IPPROTO_HOPOPTS: u8 = 0;
+const IPPROTO_UDP: u8 = 17;
+const IPPROTO_ROUTING: u8 = 43;
+const IPPROTO_DSTOPTS: u8 = 60;
+
+/// A UDP datagram found in a capture.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Datagram {
+    /// The capture timestamp, relative to the UNIX epoch.
+    pub time: Duration,
+    pub from: SocketAddr,
+    pub to: SocketAddr,
+    pub payload: Vec<u8>,
+}
+
+/// The contents of a capture relevant to QUIC.
+#[derive(Debug, Default)]
+pub struct Capture {
+    /// The UDP datagrams, in capture order.
+    pub datagrams: Vec<Datagram>,
+    /// The TLS key logs embedded in a pcapng capture.
+    pub key_log: String,
+}
+
+impl Capture {
+    fn push(
+        &mut self, link_type: u32, data: &[u8], time: Duration,
+    ) -> Result<()> {
+        match parse_link(link_type, data, time) {
+            Ok(Some(d)) => self.datagrams.push(d),
+
+            // Packets truncated by the capture's snap length are skipped.
+            Ok(None) | Err(Error::InvalidCapture(_)) => (),
+
+            Err(e) => return Err(e),
+        }
+
+        Ok(())
+    }
+}
+
+/// Parses a pcap or pcapng capture.
+///
+/// Packets that aren't UDP over IPv4 or IPv6, as well as IP fragments, are
+/// skipped.
+pub fn parse(buf: &[u8]) -> Result<Capture> {
+    let magic = read_u32(buf, 0, false)?;
+
+    match magic {
+        PCAPNG_SECTION_HEADER => parse_pcapng(buf),
+
+        _ => parse_pcap(buf),
+    }
+}
+
+fn parse_pcap(buf: &[u8]) -> Result<Capture> {
+    let (big_endian, nanos) = match read_u32(buf, 0, false)? {
+        PCAP_MAGIC_MICROS => (false, false),
+        PCAP_MAGIC_NANOS => (false, true),
+
+        m => match m.swap_bytes() {
+            PCAP_MAGIC_MICROS => (true, false),
+            PCAP_MAGIC_NANOS => (true, true),
+
+            _ => return Err(Error::InvalidCapture("unknown file format".into())),
+        },
+    };
+
+    let link_type = read_u32(buf, 20, big_endian)? & 0xffff;
+
+    let mut capture = Capture::default();
+    let mut off = 24;
+
+    while off < buf.len() {
+        let secs = read_u32(buf, off, big_endian)?;
+        let frac = read_u32(buf, off + 4, big_endian)?;
+        let caplen = read_u32(buf, off + 8, big_endian)? as usize;
+
+        let data = slice(buf, off + 16, caplen)?;
+
+        let time = if nanos {
+            Duration::new(secs.into(), frac)
+        } else {
+            Duration::new(secs.into(), 0) + Duration::from_micros(frac.into())
+        };
+
+        capture.push(link_type, data, time)?;
+
+        off += 16 + caplen;
+    }
+
+    Ok(capture)
+}
+
+/// A pcapng interface, as needed to interpret its packets.
+struct Interface {
+    link_type: u32,
+    /// The number of timestamp units per second.
+    units_per_sec: u64,
+}
+
+fn parse_pcapng(buf: &[u8]) -> Result<Capture> {
+    let mut capture = Capture::default();
+    let mut interfaces: Vec<Interface> = Vec::new();
+    let mut big_endian = false;
+    let mut off = 0;
+
+    while off < buf.len() {
+        let block_type = read_u32(buf, off, big_endian)?;
+
+        // The byte order of a section is only known once its header is read.
+        if block_type == PCAPNG_SECTION_HEADER {
+            big_endian = match read_u32(buf, off + 8, false)? {
+                PCAPNG_BYTE_ORDER_MAGIC => false,
+
+                m if m.swap_bytes() == PCAPNG_BYTE_ORDER_MAGIC => true,
+
+                _ =>
+                    return Err(Error::InvalidCapture(
+                        "invalid pcapng byte order".into(),
+                    )),
+            };
+
+            interfaces.clear();
+        }
+
+        let block_len = read_u32(buf, off + 4, big_endian)? as usize;
+
+        if block_len < 12 || block_len % 4 != 0 {
+            return Err(Error::InvalidCapture(format!(
+                "invalid pcapng block length {block_len}"
+            )));
+        }
+
+        let body = slice(buf, off + 8, block_len - 12)?;
+
+        match block_type {
+            PCAPNG_INTERFACE_DESCRIPTION => {
+                let link_type = u32::from(read_u16(body, 0, big_endian)?);
+                let units_per_sec =
+                    interface_units_per_sec(rest(body, 8)?, big_endian)?;
+
+                interfaces.push(Interface {
+                    link_type,
+                    units_per_sec,
+                });
+            },
+
+            PCAPNG_ENHANCED_PACKET => {
+                let id = read_u32(body, 0, big_endian)? as usize;
+                let ts = (u64::from(read_u32(body, 4, big_endian)?) << 32) |
+                    u64::from(read_u32(body, 8, big_endian)?);
+                let caplen = read_u32(body, 12, big_endian)? as usize;
+
+                let iface = interfaces.get(id).ok_or_else(|| {
+                    Error::InvalidCapture(format!("unknown interface {id}"))
+                })?;
+
+                let time = Duration::from_secs(ts / iface.units_per_sec) +
+                    Duration::from_nanos(
+                        (ts % iface.units_per_sec) * 1_000_000_000 /
+                            iface.units_per_sec,
+                    );
+
+                let data = slice(body, 20, caplen)?;
+
+                capture.push(iface.link_type, data, time)?;
+            },
+
+            PCAPNG_SIMPLE_PACKET => {
+                // Simple packets have no timestamp, nor an explicit interface.
+                let iface = interfaces.first().ok_or_else(|| {
+                    Error::InvalidCapture("no interface".into())
+                })?;
+
+                let data = rest(body, 4)?;
+
+                capture.push(iface.link_type, data, Duration::ZERO)?;
+            },
+
+            PCAPNG_DECRYPTION_SECRETS => {
+                let secrets_type = read_u32(body, 0, big_endian)?;
+                let len = read_u32(body, 4, big_endian)? as usize;
+
+                if secrets_type == PCAPNG_SECRETS_TLS_KEY_LOG {
+                    let secrets = slice(body, 8, len)?;
+
+                    capture.key_log.push_str(&String::from_utf8_lossy(secrets));
+                    capture.key_log.push('\n');
+                }
+            },
+
+            _ => (),
+        }
+
+        off += block_len;
+    }
+
+    Ok(capture)
+}
+
+/// Returns the timestamp resolution of a pcapng interface, given its options.
+fn interface_units_per_sec(mut opts: &[u8], big_endian: bool) -> Result<u64> {
+    while opts.len() >= 4 {
+        let code = read_u16(opts, 0, big_endian)?;
+        let len = read_u16(opts, 2, big_endian)? as usize;
+
+        if code == PCAPNG_OPTION_END {
+            break;
+        }
+
+        if code == PCAPNG_OPTION_IF_TSRESOL && len == 1 {
+            let v = slice(opts, 4, 1)?[0];
+            let exp = u32::from(v & 0x7f);
+
+            let base: u64 = if v & 0x80 != 0 { 2 } else { 10 };
+
+            return base.checked_pow(exp).filter(|v| *v > 0).ok_or_else(|| {
+                Error::InvalidCapture(format!("invalid timestamp resolution {v}"))
+            });
+        }
+
+        let padded = (len + 3) & !3;
+
+        opts = rest(opts, 4 + padded)?;
+    }
+
+    // Microseconds.
+    Ok(1_000_000)
+}
+
+/// Extracts a UDP datagram from a link-layer frame.
+fn parse_link(
+    link_type: u32, data: &[u8], time: Duration,
+) -> Result<Option<Datagram>> {
+    let (ethertype, ip) = match link_type {
+        LINKTYPE_ETHERNET => {
+            let mut off = 12;
+            let mut ethertype = read_u16(data, off, true)?;
+
+            while ethertype == ETHERTYPE_VLAN || ethertype == ETHERTYPE_QINQ {
+                off += 4;
+                ethertype = read_u16(data, off, true)?;
+            }
+
+            (Some(ethertype), rest(data, off + 2)?)
+        },
+
+        LINKTYPE_LINUX_SLL => (Some(read_u16(data, 14, true)?), rest(data, 16)?),
+
+        LINKTYPE_LINUX_SLL2 => (Some(read_u16(data, 0, true)?), rest(data, 20)?),
+
+        // The address family is in host byte order, the IP version tells
+        // which one it is anyway.
+        LINKTYPE_NULL => (None, rest(data, 4)?),
+
+        LINKTYPE_RAW | LINKTYPE_IPV4 | LINKTYPE_IPV6 => (None, data),
+
+        _ => return Err(Error::UnsupportedLinkType(link_type)),
+    };
+
+    let version = match ethertype {
+        Some(ETHERTYPE_IPV4) => 4,
+
+        Some(ETHERTYPE_IPV6) => 6,
+
+        Some(_) => return Ok(None),
+
+        None => match ip.first() {
+            Some(v) => v >> 4,
+
+            None => return Ok(None),
+        },
+    };
+
+    let (src, dst, udp) = match version {
+        4 => match parse_ipv4(ip)? {
+            Some(v) => v,
+
+            None => return Ok(None),
+        },
+
+        6 => match parse_ipv6(ip)? {
+            Some(v) => v,
+
+            None => return Ok(None),
+        },
+
+        _ => return Ok(None),
+    };
+
+    let src_port = read_u16(udp, 0, true)?;
+    let dst_port = read_u16(udp, 2, true)?;
+    let len = read_u16(udp, 4, true)? as usize;
+
+    if len < 8 {
+        return Ok(None);
+    }
+
+    // Truncated by the capture's snap length.
+    let Ok(payload) = slice(udp, 8, len - 8) else {
+        return Ok(None);
+    };
+
+    Ok(Some(Datagram {
+        time,
+        from: SocketAddr::new(src, src_port),
+        to: SocketAddr::new(dst, dst_port),
+        payload: payload.to_vec(),
+    }))
+}
+
+type IpPacket<'a> = (IpAddr, IpAddr, &'a [u8]);
+
+fn parse_ipv4(ip: &[u8]) -> Result<Option<IpPacket<'_>>> {
+    let ihl = usize::from(slice(ip, 0, 1)?[0] & 0x0f) * 4;
+    let total_len = read_u16(ip, 2, true)? as usize;
+    let frag = read_u16(ip, 6, true)?;
+    let proto = slice(ip, 9, 1)?[0];
+
+    // Skip fragments, as well as non-UDP packets.
+    if frag & 0x3fff != 0 || proto != IPPROTO_UDP || total_len < ihl {
+        return Ok(None);
+    }
+
+    let src: [u8; 4] = slice(ip, 12, 4)?.try_into().unwrap();
+    let dst: [u8; 4] = slice(ip, 16, 4)?.try_into().unwrap();
+
+    let end = total_len.min(ip.len());
+
+    Ok(Some((
+        Ipv4Addr::from(src).into(),
+        Ipv4Addr::from(dst).into(),
+        slice(ip, ihl, end.saturating_sub(ihl))?,
+    )))
+}
+
+fn parse_ipv6(ip: &[u8]) -> Result<Option<IpPacket<'_>>> {
+    let payload_len = read_u16(ip, 4, true)? as usize;
+    let mut next = slice(ip, 6, 1)?[0];
+
+    let src: [u8; 16] = slice(ip, 8, 16)?.try_into().unwrap();
+    let dst: [u8; 16] = slice(ip, 24, 16)?.try_into().unwrap();
+
+    let end = (40 + payload_len).min(ip.len());
+    let mut off = 40;
+
+    loop {
+        match next {
+            IPPROTO_UDP => break,
+
+            IPPROTO_HOPOPTS | IPPROTO_ROUTING | IPPROTO_DSTOPTS => {
+                next = slice(ip, off, 1)?[0];
+                off += (usize::from(slice(ip, off + 1, 1)?[0]) + 1) * 8;
+            },
+
+            // Fragments and non-UDP packets.
+            _ => return Ok(None),
+        }
+    }
+
+    Ok(Some((
+        Ipv6Addr::from(src).into(),
+        Ipv6Addr::from(dst).into(),
+        slice(ip, off, end.saturating_sub(off))?,
+    )))
+}
+
+fn slice(buf: &[u8], off: usize, len: usize) -> Result<&[u8]> {
+    off.checked_add(len)
+        .and_then(|end| buf.get(off..end))
+        .ok_or_else(|| Error::InvalidCapture("truncated capture".into()))
+}
+
+fn rest(buf: &[u8], off: usize) -> Result<&[u8]> {
+    buf.get(off..)
+        .ok_or_else(|| Error::InvalidCapture("truncated capture".into()))
+}
+
+fn read_u16(buf: &[u8], off: usize, big_endian: bool) -> Result<u16> {
+    let b: [u8; 2] = slice(buf, off, 2)?.try_into().unwrap();
+
+    Ok(if big_endian {
+        u16::from_be_bytes(b)
+    } else {
+        u16::from_le_bytes(b)
+    })
+}
+
+fn read_u32(buf: &[u8], off: usize, big_endian: bool) -> Result<u32> {
+    let b: [u8; 4] = slice(buf, off, 4)?.try_into().unwrap();
+
+    Ok(if big_endian {
+        u32::from_be_bytes(b)
+    } else {
+        u32::from_le_bytes(b)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn udp_ipv4(payload: &[u8]) -> Vec<u8> {
+        let mut ip = vec![
+            0x45,
+            0x00,
+            0x00,
+            0x00,
+            0x00,
+            0x00,
+            0x40,
+            0x00,
+            0x40,
+            IPPROTO_UDP,
+            0x00,
+            0x00,
+            10,
+            0,
+            0,
+            1,
+            10,
+            0,
+            0,
+            2,
+        ];
+
+        ip.extend_from_slice(&1234_u16.to_be_bytes());
+        ip.extend_from_slice(&443_u16.to_be_bytes());
+        ip.extend_from_slice(&(8 + payload.len() as u16).to_be_bytes());
+        ip.extend_from_slice(&[0, 0]);
+        ip.extend_from_slice(payload);
+
+        let len = ip.len() as u16;
+        ip[2..4].copy_from_slice(&len.to_be_bytes());
+
+        ip
+    }
+
+    fn ethernet(ip: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0; 12];
+        frame.extend_from_slice(&ETHERTYPE_VLAN.to_be_bytes());
+        frame.extend_from_slice(&[0, 1]);
+        frame.extend_from_slice(&ETHERTYPE_IPV4.to_be_bytes());
+        frame.extend_from_slice(ip);
+        frame
+    }
+
+    fn expected_datagram(time: Duration) -> Datagram {
+        Datagram {
+            time,
+            from: "10.0.0.1:1234".parse().unwrap(),
+            to: "10.0.0.2:443".parse().unwrap(),
+            payload: b"quic".to_vec(),
+        }
+    }
+
+    #[test]
+    fn pcap() {
+        let frame = ethernet(&udp_ipv4(b"quic"));
+
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&PCAP_MAGIC_MICROS.to_be_bytes());
+        buf.extend_from_slice(&[0, 2, 0, 4]);
+        buf.extend_from_slice(&[0; 8]);
+        buf.extend_from_slice(&65535_u32.to_be_bytes());
+        buf.extend_from_slice(&LINKTYPE_ETHERNET.to_be_bytes());
+
+        buf.extend_from_slice(&10_u32.to_be_bytes());
+        buf.extend_from_slice(&500_u32.to_be_bytes());
+        buf.extend_from_slice(&(frame.len() as u32).to_be_bytes());
+        buf.extend_from_slice(&(frame.len() as u32).to_be_bytes());
+        buf.extend_from_slice(&frame);
+
+        let capture = parse(&buf).unwrap();
+        assert_eq!(capture.datagrams, vec![expected_datagram(Duration::new(
+            10, 500_000
+        ))]);
+        assert!(capture.key_log.is_empty());
+
+        // A truncated record.
+        assert!(parse(&buf[..buf.len() - 1]).is_err());
+    }
+
+    fn pcapng_block(ty: u32, body: &[u8]) -> Vec<u8> {
+        let len = 12 + body.len().div_ceil(4) * 4;
+
+        let mut block = Vec::new();
+        block.extend_from_slice(&ty.to_le_bytes());
+        block.extend_from_slice(&(len as u32).to_le_bytes());
+        block.extend_from_slice(body);
+        block.resize(len - 4, 0);
+        block.extend_from_slice(&(len as u32).to_le_bytes());
+        block
+    }
+
+    #[test]
+    fn pcapng() {
+        let ip = udp_ipv4(b"quic");
+
+        let mut shb = PCAPNG_BYTE_ORDER_MAGIC.to_le_bytes().to_vec();
+        shb.extend_from_slice(&[1, 0, 0, 0]);
+        shb.extend_from_slice(&u64::MAX.to_le_bytes());
+
+        // Raw IP, nanosecond timestamps.
+        let mut idb = (LINKTYPE_RAW as u16).to_le_bytes().to_vec();
+        idb.extend_from_slice(&[0, 0]);
+        idb.extend_from_slice(&0_u32.to_le_bytes());
+        idb.extend_from_slice(&PCAPNG_OPTION_IF_TSRESOL.to_le_bytes());
+        idb.extend_from_slice(&1_u16.to_le_bytes());
+        idb.extend_from_slice(&[9, 0, 0, 0]);
+        idb.extend_from_slice(&[0; 4]);
+
+        let key_log = b"CLIENT_TRAFFIC_SECRET_0 00 11";
+
+        let mut dsb = PCAPNG_SECRETS_TLS_KEY_LOG.to_le_bytes().to_vec();
+        dsb.extend_from_slice(&(key_log.len() as u32).to_le_bytes());
+        dsb.extend_from_slice(key_log);
+
+        let ts: u64 = 10_000_000_500;
+
+        let mut epb = 0_u32.to_le_bytes().to_vec();
+        epb.extend_from_slice(&((ts >> 32) as u32).to_le_bytes());
+        epb.extend_from_slice(&(ts as u32).to_le_bytes());
+        epb.extend_from_slice(&(ip.len() as u32).to_le_bytes());
+        epb.extend_from_slice(&(ip.len() as u32).to_le_bytes());
+        epb.extend_from_slice(&ip);
+
+        let mut buf = pcapng_block(PCAPNG_SECTION_HEADER, &shb);
+        buf.extend(pcapng_block(PCAPNG_INTERFACE_DESCRIPTION, &idb));
+        buf.extend(pcapng_block(PCAPNG_DECRYPTION_SECRETS, &dsb));
+        buf.extend(pcapng_block(PCAPNG_ENHANCED_PACKET, &epb));
+
+        let capture = parse(&buf).unwrap();
+        assert_eq!(capture.datagrams, vec![expected_datagram(Duration::new(
+            10, 500
+        ))]);
+        assert_eq!(capture.key_log, "CLIENT_TRAFFIC_SECRET_0 00 11\n");
+    }
+
+    #[test]
+    fn skip_non_udp() {
+        let mut ip = udp_ipv4(b"quic");
+
+        // TCP.
+        ip[9] = 6;
+        assert_eq!(parse_link(LINKTYPE_RAW, &ip, Duration::ZERO), Ok(None));
+
+        // A fragment.
+        ip[9] = IPPROTO_UDP;
+        ip[6] = 0x20;
+        assert_eq!(parse_link(LINKTYPE_RAW, &ip, Duration::ZERO), Ok(None));
+
+        assert_eq!(
+            parse_link(42, &ip, Duration::ZERO),
+            Err(Error::UnsupportedLinkType(42))
+        );
+    }
+}
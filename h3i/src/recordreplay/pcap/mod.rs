@@ -0,0 +1,1117 @@
+// Copyright (C) 2025, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Reconstruction of h3i actions from a packet capture of an HTTP/3 client.
+//!
+//! A capture, in pcap or pcapng format, and the TLS key log written by the
+//! client, e.g. via `SSLKEYLOGFILE`, are enough to decrypt what the client
+//! sent on its first connection. The streams the client opened are turned
+//! into the [Action]s that reproduce them: HEADERS frames with the decoded
+//! header list, other HTTP/3 frames, unidirectional streams, as well as stream
+//! resets and the connection close. The gaps between them are reproduced with
+//! [WaitType::WaitDuration] waits.
+//!
+//! Only QUIC version 1 is supported. Key logs embedded in pcapng captures,
+//! e.g. with `editcap --inject-secrets`, are used too.
+//!
+//! ```no_run
+//! use h3i::recordreplay::pcap;
+//!
+//! let capture = std::fs::read("capture.pcapng").unwrap();
+//! let key_log = std::fs::read_to_string("keys.log").unwrap();
+//!
+//! let actions =
+//!     pcap::actions_from_pcap(&capture, Some(&key_log), &Default::default())
+//!         .unwrap();
+//! ```
+
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use quiche::h3::frame::Frame;
+use quiche::h3::NameValue;
+use quiche::ConnectionError;
+
+use crate::actions::h3::send_headers_frame;
+use crate::actions::h3::send_headers_frame_literal;
+use crate::actions::h3::Action;
+use crate::actions::h3::WaitType;
+use crate::HTTP3_CONTROL_STREAM_TYPE_ID;
+
+use super::hex_decode;
+
+use capture::Datagram;
+
+pub use writer::Direction;
+pub use writer::PcapngWriter;
+
+mod capture;
+mod quic;
+mod writer;
+
+/// The minimum size of a datagram carrying a client's first Initial packet.
+const MIN_CLIENT_INITIAL_LEN: usize = 1200;
+
+/// A specialized [`Result`] type for capture conversions.
+///
+/// [`Result`]: https://doc.rust-lang.org/std/result/enum.Result.html
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// An error converting a capture.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The capture isn't a valid pcap or pcapng file.
+    InvalidCapture(String),
+
+    /// The capture uses an unsupported link-layer header type.
+    UnsupportedLinkType(u32),
+
+    /// No QUIC version 1 connection was found in the capture.
+    NoConnection,
+
+    /// The key log has no secrets for the connection.
+    MissingSecrets,
+
+    /// A packet couldn't be decrypted.
+    CryptoFail,
+
+    /// A QUIC packet or frame is malformed.
+    InvalidPacket(String),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Error::InvalidCapture(e) => write!(f, "invalid capture: {e}"),
+
+            Error::UnsupportedLinkType(v) =>
+                write!(f, "unsupported link-layer header type {v}"),
+
+            Error::NoConnection => write!(f, "no QUIC connection found"),
+
+            Error::MissingSecrets =>
+                write!(f, "no secrets for the connection in the key log"),
+
+            Error::CryptoFail => write!(f, "cryptographic operation failed"),
+
+            Error::InvalidPacket(e) => write!(f, "invalid packet: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Configures how a capture is converted.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Config {
+    /// The UDP port of the server. The first connection to that port is
+    /// converted, or the first connection in the capture if `None`.
+    pub server_port: Option<u16>,
+
+    /// The shortest gap between two actions that is reproduced with a wait.
+    pub min_wait: Duration,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            server_port: None,
+            min_wait: Duration::from_millis(1),
+        }
+    }
+}
+
+/// Reconstructs the actions of the client of the first connection in a pcap
+/// or pcapng `capture`.
+///
+/// `key_log` is a TLS key log in the NSS format, and may be omitted when the
+/// secrets are embedded in a pcapng capture.
+pub fn actions_from_pcap(
+    capture: &[u8], key_log: Option<&str>, config: &Config,
+) -> Result<Vec<Action>> {
+    let capture = capture::parse(capture)?;
+
+    let mut secrets = parse_key_log(key_log.unwrap_or_default());
+    secrets.extend(parse_key_log(&capture.key_log));
+
+    let (client, server) =
+        find_connection(&capture.datagrams, config.server_port)?;
+
+    log::info!("converting connection from {client} to {server}");
+
+    let datagrams: Vec<&Datagram> = capture
+        .datagrams
+        .iter()
+        .filter(|d| {
+            (d.from == client && d.to == server) ||
+                (d.from == server && d.to == client)
+        })
+        .collect();
+
+    let handshake = Handshake::new(&datagrams, client);
+
+    let secrets = match handshake.client_random {
+        Some(random) => secrets.get(&random[..]),
+
+        // Without the ClientHello, a key log for a single connection can still
+        // be used.
+        None if secrets.len() == 1 => secrets.values().next(),
+
+        None => None,
+    }
+    .ok_or(Error::MissingSecrets)?;
+
+    let dcid_len = handshake.server_cid_len.ok_or_else(|| {
+        Error::InvalidCapture("no packets from the server".into())
+    })?;
+
+    let alg = match handshake.alg {
+        Some(v) => v,
+
+        None => {
+            log::warn!(
+                "no ServerHello in the capture, guessing the cipher suite"
+            );
+
+            match secrets.traffic.as_ref().map(|s| s.len()) {
+                Some(48) => quic::Algorithm::AES256_GCM,
+
+                _ => quic::Algorithm::AES128_GCM,
+            }
+        },
+    };
+
+    let early = secrets
+        .early
+        .as_ref()
+        .map(|s| quic::Open::from_secret(alg, s))
+        .transpose()?;
+
+    let mut keys = secrets
+        .traffic
+        .as_ref()
+        .map(|s| quic::Open::from_secret(alg, s).map(AppKeys::new))
+        .transpose()?;
+
+    if keys.is_none() {
+        log::warn!("no 1-RTT secret in the key log");
+    }
+
+    let mut largest_pn = None;
+    let mut sent = SentData::default();
+
+    for d in datagrams.iter().filter(|d| d.from == client) {
+        let mut buf = d.payload.clone();
+        let mut off = 0;
+
+        while off < buf.len() {
+            let hdr = quic::Header::parse(&buf[off..], dcid_len);
+
+            let (ty, pn_off, len) = match hdr {
+                Ok(hdr) => (hdr.ty, hdr.pn_off, hdr.len),
+
+                Err(e) => {
+                    log::debug!("skipping rest of datagram: {e}");
+                    break;
+                },
+            };
+
+            let pkt = &mut buf[off..off + len];
+            off += len;
+
+            let payload = match ty {
+                quic::Type::ZeroRTT => match early.as_ref() {
+                    Some(open) => open_packet(pkt, pn_off, largest_pn, open),
+
+                    None => continue,
+                },
+
+                quic::Type::Short => match keys.as_mut() {
+                    Some(keys) => keys.open_packet(pkt, pn_off, largest_pn),
+
+                    None => continue,
+                },
+
+                _ => continue,
+            };
+
+            let (pn, payload) = match payload {
+                Ok(v) => v,
+
+                Err(e) => {
+                    log::warn!("skipping {ty:?} packet: {e}");
+                    continue;
+                },
+            };
+
+            largest_pn = largest_pn.max(Some(pn));
+
+            let frames = match quic::parse_frames(&payload) {
+                Ok(v) => v,
+
+                Err(e) => {
+                    log::warn!("skipping packet {pn}: {e}");
+                    continue;
+                },
+            };
+
+            for frame in frames {
+                sent.record(frame, d.time);
+            }
+        }
+    }
+
+    Ok(sent.into_actions(config.min_wait))
+}
+
+/// Returns the client and server addresses of the first connection.
+fn find_connection(
+    datagrams: &[Datagram], server_port: Option<u16>,
+) -> Result<(SocketAddr, SocketAddr)> {
+    for d in datagrams {
+        if server_port.is_some_and(|port| d.to.port() != port) ||
+            d.payload.len() < MIN_CLIENT_INITIAL_LEN
+        {
+            continue;
+        }
+
+        let Ok(hdr) = quic::Header::parse(&d.payload, 0) else {
+            continue;
+        };
+
+        if hdr.ty != quic::Type::Initial {
+            continue;
+        }
+
+        if hdr.version != quic::PROTOCOL_VERSION_V1 {
+            log::warn!(
+                "skipping connection from {} with version {:#x}",
+                d.from,
+                hdr.version
+            );
+            continue;
+        }
+
+        return Ok((d.from, d.to));
+    }
+
+    Err(Error::NoConnection)
+}
+
+/// The client secrets logged for a connection.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct ClientSecrets {
+    early: Option<Vec<u8>>,
+    traffic: Option<Vec<u8>>,
+}
+
+/// Parses a key log in the NSS format, keyed on the client random.
+fn parse_key_log(key_log: &str) -> HashMap<Vec<u8>, ClientSecrets> {
+    let mut secrets: HashMap<Vec<u8>, ClientSecrets> = HashMap::new();
+
+    for line in key_log.lines() {
+        let mut fields = line.split_whitespace();
+
+        let (Some(label), Some(random), Some(secret)) =
+            (fields.next(), fields.next(), fields.next())
+        else {
+            continue;
+        };
+
+        let (Some(random), Some(secret)) =
+            (hex_decode(random), hex_decode(secret))
+        else {
+            continue;
+        };
+
+        match label {
+            "CLIENT_EARLY_TRAFFIC_SECRET" =>
+                secrets.entry(random).or_default().early = Some(secret),
+
+            "CLIENT_TRAFFIC_SECRET_0" =>
+                secrets.entry(random).or_default().traffic = Some(secret),
+
+            _ => (),
+        }
+    }
+
+    secrets
+}
+
+/// What is learned from the Initial packets of a connection.
+#[derive(Debug, Default)]
+struct Handshake {
+    client_random: Option<[u8; 32]>,
+    alg: Option<quic::Algorithm>,
+    /// The length of the connection IDs chosen by the server.
+    server_cid_len: Option<usize>,
+}
+
+impl Handshake {
+    fn new(datagrams: &[&Datagram], client: SocketAddr) -> Self {
+        let mut hs = Handshake::default();
+
+        let mut client_hello = CryptoStream::default();
+        let mut server_hello = CryptoStream::default();
+
+        // The Initial keys are derived from the destination connection ID of
+        // the client's latest Initial packet, which changes after a Retry.
+        let mut dcid = Vec::new();
+        let mut largest_pn = [None, None];
+
+        for d in datagrams {
+            let from_client = d.from == client;
+
+            let mut buf = d.payload.clone();
+            let mut off = 0;
+
+            while off < buf.len() {
+                let Ok(hdr) = quic::Header::parse(&buf[off..], 0) else {
+                    break;
+                };
+
+                if hdr.ty == quic::Type::Short {
+                    break;
+                }
+
+                if !from_client && hs.server_cid_len.is_none() {
+                    hs.server_cid_len = Some(hdr.scid.len());
+                }
+
+                if from_client && hdr.ty == quic::Type::Initial {
+                    dcid = hdr.dcid.to_vec();
+                }
+
+                let (ty, pn_off, len) = (hdr.ty, hdr.pn_off, hdr.len);
+
+                let pkt = &mut buf[off..off + len];
+                off += len;
+
+                if ty != quic::Type::Initial {
+                    continue;
+                }
+
+                let largest_pn = &mut largest_pn[usize::from(!from_client)];
+
+                let res =
+                    quic::Open::initial(&dcid, !from_client).and_then(|open| {
+                        open_packet(pkt, pn_off, *largest_pn, &open)
+                    });
+
+                let (pn, payload) = match res {
+                    Ok(v) => v,
+
+                    Err(e) => {
+                        log::debug!("skipping Initial packet: {e}");
+                        continue;
+                    },
+                };
+
+                *largest_pn = (*largest_pn).max(Some(pn));
+
+                for frame in quic::parse_frames(&payload).unwrap_or_default() {
+                    if let quic::Frame::Crypto { offset, data } = frame {
+                        if from_client {
+                            client_hello.push(offset, data);
+                        } else {
+                            server_hello.push(offset, data);
+                        }
+                    }
+                }
+            }
+
+            hs.client_random = hs
+                .client_random
+                .or_else(|| quic::client_hello_random(&client_hello.buf));
+
+            hs.alg = hs.alg.or_else(|| {
+                quic::server_hello_cipher_suite(&server_hello.buf)
+                    .and_then(quic::Algorithm::from_cipher_suite)
+            });
+
+            if hs.client_random.is_some() &&
+                hs.alg.is_some() &&
+                hs.server_cid_len.is_some()
+            {
+                break;
+            }
+        }
+
+        hs
+    }
+}
+
+/// Reassembles CRYPTO frames.
+#[derive(Debug, Default)]
+struct CryptoStream {
+    /// The contiguous data from offset 0.
+    buf: Vec<u8>,
+    /// Data received past the end of `buf`, keyed on offset.
+    pending: BTreeMap<u64, Vec<u8>>,
+}
+
+impl CryptoStream {
+    fn push(&mut self, offset: u64, data: &[u8]) {
+        self.pending.insert(offset, data.to_vec());
+
+        while let Some(entry) = self.pending.first_entry() {
+            let start = *entry.key() as usize;
+
+            if start > self.buf.len() {
+                break;
+            }
+
+            let data = entry.remove();
+
+            if start + data.len() > self.buf.len() {
+                self.buf.extend_from_slice(&data[self.buf.len() - start..]);
+            }
+        }
+    }
+}
+
+/// The 1-RTT keys of the client, across key updates.
+struct AppKeys {
+    /// The keys of each key phase, in order. The header protection key of the
+    /// first one is used throughout.
+    generations: Vec<quic::Open>,
+}
+
+impl AppKeys {
+    fn new(open: quic::Open) -> Self {
+        Self {
+            generations: vec![open],
+        }
+    }
+
+    fn open_packet(
+        &mut self, pkt: &mut [u8], pn_off: usize, largest_pn: Option<u64>,
+    ) -> Result<(u64, Vec<u8>)> {
+        let u = quic::remove_header_protection(
+            pkt,
+            pn_off,
+            largest_pn,
+            &self.generations[0],
+        )?;
+
+        let last = self.generations.len() - 1;
+
+        if u.key_phase == (last % 2 == 1) {
+            return open_payload(pkt, &u, &self.generations[last]);
+        }
+
+        // The packet was either sent before the latest key update, or after a
+        // new one.
+        if last > 0 {
+            if let Ok(v) = open_payload(pkt, &u, &self.generations[last - 1]) {
+                return Ok(v);
+            }
+        }
+
+        let next = self.generations[last].next()?;
+        let res = open_payload(pkt, &u, &next)?;
+
+        self.generations.push(next);
+
+        Ok(res)
+    }
+}
+
+/// Removes the protection of a packet, and returns its packet number and
+/// decrypted payload.
+fn open_packet(
+    pkt: &mut [u8], pn_off: usize, largest_pn: Option<u64>, open: &quic::Open,
+) -> Result<(u64, Vec<u8>)> {
+    let u = quic::remove_header_protection(pkt, pn_off, largest_pn, open)?;
+
+    open_payload(pkt, &u, open)
+}
+
+fn open_payload(
+    pkt: &[u8], u: &quic::Unprotected, open: &quic::Open,
+) -> Result<(u64, Vec<u8>)> {
+    let (header, payload) = pkt.split_at(u.header_len);
+
+    let mut payload = payload.to_vec();
+    let len = open.open(u.pn, header, &mut payload)?;
+
+    payload.truncate(len);
+
+    Ok((u.pn, payload))
+}
+
+/// What the client sent, with the capture time of each part.
+#[derive(Debug, Default)]
+struct SentData {
+    /// The client-initiated streams.
+    streams: BTreeMap<u64, SentStream>,
+    /// The actions not carrying stream data.
+    actions: Vec<(Duration, Action)>,
+    reset_streams: HashSet<u64>,
+    stopped_streams: HashSet<u64>,
+    closed: bool,
+}
+
+impl SentData {
+    fn record(&mut self, frame: quic::Frame, time: Duration) {
+        match frame {
+            quic::Frame::Stream {
+                stream_id,
+                offset,
+                data,
+                fin,
+            } if stream_id & 0x1 == 0 => self
+                .streams
+                .entry(stream_id)
+                .or_default()
+                .push(offset, data, fin, time),
+
+            quic::Frame::ResetStream {
+                stream_id,
+                error_code,
+            } if self.reset_streams.insert(stream_id) =>
+                self.actions.push((time, Action::ResetStream {
+                    stream_id,
+                    error_code,
+                })),
+
+            quic::Frame::StopSending {
+                stream_id,
+                error_code,
+            } if self.stopped_streams.insert(stream_id) =>
+                self.actions.push((time, Action::StopSending {
+                    stream_id,
+                    error_code,
+                })),
+
+            quic::Frame::ConnectionClose {
+                is_app,
+                error_code,
+                reason,
+            } if !self.closed => {
+                self.closed = true;
+
+                self.actions.push((time, Action::ConnectionClose {
+                    error: ConnectionError {
+                        is_app,
+                        error_code,
+                        reason: reason.to_vec(),
+                    },
+                }));
+            },
+
+            _ => (),
+        }
+    }
+
+    /// Orders the actions by capture time, with waits in between.
+    fn into_actions(self, min_wait: Duration) -> Vec<Action> {
+        let mut timed = Vec::new();
+
+        // Stream data goes first, so that a stream is never reset before its
+        // data was sent.
+        for (stream_id, stream) in &self.streams {
+            stream.actions(*stream_id, min_wait, &mut timed);
+        }
+
+        timed.extend(self.actions);
+        timed.sort_by_key(|(time, _)| *time);
+
+        let mut actions = Vec::with_capacity(timed.len());
+        let mut last_time = None;
+
+        for (time, action) in timed {
+            if let Some(last_time) = last_time {
+                let gap = time.saturating_sub(last_time);
+
+                if !gap.is_zero() && gap >= min_wait {
+                    actions.push(Action::Wait {
+                        wait_type: WaitType::WaitDuration(gap),
+                    });
+                }
+            }
+
+            last_time = Some(time);
+            actions.push(action);
+        }
+
+        actions
+    }
+}
+
+/// The data sent on a stream.
+#[derive(Debug, Default)]
+struct SentStream {
+    chunks: Vec<Chunk>,
+    /// The final size of the stream, and when the FIN bit was sent.
+    fin: Option<(u64, Duration)>,
+}
+
+#[derive(Debug)]
+struct Chunk {
+    offset: u64,
+    data: Vec<u8>,
+    time: Duration,
+}
+
+impl SentStream {
+    fn push(&mut self, offset: u64, data: &[u8], fin: bool, time: Duration) {
+        let end = offset + data.len() as u64;
+
+        if fin && self.fin.is_none() {
+            self.fin = Some((end, time));
+        }
+
+        // Skip retransmissions.
+        let retransmitted = self
+            .chunks
+            .iter()
+            .any(|c| c.offset <= offset && end <= c.offset + c.data.len() as u64);
+
+        if data.is_empty() || retransmitted {
+            return;
+        }
+
+        self.chunks.push(Chunk {
+            offset,
+            data: data.to_vec(),
+            time,
+        });
+    }
+
+    /// Returns the contiguous data from the start of the stream.
+    fn data(&self) -> Vec<u8> {
+        let mut chunks: Vec<&Chunk> = self.chunks.iter().collect();
+        chunks.sort_by_key(|c| c.offset);
+
+        let mut data = Vec::new();
+
+        for c in chunks {
+            let start = c.offset as usize;
+
+            if start > data.len() {
+                break;
+            }
+
+            if start + c.data.len() > data.len() {
+                data.extend_from_slice(&c.data[data.len() - start..]);
+            }
+        }
+
+        data
+    }
+
+    /// Returns when the byte at `offset` was first sent.
+    fn time_at(&self, offset: u64) -> Option<Duration> {
+        self.chunks
+            .iter()
+            .filter(|c| {
+                c.offset <= offset && offset < c.offset + c.data.len() as u64
+            })
+            .map(|c| c.time)
+            .min()
+    }
+
+    /// Converts the stream data into actions, appended to `out` with their
+    /// capture time.
+    fn actions(
+        &self, stream_id: u64, min_wait: Duration,
+        out: &mut Vec<(Duration, Action)>,
+    ) {
+        let data = self.data();
+
+        if self.chunks.iter().any(|c| c.offset > data.len() as u64) {
+            log::warn!("stream {stream_id} is missing data from the capture");
+        }
+
+        // The actions, keyed on the offset of their first byte.
+        let mut actions = Vec::new();
+        let mut off = 0;
+
+        if stream_id & 0x2 != 0 {
+            let mut b = octets::Octets::with_slice(&data);
+
+            if let Ok(stream_type) = b.get_varint() {
+                actions.push((0, Action::OpenUniStream {
+                    stream_id,
+                    fin_stream: false,
+                    stream_type,
+                }));
+
+                off = b.off();
+
+                // Only the control stream carries HTTP/3 frames.
+                if stream_type != HTTP3_CONTROL_STREAM_TYPE_ID && off < data.len()
+                {
+                    actions.push((off, Action::StreamBytes {
+                        stream_id,
+                        fin_stream: false,
+                        bytes: data[off..].to_vec(),
+                    }));
+
+                    off = data.len();
+                }
+            }
+        }
+
+        while off < data.len() {
+            let mut b = octets::Octets::with_slice(&data[off..]);
+
+            let (Ok(ty), Ok(len)) = (b.get_varint(), b.get_varint()) else {
+                break;
+            };
+
+            let Ok(payload) = b.get_bytes(len as usize) else {
+                break;
+            };
+
+            let end = off + b.off();
+
+            actions.push((
+                off,
+                frame_action(stream_id, ty, payload.buf(), &data[off..end]),
+            ));
+
+            off = end;
+        }
+
+        // A truncated frame.
+        if off < data.len() {
+            actions.push((off, Action::StreamBytes {
+                stream_id,
+                fin_stream: false,
+                bytes: data[off..].to_vec(),
+            }));
+        }
+
+        let first = out.len();
+        let mut last_time = Duration::ZERO;
+
+        for (off, action) in actions {
+            // Keep the actions of the stream in order, even if the capture
+            // isn't.
+            let time =
+                self.time_at(off as u64).unwrap_or(last_time).max(last_time);
+            last_time = time;
+
+            // Action files record stream bytes and DATA payloads as text.
+            let bytes = match &action {
+                Action::StreamBytes { bytes, .. } |
+                Action::SendFrame {
+                    frame: Frame::Data { payload: bytes },
+                    ..
+                } => Some(bytes),
+
+                _ => None,
+            };
+
+            if bytes.is_some_and(|b| std::str::from_utf8(b).is_err()) {
+                log::warn!(
+                    "stream {stream_id}: binary data at offset {off} can't be recorded in an action file"
+                );
+            }
+
+            out.push((time, action));
+        }
+
+        let Some((final_size, fin_time)) = self.fin else {
+            return;
+        };
+
+        if final_size != data.len() as u64 {
+            return;
+        }
+
+        // Set the FIN bit on the last action, unless it was sent noticeably
+        // later.
+        match out[first..].last_mut() {
+            Some((time, action)) if fin_time.saturating_sub(*time) < min_wait =>
+                set_fin(action),
+
+            _ => out.push((fin_time.max(last_time), Action::StreamBytes {
+                stream_id,
+                fin_stream: true,
+                bytes: vec![],
+            })),
+        }
+    }
+}
+
+/// Converts an HTTP/3 frame into the action sending it. `raw` is the whole
+/// frame, which is sent as is if it can't be parsed.
+fn frame_action(stream_id: u64, ty: u64, payload: &[u8], raw: &[u8]) -> Action {
+    let frame = match Frame::from_bytes(ty, payload.len() as u64, payload) {
+        Ok(v) => v,
+
+        Err(e) => {
+            log::warn!(
+                "stream {stream_id}: can't parse frame of type {ty:#x}, sending its bytes as is: {e:?}"
+            );
+
+            return Action::StreamBytes {
+                stream_id,
+                fin_stream: false,
+                bytes: raw.to_vec(),
+            };
+        },
+    };
+
+    let Frame::Headers { header_block } = frame else {
+        return Action::SendFrame {
+            stream_id,
+            fin_stream: false,
+            frame,
+        };
+    };
+
+    let mut decoder = quiche::h3::qpack::Decoder::new();
+
+    match decoder.decode(&header_block, u64::MAX) {
+        Ok(headers)
+            if headers
+                .iter()
+                .any(|h| h.name().iter().any(u8::is_ascii_uppercase)) =>
+            send_headers_frame_literal(stream_id, false, headers),
+
+        Ok(headers) => send_headers_frame(stream_id, false, headers),
+
+        // E.g. when the client used the QPACK dynamic table.
+        Err(e) => {
+            log::warn!(
+                "stream {stream_id}: can't decode header block, the replayed headers will be empty: {e:?}"
+            );
+
+            Action::SendFrame {
+                stream_id,
+                fin_stream: false,
+                frame: Frame::Headers { header_block },
+            }
+        },
+    }
+}
+
+fn set_fin(action: &mut Action) {
+    match action {
+        Action::SendFrame { fin_stream, .. } |
+        Action::SendHeadersFrame { fin_stream, .. } |
+        Action::StreamBytes { fin_stream, .. } |
+        Action::OpenUniStream { fin_stream, .. } => *fin_stream = true,
+
+        _ => (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quiche::h3::Header;
+
+    fn ms(v: u64) -> Duration {
+        Duration::from_millis(v)
+    }
+
+    fn frame_bytes(frame: &Frame) -> Vec<u8> {
+        let mut buf = [0; 1024];
+        let mut b = octets::OctetsMut::with_slice(&mut buf);
+
+        let len = frame.to_bytes(&mut b).unwrap();
+
+        buf[..len].to_vec()
+    }
+
+    fn stream(
+        stream_id: u64, offset: u64, data: &[u8], fin: bool,
+    ) -> quic::Frame<'_> {
+        quic::Frame::Stream {
+            stream_id,
+            offset,
+            data,
+            fin,
+        }
+    }
+
+    #[test]
+    fn key_log() {
+        let key_log = "\
+            # comment\n\
+            CLIENT_HANDSHAKE_TRAFFIC_SECRET 0a0b 0102\n\
+            CLIENT_TRAFFIC_SECRET_0 0a0b 0304\n\
+            CLIENT_EARLY_TRAFFIC_SECRET 0c0d 0506\n\
+            SERVER_TRAFFIC_SECRET_0 0a0b 0708\n\
+            CLIENT_TRAFFIC_SECRET_0 0e0f 0x\n";
+
+        let secrets = parse_key_log(key_log);
+
+        assert_eq!(secrets.len(), 2);
+        assert_eq!(secrets[&vec![0x0a, 0x0b]], ClientSecrets {
+            early: None,
+            traffic: Some(vec![0x03, 0x04]),
+        });
+        assert_eq!(secrets[&vec![0x0c, 0x0d]], ClientSecrets {
+            early: Some(vec![0x05, 0x06]),
+            traffic: None,
+        });
+    }
+
+    #[test]
+    fn stream_reassembly() {
+        let mut stream = SentStream::default();
+
+        stream.push(3, b"def", false, ms(2));
+        stream.push(0, b"abc", false, ms(1));
+        // A retransmission.
+        stream.push(0, b"abc", false, ms(3));
+        stream.push(10, b"xyz", true, ms(4));
+
+        assert_eq!(stream.data(), b"abcdef");
+        assert_eq!(stream.time_at(4), Some(ms(2)));
+        assert_eq!(stream.time_at(6), None);
+        assert_eq!(stream.fin, Some((13, ms(4))));
+    }
+
+    #[test]
+    fn request() {
+        let headers = vec![
+            Header::new(b":method", b"POST"),
+            Header::new(b":path", b"/"),
+        ];
+
+        let headers_frame = Frame::Headers {
+            header_block: crate::encode_header_block(&headers).unwrap(),
+        };
+
+        let data_frame = Frame::Data {
+            payload: b"body".to_vec(),
+        };
+
+        let mut data = frame_bytes(&headers_frame);
+        let headers_len = data.len() as u64;
+        data.extend(frame_bytes(&data_frame));
+
+        let mut sent = SentData::default();
+
+        // The control stream and its SETTINGS frame.
+        sent.record(stream(2, 0, &[0x00, 0x04, 0x00], false), ms(0));
+
+        sent.record(stream(0, 0, &data[..headers_len as usize], false), ms(10));
+        sent.record(
+            stream(0, headers_len, &data[headers_len as usize..], false),
+            ms(10),
+        );
+        sent.record(stream(0, data.len() as u64, b"", true), ms(50));
+
+        sent.record(
+            quic::Frame::ConnectionClose {
+                is_app: true,
+                error_code: 0x100,
+                reason: b"bye",
+            },
+            ms(60),
+        );
+
+        let actions = sent.into_actions(ms(1));
+
+        assert_eq!(actions, vec![
+            Action::OpenUniStream {
+                stream_id: 2,
+                fin_stream: false,
+                stream_type: HTTP3_CONTROL_STREAM_TYPE_ID,
+            },
+            Action::SendFrame {
+                stream_id: 2,
+                fin_stream: false,
+                frame: Frame::Settings {
+                    max_field_section_size: None,
+                    qpack_max_table_capacity: None,
+                    qpack_blocked_streams: None,
+                    connect_protocol_enabled: None,
+                    h3_datagram: None,
+                    grease: None,
+                    raw: Some(vec![]),
+                    additional_settings: None,
+                },
+            },
+            Action::Wait {
+                wait_type: WaitType::WaitDuration(ms(10)),
+            },
+            send_headers_frame(0, false, headers),
+            Action::SendFrame {
+                stream_id: 0,
+                fin_stream: false,
+                frame: data_frame,
+            },
+            Action::Wait {
+                wait_type: WaitType::WaitDuration(ms(40)),
+            },
+            Action::StreamBytes {
+                stream_id: 0,
+                fin_stream: true,
+                bytes: vec![],
+            },
+            Action::Wait {
+                wait_type: WaitType::WaitDuration(ms(10)),
+            },
+            Action::ConnectionClose {
+                error: ConnectionError {
+                    is_app: true,
+                    error_code: 0x100,
+                    reason: b"bye".to_vec(),
+                },
+            },
+        ]);
+    }
+
+    #[test]
+    fn fin_on_last_frame() {
+        let data = frame_bytes(&Frame::Data {
+            payload: b"body".to_vec(),
+        });
+
+        let mut sent = SentData::default();
+        sent.record(stream(4, 0, &data, true), ms(0));
+
+        // A truncated frame.
+        sent.record(stream(8, 0, &data[..3], false), ms(0));
+
+        assert_eq!(sent.into_actions(ms(1)), vec![
+            Action::SendFrame {
+                stream_id: 4,
+                fin_stream: true,
+                frame: Frame::Data {
+                    payload: b"body".to_vec(),
+                },
+            },
+            Action::StreamBytes {
+                stream_id: 8,
+                fin_stream: false,
+                bytes: data[..3].to_vec(),
+            },
+        ]);
+    }
+}
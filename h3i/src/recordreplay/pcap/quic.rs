@@ -0,0 +1,764 @@
+// Copyright (C) 2025, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Removal of QUIC v1 packet protection, and parsing of the frames needed to
+//! reconstruct what a client sent.
+
+use ring::aead;
+use ring::aead::quic as hp;
+use ring::hkdf;
+
+use super::Error;
+use super::Result;
+
+/// The QUIC version 1 protocol version.
+pub const PROTOCOL_VERSION_V1: u32 = 0x0000_0001;
+
+const INITIAL_SALT_V1: [u8; 20] = [
+    0x38, 0x76, 0x2c, 0xf7, 0xf5, 0x59, 0x34, 0xb3, 0x4d, 0x17, 0x9a, 0xe6, 0xa4,
+    0xc8, 0x0c, 0xad, 0xcc, 0xbb, 0x7f, 0x0a,
+];
+
+const FORM_BIT: u8 = 0x80;
+const KEY_PHASE_BIT: u8 = 0x04;
+
+const MAX_PKT_NUM_LEN: usize = 4;
+const SAMPLE_LEN: usize = 16;
+
+/// A QUIC packet type.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Type {
+    Initial,
+    ZeroRTT,
+    Handshake,
+    Retry,
+    VersionNegotiation,
+    Short,
+}
+
+/// The header of a QUIC packet, still protected.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Header<'a> {
+    pub ty: Type,
+    pub version: u32,
+    pub dcid: &'a [u8],
+    pub scid: &'a [u8],
+    /// The offset of the packet number.
+    pub pn_off: usize,
+    /// The length of the packet, including the header. Retry and Version
+    /// Negotiation packets span the whole datagram.
+    pub len: usize,
+}
+
+impl<'a> Header<'a> {
+    /// Parses the header of the first packet in `buf`.
+    ///
+    /// `dcid_len` is the length of the destination connection ID of short
+    /// header packets.
+    pub fn parse(buf: &'a [u8], dcid_len: usize) -> Result<Header<'a>> {
+        let mut b = octets::Octets::with_slice(buf);
+
+        let first = b.get_u8().map_err(invalid)?;
+
+        if first & FORM_BIT == 0 {
+            let dcid = b.get_bytes(dcid_len).map_err(invalid)?.buf();
+
+            return Ok(Header {
+                ty: Type::Short,
+                version: PROTOCOL_VERSION_V1,
+                dcid,
+                scid: &[],
+                pn_off: b.off(),
+                len: buf.len(),
+            });
+        }
+
+        let version = b.get_u32().map_err(invalid)?;
+        let dcid = b.get_bytes_with_u8_length().map_err(invalid)?.buf();
+        let scid = b.get_bytes_with_u8_length().map_err(invalid)?.buf();
+
+        let ty = if version == 0 {
+            Type::VersionNegotiation
+        } else {
+            match (first & 0x30) >> 4 {
+                0x00 => Type::Initial,
+                0x01 => Type::ZeroRTT,
+                0x02 => Type::Handshake,
+                _ => Type::Retry,
+            }
+        };
+
+        if matches!(ty, Type::VersionNegotiation | Type::Retry) {
+            return Ok(Header {
+                ty,
+                version,
+                dcid,
+                scid,
+                pn_off: b.off(),
+                len: buf.len(),
+            });
+        }
+
+        if ty == Type::Initial {
+            b.get_bytes_with_varint_length().map_err(invalid)?;
+        }
+
+        let len = b.get_varint().map_err(invalid)? as usize;
+        let pn_off = b.off();
+
+        if len > b.cap() {
+            return Err(Error::InvalidPacket("packet length too large".into()));
+        }
+
+        Ok(Header {
+            ty,
+            version,
+            dcid,
+            scid,
+            pn_off,
+            len: pn_off + len,
+        })
+    }
+}
+
+/// An AEAD algorithm negotiated for a connection.
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Algorithm {
+    AES128_GCM,
+    AES256_GCM,
+    ChaCha20_Poly1305,
+}
+
+impl Algorithm {
+    /// Returns the algorithm of a TLS 1.3 cipher suite.
+    pub fn from_cipher_suite(cipher_suite: u16) -> Option<Self> {
+        match cipher_suite {
+            0x1301 => Some(Algorithm::AES128_GCM),
+            0x1302 => Some(Algorithm::AES256_GCM),
+            0x1303 => Some(Algorithm::ChaCha20_Poly1305),
+
+            _ => None,
+        }
+    }
+
+    fn aead(self) -> &'static aead::Algorithm {
+        match self {
+            Algorithm::AES128_GCM => &aead::AES_128_GCM,
+            Algorithm::AES256_GCM => &aead::AES_256_GCM,
+            Algorithm::ChaCha20_Poly1305 => &aead::CHACHA20_POLY1305,
+        }
+    }
+
+    fn hp(self) -> &'static hp::Algorithm {
+        match self {
+            Algorithm::AES128_GCM => &hp::AES_128,
+            Algorithm::AES256_GCM => &hp::AES_256,
+            Algorithm::ChaCha20_Poly1305 => &hp::CHACHA20,
+        }
+    }
+
+    fn hkdf(self) -> hkdf::Algorithm {
+        match self {
+            Algorithm::AES256_GCM => hkdf::HKDF_SHA384,
+
+            _ => hkdf::HKDF_SHA256,
+        }
+    }
+}
+
+/// Packet protection keys, derived from a TLS secret.
+pub struct Open {
+    alg: Algorithm,
+    secret: Vec<u8>,
+    key: aead::LessSafeKey,
+    iv: Vec<u8>,
+    hp_key: Vec<u8>,
+    hp: hp::HeaderProtectionKey,
+}
+
+impl Open {
+    /// Derives the keys of the Initial packets sent by the client, or by the
+    /// server, from the client's first destination connection ID.
+    pub fn initial(dcid: &[u8], is_server: bool) -> Result<Self> {
+        let initial_secret =
+            hkdf::Salt::new(hkdf::HKDF_SHA256, &INITIAL_SALT_V1).extract(dcid);
+
+        let label: &[u8] = if is_server {
+            b"server in"
+        } else {
+            b"client in"
+        };
+
+        let mut secret = [0; 32];
+        hkdf_expand_label(&initial_secret, label, &mut secret)?;
+
+        Self::from_secret(Algorithm::AES128_GCM, &secret)
+    }
+
+    /// Derives the keys for a TLS traffic secret.
+    pub fn from_secret(alg: Algorithm, secret: &[u8]) -> Result<Self> {
+        let mut hp_key = vec![0; alg.hp().key_len()];
+        let prk = hkdf::Prk::new_less_safe(alg.hkdf(), secret);
+
+        hkdf_expand_label(&prk, b"quic hp", &mut hp_key)?;
+
+        Self::with_hp_key(alg, secret, hp_key)
+    }
+
+    fn with_hp_key(
+        alg: Algorithm, secret: &[u8], hp_key: Vec<u8>,
+    ) -> Result<Self> {
+        let mut key = vec![0; alg.aead().key_len()];
+        let mut iv = vec![0; aead::NONCE_LEN];
+        let prk = hkdf::Prk::new_less_safe(alg.hkdf(), secret);
+
+        hkdf_expand_label(&prk, b"quic key", &mut key)?;
+        hkdf_expand_label(&prk, b"quic iv", &mut iv)?;
+
+        let key = aead::UnboundKey::new(alg.aead(), &key)
+            .map_err(|_| Error::CryptoFail)?;
+
+        let hp = hp::HeaderProtectionKey::new(alg.hp(), &hp_key)
+            .map_err(|_| Error::CryptoFail)?;
+
+        Ok(Open {
+            alg,
+            secret: secret.to_vec(),
+            key: aead::LessSafeKey::new(key),
+            iv,
+            hp_key,
+            hp,
+        })
+    }
+
+    /// Derives the keys of the next key phase. The header protection key
+    /// doesn't change.
+    pub fn next(&self) -> Result<Self> {
+        let mut secret = vec![0; self.secret.len()];
+        let prk = hkdf::Prk::new_less_safe(self.alg.hkdf(), &self.secret);
+
+        hkdf_expand_label(&prk, b"quic ku", &mut secret)?;
+
+        Self::with_hp_key(self.alg, &secret, self.hp_key.clone())
+    }
+
+    /// Decrypts the payload of a packet in place, and returns its length.
+    pub fn open(
+        &self, pn: u64, header: &[u8], payload: &mut [u8],
+    ) -> Result<usize> {
+        let mut nonce = [0; aead::NONCE_LEN];
+        nonce.copy_from_slice(&self.iv);
+
+        for (n, pn) in nonce[4..].iter_mut().zip(pn.to_be_bytes()) {
+            *n ^= pn;
+        }
+
+        let plain = self
+            .key
+            .open_in_place(
+                aead::Nonce::assume_unique_for_key(nonce),
+                aead::Aad::from(header),
+                payload,
+            )
+            .map_err(|_| Error::CryptoFail)?;
+
+        Ok(plain.len())
+    }
+}
+
+/// A packet whose header protection was removed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Unprotected {
+    pub pn: u64,
+    pub key_phase: bool,
+    /// The length of the header, including the packet number.
+    pub header_len: usize,
+}
+
+/// Removes the header protection of the packet in `pkt`, whose packet number
+/// starts at `pn_off`, given the largest packet number received so far in its
+/// packet number space.
+pub fn remove_header_protection(
+    pkt: &mut [u8], pn_off: usize, largest_pn: Option<u64>, open: &Open,
+) -> Result<Unprotected> {
+    let sample_off = pn_off + MAX_PKT_NUM_LEN;
+
+    let sample = pkt
+        .get(sample_off..sample_off + SAMPLE_LEN)
+        .ok_or_else(|| Error::InvalidPacket("packet too short".into()))?;
+
+    let mask = open.hp.new_mask(sample).map_err(|_| Error::CryptoFail)?;
+
+    if pkt[0] & FORM_BIT != 0 {
+        pkt[0] ^= mask[0] & 0x0f;
+    } else {
+        pkt[0] ^= mask[0] & 0x1f;
+    }
+
+    let pn_len = usize::from(pkt[0] & 0x03) + 1;
+    let mut truncated_pn = 0;
+
+    for i in 0..pn_len {
+        pkt[pn_off + i] ^= mask[1 + i];
+        truncated_pn = (truncated_pn << 8) | u64::from(pkt[pn_off + i]);
+    }
+
+    Ok(Unprotected {
+        pn: decode_pkt_num(largest_pn, truncated_pn, pn_len),
+        key_phase: pkt[0] & FORM_BIT == 0 && pkt[0] & KEY_PHASE_BIT != 0,
+        header_len: pn_off + pn_len,
+    })
+}
+
+/// Decodes a truncated packet number, see RFC 9000 Appendix A.3.
+fn decode_pkt_num(
+    largest_pn: Option<u64>, truncated_pn: u64, pn_len: usize,
+) -> u64 {
+    let expected_pn = largest_pn.map_or(0, |pn| pn + 1);
+    let pn_win = 1 << (pn_len * 8);
+    let pn_hwin = pn_win / 2;
+    let pn_mask = pn_win - 1;
+    let candidate_pn = (expected_pn & !pn_mask) | truncated_pn;
+
+    if candidate_pn + pn_hwin <= expected_pn && candidate_pn < (1 << 62) - pn_win
+    {
+        return candidate_pn + pn_win;
+    }
+
+    if candidate_pn > expected_pn + pn_hwin && candidate_pn >= pn_win {
+        return candidate_pn - pn_win;
+    }
+
+    candidate_pn
+}
+
+/// The QUIC frames relevant to reconstructing what a client sent. Other
+/// frames are parsed and skipped.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Frame<'a> {
+    Crypto {
+        offset: u64,
+        data: &'a [u8],
+    },
+
+    Stream {
+        stream_id: u64,
+        offset: u64,
+        data: &'a [u8],
+        fin: bool,
+    },
+
+    ResetStream {
+        stream_id: u64,
+        error_code: u64,
+    },
+
+    StopSending {
+        stream_id: u64,
+        error_code: u64,
+    },
+
+    ConnectionClose {
+        is_app: bool,
+        error_code: u64,
+        reason: &'a [u8],
+    },
+}
+
+/// Parses the frames of a decrypted packet payload.
+pub fn parse_frames(payload: &[u8]) -> Result<Vec<Frame<'_>>> {
+    let mut b = octets::Octets::with_slice(payload);
+    let mut frames = Vec::new();
+
+    while b.cap() > 0 {
+        let ty = b.get_varint().map_err(invalid)?;
+
+        let frame = match ty {
+            // PADDING, PING and HANDSHAKE_DONE.
+            0x00 | 0x01 | 0x1e => None,
+
+            // ACK.
+            0x02 | 0x03 => {
+                b.get_varint().map_err(invalid)?;
+                b.get_varint().map_err(invalid)?;
+
+                let ranges = b.get_varint().map_err(invalid)?;
+
+                b.get_varint().map_err(invalid)?;
+
+                for _ in 0..ranges * 2 {
+                    b.get_varint().map_err(invalid)?;
+                }
+
+                // ECN counts.
+                if ty == 0x03 {
+                    for _ in 0..3 {
+                        b.get_varint().map_err(invalid)?;
+                    }
+                }
+
+                None
+            },
+
+            0x04 => {
+                let stream_id = b.get_varint().map_err(invalid)?;
+                let error_code = b.get_varint().map_err(invalid)?;
+
+                // Final size.
+                b.get_varint().map_err(invalid)?;
+
+                Some(Frame::ResetStream {
+                    stream_id,
+                    error_code,
+                })
+            },
+
+            0x05 => Some(Frame::StopSending {
+                stream_id: b.get_varint().map_err(invalid)?,
+                error_code: b.get_varint().map_err(invalid)?,
+            }),
+
+            0x06 => Some(Frame::Crypto {
+                offset: b.get_varint().map_err(invalid)?,
+                data: b.get_bytes_with_varint_length().map_err(invalid)?.buf(),
+            }),
+
+            // NEW_TOKEN.
+            0x07 => {
+                b.get_bytes_with_varint_length().map_err(invalid)?;
+
+                None
+            },
+
+            0x08..=0x0f => {
+                let stream_id = b.get_varint().map_err(invalid)?;
+
+                let offset = if ty & 0x04 != 0 {
+                    b.get_varint().map_err(invalid)?
+                } else {
+                    0
+                };
+
+                let len = if ty & 0x02 != 0 {
+                    b.get_varint().map_err(invalid)? as usize
+                } else {
+                    b.cap()
+                };
+
+                Some(Frame::Stream {
+                    stream_id,
+                    offset,
+                    data: b.get_bytes(len).map_err(invalid)?.buf(),
+                    fin: ty & 0x01 != 0,
+                })
+            },
+
+            // MAX_DATA, MAX_STREAMS, DATA_BLOCKED, STREAMS_BLOCKED and
+            // RETIRE_CONNECTION_ID.
+            0x10 | 0x12 | 0x13 | 0x14 | 0x16 | 0x17 | 0x19 => {
+                b.get_varint().map_err(invalid)?;
+
+                None
+            },
+
+            // MAX_STREAM_DATA and STREAM_DATA_BLOCKED.
+            0x11 | 0x15 => {
+                b.get_varint().map_err(invalid)?;
+                b.get_varint().map_err(invalid)?;
+
+                None
+            },
+
+            // NEW_CONNECTION_ID.
+            0x18 => {
+                b.get_varint().map_err(invalid)?;
+                b.get_varint().map_err(invalid)?;
+                b.get_bytes_with_u8_length().map_err(invalid)?;
+                b.skip(16).map_err(invalid)?;
+
+                None
+            },
+
+            // PATH_CHALLENGE and PATH_RESPONSE.
+            0x1a | 0x1b => {
+                b.skip(8).map_err(invalid)?;
+
+                None
+            },
+
+            0x1c | 0x1d => {
+                let error_code = b.get_varint().map_err(invalid)?;
+
+                if ty == 0x1c {
+                    // Frame type.
+                    b.get_varint().map_err(invalid)?;
+                }
+
+                let reason =
+                    b.get_bytes_with_varint_length().map_err(invalid)?.buf();
+
+                Some(Frame::ConnectionClose {
+                    is_app: ty == 0x1d,
+                    error_code,
+                    reason,
+                })
+            },
+
+            // DATAGRAM.
+            0x30 => {
+                b.skip(b.cap()).map_err(invalid)?;
+
+                None
+            },
+
+            0x31 => {
+                b.get_bytes_with_varint_length().map_err(invalid)?;
+
+                None
+            },
+
+            _ =>
+                return Err(Error::InvalidPacket(format!(
+                    "unknown frame type {ty:#x}"
+                ))),
+        };
+
+        frames.extend(frame);
+    }
+
+    Ok(frames)
+}
+
+/// Returns the random of a TLS ClientHello.
+pub fn client_hello_random(crypto: &[u8]) -> Option<[u8; 32]> {
+    let mut b = octets::Octets::with_slice(crypto);
+
+    // Handshake type, length and legacy version.
+    if b.get_u8().ok()? != 0x01 {
+        return None;
+    }
+
+    b.skip(3 + 2).ok()?;
+
+    b.get_bytes(32).ok()?.buf().try_into().ok()
+}
+
+/// Returns the cipher suite of a TLS ServerHello.
+pub fn server_hello_cipher_suite(crypto: &[u8]) -> Option<u16> {
+    let mut b = octets::Octets::with_slice(crypto);
+
+    // Handshake type, length, legacy version, random and session ID.
+    if b.get_u8().ok()? != 0x02 {
+        return None;
+    }
+
+    b.skip(3 + 2 + 32).ok()?;
+    b.get_bytes_with_u8_length().ok()?;
+
+    b.get_u16().ok()
+}
+
+/// HKDF-Expand-Label, see RFC 8446 Section 7.1.
+fn hkdf_expand_label(
+    prk: &hkdf::Prk, label: &[u8], out: &mut [u8],
+) -> Result<()> {
+    const LABEL_PREFIX: &[u8] = b"tls13 ";
+
+    let out_len = (out.len() as u16).to_be_bytes();
+    let label_len = [(LABEL_PREFIX.len() + label.len()) as u8];
+
+    let info = [&out_len[..], &label_len, LABEL_PREFIX, label, &[0][..]];
+
+    prk.expand(&info, ArbitraryOutputLen(out.len()))
+        .map_err(|_| Error::CryptoFail)?
+        .fill(out)
+        .map_err(|_| Error::CryptoFail)
+}
+
+struct ArbitraryOutputLen(usize);
+
+impl hkdf::KeyType for ArbitraryOutputLen {
+    fn len(&self) -> usize {
+        self.0
+    }
+}
+
+fn invalid(_: octets::BufferTooShortError) -> Error {
+    Error::InvalidPacket("truncated packet or frame".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    // RFC 9001 Appendix A.1.
+    #[test]
+    fn initial_keys() {
+        let dcid = hex("8394c8f03e515708");
+
+        let client = Open::initial(&dcid, false).unwrap();
+        assert_eq!(client.iv, hex("fa044b2f42a3fd3b46fb255c"));
+        assert_eq!(client.hp_key, hex("9f50449e04a0e810283a1e9933adedd2"));
+
+        let server = Open::initial(&dcid, true).unwrap();
+        assert_eq!(server.iv, hex("0ac1493ca1905853b0bba03e"));
+        assert_eq!(server.hp_key, hex("c206b8d9b9f0f37644430b490eeaa314"));
+    }
+
+    // RFC 9001 Appendix A.5.
+    #[test]
+    fn chacha20_short_header() {
+        let secret = hex(
+            "9ac312a7f877468ebe69422748ad00a15443f18203a07d6060f688f30f21632b",
+        );
+
+        let open =
+            Open::from_secret(Algorithm::ChaCha20_Poly1305, &secret).unwrap();
+        assert_eq!(open.iv, hex("e0459b3474bdd0e44a41c144"));
+
+        let next = open.next().unwrap();
+        let next_secret = hex(
+            "1223504755036d556342ee9361d253421a826c9ecdf3c7148684b36b714881f9",
+        );
+        assert_eq!(next.secret, next_secret);
+        assert_eq!(next.hp_key, open.hp_key);
+
+        let mut pkt = hex("4cfe4189655e5cd55c41f69080575d7999c25a5bfb");
+
+        let hdr = Header::parse(&pkt, 0).unwrap();
+        assert_eq!(hdr.ty, Type::Short);
+        assert_eq!(hdr.pn_off, 1);
+
+        let u = remove_header_protection(&mut pkt, 1, Some(654360563), &open)
+            .unwrap();
+        assert_eq!(u, Unprotected {
+            pn: 654360564,
+            key_phase: false,
+            header_len: 4,
+        });
+        assert_eq!(&pkt[..4], &hex("4200bff4")[..]);
+
+        let (header, payload) = pkt.split_at_mut(u.header_len);
+        let len = open.open(u.pn, header, payload).unwrap();
+        assert_eq!(&payload[..len], &[0x01]);
+    }
+
+    #[test]
+    fn decode_pkt_num_wraps() {
+        // RFC 9000 Appendix A.3.
+        assert_eq!(decode_pkt_num(Some(0xa82f30ea), 0x9b32, 2), 0xa82f9b32);
+
+        assert_eq!(decode_pkt_num(None, 0, 1), 0);
+        assert_eq!(decode_pkt_num(Some(254), 0x01, 1), 257);
+    }
+
+    #[test]
+    fn parse_long_header() {
+        let mut pkt = vec![0xc3, 0x00, 0x00, 0x00, 0x01];
+        pkt.extend_from_slice(&[4, 1, 2, 3, 4]);
+        pkt.extend_from_slice(&[2, 5, 6]);
+        // Token, length, packet number and payload.
+        pkt.extend_from_slice(&[0, 5, 0, 0, 0, 0, 0xff]);
+        // A coalesced packet.
+        pkt.extend_from_slice(&[0x40]);
+
+        let hdr = Header::parse(&pkt, 0).unwrap();
+        assert_eq!(hdr, Header {
+            ty: Type::Initial,
+            version: PROTOCOL_VERSION_V1,
+            dcid: &[1, 2, 3, 4],
+            scid: &[5, 6],
+            pn_off: 15,
+            len: 20,
+        });
+    }
+
+    #[test]
+    fn parse_stream_frames() {
+        let payload = [
+            // PADDING, PING.
+            0x00, 0x01, // ACK, largest 5, delay 0, 1 range.
+            0x02, 0x05, 0x00, 0x01, 0x00, 0x01, 0x01,
+            // STREAM with offset, length and FIN.
+            0x0f, 0x04, 0x02, 0x03, b'a', b'b', b'c', // RESET_STREAM.
+            0x04, 0x08, 0x10, 0x00, // CONNECTION_CLOSE, application.
+            0x1d, 0x00, 0x02, b'o', b'k',
+            // STREAM, up to the end of the packet.
+            0x08, 0x00, b'x',
+        ];
+
+        assert_eq!(parse_frames(&payload).unwrap(), vec![
+            Frame::Stream {
+                stream_id: 4,
+                offset: 2,
+                data: b"abc",
+                fin: true,
+            },
+            Frame::ResetStream {
+                stream_id: 8,
+                error_code: 0x10,
+            },
+            Frame::ConnectionClose {
+                is_app: true,
+                error_code: 0,
+                reason: b"ok",
+            },
+            Frame::Stream {
+                stream_id: 0,
+                offset: 0,
+                data: b"x",
+                fin: false,
+            },
+        ]);
+
+        assert!(parse_frames(&[0x08, 0x00, 0x01]).is_ok());
+        assert!(parse_frames(&[0x0a, 0x00, 0x05, b'x']).is_err());
+    }
+
+    #[test]
+    fn hellos() {
+        let mut client_hello = vec![0x01, 0x00, 0x00, 0x26, 0x03, 0x03];
+        client_hello.extend_from_slice(&[0xab; 32]);
+
+        assert_eq!(client_hello_random(&client_hello), Some([0xab; 32]));
+        assert_eq!(client_hello_random(&client_hello[..20]), None);
+
+        let mut server_hello = vec![0x02, 0x00, 0x00, 0x28, 0x03, 0x03];
+        server_hello.extend_from_slice(&[0xcd; 32]);
+        server_hello.extend_from_slice(&[0x00, 0x13, 0x03]);
+
+        assert_eq!(server_hello_cipher_suite(&server_hello), Some(0x1303));
+        assert_eq!(server_hello_cipher_suite(&client_hello), None);
+    }
+}
@@ -0,0 +1,296 @@
+// Copyright (C) 2025, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Writing of the UDP datagrams of a connection to a pcapng capture.
+
+use std::io;
+use std::io::Write;
+use std::net::IpAddr;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+const PCAPNG_SECTION_HEADER: u32 = 0x0a0d_0d0a;
+const PCAPNG_BYTE_ORDER_MAGIC: u32 = 0x1a2b_3c4d;
+const PCAPNG_INTERFACE_DESCRIPTION: u32 = 0x0000_0001;
+const PCAPNG_ENHANCED_PACKET: u32 = 0x0000_0006;
+
+const PCAPNG_OPTION_END: u16 = 0;
+const PCAPNG_OPTION_EPB_FLAGS: u16 = 2;
+const PCAPNG_OPTION_IF_TSRESOL: u16 = 9;
+
+/// Timestamps are written in nanoseconds.
+const TSRESOL_NANOS: u8 = 9;
+
+const LINKTYPE_RAW: u16 = 101;
+
+const IPPROTO_UDP: u8 = 17;
+
+const TTL: u8 = 64;
+
+/// The direction of a datagram, relative to the capturing endpoint.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Inbound,
+    Outbound,
+}
+
+impl Direction {
+    /// The direction bits of the pcapng `epb_flags` option.
+    fn epb_flags(self) -> u32 {
+        match self {
+            Direction::Inbound => 0b01,
+            Direction::Outbound => 0b10,
+        }
+    }
+}
+
+/// Writes UDP datagrams to a pcapng capture, so that a connection can be
+/// inspected with e.g. Wireshark.
+///
+/// The datagrams are wrapped in IPv4 or IPv6 headers with the given
+/// addresses, on a raw IP interface, and tagged with their direction.
+pub struct PcapngWriter<W: Write> {
+    out: W,
+}
+
+impl<W: Write> PcapngWriter<W> {
+    /// Creates a writer, writing the capture's section header and interface
+    /// description to `out`.
+    pub fn new(mut out: W) -> io::Result<Self> {
+        let mut shb = Vec::new();
+        shb.extend(PCAPNG_BYTE_ORDER_MAGIC.to_le_bytes());
+        shb.extend(1u16.to_le_bytes());
+        shb.extend(0u16.to_le_bytes());
+        // The section length is unknown.
+        shb.extend((-1i64).to_le_bytes());
+
+        write_block(&mut out, PCAPNG_SECTION_HEADER, &shb)?;
+
+        let mut idb = Vec::new();
+        idb.extend(LINKTYPE_RAW.to_le_bytes());
+        idb.extend(0u16.to_le_bytes());
+        // No snap length.
+        idb.extend(0u32.to_le_bytes());
+        push_option(&mut idb, PCAPNG_OPTION_IF_TSRESOL, &[TSRESOL_NANOS]);
+        push_option(&mut idb, PCAPNG_OPTION_END, &[]);
+
+        write_block(&mut out, PCAPNG_INTERFACE_DESCRIPTION, &idb)?;
+
+        Ok(Self { out })
+    }
+
+    /// Writes a datagram, `time` being its timestamp relative to the UNIX
+    /// epoch.
+    pub fn write_datagram(
+        &mut self, time: Duration, from: SocketAddr, to: SocketAddr,
+        payload: &[u8], direction: Direction,
+    ) -> io::Result<()> {
+        let packet = ip_packet(from, to, payload);
+        let ts = time.as_nanos() as u64;
+
+        let mut epb = Vec::with_capacity(packet.len() + 40);
+        // The interface ID.
+        epb.extend(0u32.to_le_bytes());
+        epb.extend(((ts >> 32) as u32).to_le_bytes());
+        epb.extend((ts as u32).to_le_bytes());
+        epb.extend((packet.len() as u32).to_le_bytes());
+        epb.extend((packet.len() as u32).to_le_bytes());
+        epb.extend(&packet);
+        pad(&mut epb);
+        push_option(
+            &mut epb,
+            PCAPNG_OPTION_EPB_FLAGS,
+            &direction.epb_flags().to_le_bytes(),
+        );
+        push_option(&mut epb, PCAPNG_OPTION_END, &[]);
+
+        write_block(&mut self.out, PCAPNG_ENHANCED_PACKET, &epb)
+    }
+
+    /// Flushes the underlying writer.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/// Writes a block, `body` being already padded to 32 bits.
+fn write_block<W: Write>(out: &mut W, ty: u32, body: &[u8]) -> io::Result<()> {
+    let len = (body.len() as u32 + 12).to_le_bytes();
+
+    out.write_all(&ty.to_le_bytes())?;
+    out.write_all(&len)?;
+    out.write_all(body)?;
+    out.write_all(&len)
+}
+
+fn push_option(buf: &mut Vec<u8>, code: u16, value: &[u8]) {
+    buf.extend(code.to_le_bytes());
+    buf.extend((value.len() as u16).to_le_bytes());
+    buf.extend(value);
+    pad(buf);
+}
+
+fn pad(buf: &mut Vec<u8>) {
+    buf.resize((buf.len() + 3) & !3, 0);
+}
+
+/// Builds the IP packet carrying a UDP datagram. IPv4 addresses are mapped to
+/// IPv6 if the other address is IPv6.
+fn ip_packet(from: SocketAddr, to: SocketAddr, payload: &[u8]) -> Vec<u8> {
+    // Oversized payloads are cut, so that the lengths fit in the headers.
+    let payload = &payload[..payload.len().min(usize::from(u16::MAX) - 48)];
+
+    let udp_len = (payload.len() + 8) as u16;
+
+    let mut udp = Vec::with_capacity(usize::from(udp_len));
+    udp.extend(from.port().to_be_bytes());
+    udp.extend(to.port().to_be_bytes());
+    udp.extend(udp_len.to_be_bytes());
+    udp.extend([0, 0]);
+    udp.extend(payload);
+
+    let mut packet = Vec::with_capacity(udp.len() + 40);
+
+    let pseudo_header = match (from.ip(), to.ip()) {
+        (IpAddr::V4(src), IpAddr::V4(dst)) => {
+            packet.extend([0x45, 0]);
+            packet.extend((udp_len + 20).to_be_bytes());
+            // No ID, and the Don't Fragment flag.
+            packet.extend([0, 0, 0x40, 0, TTL, IPPROTO_UDP, 0, 0]);
+            packet.extend(src.octets());
+            packet.extend(dst.octets());
+
+            let sum = checksum(&[&packet]);
+            packet[10..12].copy_from_slice(&sum.to_be_bytes());
+
+            [&src.octets()[..], &dst.octets(), &[0, IPPROTO_UDP]].concat()
+        },
+
+        (src, dst) => {
+            let src = to_ipv6(src).octets();
+            let dst = to_ipv6(dst).octets();
+
+            packet.extend([0x60, 0, 0, 0]);
+            packet.extend(udp_len.to_be_bytes());
+            packet.extend([IPPROTO_UDP, TTL]);
+            packet.extend(src);
+            packet.extend(dst);
+
+            [&src[..], &dst, &[0, IPPROTO_UDP]].concat()
+        },
+    };
+
+    let sum = match checksum(&[&pseudo_header, &udp_len.to_be_bytes(), &udp]) {
+        // A zero checksum means there is none.
+        0 => 0xffff,
+
+        sum => sum,
+    };
+    udp[6..8].copy_from_slice(&sum.to_be_bytes());
+
+    packet.extend(udp);
+    packet
+}
+
+fn to_ipv6(ip: IpAddr) -> std::net::Ipv6Addr {
+    match ip {
+        IpAddr::V4(ip) => ip.to_ipv6_mapped(),
+
+        IpAddr::V6(ip) => ip,
+    }
+}
+
+/// The Internet checksum of the concatenation of `chunks`. All chunks but the
+/// last one must have an even length.
+fn checksum(chunks: &[&[u8]]) -> u16 {
+    let mut sum: u32 = chunks
+        .iter()
+        .flat_map(|c| c.chunks(2))
+        .map(|w| u32::from(u16::from_be_bytes([w[0], *w.get(1).unwrap_or(&0)])))
+        .sum();
+
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+
+    !(sum as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::recordreplay::pcap::capture;
+    use crate::recordreplay::pcap::capture::Datagram;
+
+    #[test]
+    fn round_trip() {
+        let mut buf = Vec::new();
+        let mut writer = PcapngWriter::new(&mut buf).unwrap();
+
+        let datagrams = vec![
+            Datagram {
+                time: Duration::new(1_700_000_000, 123_456_789),
+                from: "192.0.2.1:5000".parse().unwrap(),
+                to: "192.0.2.2:443".parse().unwrap(),
+                payload: vec![0xc0; 1200],
+            },
+            Datagram {
+                time: Duration::new(1_700_000_001, 1),
+                from: "[2001:db8::2]:443".parse().unwrap(),
+                to: "[2001:db8::1]:5000".parse().unwrap(),
+                payload: vec![0x40; 33],
+            },
+        ];
+
+        for (d, direction) in datagrams
+            .iter()
+            .zip([Direction::Outbound, Direction::Inbound])
+        {
+            writer
+                .write_datagram(d.time, d.from, d.to, &d.payload, direction)
+                .unwrap();
+        }
+
+        let capture = capture::parse(&buf).unwrap();
+        assert_eq!(capture.datagrams, datagrams);
+    }
+
+    #[test]
+    fn checksums() {
+        let packet = ip_packet(
+            "192.0.2.1:5000".parse().unwrap(),
+            "192.0.2.2:443".parse().unwrap(),
+            b"hello",
+        );
+
+        // The checksum of a header including its checksum is zero.
+        assert_eq!(checksum(&[&packet[..20]]), 0);
+
+        let pseudo_header = [&packet[12..20], &[0, IPPROTO_UDP, 0, 13]].concat();
+        assert_eq!(checksum(&[&pseudo_header, &packet[20..]]), 0);
+    }
+}
@@ -25,30 +25,46 @@
 // SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
 
 use std::collections::BTreeMap;
+use std::time::Instant;
 
 use qlog::events::h3::H3FrameCreated;
 use qlog::events::h3::H3Owner;
+use qlog::events::h3::H3PriorityTargetStreamType;
 use qlog::events::h3::H3StreamTypeSet;
 use qlog::events::h3::Http3Frame;
 use qlog::events::h3::HttpHeader;
 use qlog::events::quic::ErrorSpace;
 use qlog::events::quic::PacketSent;
 use qlog::events::quic::QuicFrame;
+use qlog::events::quic::StreamType;
+use qlog::events::security::KeyType;
+use qlog::events::security::KeyUpdateOrRetiredTrigger;
+use qlog::events::security::KeyUpdated;
 use qlog::events::Event;
 use qlog::events::EventData;
 use qlog::events::ExData;
 use qlog::events::JsonEvent;
 use qlog::events::RawInfo;
+use qlog::reader::QlogSeqReader;
+use qlog::streamer::QlogStreamer;
+use qlog::VantagePointType;
 use quiche;
 use quiche::h3::frame::Frame;
 use quiche::h3::NameValue;
 
+use serde::Deserialize;
 use serde_json::json;
 
 use smallvec::smallvec;
 
+use crate::actions::h3::encode_h3_datagram;
 use crate::actions::h3::Action;
+use crate::actions::h3::BodyAssertion;
+use crate::actions::h3::QpackInstruction;
+use crate::actions::h3::RampPolicy;
+use crate::actions::h3::StreamEvent;
 use crate::actions::h3::WaitType;
+use crate::client::expectations::Expectation;
 use crate::encode_header_block;
 use crate::encode_header_block_literal;
 use crate::fake_packet_sent;
@@ -57,6 +73,42 @@ use crate::HTTP3_PUSH_STREAM_TYPE_ID;
 use crate::QPACK_DECODER_STREAM_TYPE_ID;
 use crate::QPACK_ENCODER_STREAM_TYPE_ID;
 
+use super::hex_decode;
+
+/// The name of the JSON event recording [Action::WithholdFlowControlUpdates].
+const H3I_WITHHOLD_FLOW_CONTROL_UPDATES: &str =
+    "h3i:withhold_flow_control_updates";
+
+/// The name of the JSON event recording [Action::AssertBody].
+const H3I_ASSERT_BODY: &str = "h3i:assert_body";
+
+/// The name of the JSON event recording [Action::OpenConcurrentRequests].
+const H3I_OPEN_CONCURRENT_REQUESTS: &str = "h3i:open_concurrent_requests";
+
+/// The name of the JSON event recording [Action::SendQpackInstruction].
+const H3I_QPACK_INSTRUCTION: &str = "h3i:qpack_instruction";
+
+/// The name of the JSON event recording [Action::Migrate].
+const H3I_MIGRATE: &str = "h3i:migrate";
+
+/// The name of the JSON event recording [Action::SnapshotStats].
+const H3I_SNAPSHOT_STATS: &str = "h3i:snapshot_stats";
+
+/// The name of the JSON event recording [Action::ProbePath].
+const H3I_PROBE_PATH: &str = "h3i:probe_path";
+
+/// The name of the JSON event recording [Action::SwitchPath].
+const H3I_SWITCH_PATH: &str = "h3i:switch_path";
+
+/// The name of the JSON event recording [Action::OnFrame].
+const H3I_ON_FRAME: &str = "h3i:on_frame";
+
+/// The name of the JSON event recording [Action::Burst].
+const H3I_BURST: &str = "h3i:burst";
+
+/// The name of the JSON event recording [Action::OpenWebTransportStream].
+const H3I_OPEN_WEBTRANSPORT_STREAM: &str = "h3i:open_webtransport_stream";
+
 /// A qlog event representation using either the official RFC format or the
 /// catch-al JSON event.
 pub enum QlogEvent {
@@ -87,9 +139,22 @@ impl From<&Action> for QlogEvents {
                 fin_stream,
                 frame,
             } => {
+                // Record the DATA payload, so that it can be replayed.
+                let qlog_frame = match frame {
+                    Frame::Data { payload } => Http3Frame::Data {
+                        raw: Some(RawInfo {
+                            length: Some(payload.len() as u64),
+                            payload_length: Some(payload.len() as u64),
+                            data: String::from_utf8(payload.clone()).ok(),
+                        }),
+                    },
+
+                    _ => frame.to_qlog(),
+                };
+
                 let frame_ev = EventData::H3FrameCreated(H3FrameCreated {
                     stream_id: *stream_id,
-                    frame: frame.to_qlog(),
+                    frame: qlog_frame,
                     ..Default::default()
                 });
 
@@ -204,7 +269,7 @@ impl From<&Action> for QlogEvents {
                     raw: Some(RawInfo {
                         length: Some(len),
                         payload_length: Some(len),
-                        data: String::from_utf8(bytes.clone()).ok()
+                        data: qlog::HexSlice::maybe_string(Some(bytes)),
                     })
                 }]));
 
@@ -253,7 +318,8 @@ impl From<&Action> for QlogEvents {
                 let name = "h3i:wait".into();
 
                 let data = match wait_type {
-                    d @ WaitType::WaitDuration(_) =>
+                    d @ (WaitType::WaitDuration(_) |
+                    WaitType::ConnectionEvent(_)) =>
                         serde_json::to_value(d).unwrap(),
                     WaitType::StreamEvent(event) =>
                         serde_json::to_value(event).unwrap(),
@@ -297,6 +363,286 @@ impl From<&Action> for QlogEvents {
                 }]
             },
 
+            Action::MaxData { max } => {
+                let ev = fake_packet_sent(Some(smallvec![QuicFrame::MaxData {
+                    maximum: *max,
+                }]));
+
+                vec![QlogEvent::Event {
+                    data: Box::new(ev),
+                    ex_data: BTreeMap::new(),
+                }]
+            },
+
+            Action::MaxStreamData { stream_id, max } => {
+                let ev =
+                    fake_packet_sent(Some(smallvec![QuicFrame::MaxStreamData {
+                        stream_id: *stream_id,
+                        maximum: *max,
+                    }]));
+
+                vec![QlogEvent::Event {
+                    data: Box::new(ev),
+                    ex_data: BTreeMap::new(),
+                }]
+            },
+
+            Action::MaxStreams { bidi, max } => {
+                let stream_type = if *bidi {
+                    StreamType::Bidirectional
+                } else {
+                    StreamType::Unidirectional
+                };
+
+                let ev =
+                    fake_packet_sent(Some(smallvec![QuicFrame::MaxStreams {
+                        stream_type,
+                        maximum: *max,
+                    }]));
+
+                vec![QlogEvent::Event {
+                    data: Box::new(ev),
+                    ex_data: BTreeMap::new(),
+                }]
+            },
+
+            Action::Ping => {
+                let ev = fake_packet_sent(Some(smallvec![QuicFrame::Ping {
+                    length: None,
+                    payload_length: None,
+                }]));
+
+                vec![QlogEvent::Event {
+                    data: Box::new(ev),
+                    ex_data: BTreeMap::new(),
+                }]
+            },
+
+            Action::Padding { len } => {
+                let ev = fake_packet_sent(Some(smallvec![QuicFrame::Padding {
+                    length: None,
+                    payload_length: *len as u32,
+                }]));
+
+                vec![QlogEvent::Event {
+                    data: Box::new(ev),
+                    ex_data: BTreeMap::new(),
+                }]
+            },
+
+            Action::SendDatagram {
+                quarter_stream_id,
+                payload,
+            } => {
+                let dgram = encode_h3_datagram(*quarter_stream_id, payload);
+
+                let ev = fake_packet_sent(Some(smallvec![QuicFrame::Datagram {
+                    length: dgram.len() as u64,
+                    raw: qlog::HexSlice::maybe_string(Some(&dgram)),
+                }]));
+
+                vec![QlogEvent::Event {
+                    data: Box::new(ev),
+                    ex_data: BTreeMap::new(),
+                }]
+            },
+
+            Action::UpdateKeys => {
+                let ev = EventData::KeyUpdated(KeyUpdated {
+                    key_type: KeyType::Client1RttSecret,
+                    trigger: Some(KeyUpdateOrRetiredTrigger::LocalUpdate),
+                    ..Default::default()
+                });
+
+                vec![QlogEvent::Event {
+                    data: Box::new(ev),
+                    ex_data: BTreeMap::new(),
+                }]
+            },
+
+            Action::RawQuicFrame { bytes } => {
+                let raw_frame_type =
+                    octets::Octets::with_slice(bytes).get_varint().unwrap_or(0);
+
+                let ev = fake_packet_sent(Some(smallvec![QuicFrame::Unknown {
+                    raw_frame_type,
+                    frame_type_value: None,
+                    raw: Some(RawInfo {
+                        length: Some(bytes.len() as u64),
+                        payload_length: None,
+                        data: qlog::HexSlice::maybe_string(Some(bytes)),
+                    }),
+                }]));
+
+                vec![QlogEvent::Event {
+                    data: Box::new(ev),
+                    ex_data: BTreeMap::new(),
+                }]
+            },
+
+            // qlog has no notion of repetition, so the repeated actions are
+            // recorded as if they were sent one after the other.
+            Action::Repeat { count, actions } => (0..*count)
+                .flat_map(|_| actions.iter().flat_map(QlogEvents::from))
+                .collect(),
+
+            Action::OnFrame {
+                matcher,
+                then,
+                otherwise,
+            } => {
+                vec![QlogEvent::JsonEvent(qlog::events::JsonEvent {
+                    time: 0.0,
+                    importance: qlog::events::EventImportance::Core,
+                    name: H3I_ON_FRAME.into(),
+                    data: json!({
+                        "matcher": matcher,
+                        "then": nested_events(then),
+                        "otherwise": nested_events(otherwise),
+                    }),
+                })]
+            },
+
+            Action::Migrate {
+                local_addr,
+                nat_rebinding,
+            } => {
+                vec![QlogEvent::JsonEvent(qlog::events::JsonEvent {
+                    time: 0.0,
+                    importance: qlog::events::EventImportance::Core,
+                    name: H3I_MIGRATE.into(),
+                    data: json!({
+                        "local_addr": local_addr,
+                        "nat_rebinding": nat_rebinding,
+                    }),
+                })]
+            },
+
+            Action::ProbePath { local_addr } => {
+                vec![QlogEvent::JsonEvent(qlog::events::JsonEvent {
+                    time: 0.0,
+                    importance: qlog::events::EventImportance::Core,
+                    name: H3I_PROBE_PATH.into(),
+                    data: json!({
+                        "local_addr": local_addr,
+                    }),
+                })]
+            },
+
+            Action::SwitchPath { path } => {
+                vec![QlogEvent::JsonEvent(qlog::events::JsonEvent {
+                    time: 0.0,
+                    importance: qlog::events::EventImportance::Core,
+                    name: H3I_SWITCH_PATH.into(),
+                    data: json!({
+                        "path": path,
+                    }),
+                })]
+            },
+
+            Action::OpenWebTransportStream {
+                stream_id,
+                session_id,
+                bidi,
+            } => {
+                vec![QlogEvent::JsonEvent(qlog::events::JsonEvent {
+                    time: 0.0,
+                    importance: qlog::events::EventImportance::Core,
+                    name: H3I_OPEN_WEBTRANSPORT_STREAM.into(),
+                    data: json!({
+                        "stream_id": stream_id,
+                        "session_id": session_id,
+                        "bidi": bidi,
+                    }),
+                })]
+            },
+
+            Action::SendQpackInstruction {
+                stream_id,
+                instruction,
+            } => {
+                vec![QlogEvent::JsonEvent(qlog::events::JsonEvent {
+                    time: 0.0,
+                    importance: qlog::events::EventImportance::Core,
+                    name: H3I_QPACK_INSTRUCTION.into(),
+                    data: json!({
+                        "stream_id": stream_id,
+                        "instruction": instruction,
+                    }),
+                })]
+            },
+
+            Action::WithholdFlowControlUpdates { withhold } => {
+                vec![QlogEvent::JsonEvent(qlog::events::JsonEvent {
+                    time: 0.0,
+                    importance: qlog::events::EventImportance::Core,
+                    name: H3I_WITHHOLD_FLOW_CONTROL_UPDATES.into(),
+                    data: json!({ "withhold": withhold }),
+                })]
+            },
+
+            Action::Burst { packets } => {
+                vec![QlogEvent::JsonEvent(qlog::events::JsonEvent {
+                    time: 0.0,
+                    importance: qlog::events::EventImportance::Core,
+                    name: H3I_BURST.into(),
+                    data: json!({ "packets": packets }),
+                })]
+            },
+
+            Action::OpenConcurrentRequests {
+                first_stream_id,
+                count,
+                headers,
+                ramp,
+            } => {
+                let qlog_headers: Vec<HttpHeader> = headers
+                    .iter()
+                    .map(|h| HttpHeader {
+                        name: String::from_utf8_lossy(h.name()).into_owned(),
+                        value: String::from_utf8_lossy(h.value()).into_owned(),
+                    })
+                    .collect();
+
+                vec![QlogEvent::JsonEvent(qlog::events::JsonEvent {
+                    time: 0.0,
+                    importance: qlog::events::EventImportance::Core,
+                    name: H3I_OPEN_CONCURRENT_REQUESTS.into(),
+                    data: json!({
+                        "first_stream_id": first_stream_id,
+                        "count": count,
+                        "headers": qlog_headers,
+                        "ramp": ramp,
+                    }),
+                })]
+            },
+
+            Action::AssertBody {
+                stream_id,
+                assertion,
+            } => {
+                vec![QlogEvent::JsonEvent(qlog::events::JsonEvent {
+                    time: 0.0,
+                    importance: qlog::events::EventImportance::Core,
+                    name: H3I_ASSERT_BODY.into(),
+                    data: json!({
+                        "stream_id": stream_id,
+                        "assertion": assertion,
+                    }),
+                })]
+            },
+
+            Action::SnapshotStats { label } => {
+                vec![QlogEvent::JsonEvent(qlog::events::JsonEvent {
+                    time: 0.0,
+                    importance: qlog::events::EventImportance::Core,
+                    name: H3I_SNAPSHOT_STATS.into(),
+                    data: json!({
+                        "label": label,
+                    }),
+                })]
+            },
+
             Action::FlushPackets => {
                 vec![]
             },
@@ -312,6 +658,10 @@ pub fn actions_from_qlog(event: Event, host_override: Option<&str>) -> H3Actions
             actions.extend(packet_actions.0);
         },
 
+        EventData::H3FrameCreated(fc) if !is_replayable(&fc.frame) => {
+            log::debug!("skipping frame that can't be replayed: {:?}", fc);
+        },
+
         EventData::H3FrameCreated(fc) => {
             let mut frame_created = H3FrameCreatedEx {
                 frame_created: fc.clone(),
@@ -329,7 +679,52 @@ pub fn actions_from_qlog(event: Event, host_override: Option<&str>) -> H3Actions
             actions.push(frame_created.into());
         },
 
-        EventData::H3StreamTypeSet(st) => {
+        // Streams opened by the peer are logged too, but not by h3i.
+        EventData::H3StreamTypeSet(st) if st.owner != Some(H3Owner::Remote) => {
+            let stream_actions = from_qlog_stream_type_set(st, &event.ex_data);
+            actions.extend(stream_actions);
+        },
+
+        EventData::KeyUpdated(KeyUpdated {
+            key_type: KeyType::Client1RttSecret,
+            trigger: Some(KeyUpdateOrRetiredTrigger::LocalUpdate),
+            ..
+        }) => actions.push(Action::UpdateKeys),
+
+        _ => (),
+    }
+
+    H3Actions(actions)
+}
+
+/// Converts a qlog [Event] logged by a server into the actions the client
+/// took, using the frames and streams the server received.
+fn actions_from_server_qlog(
+    event: Event, host_override: Option<&str>,
+) -> H3Actions {
+    let mut actions = vec![];
+    match &event.data {
+        EventData::H3FrameParsed(fp) if is_replayable(&fp.frame) => {
+            let mut frame_created = H3FrameCreatedEx {
+                frame_created: H3FrameCreated {
+                    stream_id: fp.stream_id,
+                    length: fp.length,
+                    frame: fp.frame.clone(),
+                    raw: fp.raw.clone(),
+                },
+                ex_data: event.ex_data.clone(),
+            };
+
+            if let Some(host) = host_override {
+                frame_created
+                    .ex_data
+                    .insert("host_override".into(), host.into());
+            }
+
+            actions.push(frame_created.into());
+        },
+
+        EventData::H3StreamTypeSet(st) if st.owner == Some(H3Owner::Remote) => {
             let stream_actions = from_qlog_stream_type_set(st, &event.ex_data);
             actions.extend(stream_actions);
         },
@@ -340,13 +735,195 @@ pub fn actions_from_qlog(event: Event, host_override: Option<&str>) -> H3Actions
     H3Actions(actions)
 }
 
+/// Reads a qlog written by h3i, quiche or another implementation, and
+/// reconstructs the HTTP/3 frames sent by the client as actions.
+///
+/// Both the JSON-SEQ and the JSON qlog formats are supported. Client traces
+/// are converted from the frames and streams the client created, and server
+/// traces from the ones the server received. Events that can't be represented
+/// as actions are skipped.
+///
+/// Only h3i records which frames end a stream. For traces written by other
+/// implementations, the last frame sent on each request stream is assumed to
+/// end it.
+pub fn actions_from_qlog_reader<R: std::io::BufRead + Send + Sync>(
+    mut reader: R, host_override: Option<&str>,
+) -> Result<Vec<Action>, Box<dyn std::error::Error>> {
+    let mut actions = vec![];
+    let mut has_fin = false;
+
+    let convert =
+        |event: Event, vantage_point: &VantagePointType| match vantage_point {
+            VantagePointType::Server =>
+                actions_from_server_qlog(event, host_override),
+            _ => actions_from_qlog(event, host_override),
+        };
+
+    // JSON-SEQ records start with the ASCII record separator.
+    if reader.fill_buf()?.first() == Some(&0x1e) {
+        let qlog_reader = QlogSeqReader::new(Box::new(reader))?;
+        let vantage_point = qlog_reader.qlog.trace.vantage_point.ty.clone();
+
+        for event in qlog_reader {
+            match event {
+                qlog::reader::Event::Qlog(ev) => {
+                    has_fin |= ev.ex_data.contains_key("fin_stream");
+                    actions.extend(convert(ev, &vantage_point).0);
+                },
+
+                qlog::reader::Event::Json(ev) => {
+                    has_fin = true;
+                    let ac: H3Actions = ev.into();
+                    actions.extend(ac.0);
+                },
+            }
+        }
+    } else {
+        let qlog: serde_json::Value = serde_json::from_reader(reader)?;
+        let traces = qlog
+            .get("traces")
+            .and_then(|t| t.as_array())
+            .ok_or_else(|| std::io::Error::other("qlog has no traces"))?;
+
+        for trace in traces {
+            let vantage_point = trace
+                .get("vantage_point")
+                .and_then(|vp| vp.get("type"))
+                .and_then(|ty| VantagePointType::deserialize(ty).ok())
+                .unwrap_or(VantagePointType::Unknown);
+
+            let events = trace.get("events").and_then(|e| e.as_array());
+
+            // Events of other schemas are skipped.
+            for event in events.into_iter().flatten() {
+                if let Ok(ev) = Event::deserialize(event) {
+                    has_fin |= ev.ex_data.contains_key("fin_stream");
+                    actions.extend(convert(ev, &vantage_point).0);
+                }
+            }
+        }
+    }
+
+    if !has_fin {
+        end_request_streams(&mut actions);
+    }
+
+    Ok(actions)
+}
+
+/// Sets `fin_stream` on the last frame sent on each client-initiated
+/// bidirectional stream.
+fn end_request_streams(actions: &mut [Action]) {
+    let mut ended = std::collections::HashSet::new();
+
+    for action in actions.iter_mut().rev() {
+        match action {
+            Action::SendFrame {
+                stream_id,
+                fin_stream,
+                ..
+            } |
+            Action::SendHeadersFrame {
+                stream_id,
+                fin_stream,
+                ..
+            } if *stream_id % 4 == 0 && ended.insert(*stream_id) =>
+                *fin_stream = true,
+
+            _ => (),
+        }
+    }
+}
+
+/// Whether an HTTP/3 frame logged in a qlog can be converted into an action.
+fn is_replayable(frame: &Http3Frame) -> bool {
+    matches!(
+        frame,
+        Http3Frame::Data { .. } |
+            Http3Frame::Headers { .. } |
+            Http3Frame::CancelPush { .. } |
+            Http3Frame::Settings { .. } |
+            Http3Frame::Goaway { .. } |
+            Http3Frame::MaxPushId { .. } |
+            Http3Frame::PriorityUpdate { .. }
+    )
+}
+
+/// Makes a [QlogStreamer] recording h3i actions to `writer`.
+pub fn make_streamer(
+    writer: Box<dyn std::io::Write + Send + Sync>,
+) -> QlogStreamer {
+    let vp = qlog::VantagePointType::Client;
+
+    let trace = qlog::TraceSeq::new(
+        qlog::VantagePoint {
+            name: None,
+            ty: vp,
+            flow: None,
+        },
+        Some("h3i".into()),
+        Some("h3i".into()),
+        Some(qlog::Configuration {
+            time_offset: Some(0.0),
+            original_uris: None,
+        }),
+        None,
+    );
+
+    let mut streamer = QlogStreamer::new(
+        qlog::QLOG_VERSION.to_string(),
+        Some("h3i".into()),
+        Some("h3i".into()),
+        None,
+        Instant::now(),
+        trace,
+        qlog::events::EventImportance::Extra,
+        writer,
+    );
+
+    streamer.start_log().ok();
+
+    streamer
+}
+
+/// Records `actions` to `streamer`, so that they can be replayed with
+/// [actions_from_qlog].
+pub fn record_actions(streamer: &mut QlogStreamer, actions: &[Action]) {
+    for action in actions {
+        let events: QlogEvents = action.into();
+        for event in events {
+            match event {
+                QlogEvent::Event { data, ex_data } => {
+                    streamer.add_event_data_ex_now(*data, ex_data).ok();
+                },
+
+                QlogEvent::JsonEvent(mut ev) => {
+                    // need to rewrite the event time
+                    ev.time = Instant::now()
+                        .duration_since(streamer.start_time())
+                        .as_secs_f32() *
+                        1000.0;
+                    streamer.add_event(ev).ok();
+                },
+            }
+        }
+    }
+}
+
 impl From<JsonEvent> for H3Actions {
     fn from(event: JsonEvent) -> Self {
         let mut actions = vec![];
         match event.name.as_ref() {
             "h3i:wait" => {
+                // Stream event waits are recorded as the bare event.
                 let wait_type =
-                    serde_json::from_value::<WaitType>(event.clone().data);
+                    serde_json::from_value::<WaitType>(event.clone().data)
+                        .or_else(|_| {
+                            serde_json::from_value::<StreamEvent>(
+                                event.clone().data,
+                            )
+                            .map(WaitType::StreamEvent)
+                        });
 
                 if let Ok(wt) = wait_type {
                     actions.push(Action::Wait { wait_type: wt });
@@ -354,7 +931,185 @@ impl From<JsonEvent> for H3Actions {
                     log::debug!("couldn't create action from event: {:?}", event);
                 }
             },
-            _ => unimplemented!(),
+            H3I_WITHHOLD_FLOW_CONTROL_UPDATES => {
+                if let Some(withhold) =
+                    event.data.get("withhold").and_then(|v| v.as_bool())
+                {
+                    actions.push(Action::WithholdFlowControlUpdates { withhold });
+                } else {
+                    log::debug!("couldn't create action from event: {:?}", event);
+                }
+            },
+            H3I_BURST => {
+                if let Some(packets) =
+                    event.data.get("packets").and_then(|v| v.as_u64())
+                {
+                    actions.push(Action::Burst {
+                        packets: packets as usize,
+                    });
+                } else {
+                    log::debug!("couldn't create action from event: {:?}", event);
+                }
+            },
+            H3I_OPEN_CONCURRENT_REQUESTS => {
+                let first_stream_id =
+                    event.data.get("first_stream_id").and_then(|v| v.as_u64());
+                let count = event.data.get("count").and_then(|v| v.as_u64());
+                let headers = event.data.get("headers").and_then(|v| {
+                    serde_json::from_value::<Vec<HttpHeader>>(v.clone()).ok()
+                });
+                let ramp = event.data.get("ramp").and_then(|v| {
+                    serde_json::from_value::<RampPolicy>(v.clone()).ok()
+                });
+
+                if let (
+                    Some(first_stream_id),
+                    Some(count),
+                    Some(headers),
+                    Some(ramp),
+                ) = (first_stream_id, count, headers, ramp)
+                {
+                    let headers =
+                        headers.iter().map(|h| map_header(h, None)).collect();
+
+                    actions.push(Action::OpenConcurrentRequests {
+                        first_stream_id,
+                        count,
+                        headers,
+                        ramp,
+                    });
+                } else {
+                    log::debug!("couldn't create action from event: {:?}", event);
+                }
+            },
+            H3I_MIGRATE => {
+                let local_addr = event
+                    .data
+                    .get("local_addr")
+                    .map(|v| serde_json::from_value(v.clone()));
+                let nat_rebinding =
+                    event.data.get("nat_rebinding").and_then(|v| v.as_bool());
+
+                if let (Some(Ok(local_addr)), Some(nat_rebinding)) =
+                    (local_addr, nat_rebinding)
+                {
+                    actions.push(Action::Migrate {
+                        local_addr,
+                        nat_rebinding,
+                    });
+                } else {
+                    log::debug!("couldn't create action from event: {:?}", event);
+                }
+            },
+            H3I_PROBE_PATH => {
+                let local_addr = event
+                    .data
+                    .get("local_addr")
+                    .map(|v| serde_json::from_value(v.clone()));
+
+                if let Some(Ok(local_addr)) = local_addr {
+                    actions.push(Action::ProbePath { local_addr });
+                } else {
+                    log::debug!("couldn't create action from event: {:?}", event);
+                }
+            },
+            H3I_SWITCH_PATH => {
+                let path = event.data.get("path").and_then(|v| v.as_u64());
+
+                if let Some(path) = path {
+                    actions.push(Action::SwitchPath {
+                        path: path as usize,
+                    });
+                } else {
+                    log::debug!("couldn't create action from event: {:?}", event);
+                }
+            },
+            H3I_ON_FRAME => {
+                let matcher = event.data.get("matcher").and_then(|v| {
+                    serde_json::from_value::<Expectation>(v.clone()).ok()
+                });
+                let then = event.data.get("then").and_then(nested_actions);
+                let otherwise =
+                    event.data.get("otherwise").and_then(nested_actions);
+
+                if let (Some(matcher), Some(then), Some(otherwise)) =
+                    (matcher, then, otherwise)
+                {
+                    actions.push(Action::OnFrame {
+                        matcher,
+                        then,
+                        otherwise,
+                    });
+                } else {
+                    log::debug!("couldn't create action from event: {:?}", event);
+                }
+            },
+            H3I_OPEN_WEBTRANSPORT_STREAM => {
+                let stream_id =
+                    event.data.get("stream_id").and_then(|v| v.as_u64());
+                let session_id =
+                    event.data.get("session_id").and_then(|v| v.as_u64());
+                let bidi = event.data.get("bidi").and_then(|v| v.as_bool());
+
+                if let (Some(stream_id), Some(session_id), Some(bidi)) =
+                    (stream_id, session_id, bidi)
+                {
+                    actions.push(Action::OpenWebTransportStream {
+                        stream_id,
+                        session_id,
+                        bidi,
+                    });
+                } else {
+                    log::debug!("couldn't create action from event: {:?}", event);
+                }
+            },
+            H3I_QPACK_INSTRUCTION => {
+                let stream_id =
+                    event.data.get("stream_id").and_then(|v| v.as_u64());
+                let instruction = event.data.get("instruction").and_then(|v| {
+                    serde_json::from_value::<QpackInstruction>(v.clone()).ok()
+                });
+
+                if let (Some(stream_id), Some(instruction)) =
+                    (stream_id, instruction)
+                {
+                    actions.push(Action::SendQpackInstruction {
+                        stream_id,
+                        instruction,
+                    });
+                } else {
+                    log::debug!("couldn't create action from event: {:?}", event);
+                }
+            },
+            H3I_SNAPSHOT_STATS => {
+                let label = event.data.get("label").and_then(|v| v.as_str());
+
+                if let Some(label) = label {
+                    actions.push(Action::SnapshotStats {
+                        label: label.to_string(),
+                    });
+                } else {
+                    log::debug!("couldn't create action from event: {:?}", event);
+                }
+            },
+            H3I_ASSERT_BODY => {
+                let stream_id =
+                    event.data.get("stream_id").and_then(|v| v.as_u64());
+                let assertion = event.data.get("assertion").and_then(|v| {
+                    serde_json::from_value::<BodyAssertion>(v.clone()).ok()
+                });
+
+                if let (Some(stream_id), Some(assertion)) = (stream_id, assertion)
+                {
+                    actions.push(Action::AssertBody {
+                        stream_id,
+                        assertion,
+                    });
+                } else {
+                    log::debug!("couldn't create action from event: {:?}", event);
+                }
+            },
+            _ => log::debug!("skipping unknown event: {:?}", event),
         }
 
         Self(actions)
@@ -413,10 +1168,86 @@ impl From<&PacketSent> for H3Actions {
                         })
                     },
 
-                    QuicFrame::Stream { stream_id, fin, .. } => {
+                    QuicFrame::MaxData { maximum } =>
+                        actions.push(Action::MaxData { max: *maximum }),
+
+                    QuicFrame::MaxStreamData { stream_id, maximum } => actions
+                        .push(Action::MaxStreamData {
+                            stream_id: *stream_id,
+                            max: *maximum,
+                        }),
+
+                    QuicFrame::MaxStreams {
+                        stream_type,
+                        maximum,
+                    } => actions.push(Action::MaxStreams {
+                        bidi: matches!(stream_type, StreamType::Bidirectional),
+                        max: *maximum,
+                    }),
+
+                    QuicFrame::Ping { .. } => actions.push(Action::Ping),
+
+                    QuicFrame::Datagram { raw: Some(raw), .. } => {
+                        let dgram = hex_decode(raw).unwrap_or_default();
+                        let mut b = octets::Octets::with_slice(&dgram);
+
+                        match b.get_varint() {
+                            Ok(quarter_stream_id) =>
+                                actions.push(Action::SendDatagram {
+                                    quarter_stream_id,
+                                    payload: b.to_vec(),
+                                }),
+
+                            Err(_) => log::debug!(
+                                "couldn't create action from datagram: {}",
+                                raw
+                            ),
+                        }
+                    },
+
+                    QuicFrame::Padding { payload_length, .. } =>
+                        actions.push(Action::Padding {
+                            len: *payload_length as usize,
+                        }),
+
+                    QuicFrame::Unknown {
+                        raw:
+                            Some(RawInfo {
+                                data: Some(data), ..
+                            }),
+                        ..
+                    } => match hex_decode(data) {
+                        Some(bytes) =>
+                            actions.push(Action::RawQuicFrame { bytes }),
+
+                        None => log::debug!(
+                            "couldn't create action from raw frame: {}",
+                            data
+                        ),
+                    },
+
+                    QuicFrame::Stream {
+                        stream_id,
+                        fin,
+                        raw,
+                        ..
+                    } => {
                         let fin = fin.unwrap_or_default();
 
-                        if fin {
+                        // Only h3i records the stream data, packets sent by
+                        // quiche itself just carry the FIN bit.
+                        let bytes = raw
+                            .as_ref()
+                            .and_then(|raw| raw.data.as_deref())
+                            .and_then(hex_decode);
+
+                        if let Some(bytes) = bytes {
+                            actions.push(Action::StreamBytes {
+                                stream_id: *stream_id,
+                                fin_stream: fin,
+                                bytes,
+                            });
+                        } else if fin {
                             actions.push(Action::StreamBytes {
                                 stream_id: *stream_id,
                                 fin_stream: true,
@@ -536,12 +1367,12 @@ impl From<H3FrameCreatedEx> for Action {
             Http3Frame::Data { raw } => {
                 let mut payload = vec![];
                 if let Some(r) = raw {
-                    payload = r
-                        .data
-                        .clone()
-                        .unwrap_or("".to_string())
-                        .as_bytes()
-                        .to_vec();
+                    // Implementations that don't log the body usually log
+                    // its length, so replay the same amount of data.
+                    payload = match &r.data {
+                        Some(data) => data.as_bytes().to_vec(),
+                        None => vec![0; r.payload_length.unwrap_or(0) as usize],
+                    };
                 }
 
                 Action::SendFrame {
@@ -557,6 +1388,48 @@ impl From<H3FrameCreatedEx> for Action {
                 frame: Frame::GoAway { id: *id },
             },
 
+            Http3Frame::CancelPush { push_id } => Action::SendFrame {
+                stream_id,
+                fin_stream,
+                frame: Frame::CancelPush { push_id: *push_id },
+            },
+
+            Http3Frame::MaxPushId { push_id } => Action::SendFrame {
+                stream_id,
+                fin_stream,
+                frame: Frame::MaxPushId { push_id: *push_id },
+            },
+
+            Http3Frame::PriorityUpdate {
+                target_stream_type,
+                prioritized_element_id,
+                priority_field_value,
+            } => {
+                let prioritized_element_id = *prioritized_element_id;
+                let priority_field_value =
+                    priority_field_value.as_bytes().to_vec();
+
+                let frame = match target_stream_type {
+                    H3PriorityTargetStreamType::Request =>
+                        Frame::PriorityUpdateRequest {
+                            prioritized_element_id,
+                            priority_field_value,
+                        },
+
+                    H3PriorityTargetStreamType::Push =>
+                        Frame::PriorityUpdatePush {
+                            prioritized_element_id,
+                            priority_field_value,
+                        },
+                };
+
+                Action::SendFrame {
+                    stream_id,
+                    fin_stream,
+                    frame,
+                }
+            },
+
             _ => unimplemented!(),
         };
 
@@ -564,6 +1437,41 @@ impl From<H3FrameCreatedEx> for Action {
     }
 }
 
+/// Serializes the events recording `actions`, for actions that contain other
+/// actions.
+fn nested_events(actions: &[Action]) -> serde_json::Value {
+    let events: Vec<serde_json::Value> = actions
+        .iter()
+        .flat_map(QlogEvents::from)
+        .filter_map(|event| match event {
+            QlogEvent::Event { data, ex_data } =>
+                serde_json::to_value(Event::with_time_ex(0.0, *data, ex_data))
+                    .ok(),
+
+            QlogEvent::JsonEvent(ev) => serde_json::to_value(ev).ok(),
+        })
+        .collect();
+
+    json!(events)
+}
+
+/// Parses back the actions serialized by [nested_events].
+fn nested_actions(events: &serde_json::Value) -> Option<Vec<Action>> {
+    let mut actions = vec![];
+
+    for event in events.as_array()? {
+        // Same as reading a qlog file, try native events first.
+        if let Ok(ev) = serde_json::from_value::<Event>(event.clone()) {
+            actions.extend(actions_from_qlog(ev, None).0);
+        } else {
+            let ev = serde_json::from_value::<JsonEvent>(event.clone()).ok()?;
+            actions.extend(H3Actions::from(ev).0);
+        }
+    }
+
+    Some(actions)
+}
+
 fn from_qlog_stream_type_set(
     st: &H3StreamTypeSet, ex_data: &ExData,
 ) -> Vec<Action> {
@@ -600,7 +1508,7 @@ fn parse_ex_data(ex_data: &ExData) -> bool {
 
 #[cfg(test)]
 mod tests {
-    use crate::actions::h3::StreamEvent;
+    use crate::actions::h3::ConnectionEvent;
     use crate::actions::h3::StreamEventType;
     use crate::encode_header_block_literal;
     use std::time::Duration;
@@ -666,6 +1574,24 @@ mod tests {
         assert_eq!(&serialized.unwrap(), expected);
     }
 
+    #[test]
+    fn ser_data_bytes_wait() {
+        let expected = r#"{"time":123.0,"name":"h3i:wait","data":{"stream_id":4,"type":{"data_bytes":100}}}"#;
+        let ev = JsonEvent {
+            time: NOW,
+            importance: qlog::events::EventImportance::Core,
+            name: H3I_WAIT.to_string(),
+            data: serde_json::to_value(StreamEvent {
+                stream_id: 4,
+                event_type: StreamEventType::DataBytes(100),
+            })
+            .unwrap(),
+        };
+
+        let serialized = serde_json::to_string(&ev);
+        assert_eq!(&serialized.unwrap(), expected);
+    }
+
     #[test]
     fn deser_stream_wait() {
         let ev = JsonEvent {
@@ -684,6 +1610,283 @@ mod tests {
         assert_eq!(deser.data, ev.data);
     }
 
+    #[test]
+    fn wait_roundtrip() {
+        let actions = [
+            Action::Wait {
+                wait_type: WaitType::StreamEvent(StreamEvent {
+                    stream_id: 4,
+                    event_type: StreamEventType::Headers,
+                }),
+            },
+            Action::Wait {
+                wait_type: WaitType::ConnectionEvent(ConnectionEvent::GoAway),
+            },
+        ];
+
+        for action in actions {
+            let event = match QlogEvents::from(&action).into_iter().next() {
+                Some(QlogEvent::JsonEvent(ev)) => ev,
+                _ => panic!("unexpected event"),
+            };
+
+            assert_eq!(H3Actions::from(event).0, vec![action]);
+        }
+    }
+
+    #[test]
+    fn update_keys_roundtrip() {
+        let events: QlogEvents = (&Action::UpdateKeys).into();
+        assert_eq!(events.len(), 1);
+
+        let data = match events.into_iter().next().unwrap() {
+            QlogEvent::Event { data, .. } => data,
+            QlogEvent::JsonEvent(_) => panic!("unexpected event"),
+        };
+
+        let event = Event::with_time(0.0, *data);
+
+        assert_eq!(actions_from_qlog(event, None).0, vec![Action::UpdateKeys]);
+    }
+
+    #[test]
+    fn repeat_is_recorded_expanded() {
+        let action = Action::Repeat {
+            count: 3,
+            actions: vec![Action::Ping, Action::Padding { len: 2 }],
+        };
+
+        let converted: Vec<Action> = QlogEvents::from(&action)
+            .into_iter()
+            .flat_map(|event| match event {
+                QlogEvent::Event { data, .. } => match *data {
+                    EventData::PacketSent(ps) => H3Actions::from(&ps).0,
+                    _ => panic!("unexpected event"),
+                },
+
+                QlogEvent::JsonEvent(_) => panic!("unexpected event"),
+            })
+            .collect();
+
+        let expected: Vec<Action> = (0..3)
+            .flat_map(|_| [Action::Ping, Action::Padding { len: 2 }])
+            .collect();
+        assert_eq!(converted, expected);
+    }
+
+    #[test]
+    fn priority_update_roundtrip() {
+        let actions = [
+            crate::actions::h3::send_priority_update_request(2, 0, b"u=1, i"),
+            crate::actions::h3::send_priority_update_push(2, 3, b"u=9"),
+        ];
+
+        for action in actions {
+            let events: QlogEvents = (&action).into();
+            assert_eq!(events.len(), 1);
+
+            let (data, ex_data) = match events.into_iter().next().unwrap() {
+                QlogEvent::Event { data, ex_data } => (data, ex_data),
+                QlogEvent::JsonEvent(_) => panic!("unexpected event"),
+            };
+
+            let frame_created = match *data {
+                EventData::H3FrameCreated(fc) => fc,
+                _ => panic!("unexpected event"),
+            };
+
+            let converted: Action = H3FrameCreatedEx {
+                frame_created,
+                ex_data,
+            }
+            .into();
+
+            assert_eq!(converted, action);
+        }
+    }
+
+    #[test]
+    fn quic_frame_actions_roundtrip() {
+        let actions = [
+            Action::MaxData { max: 0 },
+            Action::MaxStreamData {
+                stream_id: 3,
+                max: 1 << 40,
+            },
+            Action::MaxStreams {
+                bidi: false,
+                max: 1,
+            },
+            Action::WithholdFlowControlUpdates { withhold: true },
+            Action::Ping,
+            Action::Padding { len: 20 },
+            Action::Burst { packets: 100 },
+            Action::RawQuicFrame {
+                bytes: vec![0x1f, 0xff],
+            },
+            Action::StreamBytes {
+                stream_id: 2,
+                fin_stream: false,
+                bytes: vec![0xff, 0xff, 0x00],
+            },
+            Action::StreamBytes {
+                stream_id: 0,
+                fin_stream: true,
+                bytes: b"hi".to_vec(),
+            },
+            Action::SendDatagram {
+                quarter_stream_id: 1,
+                payload: b"ping".to_vec(),
+            },
+            Action::Migrate {
+                local_addr: None,
+                nat_rebinding: true,
+            },
+            Action::Migrate {
+                local_addr: Some("[::1]:443".parse().unwrap()),
+                nat_rebinding: false,
+            },
+            Action::ProbePath { local_addr: None },
+            Action::SwitchPath { path: 1 },
+            Action::SnapshotStats {
+                label: "before".to_string(),
+            },
+            Action::OpenWebTransportStream {
+                stream_id: 8,
+                session_id: 0,
+                bidi: true,
+            },
+            Action::OnFrame {
+                matcher: Expectation::Status {
+                    stream_id: 0,
+                    status: 200,
+                },
+                then: vec![Action::Ping, Action::Wait {
+                    wait_type: WaitType::WaitDuration(Duration::from_millis(5)),
+                }],
+                otherwise: vec![Action::ResetStream {
+                    stream_id: 0,
+                    error_code: 0x10c,
+                }],
+            },
+        ];
+
+        for action in actions {
+            let events: QlogEvents = (&action).into();
+            assert_eq!(events.len(), 1);
+
+            let converted = match events.into_iter().next().unwrap() {
+                QlogEvent::Event { data, .. } => match *data {
+                    EventData::PacketSent(ps) => H3Actions::from(&ps),
+                    _ => panic!("unexpected event"),
+                },
+
+                QlogEvent::JsonEvent(ev) => H3Actions::from(ev),
+            };
+
+            assert_eq!(converted.0, vec![action]);
+        }
+    }
+
+    #[test]
+    fn assert_body_roundtrip() {
+        let actions = [
+            BodyAssertion::Bytes(b"hello".to_vec()),
+            BodyAssertion::Length(5),
+            BodyAssertion::Sha256("2cf24dba".to_string()),
+            BodyAssertion::Regex("^h.*o$".to_string()),
+        ]
+        .map(|assertion| Action::AssertBody {
+            stream_id: 4,
+            assertion,
+        });
+
+        for action in actions {
+            let events: QlogEvents = (&action).into();
+            assert_eq!(events.len(), 1);
+
+            let converted = match events.into_iter().next().unwrap() {
+                QlogEvent::JsonEvent(ev) => H3Actions::from(ev),
+                QlogEvent::Event { .. } => panic!("unexpected event"),
+            };
+
+            assert_eq!(converted.0, vec![action]);
+        }
+    }
+
+    #[test]
+    fn open_concurrent_requests_roundtrip() {
+        let headers =
+            vec![Header::new(b":method", b"GET"), Header::new(b":path", b"/")];
+
+        let actions = [RampPolicy::Immediate, RampPolicy::Step {
+            step: 2,
+            interval: Duration::from_millis(50),
+        }]
+        .map(|ramp| Action::OpenConcurrentRequests {
+            first_stream_id: 8,
+            count: 100,
+            headers: headers.clone(),
+            ramp,
+        });
+
+        for action in actions {
+            let events: QlogEvents = (&action).into();
+            assert_eq!(events.len(), 1);
+
+            let converted = match events.into_iter().next().unwrap() {
+                QlogEvent::JsonEvent(ev) => H3Actions::from(ev),
+                QlogEvent::Event { .. } => panic!("unexpected event"),
+            };
+
+            assert_eq!(converted.0, vec![action]);
+        }
+    }
+
+    #[test]
+    fn qpack_instruction_roundtrip() {
+        let actions = [
+            QpackInstruction::SetDynamicTableCapacity { capacity: 4096 },
+            QpackInstruction::InsertWithLiteralName {
+                name: b"x-h3i".to_vec(),
+                value: vec![0xff, 0x00],
+            },
+            QpackInstruction::Raw(vec![0x3f]),
+        ]
+        .map(|instruction| Action::SendQpackInstruction {
+            stream_id: 6,
+            instruction,
+        });
+
+        for action in actions {
+            let converted = match QlogEvents::from(&action).pop().unwrap() {
+                QlogEvent::JsonEvent(ev) => H3Actions::from(ev),
+                QlogEvent::Event { .. } => panic!("unexpected event"),
+            };
+
+            assert_eq!(converted.0, vec![action]);
+        }
+    }
+
+    #[test]
+    fn ser_assert_body() {
+        let action = Action::AssertBody {
+            stream_id: 0,
+            assertion: BodyAssertion::Length(42),
+        };
+
+        let ev = match QlogEvents::from(&action).pop().unwrap() {
+            QlogEvent::JsonEvent(ev) => ev,
+            QlogEvent::Event { .. } => panic!("unexpected event"),
+        };
+
+        assert_eq!(ev.name, "h3i:assert_body");
+        assert_eq!(
+            serde_json::to_string(&ev.data).unwrap(),
+            r#"{"stream_id":0,"assertion":{"length":42}}"#
+        );
+    }
+
     #[test]
     fn deser_http_headers_to_action() {
         let serialized = r#"{"time":0.074725,"name":"http:frame_created","data":{"stream_id":0,"frame":{"frame_type":"headers","headers":[{"name":":method","value":"GET"},{"name":":authority","value":"example.net"},{"name":":path","value":"/"},{"name":":scheme","value":"https"}]}},"fin_stream":true}"#;
@@ -762,4 +1965,98 @@ mod tests {
 
         assert_eq!(actions.0[0], expected);
     }
+
+    #[test]
+    fn import_json_client_qlog() {
+        let qlog = r#"{"qlog_version":"0.3","qlog_format":"JSON","traces":[{"vantage_point":{"type":"client"},"events":[
+            {"time":0.1,"name":"http:stream_type_set","data":{"owner":"local","stream_id":2,"stream_type":"control"}},
+            {"time":0.1,"name":"http:stream_type_set","data":{"owner":"remote","stream_id":3,"stream_type":"control"}},
+            {"time":0.2,"name":"http:frame_created","data":{"stream_id":0,"frame":{"frame_type":"headers","headers":[{"name":":method","value":"POST"},{"name":":path","value":"/"}]}}},
+            {"time":0.3,"name":"http:frame_created","data":{"stream_id":0,"frame":{"frame_type":"data","raw":{"payload_length":3}}}},
+            {"time":0.4,"name":"http:frame_created","data":{"stream_id":0,"frame":{"frame_type":"push_promise","push_id":0,"headers":[]}}},
+            {"time":0.5,"name":"transport:foo","data":{}}
+        ]}]}"#;
+
+        let actions =
+            actions_from_qlog_reader(qlog.as_bytes(), Some("example.org"))
+                .unwrap();
+
+        let headers = vec![
+            Header::new(b":method", b"POST"),
+            Header::new(b":path", b"/"),
+        ];
+        let header_block = encode_header_block(&headers).unwrap();
+
+        assert_eq!(actions, vec![
+            Action::OpenUniStream {
+                stream_id: 2,
+                fin_stream: false,
+                stream_type: 0x0,
+            },
+            Action::SendHeadersFrame {
+                stream_id: 0,
+                fin_stream: false,
+                literal_headers: false,
+                headers,
+                frame: Frame::Headers { header_block },
+            },
+            Action::SendFrame {
+                stream_id: 0,
+                fin_stream: true,
+                frame: Frame::Data {
+                    payload: vec![0; 3],
+                },
+            },
+        ]);
+    }
+
+    #[test]
+    fn import_json_server_qlog() {
+        let qlog = r#"{"qlog_version":"0.3","qlog_format":"JSON","traces":[{"vantage_point":{"type":"server"},"events":[
+            {"time":0.1,"name":"http:stream_type_set","data":{"owner":"local","stream_id":3,"stream_type":"control"}},
+            {"time":0.1,"name":"http:stream_type_set","data":{"owner":"remote","stream_id":2,"stream_type":"control"}},
+            {"time":0.2,"name":"http:frame_created","data":{"stream_id":3,"frame":{"frame_type":"goaway","id":0}}},
+            {"time":0.3,"name":"http:frame_parsed","data":{"stream_id":2,"frame":{"frame_type":"max_push_id","push_id":4}}}
+        ]}]}"#;
+
+        let actions = actions_from_qlog_reader(qlog.as_bytes(), None).unwrap();
+
+        assert_eq!(actions, vec![
+            Action::OpenUniStream {
+                stream_id: 2,
+                fin_stream: false,
+                stream_type: 0x0,
+            },
+            Action::SendFrame {
+                stream_id: 2,
+                fin_stream: false,
+                frame: Frame::MaxPushId { push_id: 4 },
+            },
+        ]);
+    }
+
+    #[test]
+    fn import_json_seq_qlog() {
+        let header = r#"{"qlog_version":"0.3","qlog_format":"JSON-SEQ","trace":{"vantage_point":{"type":"client"}}}"#;
+        let headers = r#"{"time":0.1,"name":"http:frame_created","data":{"stream_id":0,"frame":{"frame_type":"headers","headers":[{"name":":path","value":"/"}]}},"fin_stream":false}"#;
+        let data = r#"{"time":0.2,"name":"http:frame_created","data":{"stream_id":0,"frame":{"frame_type":"data","raw":{"data":"body"}}},"fin_stream":false}"#;
+        let qlog = format!("\x1e{header}\n\x1e{headers}\n\x1e{data}\n");
+
+        let actions = actions_from_qlog_reader(qlog.as_bytes(), None).unwrap();
+
+        // The FINs recorded by h3i are kept as they are.
+        assert_eq!(actions.len(), 2);
+        assert!(matches!(actions[0], Action::SendHeadersFrame {
+            stream_id: 0,
+            fin_stream: false,
+            ..
+        }));
+        assert_eq!(actions[1], Action::SendFrame {
+            stream_id: 0,
+            fin_stream: false,
+            frame: Frame::Data {
+                payload: b"body".to_vec(),
+            },
+        });
+    }
 }
@@ -0,0 +1,213 @@
+// Copyright (C) 2025, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Recording of h3i sessions as action scripts.
+//!
+//! A [Recorder] captures the actions h3i executes during a session, along
+//! with when they were executed, so that the session can be saved as an
+//! [action script] and re-run later, e.g. as a regression test.
+//!
+//! [action script]: crate::actions::script
+
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::actions::h3::Action;
+use crate::actions::h3::WaitType;
+use crate::actions::script;
+
+/// How the timing of a recorded session is reproduced in its script.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RecordedTiming {
+    /// Keep the waits as they were executed, so a replay waits for the same
+    /// durations and stream events.
+    #[default]
+    Waits,
+    /// Replace each wait with a wait for the duration it actually took, so a
+    /// replay reproduces the original timing regardless of how the peer
+    /// responds.
+    Durations,
+}
+
+/// Captures the actions executed during a session.
+#[derive(Debug)]
+pub struct Recorder {
+    start: Instant,
+    actions: Vec<(Duration, Action)>,
+}
+
+impl Default for Recorder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Recorder {
+    /// Creates a recorder. Times are measured from now.
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            actions: vec![],
+        }
+    }
+
+    /// Records that `action` is being executed.
+    pub fn record(&mut self, action: &Action) {
+        self.actions.push((self.start.elapsed(), action.clone()));
+    }
+
+    /// Returns the recorded actions, along with when they were executed
+    /// relative to the creation of the recorder.
+    pub fn recorded(&self) -> &[(Duration, Action)] {
+        &self.actions
+    }
+
+    /// Returns the recorded actions, with the waits reproduced according to
+    /// `timing`.
+    ///
+    /// With [RecordedTiming::Durations], the duration of a wait is measured
+    /// until the next action was executed. A trailing wait is kept as is,
+    /// since it didn't complete before the session ended.
+    pub fn actions(&self, timing: RecordedTiming) -> Vec<Action> {
+        self.actions
+            .iter()
+            .enumerate()
+            .map(|(i, (at, action))| match (timing, action) {
+                (RecordedTiming::Durations, Action::Wait { .. }) =>
+                    match self.actions.get(i + 1) {
+                        Some((next, _)) => Action::Wait {
+                            wait_type: WaitType::WaitDuration(
+                                next.saturating_sub(*at),
+                            ),
+                        },
+
+                        None => action.clone(),
+                    },
+
+                _ => action.clone(),
+            })
+            .collect()
+    }
+
+    /// Writes the recorded actions to a script file.
+    ///
+    /// See [`script::to_file()`] for the supported formats.
+    pub fn write_script(
+        &self, path: impl AsRef<Path>, timing: RecordedTiming,
+    ) -> io::Result<()> {
+        script::to_file(path, &self.actions(timing))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::actions::h3::StreamEvent;
+    use crate::actions::h3::StreamEventType;
+
+    fn wait_headers() -> Action {
+        Action::Wait {
+            wait_type: WaitType::StreamEvent(StreamEvent {
+                stream_id: 0,
+                event_type: StreamEventType::Headers,
+            }),
+        }
+    }
+
+    #[test]
+    fn recorded_timing() {
+        let stream_bytes = Action::StreamBytes {
+            stream_id: 0,
+            fin_stream: true,
+            bytes: b"hello".to_vec(),
+        };
+
+        let mut recorder = Recorder::new();
+        recorder.actions = vec![
+            (Duration::from_millis(1), stream_bytes.clone()),
+            (Duration::from_millis(2), wait_headers()),
+            (Duration::from_millis(52), Action::FlushPackets),
+            (Duration::from_millis(53), wait_headers()),
+        ];
+
+        assert_eq!(recorder.actions(RecordedTiming::Waits), vec![
+            stream_bytes.clone(),
+            wait_headers(),
+            Action::FlushPackets,
+            wait_headers(),
+        ]);
+
+        assert_eq!(recorder.actions(RecordedTiming::Durations), vec![
+            stream_bytes,
+            Action::Wait {
+                wait_type: WaitType::WaitDuration(Duration::from_millis(50)),
+            },
+            Action::FlushPackets,
+            wait_headers(),
+        ]);
+    }
+
+    #[test]
+    fn round_trip() {
+        let actions = vec![
+            crate::actions::h3::send_headers_frame(0, false, vec![
+                quiche::h3::Header::new(b":method", b"GET"),
+                quiche::h3::Header::new(b":path", b"/"),
+            ]),
+            crate::actions::h3::HeadersFrameBuilder::new(4)
+                .with_pseudo_header(b"Method", b"GET")
+                .build(),
+            Action::SendFrame {
+                stream_id: 0,
+                fin_stream: true,
+                frame: quiche::h3::frame::Frame::Data {
+                    payload: vec![0xff, 0x00],
+                },
+            },
+            wait_headers(),
+            Action::Ping,
+            Action::RawQuicFrame {
+                bytes: b"\x1fhi".to_vec(),
+            },
+            Action::ConnectionClose {
+                error: quiche::ConnectionError {
+                    is_app: true,
+                    error_code: 0x100,
+                    reason: b"done".to_vec(),
+                },
+            },
+        ];
+
+        let yaml = script::to_yaml(&actions).unwrap();
+        assert_eq!(script::from_yaml(&yaml).unwrap().actions, actions);
+
+        let json = script::to_json(&actions).unwrap();
+        assert_eq!(script::from_json(&json).unwrap().actions, actions);
+    }
+}
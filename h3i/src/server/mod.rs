@@ -0,0 +1,476 @@
+// Copyright (C) 2025, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! The h3i server runner.
+//!
+//! The server waits for a client to connect, executes a series of [Action]s
+//! against it, and captures the results in a [ConnectionSummary]. This makes
+//! it possible to test how HTTP/3 clients deal with misbehaving servers, e.g.
+//! servers that send malformed SETTINGS, delay their response HEADERS, or send
+//! bogus frame types.
+//!
+//! The server doesn't set up any HTTP/3 state by itself: the control and QPACK
+//! streams need to be opened with actions like any other stream. Keep in mind
+//! that server-initiated unidirectional streams use IDs 3, 7, 11 and so on,
+//! and that requests can be waited for with [WaitType::StreamEvent]s on the
+//! client's request streams.
+//!
+//! [WaitType::StreamEvent]: crate::actions::h3::WaitType::StreamEvent
+
+use std::net::SocketAddr;
+use std::time::Duration;
+use std::time::Instant;
+
+use serde::Serialize;
+
+use crate::actions::h3::Action;
+use crate::actions::h3::ActionIter;
+use crate::actions::h3::WaitingFor;
+use crate::client::connection_summary::CloseTriggerFrames;
+use crate::client::connection_summary::ConnectionSummary;
+use crate::client::sync_client::generate_cid_and_reset_token;
+use crate::client::sync_client::run_actions;
+use crate::client::sync_client::SyncClient;
+use crate::client::Client;
+use crate::client::MAX_DATAGRAM_SIZE;
+use crate::config::ServerConfig;
+use crate::frame_parser::FrameParser;
+use crate::quiche;
+
+#[derive(Debug, Serialize)]
+/// Represents different errors that can occur when the [server](self) runs.
+pub enum ServerError {
+    /// The server couldn't be set up, e.g. because the certificate couldn't
+    /// be loaded or the socket couldn't be bound.
+    Setup(String),
+    /// An error during the QUIC handshake.
+    HandshakeFail,
+    /// Some other type of error.
+    Other(String),
+}
+
+fn build_quiche_config(
+    args: &ServerConfig,
+) -> std::result::Result<quiche::Config, ServerError> {
+    let mut config = quiche::Config::new(quiche::PROTOCOL_VERSION)
+        .map_err(|e| ServerError::Setup(e.to_string()))?;
+
+    config
+        .load_cert_chain_from_pem_file(&args.cert_file)
+        .map_err(|e| {
+            ServerError::Setup(format!(
+                "failed to load certificate {}: {e}",
+                args.cert_file
+            ))
+        })?;
+    config
+        .load_priv_key_from_pem_file(&args.key_file)
+        .map_err(|e| {
+            ServerError::Setup(format!(
+                "failed to load private key {}: {e}",
+                args.key_file
+            ))
+        })?;
+
+    config.set_application_protos(&[b"h3"]).unwrap();
+    config.set_max_idle_timeout(args.idle_timeout);
+    config.set_max_recv_udp_payload_size(MAX_DATAGRAM_SIZE);
+    config.set_max_send_udp_payload_size(MAX_DATAGRAM_SIZE);
+    config.set_initial_max_data(args.max_data);
+    config
+        .set_initial_max_stream_data_bidi_local(args.max_stream_data_bidi_local);
+    config.set_initial_max_stream_data_bidi_remote(
+        args.max_stream_data_bidi_remote,
+    );
+    config.set_initial_max_stream_data_uni(args.max_stream_data_uni);
+    config.set_initial_max_streams_bidi(args.max_streams_bidi);
+    config.set_initial_max_streams_uni(args.max_streams_uni);
+    config.set_disable_active_migration(true);
+
+    config.set_max_connection_window(args.max_window);
+    config.set_max_stream_window(args.max_stream_window);
+
+    if std::env::var_os("SSLKEYLOGFILE").is_some() {
+        config.log_keys();
+    }
+
+    config.grease(false);
+
+    Ok(config)
+}
+
+/// Waits for a client to connect, and accepts its connection.
+///
+/// Datagrams that don't start a new connection are ignored, and clients using
+/// an unsupported version are sent a Version Negotiation packet.
+fn accept(
+    socket: &mio::net::UdpSocket, poll: &mut mio::Poll,
+    config: &mut quiche::Config, buf: &mut [u8], out: &mut [u8],
+) -> std::result::Result<quiche::Connection, ServerError> {
+    let mut events = mio::Events::with_capacity(1024);
+    let local_addr = socket.local_addr().unwrap();
+
+    loop {
+        poll.poll(&mut events, None)
+            .map_err(|e| ServerError::Other(format!("poll() failed: {e:?}")))?;
+
+        'read: loop {
+            let (len, from) = match socket.recv_from(buf) {
+                Ok(v) => v,
+
+                Err(e) => {
+                    if e.kind() == std::io::ErrorKind::WouldBlock {
+                        break 'read;
+                    }
+
+                    return Err(ServerError::Other(format!(
+                        "{local_addr}: recv() failed: {e:?}"
+                    )));
+                },
+            };
+
+            let hdr = match quiche::Header::from_slice(
+                &mut buf[..len],
+                quiche::MAX_CONN_ID_LEN,
+            ) {
+                Ok(v) => v,
+
+                Err(e) => {
+                    log::debug!("parsing packet header failed: {:?}", e);
+                    continue 'read;
+                },
+            };
+
+            if hdr.ty != quiche::Type::Initial {
+                log::debug!("ignoring {:?} packet from {}", hdr.ty, from);
+                continue 'read;
+            }
+
+            if !quiche::version_is_supported(hdr.version) {
+                log::info!("doing version negotiation with {}", from);
+
+                let len =
+                    quiche::negotiate_version(&hdr.scid, &hdr.dcid, out).unwrap();
+
+                if let Err(e) = socket.send_to(&out[..len], from) {
+                    log::debug!(
+                        "{} -> {}: send() failed: {:?}",
+                        local_addr,
+                        from,
+                        e
+                    );
+                }
+
+                continue 'read;
+            }
+
+            let (scid, _) = generate_cid_and_reset_token();
+
+            let mut conn = quiche::accept(&scid, None, local_addr, from, config)
+                .map_err(|e| {
+                    ServerError::Other(format!("accept() failed: {e:?}"))
+                })?;
+
+            if let Some(keylog_path) = std::env::var_os("SSLKEYLOGFILE") {
+                if let Ok(file) = std::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(keylog_path)
+                {
+                    conn.set_keylog(Box::new(file));
+                }
+            }
+
+            log::info!(
+                "accepted connection from {:} on {:} with scid {:?}",
+                from,
+                local_addr,
+                scid,
+            );
+
+            let recv_info = quiche::RecvInfo {
+                to: local_addr,
+                from,
+                ecn: quiche::Ecn::NotEct,
+            };
+
+            if let Err(e) = conn.recv(&mut buf[..len], recv_info) {
+                log::debug!("{}: recv failed: {:?}", local_addr, e);
+            }
+
+            return Ok(conn);
+        }
+    }
+}
+
+/// Wait for a client to connect and execute provided actions.
+///
+/// Binds a socket to the address provided in `args`, accepts the first
+/// connection made to it, then iterates over `actions`. Only one connection is
+/// served, so this returns once it is closed.
+///
+/// If `close_trigger_frames` is specified, h3i will close the connection
+/// immediately upon receiving all of the supplied frames rather than waiting
+/// for the idle timeout. See [`CloseTriggerFrames`] for details.
+///
+/// Returns a [ConnectionSummary] on success, [ServerError] on failure.
+pub fn serve(
+    args: ServerConfig, actions: &[Action],
+    close_trigger_frames: Option<CloseTriggerFrames>,
+) -> std::result::Result<ConnectionSummary, ServerError> {
+    let mut buf = [0; 65535];
+    let mut out = [0; MAX_DATAGRAM_SIZE];
+
+    let listen: SocketAddr = args.listen.parse().map_err(|e| {
+        ServerError::Setup(format!("invalid listen address {}: {e}", args.listen))
+    })?;
+
+    let mut config = build_quiche_config(&args)?;
+
+    // Setup the event loop.
+    let mut poll = mio::Poll::new().unwrap();
+    let mut events = mio::Events::with_capacity(1024);
+
+    let mut socket = mio::net::UdpSocket::bind(listen).map_err(|e| {
+        ServerError::Setup(format!("failed to bind {listen}: {e:?}"))
+    })?;
+    poll.registry()
+        .register(&mut socket, mio::Token(0), mio::Interest::READABLE)
+        .unwrap();
+
+    let local_addr = socket.local_addr().unwrap();
+
+    log::info!("listening on {:}", local_addr);
+
+    // Make the connection's randomness deterministic, if requested. This
+    // needs to happen before anything random is generated, and is undone once
+    // the connection is over.
+    let _rng_guard = args.rng_seed.map(|seed| {
+        quiche::scoped_thread_rng(Box::new(quiche::SeededRng::new(seed)))
+    });
+
+    let mut conn = accept(&socket, &mut poll, &mut config, &mut buf, &mut out)?;
+
+    let app_data_start = Instant::now();
+
+    let mut action_iter = ActionIter::new(actions);
+    let mut wait_duration: Option<Duration> = None;
+    let mut wait_instant = None;
+
+    let mut client = SyncClient::new(close_trigger_frames);
+    let mut waiting_for = WaitingFor::default();
+
+    if args.record_script.is_some() {
+        client.record();
+    }
+
+    // The handshake's first flight is sent before waiting for events.
+    let mut first = true;
+
+    loop {
+        if !first {
+            let actual_sleep = match (wait_duration, conn.timeout()) {
+                (Some(wait), Some(timeout)) => Some(wait.min(timeout)),
+                (wait, timeout) => wait.or(timeout),
+            };
+
+            log::debug!("actual sleep is {:?}", actual_sleep);
+            poll.poll(&mut events, actual_sleep).unwrap();
+
+            if events.is_empty() {
+                log::debug!("timed out");
+
+                conn.on_timeout();
+            }
+
+            'read: loop {
+                let (len, from) = match socket.recv_from(&mut buf) {
+                    Ok(v) => v,
+
+                    Err(e) => {
+                        if e.kind() == std::io::ErrorKind::WouldBlock {
+                            break 'read;
+                        }
+
+                        return Err(ServerError::Other(format!(
+                            "{local_addr}: recv() failed: {e:?}"
+                        )));
+                    },
+                };
+
+                let recv_info = quiche::RecvInfo {
+                    to: local_addr,
+                    from,
+                    ecn: quiche::Ecn::NotEct,
+                };
+
+                if let Err(e) = conn.recv(&mut buf[..len], recv_info) {
+                    log::debug!("{}: recv failed: {:?}", local_addr, e);
+                }
+            }
+        }
+
+        first = false;
+
+        if conn.is_closed() {
+            log::info!(
+                "connection closed with error={:?} did_idle_timeout={}, stats={:?}",
+                conn.peer_error(),
+                conn.is_timed_out(),
+                conn.stats(),
+            );
+
+            if !conn.is_established() {
+                log::info!(
+                    "connection timed out after {:?}",
+                    app_data_start.elapsed(),
+                );
+
+                return Err(ServerError::HandshakeFail);
+            }
+
+            break;
+        }
+
+        if conn.is_established() {
+            // Requests are received on streams opened by the client, so there
+            // is no parser for them until they become readable.
+            for stream_id in conn.readable() {
+                if stream_id % 4 == 0 {
+                    client
+                        .stream_parsers_mut()
+                        .entry(stream_id)
+                        .or_insert_with(|| FrameParser::new(stream_id));
+                }
+            }
+
+            run_actions(
+                &mut wait_duration,
+                &mut wait_instant,
+                &mut action_iter,
+                &mut conn,
+                &mut waiting_for,
+                &mut client,
+            );
+        }
+
+        // Provides as many CIDs as possible.
+        while conn.scids_left() > 0 {
+            let (scid, reset_token) = generate_cid_and_reset_token();
+
+            if conn.new_scid(&scid, reset_token, false).is_err() {
+                break;
+            }
+        }
+
+        // Generate outgoing QUIC packets and send them on the UDP socket, until
+        // quiche reports that there are no more packets to be sent.
+        loop {
+            let (write, send_info) = match conn.send(&mut out) {
+                Ok(v) => v,
+
+                Err(quiche::Error::Done) => break,
+
+                Err(e) => {
+                    log::error!("{}: send failed: {:?}", local_addr, e);
+
+                    conn.close(false, 0x1, b"fail").ok();
+                    break;
+                },
+            };
+
+            if let Err(e) = socket.send_to(&out[..write], send_info.to) {
+                if e.kind() == std::io::ErrorKind::WouldBlock {
+                    log::debug!(
+                        "{} -> {}: send() would block",
+                        local_addr,
+                        send_info.to
+                    );
+                    break;
+                }
+
+                return Err(ServerError::Other(format!(
+                    "{} -> {}: send() failed: {:?}",
+                    local_addr, send_info.to, e
+                )));
+            }
+        }
+
+        if conn.is_closed() {
+            log::info!("connection closed, {:?}", conn.stats());
+
+            if !conn.is_established() {
+                return Err(ServerError::HandshakeFail);
+            }
+
+            break;
+        }
+    }
+
+    if let Some(path) = &args.record_script {
+        client.write_script(path, args.record_timing);
+    }
+
+    Ok(client.into_summary(&conn))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_requires_certificate() {
+        assert!(ServerConfig::new().build().is_err());
+
+        assert!(ServerConfig::new()
+            .with_cert_file("cert.crt".to_string())
+            .with_key_file("cert.key".to_string())
+            .build()
+            .is_ok());
+    }
+
+    #[test]
+    fn serve_setup_errors() {
+        let config = ServerConfig::new()
+            .with_listen("not an address".to_string())
+            .with_cert_file("../quiche/examples/cert.crt".to_string())
+            .with_key_file("../quiche/examples/cert.key".to_string());
+
+        assert!(matches!(
+            serve(config, &[], None),
+            Err(ServerError::Setup(_))
+        ));
+
+        let config = ServerConfig::new()
+            .with_cert_file("missing.crt".to_string())
+            .with_key_file("missing.key".to_string());
+
+        assert!(matches!(
+            serve(config, &[], None),
+            Err(ServerError::Setup(_))
+        ));
+    }
+}
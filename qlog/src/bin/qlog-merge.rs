@@ -0,0 +1,83 @@
+// Copyright (C) 2025, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Merges qlog files onto a common timeline and prints the result as a JSON
+//! qlog with one trace per input trace.
+
+use std::io::BufRead;
+
+use qlog::merge;
+use qlog::reader::QlogSeqReader;
+use qlog::Qlog;
+use qlog::Trace;
+
+const USAGE: &str = "Usage: qlog-merge FILE...
+
+Merges JSON and JSON-SEQ qlog files onto the clock of the first client trace.
+The clock offset of server traces is estimated from the handshake packets.";
+
+fn read_traces(path: &str) -> Result<Vec<Trace>, Box<dyn std::error::Error>> {
+    let file = std::fs::File::open(path)?;
+    let mut reader = std::io::BufReader::new(file);
+
+    // JSON-SEQ records start with a record separator.
+    if reader.fill_buf()?.first() == Some(&b'\x1e') {
+        let reader = QlogSeqReader::new(Box::new(reader))?;
+
+        return Ok(vec![merge::trace_from_seq(reader)]);
+    }
+
+    let qlog: Qlog = serde_json::from_reader(reader)?;
+
+    Ok(qlog.traces)
+}
+
+fn main() {
+    let paths: Vec<String> = std::env::args().skip(1).collect();
+
+    if paths.is_empty() || paths.iter().any(|p| p == "-h" || p == "--help") {
+        eprintln!("{USAGE}");
+        std::process::exit(1);
+    }
+
+    let mut traces = vec![];
+
+    for path in &paths {
+        match read_traces(path) {
+            Ok(v) => traces.extend(v),
+
+            Err(e) => {
+                eprintln!("failed to read {path}: {e}");
+                std::process::exit(1);
+            },
+        }
+    }
+
+    let qlog = merge::align(traces);
+
+    serde_json::to_writer_pretty(std::io::stdout().lock(), &qlog).unwrap();
+    println!();
+}
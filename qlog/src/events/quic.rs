@@ -736,6 +736,10 @@ pub struct DataMoved {
     pub to: Option<DataRecipient>,
 
     pub raw: Option<RawInfo>,
+
+    // quiche defined
+    pub urgency: Option<u8>,
+    pub incremental: Option<bool>,
 }
 
 #[serde_with::skip_serializing_none]
@@ -772,6 +776,15 @@ pub struct MetricsUpdated {
     pub packets_in_flight: Option<u64>,
 
     pub pacing_rate: Option<u64>,
+
+    // quiche defined
+    pub pacing_burst: Option<u64>,
+    pub paced: Option<bool>,
+    pub bbr_state: Option<String>,
+    pub bbr_bw: Option<u64>,
+    pub bbr_max_bw: Option<u64>,
+    pub bbr_inflight_hi: Option<u64>,
+    pub bbr_inflight_lo: Option<u64>,
 }
 
 #[serde_with::skip_serializing_none]
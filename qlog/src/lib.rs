@@ -250,7 +250,7 @@
 //!
 //! Create an object with the [`Write`] trait:
 //!
-//! ```
+//! ```no_run
 //! let mut file = std::fs::File::create("foo.sqlog").unwrap();
 //! ```
 //!
@@ -272,7 +272,8 @@
 //! #    }),
 //! #    None,
 //! # );
-//! # let mut file = std::fs::File::create("foo.sqlog").unwrap();
+//! # let mut file =
+//! #     std::fs::File::create(std::env::temp_dir().join("foo.sqlog")).unwrap();
 //! let mut streamer = qlog::streamer::QlogStreamer::new(
 //!     qlog::QLOG_VERSION.to_string(),
 //!     Some("Example qlog".to_string()),
@@ -309,7 +310,8 @@
 //! #    }),
 //! #    None,
 //! # );
-//! # let mut file = std::fs::File::create("foo.qlog").unwrap();
+//! # let mut file =
+//! #     std::fs::File::create(std::env::temp_dir().join("foo.qlog")).unwrap();
 //! # let mut streamer = qlog::streamer::QlogStreamer::new(
 //! #     qlog::QLOG_VERSION.to_string(),
 //! #     Some("Example qlog".to_string()),
@@ -378,7 +380,8 @@
 //! #    }),
 //! #    None,
 //! # );
-//! # let mut file = std::fs::File::create("foo.qlog").unwrap();
+//! # let mut file =
+//! #     std::fs::File::create(std::env::temp_dir().join("foo.qlog")).unwrap();
 //! # let mut streamer = qlog::streamer::QlogStreamer::new(
 //! #     qlog::QLOG_VERSION.to_string(),
 //! #     Some("Example qlog".to_string()),
@@ -397,6 +400,24 @@
 //! Serialization to JSON occurs as methods on the [`QlogStreamer`]
 //! are called. No additional steps are required.
 //!
+//! ## Merging traces
+//!
+//! Traces logged by different hosts, e.g. the client and the server of a
+//! connection, use different clocks. The [`merge`] module rebases the events of
+//! several traces onto a common timeline, estimating the clock offset between a
+//! client and a server from the handshake packets both of them logged:
+//!
+//! ```
+//! # let client = qlog::testing::make_trace();
+//! # let server = qlog::testing::make_trace();
+//! let offset = qlog::merge::estimate_clock_offset(&client, &server);
+//!
+//! let merged =
+//!     qlog::merge::merge(vec![(client, 0.0), (server, offset.unwrap_or(0.0))]);
+//! ```
+//!
+//! The `qlog-merge` tool does the same for a set of JSON or JSON-SEQ files.
+//!
 //! [`Trace`]: struct.Trace.html
 //! [`TraceSeq`]: struct.TraceSeq.html
 //! [`VantagePoint`]: struct.VantagePoint.html
@@ -412,6 +433,7 @@
 //! [`add_event_data_with_instant()`]: streamer/struct.QlogStreamer.html#method.add_event_data_with_instant
 //! [`add_event_data_now()`]: streamer/struct.QlogStreamer.html#method.add_event_data_now
 //! [`finish_log()`]: streamer/struct.QlogStreamer.html#method.finish_log
+//! [`merge`]: merge/index.html
 
 use crate::events::quic::PacketHeader;
 use crate::events::Event;
@@ -964,5 +986,6 @@ mod tests {
 }
 
 pub mod events;
+pub mod merge;
 pub mod reader;
 pub mod streamer;
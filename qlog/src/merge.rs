@@ -0,0 +1,368 @@
+// Copyright (C) 2025, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Merging of traces onto a common timeline.
+//!
+//! Each trace's event times are relative to its own reference time, taken
+//! from the clock of the host that logged it. [`merge()`] rebases the events
+//! of several traces onto a single reference time, after correcting each
+//! trace by a clock offset.
+//!
+//! Traces logged on the same host share a clock and need no correction. The
+//! offset between the clocks of a client and a server can be estimated from
+//! the packets both of them logged during the handshake with
+//! [`estimate_clock_offset()`], or [`align()`] can do it for a whole set of
+//! traces.
+
+use crate::events::quic::PacketHeader;
+use crate::events::quic::PacketType;
+use crate::events::EventData;
+use crate::reader;
+use crate::reader::QlogSeqReader;
+use crate::CommonFields;
+use crate::Configuration;
+use crate::Qlog;
+use crate::Trace;
+use crate::VantagePointType;
+use crate::QLOG_VERSION;
+
+/// Builds a [`Trace`] from the events read from a JSON-SEQ qlog.
+///
+/// Events that don't map to a native qlog event are skipped.
+pub fn trace_from_seq(reader: QlogSeqReader) -> Trace {
+    let seq = reader.qlog.trace.clone();
+
+    let mut trace = Trace::new(
+        seq.vantage_point,
+        seq.title,
+        seq.description,
+        seq.configuration,
+        seq.common_fields,
+    );
+
+    for event in reader {
+        if let reader::Event::Qlog(event) = event {
+            trace.push_event(event);
+        }
+    }
+
+    trace
+}
+
+/// Returns the absolute time, in milliseconds, that the trace's event times
+/// are relative to.
+pub fn reference_time(trace: &Trace) -> f64 {
+    let reference_time = trace
+        .common_fields
+        .as_ref()
+        .and_then(|c| c.reference_time)
+        .unwrap_or(0.0);
+
+    let time_offset = trace
+        .configuration
+        .as_ref()
+        .and_then(|c| c.time_offset)
+        .unwrap_or(0.0);
+
+    reference_time + time_offset
+}
+
+/// Estimates by how many milliseconds the server's clock is ahead of the
+/// client's.
+///
+/// The estimation relies on the Initial and Handshake packets logged by both
+/// endpoints. The smallest observed delay in each direction is the sum of the
+/// network delay and of the clock offset, with opposite signs, so assuming
+/// the network delay is symmetric the offset is half their difference.
+///
+/// Returns `None` if no packet was logged in both traces in each direction.
+pub fn estimate_clock_offset(client: &Trace, server: &Trace) -> Option<f64> {
+    let client_ref = reference_time(client);
+    let server_ref = reference_time(server);
+
+    // Client to server delays.
+    let forward = min_delay(client, client_ref, server, server_ref)?;
+
+    // Server to client delays.
+    let backward = min_delay(server, server_ref, client, client_ref)?;
+
+    Some((forward - backward) / 2.0)
+}
+
+/// Returns the smallest difference between the times the packets sent by
+/// `from` were received by `to`.
+fn min_delay(
+    from: &Trace, from_ref: f64, to: &Trace, to_ref: f64,
+) -> Option<f64> {
+    let sent = handshake_packets(from, false);
+    let received = handshake_packets(to, true);
+
+    sent.iter()
+        .filter_map(|(hdr, sent_time)| {
+            received.iter().find(|(r, _)| same_packet(hdr, r)).map(
+                |(_, recv_time)| (to_ref + recv_time) - (from_ref + sent_time),
+            )
+        })
+        .min_by(|a, b| a.total_cmp(b))
+}
+
+/// Returns the headers of the Initial and Handshake packets sent or received
+/// in the trace, along with the time they were logged.
+fn handshake_packets(trace: &Trace, received: bool) -> Vec<(&PacketHeader, f64)> {
+    trace
+        .events
+        .iter()
+        .filter_map(|ev| {
+            let hdr = match (&ev.data, received) {
+                (EventData::PacketSent(pkt), false) => &pkt.header,
+
+                (EventData::PacketReceived(pkt), true) => &pkt.header,
+
+                _ => return None,
+            };
+
+            matches!(hdr.packet_type, PacketType::Initial | PacketType::Handshake)
+                .then_some((hdr, ev.time as f64))
+        })
+        .collect()
+}
+
+fn same_packet(a: &PacketHeader, b: &PacketHeader) -> bool {
+    if a.packet_type != b.packet_type || a.packet_number != b.packet_number {
+        return false;
+    }
+
+    // Tell apart the packets of different connections when possible.
+    match (&a.dcid, &b.dcid) {
+        (Some(a), Some(b)) => a == b,
+
+        _ => true,
+    }
+}
+
+/// Merges traces onto a common timeline.
+///
+/// Each trace comes with the offset, in milliseconds, by which the clock of
+/// the host that logged it is ahead of the reference clock. The event times of
+/// all the returned traces are relative to the earliest corrected reference
+/// time.
+pub fn merge(traces: Vec<(Trace, f64)>) -> Qlog {
+    let base = traces
+        .iter()
+        .map(|(trace, offset)| reference_time(trace) - offset)
+        .min_by(|a, b| a.total_cmp(b))
+        .unwrap_or(0.0);
+
+    let traces = traces
+        .into_iter()
+        .map(|(mut trace, offset)| {
+            let shift = reference_time(&trace) - offset - base;
+
+            for event in &mut trace.events {
+                event.time = (event.time as f64 + shift) as f32;
+            }
+
+            let original_uris = trace.configuration.and_then(|c| c.original_uris);
+
+            trace.configuration = Some(Configuration {
+                time_offset: Some(0.0),
+                original_uris,
+            });
+
+            trace.common_fields = Some(CommonFields {
+                reference_time: Some(base),
+                ..trace.common_fields.unwrap_or_default()
+            });
+
+            trace
+        })
+        .collect();
+
+    Qlog {
+        qlog_version: QLOG_VERSION.to_string(),
+        qlog_format: "JSON".to_string(),
+        title: Some("merged qlog".to_string()),
+        description: None,
+        summary: None,
+        traces,
+    }
+}
+
+/// Merges traces onto the clock of the first client trace.
+///
+/// The clock offset of server traces is estimated against the first client
+/// trace, using [`estimate_clock_offset()`]. Other client traces, and server
+/// traces whose offset can't be estimated, are assumed to share its clock.
+pub fn align(traces: Vec<Trace>) -> Qlog {
+    let reference = traces
+        .iter()
+        .position(|t| t.vantage_point.ty == VantagePointType::Client);
+
+    let offsets: Vec<f64> = traces
+        .iter()
+        .map(|trace| {
+            let client = reference.map(|i| &traces[i]);
+
+            match (client, &trace.vantage_point.ty) {
+                (Some(client), VantagePointType::Server) =>
+                    estimate_clock_offset(client, trace).unwrap_or(0.0),
+
+                _ => 0.0,
+            }
+        })
+        .collect();
+
+    merge(traces.into_iter().zip(offsets).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::events::quic::PacketReceived;
+    use crate::events::quic::PacketSent;
+    use crate::events::Event;
+    use crate::VantagePoint;
+
+    fn hdr(ty: PacketType, pn: u64, dcid: &[u8]) -> PacketHeader {
+        PacketHeader::with_type(ty, Some(pn), Some(1), None, Some(dcid))
+    }
+
+    fn sent(time: f32, hdr: PacketHeader) -> Event {
+        Event::with_time(
+            time,
+            EventData::PacketSent(PacketSent {
+                header: hdr,
+                ..Default::default()
+            }),
+        )
+    }
+
+    fn received(time: f32, hdr: PacketHeader) -> Event {
+        Event::with_time(
+            time,
+            EventData::PacketReceived(PacketReceived {
+                header: hdr,
+                ..Default::default()
+            }),
+        )
+    }
+
+    fn trace(ty: VantagePointType, reference_time: f64) -> Trace {
+        Trace::new(
+            VantagePoint {
+                name: None,
+                ty,
+                flow: None,
+            },
+            None,
+            None,
+            Some(Configuration::default()),
+            Some(CommonFields {
+                reference_time: Some(reference_time),
+                ..Default::default()
+            }),
+        )
+    }
+
+    /// A handshake with a 10ms one-way delay, where the server's clock is
+    /// 500ms ahead of the client's.
+    fn handshake() -> (Trace, Trace) {
+        let mut client = trace(VantagePointType::Client, 1000.0);
+        let mut server = trace(VantagePointType::Server, 1400.0);
+
+        // The client sends its first Initial at 1000ms (client clock), the
+        // server receives it at 1510ms (server clock).
+        client.push_event(sent(0.0, hdr(PacketType::Initial, 0, &[1])));
+        server.push_event(received(110.0, hdr(PacketType::Initial, 0, &[1])));
+
+        // The server replies at 1511ms, the client receives the reply at
+        // 1021ms.
+        server.push_event(sent(111.0, hdr(PacketType::Initial, 0, &[2])));
+        server.push_event(sent(111.0, hdr(PacketType::Handshake, 0, &[2])));
+        client.push_event(received(21.0, hdr(PacketType::Initial, 0, &[2])));
+
+        // A late retransmission doesn't affect the estimation.
+        client.push_event(received(80.0, hdr(PacketType::Handshake, 0, &[2])));
+
+        (client, server)
+    }
+
+    #[test]
+    fn clock_offset() {
+        let (client, server) = handshake();
+
+        assert_eq!(estimate_clock_offset(&client, &server), Some(500.0));
+        assert_eq!(estimate_clock_offset(&server, &client), Some(-500.0));
+    }
+
+    #[test]
+    fn clock_offset_unrelated_traces() {
+        let (client, _) = handshake();
+
+        let mut server = trace(VantagePointType::Server, 0.0);
+        server.push_event(received(5.0, hdr(PacketType::Initial, 0, &[9])));
+        server.push_event(sent(6.0, hdr(PacketType::Initial, 0, &[8])));
+
+        assert_eq!(estimate_clock_offset(&client, &server), None);
+    }
+
+    #[test]
+    fn merge_on_client_clock() {
+        let (client, server) = handshake();
+
+        let qlog = align(vec![server, client]);
+        assert_eq!(qlog.traces.len(), 2);
+
+        let server = &qlog.traces[0];
+        let client = &qlog.traces[1];
+
+        // The server's reference time is the earliest once corrected.
+        for trace in &qlog.traces {
+            assert_eq!(reference_time(trace), 900.0);
+        }
+
+        let times =
+            |t: &Trace| t.events.iter().map(|e| e.time).collect::<Vec<_>>();
+
+        assert_eq!(times(client), [100.0, 121.0, 180.0]);
+        assert_eq!(times(server), [110.0, 111.0, 111.0]);
+    }
+
+    #[test]
+    fn merge_same_host() {
+        let mut first = trace(VantagePointType::Server, 2000.0);
+        first.push_event(sent(1.0, hdr(PacketType::Initial, 0, &[1])));
+
+        let mut second = trace(VantagePointType::Server, 1500.0);
+        second.push_event(sent(1.0, hdr(PacketType::Initial, 0, &[2])));
+
+        let qlog = merge(vec![(first, 0.0), (second, 0.0)]);
+
+        assert_eq!(qlog.traces[0].events[0].time, 501.0);
+        assert_eq!(qlog.traces[1].events[0].time, 1.0);
+    }
+}
@@ -310,6 +310,14 @@ static void recv_cb(EV_P_ ev_io *w, int revents) {
                     }
                     break;
 
+                case QUICHE_H3_EVENT_CANCELLED:
+                    fprintf(stderr, "request was cancelled\n");
+
+                    if (quiche_conn_close(conn_io->conn, true, 0, NULL, 0) < 0) {
+                        fprintf(stderr, "failed to close connection\n");
+                    }
+                    break;
+
                 case QUICHE_H3_EVENT_PRIORITY_UPDATE:
                     break;
 
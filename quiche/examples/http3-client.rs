@@ -191,6 +191,7 @@ fn main() {
             let recv_info = quiche::RecvInfo {
                 to: local_addr,
                 from,
+                ecn: quiche::Ecn::NotEct,
             };
 
             // Process potentially coalesced packets.
@@ -278,12 +279,23 @@ fn main() {
                         conn.close(true, 0x100, b"kthxbye").unwrap();
                     },
 
+                    Ok((_stream_id, quiche::h3::Event::Cancelled(c))) => {
+                        error!(
+                            "request was cancelled by peer ({:?}), closing...",
+                            c
+                        );
+
+                        conn.close(true, 0x100, b"kthxbye").unwrap();
+                    },
+
                     Ok((_, quiche::h3::Event::PriorityUpdate)) => unreachable!(),
 
                     Ok((goaway_id, quiche::h3::Event::GoAway)) => {
                         info!("GOAWAY id={}", goaway_id);
                     },
 
+                    Ok((_, quiche::h3::Event::Metadata { .. })) => unreachable!(),
+
                     Err(quiche::h3::Error::Done) => {
                         break;
                     },
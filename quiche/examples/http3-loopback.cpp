@@ -0,0 +1,266 @@
+// Copyright (C) 2025, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+// Runs an HTTP/3 request between a client and a server connected in memory,
+// using the C++ wrapper in quiche.hpp. Exits with a non-zero status if any
+// step of the exchange fails.
+
+#include <arpa/inet.h>
+#include <netinet/in.h>
+
+#include <array>
+#include <cstdio>
+#include <string>
+#include <string_view>
+#include <vector>
+
+#include <quiche.hpp>
+
+#define MAX_DATAGRAM_SIZE 1350
+
+#define LOCAL_CONN_ID_LEN 16
+
+static const std::string_view BODY = "hello from quiche.hpp";
+
+static sockaddr_in make_addr(uint16_t port) {
+    sockaddr_in addr{};
+
+    addr.sin_family = AF_INET;
+    addr.sin_port = htons(port);
+    addr.sin_addr.s_addr = htonl(INADDR_LOOPBACK);
+
+    return addr;
+}
+
+static quiche::config make_config(bool is_server) {
+    quiche::config config;
+
+    if (is_server) {
+        config.load_cert_chain_from_pem_file("./cert.crt").value();
+        config.load_priv_key_from_pem_file("./cert.key").value();
+    }
+
+    config.verify_peer(false);
+    config.set_application_protos(QUICHE_H3_APPLICATION_PROTOCOL).value();
+    config.set_max_idle_timeout(std::chrono::seconds(5));
+    config.set_max_recv_udp_payload_size(MAX_DATAGRAM_SIZE);
+    config.set_max_send_udp_payload_size(MAX_DATAGRAM_SIZE);
+    config.set_initial_max_data(10000000);
+    config.set_initial_max_stream_data_bidi_local(1000000);
+    config.set_initial_max_stream_data_bidi_remote(1000000);
+    config.set_initial_max_stream_data_uni(1000000);
+    config.set_initial_max_streams_bidi(100);
+    config.set_initial_max_streams_uni(100);
+
+    return config;
+}
+
+// Moves all pending packets from `from` to `to`. Returns the number of
+// packets moved.
+static size_t flush(quiche::connection &from, quiche::connection &to,
+                    sockaddr_in &from_addr, sockaddr_in &to_addr) {
+    std::array<uint8_t, MAX_DATAGRAM_SIZE> buf;
+    size_t packets = 0;
+
+    while (true) {
+        quiche_send_info send_info;
+
+        quiche::result<size_t> written = from.send(buf, send_info);
+
+        if (written.error() == quiche::errc::done) {
+            break;
+        }
+
+        size_t len = written.value();
+
+        quiche_recv_info recv_info = {
+            reinterpret_cast<struct sockaddr *>(&from_addr),
+            sizeof(from_addr),
+            reinterpret_cast<struct sockaddr *>(&to_addr),
+            sizeof(to_addr),
+        };
+
+        to.recv(std::span(buf.data(), len), recv_info).value();
+
+        packets++;
+    }
+
+    return packets;
+}
+
+static void exchange(quiche::connection &client, quiche::connection &server,
+                     sockaddr_in &client_addr, sockaddr_in &server_addr) {
+    while (flush(client, server, client_addr, server_addr) +
+               flush(server, client, server_addr, client_addr) >
+           0) {}
+}
+
+int main() {
+    sockaddr_in client_addr = make_addr(4433);
+    sockaddr_in server_addr = make_addr(4434);
+
+    quiche::config client_config = make_config(false);
+    quiche::config server_config = make_config(true);
+
+    std::array<uint8_t, LOCAL_CONN_ID_LEN> client_scid;
+    std::array<uint8_t, LOCAL_CONN_ID_LEN> server_scid;
+
+    client_scid.fill(0xc1);
+    server_scid.fill(0x5e);
+
+    auto client = quiche::connection::connect(
+                      "quic.tech", client_scid,
+                      reinterpret_cast<struct sockaddr *>(&client_addr),
+                      sizeof(client_addr),
+                      reinterpret_cast<struct sockaddr *>(&server_addr),
+                      sizeof(server_addr), client_config)
+                      .value();
+
+    auto server = quiche::connection::accept(
+                      server_scid, {},
+                      reinterpret_cast<struct sockaddr *>(&server_addr),
+                      sizeof(server_addr),
+                      reinterpret_cast<struct sockaddr *>(&client_addr),
+                      sizeof(client_addr), server_config)
+                      .value();
+
+    exchange(client, server, client_addr, server_addr);
+
+    if (!client.is_established() || !server.is_established()) {
+        fprintf(stderr, "handshake failed\n");
+        return 1;
+    }
+
+    quiche::h3::config h3_config;
+
+    auto h3_client =
+        quiche::h3::connection::with_transport(client, h3_config).value();
+    auto h3_server =
+        quiche::h3::connection::with_transport(server, h3_config).value();
+
+    std::array<quiche_h3_header, 4> req = {
+        quiche::h3::header(":method", "GET"),
+        quiche::h3::header(":scheme", "https"),
+        quiche::h3::header(":authority", "quic.tech"),
+        quiche::h3::header(":path", "/"),
+    };
+
+    uint64_t stream_id = h3_client.send_request(client, req, true).value();
+
+    exchange(client, server, client_addr, server_addr);
+
+    bool got_request = false;
+
+    while (true) {
+        auto polled = h3_server.poll(server);
+
+        if (polled.error() == quiche::h3::errc::done) {
+            break;
+        }
+
+        if (polled.value().event.type() == QUICHE_H3_EVENT_HEADERS) {
+            for (const auto &[name, value] : polled->event.headers()) {
+                if (name == ":path" && value == "/") {
+                    got_request = true;
+                }
+            }
+        }
+    }
+
+    if (!got_request) {
+        fprintf(stderr, "server did not receive the request\n");
+        return 1;
+    }
+
+    std::array<quiche_h3_header, 1> resp = {
+        quiche::h3::header(":status", "200"),
+    };
+
+    quiche_h3_priority priority = {1, true};
+
+    h3_server.send_response(server, stream_id, resp, priority, false).value();
+    h3_server
+        .send_body(server, stream_id,
+                   std::span(reinterpret_cast<const uint8_t *>(BODY.data()),
+                             BODY.size()),
+                   true)
+        .value();
+
+    exchange(client, server, client_addr, server_addr);
+
+    std::string body;
+    bool finished = false;
+
+    while (true) {
+        auto polled = h3_client.poll(client);
+
+        if (polled.error() == quiche::h3::errc::done) {
+            break;
+        }
+
+        switch (polled.value().event.type()) {
+            case QUICHE_H3_EVENT_DATA: {
+                std::array<uint8_t, MAX_DATAGRAM_SIZE> buf;
+
+                while (true) {
+                    auto len = h3_client.recv_body(client, stream_id, buf);
+
+                    if (!len) {
+                        break;
+                    }
+
+                    body.append(reinterpret_cast<const char *>(buf.data()),
+                                *len);
+                }
+
+                break;
+            }
+
+            case QUICHE_H3_EVENT_FINISHED:
+                finished = true;
+                break;
+
+            default:
+                break;
+        }
+    }
+
+    if (!finished || body != BODY) {
+        fprintf(stderr, "unexpected response body: %s\n", body.c_str());
+        return 1;
+    }
+
+    if (quiche::h3::to_field_value(priority) != "u=1,i") {
+        fprintf(stderr, "unexpected priority serialization\n");
+        return 1;
+    }
+
+    client.close(true, 0, "done").value();
+
+    printf("%s\n", body.c_str());
+
+    return 0;
+}
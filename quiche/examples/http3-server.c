@@ -27,6 +27,7 @@
 #include <inttypes.h>
 #include <stdio.h>
 #include <stdlib.h>
+#include <string.h>
 #include <stdint.h>
 #include <stdbool.h>
 #include <unistd.h>
@@ -229,12 +230,30 @@ static struct conn_io *create_conn(uint8_t *scid, size_t scid_len,
 static int for_each_header(uint8_t *name, size_t name_len,
                            uint8_t *value, size_t value_len,
                            void *argp) {
+    quiche_h3_priority *priority = argp;
+
     fprintf(stderr, "got HTTP header: %.*s=%.*s\n",
             (int) name_len, name, (int) value_len, value);
 
+    if (name_len == sizeof("priority") - 1 &&
+        memcmp(name, "priority", name_len) == 0) {
+        // Keep the default priority if the field value is invalid.
+        quiche_h3_parse_extensible_priority(value, value_len, priority);
+    }
+
     return 0;
 }
 
+static int parse_priority_update(uint8_t *priority_field_value,
+                                 uint64_t priority_field_value_len,
+                                 void *argp) {
+    quiche_h3_priority *priority = argp;
+
+    return quiche_h3_parse_extensible_priority(priority_field_value,
+                                               priority_field_value_len,
+                                               priority);
+}
+
 static void recv_cb(EV_P_ ev_io *w, int revents) {
     struct conn_io *tmp, *conn_io = NULL;
 
@@ -403,9 +422,14 @@ static void recv_cb(EV_P_ ev_io *w, int revents) {
 
                 switch (quiche_h3_event_type(ev)) {
                     case QUICHE_H3_EVENT_HEADERS: {
+                        quiche_h3_priority priority = {
+                            .urgency = 3,
+                            .incremental = false,
+                        };
+
                         int rc = quiche_h3_event_for_each_header(ev,
                                                                  for_each_header,
-                                                                 NULL);
+                                                                 &priority);
 
                         if (rc != 0) {
                             fprintf(stderr, "failed to process headers\n");
@@ -437,8 +461,11 @@ static void recv_cb(EV_P_ ev_io *w, int revents) {
                             },
                         };
 
-                        quiche_h3_send_response(conn_io->http3, conn_io->conn,
-                                                s, headers, 3, false);
+                        quiche_h3_send_response_with_priority(conn_io->http3,
+                                                              conn_io->conn,
+                                                              s, headers, 3,
+                                                              &priority,
+                                                              false);
 
                         quiche_h3_send_body(conn_io->http3, conn_io->conn,
                                             s, (uint8_t *) "byez\n", 5, true);
@@ -456,9 +483,28 @@ static void recv_cb(EV_P_ ev_io *w, int revents) {
                     case QUICHE_H3_EVENT_RESET:
                         break;
 
-                    case QUICHE_H3_EVENT_PRIORITY_UPDATE:
+                    case QUICHE_H3_EVENT_CANCELLED:
                         break;
 
+                    case QUICHE_H3_EVENT_PRIORITY_UPDATE: {
+                        quiche_h3_priority priority = {
+                            .urgency = 3,
+                            .incremental = false,
+                        };
+
+                        int rc = quiche_h3_take_last_priority_update(
+                            conn_io->http3, s, parse_priority_update,
+                            &priority);
+
+                        if (rc == 0) {
+                            quiche_h3_stream_priority(conn_io->http3,
+                                                      conn_io->conn, s,
+                                                      &priority);
+                        }
+
+                        break;
+                    }
+
                     case QUICHE_H3_EVENT_GOAWAY: {
                         fprintf(stderr, "got GOAWAY\n");
                         break;
@@ -292,6 +292,7 @@ fn main() {
             let recv_info = quiche::RecvInfo {
                 to: socket.local_addr().unwrap(),
                 from,
+                ecn: quiche::Ecn::NotEct,
             };
 
             // Process potentially coalesced packets.
@@ -367,6 +368,9 @@ fn main() {
 
                         Ok((_stream_id, quiche::h3::Event::Reset { .. })) => (),
 
+                        Ok((_stream_id, quiche::h3::Event::Cancelled { .. })) =>
+                            (),
+
                         Ok((
                             _prioritized_element_id,
                             quiche::h3::Event::PriorityUpdate,
@@ -374,6 +378,9 @@ fn main() {
 
                         Ok((_goaway_id, quiche::h3::Event::GoAway)) => (),
 
+                        Ok((_stream_id, quiche::h3::Event::Metadata { .. })) =>
+                            (),
+
                         Err(quiche::h3::Error::Done) => {
                             break;
                         },
@@ -58,6 +58,12 @@ extern "C" {
 // The current QUIC wire version.
 #define QUICHE_PROTOCOL_VERSION 0x00000001
 
+// QUIC version 1 (RFC 9000).
+#define QUICHE_PROTOCOL_VERSION_V1 0x00000001
+
+// QUIC version 2 (RFC 9369).
+#define QUICHE_PROTOCOL_VERSION_V2 0x6b3343cf
+
 // The maximum length of a connection ID.
 #define QUICHE_MAX_CONN_ID_LEN 20
 
@@ -127,6 +133,9 @@ enum quiche_error {
 
     // The peer sent more data in CRYPTO frames than we can buffer.
     QUICHE_ERR_CRYPTO_BUFFER_EXCEEDED = -20,
+
+    // The peer's version information doesn't match the negotiated version.
+    QUICHE_ERR_VERSION_NEGOTIATION = -21,
 };
 
 // Returns a human readable string with the quiche version number.
@@ -167,12 +176,20 @@ void quiche_config_grease(quiche_config *config, bool v);
 // Configures whether to do path MTU discovery.
 void quiche_config_discover_pmtu(quiche_config *config, bool v);
 
+// Sets the ECN codepoint used to mark outgoing packets, either ECT(0) (2) or
+// ECT(1) (1). Any other value disables marking.
+void quiche_config_set_ecn(quiche_config *config, uint8_t v);
+
 // Enables logging of secrets.
 void quiche_config_log_keys(quiche_config *config);
 
 // Enables sending or receiving early data.
 void quiche_config_enable_early_data(quiche_config *config);
 
+// Configures the QUIC versions that can be used, in order of preference.
+int quiche_config_set_versions(quiche_config *config, const uint32_t *versions,
+                               size_t versions_len);
+
 // Configures the list of supported application protocols.
 int quiche_config_set_application_protos(quiche_config *config,
                                          const uint8_t *protos,
@@ -218,6 +235,19 @@ void quiche_config_set_max_ack_delay(quiche_config *config, uint64_t v);
 // Sets the `disable_active_migration` transport parameter.
 void quiche_config_set_disable_active_migration(quiche_config *config, bool v);
 
+// Enables the multipath extension.
+void quiche_config_enable_multipath(quiche_config *config, bool v);
+
+// Sets the `min_ack_delay` transport parameter, in microseconds.
+void quiche_config_set_min_ack_delay(quiche_config *config, uint64_t v);
+
+// Sets the ack-eliciting threshold requested from the peer.
+void quiche_config_set_ack_eliciting_threshold(quiche_config *config, uint64_t v);
+
+// Sets the maximum ACK delay requested from the peer, in milliseconds (unlike
+// `min_ack_delay`, which is in microseconds).
+void quiche_config_set_requested_max_ack_delay(quiche_config *config, uint64_t v);
+
 // Sets the congestion control algorithm used by string.
 int quiche_config_set_cc_algorithm_name(quiche_config *config, const char *algo);
 
@@ -229,6 +259,8 @@ enum quiche_cc_algorithm {
     QUICHE_CC_CUBIC = 1,
     QUICHE_CC_BBR = 2,
     QUICHE_CC_BBR2 = 3,
+    QUICHE_CC_BBR2_GCONGESTION = 4,
+    QUICHE_CC_BBR3 = 5,
 };
 
 // Sets the congestion control algorithm used.
@@ -243,6 +275,24 @@ void quiche_config_enable_pacing(quiche_config *config, bool v);
 // Configures max pacing rate to be used.
 void quiche_config_set_max_pacing_rate(quiche_config *config, uint64_t v);
 
+// Sets the pacer implementation used by string.
+int quiche_config_set_pacer_algorithm_name(quiche_config *config, const char *algo);
+
+enum quiche_pacer_algorithm {
+    QUICHE_PACER_LEAKY_BUCKET = 0,
+    QUICHE_PACER_TOKEN_BUCKET = 1,
+    QUICHE_PACER_HYBRID = 2,
+};
+
+// Sets the pacer implementation used.
+void quiche_config_set_pacer_algorithm(quiche_config *config, enum quiche_pacer_algorithm algo);
+
+// Sets the max burst size of the pacer, in packets.
+void quiche_config_set_max_pacing_burst(quiche_config *config, size_t packets);
+
+// Sets the multiplier applied to cwnd / srtt to compute the pacing rate.
+void quiche_config_set_pacing_gain(quiche_config *config, double v);
+
 // Configures whether to enable receiving DATAGRAM frames.
 void quiche_config_enable_dgram(quiche_config *config, bool enabled,
                                 size_t recv_queue_len,
@@ -330,6 +380,15 @@ bool quiche_conn_set_qlog_path(quiche_conn *conn, const char *path,
 void quiche_conn_set_qlog_fd(quiche_conn *conn, int fd, const char *log_title,
                              const char *log_desc);
 
+// Enables qlog, passing each serialized JSON-SEQ record to the given callback
+// instead of writing it to a file. The record is only valid for the duration
+// of the callback.
+void quiche_conn_set_qlog_cb(quiche_conn *conn,
+                             void (*cb)(const uint8_t *data, size_t data_len,
+                                        void *argp),
+                             void *argp, const char *log_title,
+                             const char *log_desc);
+
 // Configures the given session for resumption.
 int quiche_conn_set_session(quiche_conn *conn, const uint8_t *buf, size_t buf_len);
 
@@ -345,12 +404,22 @@ typedef struct {
     // The local address the packet was received on.
     struct sockaddr *to;
     socklen_t to_len;
+
+    // The ECN codepoint of the IP header of the packet.
+    uint8_t ecn;
 } quiche_recv_info;
 
 // Processes QUIC packets received from the peer.
 ssize_t quiche_conn_recv(quiche_conn *conn, uint8_t *buf, size_t buf_len,
                          const quiche_recv_info *info);
 
+// Processes a batch of UDP datagrams received from the peer. The "bufs",
+// "buf_lens" and "infos" arrays must all have "count" elements. Empty buffers
+// are skipped.
+ssize_t quiche_conn_recv_batch(quiche_conn *conn, uint8_t *const *bufs,
+                               const size_t *buf_lens,
+                               const quiche_recv_info *infos, size_t count);
+
 typedef struct {
     // The local address the packet should be sent from.
     struct sockaddr_storage from;
@@ -362,6 +431,12 @@ typedef struct {
 
     // The time to send the packet out.
     struct timespec at;
+
+    // The ECN codepoint the IP header of the packet should be marked with.
+    uint8_t ecn;
+
+    // The pacing rate of the path the packet is sent on, in bytes/s.
+    uint64_t pacing_rate;
 } quiche_send_info;
 
 // Writes a single QUIC packet to be sent to the peer.
@@ -378,6 +453,13 @@ ssize_t quiche_conn_send_on_path(quiche_conn *conn, uint8_t *out, size_t out_len
                                  const struct sockaddr *to, socklen_t to_len,
                                  quiche_send_info *out_info);
 
+// Writes multiple QUIC packets of the same size to be sent to the peer, so
+// that they can be sent with UDP GSO. The size of each datagram is written
+// to "out_segment_size", and the last datagram can be shorter than that.
+ssize_t quiche_conn_send_multiple(quiche_conn *conn, uint8_t *out, size_t out_len,
+                                  size_t *out_segment_size,
+                                  quiche_send_info *out_info);
+
 // Returns the size of the send quantum over the given 4-tuple, in bytes.
 size_t quiche_conn_send_quantum_on_path(const quiche_conn *conn,
                                         const struct sockaddr *local_addr, socklen_t local_len,
@@ -587,6 +669,10 @@ typedef struct {
 
     // The number of streams stopped by remote.
     uint64_t stopped_stream_count_remote;
+
+    // The number of sent QUIC packets that the peer reported as marked with
+    // ECN Congestion Experienced.
+    uint64_t ecn_ce_count;
 } quiche_stats;
 
 // Collects and returns statistics about the connection.
@@ -693,6 +779,16 @@ typedef struct {
 
     // The most recent data delivery rate estimate in bytes/s.
     uint64_t delivery_rate;
+
+    // The current pacing rate in bytes/s.
+    uint64_t pacing_rate;
+
+    // Whether ECN was validated on this path.
+    bool ecn_capable;
+
+    // The number of packets sent on this path that the peer reported as
+    // marked with ECN Congestion Experienced.
+    uint64_t ecn_ce_count;
 } quiche_path_stats;
 
 
@@ -702,6 +798,9 @@ typedef struct {
 // field of `quiche_stats`).
 int quiche_conn_path_stats(const quiche_conn *conn, size_t idx, quiche_path_stats *out);
 
+// Returns the QUIC version used by the connection.
+uint32_t quiche_conn_version(const quiche_conn *conn);
+
 // Returns whether or not this is a server-side connection.
 bool quiche_conn_is_server(const quiche_conn *conn);
 
@@ -788,6 +887,19 @@ int quiche_conn_migrate(quiche_conn *conn,
                              const struct sockaddr *peer, socklen_t peer_len,
                              uint64_t *seq);
 
+// Asks the peer to acknowledge packets immediately.
+int quiche_conn_send_immediate_ack(quiche_conn *conn);
+
+// Returns true if the multipath extension was negotiated.
+bool quiche_conn_is_multipath_enabled(const quiche_conn *conn);
+
+// Abandons the path identified by the given address tuple.
+int quiche_conn_abandon_path(quiche_conn *conn,
+                             const struct sockaddr *local, socklen_t local_len,
+                             const struct sockaddr *peer, socklen_t peer_len,
+                             uint64_t error_code, const uint8_t *reason,
+                             size_t reason_len);
+
 enum quiche_path_event_type {
     QUICHE_PATH_EVENT_NEW,
     QUICHE_PATH_EVENT_VALIDATED,
@@ -795,6 +907,7 @@ enum quiche_path_event_type {
     QUICHE_PATH_EVENT_CLOSED,
     QUICHE_PATH_EVENT_REUSED_SOURCE_CONNECTION_ID,
     QUICHE_PATH_EVENT_PEER_MIGRATED,
+    QUICHE_PATH_EVENT_VALIDATION_STARTED,
 };
 
 typedef struct quiche_path_event quiche_path_event;
@@ -809,6 +922,10 @@ enum quiche_path_event_type quiche_path_event_type(const quiche_path_event *ev);
 void quiche_path_event_new(const quiche_path_event *ev,
                            struct sockaddr_storage *local, socklen_t *local_len, struct sockaddr_storage *peer, socklen_t *peer_len);
 
+// Should be called if the quiche_path_event_type(...) returns QUICHE_PATH_EVENT_VALIDATION_STARTED.
+void quiche_path_event_validation_started(const quiche_path_event *ev,
+                           struct sockaddr_storage *local, socklen_t *local_len, struct sockaddr_storage *peer, socklen_t *peer_len);
+
 // Should be called if the quiche_path_event_type(...) returns QUICHE_PATH_EVENT_VALIDATED.
 void quiche_path_event_validated(const quiche_path_event *ev,
                            struct sockaddr_storage *local, socklen_t *local_len, struct sockaddr_storage *peer, socklen_t *peer_len);
@@ -1023,6 +1140,9 @@ void quiche_h3_config_set_qpack_blocked_streams(quiche_h3_config *config, uint64
 // Sets the `SETTINGS_ENABLE_CONNECT_PROTOCOL` setting.
 void quiche_h3_config_enable_extended_connect(quiche_h3_config *config, bool enabled);
 
+// Sets the `SETTINGS_ENABLE_METADATA` setting.
+void quiche_h3_config_enable_metadata(quiche_h3_config *config, bool enabled);
+
 // Frees the HTTP/3 config object.
 void quiche_h3_config_free(quiche_h3_config *config);
 
@@ -1040,6 +1160,21 @@ enum quiche_h3_event_type {
     QUICHE_H3_EVENT_GOAWAY,
     QUICHE_H3_EVENT_RESET,
     QUICHE_H3_EVENT_PRIORITY_UPDATE,
+    QUICHE_H3_EVENT_CANCELLED,
+    QUICHE_H3_EVENT_METADATA,
+};
+
+// How a request was cancelled by the peer.
+enum quiche_h3_cancellation {
+    // The server rejected the request without processing it, so it can
+    // safely be retried.
+    QUICHE_H3_CANCELLATION_REJECTED,
+
+    // The request was cancelled before the response started.
+    QUICHE_H3_CANCELLATION_BEFORE_RESPONSE,
+
+    // The request was cancelled after the response started.
+    QUICHE_H3_CANCELLATION_MID_RESPONSE,
 };
 
 typedef struct quiche_h3_event quiche_h3_event;
@@ -1051,7 +1186,8 @@ int64_t quiche_h3_conn_poll(quiche_h3_conn *conn, quiche_conn *quic_conn,
 // Returns the type of the event.
 enum quiche_h3_event_type quiche_h3_event_type(quiche_h3_event *ev);
 
-// Iterates over the headers in the event.
+// Iterates over the headers in the event, or over the fields of
+// QUICHE_H3_EVENT_METADATA events.
 //
 // The `cb` callback will be called for each header in `ev`. `cb` should check
 // the validity of pseudo-headers and headers. If `cb` returns any value other
@@ -1076,9 +1212,15 @@ int quiche_h3_for_each_setting(quiche_h3_conn *conn,
 // Check whether more frames will follow the headers on the stream.
 bool quiche_h3_event_headers_has_more_frames(quiche_h3_event *ev);
 
+// Returns how the request was cancelled, for QUICHE_H3_EVENT_CANCELLED events.
+enum quiche_h3_cancellation quiche_h3_event_cancellation(quiche_h3_event *ev);
+
 // Check whether or not extended connection is enabled by the peer
 bool quiche_h3_extended_connect_enabled_by_peer(quiche_h3_conn *conn);
 
+// Check whether or not the METADATA extension is enabled by the peer
+bool quiche_h3_metadata_enabled_by_peer(quiche_h3_conn *conn);
+
 // Frees the HTTP/3 event object.
 void quiche_h3_event_free(quiche_h3_event *ev);
 
@@ -1110,7 +1252,7 @@ int quiche_h3_send_response(quiche_h3_conn *conn, quiche_conn *quic_conn,
 int quiche_h3_send_response_with_priority(quiche_h3_conn *conn,
                             quiche_conn *quic_conn, uint64_t stream_id,
                             const quiche_h3_header *headers, size_t headers_len,
-                            quiche_h3_priority *priority, bool fin);
+                            const quiche_h3_priority *priority, bool fin);
 
 // Sends additional HTTP/3 headers on the specified stream.
 int quiche_h3_send_additional_headers(quiche_h3_conn *conn,
@@ -1118,6 +1260,11 @@ int quiche_h3_send_additional_headers(quiche_h3_conn *conn,
                             quiche_h3_header *headers, size_t headers_len,
                             bool is_trailer_section, bool fin);
 
+// Sends a METADATA frame with the given fields on the specified stream.
+int quiche_h3_send_metadata(quiche_h3_conn *conn, quiche_conn *quic_conn,
+                            uint64_t stream_id, quiche_h3_header *fields,
+                            size_t fields_len);
+
 // Sends an HTTP/3 body chunk on the given stream.
 ssize_t quiche_h3_send_body(quiche_h3_conn *conn, quiche_conn *quic_conn,
                             uint64_t stream_id, const uint8_t *body, size_t body_len,
@@ -1131,17 +1278,40 @@ ssize_t quiche_h3_recv_body(quiche_h3_conn *conn, quiche_conn *quic_conn,
 int quiche_h3_send_goaway(quiche_h3_conn *conn, quiche_conn *quic_conn,
                           uint64_t id);
 
-// Try to parse an Extensible Priority field value.
-int quiche_h3_parse_extensible_priority(uint8_t *priority,
+// Cancels a request by aborting both directions of the request stream with
+// the H3_REQUEST_CANCELLED error code.
+int quiche_h3_cancel_request(quiche_h3_conn *conn, quiche_conn *quic_conn,
+                             uint64_t stream_id);
+
+// Try to parse an Extensible Priority field value, e.g. the value of a
+// Priority header field or of a PRIORITY_UPDATE frame.
+//
+// Omitted parameters yield default values, and urgency values outside of the
+// valid range (0 through 7) are clamped to 7. This requires the `sfv` feature.
+int quiche_h3_parse_extensible_priority(const uint8_t *priority,
                                         size_t priority_len,
                                         quiche_h3_priority *parsed);
 
+// Serializes a priority as an Extensible Priority field value, e.g. to send as
+// the value of a Priority header field.
+//
+// Returns the number of bytes written to `out`, or
+// QUICHE_H3_ERR_BUFFER_TOO_SHORT if `out` is too small.
+ssize_t quiche_h3_priority_to_field_value(const quiche_h3_priority *priority,
+                                          uint8_t *out, size_t out_len);
+
+// Sets the priority of a request stream, e.g. to apply a PRIORITY_UPDATE
+// received after the response was sent.
+int quiche_h3_stream_priority(quiche_h3_conn *conn, quiche_conn *quic_conn,
+                              uint64_t stream_id,
+                              const quiche_h3_priority *priority);
+
 /// Sends a PRIORITY_UPDATE frame on the control stream with specified
 /// request stream ID and priority.
 int quiche_h3_send_priority_update_for_request(quiche_h3_conn *conn,
                                                quiche_conn *quic_conn,
                                                uint64_t stream_id,
-                                               quiche_h3_priority *priority);
+                                               const quiche_h3_priority *priority);
 
 // Take the last received PRIORITY_UPDATE frame for a stream.
 //
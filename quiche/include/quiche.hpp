@@ -0,0 +1,1361 @@
+// Copyright (C) 2025, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+// Header-only C++20 wrapper around the quiche C API.
+//
+// Objects returned by the C API are owned by move-only RAII types that free
+// them on destruction. Buffers are passed as `std::span`, and fallible calls
+// return a `quiche::result<T>` that either holds a value or a
+// `std::error_code`. Callers can check the result explicitly, or call
+// `value()` which throws `quiche::error` on failure (or aborts when
+// exceptions are disabled).
+//
+// Note that "done" is reported as an error with code `quiche::errc::done`
+// (or `quiche::h3::errc::done`), exactly like the C API does.
+
+#ifndef QUICHE_HPP
+#define QUICHE_HPP
+
+#include <quiche.h>
+
+#include <chrono>
+#include <cstdint>
+#include <cstdlib>
+#include <functional>
+#include <memory>
+#include <new>
+#include <optional>
+#include <span>
+#include <string>
+#include <string_view>
+#include <system_error>
+#include <type_traits>
+#include <utility>
+#include <vector>
+
+namespace quiche {
+
+// QUIC transport errors, see `enum quiche_error`.
+enum class errc : int {
+    done = QUICHE_ERR_DONE,
+    buffer_too_short = QUICHE_ERR_BUFFER_TOO_SHORT,
+    unknown_version = QUICHE_ERR_UNKNOWN_VERSION,
+    invalid_frame = QUICHE_ERR_INVALID_FRAME,
+    invalid_packet = QUICHE_ERR_INVALID_PACKET,
+    invalid_state = QUICHE_ERR_INVALID_STATE,
+    invalid_stream_state = QUICHE_ERR_INVALID_STREAM_STATE,
+    invalid_transport_param = QUICHE_ERR_INVALID_TRANSPORT_PARAM,
+    crypto_fail = QUICHE_ERR_CRYPTO_FAIL,
+    tls_fail = QUICHE_ERR_TLS_FAIL,
+    flow_control = QUICHE_ERR_FLOW_CONTROL,
+    stream_limit = QUICHE_ERR_STREAM_LIMIT,
+    stream_stopped = QUICHE_ERR_STREAM_STOPPED,
+    stream_reset = QUICHE_ERR_STREAM_RESET,
+    final_size = QUICHE_ERR_FINAL_SIZE,
+    congestion_control = QUICHE_ERR_CONGESTION_CONTROL,
+    id_limit = QUICHE_ERR_ID_LIMIT,
+    out_of_identifiers = QUICHE_ERR_OUT_OF_IDENTIFIERS,
+    key_update = QUICHE_ERR_KEY_UPDATE,
+    crypto_buffer_exceeded = QUICHE_ERR_CRYPTO_BUFFER_EXCEEDED,
+};
+
+namespace h3 {
+
+enum class errc : int;
+
+} // namespace h3
+
+} // namespace quiche
+
+template <>
+struct std::is_error_code_enum<quiche::errc> : std::true_type {};
+
+template <>
+struct std::is_error_code_enum<quiche::h3::errc> : std::true_type {};
+
+namespace quiche {
+
+namespace detail {
+
+class quic_category : public std::error_category {
+  public:
+    const char *name() const noexcept override {
+        return "quiche";
+    }
+
+    std::string message(int ev) const override {
+        switch (static_cast<errc>(ev)) {
+            case errc::done:
+                return "no more work to do";
+            case errc::buffer_too_short:
+                return "buffer too short";
+            case errc::unknown_version:
+                return "unknown version";
+            case errc::invalid_frame:
+                return "invalid frame";
+            case errc::invalid_packet:
+                return "invalid packet";
+            case errc::invalid_state:
+                return "invalid connection state";
+            case errc::invalid_stream_state:
+                return "invalid stream state";
+            case errc::invalid_transport_param:
+                return "invalid transport parameter";
+            case errc::crypto_fail:
+                return "crypto operation failed";
+            case errc::tls_fail:
+                return "TLS handshake failed";
+            case errc::flow_control:
+                return "flow control limit violated";
+            case errc::stream_limit:
+                return "stream limit violated";
+            case errc::stream_stopped:
+                return "stream stopped by peer";
+            case errc::stream_reset:
+                return "stream reset by peer";
+            case errc::final_size:
+                return "data exceeds stream final size";
+            case errc::congestion_control:
+                return "congestion control error";
+            case errc::id_limit:
+                return "too many identifiers provided";
+            case errc::out_of_identifiers:
+                return "not enough available identifiers";
+            case errc::key_update:
+                return "key update error";
+            case errc::crypto_buffer_exceeded:
+                return "crypto buffer exceeded";
+        }
+
+        return "unknown quiche error " + std::to_string(ev);
+    }
+};
+
+} // namespace detail
+
+// Returns the error category of QUIC transport errors.
+inline const std::error_category &quic_category() noexcept {
+    static const detail::quic_category category;
+    return category;
+}
+
+inline std::error_code make_error_code(errc e) noexcept {
+    return {static_cast<int>(e), quic_category()};
+}
+
+// The exception thrown by `result::value()` on failure.
+class error : public std::system_error {
+  public:
+    explicit error(std::error_code ec) : std::system_error(ec) {}
+};
+
+namespace detail {
+
+[[noreturn]] inline void throw_error(std::error_code ec) {
+#if defined(__cpp_exceptions)
+    throw error(ec);
+#else
+    (void) ec;
+    std::abort();
+#endif
+}
+
+[[noreturn]] inline void throw_bad_alloc() {
+#if defined(__cpp_exceptions)
+    throw std::bad_alloc();
+#else
+    std::abort();
+#endif
+}
+
+} // namespace detail
+
+// Either a value of type `T` or the error that prevented producing it.
+template <typename T>
+class [[nodiscard]] result {
+  public:
+    result(T value) : value_(std::move(value)) {}
+
+    result(std::error_code ec) : ec_(ec) {}
+
+    template <typename E,
+              typename = std::enable_if_t<std::is_error_code_enum_v<E>>>
+    result(E e) : ec_(e) {}
+
+    bool has_value() const noexcept {
+        return value_.has_value();
+    }
+
+    explicit operator bool() const noexcept {
+        return has_value();
+    }
+
+    // Returns the value, throwing `quiche::error` if there is none.
+    T &value() & {
+        if (!value_) {
+            detail::throw_error(ec_);
+        }
+
+        return *value_;
+    }
+
+    const T &value() const & {
+        if (!value_) {
+            detail::throw_error(ec_);
+        }
+
+        return *value_;
+    }
+
+    T &&value() && {
+        if (!value_) {
+            detail::throw_error(ec_);
+        }
+
+        return std::move(*value_);
+    }
+
+    T &operator*() & {
+        return *value_;
+    }
+
+    const T &operator*() const & {
+        return *value_;
+    }
+
+    T *operator->() {
+        return &*value_;
+    }
+
+    const T *operator->() const {
+        return &*value_;
+    }
+
+    template <typename U>
+    T value_or(U &&fallback) const & {
+        return value_ ? *value_ : static_cast<T>(std::forward<U>(fallback));
+    }
+
+    // Returns the error, or an empty error code on success.
+    std::error_code error() const noexcept {
+        return ec_;
+    }
+
+  private:
+    std::optional<T> value_;
+    std::error_code ec_;
+};
+
+template <>
+class [[nodiscard]] result<void> {
+  public:
+    result() = default;
+
+    result(std::error_code ec) : ec_(ec) {}
+
+    template <typename E,
+              typename = std::enable_if_t<std::is_error_code_enum_v<E>>>
+    result(E e) : ec_(e) {}
+
+    bool has_value() const noexcept {
+        return !ec_;
+    }
+
+    explicit operator bool() const noexcept {
+        return has_value();
+    }
+
+    // Throws `quiche::error` if the operation failed.
+    void value() const {
+        if (ec_) {
+            detail::throw_error(ec_);
+        }
+    }
+
+    std::error_code error() const noexcept {
+        return ec_;
+    }
+
+  private:
+    std::error_code ec_;
+};
+
+namespace detail {
+
+inline result<void> check(int rc) {
+    if (rc < 0) {
+        return make_error_code(static_cast<errc>(rc));
+    }
+
+    return {};
+}
+
+inline result<size_t> check_len(ssize_t rc) {
+    if (rc < 0) {
+        return make_error_code(static_cast<errc>(rc));
+    }
+
+    return static_cast<size_t>(rc);
+}
+
+template <auto Free>
+struct deleter {
+    template <typename T>
+    void operator()(T *ptr) const noexcept {
+        Free(ptr);
+    }
+};
+
+template <typename T, auto Free>
+using handle = std::unique_ptr<T, deleter<Free>>;
+
+inline std::span<const uint8_t> bytes(const uint8_t *ptr, size_t len) {
+    return ptr == nullptr ? std::span<const uint8_t>() :
+                            std::span<const uint8_t>(ptr, len);
+}
+
+inline std::string_view chars(const uint8_t *ptr, size_t len) {
+    return ptr == nullptr ?
+               std::string_view() :
+               std::string_view(reinterpret_cast<const char *>(ptr), len);
+}
+
+} // namespace detail
+
+// Returns the quiche version.
+inline std::string_view version() noexcept {
+    return quiche_version();
+}
+
+// Stores configuration shared between multiple connections.
+class config {
+  public:
+    explicit config(uint32_t version = QUICHE_PROTOCOL_VERSION)
+        : ptr_(quiche_config_new(version)) {
+        if (!ptr_) {
+            detail::throw_bad_alloc();
+        }
+    }
+
+    quiche_config *native_handle() const noexcept {
+        return ptr_.get();
+    }
+
+    result<void> load_cert_chain_from_pem_file(const char *path) {
+        return detail::check(
+            quiche_config_load_cert_chain_from_pem_file(ptr_.get(), path));
+    }
+
+    result<void> load_priv_key_from_pem_file(const char *path) {
+        return detail::check(
+            quiche_config_load_priv_key_from_pem_file(ptr_.get(), path));
+    }
+
+    result<void> load_verify_locations_from_file(const char *path) {
+        return detail::check(
+            quiche_config_load_verify_locations_from_file(ptr_.get(), path));
+    }
+
+    result<void> load_verify_locations_from_directory(const char *path) {
+        return detail::check(quiche_config_load_verify_locations_from_directory(
+            ptr_.get(), path));
+    }
+
+    // Sets the ALPN list, in wire format (e.g. QUICHE_H3_APPLICATION_PROTOCOL).
+    result<void> set_application_protos(std::span<const uint8_t> protos) {
+        return detail::check(quiche_config_set_application_protos(
+            ptr_.get(), protos.data(), protos.size()));
+    }
+
+    result<void> set_application_protos(std::string_view protos) {
+        return set_application_protos(std::span<const uint8_t>(
+            reinterpret_cast<const uint8_t *>(protos.data()), protos.size()));
+    }
+
+    result<void> set_cc_algorithm_name(const char *algo) {
+        return detail::check(
+            quiche_config_set_cc_algorithm_name(ptr_.get(), algo));
+    }
+
+    result<void> set_ticket_key(std::span<const uint8_t> key) {
+        return detail::check(quiche_config_set_ticket_key(
+            ptr_.get(), key.data(), key.size()));
+    }
+
+    void verify_peer(bool v) {
+        quiche_config_verify_peer(ptr_.get(), v);
+    }
+
+    void grease(bool v) {
+        quiche_config_grease(ptr_.get(), v);
+    }
+
+    void discover_pmtu(bool v) {
+        quiche_config_discover_pmtu(ptr_.get(), v);
+    }
+
+    void log_keys() {
+        quiche_config_log_keys(ptr_.get());
+    }
+
+    void enable_early_data() {
+        quiche_config_enable_early_data(ptr_.get());
+    }
+
+    void set_max_amplification_factor(size_t v) {
+        quiche_config_set_max_amplification_factor(ptr_.get(), v);
+    }
+
+    void set_max_idle_timeout(std::chrono::milliseconds v) {
+        quiche_config_set_max_idle_timeout(ptr_.get(), v.count());
+    }
+
+    void set_max_recv_udp_payload_size(size_t v) {
+        quiche_config_set_max_recv_udp_payload_size(ptr_.get(), v);
+    }
+
+    void set_max_send_udp_payload_size(size_t v) {
+        quiche_config_set_max_send_udp_payload_size(ptr_.get(), v);
+    }
+
+    void set_initial_max_data(uint64_t v) {
+        quiche_config_set_initial_max_data(ptr_.get(), v);
+    }
+
+    void set_initial_max_stream_data_bidi_local(uint64_t v) {
+        quiche_config_set_initial_max_stream_data_bidi_local(ptr_.get(), v);
+    }
+
+    void set_initial_max_stream_data_bidi_remote(uint64_t v) {
+        quiche_config_set_initial_max_stream_data_bidi_remote(ptr_.get(), v);
+    }
+
+    void set_initial_max_stream_data_uni(uint64_t v) {
+        quiche_config_set_initial_max_stream_data_uni(ptr_.get(), v);
+    }
+
+    void set_initial_max_streams_bidi(uint64_t v) {
+        quiche_config_set_initial_max_streams_bidi(ptr_.get(), v);
+    }
+
+    void set_initial_max_streams_uni(uint64_t v) {
+        quiche_config_set_initial_max_streams_uni(ptr_.get(), v);
+    }
+
+    void set_ack_delay_exponent(uint64_t v) {
+        quiche_config_set_ack_delay_exponent(ptr_.get(), v);
+    }
+
+    void set_max_ack_delay(std::chrono::milliseconds v) {
+        quiche_config_set_max_ack_delay(ptr_.get(), v.count());
+    }
+
+    void set_disable_active_migration(bool v) {
+        quiche_config_set_disable_active_migration(ptr_.get(), v);
+    }
+
+    void set_cc_algorithm(enum quiche_cc_algorithm algo) {
+        quiche_config_set_cc_algorithm(ptr_.get(), algo);
+    }
+
+    void set_initial_congestion_window_packets(size_t packets) {
+        quiche_config_set_initial_congestion_window_packets(ptr_.get(),
+                                                            packets);
+    }
+
+    void enable_hystart(bool v) {
+        quiche_config_enable_hystart(ptr_.get(), v);
+    }
+
+    void enable_pacing(bool v) {
+        quiche_config_enable_pacing(ptr_.get(), v);
+    }
+
+    void set_max_pacing_rate(uint64_t v) {
+        quiche_config_set_max_pacing_rate(ptr_.get(), v);
+    }
+
+    void enable_dgram(bool enabled, size_t recv_queue_len,
+                      size_t send_queue_len) {
+        quiche_config_enable_dgram(ptr_.get(), enabled, recv_queue_len,
+                                   send_queue_len);
+    }
+
+    void set_max_connection_window(uint64_t v) {
+        quiche_config_set_max_connection_window(ptr_.get(), v);
+    }
+
+    void set_max_stream_window(uint64_t v) {
+        quiche_config_set_max_stream_window(ptr_.get(), v);
+    }
+
+    void set_active_connection_id_limit(uint64_t v) {
+        quiche_config_set_active_connection_id_limit(ptr_.get(), v);
+    }
+
+    void set_stateless_reset_token(std::span<const uint8_t, 16> token) {
+        quiche_config_set_stateless_reset_token(ptr_.get(), token.data());
+    }
+
+    void set_disable_dcid_reuse(bool v) {
+        quiche_config_set_disable_dcid_reuse(ptr_.get(), v);
+    }
+
+  private:
+    detail::handle<quiche_config, quiche_config_free> ptr_;
+};
+
+// The result of a successful stream read.
+struct stream_read {
+    size_t len;
+    bool fin;
+};
+
+// An error reported by a connection's peer, or queued locally.
+struct connection_error {
+    bool is_app;
+    uint64_t error_code;
+    std::string_view reason;
+};
+
+// A QUIC connection.
+class connection {
+  public:
+    // Called with each serialized qlog record.
+    using qlog_callback = std::function<void(std::span<const uint8_t>)>;
+
+    // Takes ownership of a connection created through the C API.
+    explicit connection(quiche_conn *conn) noexcept : ptr_(conn) {}
+
+    // Creates a new client-side connection.
+    static result<connection> connect(const char *server_name,
+                                      std::span<const uint8_t> scid,
+                                      const struct sockaddr *local,
+                                      socklen_t local_len,
+                                      const struct sockaddr *peer,
+                                      socklen_t peer_len, config &config) {
+        quiche_conn *conn = quiche_connect(
+            server_name, scid.data(), scid.size(), local, local_len, peer,
+            peer_len, config.native_handle());
+
+        if (conn == nullptr) {
+            return errc::invalid_state;
+        }
+
+        return connection(conn);
+    }
+
+    // Creates a new server-side connection.
+    static result<connection> accept(std::span<const uint8_t> scid,
+                                     std::span<const uint8_t> odcid,
+                                     const struct sockaddr *local,
+                                     socklen_t local_len,
+                                     const struct sockaddr *peer,
+                                     socklen_t peer_len, config &config) {
+        quiche_conn *conn = quiche_accept(
+            scid.data(), scid.size(), odcid.empty() ? nullptr : odcid.data(),
+            odcid.size(), local, local_len, peer, peer_len,
+            config.native_handle());
+
+        if (conn == nullptr) {
+            return errc::invalid_state;
+        }
+
+        return connection(conn);
+    }
+
+    quiche_conn *native_handle() const noexcept {
+        return ptr_.get();
+    }
+
+    // Releases ownership of the underlying C object.
+    quiche_conn *release() noexcept {
+        return ptr_.release();
+    }
+
+    bool set_keylog_path(const char *path) {
+        return quiche_conn_set_keylog_path(ptr_.get(), path);
+    }
+
+    bool set_qlog_path(const char *path, const char *title,
+                       const char *desc) {
+        return quiche_conn_set_qlog_path(ptr_.get(), path, title, desc);
+    }
+
+    // Delivers qlog records to `cb`, which is kept alive by the connection.
+    void set_qlog_callback(qlog_callback cb, const char *title,
+                           const char *desc) {
+        qlog_cb_ = std::make_unique<qlog_callback>(std::move(cb));
+
+        quiche_conn_set_qlog_cb(
+            ptr_.get(),
+            [](const uint8_t *data, size_t data_len, void *argp) {
+                (*static_cast<qlog_callback *>(argp))(
+                    std::span<const uint8_t>(data, data_len));
+            },
+            qlog_cb_.get(), title, desc);
+    }
+
+    result<void> set_session(std::span<const uint8_t> session) {
+        return detail::check(quiche_conn_set_session(
+            ptr_.get(), session.data(), session.size()));
+    }
+
+    // Processes QUIC packets received from the peer.
+    result<size_t> recv(std::span<uint8_t> buf, const quiche_recv_info &info) {
+        return detail::check_len(
+            quiche_conn_recv(ptr_.get(), buf.data(), buf.size(), &info));
+    }
+
+    // Writes a single QUIC packet to be sent to the peer.
+    result<size_t> send(std::span<uint8_t> out, quiche_send_info &info) {
+        return detail::check_len(
+            quiche_conn_send(ptr_.get(), out.data(), out.size(), &info));
+    }
+
+    size_t send_quantum() const {
+        return quiche_conn_send_quantum(ptr_.get());
+    }
+
+    // Reads contiguous data from a stream.
+    //
+    // When the stream was reset by the peer, the peer's error code is stored
+    // in `error_code`, if provided.
+    result<stream_read> stream_recv(uint64_t stream_id, std::span<uint8_t> out,
+                                    uint64_t *error_code = nullptr) {
+        bool fin = false;
+        uint64_t ec = 0;
+
+        ssize_t rc = quiche_conn_stream_recv(ptr_.get(), stream_id, out.data(),
+                                             out.size(), &fin, &ec);
+
+        if (rc < 0) {
+            if (error_code != nullptr) {
+                *error_code = ec;
+            }
+
+            return make_error_code(static_cast<errc>(rc));
+        }
+
+        return stream_read{static_cast<size_t>(rc), fin};
+    }
+
+    // Writes data to a stream.
+    //
+    // When the stream was stopped by the peer, the peer's error code is
+    // stored in `error_code`, if provided.
+    result<size_t> stream_send(uint64_t stream_id, std::span<const uint8_t> buf,
+                               bool fin, uint64_t *error_code = nullptr) {
+        uint64_t ec = 0;
+
+        ssize_t rc = quiche_conn_stream_send(ptr_.get(), stream_id, buf.data(),
+                                             buf.size(), fin, &ec);
+
+        if (rc < 0 && error_code != nullptr) {
+            *error_code = ec;
+        }
+
+        return detail::check_len(rc);
+    }
+
+    result<void> stream_priority(uint64_t stream_id, uint8_t urgency,
+                                 bool incremental) {
+        return detail::check(quiche_conn_stream_priority(
+            ptr_.get(), stream_id, urgency, incremental));
+    }
+
+    result<void> stream_shutdown(uint64_t stream_id,
+                                 enum quiche_shutdown direction,
+                                 uint64_t err) {
+        return detail::check(
+            quiche_conn_stream_shutdown(ptr_.get(), stream_id, direction, err));
+    }
+
+    result<size_t> stream_capacity(uint64_t stream_id) const {
+        return detail::check_len(
+            quiche_conn_stream_capacity(ptr_.get(), stream_id));
+    }
+
+    bool stream_readable(uint64_t stream_id) const {
+        return quiche_conn_stream_readable(ptr_.get(), stream_id);
+    }
+
+    bool stream_finished(uint64_t stream_id) const {
+        return quiche_conn_stream_finished(ptr_.get(), stream_id);
+    }
+
+    // Returns the streams that have outstanding data to read.
+    std::vector<uint64_t> readable() const {
+        return collect(quiche_conn_readable(ptr_.get()));
+    }
+
+    // Returns the streams that have enough flow control capacity to be
+    // written to.
+    std::vector<uint64_t> writable() const {
+        return collect(quiche_conn_writable(ptr_.get()));
+    }
+
+    size_t max_send_udp_payload_size() const {
+        return quiche_conn_max_send_udp_payload_size(ptr_.get());
+    }
+
+    // Returns the amount of time until the next timeout event, if any.
+    std::optional<std::chrono::nanoseconds> timeout() const {
+        uint64_t v = quiche_conn_timeout_as_nanos(ptr_.get());
+
+        if (v == UINT64_MAX) {
+            return std::nullopt;
+        }
+
+        return std::chrono::nanoseconds(v);
+    }
+
+    void on_timeout() {
+        quiche_conn_on_timeout(ptr_.get());
+    }
+
+    result<void> close(bool app, uint64_t err, std::string_view reason = {}) {
+        return detail::check(quiche_conn_close(
+            ptr_.get(), app, err,
+            reinterpret_cast<const uint8_t *>(reason.data()), reason.size()));
+    }
+
+    std::string_view trace_id() const {
+        const uint8_t *out = nullptr;
+        size_t out_len = 0;
+
+        quiche_conn_trace_id(ptr_.get(), &out, &out_len);
+
+        return detail::chars(out, out_len);
+    }
+
+    std::span<const uint8_t> source_id() const {
+        const uint8_t *out = nullptr;
+        size_t out_len = 0;
+
+        quiche_conn_source_id(ptr_.get(), &out, &out_len);
+
+        return detail::bytes(out, out_len);
+    }
+
+    std::span<const uint8_t> destination_id() const {
+        const uint8_t *out = nullptr;
+        size_t out_len = 0;
+
+        quiche_conn_destination_id(ptr_.get(), &out, &out_len);
+
+        return detail::bytes(out, out_len);
+    }
+
+    std::string_view application_proto() const {
+        const uint8_t *out = nullptr;
+        size_t out_len = 0;
+
+        quiche_conn_application_proto(ptr_.get(), &out, &out_len);
+
+        return detail::chars(out, out_len);
+    }
+
+    std::span<const uint8_t> peer_cert() const {
+        const uint8_t *out = nullptr;
+        size_t out_len = 0;
+
+        quiche_conn_peer_cert(ptr_.get(), &out, &out_len);
+
+        return detail::bytes(out, out_len);
+    }
+
+    std::span<const uint8_t> session() const {
+        const uint8_t *out = nullptr;
+        size_t out_len = 0;
+
+        quiche_conn_session(ptr_.get(), &out, &out_len);
+
+        return detail::bytes(out, out_len);
+    }
+
+    std::string_view server_name() const {
+        const uint8_t *out = nullptr;
+        size_t out_len = 0;
+
+        quiche_conn_server_name(ptr_.get(), &out, &out_len);
+
+        return detail::chars(out, out_len);
+    }
+
+    bool is_established() const {
+        return quiche_conn_is_established(ptr_.get());
+    }
+
+    bool is_resumed() const {
+        return quiche_conn_is_resumed(ptr_.get());
+    }
+
+    bool is_in_early_data() const {
+        return quiche_conn_is_in_early_data(ptr_.get());
+    }
+
+    bool is_readable() const {
+        return quiche_conn_is_readable(ptr_.get());
+    }
+
+    bool is_draining() const {
+        return quiche_conn_is_draining(ptr_.get());
+    }
+
+    bool is_closed() const {
+        return quiche_conn_is_closed(ptr_.get());
+    }
+
+    bool is_timed_out() const {
+        return quiche_conn_is_timed_out(ptr_.get());
+    }
+
+    bool is_server() const {
+        return quiche_conn_is_server(ptr_.get());
+    }
+
+    uint64_t peer_streams_left_bidi() const {
+        return quiche_conn_peer_streams_left_bidi(ptr_.get());
+    }
+
+    uint64_t peer_streams_left_uni() const {
+        return quiche_conn_peer_streams_left_uni(ptr_.get());
+    }
+
+    std::optional<connection_error> peer_error() const {
+        connection_error err{};
+        const uint8_t *reason = nullptr;
+        size_t reason_len = 0;
+
+        if (!quiche_conn_peer_error(ptr_.get(), &err.is_app, &err.error_code,
+                                    &reason, &reason_len)) {
+            return std::nullopt;
+        }
+
+        err.reason = detail::chars(reason, reason_len);
+
+        return err;
+    }
+
+    std::optional<connection_error> local_error() const {
+        connection_error err{};
+        const uint8_t *reason = nullptr;
+        size_t reason_len = 0;
+
+        if (!quiche_conn_local_error(ptr_.get(), &err.is_app, &err.error_code,
+                                     &reason, &reason_len)) {
+            return std::nullopt;
+        }
+
+        err.reason = detail::chars(reason, reason_len);
+
+        return err;
+    }
+
+    quiche_stats stats() const {
+        quiche_stats out{};
+        quiche_conn_stats(ptr_.get(), &out);
+        return out;
+    }
+
+    std::optional<quiche_transport_params> peer_transport_params() const {
+        quiche_transport_params out{};
+
+        if (!quiche_conn_peer_transport_params(ptr_.get(), &out)) {
+            return std::nullopt;
+        }
+
+        return out;
+    }
+
+    result<quiche_path_stats> path_stats(size_t idx) const {
+        quiche_path_stats out{};
+
+        if (result<void> rc = detail::check(
+                quiche_conn_path_stats(ptr_.get(), idx, &out));
+            !rc) {
+            return rc.error();
+        }
+
+        return out;
+    }
+
+    result<size_t> dgram_max_writable_len() const {
+        return detail::check_len(quiche_conn_dgram_max_writable_len(ptr_.get()));
+    }
+
+    result<size_t> dgram_recv(std::span<uint8_t> buf) {
+        return detail::check_len(
+            quiche_conn_dgram_recv(ptr_.get(), buf.data(), buf.size()));
+    }
+
+    result<size_t> dgram_send(std::span<const uint8_t> buf) {
+        return detail::check_len(
+            quiche_conn_dgram_send(ptr_.get(), buf.data(), buf.size()));
+    }
+
+    result<void> send_ack_eliciting() {
+        return detail::check(
+            static_cast<int>(quiche_conn_send_ack_eliciting(ptr_.get())));
+    }
+
+  private:
+    static std::vector<uint64_t> collect(quiche_stream_iter *iter) {
+        std::vector<uint64_t> ids;
+        uint64_t id = 0;
+
+        if (iter == nullptr) {
+            return ids;
+        }
+
+        while (quiche_stream_iter_next(iter, &id)) {
+            ids.push_back(id);
+        }
+
+        quiche_stream_iter_free(iter);
+
+        return ids;
+    }
+
+    // The callback must outlive the connection, as freeing the connection
+    // flushes any pending qlog data.
+    std::unique_ptr<qlog_callback> qlog_cb_;
+
+    detail::handle<quiche_conn, quiche_conn_free> ptr_;
+};
+
+namespace h3 {
+
+// HTTP/3 errors, see `enum quiche_h3_error`.
+//
+// Transport errors propagated by the HTTP/3 layer are reported using
+// `quiche::errc` instead.
+enum class errc : int {
+    done = QUICHE_H3_ERR_DONE,
+    buffer_too_short = QUICHE_H3_ERR_BUFFER_TOO_SHORT,
+    internal_error = QUICHE_H3_ERR_INTERNAL_ERROR,
+    excessive_load = QUICHE_H3_ERR_EXCESSIVE_LOAD,
+    id_error = QUICHE_H3_ERR_ID_ERROR,
+    stream_creation_error = QUICHE_H3_ERR_STREAM_CREATION_ERROR,
+    closed_critical_stream = QUICHE_H3_ERR_CLOSED_CRITICAL_STREAM,
+    missing_settings = QUICHE_H3_ERR_MISSING_SETTINGS,
+    frame_unexpected = QUICHE_H3_ERR_FRAME_UNEXPECTED,
+    frame_error = QUICHE_H3_ERR_FRAME_ERROR,
+    qpack_decompression_failed = QUICHE_H3_ERR_QPACK_DECOMPRESSION_FAILED,
+    stream_blocked = QUICHE_H3_ERR_STREAM_BLOCKED,
+    settings_error = QUICHE_H3_ERR_SETTINGS_ERROR,
+    request_rejected = QUICHE_H3_ERR_REQUEST_REJECTED,
+    request_cancelled = QUICHE_H3_ERR_REQUEST_CANCELLED,
+    request_incomplete = QUICHE_H3_ERR_REQUEST_INCOMPLETE,
+    message_error = QUICHE_H3_ERR_MESSAGE_ERROR,
+    connect_error = QUICHE_H3_ERR_CONNECT_ERROR,
+    version_fallback = QUICHE_H3_ERR_VERSION_FALLBACK,
+};
+
+namespace detail {
+
+class h3_category : public std::error_category {
+  public:
+    const char *name() const noexcept override {
+        return "quiche-h3";
+    }
+
+    std::string message(int ev) const override {
+        switch (static_cast<errc>(ev)) {
+            case errc::done:
+                return "no more work to do";
+            case errc::buffer_too_short:
+                return "buffer too short";
+            case errc::internal_error:
+                return "internal error";
+            case errc::excessive_load:
+                return "excessive load";
+            case errc::id_error:
+                return "invalid identifier";
+            case errc::stream_creation_error:
+                return "stream creation error";
+            case errc::closed_critical_stream:
+                return "critical stream closed";
+            case errc::missing_settings:
+                return "missing SETTINGS";
+            case errc::frame_unexpected:
+                return "unexpected frame";
+            case errc::frame_error:
+                return "frame error";
+            case errc::qpack_decompression_failed:
+                return "QPACK decompression failed";
+            case errc::stream_blocked:
+                return "stream blocked";
+            case errc::settings_error:
+                return "SETTINGS error";
+            case errc::request_rejected:
+                return "request rejected";
+            case errc::request_cancelled:
+                return "request cancelled";
+            case errc::request_incomplete:
+                return "request incomplete";
+            case errc::message_error:
+                return "malformed message";
+            case errc::connect_error:
+                return "CONNECT error";
+            case errc::version_fallback:
+                return "version fallback";
+        }
+
+        return "unknown quiche HTTP/3 error " + std::to_string(ev);
+    }
+};
+
+} // namespace detail
+
+// Returns the error category of HTTP/3 errors.
+inline const std::error_category &h3_category() noexcept {
+    static const detail::h3_category category;
+    return category;
+}
+
+inline std::error_code make_error_code(errc e) noexcept {
+    return {static_cast<int>(e), h3_category()};
+}
+
+namespace detail {
+
+// Maps an HTTP/3 C error code, which may be a propagated transport error.
+inline std::error_code to_error_code(int64_t rc) {
+    if (rc <= QUICHE_H3_TRANSPORT_ERR_DONE) {
+        return quiche::make_error_code(
+            static_cast<quiche::errc>(rc - QUICHE_H3_TRANSPORT_ERR_DONE +
+                                      QUICHE_ERR_DONE));
+    }
+
+    return make_error_code(static_cast<errc>(rc));
+}
+
+inline result<void> check(int rc) {
+    if (rc < 0) {
+        return to_error_code(rc);
+    }
+
+    return {};
+}
+
+inline result<size_t> check_len(ssize_t rc) {
+    if (rc < 0) {
+        return to_error_code(rc);
+    }
+
+    return static_cast<size_t>(rc);
+}
+
+} // namespace detail
+
+// Builds a header that borrows `name` and `value`.
+inline quiche_h3_header header(std::string_view name,
+                               std::string_view value) noexcept {
+    return {reinterpret_cast<const uint8_t *>(name.data()), name.size(),
+            reinterpret_cast<const uint8_t *>(value.data()), value.size()};
+}
+
+// Stores configuration shared between multiple connections.
+class config {
+  public:
+    config() : ptr_(quiche_h3_config_new()) {
+        if (!ptr_) {
+            quiche::detail::throw_bad_alloc();
+        }
+    }
+
+    quiche_h3_config *native_handle() const noexcept {
+        return ptr_.get();
+    }
+
+    void set_max_field_section_size(uint64_t v) {
+        quiche_h3_config_set_max_field_section_size(ptr_.get(), v);
+    }
+
+    void set_qpack_max_table_capacity(uint64_t v) {
+        quiche_h3_config_set_qpack_max_table_capacity(ptr_.get(), v);
+    }
+
+    void set_qpack_blocked_streams(uint64_t v) {
+        quiche_h3_config_set_qpack_blocked_streams(ptr_.get(), v);
+    }
+
+    void enable_extended_connect(bool enabled) {
+        quiche_h3_config_enable_extended_connect(ptr_.get(), enabled);
+    }
+
+  private:
+    quiche::detail::handle<quiche_h3_config, quiche_h3_config_free> ptr_;
+};
+
+// An HTTP/3 connection event.
+class event {
+  public:
+    explicit event(quiche_h3_event *ev) noexcept : ptr_(ev) {}
+
+    quiche_h3_event *native_handle() const noexcept {
+        return ptr_.get();
+    }
+
+    enum quiche_h3_event_type type() const {
+        return quiche_h3_event_type(ptr_.get());
+    }
+
+    // Calls `f(name, value)` for each header in the event.
+    template <typename F>
+    result<void> for_each_header(F &&f) const {
+        int rc = quiche_h3_event_for_each_header(
+            ptr_.get(),
+            [](uint8_t *name, size_t name_len, uint8_t *value,
+               size_t value_len, void *argp) -> int {
+                (*static_cast<std::remove_reference_t<F> *>(argp))(
+                    quiche::detail::chars(name, name_len),
+                    quiche::detail::chars(value, value_len));
+                return 0;
+            },
+            &f);
+
+        return detail::check(rc);
+    }
+
+    // Returns a copy of the headers in the event.
+    std::vector<std::pair<std::string, std::string>> headers() const {
+        std::vector<std::pair<std::string, std::string>> out;
+
+        (void) for_each_header([&](std::string_view name,
+                                   std::string_view value) {
+            out.emplace_back(name, value);
+        });
+
+        return out;
+    }
+
+    bool headers_has_more_frames() const {
+        return quiche_h3_event_headers_has_more_frames(ptr_.get());
+    }
+
+    enum quiche_h3_cancellation cancellation() const {
+        return quiche_h3_event_cancellation(ptr_.get());
+    }
+
+  private:
+    quiche::detail::handle<quiche_h3_event, quiche_h3_event_free> ptr_;
+};
+
+// An event returned by `connection::poll()`, and the stream it refers to.
+struct polled_event {
+    uint64_t stream_id;
+    h3::event event;
+};
+
+// An HTTP/3 connection.
+class connection {
+  public:
+    // Takes ownership of a connection created through the C API.
+    explicit connection(quiche_h3_conn *conn) noexcept : ptr_(conn) {}
+
+    // Creates a new HTTP/3 connection using the provided QUIC connection.
+    static result<connection> with_transport(quiche::connection &quic_conn,
+                                             h3::config &config) {
+        quiche_h3_conn *conn = quiche_h3_conn_new_with_transport(
+            quic_conn.native_handle(), config.native_handle());
+
+        if (conn == nullptr) {
+            return errc::internal_error;
+        }
+
+        return connection(conn);
+    }
+
+    quiche_h3_conn *native_handle() const noexcept {
+        return ptr_.get();
+    }
+
+    // Processes HTTP/3 data received from the peer.
+    result<polled_event> poll(quiche::connection &quic_conn) {
+        quiche_h3_event *ev = nullptr;
+
+        int64_t rc =
+            quiche_h3_conn_poll(ptr_.get(), quic_conn.native_handle(), &ev);
+
+        if (rc < 0) {
+            return detail::to_error_code(rc);
+        }
+
+        return polled_event{static_cast<uint64_t>(rc), event(ev)};
+    }
+
+    result<uint64_t> send_request(quiche::connection &quic_conn,
+                                  std::span<const quiche_h3_header> headers,
+                                  bool fin) {
+        int64_t rc =
+            quiche_h3_send_request(ptr_.get(), quic_conn.native_handle(),
+                                   headers.data(), headers.size(), fin);
+
+        if (rc < 0) {
+            return detail::to_error_code(rc);
+        }
+
+        return static_cast<uint64_t>(rc);
+    }
+
+    result<void> send_response(quiche::connection &quic_conn,
+                               uint64_t stream_id,
+                               std::span<const quiche_h3_header> headers,
+                               bool fin) {
+        return detail::check(quiche_h3_send_response(
+            ptr_.get(), quic_conn.native_handle(), stream_id, headers.data(),
+            headers.size(), fin));
+    }
+
+    result<void> send_response(quiche::connection &quic_conn,
+                               uint64_t stream_id,
+                               std::span<const quiche_h3_header> headers,
+                               const quiche_h3_priority &priority, bool fin) {
+        return detail::check(quiche_h3_send_response_with_priority(
+            ptr_.get(), quic_conn.native_handle(), stream_id, headers.data(),
+            headers.size(), &priority, fin));
+    }
+
+    result<void> send_additional_headers(quiche::connection &quic_conn,
+                                         uint64_t stream_id,
+                                         std::span<quiche_h3_header> headers,
+                                         bool is_trailer_section, bool fin) {
+        return detail::check(quiche_h3_send_additional_headers(
+            ptr_.get(), quic_conn.native_handle(), stream_id, headers.data(),
+            headers.size(), is_trailer_section, fin));
+    }
+
+    result<size_t> send_body(quiche::connection &quic_conn, uint64_t stream_id,
+                             std::span<const uint8_t> body, bool fin) {
+        return detail::check_len(
+            quiche_h3_send_body(ptr_.get(), quic_conn.native_handle(),
+                                stream_id, body.data(), body.size(), fin));
+    }
+
+    result<size_t> recv_body(quiche::connection &quic_conn, uint64_t stream_id,
+                             std::span<uint8_t> out) {
+        return detail::check_len(
+            quiche_h3_recv_body(ptr_.get(), quic_conn.native_handle(),
+                                stream_id, out.data(), out.size()));
+    }
+
+    result<void> send_goaway(quiche::connection &quic_conn, uint64_t id) {
+        return detail::check(
+            quiche_h3_send_goaway(ptr_.get(), quic_conn.native_handle(), id));
+    }
+
+    result<void> cancel_request(quiche::connection &quic_conn,
+                                uint64_t stream_id) {
+        return detail::check(quiche_h3_cancel_request(
+            ptr_.get(), quic_conn.native_handle(), stream_id));
+    }
+
+    result<void> stream_priority(quiche::connection &quic_conn,
+                                 uint64_t stream_id,
+                                 const quiche_h3_priority &priority) {
+        return detail::check(quiche_h3_stream_priority(
+            ptr_.get(), quic_conn.native_handle(), stream_id, &priority));
+    }
+
+    result<void> send_priority_update_for_request(
+        quiche::connection &quic_conn, uint64_t stream_id,
+        const quiche_h3_priority &priority) {
+        return detail::check(quiche_h3_send_priority_update_for_request(
+            ptr_.get(), quic_conn.native_handle(), stream_id, &priority));
+    }
+
+    // Returns the last PRIORITY_UPDATE field value received for a stream.
+    result<std::string> take_last_priority_update(uint64_t stream_id) {
+        std::string out;
+
+        int rc = quiche_h3_take_last_priority_update(
+            ptr_.get(), stream_id,
+            [](uint8_t *value, uint64_t value_len, void *argp) -> int {
+                static_cast<std::string *>(argp)->assign(
+                    quiche::detail::chars(value, value_len));
+                return 0;
+            },
+            &out);
+
+        if (rc < 0) {
+            return detail::to_error_code(rc);
+        }
+
+        return out;
+    }
+
+    bool extended_connect_enabled_by_peer() const {
+        return quiche_h3_extended_connect_enabled_by_peer(ptr_.get());
+    }
+
+    bool dgram_enabled_by_peer(quiche::connection &quic_conn) const {
+        return quiche_h3_dgram_enabled_by_peer(ptr_.get(),
+                                               quic_conn.native_handle());
+    }
+
+    quiche_h3_stats stats() const {
+        quiche_h3_stats out{};
+        quiche_h3_conn_stats(ptr_.get(), &out);
+        return out;
+    }
+
+  private:
+    quiche::detail::handle<quiche_h3_conn, quiche_h3_conn_free> ptr_;
+};
+
+// Parses an Extensible Priority field value. Requires the `sfv` feature.
+inline result<quiche_h3_priority> parse_extensible_priority(
+    std::string_view value) {
+    quiche_h3_priority out{};
+
+    int rc = quiche_h3_parse_extensible_priority(
+        reinterpret_cast<const uint8_t *>(value.data()), value.size(), &out);
+
+    if (rc < 0) {
+        return detail::to_error_code(rc);
+    }
+
+    return out;
+}
+
+// Serializes a priority as an Extensible Priority field value.
+inline std::string to_field_value(const quiche_h3_priority &priority) {
+    // "u=N,i" is the longest possible value.
+    uint8_t buf[8];
+
+    ssize_t rc = quiche_h3_priority_to_field_value(&priority, buf, sizeof(buf));
+
+    return std::string(quiche::detail::chars(buf, rc < 0 ? 0 : rc));
+}
+
+} // namespace h3
+
+} // namespace quiche
+
+#endif // QUICHE_HPP
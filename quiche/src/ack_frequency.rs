@@ -0,0 +1,327 @@
+// Copyright (C) 2025, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! ACK frequency extension state.
+//!
+//! When the peer advertises the `min_ack_delay` transport parameter, an
+//! ACK_FREQUENCY frame is sent once the handshake completes, asking the peer to
+//! acknowledge packets according to the locally configured ack-eliciting
+//! threshold and maximum ACK delay.
+//!
+//! Conversely, when the `min_ack_delay` transport parameter was advertised
+//! locally, ACK_FREQUENCY frames received from the peer control how long
+//! acknowledgements of application data packets can be delayed. Until such a
+//! frame is received every ack-eliciting packet is acknowledged right away.
+
+use std::time;
+
+/// Reordering threshold requested from the peer, matching the default RFC 9000
+/// behavior of acknowledging out-of-order packets immediately.
+const DEFAULT_REORDERING_THRESHOLD: u64 = 1;
+
+/// Acknowledgement behavior requested by an ACK_FREQUENCY frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AckFrequencyRequest {
+    pub seq_num: u64,
+
+    pub ack_eliciting_threshold: u64,
+
+    pub max_ack_delay: time::Duration,
+
+    pub reordering_threshold: u64,
+}
+
+/// ACK frequency state of a connection.
+#[derive(Default)]
+pub struct AckFrequency {
+    /// The ack-eliciting threshold to request from the peer.
+    local_threshold: Option<u64>,
+
+    /// The maximum ACK delay to request from the peer.
+    local_max_ack_delay: Option<time::Duration>,
+
+    /// The request to send to the peer, once it is known to support it.
+    local_request: Option<AckFrequencyRequest>,
+
+    /// Whether the ACK_FREQUENCY frame still needs to be sent.
+    request_pending: bool,
+
+    /// Whether an IMMEDIATE_ACK frame needs to be sent.
+    immediate_ack_pending: bool,
+
+    /// The `min_ack_delay` advertised by the peer.
+    peer_min_ack_delay: Option<time::Duration>,
+
+    /// The latest request received from the peer.
+    peer_request: Option<AckFrequencyRequest>,
+
+    /// Number of ack-eliciting packets received since the last ACK was sent.
+    unacked: u64,
+
+    /// When a delayed acknowledgement must be sent at the latest.
+    ack_timer: Option<time::Instant>,
+}
+
+impl AckFrequency {
+    pub fn new(
+        threshold: Option<u64>, max_ack_delay: Option<time::Duration>,
+    ) -> Self {
+        AckFrequency {
+            local_threshold: threshold,
+            local_max_ack_delay: max_ack_delay,
+            ..Default::default()
+        }
+    }
+
+    /// Returns whether the peer can receive ACK_FREQUENCY and IMMEDIATE_ACK
+    /// frames.
+    pub fn peer_enabled(&self) -> bool {
+        self.peer_min_ack_delay.is_some()
+    }
+
+    /// Records the peer's `min_ack_delay` and its `max_ack_delay` transport
+    /// parameters, and schedules an ACK_FREQUENCY frame if a non-default
+    /// behavior was configured.
+    pub fn on_peer_params(
+        &mut self, min_ack_delay: time::Duration, max_ack_delay: time::Duration,
+    ) {
+        self.peer_min_ack_delay = Some(min_ack_delay);
+
+        if self.local_threshold.is_none() && self.local_max_ack_delay.is_none() {
+            return;
+        }
+
+        // The requested delay can't be lower than what the peer supports.
+        let max_ack_delay = self
+            .local_max_ack_delay
+            .unwrap_or(max_ack_delay)
+            .max(min_ack_delay);
+
+        self.local_request = Some(AckFrequencyRequest {
+            seq_num: 0,
+            ack_eliciting_threshold: self.local_threshold.unwrap_or(1),
+            max_ack_delay,
+            reordering_threshold: DEFAULT_REORDERING_THRESHOLD,
+        });
+
+        self.request_pending = true;
+    }
+
+    /// Returns the ACK_FREQUENCY request to send, if any.
+    pub fn next_request(&self) -> Option<AckFrequencyRequest> {
+        if !self.request_pending {
+            return None;
+        }
+
+        self.local_request
+    }
+
+    /// Removes the ACK_FREQUENCY frame from the send queue.
+    pub fn on_request_sent(&mut self) {
+        self.request_pending = false;
+    }
+
+    /// Schedules a lost ACK_FREQUENCY frame again, unless it was superseded.
+    pub fn on_request_lost(&mut self, seq_num: u64) {
+        if self.local_request.map(|r| r.seq_num) == Some(seq_num) {
+            self.request_pending = true;
+        }
+    }
+
+    /// Returns the maximum ACK delay the peer now uses, once the ACK_FREQUENCY
+    /// frame with the given sequence number got acknowledged.
+    pub fn on_request_acked(&self, seq_num: u64) -> Option<time::Duration> {
+        self.local_request
+            .filter(|r| r.seq_num == seq_num)
+            .map(|r| r.max_ack_delay)
+    }
+
+    /// Schedules an IMMEDIATE_ACK frame.
+    pub fn send_immediate_ack(&mut self) {
+        self.immediate_ack_pending = true;
+    }
+
+    /// Returns whether an IMMEDIATE_ACK frame needs to be sent.
+    pub fn immediate_ack_pending(&self) -> bool {
+        self.immediate_ack_pending
+    }
+
+    /// Removes the IMMEDIATE_ACK frame from the send queue.
+    pub fn on_immediate_ack_sent(&mut self) {
+        self.immediate_ack_pending = false;
+    }
+
+    /// Applies an ACK_FREQUENCY request received from the peer. Requests older
+    /// than the current one are ignored.
+    pub fn on_peer_request(&mut self, request: AckFrequencyRequest) {
+        if let Some(current) = self.peer_request {
+            if request.seq_num < current.seq_num {
+                return;
+            }
+        }
+
+        self.peer_request = Some(request);
+    }
+
+    /// Records an ack-eliciting application data packet, given the largest
+    /// packet number received before it, and returns whether it needs to be
+    /// acknowledged immediately.
+    pub fn on_ack_eliciting_received(
+        &mut self, pn: u64, largest_rx_pkt_num: u64, now: time::Instant,
+    ) -> bool {
+        let request = match self.peer_request {
+            Some(v) => v,
+
+            None => return true,
+        };
+
+        self.unacked += 1;
+
+        if self.unacked > request.ack_eliciting_threshold {
+            return true;
+        }
+
+        // Acknowledge right away when the packet fills a gap, or when it
+        // leaves at least `reordering_threshold` packets missing.
+        if request.reordering_threshold > 0 &&
+            (pn < largest_rx_pkt_num ||
+                pn - largest_rx_pkt_num > request.reordering_threshold)
+        {
+            return true;
+        }
+
+        if self.ack_timer.is_none() {
+            self.ack_timer = Some(now + request.max_ack_delay);
+        }
+
+        false
+    }
+
+    /// Returns when a delayed acknowledgement must be sent.
+    pub fn ack_timer(&self) -> Option<time::Instant> {
+        self.ack_timer
+    }
+
+    /// Disarms the delayed acknowledgement timer once it expired.
+    pub fn on_ack_timeout(&mut self) {
+        self.ack_timer = None;
+    }
+
+    /// Resets the delayed acknowledgement state after an ACK frame was sent.
+    pub fn on_ack_sent(&mut self) {
+        self.unacked = 0;
+        self.ack_timer = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn local_request() {
+        let mut af =
+            AckFrequency::new(Some(10), Some(time::Duration::from_millis(1)));
+
+        assert!(!af.peer_enabled());
+        assert_eq!(af.next_request(), None);
+
+        af.on_peer_params(
+            time::Duration::from_millis(5),
+            time::Duration::from_millis(25),
+        );
+        assert!(af.peer_enabled());
+
+        // The requested delay is raised to the peer's minimum.
+        let request = af.next_request().unwrap();
+        assert_eq!(request.ack_eliciting_threshold, 10);
+        assert_eq!(request.max_ack_delay, time::Duration::from_millis(5));
+
+        af.on_request_sent();
+        assert_eq!(af.next_request(), None);
+
+        af.on_request_lost(1);
+        assert_eq!(af.next_request(), None);
+
+        af.on_request_lost(0);
+        assert_eq!(af.next_request(), Some(request));
+
+        assert_eq!(af.on_request_acked(0), Some(request.max_ack_delay));
+    }
+
+    #[test]
+    fn no_local_request() {
+        let mut af = AckFrequency::new(None, None);
+
+        af.on_peer_params(
+            time::Duration::from_millis(1),
+            time::Duration::from_millis(25),
+        );
+        assert!(af.peer_enabled());
+        assert_eq!(af.next_request(), None);
+    }
+
+    #[test]
+    fn delayed_ack() {
+        let mut af = AckFrequency::new(None, None);
+        let now = time::Instant::now();
+
+        // Without a request from the peer, everything is acked immediately.
+        assert!(af.on_ack_eliciting_received(1, 0, now));
+
+        let max_ack_delay = time::Duration::from_millis(50);
+
+        af.on_peer_request(AckFrequencyRequest {
+            seq_num: 1,
+            ack_eliciting_threshold: 2,
+            max_ack_delay,
+            reordering_threshold: 1,
+        });
+
+        // Older requests are ignored.
+        af.on_peer_request(AckFrequencyRequest {
+            seq_num: 0,
+            ack_eliciting_threshold: 0,
+            max_ack_delay,
+            reordering_threshold: 0,
+        });
+
+        assert!(!af.on_ack_eliciting_received(2, 1, now));
+        assert_eq!(af.ack_timer(), Some(now + max_ack_delay));
+
+        assert!(!af.on_ack_eliciting_received(3, 2, now));
+        assert!(af.on_ack_eliciting_received(4, 3, now));
+
+        af.on_ack_sent();
+        assert_eq!(af.ack_timer(), None);
+
+        // Out-of-order packets are acked immediately.
+        assert!(af.on_ack_eliciting_received(7, 4, now));
+
+        af.on_ack_sent();
+        assert!(af.on_ack_eliciting_received(6, 7, now));
+    }
+}
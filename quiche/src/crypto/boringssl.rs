@@ -57,15 +57,17 @@ impl PacketKey {
         })
     }
 
-    pub fn from_secret(aead: Algorithm, secret: &[u8], enc: u32) -> Result<Self> {
+    pub fn from_secret(
+        aead: Algorithm, secret: &[u8], enc: u32, version: u32,
+    ) -> Result<Self> {
         let key_len = aead.key_len();
         let nonce_len = aead.nonce_len();
 
         let mut key = vec![0; key_len];
         let mut iv = vec![0; nonce_len];
 
-        derive_pkt_key(aead, secret, &mut key)?;
-        derive_pkt_iv(aead, secret, &mut iv)?;
+        derive_pkt_key(aead, secret, version, &mut key)?;
+        derive_pkt_iv(aead, secret, version, &mut iv)?;
 
         let pkt_key = Self::new(aead, key, iv, enc)?;
 
@@ -75,13 +77,14 @@ impl PacketKey {
         // packet number) to be zero, which would not be the case for packet
         // number spaces after Initial as the same packet number sequence is
         // shared.
-        let _ = pkt_key.seal_with_u64_counter(0, b"", &mut [0_u8; 16], 0, None);
+        let _ =
+            pkt_key.seal_with_u64_counter(0, 0, b"", &mut [0_u8; 16], 0, None);
 
         Ok(pkt_key)
     }
 
     pub fn open_with_u64_counter(
-        &self, counter: u64, ad: &[u8], buf: &mut [u8],
+        &self, path_id: u32, counter: u64, ad: &[u8], buf: &mut [u8],
     ) -> Result<usize> {
         let tag_len = self.alg.tag_len();
 
@@ -92,7 +95,7 @@ impl PacketKey {
 
         let max_out_len = out_len;
 
-        let nonce = make_nonce(&self.nonce, counter);
+        let nonce = make_nonce(&self.nonce, path_id, counter);
 
         let rc = unsafe {
             EVP_AEAD_CTX_open(
@@ -117,8 +120,8 @@ impl PacketKey {
     }
 
     pub fn seal_with_u64_counter(
-        &self, counter: u64, ad: &[u8], buf: &mut [u8], in_len: usize,
-        extra_in: Option<&[u8]>,
+        &self, path_id: u32, counter: u64, ad: &[u8], buf: &mut [u8],
+        in_len: usize, extra_in: Option<&[u8]>,
     ) -> Result<usize> {
         let tag_len = self.alg.tag_len();
 
@@ -135,7 +138,7 @@ impl PacketKey {
             return Err(Error::CryptoFail);
         }
 
-        let nonce = make_nonce(&self.nonce, counter);
+        let nonce = make_nonce(&self.nonce, path_id, counter);
 
         let rc = unsafe {
             EVP_AEAD_CTX_seal_scatter(
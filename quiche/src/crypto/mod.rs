@@ -24,6 +24,9 @@
 // NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
 // SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
 
+use std::collections::btree_map;
+use std::collections::BTreeMap;
+
 use libc::c_int;
 use libc::c_void;
 
@@ -130,6 +133,8 @@ pub struct Open {
 
     secret: Vec<u8>,
 
+    version: u32,
+
     header: HeaderProtectionKey,
 
     packet: PacketKey,
@@ -143,28 +148,34 @@ impl Open {
 
     pub fn new(
         alg: Algorithm, key: Vec<u8>, iv: Vec<u8>, hp_key: Vec<u8>,
-        secret: Vec<u8>,
+        secret: Vec<u8>, version: u32,
     ) -> Result<Open> {
         Ok(Open {
             alg,
 
             secret,
 
+            version,
+
             header: HeaderProtectionKey::new(alg, hp_key)?,
 
             packet: PacketKey::new(alg, key, iv, Self::DECRYPT)?,
         })
     }
 
-    pub fn from_secret(aead: Algorithm, secret: &[u8]) -> Result<Open> {
+    pub fn from_secret(
+        aead: Algorithm, secret: &[u8], version: u32,
+    ) -> Result<Open> {
         Ok(Open {
             alg: aead,
 
             secret: secret.to_vec(),
 
-            header: HeaderProtectionKey::from_secret(aead, secret)?,
+            version,
+
+            header: HeaderProtectionKey::from_secret(aead, secret, version)?,
 
-            packet: PacketKey::from_secret(aead, secret, Self::DECRYPT)?,
+            packet: PacketKey::from_secret(aead, secret, Self::DECRYPT, version)?,
         })
     }
 
@@ -181,16 +192,23 @@ impl Open {
     }
 
     pub fn derive_next_packet_key(&self) -> Result<Open> {
-        let next_secret = derive_next_secret(self.alg, &self.secret)?;
+        let next_secret =
+            derive_next_secret(self.alg, &self.secret, self.version)?;
 
-        let next_packet_key =
-            PacketKey::from_secret(self.alg, &next_secret, Self::DECRYPT)?;
+        let next_packet_key = PacketKey::from_secret(
+            self.alg,
+            &next_secret,
+            Self::DECRYPT,
+            self.version,
+        )?;
 
         Ok(Open {
             alg: self.alg,
 
             secret: next_secret,
 
+            version: self.version,
+
             header: self.header.clone(),
 
             packet: next_packet_key,
@@ -198,13 +216,13 @@ impl Open {
     }
 
     pub fn open_with_u64_counter(
-        &self, counter: u64, ad: &[u8], buf: &mut [u8],
+        &self, path_id: u32, counter: u64, ad: &[u8], buf: &mut [u8],
     ) -> Result<usize> {
         if cfg!(feature = "fuzzing") {
             return Ok(buf.len());
         }
 
-        self.packet.open_with_u64_counter(counter, ad, buf)
+        self.packet.open_with_u64_counter(path_id, counter, ad, buf)
     }
 }
 
@@ -213,9 +231,16 @@ pub struct Seal {
 
     secret: Vec<u8>,
 
+    version: u32,
+
     header: HeaderProtectionKey,
 
     packet: PacketKey,
+
+    /// Packet keys used for multipath paths other than path 0. Each path gets
+    /// its own AEAD context, as some implementations require the packet
+    /// number to increase with every sealed packet.
+    path_packets: BTreeMap<u32, PacketKey>,
 }
 
 impl Seal {
@@ -226,28 +251,38 @@ impl Seal {
 
     pub fn new(
         alg: Algorithm, key: Vec<u8>, iv: Vec<u8>, hp_key: Vec<u8>,
-        secret: Vec<u8>,
+        secret: Vec<u8>, version: u32,
     ) -> Result<Seal> {
         Ok(Seal {
             alg,
 
             secret,
 
+            version,
+
             header: HeaderProtectionKey::new(alg, hp_key)?,
 
             packet: PacketKey::new(alg, key, iv, Self::ENCRYPT)?,
+
+            path_packets: BTreeMap::new(),
         })
     }
 
-    pub fn from_secret(aead: Algorithm, secret: &[u8]) -> Result<Seal> {
+    pub fn from_secret(
+        aead: Algorithm, secret: &[u8], version: u32,
+    ) -> Result<Seal> {
         Ok(Seal {
             alg: aead,
 
             secret: secret.to_vec(),
 
-            header: HeaderProtectionKey::from_secret(aead, secret)?,
+            version,
+
+            header: HeaderProtectionKey::from_secret(aead, secret, version)?,
 
-            packet: PacketKey::from_secret(aead, secret, Self::ENCRYPT)?,
+            packet: PacketKey::from_secret(aead, secret, Self::ENCRYPT, version)?,
+
+            path_packets: BTreeMap::new(),
         })
     }
 
@@ -264,25 +299,34 @@ impl Seal {
     }
 
     pub fn derive_next_packet_key(&self) -> Result<Seal> {
-        let next_secret = derive_next_secret(self.alg, &self.secret)?;
+        let next_secret =
+            derive_next_secret(self.alg, &self.secret, self.version)?;
 
-        let next_packet_key =
-            PacketKey::from_secret(self.alg, &next_secret, Self::ENCRYPT)?;
+        let next_packet_key = PacketKey::from_secret(
+            self.alg,
+            &next_secret,
+            Self::ENCRYPT,
+            self.version,
+        )?;
 
         Ok(Seal {
             alg: self.alg,
 
             secret: next_secret,
 
+            version: self.version,
+
             header: self.header.clone(),
 
             packet: next_packet_key,
+
+            path_packets: BTreeMap::new(),
         })
     }
 
     pub fn seal_with_u64_counter(
-        &self, counter: u64, ad: &[u8], buf: &mut [u8], in_len: usize,
-        extra_in: Option<&[u8]>,
+        &mut self, path_id: u32, counter: u64, ad: &[u8], buf: &mut [u8],
+        in_len: usize, extra_in: Option<&[u8]>,
     ) -> Result<usize> {
         if cfg!(feature = "fuzzing") {
             if let Some(extra) = extra_in {
@@ -293,18 +337,34 @@ impl Seal {
             return Ok(in_len);
         }
 
-        self.packet
-            .seal_with_u64_counter(counter, ad, buf, in_len, extra_in)
+        let packet = if path_id == 0 {
+            &self.packet
+        } else {
+            match self.path_packets.entry(path_id) {
+                btree_map::Entry::Occupied(v) => v.into_mut(),
+
+                btree_map::Entry::Vacant(v) => v.insert(PacketKey::from_secret(
+                    self.alg,
+                    &self.secret,
+                    Self::ENCRYPT,
+                    self.version,
+                )?),
+            }
+        };
+
+        packet.seal_with_u64_counter(path_id, counter, ad, buf, in_len, extra_in)
     }
 }
 
 impl HeaderProtectionKey {
-    pub fn from_secret(aead: Algorithm, secret: &[u8]) -> Result<Self> {
+    pub fn from_secret(
+        aead: Algorithm, secret: &[u8], version: u32,
+    ) -> Result<Self> {
         let key_len = aead.key_len();
 
         let mut hp_key = vec![0; key_len];
 
-        derive_hdr_key(aead, secret, &mut hp_key)?;
+        derive_hdr_key(aead, secret, version, &mut hp_key)?;
 
         Self::new(aead, hp_key)
     }
@@ -335,13 +395,13 @@ pub fn derive_initial_key_material(
     if did_reset {
         let (open, seal) = if is_server {
             (
-                Open::from_secret(aead, &client_secret)?,
-                Seal::from_secret(aead, &server_secret)?,
+                Open::from_secret(aead, &client_secret, version)?,
+                Seal::from_secret(aead, &server_secret, version)?,
             )
         } else {
             (
-                Open::from_secret(aead, &server_secret)?,
-                Seal::from_secret(aead, &client_secret)?,
+                Open::from_secret(aead, &server_secret, version)?,
+                Seal::from_secret(aead, &client_secret, version)?,
             )
         };
 
@@ -353,28 +413,56 @@ pub fn derive_initial_key_material(
     let mut client_iv = vec![0; nonce_len];
     let mut client_hp_key = vec![0; key_len];
 
-    derive_pkt_key(aead, &client_secret, &mut client_key)?;
-    derive_pkt_iv(aead, &client_secret, &mut client_iv)?;
-    derive_hdr_key(aead, &client_secret, &mut client_hp_key)?;
+    derive_pkt_key(aead, &client_secret, version, &mut client_key)?;
+    derive_pkt_iv(aead, &client_secret, version, &mut client_iv)?;
+    derive_hdr_key(aead, &client_secret, version, &mut client_hp_key)?;
 
     // Server.
     let mut server_key = vec![0; key_len];
     let mut server_iv = vec![0; nonce_len];
     let mut server_hp_key = vec![0; key_len];
 
-    derive_pkt_key(aead, &server_secret, &mut server_key)?;
-    derive_pkt_iv(aead, &server_secret, &mut server_iv)?;
-    derive_hdr_key(aead, &server_secret, &mut server_hp_key)?;
+    derive_pkt_key(aead, &server_secret, version, &mut server_key)?;
+    derive_pkt_iv(aead, &server_secret, version, &mut server_iv)?;
+    derive_hdr_key(aead, &server_secret, version, &mut server_hp_key)?;
 
     let (open, seal) = if is_server {
         (
-            Open::new(aead, client_key, client_iv, client_hp_key, client_secret)?,
-            Seal::new(aead, server_key, server_iv, server_hp_key, server_secret)?,
+            Open::new(
+                aead,
+                client_key,
+                client_iv,
+                client_hp_key,
+                client_secret,
+                version,
+            )?,
+            Seal::new(
+                aead,
+                server_key,
+                server_iv,
+                server_hp_key,
+                server_secret,
+                version,
+            )?,
         )
     } else {
         (
-            Open::new(aead, server_key, server_iv, server_hp_key, server_secret)?,
-            Seal::new(aead, client_key, client_iv, client_hp_key, client_secret)?,
+            Open::new(
+                aead,
+                server_key,
+                server_iv,
+                server_hp_key,
+                server_secret,
+                version,
+            )?,
+            Seal::new(
+                aead,
+                client_key,
+                client_iv,
+                client_hp_key,
+                client_secret,
+                version,
+            )?,
         )
     };
 
@@ -389,9 +477,16 @@ fn derive_initial_secret(
         0xa4, 0xc8, 0x0c, 0xad, 0xcc, 0xbb, 0x7f, 0x0a,
     ];
 
+    const INITIAL_SALT_V2: [u8; 20] = [
+        0x0d, 0xed, 0xe3, 0xde, 0xf7, 0x00, 0xa6, 0xdb, 0x81, 0x93, 0x81, 0xbe,
+        0x6e, 0x26, 0x9d, 0xcb, 0xf9, 0xbd, 0x2e, 0xd9,
+    ];
+
     let salt = match version {
         crate::PROTOCOL_VERSION_V1 => &INITIAL_SALT_V1,
 
+        crate::PROTOCOL_VERSION_V2 => &INITIAL_SALT_V2,
+
         _ => &INITIAL_SALT_V1,
     };
 
@@ -412,20 +507,34 @@ fn derive_server_initial_secret(
     hkdf_expand_label(aead, prk, LABEL, out)
 }
 
-fn derive_next_secret(aead: Algorithm, secret: &[u8]) -> Result<Vec<u8>> {
-    const LABEL: &[u8] = b"quic ku";
+// Returns the packet protection label to use for the given version, as QUIC
+// v2 uses its own labels (RFC 9369, Section 3.3.2).
+fn version_label(
+    version: u32, v1: &'static [u8], v2: &'static [u8],
+) -> &'static [u8] {
+    match version {
+        crate::PROTOCOL_VERSION_V2 => v2,
+
+        _ => v1,
+    }
+}
+
+fn derive_next_secret(
+    aead: Algorithm, secret: &[u8], version: u32,
+) -> Result<Vec<u8>> {
+    let label = version_label(version, b"quic ku", b"quicv2 ku");
 
     let mut next_secret = vec![0u8; 32];
 
-    hkdf_expand_label(aead, secret, LABEL, &mut next_secret)?;
+    hkdf_expand_label(aead, secret, label, &mut next_secret)?;
 
     Ok(next_secret)
 }
 
 pub fn derive_hdr_key(
-    aead: Algorithm, secret: &[u8], out: &mut [u8],
+    aead: Algorithm, secret: &[u8], version: u32, out: &mut [u8],
 ) -> Result<()> {
-    const LABEL: &[u8] = b"quic hp";
+    let label = version_label(version, b"quic hp", b"quicv2 hp");
 
     let key_len = aead.key_len();
 
@@ -433,11 +542,13 @@ pub fn derive_hdr_key(
         return Err(Error::CryptoFail);
     }
 
-    hkdf_expand_label(aead, secret, LABEL, &mut out[..key_len])
+    hkdf_expand_label(aead, secret, label, &mut out[..key_len])
 }
 
-pub fn derive_pkt_key(aead: Algorithm, prk: &[u8], out: &mut [u8]) -> Result<()> {
-    const LABEL: &[u8] = b"quic key";
+pub fn derive_pkt_key(
+    aead: Algorithm, prk: &[u8], version: u32, out: &mut [u8],
+) -> Result<()> {
+    let label = version_label(version, b"quic key", b"quicv2 key");
 
     let key_len: usize = aead.key_len();
 
@@ -445,11 +556,13 @@ pub fn derive_pkt_key(aead: Algorithm, prk: &[u8], out: &mut [u8]) -> Result<()>
         return Err(Error::CryptoFail);
     }
 
-    hkdf_expand_label(aead, prk, LABEL, &mut out[..key_len])
+    hkdf_expand_label(aead, prk, label, &mut out[..key_len])
 }
 
-pub fn derive_pkt_iv(aead: Algorithm, prk: &[u8], out: &mut [u8]) -> Result<()> {
-    const LABEL: &[u8] = b"quic iv";
+pub fn derive_pkt_iv(
+    aead: Algorithm, prk: &[u8], version: u32, out: &mut [u8],
+) -> Result<()> {
+    let label = version_label(version, b"quic iv", b"quicv2 iv");
 
     let nonce_len = aead.nonce_len();
 
@@ -457,7 +570,7 @@ pub fn derive_pkt_iv(aead: Algorithm, prk: &[u8], out: &mut [u8]) -> Result<()>
         return Err(Error::CryptoFail);
     }
 
-    hkdf_expand_label(aead, prk, LABEL, &mut out[..nonce_len])
+    hkdf_expand_label(aead, prk, label, &mut out[..nonce_len])
 }
 
 fn hkdf_expand_label(
@@ -476,12 +589,17 @@ fn hkdf_expand_label(
     Ok(())
 }
 
-fn make_nonce(iv: &[u8], counter: u64) -> [u8; MAX_NONCE_LEN] {
+fn make_nonce(iv: &[u8], path_id: u32, counter: u64) -> [u8; MAX_NONCE_LEN] {
     let mut nonce = [0; MAX_NONCE_LEN];
     nonce.copy_from_slice(iv);
 
-    // XOR the last bytes of the IV with the counter. This is equivalent to
-    // left-padding the counter with zero bytes.
+    // XOR the last bytes of the IV with the counter, left-padded with the
+    // multipath path ID. With path ID 0 this is equivalent to left-padding
+    // the counter with zero bytes.
+    for (a, b) in nonce[..4].iter_mut().zip(path_id.to_be_bytes().iter()) {
+        *a ^= b;
+    }
+
     for (a, b) in nonce[4..].iter_mut().zip(counter.to_be_bytes().iter()) {
         *a ^= b;
     }
@@ -548,21 +666,39 @@ mod tests {
         ];
         assert_eq!(&secret, &expected_client_initial_secret);
 
-        assert!(derive_pkt_key(aead, &secret, &mut pkt_key).is_ok());
+        assert!(derive_pkt_key(
+            aead,
+            &secret,
+            crate::PROTOCOL_VERSION_V1,
+            &mut pkt_key
+        )
+        .is_ok());
         let expected_client_pkt_key = [
             0x1f, 0x36, 0x96, 0x13, 0xdd, 0x76, 0xd5, 0x46, 0x77, 0x30, 0xef,
             0xcb, 0xe3, 0xb1, 0xa2, 0x2d,
         ];
         assert_eq!(&pkt_key, &expected_client_pkt_key);
 
-        assert!(derive_pkt_iv(aead, &secret, &mut pkt_iv).is_ok());
+        assert!(derive_pkt_iv(
+            aead,
+            &secret,
+            crate::PROTOCOL_VERSION_V1,
+            &mut pkt_iv
+        )
+        .is_ok());
         let expected_client_pkt_iv = [
             0xfa, 0x04, 0x4b, 0x2f, 0x42, 0xa3, 0xfd, 0x3b, 0x46, 0xfb, 0x25,
             0x5c,
         ];
         assert_eq!(&pkt_iv, &expected_client_pkt_iv);
 
-        assert!(derive_hdr_key(aead, &secret, &mut hdr_key).is_ok());
+        assert!(derive_hdr_key(
+            aead,
+            &secret,
+            crate::PROTOCOL_VERSION_V1,
+            &mut hdr_key
+        )
+        .is_ok());
         let expected_client_hdr_key = [
             0x9f, 0x50, 0x44, 0x9e, 0x04, 0xa0, 0xe8, 0x10, 0x28, 0x3a, 0x1e,
             0x99, 0x33, 0xad, 0xed, 0xd2,
@@ -582,21 +718,39 @@ mod tests {
         ];
         assert_eq!(&secret, &expected_server_initial_secret);
 
-        assert!(derive_pkt_key(aead, &secret, &mut pkt_key).is_ok());
+        assert!(derive_pkt_key(
+            aead,
+            &secret,
+            crate::PROTOCOL_VERSION_V1,
+            &mut pkt_key
+        )
+        .is_ok());
         let expected_server_pkt_key = [
             0xcf, 0x3a, 0x53, 0x31, 0x65, 0x3c, 0x36, 0x4c, 0x88, 0xf0, 0xf3,
             0x79, 0xb6, 0x06, 0x7e, 0x37,
         ];
         assert_eq!(&pkt_key, &expected_server_pkt_key);
 
-        assert!(derive_pkt_iv(aead, &secret, &mut pkt_iv).is_ok());
+        assert!(derive_pkt_iv(
+            aead,
+            &secret,
+            crate::PROTOCOL_VERSION_V1,
+            &mut pkt_iv
+        )
+        .is_ok());
         let expected_server_pkt_iv = [
             0x0a, 0xc1, 0x49, 0x3c, 0xa1, 0x90, 0x58, 0x53, 0xb0, 0xbb, 0xa0,
             0x3e,
         ];
         assert_eq!(&pkt_iv, &expected_server_pkt_iv);
 
-        assert!(derive_hdr_key(aead, &secret, &mut hdr_key).is_ok());
+        assert!(derive_hdr_key(
+            aead,
+            &secret,
+            crate::PROTOCOL_VERSION_V1,
+            &mut hdr_key
+        )
+        .is_ok());
         let expected_server_hdr_key = [
             0xc2, 0x06, 0xb8, 0xd9, 0xb9, 0xf0, 0xf3, 0x76, 0x44, 0x43, 0x0b,
             0x49, 0x0e, 0xea, 0xa3, 0x14,
@@ -604,6 +758,129 @@ mod tests {
         assert_eq!(&hdr_key, &expected_server_hdr_key);
     }
 
+    #[test]
+    fn derive_initial_secrets_v2() {
+        let dcid = [0x83, 0x94, 0xc8, 0xf0, 0x3e, 0x51, 0x57, 0x08];
+
+        let mut initial_secret = [0; 32];
+
+        let mut secret = [0; 32];
+        let mut pkt_key = [0; 16];
+        let mut pkt_iv = [0; 12];
+        let mut hdr_key = [0; 16];
+
+        let aead = Algorithm::AES128_GCM;
+
+        assert!(derive_initial_secret(
+            &dcid,
+            crate::PROTOCOL_VERSION_V2,
+            &mut initial_secret,
+        )
+        .is_ok());
+
+        // Client.
+        assert!(
+            derive_client_initial_secret(aead, &initial_secret, &mut secret)
+                .is_ok()
+        );
+        let expected_client_initial_secret = [
+            0x14, 0xec, 0x9d, 0x6e, 0xb9, 0xfd, 0x7a, 0xf8, 0x3b, 0xf5, 0xa6,
+            0x68, 0xbc, 0x17, 0xa7, 0xe2, 0x83, 0x76, 0x6a, 0xad, 0xe7, 0xec,
+            0xd0, 0x89, 0x1f, 0x70, 0xf9, 0xff, 0x7f, 0x4b, 0xf4, 0x7b,
+        ];
+        assert_eq!(&secret, &expected_client_initial_secret);
+
+        assert!(derive_pkt_key(
+            aead,
+            &secret,
+            crate::PROTOCOL_VERSION_V2,
+            &mut pkt_key
+        )
+        .is_ok());
+        let expected_client_pkt_key = [
+            0x8b, 0x1a, 0x0b, 0xc1, 0x21, 0x28, 0x42, 0x90, 0xa2, 0x9e, 0x09,
+            0x71, 0xb5, 0xcd, 0x04, 0x5d,
+        ];
+        assert_eq!(&pkt_key, &expected_client_pkt_key);
+
+        assert!(derive_pkt_iv(
+            aead,
+            &secret,
+            crate::PROTOCOL_VERSION_V2,
+            &mut pkt_iv
+        )
+        .is_ok());
+        let expected_client_pkt_iv = [
+            0x91, 0xf7, 0x3e, 0x23, 0x51, 0xd8, 0xfa, 0x91, 0x66, 0x0e, 0x90,
+            0x9f,
+        ];
+        assert_eq!(&pkt_iv, &expected_client_pkt_iv);
+
+        assert!(derive_hdr_key(
+            aead,
+            &secret,
+            crate::PROTOCOL_VERSION_V2,
+            &mut hdr_key
+        )
+        .is_ok());
+        let expected_client_hdr_key = [
+            0x45, 0xb9, 0x5e, 0x15, 0x23, 0x5d, 0x6f, 0x45, 0xa6, 0xb1, 0x9c,
+            0xbc, 0xb0, 0x29, 0x4b, 0xa9,
+        ];
+        assert_eq!(&hdr_key, &expected_client_hdr_key);
+
+        // Server.
+        assert!(
+            derive_server_initial_secret(aead, &initial_secret, &mut secret)
+                .is_ok()
+        );
+        let expected_server_initial_secret = [
+            0x02, 0x63, 0xdb, 0x17, 0x82, 0x73, 0x1b, 0xf4, 0x58, 0x8e, 0x7e,
+            0x4d, 0x93, 0xb7, 0x46, 0x39, 0x07, 0xcb, 0x8c, 0xd8, 0x20, 0x0b,
+            0x5d, 0xa5, 0x5a, 0x8b, 0xd4, 0x88, 0xea, 0xfc, 0x37, 0xc1,
+        ];
+        assert_eq!(&secret, &expected_server_initial_secret);
+
+        assert!(derive_pkt_key(
+            aead,
+            &secret,
+            crate::PROTOCOL_VERSION_V2,
+            &mut pkt_key
+        )
+        .is_ok());
+        let expected_server_pkt_key = [
+            0x82, 0xdb, 0x63, 0x78, 0x61, 0xd5, 0x5e, 0x1d, 0x01, 0x1f, 0x19,
+            0xea, 0x71, 0xd5, 0xd2, 0xa7,
+        ];
+        assert_eq!(&pkt_key, &expected_server_pkt_key);
+
+        assert!(derive_pkt_iv(
+            aead,
+            &secret,
+            crate::PROTOCOL_VERSION_V2,
+            &mut pkt_iv
+        )
+        .is_ok());
+        let expected_server_pkt_iv = [
+            0xdd, 0x13, 0xc2, 0x76, 0x49, 0x9c, 0x02, 0x49, 0xd3, 0x31, 0x06,
+            0x52,
+        ];
+        assert_eq!(&pkt_iv, &expected_server_pkt_iv);
+
+        assert!(derive_hdr_key(
+            aead,
+            &secret,
+            crate::PROTOCOL_VERSION_V2,
+            &mut hdr_key
+        )
+        .is_ok());
+        let expected_server_hdr_key = [
+            0xed, 0xf6, 0xd0, 0x5c, 0x83, 0x12, 0x12, 0x01, 0xb4, 0x36, 0xe1,
+            0x68, 0x77, 0x59, 0x3c, 0x3a,
+        ];
+        assert_eq!(&hdr_key, &expected_server_hdr_key);
+    }
+
     #[test]
     fn derive_chacha20_secrets() {
         let secret = [
@@ -618,7 +895,13 @@ mod tests {
         let mut pkt_iv = [0; 12];
         let mut hdr_key = [0; 32];
 
-        assert!(derive_pkt_key(aead, &secret, &mut pkt_key).is_ok());
+        assert!(derive_pkt_key(
+            aead,
+            &secret,
+            crate::PROTOCOL_VERSION_V1,
+            &mut pkt_key
+        )
+        .is_ok());
         let expected_pkt_key = [
             0xc6, 0xd9, 0x8f, 0xf3, 0x44, 0x1c, 0x3f, 0xe1, 0xb2, 0x18, 0x20,
             0x94, 0xf6, 0x9c, 0xaa, 0x2e, 0xd4, 0xb7, 0x16, 0xb6, 0x54, 0x88,
@@ -626,14 +909,26 @@ mod tests {
         ];
         assert_eq!(&pkt_key, &expected_pkt_key);
 
-        assert!(derive_pkt_iv(aead, &secret, &mut pkt_iv).is_ok());
+        assert!(derive_pkt_iv(
+            aead,
+            &secret,
+            crate::PROTOCOL_VERSION_V1,
+            &mut pkt_iv
+        )
+        .is_ok());
         let expected_pkt_iv = [
             0xe0, 0x45, 0x9b, 0x34, 0x74, 0xbd, 0xd0, 0xe4, 0x4a, 0x41, 0xc1,
             0x44,
         ];
         assert_eq!(&pkt_iv, &expected_pkt_iv);
 
-        assert!(derive_hdr_key(aead, &secret, &mut hdr_key).is_ok());
+        assert!(derive_hdr_key(
+            aead,
+            &secret,
+            crate::PROTOCOL_VERSION_V1,
+            &mut hdr_key
+        )
+        .is_ok());
         let expected_hdr_key = [
             0x25, 0xa2, 0x82, 0xb9, 0xe8, 0x2f, 0x06, 0xf2, 0x1f, 0x48, 0x89,
             0x17, 0xa4, 0xfc, 0x8f, 0x1b, 0x73, 0x57, 0x36, 0x85, 0x60, 0x85,
@@ -641,6 +936,27 @@ mod tests {
         ];
         assert_eq!(&hdr_key, &expected_hdr_key);
     }
+
+    #[test]
+    fn make_nonce_path_id() {
+        let iv = [
+            0x6b, 0x26, 0x11, 0x4b, 0x9c, 0xba, 0x2b, 0x63, 0xa9, 0xe8, 0xdd,
+            0x4f,
+        ];
+
+        // Path ID 0 gives the same nonce as without multipath.
+        let expected_nonce = [
+            0x6b, 0x26, 0x11, 0x4b, 0x9c, 0xba, 0x2b, 0x63, 0xa9, 0xe8, 0x09,
+            0x7e,
+        ];
+        assert_eq!(make_nonce(&iv, 0, 54321), expected_nonce);
+
+        let expected_nonce = [
+            0x6b, 0x26, 0x11, 0x48, 0x9c, 0xba, 0x2b, 0x63, 0xa9, 0xe8, 0x09,
+            0x7e,
+        ];
+        assert_eq!(make_nonce(&iv, 3, 54321), expected_nonce);
+    }
 }
 
 #[cfg(not(feature = "openssl"))]
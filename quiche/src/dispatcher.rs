@@ -0,0 +1,646 @@
+// Copyright (C) 2025, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Packet dispatching for multi-threaded servers.
+//!
+//! A server that runs its connections on multiple worker threads needs to
+//! deliver each incoming packet to the worker owning the packet's
+//! connection. The [`Dispatcher`] makes that decision, based on the packet's
+//! destination connection ID:
+//!
+//! * Packets for a connection ID that was [registered] by a worker are routed
+//!   to that worker.
+//!
+//! * Long header packets for unknown connection IDs are assumed to start a new
+//!   connection, and are routed to a worker chosen from a hash of the
+//!   connection ID. This way all the packets a client sends before learning the
+//!   server's connection ID land on the same worker.
+//!
+//! * Packets with an unsupported version are answered with a Version
+//!   Negotiation packet, and, when [Retry is enabled], Initial packets without
+//!   a valid token are answered with a Retry packet, without involving any
+//!   worker.
+//!
+//! The dispatcher doesn't perform any I/O: it's up to the application to
+//! hand the packet off to the chosen worker, e.g. through a channel, or to
+//! send the response back to the client.
+//!
+//! ## Examples:
+//!
+//! ```no_run
+//! # let mut buf = [0; 65535];
+//! # let mut out = [0; 1350];
+//! # let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+//! # let workers: Vec<std::sync::mpsc::Sender<(Vec<u8>, std::net::SocketAddr)>> = vec![];
+//! let dispatcher = quiche::dispatcher::Dispatcher::new(4, 16).unwrap();
+//!
+//! loop {
+//!     let (len, from) = socket.recv_from(&mut buf).unwrap();
+//!
+//!     match dispatcher.dispatch(&buf[..len], from, &mut out) {
+//!         Ok(quiche::dispatcher::Dispatch::Worker { worker, .. }) => {
+//!             workers[worker].send((buf[..len].to_vec(), from)).unwrap();
+//!         },
+//!
+//!         Ok(quiche::dispatcher::Dispatch::Respond(len)) => {
+//!             socket.send_to(&out[..len], from).unwrap();
+//!         },
+//!
+//!         Ok(quiche::dispatcher::Dispatch::Drop) | Err(_) => (),
+//!     }
+//! }
+//! ```
+//!
+//! [registered]: Dispatcher::register
+//! [Retry is enabled]: Dispatcher::enable_retry
+
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+
+use std::hash::BuildHasher;
+
+use std::net::SocketAddr;
+
+use std::sync::RwLock;
+
+use crate::packet;
+use crate::ConnectionId;
+use crate::Error;
+use crate::Result;
+use crate::Type;
+
+/// The header fields of a packet needed to route it, borrowed from the
+/// packet's buffer.
+///
+/// Unlike [`Header`], parsing it doesn't copy or allocate anything.
+///
+/// [`Header`]: crate::Header
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HeaderView<'a> {
+    /// The type of the packet.
+    pub ty: Type,
+
+    /// The version of the packet. It's 0 for short header packets.
+    pub version: u32,
+
+    /// The destination connection ID of the packet.
+    pub dcid: &'a [u8],
+
+    /// The source connection ID of the packet. It's empty for short header
+    /// packets.
+    pub scid: &'a [u8],
+
+    /// The address verification token of the packet. Only present in
+    /// `Initial` packets of a supported version.
+    pub token: Option<&'a [u8]>,
+}
+
+impl<'a> HeaderView<'a> {
+    /// Parses the header of the QUIC packet in the given buffer.
+    ///
+    /// The `dcid_len` parameter is the length of the destination connection
+    /// ID, required to parse short header packets.
+    pub fn from_slice(buf: &'a [u8], dcid_len: usize) -> Result<Self> {
+        let mut b = octets::Octets::with_slice(buf);
+
+        let first = b.get_u8()?;
+
+        if !packet::Header::is_long(first) {
+            let dcid = b.get_bytes(dcid_len)?;
+
+            return Ok(HeaderView {
+                ty: Type::Short,
+                version: 0,
+                dcid: dcid.buf(),
+                scid: &[],
+                token: None,
+            });
+        }
+
+        let version = b.get_u32()?;
+
+        let ty = if version == 0 {
+            Type::VersionNegotiation
+        } else {
+            Type::from_long_header(first, version)?
+        };
+
+        let supported = crate::version_is_supported(version);
+
+        let dcid = b.get_bytes_with_u8_length()?;
+        let scid = b.get_bytes_with_u8_length()?;
+
+        if supported &&
+            (dcid.len() > crate::MAX_CONN_ID_LEN ||
+                scid.len() > crate::MAX_CONN_ID_LEN)
+        {
+            return Err(Error::InvalidPacket);
+        }
+
+        let token = if supported && ty == Type::Initial {
+            Some(b.get_bytes_with_varint_length()?.buf())
+        } else {
+            None
+        };
+
+        Ok(HeaderView {
+            ty,
+            version,
+            dcid: dcid.buf(),
+            scid: scid.buf(),
+            token,
+        })
+    }
+}
+
+type Shard = RwLock<HashMap<Vec<u8>, usize>>;
+
+/// A thread-safe map from connection IDs to the index of the worker owning
+/// the connection.
+///
+/// The map is split into shards, each behind its own lock, to reduce
+/// contention between workers updating it.
+pub struct RoutingTable {
+    shards: Box<[Shard]>,
+
+    hasher: RandomState,
+}
+
+impl RoutingTable {
+    /// Creates an empty table with the given number of shards.
+    pub fn new(shards: usize) -> Self {
+        RoutingTable {
+            shards: (0..shards.max(1))
+                .map(|_| RwLock::new(HashMap::new()))
+                .collect(),
+
+            hasher: RandomState::new(),
+        }
+    }
+
+    /// Routes the given connection ID to `worker`, returning the worker it
+    /// was previously routed to, if any.
+    pub fn insert(&self, cid: &[u8], worker: usize) -> Option<usize> {
+        self.shard(cid)
+            .write()
+            .unwrap()
+            .insert(cid.to_vec(), worker)
+    }
+
+    /// Removes the route of the given connection ID, returning the worker it
+    /// was routed to, if any.
+    pub fn remove(&self, cid: &[u8]) -> Option<usize> {
+        self.shard(cid).write().unwrap().remove(cid)
+    }
+
+    /// Returns the worker the given connection ID is routed to.
+    pub fn get(&self, cid: &[u8]) -> Option<usize> {
+        self.shard(cid).read().unwrap().get(cid).copied()
+    }
+
+    /// Returns the number of routes in the table.
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|s| s.read().unwrap().len()).sum()
+    }
+
+    /// Returns true if the table has no routes.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn shard(&self, cid: &[u8]) -> &Shard {
+        let i = self.hasher.hash_one(cid) as usize % self.shards.len();
+
+        &self.shards[i]
+    }
+}
+
+/// Mints and validates the address validation tokens carried by Retry
+/// packets.
+///
+/// Tokens need to be authenticated, e.g. with an AEAD whose key is shared
+/// by all the dispatchers of a deployment, so that clients can't forge them.
+pub trait TokenValidator: Send + Sync {
+    /// Returns a token for a client at `peer`, whose first Initial packet
+    /// had the given destination connection ID.
+    fn mint(&self, odcid: &[u8], peer: SocketAddr) -> Vec<u8>;
+
+    /// Checks a token received from `peer`, returning the original
+    /// destination connection ID it was minted for, or `None` if it's
+    /// invalid.
+    fn validate(&self, token: &[u8], peer: SocketAddr) -> Option<Vec<u8>>;
+}
+
+/// What to do with a packet, as decided by [`Dispatcher::dispatch()`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Dispatch {
+    /// Hand the packet off to the given worker.
+    Worker {
+        /// The index of the worker.
+        worker: usize,
+
+        /// The original destination connection ID of a client that was
+        /// sent a Retry, to be passed to [`accept()`].
+        ///
+        /// [`accept()`]: crate::accept
+        odcid: Option<ConnectionId<'static>>,
+    },
+
+    /// Send the packet written to the output buffer, of the given length,
+    /// back to the peer, and drop the received packet.
+    Respond(usize),
+
+    /// Drop the packet.
+    Drop,
+}
+
+/// Routes incoming packets to the worker owning their connection.
+pub struct Dispatcher {
+    routes: RoutingTable,
+
+    workers: usize,
+
+    dcid_len: usize,
+
+    token_validator: Option<Box<dyn TokenValidator>>,
+}
+
+impl Dispatcher {
+    /// Creates a dispatcher for the given number of workers.
+    ///
+    /// The `dcid_len` parameter is the length of the connection IDs issued
+    /// by the workers, required to parse short header packets. It can't be
+    /// longer than [`MAX_CONN_ID_LEN`].
+    ///
+    /// [`MAX_CONN_ID_LEN`]: crate::MAX_CONN_ID_LEN
+    pub fn new(workers: usize, dcid_len: usize) -> Result<Self> {
+        if dcid_len > crate::MAX_CONN_ID_LEN {
+            return Err(Error::InvalidState);
+        }
+
+        Ok(Dispatcher {
+            routes: RoutingTable::new(workers * 4),
+            workers: workers.max(1),
+            dcid_len,
+            token_validator: None,
+        })
+    }
+
+    /// Answers Initial packets that don't carry a valid token with a Retry
+    /// packet, so that only clients whose address was validated are handed
+    /// off to a worker.
+    pub fn enable_retry(&mut self, token_validator: Box<dyn TokenValidator>) {
+        self.token_validator = Some(token_validator);
+    }
+
+    /// Routes the given connection ID to `worker`.
+    ///
+    /// Workers need to register the source connection IDs of their
+    /// connections, including the ones issued after the handshake, for
+    /// packets sent to them to be routed back.
+    pub fn register(&self, cid: &[u8], worker: usize) {
+        self.routes.insert(cid, worker);
+    }
+
+    /// Removes the route of the given connection ID, e.g. after it was
+    /// retired or its connection closed.
+    pub fn unregister(&self, cid: &[u8]) {
+        self.routes.remove(cid);
+    }
+
+    /// Returns the table of registered routes.
+    pub fn routes(&self) -> &RoutingTable {
+        &self.routes
+    }
+
+    /// Decides what to do with a packet received from `from`.
+    ///
+    /// Responses, if any, are written to `out`.
+    pub fn dispatch(
+        &self, buf: &[u8], from: SocketAddr, out: &mut [u8],
+    ) -> Result<Dispatch> {
+        let hdr = HeaderView::from_slice(buf, self.dcid_len)?;
+
+        if let Some(worker) = self.routes.get(hdr.dcid) {
+            return Ok(Dispatch::Worker {
+                worker,
+                odcid: None,
+            });
+        }
+
+        // Short header packets for unknown connections can't be routed.
+        if hdr.ty == Type::Short {
+            return Ok(Dispatch::Drop);
+        }
+
+        if !crate::version_is_supported(hdr.version) {
+            // Only answer packets that could be a client's first Initial,
+            // to avoid being used for amplification.
+            if hdr.ty == Type::VersionNegotiation ||
+                buf.len() < crate::MIN_CLIENT_INITIAL_LEN
+            {
+                return Ok(Dispatch::Drop);
+            }
+
+            let len = packet::negotiate_version(hdr.scid, hdr.dcid, out)?;
+
+            return Ok(Dispatch::Respond(len));
+        }
+
+        let mut odcid = None;
+
+        if let (Type::Initial, Some(validator)) = (hdr.ty, &self.token_validator)
+        {
+            // Initials in datagrams too small to be a client's first flight
+            // must be discarded, rather than answered with a Retry.
+            if buf.len() < crate::MIN_CLIENT_INITIAL_LEN {
+                return Ok(Dispatch::Drop);
+            }
+
+            let token = hdr.token.unwrap_or_default();
+
+            if token.is_empty() {
+                let mut new_scid = [0; crate::MAX_CONN_ID_LEN];
+                let new_scid = &mut new_scid[..self.dcid_len];
+                crate::rand::rand_bytes(new_scid);
+
+                let token = validator.mint(hdr.dcid, from);
+
+                let len = packet::retry(
+                    hdr.scid,
+                    hdr.dcid,
+                    new_scid,
+                    &token,
+                    hdr.version,
+                    out,
+                )?;
+
+                return Ok(Dispatch::Respond(len));
+            }
+
+            match validator.validate(token, from) {
+                Some(v) => odcid = Some(v.into()),
+
+                None => return Ok(Dispatch::Drop),
+            }
+        }
+
+        Ok(Dispatch::Worker {
+            worker: self.worker_for(hdr.dcid),
+            odcid,
+        })
+    }
+
+    /// Picks the worker for a new connection from its destination
+    /// connection ID.
+    fn worker_for(&self, dcid: &[u8]) -> usize {
+        self.routes.hasher.hash_one(dcid) as usize % self.workers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct EchoTokens;
+
+    impl TokenValidator for EchoTokens {
+        fn mint(&self, odcid: &[u8], _peer: SocketAddr) -> Vec<u8> {
+            [b"token".as_slice(), odcid].concat()
+        }
+
+        fn validate(&self, token: &[u8], _peer: SocketAddr) -> Option<Vec<u8>> {
+            token.strip_prefix(b"token").map(|v| v.to_vec())
+        }
+    }
+
+    fn initial(dcid: &[u8], token: &[u8], version: u32) -> Vec<u8> {
+        let mut buf = vec![0; crate::MIN_CLIENT_INITIAL_LEN];
+
+        let hdr = packet::Header {
+            ty: Type::Initial,
+            version,
+            dcid: ConnectionId::from_ref(dcid),
+            scid: ConnectionId::from_ref(&[0xba; 8]),
+            pkt_num: 0,
+            pkt_num_len: 0,
+            token: Some(token.to_vec()),
+            versions: None,
+            key_phase: false,
+        };
+
+        let mut b = octets::OctetsMut::with_slice(&mut buf);
+        hdr.to_bytes(&mut b).unwrap();
+
+        buf
+    }
+
+    fn peer() -> SocketAddr {
+        "127.0.0.1:1234".parse().unwrap()
+    }
+
+    #[test]
+    fn header_view() {
+        let buf = initial(&[0xa1; 16], b"tok", crate::PROTOCOL_VERSION);
+
+        let hdr = HeaderView::from_slice(&buf, 16).unwrap();
+        assert_eq!(hdr.ty, Type::Initial);
+        assert_eq!(hdr.version, crate::PROTOCOL_VERSION);
+        assert_eq!(hdr.dcid, &[0xa1; 16]);
+        assert_eq!(hdr.scid, &[0xba; 8]);
+        assert_eq!(hdr.token, Some(b"tok".as_slice()));
+
+        let mut short = vec![0x40];
+        short.extend_from_slice(&[0xa2; 16]);
+        short.extend_from_slice(&[0; 32]);
+
+        let hdr = HeaderView::from_slice(&short, 16).unwrap();
+        assert_eq!(hdr.ty, Type::Short);
+        assert_eq!(hdr.dcid, &[0xa2; 16]);
+        assert_eq!(hdr.token, None);
+
+        assert_eq!(
+            HeaderView::from_slice(&short[..8], 16),
+            Err(Error::BufferTooShort)
+        );
+    }
+
+    #[test]
+    fn routing_table() {
+        let routes = RoutingTable::new(4);
+        assert!(routes.is_empty());
+
+        assert_eq!(routes.insert(&[1; 16], 2), None);
+        assert_eq!(routes.insert(&[2; 16], 3), None);
+        assert_eq!(routes.insert(&[1; 16], 1), Some(2));
+        assert_eq!(routes.len(), 2);
+
+        assert_eq!(routes.get(&[1; 16]), Some(1));
+        assert_eq!(routes.remove(&[1; 16]), Some(1));
+        assert_eq!(routes.get(&[1; 16]), None);
+        assert_eq!(routes.len(), 1);
+    }
+
+    #[test]
+    fn dispatcher_dcid_len() {
+        assert!(Dispatcher::new(4, crate::MAX_CONN_ID_LEN).is_ok());
+
+        assert!(matches!(
+            Dispatcher::new(4, crate::MAX_CONN_ID_LEN + 1),
+            Err(Error::InvalidState)
+        ));
+    }
+
+    #[test]
+    fn dispatch_registered() {
+        let dispatcher = Dispatcher::new(4, 16).unwrap();
+        let mut out = [0; 1350];
+
+        dispatcher.register(&[0xa3; 16], 3);
+
+        let mut short = vec![0x40];
+        short.extend_from_slice(&[0xa3; 16]);
+        short.extend_from_slice(&[0; 32]);
+
+        assert_eq!(
+            dispatcher.dispatch(&short, peer(), &mut out),
+            Ok(Dispatch::Worker {
+                worker: 3,
+                odcid: None
+            })
+        );
+
+        dispatcher.unregister(&[0xa3; 16]);
+
+        assert_eq!(
+            dispatcher.dispatch(&short, peer(), &mut out),
+            Ok(Dispatch::Drop)
+        );
+    }
+
+    #[test]
+    fn dispatch_new_connection() {
+        let dispatcher = Dispatcher::new(4, 16).unwrap();
+        let mut out = [0; 1350];
+
+        let buf = initial(&[0xa4; 16], b"", crate::PROTOCOL_VERSION);
+
+        let worker = match dispatcher.dispatch(&buf, peer(), &mut out) {
+            Ok(Dispatch::Worker { worker, odcid }) => {
+                assert_eq!(odcid, None);
+                worker
+            },
+
+            v => panic!("unexpected dispatch {v:?}"),
+        };
+
+        assert!(worker < 4);
+
+        // Packets of the same connection go to the same worker.
+        assert_eq!(
+            dispatcher.dispatch(&buf, peer(), &mut out),
+            Ok(Dispatch::Worker {
+                worker,
+                odcid: None
+            })
+        );
+    }
+
+    #[test]
+    fn dispatch_version_negotiation() {
+        let dispatcher = Dispatcher::new(4, 16).unwrap();
+        let mut out = [0; 1350];
+
+        let buf = initial(&[0xa5; 16], b"", 0xbabababa);
+
+        let len = match dispatcher.dispatch(&buf, peer(), &mut out) {
+            Ok(Dispatch::Respond(len)) => len,
+
+            v => panic!("unexpected dispatch {v:?}"),
+        };
+
+        let hdr = HeaderView::from_slice(&out[..len], 16).unwrap();
+        assert_eq!(hdr.ty, Type::VersionNegotiation);
+        assert_eq!(hdr.dcid, &[0xba; 8]);
+        assert_eq!(hdr.scid, &[0xa5; 16]);
+
+        // Packets too small to be a client's first Initial are ignored.
+        assert_eq!(
+            dispatcher.dispatch(&buf[..100], peer(), &mut out),
+            Ok(Dispatch::Drop)
+        );
+    }
+
+    #[test]
+    fn dispatch_retry() {
+        let mut dispatcher = Dispatcher::new(4, 16).unwrap();
+        dispatcher.enable_retry(Box::new(EchoTokens));
+
+        let mut out = [0; 1350];
+
+        let buf = initial(&[0xa6; 16], b"", crate::PROTOCOL_VERSION);
+
+        let len = match dispatcher.dispatch(&buf, peer(), &mut out) {
+            Ok(Dispatch::Respond(len)) => len,
+
+            v => panic!("unexpected dispatch {v:?}"),
+        };
+
+        let hdr = packet::Header::from_slice(&mut out[..len], 16).unwrap();
+        assert_eq!(hdr.ty, Type::Retry);
+        assert_eq!(hdr.dcid, ConnectionId::from_ref(&[0xba; 8]));
+
+        let token = hdr.token.unwrap();
+        assert_eq!(token, [b"token".as_slice(), &[0xa6; 16]].concat());
+
+        // Initials too small to be a client's first flight aren't answered.
+        let short = initial(&[0xa7; 16], b"", crate::PROTOCOL_VERSION);
+
+        assert_eq!(
+            dispatcher.dispatch(&short[..100], peer(), &mut out),
+            Ok(Dispatch::Drop)
+        );
+
+        // The client's next Initial carries the token.
+        let buf = initial(&hdr.scid, &token, crate::PROTOCOL_VERSION);
+
+        match dispatcher.dispatch(&buf, peer(), &mut out) {
+            Ok(Dispatch::Worker { odcid, .. }) => {
+                assert_eq!(odcid, Some(ConnectionId::from_ref(&[0xa6; 16])))
+            },
+
+            v => panic!("unexpected dispatch {v:?}"),
+        }
+
+        // Invalid tokens are dropped.
+        let buf = initial(&hdr.scid, b"invalid", crate::PROTOCOL_VERSION);
+
+        assert_eq!(
+            dispatcher.dispatch(&buf, peer(), &mut out),
+            Ok(Dispatch::Drop)
+        );
+    }
+}
@@ -0,0 +1,563 @@
+// Copyright (C) 2025, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Explicit Congestion Notification.
+//!
+//! Outgoing packets are marked with the configured ECN-Capable Transport
+//! codepoint, and the ECN counts carried by ACK frames are used to validate
+//! that the path and the peer support ECN, as described in Section 13.4 of
+//! RFC 9000. Increases of the ECN-CE count are reported to the congestion
+//! controller.
+
+use std::collections::VecDeque;
+
+use crate::frame::EcnCounts;
+use crate::packet;
+use crate::ranges;
+
+/// The number of packets marked while testing a path, before waiting for the
+/// ECN counts from the peer to validate it.
+const ECN_TESTING_PACKETS: usize = 10;
+
+/// The ECN codepoint of the IP header of a packet.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Ecn {
+    /// Not ECN-Capable Transport.
+    #[default]
+    NotEct = 0b00,
+
+    /// ECN-Capable Transport, ECT(1).
+    Ect1   = 0b01,
+
+    /// ECN-Capable Transport, ECT(0).
+    Ect0   = 0b10,
+
+    /// Congestion Experienced.
+    Ce     = 0b11,
+}
+
+impl Ecn {
+    /// Returns whether the codepoint marks the packet as ECN-capable.
+    pub fn is_ect(self) -> bool {
+        matches!(self, Ecn::Ect0 | Ecn::Ect1)
+    }
+}
+
+impl From<u8> for Ecn {
+    /// Converts the ECN field of an IP header, i.e. the 2 least significant
+    /// bits of the IPv4 TOS or IPv6 Traffic Class byte.
+    fn from(v: u8) -> Self {
+        match v & 0b11 {
+            0b01 => Ecn::Ect1,
+            0b10 => Ecn::Ect0,
+            0b11 => Ecn::Ce,
+            _ => Ecn::NotEct,
+        }
+    }
+}
+
+impl EcnCounts {
+    /// Accounts for a received packet carrying the given codepoint.
+    pub fn on_packet_received(&mut self, ecn: Ecn) {
+        match ecn {
+            Ecn::Ect0 => self.ect0_count += 1,
+            Ecn::Ect1 => self.ect1_count += 1,
+            Ecn::Ce => self.ecn_ce_count += 1,
+            Ecn::NotEct => (),
+        }
+    }
+
+    /// Returns whether any ECN-marked packet was received, i.e. whether the
+    /// counts should be reported in ACK frames.
+    pub fn any(&self) -> bool {
+        self.ect0_count + self.ect1_count + self.ecn_ce_count > 0
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ValidationState {
+    /// Marked packets are being sent to test the path.
+    Testing,
+
+    /// Enough marked packets were sent, waiting for them to be acknowledged.
+    Unknown,
+
+    /// The ECN counts reported by the peer were validated.
+    Capable,
+
+    /// Validation failed, packets are not marked anymore.
+    Failed,
+}
+
+/// ECN state of a single path.
+pub struct EcnState {
+    /// The ECT codepoint used to mark packets, if enabled.
+    codepoint: Ecn,
+
+    state: ValidationState,
+
+    /// The number of marked packets sent while testing the path.
+    testing_sent: usize,
+
+    /// The codepoint of the datagram currently being built.
+    datagram: Ecn,
+
+    /// The numbers of the marked packets that were not acknowledged yet, for
+    /// each packet number space.
+    marked: [VecDeque<u64>; packet::Epoch::count()],
+
+    /// The last ECN counts received in ACK frames, for each packet number
+    /// space.
+    peer_counts: [EcnCounts; packet::Epoch::count()],
+
+    /// The last ECN counts received in PATH_ACK frames, along with the path
+    /// ID they refer to.
+    mp_peer_counts: Option<(u64, EcnCounts)>,
+
+    /// The number of packets the peer reported as CE-marked.
+    ce_count: u64,
+}
+
+impl Default for EcnState {
+    fn default() -> Self {
+        EcnState::new(Ecn::NotEct)
+    }
+}
+
+impl EcnState {
+    /// Creates a new ECN state marking packets with the given codepoint.
+    ///
+    /// Codepoints other than ECT(0) and ECT(1) disable ECN.
+    pub fn new(codepoint: Ecn) -> Self {
+        let (codepoint, state) = if codepoint.is_ect() {
+            (codepoint, ValidationState::Testing)
+        } else {
+            (Ecn::NotEct, ValidationState::Failed)
+        };
+
+        EcnState {
+            codepoint,
+            state,
+            testing_sent: 0,
+            datagram: Ecn::NotEct,
+            marked: Default::default(),
+            peer_counts: Default::default(),
+            mp_peer_counts: None,
+            ce_count: 0,
+        }
+    }
+
+    /// Returns whether ECN was validated for the path.
+    pub fn is_capable(&self) -> bool {
+        self.state == ValidationState::Capable
+    }
+
+    /// Returns the number of packets the peer reported as CE-marked.
+    pub fn ce_count(&self) -> u64 {
+        self.ce_count
+    }
+
+    /// Returns the codepoint the next datagram would be marked with, without
+    /// starting it.
+    pub fn next_codepoint(&self) -> Ecn {
+        match self.state {
+            ValidationState::Testing | ValidationState::Capable => self.codepoint,
+
+            ValidationState::Unknown | ValidationState::Failed => Ecn::NotEct,
+        }
+    }
+
+    /// Returns the codepoint the next datagram should be marked with.
+    pub fn begin_datagram(&mut self) -> Ecn {
+        self.datagram = self.next_codepoint();
+
+        self.datagram
+    }
+
+    /// Records a packet sent as part of the current datagram.
+    pub fn on_packet_sent(&mut self, epoch: packet::Epoch, pkt_num: u64) {
+        if self.datagram == Ecn::NotEct {
+            return;
+        }
+
+        self.marked[epoch].push_back(pkt_num);
+
+        if self.state == ValidationState::Testing {
+            self.testing_sent += 1;
+
+            if self.testing_sent >= ECN_TESTING_PACKETS {
+                self.state = ValidationState::Unknown;
+            }
+        }
+    }
+
+    /// Validates the ECN counts carried by an ACK frame, or by a PATH_ACK
+    /// frame for the given `path_id`, and returns the number of newly
+    /// reported CE marks.
+    pub fn on_ack_received(
+        &mut self, epoch: packet::Epoch, path_id: Option<u64>,
+        ranges: &ranges::RangeSet, counts: Option<&EcnCounts>,
+    ) -> u64 {
+        if self.state == ValidationState::Failed {
+            return 0;
+        }
+
+        let newly_acked = self.detect_acked_marked_packets(epoch, ranges);
+
+        let counts = match counts {
+            Some(v) => v,
+
+            // The peer doesn't report ECN counts, or marks were cleared on
+            // the path.
+            None if newly_acked > 0 => {
+                self.fail();
+                return 0;
+            },
+
+            None => return 0,
+        };
+
+        let prev = match path_id {
+            Some(id) => match &self.mp_peer_counts {
+                Some((prev_id, prev)) if *prev_id == id => prev.clone(),
+
+                _ => EcnCounts::default(),
+            },
+
+            None => self.peer_counts[epoch].clone(),
+        };
+
+        // ECN counts can't decrease.
+        if counts.ect0_count < prev.ect0_count ||
+            counts.ect1_count < prev.ect1_count ||
+            counts.ecn_ce_count < prev.ecn_ce_count
+        {
+            self.fail();
+            return 0;
+        }
+
+        let ect0 = counts.ect0_count - prev.ect0_count;
+        let ect1 = counts.ect1_count - prev.ect1_count;
+        let ce = counts.ecn_ce_count - prev.ecn_ce_count;
+
+        let (sent_ect, other_ect) = match self.codepoint {
+            Ecn::Ect1 => (ect1, ect0),
+
+            _ => (ect0, ect1),
+        };
+
+        // The codepoint that was not sent was reported, or newly acknowledged
+        // packets were not counted, meaning marks were rewritten or cleared.
+        if other_ect > 0 || sent_ect + ce < newly_acked {
+            self.fail();
+            return 0;
+        }
+
+        match path_id {
+            Some(id) => self.mp_peer_counts = Some((id, counts.clone())),
+
+            None => self.peer_counts[epoch] = counts.clone(),
+        }
+
+        // CE marks are only attributed to the path if some of its marked
+        // packets were acknowledged, as the counts might cover other paths.
+        if newly_acked == 0 {
+            return 0;
+        }
+
+        self.state = ValidationState::Capable;
+
+        self.ce_count += ce;
+
+        ce
+    }
+
+    /// Removes the marked packets acknowledged by `ranges`, returning how
+    /// many there were.
+    fn detect_acked_marked_packets(
+        &mut self, epoch: packet::Epoch, ranges: &ranges::RangeSet,
+    ) -> u64 {
+        let smallest = match ranges.first() {
+            Some(v) => v,
+
+            None => return 0,
+        };
+
+        let marked = &mut self.marked[epoch];
+
+        // Packets below the smallest acknowledged one were either acked
+        // already or lost, so they don't need to be tracked anymore.
+        let mut newly_acked = 0;
+
+        marked.retain(|&pn| {
+            if pn < smallest {
+                return false;
+            }
+
+            if ranges.iter().any(|r| r.contains(&pn)) {
+                newly_acked += 1;
+                return false;
+            }
+
+            true
+        });
+
+        newly_acked
+    }
+
+    fn fail(&mut self) {
+        trace!("ECN validation failed");
+
+        self.state = ValidationState::Failed;
+
+        for marked in self.marked.iter_mut() {
+            marked.clear();
+        }
+    }
+}
+
+impl std::fmt::Debug for EcnState {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "codepoint={:?} ", self.codepoint)?;
+        write!(f, "state={:?} ", self.state)?;
+        write!(f, "ce_count={:?} ", self.ce_count)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::packet::Epoch;
+
+    fn send(ecn: &mut EcnState, pkt_nums: std::ops::Range<u64>) -> Ecn {
+        let codepoint = ecn.begin_datagram();
+
+        for pn in pkt_nums {
+            ecn.on_packet_sent(Epoch::Application, pn);
+        }
+
+        codepoint
+    }
+
+    fn acked(pkt_nums: std::ops::Range<u64>) -> ranges::RangeSet {
+        let mut ranges = ranges::RangeSet::default();
+        ranges.insert(pkt_nums);
+        ranges
+    }
+
+    fn counts(ect0: u64, ect1: u64, ce: u64) -> EcnCounts {
+        EcnCounts {
+            ect0_count: ect0,
+            ect1_count: ect1,
+            ecn_ce_count: ce,
+        }
+    }
+
+    #[test]
+    fn codepoint_from_u8() {
+        assert_eq!(Ecn::from(0x00), Ecn::NotEct);
+        assert_eq!(Ecn::from(0x01), Ecn::Ect1);
+        assert_eq!(Ecn::from(0x02), Ecn::Ect0);
+        assert_eq!(Ecn::from(0x03), Ecn::Ce);
+
+        // Only the ECN bits of the TOS byte are considered.
+        assert_eq!(Ecn::from(0xb8 | 0x02), Ecn::Ect0);
+    }
+
+    #[test]
+    fn disabled() {
+        let mut ecn = EcnState::new(Ecn::NotEct);
+        assert_eq!(send(&mut ecn, 0..5), Ecn::NotEct);
+
+        let ce = ecn.on_ack_received(
+            Epoch::Application,
+            None,
+            &acked(0..5),
+            Some(&counts(0, 0, 5)),
+        );
+        assert_eq!(ce, 0);
+        assert!(!ecn.is_capable());
+
+        // CE isn't a valid codepoint to send.
+        let mut ecn = EcnState::new(Ecn::Ce);
+        assert_eq!(send(&mut ecn, 0..5), Ecn::NotEct);
+    }
+
+    #[test]
+    fn validation() {
+        let mut ecn = EcnState::new(Ecn::Ect0);
+        assert_eq!(send(&mut ecn, 0..3), Ecn::Ect0);
+        assert!(!ecn.is_capable());
+
+        let ce = ecn.on_ack_received(
+            Epoch::Application,
+            None,
+            &acked(0..3),
+            Some(&counts(3, 0, 0)),
+        );
+        assert_eq!(ce, 0);
+        assert!(ecn.is_capable());
+
+        // CE marks are reported as they are received.
+        assert_eq!(send(&mut ecn, 3..6), Ecn::Ect0);
+
+        let ce = ecn.on_ack_received(
+            Epoch::Application,
+            None,
+            &acked(0..6),
+            Some(&counts(4, 0, 2)),
+        );
+        assert_eq!(ce, 2);
+        assert_eq!(ecn.ce_count(), 2);
+        assert!(ecn.is_capable());
+
+        // Duplicate ACKs don't report CE marks again.
+        let ce = ecn.on_ack_received(
+            Epoch::Application,
+            None,
+            &acked(0..6),
+            Some(&counts(4, 0, 2)),
+        );
+        assert_eq!(ce, 0);
+        assert_eq!(ecn.ce_count(), 2);
+    }
+
+    #[test]
+    fn testing_packets_limit() {
+        let mut ecn = EcnState::new(Ecn::Ect1);
+
+        for pn in 0..ECN_TESTING_PACKETS as u64 {
+            assert_eq!(send(&mut ecn, pn..pn + 1), Ecn::Ect1);
+        }
+
+        // Marking stops until the path is validated.
+        assert_eq!(send(&mut ecn, 10..12), Ecn::NotEct);
+
+        let ce = ecn.on_ack_received(
+            Epoch::Application,
+            None,
+            &acked(0..12),
+            Some(&counts(0, 10, 0)),
+        );
+        assert_eq!(ce, 0);
+        assert!(ecn.is_capable());
+        assert_eq!(send(&mut ecn, 12..13), Ecn::Ect1);
+    }
+
+    #[test]
+    fn counts_missing() {
+        let mut ecn = EcnState::new(Ecn::Ect0);
+        send(&mut ecn, 0..3);
+
+        ecn.on_ack_received(Epoch::Application, None, &acked(0..3), None);
+        assert!(!ecn.is_capable());
+        assert_eq!(send(&mut ecn, 3..4), Ecn::NotEct);
+    }
+
+    #[test]
+    fn counts_too_low() {
+        let mut ecn = EcnState::new(Ecn::Ect0);
+        send(&mut ecn, 0..3);
+
+        ecn.on_ack_received(
+            Epoch::Application,
+            None,
+            &acked(0..3),
+            Some(&counts(2, 0, 0)),
+        );
+        assert!(!ecn.is_capable());
+        assert_eq!(send(&mut ecn, 3..4), Ecn::NotEct);
+    }
+
+    #[test]
+    fn counts_wrong_codepoint() {
+        let mut ecn = EcnState::new(Ecn::Ect0);
+        send(&mut ecn, 0..3);
+
+        ecn.on_ack_received(
+            Epoch::Application,
+            None,
+            &acked(0..3),
+            Some(&counts(3, 1, 0)),
+        );
+        assert!(!ecn.is_capable());
+        assert_eq!(send(&mut ecn, 3..4), Ecn::NotEct);
+    }
+
+    #[test]
+    fn counts_decreased() {
+        let mut ecn = EcnState::new(Ecn::Ect0);
+        send(&mut ecn, 0..3);
+
+        ecn.on_ack_received(
+            Epoch::Application,
+            None,
+            &acked(0..3),
+            Some(&counts(3, 0, 0)),
+        );
+        assert!(ecn.is_capable());
+
+        send(&mut ecn, 3..4);
+
+        ecn.on_ack_received(
+            Epoch::Application,
+            None,
+            &acked(3..4),
+            Some(&counts(2, 0, 2)),
+        );
+        assert!(!ecn.is_capable());
+    }
+
+    #[test]
+    fn path_ack_counts() {
+        let mut ecn = EcnState::new(Ecn::Ect0);
+        send(&mut ecn, 0..3);
+
+        ecn.on_ack_received(
+            Epoch::Application,
+            None,
+            &acked(0..3),
+            Some(&counts(3, 0, 0)),
+        );
+        assert!(ecn.is_capable());
+
+        // Counts carried by PATH_ACK frames are tracked separately from the
+        // ones carried by ACK frames.
+        send(&mut ecn, 3..5);
+
+        let ce = ecn.on_ack_received(
+            Epoch::Application,
+            Some(0),
+            &acked(3..5),
+            Some(&counts(1, 0, 1)),
+        );
+        assert_eq!(ce, 1);
+        assert!(ecn.is_capable());
+    }
+}
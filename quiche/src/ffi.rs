@@ -215,6 +215,11 @@ pub extern "C" fn quiche_config_discover_pmtu(config: &mut Config, v: bool) {
     config.discover_pmtu(v);
 }
 
+#[no_mangle]
+pub extern "C" fn quiche_config_set_ecn(config: &mut Config, v: u8) {
+    config.set_ecn(v.into());
+}
+
 #[no_mangle]
 pub extern "C" fn quiche_config_log_keys(config: &mut Config) {
     config.log_keys();
@@ -225,6 +230,19 @@ pub extern "C" fn quiche_config_enable_early_data(config: &mut Config) {
     config.enable_early_data();
 }
 
+#[no_mangle]
+pub extern "C" fn quiche_config_set_versions(
+    config: &mut Config, versions: *const u32, versions_len: size_t,
+) -> c_int {
+    let versions = unsafe { slice::from_raw_parts(versions, versions_len) };
+
+    match config.set_versions(versions) {
+        Ok(_) => 0,
+
+        Err(e) => e.to_c() as c_int,
+    }
+}
+
 #[no_mangle]
 /// Corresponds to the `Config::set_application_protos_wire_format` Rust
 /// function.
@@ -322,6 +340,30 @@ pub extern "C" fn quiche_config_set_disable_active_migration(
     config.set_disable_active_migration(v);
 }
 
+#[no_mangle]
+pub extern "C" fn quiche_config_enable_multipath(config: &mut Config, v: bool) {
+    config.enable_multipath(v);
+}
+
+#[no_mangle]
+pub extern "C" fn quiche_config_set_min_ack_delay(config: &mut Config, v: u64) {
+    config.set_min_ack_delay(v);
+}
+
+#[no_mangle]
+pub extern "C" fn quiche_config_set_ack_eliciting_threshold(
+    config: &mut Config, v: u64,
+) {
+    config.set_ack_eliciting_threshold(v);
+}
+
+#[no_mangle]
+pub extern "C" fn quiche_config_set_requested_max_ack_delay(
+    config: &mut Config, v: u64,
+) {
+    config.set_requested_max_ack_delay(std::time::Duration::from_millis(v));
+}
+
 #[no_mangle]
 pub extern "C" fn quiche_config_set_cc_algorithm_name(
     config: &mut Config, name: *const c_char,
@@ -363,6 +405,37 @@ pub extern "C" fn quiche_config_set_max_pacing_rate(config: &mut Config, v: u64)
     config.set_max_pacing_rate(v);
 }
 
+#[no_mangle]
+pub extern "C" fn quiche_config_set_pacer_algorithm_name(
+    config: &mut Config, name: *const c_char,
+) -> c_int {
+    let name = unsafe { ffi::CStr::from_ptr(name).to_str().unwrap() };
+    match config.set_pacer_algorithm_name(name) {
+        Ok(_) => 0,
+
+        Err(e) => e.to_c() as c_int,
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn quiche_config_set_pacer_algorithm(
+    config: &mut Config, algo: PacerAlgorithm,
+) {
+    config.set_pacer_algorithm(algo);
+}
+
+#[no_mangle]
+pub extern "C" fn quiche_config_set_max_pacing_burst(
+    config: &mut Config, packets: size_t,
+) {
+    config.set_max_pacing_burst(packets);
+}
+
+#[no_mangle]
+pub extern "C" fn quiche_config_set_pacing_gain(config: &mut Config, v: f64) {
+    config.set_pacing_gain(v);
+}
+
 #[no_mangle]
 pub extern "C" fn quiche_config_enable_dgram(
     config: &mut Config, enabled: bool, recv_queue_len: size_t,
@@ -722,6 +795,74 @@ pub extern "C" fn quiche_conn_set_qlog_fd(
     );
 }
 
+#[cfg(feature = "qlog")]
+struct QlogCallbackWriter {
+    cb: extern "C" fn(data: *const u8, data_len: size_t, argp: *mut c_void),
+    argp: atomic::AtomicPtr<c_void>,
+    buf: Vec<u8>,
+}
+
+#[cfg(feature = "qlog")]
+impl QlogCallbackWriter {
+    fn emit(&mut self, len: usize) {
+        if len == 0 {
+            return;
+        }
+
+        (self.cb)(
+            self.buf.as_ptr(),
+            len,
+            self.argp.load(atomic::Ordering::Relaxed),
+        );
+
+        self.buf.drain(..len);
+    }
+}
+
+#[cfg(feature = "qlog")]
+impl std::io::Write for QlogCallbackWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.buf.extend_from_slice(buf);
+
+        // Each JSON-SEQ record is terminated by a newline, so only hand
+        // complete records over to the application.
+        if let Some(pos) = self.buf.iter().rposition(|b| *b == b'\n') {
+            self.emit(pos + 1);
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.emit(self.buf.len());
+
+        Ok(())
+    }
+}
+
+#[no_mangle]
+#[cfg(feature = "qlog")]
+pub extern "C" fn quiche_conn_set_qlog_cb(
+    conn: &mut Connection,
+    cb: extern "C" fn(data: *const u8, data_len: size_t, argp: *mut c_void),
+    argp: *mut c_void, log_title: *const c_char, log_desc: *const c_char,
+) {
+    let writer = QlogCallbackWriter {
+        cb,
+        argp: atomic::AtomicPtr::new(argp),
+        buf: Vec::new(),
+    };
+
+    let title = unsafe { ffi::CStr::from_ptr(log_title).to_str().unwrap() };
+    let description = unsafe { ffi::CStr::from_ptr(log_desc).to_str().unwrap() };
+
+    conn.set_qlog(
+        Box::new(writer),
+        title.to_string(),
+        format!("{} id={}", description, conn.trace_id),
+    );
+}
+
 #[no_mangle]
 pub extern "C" fn quiche_conn_set_session(
     conn: &mut Connection, buf: *const u8, buf_len: size_t,
@@ -752,6 +893,8 @@ pub struct RecvInfo<'a> {
     from_len: socklen_t,
     to: &'a sockaddr,
     to_len: socklen_t,
+
+    ecn: u8,
 }
 
 impl From<&RecvInfo<'_>> for crate::RecvInfo {
@@ -759,6 +902,7 @@ impl From<&RecvInfo<'_>> for crate::RecvInfo {
         crate::RecvInfo {
             from: std_addr_from_c(info.from, info.from_len),
             to: std_addr_from_c(info.to, info.to_len),
+            ecn: info.ecn.into(),
         }
     }
 }
@@ -780,6 +924,43 @@ pub extern "C" fn quiche_conn_recv(
     }
 }
 
+#[no_mangle]
+pub extern "C" fn quiche_conn_recv_batch(
+    conn: &mut Connection, bufs: *const *mut u8, buf_lens: *const size_t,
+    infos: *const RecvInfo, count: size_t,
+) -> ssize_t {
+    if count == 0 {
+        return 0;
+    }
+
+    let bufs = unsafe { slice::from_raw_parts(bufs, count) };
+    let buf_lens = unsafe { slice::from_raw_parts(buf_lens, count) };
+    let infos = unsafe { slice::from_raw_parts(infos, count) };
+
+    let mut batch = Vec::with_capacity(count);
+
+    for ((&buf, &buf_len), info) in bufs.iter().zip(buf_lens).zip(infos) {
+        if buf_len > <ssize_t>::MAX as usize {
+            panic!("The provided buffer is too large");
+        }
+
+        // Empty datagrams are skipped, and might come with a NULL pointer.
+        let buf: &mut [u8] = if buf_len == 0 {
+            &mut []
+        } else {
+            unsafe { slice::from_raw_parts_mut(buf, buf_len) }
+        };
+
+        batch.push((buf, info.into()));
+    }
+
+    match conn.recv_batch(&mut batch) {
+        Ok(v) => v as ssize_t,
+
+        Err(e) => e.to_c(),
+    }
+}
+
 #[repr(C)]
 pub struct SendInfo {
     from: sockaddr_storage,
@@ -788,6 +969,10 @@ pub struct SendInfo {
     to_len: socklen_t,
 
     at: timespec,
+
+    ecn: u8,
+
+    pacing_rate: u64,
 }
 
 #[no_mangle]
@@ -807,6 +992,10 @@ pub extern "C" fn quiche_conn_send(
 
             std_time_to_c(&info.at, &mut out_info.at);
 
+            out_info.ecn = info.ecn as u8;
+
+            out_info.pacing_rate = info.pacing_rate();
+
             v as ssize_t
         },
 
@@ -835,6 +1024,41 @@ pub extern "C" fn quiche_conn_send_on_path(
 
             std_time_to_c(&info.at, &mut out_info.at);
 
+            out_info.ecn = info.ecn as u8;
+
+            out_info.pacing_rate = info.pacing_rate();
+
+            v as ssize_t
+        },
+
+        Err(e) => e.to_c(),
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn quiche_conn_send_multiple(
+    conn: &mut Connection, out: *mut u8, out_len: size_t,
+    out_segment_size: &mut size_t, out_info: &mut SendInfo,
+) -> ssize_t {
+    if out_len > <ssize_t>::MAX as usize {
+        panic!("The provided buffer is too large");
+    }
+
+    let out = unsafe { slice::from_raw_parts_mut(out, out_len) };
+
+    match conn.send_multiple(out) {
+        Ok((v, segment_size, info)) => {
+            *out_segment_size = segment_size;
+
+            out_info.from_len = std_addr_to_c(&info.from, &mut out_info.from);
+            out_info.to_len = std_addr_to_c(&info.to, &mut out_info.to);
+
+            std_time_to_c(&info.at, &mut out_info.at);
+
+            out_info.ecn = info.ecn as u8;
+
+            out_info.pacing_rate = info.pacing_rate();
+
             v as ssize_t
         },
 
@@ -1267,6 +1491,7 @@ pub struct Stats {
     stopped_stream_count_local: u64,
     reset_stream_count_remote: u64,
     stopped_stream_count_remote: u64,
+    ecn_ce_count: u64,
 }
 
 pub struct TransportParams {
@@ -1303,6 +1528,7 @@ pub extern "C" fn quiche_conn_stats(conn: &Connection, out: &mut Stats) {
     out.stopped_stream_count_local = stats.stopped_stream_count_local;
     out.reset_stream_count_remote = stats.reset_stream_count_remote;
     out.stopped_stream_count_remote = stats.stopped_stream_count_remote;
+    out.ecn_ce_count = stats.ecn_ce_count;
 }
 
 #[no_mangle]
@@ -1359,6 +1585,9 @@ pub struct PathStats {
     stream_retrans_bytes: u64,
     pmtu: usize,
     delivery_rate: u64,
+    pacing_rate: u64,
+    ecn_capable: bool,
+    ecn_ce_count: u64,
 }
 
 #[no_mangle]
@@ -1388,10 +1617,18 @@ pub extern "C" fn quiche_conn_path_stats(
     out.stream_retrans_bytes = stats.stream_retrans_bytes;
     out.pmtu = stats.pmtu;
     out.delivery_rate = stats.delivery_rate;
+    out.pacing_rate = stats.pacing_rate;
+    out.ecn_capable = stats.ecn_capable;
+    out.ecn_ce_count = stats.ecn_ce_count;
 
     0
 }
 
+#[no_mangle]
+pub extern "C" fn quiche_conn_version(conn: &Connection) -> u32 {
+    conn.version()
+}
+
 #[no_mangle]
 pub extern "C" fn quiche_conn_is_server(conn: &Connection) -> bool {
     conn.is_server()
@@ -1713,6 +1950,42 @@ pub extern "C" fn quiche_conn_migrate(
     }
 }
 
+#[no_mangle]
+pub extern "C" fn quiche_conn_send_immediate_ack(conn: &mut Connection) -> c_int {
+    match conn.send_immediate_ack() {
+        Ok(_) => 0,
+
+        Err(e) => e.to_c() as c_int,
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn quiche_conn_is_multipath_enabled(conn: &Connection) -> bool {
+    conn.is_multipath_enabled()
+}
+
+#[no_mangle]
+pub extern "C" fn quiche_conn_abandon_path(
+    conn: &mut Connection, local: &sockaddr, local_len: socklen_t,
+    peer: &sockaddr, peer_len: socklen_t, error_code: u64, reason: *const u8,
+    reason_len: size_t,
+) -> c_int {
+    let local = std_addr_from_c(local, local_len);
+    let peer = std_addr_from_c(peer, peer_len);
+
+    if reason_len > <ssize_t>::MAX as usize {
+        panic!("The provided buffer is too large");
+    }
+
+    let reason = unsafe { slice::from_raw_parts(reason, reason_len) };
+
+    match conn.abandon_path(local, peer, error_code, reason) {
+        Ok(_) => 0,
+
+        Err(e) => e.to_c() as c_int,
+    }
+}
+
 #[no_mangle]
 pub extern "C" fn quiche_conn_path_event_next(
     conn: &mut Connection,
@@ -1737,6 +2010,8 @@ pub extern "C" fn quiche_path_event_type(ev: &PathEvent) -> u32 {
         PathEvent::ReusedSourceConnectionId { .. } => 4,
 
         PathEvent::PeerMigrated { .. } => 5,
+
+        PathEvent::ValidationStarted { .. } => 6,
     }
 }
 
@@ -1756,6 +2031,22 @@ pub extern "C" fn quiche_path_event_new(
     }
 }
 
+#[no_mangle]
+pub extern "C" fn quiche_path_event_validation_started(
+    ev: &PathEvent, local_addr: &mut sockaddr_storage,
+    local_addr_len: &mut socklen_t, peer_addr: &mut sockaddr_storage,
+    peer_addr_len: &mut socklen_t,
+) {
+    match ev {
+        PathEvent::ValidationStarted(local, peer) => {
+            *local_addr_len = std_addr_to_c(local, local_addr);
+            *peer_addr_len = std_addr_to_c(peer, peer_addr)
+        },
+
+        _ => unreachable!(),
+    }
+}
+
 #[no_mangle]
 pub extern "C" fn quiche_path_event_validated(
     ev: &PathEvent, local_addr: &mut sockaddr_storage,
@@ -2160,6 +2451,39 @@ mod tests {
         );
     }
 
+    #[test]
+    #[cfg(feature = "qlog")]
+    fn qlog_cb_records() {
+        use std::io::Write;
+
+        extern "C" fn cb(data: *const u8, data_len: size_t, argp: *mut c_void) {
+            let records = unsafe { &mut *(argp as *mut Vec<Vec<u8>>) };
+            let data = unsafe { slice::from_raw_parts(data, data_len) };
+
+            records.push(data.to_vec());
+        }
+
+        let mut records: Vec<Vec<u8>> = Vec::new();
+
+        let mut writer = QlogCallbackWriter {
+            cb,
+            argp: atomic::AtomicPtr::new(&mut records as *mut _ as *mut c_void),
+            buf: Vec::new(),
+        };
+
+        writer.write_all(b"\x1e{\"a\":").unwrap();
+        writer.write_all(b"1}").unwrap();
+        assert!(records.is_empty());
+
+        writer.write_all(b"\n\x1e{\"b\":2}\n\x1e{").unwrap();
+        writer.flush().unwrap();
+
+        assert_eq!(records, vec![
+            b"\x1e{\"a\":1}\n\x1e{\"b\":2}\n".to_vec(),
+            b"\x1e{".to_vec(),
+        ]);
+    }
+
     #[cfg(not(windows))]
     extern "C" {
         fn inet_ntop(
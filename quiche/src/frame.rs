@@ -47,11 +47,21 @@ pub const MAX_DGRAM_OVERHEAD: usize = 2;
 pub const MAX_STREAM_OVERHEAD: usize = 12;
 pub const MAX_STREAM_SIZE: u64 = 1 << 62;
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+// Frame types of the multipath extension.
+const PATH_ACK: u64 = 0x1522_8c00;
+const PATH_ACK_ECN: u64 = 0x1522_8c01;
+const PATH_ABANDON: u64 = 0x1522_8c05;
+const PATH_STATUS: u64 = 0x1522_8c06;
+
+// Frame types of the ACK frequency extension.
+const ACK_FREQUENCY: u64 = 0xaf;
+const IMMEDIATE_ACK: u64 = 0x1f;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
 pub struct EcnCounts {
-    ect0_count: u64,
-    ect1_count: u64,
-    ecn_ce_count: u64,
+    pub ect0_count: u64,
+    pub ect1_count: u64,
+    pub ecn_ce_count: u64,
 }
 
 #[derive(Clone, PartialEq, Eq)]
@@ -181,9 +191,44 @@ pub enum Frame {
         data: Vec<u8>,
     },
 
+    PathAck {
+        path_id: u64,
+        ack_delay: u64,
+        ranges: ranges::RangeSet,
+        ecn_counts: Option<EcnCounts>,
+    },
+
+    PathAbandon {
+        path_id: u64,
+        error_code: u64,
+        reason: Vec<u8>,
+    },
+
+    PathStatus {
+        path_id: u64,
+        seq_num: u64,
+        status: u64,
+    },
+
+    AckFrequency {
+        seq_num: u64,
+        ack_eliciting_threshold: u64,
+        request_max_ack_delay: u64,
+        reordering_threshold: u64,
+    },
+
+    ImmediateAck,
+
     DatagramHeader {
         length: usize,
     },
+
+    /// Bytes written to the packet as they are. They can encode any number of
+    /// frames, including malformed ones, and are never parsed.
+    #[cfg(feature = "internal")]
+    Raw {
+        data: Vec<u8>,
+    },
 }
 
 impl Frame {
@@ -331,6 +376,40 @@ impl Frame {
 
             0x30 | 0x31 => parse_datagram_frame(frame_type, b)?,
 
+            PATH_ACK | PATH_ACK_ECN => {
+                let path_id = b.get_varint()?;
+                let (ack_delay, ranges, ecn_counts) =
+                    parse_ack_body(frame_type == PATH_ACK_ECN, b)?;
+
+                Frame::PathAck {
+                    path_id,
+                    ack_delay,
+                    ranges,
+                    ecn_counts,
+                }
+            },
+
+            PATH_ABANDON => Frame::PathAbandon {
+                path_id: b.get_varint()?,
+                error_code: b.get_varint()?,
+                reason: b.get_bytes_with_varint_length()?.to_vec(),
+            },
+
+            PATH_STATUS => Frame::PathStatus {
+                path_id: b.get_varint()?,
+                seq_num: b.get_varint()?,
+                status: b.get_varint()?,
+            },
+
+            ACK_FREQUENCY => Frame::AckFrequency {
+                seq_num: b.get_varint()?,
+                ack_eliciting_threshold: b.get_varint()?,
+                request_max_ack_delay: b.get_varint()?,
+                reordering_threshold: b.get_varint()?,
+            },
+
+            IMMEDIATE_ACK => Frame::ImmediateAck,
+
             _ => return Err(Error::InvalidFrame),
         };
 
@@ -348,6 +427,11 @@ impl Frame {
             (packet::Type::ZeroRTT, Frame::RetireConnectionId { .. }) => false,
             (packet::Type::ZeroRTT, Frame::ConnectionClose { .. }) => false,
 
+            // Multipath frames are only meaningful once 1-RTT keys exist.
+            (packet::Type::ZeroRTT, Frame::PathAck { .. }) => false,
+            (packet::Type::ZeroRTT, Frame::PathAbandon { .. }) => false,
+            (packet::Type::ZeroRTT, Frame::PathStatus { .. }) => false,
+
             // ACK, CRYPTO and CONNECTION_CLOSE can be sent on all other packet
             // types.
             (_, Frame::ACK { .. }) => true,
@@ -398,33 +482,7 @@ impl Frame {
                     b.put_varint(0x03)?;
                 }
 
-                let mut it = ranges.iter().rev();
-
-                let first = it.next().unwrap();
-                let ack_block = (first.end - 1) - first.start;
-
-                b.put_varint(first.end - 1)?;
-                b.put_varint(*ack_delay)?;
-                b.put_varint(it.len() as u64)?;
-                b.put_varint(ack_block)?;
-
-                let mut smallest_ack = first.start;
-
-                for block in it {
-                    let gap = smallest_ack - block.end - 1;
-                    let ack_block = (block.end - 1) - block.start;
-
-                    b.put_varint(gap)?;
-                    b.put_varint(ack_block)?;
-
-                    smallest_ack = block.start;
-                }
-
-                if let Some(ecn) = ecn_counts {
-                    b.put_varint(ecn.ect0_count)?;
-                    b.put_varint(ecn.ect1_count)?;
-                    b.put_varint(ecn.ecn_ce_count)?;
-                }
+                encode_ack_body(*ack_delay, ranges, ecn_counts.as_ref(), b)?;
             },
 
             Frame::ResetStream {
@@ -593,6 +651,71 @@ impl Frame {
             },
 
             Frame::DatagramHeader { .. } => (),
+
+            Frame::PathAck {
+                path_id,
+                ack_delay,
+                ranges,
+                ecn_counts,
+            } => {
+                if ecn_counts.is_none() {
+                    b.put_varint(PATH_ACK)?;
+                } else {
+                    b.put_varint(PATH_ACK_ECN)?;
+                }
+
+                b.put_varint(*path_id)?;
+
+                encode_ack_body(*ack_delay, ranges, ecn_counts.as_ref(), b)?;
+            },
+
+            Frame::PathAbandon {
+                path_id,
+                error_code,
+                reason,
+            } => {
+                b.put_varint(PATH_ABANDON)?;
+
+                b.put_varint(*path_id)?;
+                b.put_varint(*error_code)?;
+                b.put_varint(reason.len() as u64)?;
+                b.put_bytes(reason.as_ref())?;
+            },
+
+            Frame::PathStatus {
+                path_id,
+                seq_num,
+                status,
+            } => {
+                b.put_varint(PATH_STATUS)?;
+
+                b.put_varint(*path_id)?;
+                b.put_varint(*seq_num)?;
+                b.put_varint(*status)?;
+            },
+
+            Frame::AckFrequency {
+                seq_num,
+                ack_eliciting_threshold,
+                request_max_ack_delay,
+                reordering_threshold,
+            } => {
+                b.put_varint(ACK_FREQUENCY)?;
+
+                b.put_varint(*seq_num)?;
+                b.put_varint(*ack_eliciting_threshold)?;
+                b.put_varint(*request_max_ack_delay)?;
+                b.put_varint(*reordering_threshold)?;
+            },
+
+            Frame::ImmediateAck => {
+                b.put_varint(IMMEDIATE_ACK)?;
+            },
+
+            #[cfg(feature = "internal")]
+            Frame::Raw { data } => {
+                b.put_bytes(data.as_ref())?;
+            },
         }
 
         Ok(before - b.cap())
@@ -609,36 +732,8 @@ impl Frame {
                 ranges,
                 ecn_counts,
             } => {
-                let mut it = ranges.iter().rev();
-
-                let first = it.next().unwrap();
-                let ack_block = (first.end - 1) - first.start;
-
-                let mut len = 1 + // frame type
-                    octets::varint_len(first.end - 1) + // largest_ack
-                    octets::varint_len(*ack_delay) + // ack_delay
-                    octets::varint_len(it.len() as u64) + // block_count
-                    octets::varint_len(ack_block); // first_block
-
-                let mut smallest_ack = first.start;
-
-                for block in it {
-                    let gap = smallest_ack - block.end - 1;
-                    let ack_block = (block.end - 1) - block.start;
-
-                    len += octets::varint_len(gap) + // gap
-                           octets::varint_len(ack_block); // ack_block
-
-                    smallest_ack = block.start;
-                }
-
-                if let Some(ecn) = ecn_counts {
-                    len += octets::varint_len(ecn.ect0_count) +
-                        octets::varint_len(ecn.ect1_count) +
-                        octets::varint_len(ecn.ecn_ce_count);
-                }
-
-                len
+                1 + // frame type
+                ack_body_wire_len(*ack_delay, ranges, ecn_counts.as_ref())
             },
 
             Frame::ResetStream {
@@ -808,6 +903,58 @@ impl Frame {
                 2 + // length, always encode as 2-byte varint
                 *length // data
             },
+
+            Frame::PathAck {
+                path_id,
+                ack_delay,
+                ranges,
+                ecn_counts,
+            } => {
+                octets::varint_len(PATH_ACK) + // frame type
+                octets::varint_len(*path_id) + // path_id
+                ack_body_wire_len(*ack_delay, ranges, ecn_counts.as_ref())
+            },
+
+            Frame::PathAbandon {
+                path_id,
+                error_code,
+                reason,
+            } => {
+                octets::varint_len(PATH_ABANDON) + // frame type
+                octets::varint_len(*path_id) + // path_id
+                octets::varint_len(*error_code) + // error_code
+                octets::varint_len(reason.len() as u64) + // reason_len
+                reason.len() // reason
+            },
+
+            Frame::PathStatus {
+                path_id,
+                seq_num,
+                status,
+            } => {
+                octets::varint_len(PATH_STATUS) + // frame type
+                octets::varint_len(*path_id) + // path_id
+                octets::varint_len(*seq_num) + // seq_num
+                octets::varint_len(*status) // status
+            },
+
+            Frame::AckFrequency {
+                seq_num,
+                ack_eliciting_threshold,
+                request_max_ack_delay,
+                reordering_threshold,
+            } => {
+                octets::varint_len(ACK_FREQUENCY) + // frame type
+                octets::varint_len(*seq_num) + // seq_num
+                octets::varint_len(*ack_eliciting_threshold) + // threshold
+                octets::varint_len(*request_max_ack_delay) + // max_ack_delay
+                octets::varint_len(*reordering_threshold) // reordering
+            },
+
+            Frame::ImmediateAck => octets::varint_len(IMMEDIATE_ACK),
+
+            #[cfg(feature = "internal")]
+            Frame::Raw { data } => data.len(),
         }
     }
 
@@ -817,6 +964,7 @@ impl Frame {
             self,
             Frame::Padding { .. } |
                 Frame::ACK { .. } |
+                Frame::PathAck { .. } |
                 Frame::ApplicationClose { .. } |
                 Frame::ConnectionClose { .. }
         )
@@ -1033,6 +1181,53 @@ impl Frame {
                 length: *length as u64,
                 raw: None,
             },
+
+            Frame::PathAck { ecn_counts, .. } => QuicFrame::Unknown {
+                raw_frame_type: if ecn_counts.is_none() {
+                    PATH_ACK
+                } else {
+                    PATH_ACK_ECN
+                },
+                frame_type_value: None,
+                raw: None,
+            },
+
+            Frame::PathAbandon { .. } => QuicFrame::Unknown {
+                raw_frame_type: PATH_ABANDON,
+                frame_type_value: None,
+                raw: None,
+            },
+
+            Frame::PathStatus { .. } => QuicFrame::Unknown {
+                raw_frame_type: PATH_STATUS,
+                frame_type_value: None,
+                raw: None,
+            },
+
+            Frame::AckFrequency { .. } => QuicFrame::Unknown {
+                raw_frame_type: ACK_FREQUENCY,
+                frame_type_value: None,
+                raw: None,
+            },
+
+            Frame::ImmediateAck => QuicFrame::Unknown {
+                raw_frame_type: IMMEDIATE_ACK,
+                frame_type_value: None,
+                raw: None,
+            },
+
+            #[cfg(feature = "internal")]
+            Frame::Raw { data } => QuicFrame::Unknown {
+                raw_frame_type: octets::Octets::with_slice(data)
+                    .get_varint()
+                    .unwrap_or(0),
+                frame_type_value: None,
+                raw: Some(qlog::events::RawInfo {
+                    data: qlog::HexSlice::maybe_string(Some(data)),
+                    length: Some(data.len() as u64),
+                    payload_length: None,
+                }),
+            },
         }
     }
 }
@@ -1200,6 +1395,61 @@ impl std::fmt::Debug for Frame {
             Frame::DatagramHeader { length } => {
                 write!(f, "DATAGRAM len={length}")?;
             },
+
+            Frame::PathAck {
+                path_id,
+                ack_delay,
+                ranges,
+                ecn_counts,
+            } => {
+                write!(
+                    f,
+                    "PATH_ACK path_id={path_id} delay={ack_delay} blocks={ranges:?} ecn_counts={ecn_counts:?}"
+                )?;
+            },
+
+            Frame::PathAbandon {
+                path_id,
+                error_code,
+                reason,
+            } => {
+                write!(
+                    f,
+                    "PATH_ABANDON path_id={path_id} err={error_code:x} reason={reason:x?}"
+                )?;
+            },
+
+            Frame::PathStatus {
+                path_id,
+                seq_num,
+                status,
+            } => {
+                write!(
+                    f,
+                    "PATH_STATUS path_id={path_id} seq_num={seq_num} status={status}"
+                )?;
+            },
+
+            Frame::AckFrequency {
+                seq_num,
+                ack_eliciting_threshold,
+                request_max_ack_delay,
+                reordering_threshold,
+            } => {
+                write!(
+                    f,
+                    "ACK_FREQUENCY seq_num={seq_num} threshold={ack_eliciting_threshold} max_ack_delay={request_max_ack_delay} reordering={reordering_threshold}"
+                )?;
+            },
+
+            Frame::ImmediateAck => {
+                write!(f, "IMMEDIATE_ACK")?;
+            },
+
+            #[cfg(feature = "internal")]
+            Frame::Raw { data } => {
+                write!(f, "RAW len={}", data.len())?;
+            },
         }
 
         Ok(())
@@ -1209,6 +1459,20 @@ impl std::fmt::Debug for Frame {
 fn parse_ack_frame(ty: u64, b: &mut octets::Octets) -> Result<Frame> {
     let first = ty as u8;
 
+    let (ack_delay, ranges, ecn_counts) = parse_ack_body(first & 0x01 != 0, b)?;
+
+    Ok(Frame::ACK {
+        ack_delay,
+        ranges,
+        ecn_counts,
+    })
+}
+
+/// Parses the fields shared by ACK and PATH_ACK frames, from the largest
+/// acknowledged packet number onwards.
+fn parse_ack_body(
+    ecn: bool, b: &mut octets::Octets,
+) -> Result<(u64, ranges::RangeSet, Option<EcnCounts>)> {
     let largest_ack = b.get_varint()?;
     let ack_delay = b.get_varint()?;
     let block_count = b.get_varint()?;
@@ -1243,7 +1507,7 @@ fn parse_ack_frame(ty: u64, b: &mut octets::Octets) -> Result<Frame> {
         ranges.insert(smallest_ack..largest_ack + 1);
     }
 
-    let ecn_counts = if first & 0x01 != 0 {
+    let ecn_counts = if ecn {
         let ecn = EcnCounts {
             ect0_count: b.get_varint()?,
             ect1_count: b.get_varint()?,
@@ -1255,11 +1519,78 @@ fn parse_ack_frame(ty: u64, b: &mut octets::Octets) -> Result<Frame> {
         None
     };
 
-    Ok(Frame::ACK {
-        ack_delay,
-        ranges,
-        ecn_counts,
-    })
+    Ok((ack_delay, ranges, ecn_counts))
+}
+
+/// Encodes the fields shared by ACK and PATH_ACK frames, from the largest
+/// acknowledged packet number onwards.
+fn encode_ack_body(
+    ack_delay: u64, ranges: &ranges::RangeSet, ecn_counts: Option<&EcnCounts>,
+    b: &mut octets::OctetsMut,
+) -> Result<()> {
+    let mut it = ranges.iter().rev();
+
+    let first = it.next().unwrap();
+    let ack_block = (first.end - 1) - first.start;
+
+    b.put_varint(first.end - 1)?;
+    b.put_varint(ack_delay)?;
+    b.put_varint(it.len() as u64)?;
+    b.put_varint(ack_block)?;
+
+    let mut smallest_ack = first.start;
+
+    for block in it {
+        let gap = smallest_ack - block.end - 1;
+        let ack_block = (block.end - 1) - block.start;
+
+        b.put_varint(gap)?;
+        b.put_varint(ack_block)?;
+
+        smallest_ack = block.start;
+    }
+
+    if let Some(ecn) = ecn_counts {
+        b.put_varint(ecn.ect0_count)?;
+        b.put_varint(ecn.ect1_count)?;
+        b.put_varint(ecn.ecn_ce_count)?;
+    }
+
+    Ok(())
+}
+
+fn ack_body_wire_len(
+    ack_delay: u64, ranges: &ranges::RangeSet, ecn_counts: Option<&EcnCounts>,
+) -> usize {
+    let mut it = ranges.iter().rev();
+
+    let first = it.next().unwrap();
+    let ack_block = (first.end - 1) - first.start;
+
+    let mut len = octets::varint_len(first.end - 1) + // largest_ack
+        octets::varint_len(ack_delay) + // ack_delay
+        octets::varint_len(it.len() as u64) + // block_count
+        octets::varint_len(ack_block); // first_block
+
+    let mut smallest_ack = first.start;
+
+    for block in it {
+        let gap = smallest_ack - block.end - 1;
+        let ack_block = (block.end - 1) - block.start;
+
+        len += octets::varint_len(gap) + // gap
+               octets::varint_len(ack_block); // ack_block
+
+        smallest_ack = block.start;
+    }
+
+    if let Some(ecn) = ecn_counts {
+        len += octets::varint_len(ecn.ect0_count) +
+            octets::varint_len(ecn.ect1_count) +
+            octets::varint_len(ecn.ecn_ce_count);
+    }
+
+    len
 }
 
 pub fn encode_crypto_header(
@@ -2081,6 +2412,167 @@ mod tests {
         assert!(Frame::from_bytes(&mut b, packet::Type::Handshake).is_err());
     }
 
+    #[test]
+    fn path_ack() {
+        let mut d = [42; 128];
+
+        let mut ranges = ranges::RangeSet::default();
+        ranges.insert(4..7);
+        ranges.insert(9..12);
+        ranges.insert(15..19);
+        ranges.insert(3000..5000);
+
+        let frame = Frame::PathAck {
+            path_id: 3,
+            ack_delay: 874_656_534,
+            ranges,
+            ecn_counts: None,
+        };
+
+        let wire_len = {
+            let mut b = octets::OctetsMut::with_slice(&mut d);
+            frame.to_bytes(&mut b).unwrap()
+        };
+
+        assert_eq!(wire_len, 21);
+        assert_eq!(frame.wire_len(), 21);
+        assert!(!frame.ack_eliciting());
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert_eq!(Frame::from_bytes(&mut b, packet::Type::Short), Ok(frame));
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert!(Frame::from_bytes(&mut b, packet::Type::Initial).is_err());
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert!(Frame::from_bytes(&mut b, packet::Type::ZeroRTT).is_err());
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert!(Frame::from_bytes(&mut b, packet::Type::Handshake).is_err());
+    }
+
+    #[test]
+    fn path_abandon() {
+        let mut d = [42; 128];
+
+        let frame = Frame::PathAbandon {
+            path_id: 1,
+            error_code: 7,
+            reason: b"bye".to_vec(),
+        };
+
+        let wire_len = {
+            let mut b = octets::OctetsMut::with_slice(&mut d);
+            frame.to_bytes(&mut b).unwrap()
+        };
+
+        assert_eq!(wire_len, 10);
+        assert_eq!(frame.wire_len(), 10);
+        assert!(frame.ack_eliciting());
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert_eq!(Frame::from_bytes(&mut b, packet::Type::Short), Ok(frame));
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert!(Frame::from_bytes(&mut b, packet::Type::Initial).is_err());
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert!(Frame::from_bytes(&mut b, packet::Type::ZeroRTT).is_err());
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert!(Frame::from_bytes(&mut b, packet::Type::Handshake).is_err());
+    }
+
+    #[test]
+    fn path_status() {
+        let mut d = [42; 128];
+
+        let frame = Frame::PathStatus {
+            path_id: 2,
+            seq_num: 1,
+            status: 2,
+        };
+
+        let wire_len = {
+            let mut b = octets::OctetsMut::with_slice(&mut d);
+            frame.to_bytes(&mut b).unwrap()
+        };
+
+        assert_eq!(wire_len, 7);
+        assert_eq!(frame.wire_len(), 7);
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert_eq!(Frame::from_bytes(&mut b, packet::Type::Short), Ok(frame));
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert!(Frame::from_bytes(&mut b, packet::Type::Initial).is_err());
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert!(Frame::from_bytes(&mut b, packet::Type::ZeroRTT).is_err());
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert!(Frame::from_bytes(&mut b, packet::Type::Handshake).is_err());
+    }
+
+    #[test]
+    fn ack_frequency() {
+        let mut d = [42; 128];
+
+        let frame = Frame::AckFrequency {
+            seq_num: 1,
+            ack_eliciting_threshold: 10,
+            request_max_ack_delay: 25_000,
+            reordering_threshold: 1,
+        };
+
+        let wire_len = {
+            let mut b = octets::OctetsMut::with_slice(&mut d);
+            frame.to_bytes(&mut b).unwrap()
+        };
+
+        assert_eq!(wire_len, 9);
+        assert_eq!(frame.wire_len(), 9);
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert_eq!(Frame::from_bytes(&mut b, packet::Type::Short), Ok(frame));
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert!(Frame::from_bytes(&mut b, packet::Type::Initial).is_err());
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert!(Frame::from_bytes(&mut b, packet::Type::ZeroRTT).is_ok());
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert!(Frame::from_bytes(&mut b, packet::Type::Handshake).is_err());
+    }
+
+    #[test]
+    fn immediate_ack() {
+        let mut d = [42; 128];
+
+        let frame = Frame::ImmediateAck;
+
+        let wire_len = {
+            let mut b = octets::OctetsMut::with_slice(&mut d);
+            frame.to_bytes(&mut b).unwrap()
+        };
+
+        assert_eq!(wire_len, 1);
+        assert_eq!(frame.wire_len(), 1);
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert_eq!(Frame::from_bytes(&mut b, packet::Type::Short), Ok(frame));
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert!(Frame::from_bytes(&mut b, packet::Type::Initial).is_err());
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert!(Frame::from_bytes(&mut b, packet::Type::ZeroRTT).is_ok());
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert!(Frame::from_bytes(&mut b, packet::Type::Handshake).is_err());
+    }
+
     #[test]
     fn datagram() {
         let mut d = [42; 128];
@@ -77,6 +77,13 @@ pub extern "C" fn quiche_h3_config_enable_extended_connect(
     config.enable_extended_connect(enabled);
 }
 
+#[no_mangle]
+pub extern "C" fn quiche_h3_config_enable_metadata(
+    config: &mut h3::Config, enabled: bool,
+) {
+    config.enable_metadata(enabled);
+}
+
 #[no_mangle]
 pub extern "C" fn quiche_h3_config_free(config: *mut h3::Config) {
     drop(unsafe { Box::from_raw(config) });
@@ -148,6 +155,10 @@ pub extern "C" fn quiche_h3_event_type(ev: &h3::Event) -> u32 {
         h3::Event::Reset { .. } => 4,
 
         h3::Event::PriorityUpdate => 5,
+
+        h3::Event::Cancelled { .. } => 6,
+
+        h3::Event::Metadata { .. } => 7,
     }
 }
 
@@ -166,7 +177,7 @@ pub extern "C" fn quiche_h3_event_for_each_header(
     argp: *mut c_void,
 ) -> c_int {
     match ev {
-        h3::Event::Headers { list, .. } =>
+        h3::Event::Headers { list, .. } | h3::Event::Metadata { list } =>
             for h in list {
                 let rc = cb(
                     h.name().as_ptr(),
@@ -198,6 +209,19 @@ pub extern "C" fn quiche_h3_event_headers_has_more_frames(
     }
 }
 
+#[no_mangle]
+pub extern "C" fn quiche_h3_event_cancellation(ev: &h3::Event) -> u32 {
+    match ev {
+        h3::Event::Cancelled(h3::Cancellation::Rejected) => 0,
+
+        h3::Event::Cancelled(h3::Cancellation::BeforeResponse) => 1,
+
+        h3::Event::Cancelled(h3::Cancellation::MidResponse) => 2,
+
+        _ => unreachable!(),
+    }
+}
+
 #[no_mangle]
 pub extern "C" fn quiche_h3_extended_connect_enabled_by_peer(
     conn: &h3::Connection,
@@ -205,6 +229,13 @@ pub extern "C" fn quiche_h3_extended_connect_enabled_by_peer(
     conn.extended_connect_enabled_by_peer()
 }
 
+#[no_mangle]
+pub extern "C" fn quiche_h3_metadata_enabled_by_peer(
+    conn: &h3::Connection,
+) -> bool {
+    conn.metadata_enabled_by_peer()
+}
+
 #[no_mangle]
 pub extern "C" fn quiche_h3_event_free(ev: *mut h3::Event) {
     drop(unsafe { Box::from_raw(ev) });
@@ -288,6 +319,20 @@ pub extern "C" fn quiche_h3_send_additional_headers(
     }
 }
 
+#[no_mangle]
+pub extern "C" fn quiche_h3_send_metadata(
+    conn: &mut h3::Connection, quic_conn: &mut Connection, stream_id: u64,
+    fields: *const Header, fields_len: size_t,
+) -> c_int {
+    let fields = headers_from_ptr(fields, fields_len);
+
+    match conn.send_metadata(quic_conn, stream_id, &fields) {
+        Ok(_) => 0,
+
+        Err(e) => e.to_c() as c_int,
+    }
+}
+
 #[no_mangle]
 pub extern "C" fn quiche_h3_send_body(
     conn: &mut h3::Connection, quic_conn: &mut Connection, stream_id: u64,
@@ -335,6 +380,17 @@ pub extern "C" fn quiche_h3_send_goaway(
     }
 }
 
+#[no_mangle]
+pub extern "C" fn quiche_h3_cancel_request(
+    conn: &mut h3::Connection, quic_conn: &mut Connection, stream_id: u64,
+) -> c_int {
+    match conn.cancel_request(quic_conn, stream_id) {
+        Ok(()) => 0,
+
+        Err(e) => e.to_c() as c_int,
+    }
+}
+
 #[no_mangle]
 #[cfg(feature = "sfv")]
 pub extern "C" fn quiche_h3_parse_extensible_priority(
@@ -353,6 +409,39 @@ pub extern "C" fn quiche_h3_parse_extensible_priority(
     }
 }
 
+#[no_mangle]
+pub extern "C" fn quiche_h3_priority_to_field_value(
+    priority: &Priority, out: *mut u8, out_len: size_t,
+) -> ssize_t {
+    if out_len > <ssize_t>::MAX as usize {
+        panic!("The provided buffer is too large");
+    }
+
+    let out = unsafe { slice::from_raw_parts_mut(out, out_len) };
+
+    let field_value = priority.to_field_value();
+
+    if field_value.len() > out.len() {
+        return h3::Error::BufferTooShort.to_c();
+    }
+
+    out[..field_value.len()].copy_from_slice(field_value.as_bytes());
+
+    field_value.len() as ssize_t
+}
+
+#[no_mangle]
+pub extern "C" fn quiche_h3_stream_priority(
+    conn: &mut h3::Connection, quic_conn: &mut Connection, stream_id: u64,
+    priority: &Priority,
+) -> c_int {
+    match conn.stream_priority(quic_conn, stream_id, priority) {
+        Ok(()) => 0,
+
+        Err(e) => e.to_c() as c_int,
+    }
+}
+
 #[no_mangle]
 pub extern "C" fn quiche_h3_send_priority_update_for_request(
     conn: &mut h3::Connection, quic_conn: &mut Connection, stream_id: u64,
@@ -39,6 +39,7 @@ pub const MAX_PUSH_FRAME_TYPE_ID: u64 = 0xD;
 pub const PRIORITY_UPDATE_FRAME_REQUEST_TYPE_ID: u64 = 0xF0700;
 pub const PRIORITY_UPDATE_FRAME_PUSH_TYPE_ID: u64 = 0xF0701;
 pub const WEBTRANSPORT_FRAME_TYPE_ID: u64 = 0x41;
+pub const METADATA_FRAME_TYPE_ID: u64 = 0x4d;
 
 pub const SETTINGS_QPACK_MAX_TABLE_CAPACITY: u64 = 0x1;
 pub const SETTINGS_MAX_FIELD_SECTION_SIZE: u64 = 0x6;
@@ -46,6 +47,7 @@ pub const SETTINGS_QPACK_BLOCKED_STREAMS: u64 = 0x7;
 pub const SETTINGS_ENABLE_CONNECT_PROTOCOL: u64 = 0x8;
 pub const SETTINGS_H3_DATAGRAM_00: u64 = 0x276;
 pub const SETTINGS_H3_DATAGRAM: u64 = 0x33;
+pub const SETTINGS_ENABLE_METADATA: u64 = 0x4d44;
 
 // Permit between 16 maximally-encoded and 128 minimally-encoded SETTINGS.
 const MAX_SETTINGS_PAYLOAD_SIZE: usize = 256;
@@ -98,6 +100,10 @@ pub enum Frame {
         priority_field_value: Vec<u8>,
     },
 
+    Metadata {
+        header_block: Vec<u8>,
+    },
+
     Unknown {
         raw_type: u64,
         payload: Vec<u8>,
@@ -142,6 +148,10 @@ impl Frame {
             PRIORITY_UPDATE_FRAME_PUSH_TYPE_ID =>
                 parse_priority_update(frame_type, payload_length, &mut b)?,
 
+            METADATA_FRAME_TYPE_ID => Frame::Metadata {
+                header_block: b.get_bytes(payload_length as usize)?.to_vec(),
+            },
+
             _ => Frame::Unknown {
                 raw_type: frame_type,
                 payload: b.get_bytes(payload_length as usize)?.to_vec(),
@@ -324,6 +334,13 @@ impl Frame {
                 b.put_bytes(priority_field_value)?;
             },
 
+            Frame::Metadata { header_block } => {
+                b.put_varint(METADATA_FRAME_TYPE_ID)?;
+                b.put_varint(header_block.len() as u64)?;
+
+                b.put_bytes(header_block.as_ref())?;
+            },
+
             Frame::Unknown { raw_type, payload } => {
                 b.put_varint(*raw_type)?;
                 b.put_varint(payload.len() as u64)?;
@@ -447,7 +464,7 @@ impl Frame {
                 priority_field_value,
             } => Http3Frame::PriorityUpdate {
                 target_stream_type:
-                    qlog::events::h3::H3PriorityTargetStreamType::Request,
+                    qlog::events::h3::H3PriorityTargetStreamType::Push,
                 prioritized_element_id: *prioritized_element_id,
                 priority_field_value: String::from_utf8_lossy(
                     priority_field_value,
@@ -455,6 +472,16 @@ impl Frame {
                 .into_owned(),
             },
 
+            // Qlog doesn't define METADATA frames, so log them as unknown.
+            Frame::Metadata { header_block } => Http3Frame::Unknown {
+                frame_type_value: METADATA_FRAME_TYPE_ID,
+                raw: Some(RawInfo {
+                    data: None,
+                    payload_length: Some(header_block.len() as u64),
+                    length: None,
+                }),
+            },
+
             Frame::Unknown { raw_type, payload } => Http3Frame::Unknown {
                 frame_type_value: *raw_type,
                 raw: Some(RawInfo {
@@ -537,6 +564,10 @@ impl std::fmt::Debug for Frame {
                 )?;
             },
 
+            Frame::Metadata { header_block } => {
+                write!(f, "METADATA len={}", header_block.len())?;
+            },
+
             Frame::Unknown { raw_type, .. } => {
                 write!(f, "UNKNOWN raw_type={raw_type}",)?;
             },
@@ -1323,6 +1354,34 @@ mod tests {
         );
     }
 
+    #[test]
+    fn metadata() {
+        let mut d = [42; 128];
+
+        let header_block = vec![0xd1, 0xd7, 0xc1];
+        let frame_payload_len = header_block.len();
+        let frame_header_len = 3;
+
+        let frame = Frame::Metadata { header_block };
+
+        let wire_len = {
+            let mut b = octets::OctetsMut::with_slice(&mut d);
+            frame.to_bytes(&mut b).unwrap()
+        };
+
+        assert_eq!(wire_len, frame_header_len + frame_payload_len);
+
+        assert_eq!(
+            Frame::from_bytes(
+                METADATA_FRAME_TYPE_ID,
+                frame_payload_len as u64,
+                &d[frame_header_len..]
+            )
+            .unwrap(),
+            frame
+        );
+    }
+
     #[test]
     fn unknown_type() {
         let d = [42; 12];
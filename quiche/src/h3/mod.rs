@@ -172,6 +172,10 @@
 //!             // Peer reset the stream, handle it.
 //!         },
 //!
+//!         Ok((stream_id, quiche::h3::Event::Cancelled(cancellation))) => {
+//!             // Peer cancelled the request, handle it.
+//!         },
+//!
 //!         Ok((_flow_id, quiche::h3::Event::PriorityUpdate)) => (),
 //!
 //!         Ok((goaway_id, quiche::h3::Event::GoAway)) => {
@@ -192,6 +196,11 @@
 //! # Ok::<(), quiche::h3::Error>(())
 //! ```
 //!
+//! Alternatively, the [`server`] module assembles validated requests from
+//! these events, and provides a handle per stream to send the response.
+//!
+//! [`server`]: server/index.html
+//!
 //! An HTTP/3 client uses [`poll()`] to read responses:
 //!
 //! ```no_run
@@ -233,6 +242,10 @@
 //!             // Peer reset the stream, handle it.
 //!         },
 //!
+//!         Ok((stream_id, quiche::h3::Event::Cancelled(cancellation))) => {
+//!             // Peer cancelled the request, handle it.
+//!         },
+//!
 //!         Ok((_prioritized_element_id, quiche::h3::Event::PriorityUpdate)) => (),
 //!
 //!         Ok((goaway_id, quiche::h3::Event::GoAway)) => {
@@ -290,6 +303,8 @@ use std::convert::TryFrom;
 use std::fmt;
 use std::fmt::Write;
 
+use std::time::Instant;
+
 #[cfg(feature = "qlog")]
 use qlog::events::h3::H3FrameCreated;
 #[cfg(feature = "qlog")]
@@ -572,6 +587,8 @@ pub struct Config {
     /// additional settings are settings that are not part of the H3
     /// settings explicitly handled above
     additional_settings: Option<Vec<(u64, u64)>>,
+    metadata_enabled: bool,
+    request_stats: bool,
 }
 
 impl Config {
@@ -583,6 +600,8 @@ impl Config {
             qpack_blocked_streams: None,
             connect_protocol_enabled: None,
             additional_settings: None,
+            metadata_enabled: false,
+            request_stats: false,
         })
     }
 
@@ -624,6 +643,36 @@ impl Config {
         }
     }
 
+    /// Sets or omits the `SETTINGS_ENABLE_METADATA` setting.
+    ///
+    /// When both endpoints enable it, METADATA frames can be sent on request
+    /// streams using the [`send_metadata()`] method, and received ones are
+    /// reported by the [`poll()`] method as [`Metadata`] events. Otherwise
+    /// received METADATA frames are ignored.
+    ///
+    /// The default value is `false`.
+    ///
+    /// [`send_metadata()`]: struct.Connection.html#method.send_metadata
+    /// [`poll()`]: struct.Connection.html#method.poll
+    /// [`Metadata`]: enum.Event.html#variant.Metadata
+    pub fn enable_metadata(&mut self, enabled: bool) {
+        self.metadata_enabled = enabled;
+    }
+
+    /// Enables per-request statistics.
+    ///
+    /// When enabled, timing and frame accounting is collected for every
+    /// request stream, and can be retrieved using the [`request_stats()`]
+    /// and [`take_request_stats()`] methods.
+    ///
+    /// The default value is `false`.
+    ///
+    /// [`request_stats()`]: struct.Connection.html#method.request_stats
+    /// [`take_request_stats()`]: struct.Connection.html#method.take_request_stats
+    pub fn enable_request_stats(&mut self, v: bool) {
+        self.request_stats = v;
+    }
+
     /// Sets additional HTTP/3 settings.
     ///
     /// The default value is no additional settings.
@@ -635,6 +684,7 @@ impl Config {
     /// - SETTINGS_QPACK_BLOCKED_STREAMS
     /// - SETTINGS_ENABLE_CONNECT_PROTOCOL
     /// - SETTINGS_H3_DATAGRAM
+    /// - SETTINGS_ENABLE_METADATA
     ///
     /// If such a setting is present in the `additional_settings`,
     /// the method will return the [`Error::SettingsError`] error.
@@ -653,6 +703,7 @@ impl Config {
             frame::SETTINGS_ENABLE_CONNECT_PROTOCOL,
             frame::SETTINGS_H3_DATAGRAM,
             frame::SETTINGS_H3_DATAGRAM_00,
+            frame::SETTINGS_ENABLE_METADATA,
         ]);
 
         let dedup_settings: HashSet<u64> =
@@ -786,6 +837,15 @@ pub enum Event {
     /// The associated data represents the error code sent by the peer.
     Reset(u64),
 
+    /// Request was cancelled or rejected by the peer.
+    ///
+    /// This is returned instead of [`Reset`] when the peer resets a request
+    /// stream with the `H3_REQUEST_CANCELLED` error code, or with the
+    /// `H3_REQUEST_REJECTED` error code in the case of a server.
+    ///
+    /// [`Reset`]: enum.Event.html#variant.Reset
+    Cancelled(Cancellation),
+
     /// PRIORITY_UPDATE was received.
     ///
     /// This indicates that the application can use the
@@ -803,6 +863,44 @@ pub enum Event {
 
     /// GOAWAY was received.
     GoAway,
+
+    /// METADATA was received on a request stream.
+    ///
+    /// This is only returned when both endpoints enabled the METADATA
+    /// extension, see [`Config::enable_metadata()`].
+    ///
+    /// [`Config::enable_metadata()`]: struct.Config.html#method.enable_metadata
+    Metadata {
+        /// The list of received metadata fields.
+        list: Vec<Header>,
+    },
+}
+
+/// How a request was cancelled by the peer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cancellation {
+    /// The server rejected the request without performing any application
+    /// processing, so it can safely be retried.
+    Rejected,
+
+    /// The request was cancelled before the response started, i.e. before
+    /// response headers were sent (on a server) or received (on a client).
+    BeforeResponse,
+
+    /// The request was cancelled after the response started.
+    MidResponse,
+}
+
+impl Cancellation {
+    /// Returns the wire error code associated with the cancellation.
+    pub fn error_code(&self) -> u64 {
+        match self {
+            Cancellation::Rejected => WireErrorCode::RequestRejected as u64,
+
+            Cancellation::BeforeResponse | Cancellation::MidResponse =>
+                WireErrorCode::RequestCancelled as u64,
+        }
+    }
 }
 
 /// Extensible Priorities parameters.
@@ -835,6 +933,47 @@ impl Priority {
             incremental,
         }
     }
+
+    /// Returns the urgency.
+    pub const fn urgency(&self) -> u8 {
+        self.urgency
+    }
+
+    /// Returns whether the response is incremental.
+    pub const fn incremental(&self) -> bool {
+        self.incremental
+    }
+
+    /// Serializes the priority as an Extensible Priority field value, e.g. to
+    /// use as the value of a Priority header field.
+    ///
+    /// Urgency values outside of the valid range (0 through 7) are clamped to
+    /// 7.
+    ///
+    /// ```
+    /// let priority = quiche::h3::Priority::new(1, true);
+    /// assert_eq!(priority.to_field_value(), "u=1,i");
+    /// ```
+    pub fn to_field_value(&self) -> String {
+        let urgency = self
+            .urgency
+            .clamp(PRIORITY_URGENCY_LOWER_BOUND, PRIORITY_URGENCY_UPPER_BOUND);
+
+        let mut field_value = format!("u={urgency}");
+
+        if self.incremental {
+            field_value.push_str(",i");
+        }
+
+        field_value
+    }
+
+    /// Returns the urgency shifted into the QUIC stream priority space.
+    fn quic_urgency(&self) -> u8 {
+        self.urgency
+            .clamp(PRIORITY_URGENCY_LOWER_BOUND, PRIORITY_URGENCY_UPPER_BOUND) +
+            PRIORITY_URGENCY_OFFSET
+    }
 }
 
 #[cfg(feature = "sfv")]
@@ -913,6 +1052,16 @@ struct ConnectionSettings {
     pub raw: Option<Vec<(u64, u64)>>,
 }
 
+impl ConnectionSettings {
+    /// Returns whether the settings enable the METADATA extension.
+    fn metadata_enabled(&self) -> bool {
+        self.additional_settings
+            .iter()
+            .flatten()
+            .any(|&(id, v)| id == frame::SETTINGS_ENABLE_METADATA && v == 1)
+    }
+}
+
 #[derive(Default)]
 struct QpackStreams {
     pub encoder_stream_id: Option<u64>,
@@ -934,6 +1083,84 @@ pub struct Stats {
     pub qpack_decoder_stream_recv_bytes: u64,
 }
 
+/// Statistics about a single request.
+///
+/// Fields named `*_sent` refer to frames sent by the local endpoint, i.e.
+/// the request for a client or the response for a server, while fields
+/// named `*_received` refer to frames sent by the peer.
+///
+/// Request statistics are only collected when enabled with
+/// [`Config::enable_request_stats()`], and can be retrieved using the
+/// [`request_stats()`] and [`take_request_stats()`] methods.
+///
+/// [`Config::enable_request_stats()`]: struct.Config.html#method.enable_request_stats
+/// [`request_stats()`]: struct.Connection.html#method.request_stats
+/// [`take_request_stats()`]: struct.Connection.html#method.take_request_stats
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RequestStats {
+    /// The time the first HEADERS frame was sent.
+    pub headers_sent_time: Option<Instant>,
+    /// The time the first HEADERS frame was received.
+    pub headers_received_time: Option<Instant>,
+    /// The time the first DATA frame was sent.
+    pub data_sent_time: Option<Instant>,
+    /// The time the first DATA frame was received.
+    pub data_received_time: Option<Instant>,
+    /// The time the stream's fin was sent.
+    pub fin_sent_time: Option<Instant>,
+    /// The time the stream's fin was received.
+    pub fin_received_time: Option<Instant>,
+    /// The number of HEADERS frames sent, including trailers.
+    pub headers_frames_sent: u64,
+    /// The number of HEADERS frames received, including trailers.
+    pub headers_frames_received: u64,
+    /// The size of the sent header fields before QPACK compression.
+    pub header_bytes_sent: u64,
+    /// The size of the received header fields after QPACK decompression.
+    pub header_bytes_received: u64,
+    /// The size of the sent QPACK-encoded header blocks.
+    pub header_block_bytes_sent: u64,
+    /// The size of the received QPACK-encoded header blocks.
+    pub header_block_bytes_received: u64,
+    /// The number of DATA frames sent.
+    pub data_frames_sent: u64,
+    /// The number of DATA frames received.
+    pub data_frames_received: u64,
+    /// The number of DATA frame payload bytes sent.
+    pub data_bytes_sent: u64,
+    /// The number of DATA frame payload bytes received.
+    pub data_bytes_received: u64,
+}
+
+impl RequestStats {
+    /// Returns true if the stream's fin was both sent and received.
+    pub fn is_complete(&self) -> bool {
+        self.fin_sent_time.is_some() && self.fin_received_time.is_some()
+    }
+}
+
+/// Returns the statistics of a request stream, if enabled.
+fn request_stats_for(
+    request_stats: &mut Option<crate::stream::StreamIdHashMap<RequestStats>>,
+    stream_id: u64,
+) -> Option<&mut RequestStats> {
+    // Only client-initiated bidirectional streams carry requests.
+    if stream_id % 4 != 0 {
+        return None;
+    }
+
+    request_stats
+        .as_mut()
+        .map(|stats| stats.entry(stream_id).or_default())
+}
+
+fn header_fields_len<T: NameValue>(headers: &[T]) -> u64 {
+    headers
+        .iter()
+        .map(|h| (h.name().len() + h.value().len()) as u64)
+        .sum()
+}
+
 fn close_conn_critical_stream<F: BufFactory>(
     conn: &mut super::Connection<F>,
 ) -> Result<()> {
@@ -985,6 +1212,8 @@ pub struct Connection {
 
     local_goaway_id: Option<u64>,
     peer_goaway_id: Option<u64>,
+
+    request_stats: Option<crate::stream::StreamIdHashMap<RequestStats>>,
 }
 
 impl Connection {
@@ -994,6 +1223,14 @@ impl Connection {
         let initial_uni_stream_id = if is_server { 0x3 } else { 0x2 };
         let h3_datagram = if enable_dgram { Some(1) } else { None };
 
+        let mut additional_settings = config.additional_settings.clone();
+
+        if config.metadata_enabled {
+            additional_settings
+                .get_or_insert_with(Vec::new)
+                .push((frame::SETTINGS_ENABLE_METADATA, 1));
+        }
+
         Ok(Connection {
             is_server,
 
@@ -1009,7 +1246,7 @@ impl Connection {
                 qpack_blocked_streams: config.qpack_blocked_streams,
                 connect_protocol_enabled: config.connect_protocol_enabled,
                 h3_datagram,
-                additional_settings: config.additional_settings.clone(),
+                additional_settings,
                 raw: Default::default(),
             },
 
@@ -1040,6 +1277,12 @@ impl Connection {
 
             local_goaway_id: None,
             peer_goaway_id: None,
+
+            request_stats: if config.request_stats {
+                Some(Default::default())
+            } else {
+                None
+            },
         })
     }
 
@@ -1262,13 +1505,28 @@ impl Connection {
 
         self.send_headers(conn, stream_id, headers, fin)?;
 
-        // Clamp and shift urgency into quiche-priority space
-        let urgency = priority
-            .urgency
-            .clamp(PRIORITY_URGENCY_LOWER_BOUND, PRIORITY_URGENCY_UPPER_BOUND) +
-            PRIORITY_URGENCY_OFFSET;
+        self.stream_priority(conn, stream_id, priority)
+    }
 
-        conn.stream_priority(stream_id, urgency, priority.incremental)?;
+    /// Sets the priority of a request stream.
+    ///
+    /// This changes the order in which the responses are sent, for example to
+    /// apply the priority from a PRIORITY_UPDATE frame after
+    /// [`send_response_with_priority()`] was called. The `priority` parameter
+    /// represents [Extensible Priority] parameters. If the urgency is outside
+    /// the range 0-7, it will be clamped to 7.
+    ///
+    /// [`send_response_with_priority()`]: struct.Connection.html#method.send_response_with_priority
+    /// [Extensible Priority]: https://www.rfc-editor.org/rfc/rfc9218.html#section-4.
+    pub fn stream_priority<F: BufFactory>(
+        &mut self, conn: &mut super::Connection<F>, stream_id: u64,
+        priority: &Priority,
+    ) -> Result<()> {
+        conn.stream_priority(
+            stream_id,
+            priority.quic_urgency(),
+            priority.incremental,
+        )?;
 
         Ok(())
     }
@@ -1428,6 +1686,20 @@ impl Connection {
             q.add_event_data_now(ev_data).ok();
         });
 
+        if let Some(stats) = request_stats_for(&mut self.request_stats, stream_id)
+        {
+            let now = Instant::now();
+
+            stats.headers_sent_time.get_or_insert(now);
+            stats.headers_frames_sent += 1;
+            stats.header_bytes_sent += header_fields_len(headers);
+            stats.header_block_bytes_sent += header_block.len() as u64;
+
+            if fin {
+                stats.fin_sent_time = Some(now);
+            }
+        }
+
         if let Some(s) = self.streams.get_mut(&stream_id) {
             s.initialize_local();
         }
@@ -1660,6 +1932,19 @@ impl Connection {
             let _ = conn.stream_writable(stream_id, overhead + 1);
         }
 
+        if let Some(stats) = request_stats_for(&mut self.request_stats, stream_id)
+        {
+            let now = Instant::now();
+
+            stats.data_sent_time.get_or_insert(now);
+            stats.data_frames_sent += 1;
+            stats.data_bytes_sent += written as u64;
+
+            if fin && written == len {
+                stats.fin_sent_time = Some(now);
+            }
+        }
+
         if fin && written == len && conn.stream_finished(stream_id) {
             self.streams.remove(&stream_id);
         }
@@ -1692,6 +1977,96 @@ impl Connection {
         self.peer_settings.connect_protocol_enabled == Some(1)
     }
 
+    /// Returns whether the peer enabled the METADATA extension.
+    ///
+    /// Support is signalled by the peer's SETTINGS, so this method always
+    /// returns false until they have been processed using the [`poll()`]
+    /// method.
+    ///
+    /// [`poll()`]: struct.Connection.html#method.poll
+    pub fn metadata_enabled_by_peer(&self) -> bool {
+        self.peer_settings.metadata_enabled()
+    }
+
+    /// Sends a METADATA frame with the given fields on a request stream.
+    ///
+    /// METADATA frames carry hop-by-hop information, e.g. hints exchanged
+    /// between proxies, alongside the HTTP message. They can be sent at any
+    /// time after the request was sent or received, as long as the stream is
+    /// not finished, but only if both endpoints enabled the extension (see
+    /// [`Config::enable_metadata()`]). Otherwise the [`FrameUnexpected`] error
+    /// is returned.
+    ///
+    /// The [`StreamBlocked`] error is returned when the underlying QUIC stream
+    /// doesn't have enough capacity for the operation to complete. When this
+    /// happens the application should retry the operation once the stream is
+    /// reported as writable again.
+    ///
+    /// [`Config::enable_metadata()`]: struct.Config.html#method.enable_metadata
+    /// [`FrameUnexpected`]: enum.Error.html#variant.FrameUnexpected
+    /// [`StreamBlocked`]: enum.Error.html#variant.StreamBlocked
+    pub fn send_metadata<T: NameValue, F: BufFactory>(
+        &mut self, conn: &mut super::Connection<F>, stream_id: u64, fields: &[T],
+    ) -> Result<()> {
+        if !self.local_settings.metadata_enabled() ||
+            !self.peer_settings.metadata_enabled()
+        {
+            return Err(Error::FrameUnexpected);
+        }
+
+        if stream_id % 4 != 0 || !self.streams.contains_key(&stream_id) {
+            return Err(Error::FrameUnexpected);
+        }
+
+        let mut d = [42; 10];
+        let mut b = octets::OctetsMut::with_slice(&mut d);
+
+        let header_block = self.encode_header_block(fields)?;
+
+        let overhead = octets::varint_len(frame::METADATA_FRAME_TYPE_ID) +
+            octets::varint_len(header_block.len() as u64);
+
+        // The frame needs to be sent atomically, so make sure the stream has
+        // enough capacity.
+        match conn.stream_writable(stream_id, overhead + header_block.len()) {
+            Ok(true) => (),
+
+            Ok(false) => return Err(Error::StreamBlocked),
+
+            Err(e) => return Err(e.into()),
+        };
+
+        b.put_varint(frame::METADATA_FRAME_TYPE_ID)?;
+        b.put_varint(header_block.len() as u64)?;
+        let off = b.off();
+        conn.stream_send(stream_id, &d[..off], false)?;
+
+        conn.stream_send(stream_id, &header_block, false)?;
+
+        trace!(
+            "{} tx frm METADATA stream={} len={}",
+            conn.trace_id(),
+            stream_id,
+            header_block.len(),
+        );
+
+        qlog_with_type!(QLOG_FRAME_CREATED, conn.qlog, q, {
+            let len = header_block.len() as u64;
+            let frame = frame::Frame::Metadata { header_block }.to_qlog();
+
+            let ev_data = EventData::H3FrameCreated(H3FrameCreated {
+                stream_id,
+                length: Some(len),
+                frame,
+                ..Default::default()
+            });
+
+            q.add_event_data_now(ev_data).ok();
+        });
+
+        Ok(())
+    }
+
     /// Reads request or response body data into the provided buffer.
     ///
     /// Applications should call this method whenever the [`poll()`] method
@@ -1797,15 +2172,7 @@ impl Connection {
         let control_stream_id =
             self.control_stream_id.ok_or(Error::FrameUnexpected)?;
 
-        let urgency = priority
-            .urgency
-            .clamp(PRIORITY_URGENCY_LOWER_BOUND, PRIORITY_URGENCY_UPPER_BOUND);
-
-        let mut field_value = format!("u={urgency}");
-
-        if priority.incremental {
-            field_value.push_str(",i");
-        }
+        let field_value = priority.to_field_value();
 
         let priority_field_value = field_value.as_bytes();
         let frame_payload_len =
@@ -1985,7 +2352,7 @@ impl Connection {
                 // Return early if the stream was reset, to avoid returning
                 // a Finished event later as well.
                 Err(Error::TransportError(crate::Error::StreamReset(e))) =>
-                    return Ok((s, Event::Reset(e))),
+                    return Ok((s, self.reset_event(s, e))),
 
                 Err(e) => return Err(e),
             };
@@ -2010,7 +2377,7 @@ impl Connection {
                 if let Err(crate::Error::StreamReset(e)) =
                     conn.stream_recv(finished, &mut [])
                 {
-                    return Ok((finished, Event::Reset(e)));
+                    return Ok((finished, self.reset_event(finished, e)));
                 }
             }
             return Ok((finished, Event::Finished));
@@ -2087,6 +2454,40 @@ impl Connection {
         Ok(())
     }
 
+    /// Cancels a request.
+    ///
+    /// Both directions of the request stream are aborted with the
+    /// `H3_REQUEST_CANCELLED` error code, by sending a STOP_SENDING frame
+    /// (unless the whole request or response was already received) and a
+    /// RESET_STREAM frame (unless the whole request or response was already
+    /// sent). No further events will be returned for the stream.
+    ///
+    /// The peer will see this as an [`Event::Cancelled`] event.
+    ///
+    /// [`Event::Cancelled`]: enum.Event.html#variant.Cancelled
+    pub fn cancel_request<F: BufFactory>(
+        &mut self, conn: &mut super::Connection<F>, stream_id: u64,
+    ) -> Result<()> {
+        if !crate::stream::is_bidi(stream_id) {
+            return Err(Error::IdError);
+        }
+
+        let err = WireErrorCode::RequestCancelled as u64;
+
+        for direction in [crate::Shutdown::Read, crate::Shutdown::Write] {
+            match conn.stream_shutdown(stream_id, direction, err) {
+                Ok(_) | Err(super::Error::Done) => (),
+
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        self.streams.remove(&stream_id);
+        self.finished_streams.retain(|s| *s != stream_id);
+
+        Ok(())
+    }
+
     /// Gets the raw settings from peer including unknown and reserved types.
     ///
     /// The order of settings is the same as received in the SETTINGS frame.
@@ -2614,6 +3015,17 @@ impl Connection {
                     // DATA frames are handled uniquely. After this point we lose
                     // visibility of DATA framing, so just log here.
                     if Some(frame::DATA_FRAME_TYPE_ID) == stream.frame_type() {
+                        let stats =
+                            request_stats_for(&mut self.request_stats, stream_id);
+
+                        if let Some(stats) = stats {
+                            stats
+                                .data_received_time
+                                .get_or_insert_with(Instant::now);
+                            stats.data_frames_received += 1;
+                            stats.data_bytes_received += payload_len;
+                        }
+
                         trace!(
                             "{} rx frm DATA stream={} wire_payload_len={}",
                             conn.trace_id(),
@@ -2738,6 +3150,36 @@ impl Connection {
         Err(Error::Done)
     }
 
+    /// Returns the event to report for a request stream reset by the peer.
+    fn reset_event(&self, stream_id: u64, error_code: u64) -> Event {
+        let stream = match self.streams.get(&stream_id) {
+            Some(v) if v.ty() == Some(stream::Type::Request) => v,
+
+            _ => return Event::Reset(error_code),
+        };
+
+        if error_code == WireErrorCode::RequestRejected as u64 && !self.is_server
+        {
+            return Event::Cancelled(Cancellation::Rejected);
+        }
+
+        if error_code != WireErrorCode::RequestCancelled as u64 {
+            return Event::Reset(error_code);
+        }
+
+        let response_started = if self.is_server {
+            stream.local_initialized()
+        } else {
+            stream.headers_received_count() > 0
+        };
+
+        if response_started {
+            Event::Cancelled(Cancellation::MidResponse)
+        } else {
+            Event::Cancelled(Cancellation::BeforeResponse)
+        }
+    }
+
     fn process_finished_stream(&mut self, stream_id: u64) {
         let stream = match self.streams.get_mut(&stream_id) {
             Some(v) => v,
@@ -2754,6 +3196,12 @@ impl Connection {
                 stream.finished();
 
                 self.finished_streams.push_back(stream_id);
+
+                if let Some(stats) =
+                    request_stats_for(&mut self.request_stats, stream_id)
+                {
+                    stats.fin_received_time = Some(Instant::now());
+                }
             },
 
             _ => (),
@@ -2898,6 +3346,16 @@ impl Connection {
                     q.add_event_data_now(ev_data).ok();
                 });
 
+                if let Some(stats) =
+                    request_stats_for(&mut self.request_stats, stream_id)
+                {
+                    stats.headers_received_time.get_or_insert_with(Instant::now);
+                    stats.headers_frames_received += 1;
+                    stats.header_bytes_received += header_fields_len(&headers);
+                    stats.header_block_bytes_received +=
+                        header_block.len() as u64;
+                }
+
                 let more_frames = !conn.stream_finished(stream_id);
 
                 return Ok((stream_id, Event::Headers {
@@ -3139,12 +3597,74 @@ impl Connection {
                 // TODO: we only implement this if we implement server push
             },
 
+            frame::Frame::Metadata { header_block } => {
+                // METADATA is ignored unless the extension was negotiated, and
+                // on streams other than request streams.
+                if !self.local_settings.metadata_enabled() ||
+                    !self.peer_settings.metadata_enabled() ||
+                    stream_id % 4 != 0
+                {
+                    return Err(Error::Done);
+                }
+
+                let max_size = self
+                    .local_settings
+                    .max_field_section_size
+                    .unwrap_or(u64::MAX);
+
+                let list = match self
+                    .qpack_decoder
+                    .decode(&header_block[..], max_size)
+                {
+                    Ok(v) => v,
+
+                    Err(e) => {
+                        let e = match e {
+                            qpack::Error::HeaderListTooLarge =>
+                                Error::ExcessiveLoad,
+
+                            _ => Error::QpackDecompressionFailed,
+                        };
+
+                        conn.close(
+                            true,
+                            e.to_wire(),
+                            b"Error parsing metadata.",
+                        )?;
+
+                        return Err(e);
+                    },
+                };
+
+                return Ok((stream_id, Event::Metadata { list }));
+            },
+
             frame::Frame::Unknown { .. } => (),
         }
 
         Err(Error::Done)
     }
 
+    /// Returns the statistics collected for a request stream so far.
+    ///
+    /// This returns `None` if request statistics are not enabled, or if
+    /// nothing was sent or received on the stream yet.
+    pub fn request_stats(&self, stream_id: u64) -> Option<&RequestStats> {
+        self.request_stats.as_ref()?.get(&stream_id)
+    }
+
+    /// Takes the statistics collected for a request stream.
+    ///
+    /// The statistics are kept until taken, so applications that enable
+    /// request statistics should call this method once the request is
+    /// complete, e.g. after it was finished or reset in both directions.
+    ///
+    /// This returns `None` if request statistics are not enabled, or if
+    /// nothing was sent or received on the stream.
+    pub fn take_request_stats(&mut self, stream_id: u64) -> Option<RequestStats> {
+        self.request_stats.as_mut()?.remove(&stream_id)
+    }
+
     /// Collects and returns statistics about the connection.
     #[inline]
     pub fn stats(&self) -> Stats {
@@ -4734,6 +5254,81 @@ mod tests {
         assert_eq!(Err(Error::Done), Priority::try_from(b"u=7, ".as_slice()));
     }
 
+    #[test]
+    fn priority_field_value() {
+        assert_eq!(Priority::default().to_field_value(), "u=3");
+        assert_eq!(Priority::new(0, true).to_field_value(), "u=0,i");
+        assert_eq!(Priority::new(100, false).to_field_value(), "u=7");
+
+        let priority = Priority::new(5, true);
+        assert_eq!(priority.urgency(), 5);
+        assert!(priority.incremental());
+    }
+
+    #[test]
+    #[cfg(feature = "sfv")]
+    fn priority_field_value_roundtrip() {
+        for urgency in 0..=7 {
+            for incremental in [false, true] {
+                let priority = Priority::new(urgency, incremental);
+
+                assert_eq!(
+                    Priority::try_from(priority.to_field_value().as_bytes()),
+                    Ok(priority)
+                );
+            }
+        }
+    }
+
+    #[test]
+    /// Reprioritize a request stream after the response was sent.
+    fn stream_priority() {
+        let mut s = Session::new().unwrap();
+        s.handshake().unwrap();
+
+        let (stream, req) = s.send_request(true).unwrap();
+
+        let ev_headers = Event::Headers {
+            list: req,
+            more_frames: false,
+        };
+
+        assert_eq!(s.poll_server(), Ok((stream, ev_headers)));
+        assert_eq!(s.poll_server(), Ok((stream, Event::Finished)));
+
+        let resp = vec![Header::new(b":status", b"200")];
+
+        s.server
+            .send_response_with_priority(
+                &mut s.pipe.server,
+                stream,
+                &resp,
+                &Priority::new(5, false),
+                false,
+            )
+            .unwrap();
+
+        let quic_stream = s.pipe.server.streams.get(stream).unwrap();
+        assert_eq!(quic_stream.urgency, 5 + PRIORITY_URGENCY_OFFSET);
+        assert!(!quic_stream.incremental);
+
+        s.server
+            .stream_priority(&mut s.pipe.server, stream, &Priority::new(0, true))
+            .unwrap();
+
+        let quic_stream = s.pipe.server.streams.get(stream).unwrap();
+        assert_eq!(quic_stream.urgency, PRIORITY_URGENCY_OFFSET);
+        assert!(quic_stream.incremental);
+
+        // Urgency is clamped.
+        s.server
+            .stream_priority(&mut s.pipe.server, stream, &Priority::new(9, true))
+            .unwrap();
+
+        let quic_stream = s.pipe.server.streams.get(stream).unwrap();
+        assert_eq!(quic_stream.urgency, 7 + PRIORITY_URGENCY_OFFSET);
+    }
+
     #[test]
     /// Send a PRIORITY_UPDATE for request stream from the client.
     fn priority_update_request() {
@@ -7215,6 +7810,218 @@ mod tests {
         assert_eq!(s.pipe.server.readable().len(), 0);
     }
 
+    #[test]
+    fn cancel_request_before_response() {
+        let mut s = Session::new().unwrap();
+        s.handshake().unwrap();
+
+        let (stream, req) = s.send_request(false).unwrap();
+
+        let ev_headers = Event::Headers {
+            list: req,
+            more_frames: true,
+        };
+
+        assert_eq!(s.poll_server(), Ok((stream, ev_headers)));
+        assert_eq!(s.poll_server(), Err(Error::Done));
+
+        assert_eq!(s.client.cancel_request(&mut s.pipe.client, stream), Ok(()));
+        assert_eq!(s.pipe.advance(), Ok(()));
+
+        assert_eq!(
+            s.poll_server(),
+            Ok((stream, Event::Cancelled(Cancellation::BeforeResponse)))
+        );
+        assert_eq!(s.poll_server(), Err(Error::Done));
+
+        // The client also stopped the response.
+        assert_eq!(
+            s.pipe.server.stream_send(stream, b"hello", true),
+            Err(crate::Error::StreamStopped(
+                WireErrorCode::RequestCancelled as u64
+            ))
+        );
+
+        assert_eq!(s.poll_client(), Err(Error::Done));
+    }
+
+    #[test]
+    fn cancel_request_mid_response() {
+        let mut s = Session::new().unwrap();
+        s.handshake().unwrap();
+
+        let (stream, req) = s.send_request(true).unwrap();
+
+        let ev_headers = Event::Headers {
+            list: req,
+            more_frames: false,
+        };
+
+        assert_eq!(s.poll_server(), Ok((stream, ev_headers)));
+        assert_eq!(s.poll_server(), Ok((stream, Event::Finished)));
+
+        let resp = s.send_response(stream, false).unwrap();
+
+        let ev_headers = Event::Headers {
+            list: resp,
+            more_frames: true,
+        };
+
+        assert_eq!(s.poll_client(), Ok((stream, ev_headers)));
+
+        assert_eq!(s.server.cancel_request(&mut s.pipe.server, stream), Ok(()));
+        assert_eq!(s.pipe.advance(), Ok(()));
+
+        assert_eq!(
+            s.poll_client(),
+            Ok((stream, Event::Cancelled(Cancellation::MidResponse)))
+        );
+        assert_eq!(s.poll_client(), Err(Error::Done));
+
+        // Cancelling a uni stream is not allowed.
+        assert_eq!(
+            s.client.cancel_request(&mut s.pipe.client, 2),
+            Err(Error::IdError)
+        );
+    }
+
+    #[test]
+    fn request_rejected() {
+        let mut s = Session::new().unwrap();
+        s.handshake().unwrap();
+
+        let (stream, _) = s.send_request(false).unwrap();
+        assert!(s.poll_server().is_ok());
+
+        let rejected = WireErrorCode::RequestRejected as u64;
+
+        // Server rejects the request.
+        assert_eq!(
+            s.pipe.server.stream_shutdown(
+                stream,
+                crate::Shutdown::Write,
+                rejected
+            ),
+            Ok(())
+        );
+        assert_eq!(s.pipe.advance(), Ok(()));
+
+        assert_eq!(
+            s.poll_client(),
+            Ok((stream, Event::Cancelled(Cancellation::Rejected)))
+        );
+        assert_eq!(Cancellation::Rejected.error_code(), rejected);
+
+        // Only servers can reject requests.
+        let (stream, _) = s.send_request(false).unwrap();
+        assert!(s.poll_server().is_ok());
+
+        assert_eq!(
+            s.pipe.client.stream_shutdown(
+                stream,
+                crate::Shutdown::Write,
+                rejected
+            ),
+            Ok(())
+        );
+        assert_eq!(s.pipe.advance(), Ok(()));
+
+        assert_eq!(s.poll_server(), Ok((stream, Event::Reset(rejected))));
+    }
+
+    #[test]
+    fn request_stats() {
+        let mut buf = [0; 65535];
+
+        let mut config = crate::Config::new(crate::PROTOCOL_VERSION).unwrap();
+        config
+            .load_cert_chain_from_pem_file("examples/cert.crt")
+            .unwrap();
+        config
+            .load_priv_key_from_pem_file("examples/cert.key")
+            .unwrap();
+        config.set_application_protos(&[b"h3"]).unwrap();
+        config.set_initial_max_data(1500);
+        config.set_initial_max_stream_data_bidi_local(150);
+        config.set_initial_max_stream_data_bidi_remote(150);
+        config.set_initial_max_stream_data_uni(150);
+        config.set_initial_max_streams_bidi(5);
+        config.set_initial_max_streams_uni(5);
+        config.verify_peer(false);
+
+        let mut h3_config = Config::new().unwrap();
+        h3_config.enable_request_stats(true);
+
+        let mut s = Session::with_configs(&mut config, &h3_config).unwrap();
+        s.handshake().unwrap();
+
+        let (stream, req) = s.send_request(false).unwrap();
+        let body = s.send_body_client(stream, true).unwrap();
+
+        let stats = s.client.request_stats(stream).unwrap();
+        assert!(stats.headers_sent_time.is_some());
+        assert!(stats.data_sent_time.is_some());
+        assert!(stats.fin_sent_time.is_some());
+        assert_eq!(stats.headers_received_time, None);
+        assert_eq!(stats.headers_frames_sent, 1);
+        assert_eq!(stats.header_bytes_sent, header_fields_len(&req));
+        assert!(stats.header_block_bytes_sent > 0);
+        assert_eq!(stats.data_frames_sent, 1);
+        assert_eq!(stats.data_bytes_sent, body.len() as u64);
+        assert!(!stats.is_complete());
+
+        let ev_headers = Event::Headers {
+            list: req.clone(),
+            more_frames: true,
+        };
+
+        assert_eq!(s.poll_server(), Ok((stream, ev_headers)));
+        assert_eq!(s.poll_server(), Ok((stream, Event::Data)));
+        assert_eq!(s.recv_body_server(stream, &mut buf), Ok(body.len()));
+        assert_eq!(s.poll_server(), Ok((stream, Event::Finished)));
+
+        let stats = s.server.request_stats(stream).unwrap();
+        assert!(stats.headers_received_time.is_some());
+        assert!(stats.data_received_time.is_some());
+        assert!(stats.fin_received_time.is_some());
+        assert_eq!(stats.headers_frames_received, 1);
+        assert_eq!(stats.header_bytes_received, header_fields_len(&req));
+        assert_eq!(
+            stats.header_block_bytes_received,
+            s.client
+                .request_stats(stream)
+                .unwrap()
+                .header_block_bytes_sent
+        );
+        assert_eq!(stats.data_frames_received, 1);
+        assert_eq!(stats.data_bytes_received, body.len() as u64);
+
+        s.send_response(stream, false).unwrap();
+        s.send_body_server(stream, true).unwrap();
+
+        assert!(s.server.request_stats(stream).unwrap().is_complete());
+
+        assert!(matches!(s.poll_client(), Ok((_, Event::Headers { .. }))));
+        assert_eq!(s.poll_client(), Ok((stream, Event::Data)));
+        assert_eq!(s.recv_body_client(stream, &mut buf), Ok(body.len()));
+        assert_eq!(s.poll_client(), Ok((stream, Event::Finished)));
+
+        let stats = s.client.take_request_stats(stream).unwrap();
+        assert!(stats.is_complete());
+        assert!(stats.headers_received_time >= stats.headers_sent_time);
+        assert_eq!(stats.data_bytes_received, body.len() as u64);
+
+        assert_eq!(s.client.request_stats(stream), None);
+        assert_eq!(s.client.take_request_stats(stream), None);
+
+        // Stats are not collected unless enabled.
+        let mut s = Session::new().unwrap();
+        s.handshake().unwrap();
+
+        let (stream, _) = s.send_request(true).unwrap();
+        assert_eq!(s.client.request_stats(stream), None);
+    }
+
     #[test]
     fn reset_finished_at_client() {
         let mut buf = [0; 65535];
@@ -7295,6 +8102,120 @@ mod tests {
         assert_eq!(s.poll_client(), Ok((stream, Event::Finished)));
         assert_eq!(s.poll_client(), Err(Error::Done));
     }
+
+    fn metadata_session(client: bool, server: bool) -> Session {
+        let mut config = crate::Config::new(crate::PROTOCOL_VERSION).unwrap();
+        config
+            .load_cert_chain_from_pem_file("examples/cert.crt")
+            .unwrap();
+        config
+            .load_priv_key_from_pem_file("examples/cert.key")
+            .unwrap();
+        config.set_application_protos(&[b"h3"]).unwrap();
+        config.set_initial_max_data(1500);
+        config.set_initial_max_stream_data_bidi_local(150);
+        config.set_initial_max_stream_data_bidi_remote(150);
+        config.set_initial_max_stream_data_uni(150);
+        config.set_initial_max_streams_bidi(5);
+        config.set_initial_max_streams_uni(5);
+        config.verify_peer(false);
+
+        let mut client_config = Config::new().unwrap();
+        client_config.enable_metadata(client);
+
+        let mut server_config = Config::new().unwrap();
+        server_config.enable_metadata(server);
+
+        let pipe = crate::testing::Pipe::with_config(&mut config).unwrap();
+
+        let mut s = Session {
+            pipe,
+            client: Connection::new(&client_config, false, false).unwrap(),
+            server: Connection::new(&server_config, true, false).unwrap(),
+        };
+
+        s.handshake().unwrap();
+
+        s
+    }
+
+    #[test]
+    /// Tests that METADATA frames are exchanged on request streams once both
+    /// endpoints enabled the extension.
+    fn metadata() {
+        let mut s = metadata_session(true, true);
+
+        assert!(s.client.metadata_enabled_by_peer());
+        assert!(s.server.metadata_enabled_by_peer());
+
+        let (stream, req) = s.send_request(false).unwrap();
+
+        let ev_headers = Event::Headers {
+            list: req,
+            more_frames: true,
+        };
+
+        assert_eq!(s.poll_server(), Ok((stream, ev_headers)));
+
+        let fields = vec![Header::new(b"proxy-status", b"hop1; received")];
+
+        assert_eq!(
+            s.client.send_metadata(&mut s.pipe.client, stream, &fields),
+            Ok(())
+        );
+        s.advance().ok();
+
+        assert_eq!(
+            s.poll_server(),
+            Ok((stream, Event::Metadata {
+                list: fields.clone()
+            }))
+        );
+        assert_eq!(s.poll_server(), Err(Error::Done));
+
+        // The server can send METADATA back on the same stream.
+        assert_eq!(
+            s.server.send_metadata(&mut s.pipe.server, stream, &fields),
+            Ok(())
+        );
+        s.advance().ok();
+
+        assert_eq!(
+            s.poll_client(),
+            Ok((stream, Event::Metadata {
+                list: fields.clone()
+            }))
+        );
+
+        // METADATA can't be sent on streams that aren't request streams.
+        assert_eq!(
+            s.client.send_metadata(&mut s.pipe.client, 2, &fields),
+            Err(Error::FrameUnexpected)
+        );
+    }
+
+    #[test]
+    /// Tests that METADATA can't be sent unless both endpoints enabled the
+    /// extension.
+    fn metadata_not_negotiated() {
+        let mut s = metadata_session(true, false);
+
+        assert!(!s.client.metadata_enabled_by_peer());
+        assert!(s.server.metadata_enabled_by_peer());
+
+        let (stream, _) = s.send_request(false).unwrap();
+
+        let fields = vec![Header::new(b"proxy-status", b"hop1; received")];
+
+        assert_eq!(
+            s.client.send_metadata(&mut s.pipe.client, stream, &fields),
+            Err(Error::FrameUnexpected)
+        );
+        assert_eq!(
+            s.server.send_metadata(&mut s.pipe.server, stream, &fields),
+            Err(Error::FrameUnexpected)
+        );
+    }
 }
 
 #[cfg(feature = "ffi")]
@@ -7306,4 +8227,5 @@ pub mod frame;
 mod frame;
 #[doc(hidden)]
 pub mod qpack;
+pub mod server;
 mod stream;
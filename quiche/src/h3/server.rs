@@ -0,0 +1,838 @@
+// Copyright (C) 2025, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Typed requests and responses for HTTP/3 servers.
+//!
+//! This is an optional layer over the event based API of [`Connection`]. The
+//! [`Server`] polls the connection, validates the request headers as required
+//! by [Section 4.3.1 of RFC 9114], and returns each request as a [`Request`]
+//! together with a [`Responder`] that emits the response in the right order.
+//!
+//! Malformed requests are rejected with the `H3_MESSAGE_ERROR` error code
+//! without being reported to the application.
+//!
+//! ```no_run
+//! # let mut config = quiche::Config::new(quiche::PROTOCOL_VERSION).unwrap();
+//! # let scid = quiche::ConnectionId::from_ref(&[0xba; 16]);
+//! # let peer = "127.0.0.1:1234".parse().unwrap();
+//! # let local = "127.0.0.1:1234".parse().unwrap();
+//! # let mut conn = quiche::accept(&scid, None, local, peer, &mut config).unwrap();
+//! # let h3_config = quiche::h3::Config::new()?;
+//! # let mut h3_conn = quiche::h3::Connection::with_transport(&mut conn, &h3_config)?;
+//! use quiche::h3::server;
+//!
+//! let mut server = server::Server::new();
+//!
+//! loop {
+//!     match server.poll(&mut conn, &mut h3_conn) {
+//!         Ok(server::Event::Request(request, mut responder)) => {
+//!             let headers = [quiche::h3::Header::new(b"server", b"quiche")];
+//!
+//!             if request.method() == "GET" && request.path() == Some("/") {
+//!                 responder.send_response(&mut conn, &mut h3_conn, 200, &headers, false)?;
+//!                 responder.send_body(&mut conn, &mut h3_conn, b"Hello World!", true)?;
+//!             } else {
+//!                 responder.send_response(&mut conn, &mut h3_conn, 404, &headers, true)?;
+//!             }
+//!         },
+//!
+//!         Ok(server::Event::Data(stream_id)) => {
+//!             // Request body data, read it with `recv_body()`.
+//!             # return Ok(());
+//!         },
+//!
+//!         Ok(_) => (),
+//!
+//!         Err(quiche::h3::Error::Done) => break,
+//!
+//!         Err(e) => {
+//!             // An error occurred, handle it.
+//!             break;
+//!         },
+//!     }
+//! }
+//! # Ok::<(), quiche::h3::Error>(())
+//! ```
+//!
+//! [`Connection`]: ../struct.Connection.html
+//! [`Server`]: struct.Server.html
+//! [`Request`]: struct.Request.html
+//! [`Responder`]: struct.Responder.html
+//! [Section 4.3.1 of RFC 9114]: https://www.rfc-editor.org/rfc/rfc9114.html#section-4.3.1
+
+use std::collections::HashSet;
+
+use crate::range_buf::BufFactory;
+
+use super::Connection;
+use super::Error;
+use super::Header;
+use super::NameValue;
+use super::Result;
+
+/// Header fields that are specific to a connection, which are not allowed in
+/// HTTP/3.
+const CONNECTION_SPECIFIC_HEADERS: [&[u8]; 5] = [
+    b"connection",
+    b"keep-alive",
+    b"proxy-connection",
+    b"transfer-encoding",
+    b"upgrade",
+];
+
+/// An event returned by [`Server::poll()`].
+///
+/// [`Server::poll()`]: struct.Server.html#method.poll
+#[derive(Debug, PartialEq)]
+pub enum Event {
+    /// A well-formed request was received.
+    Request(Request, Responder),
+
+    /// Request body data was received on the given stream.
+    ///
+    /// As with the underlying [`Data`] event, [`Request::recv_body()`] needs
+    /// to be called repeatedly until the [`Done`] value is returned.
+    ///
+    /// [`Data`]: ../enum.Event.html#variant.Data
+    /// [`Request::recv_body()`]: struct.Request.html#method.recv_body
+    /// [`Done`]: ../enum.Error.html#variant.Done
+    Data(u64),
+
+    /// Trailers were received after the request body on the given stream.
+    Trailers(u64, Vec<Header>),
+
+    /// The request on the given stream was fully received.
+    Finished(u64),
+
+    /// Any other event of the underlying connection, such as resets or
+    /// GOAWAY, for the given stream or push ID.
+    Other(u64, super::Event),
+}
+
+/// An HTTP/3 request.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Request {
+    stream_id: u64,
+    method: String,
+    scheme: Option<String>,
+    authority: Option<String>,
+    path: Option<String>,
+    protocol: Option<String>,
+    headers: Vec<Header>,
+    has_body: bool,
+}
+
+impl Request {
+    /// Builds a request from the header fields received on a stream, checking
+    /// that it is well-formed.
+    ///
+    /// The [`MessageError`] error is returned if the request is malformed.
+    ///
+    /// [`MessageError`]: ../enum.Error.html#variant.MessageError
+    pub fn from_headers(
+        stream_id: u64, list: Vec<Header>, has_body: bool,
+    ) -> Result<Request> {
+        let mut method = None;
+        let mut scheme = None;
+        let mut authority = None;
+        let mut path = None;
+        let mut protocol = None;
+
+        let mut headers = Vec::with_capacity(list.len());
+
+        for h in list {
+            let name = h.name();
+
+            if name.iter().any(|c| c.is_ascii_uppercase()) {
+                return Err(Error::MessageError);
+            }
+
+            if !name.starts_with(b":") {
+                if CONNECTION_SPECIFIC_HEADERS.contains(&name) ||
+                    (name == b"te" && h.value() != b"trailers")
+                {
+                    return Err(Error::MessageError);
+                }
+
+                headers.push(h);
+                continue;
+            }
+
+            // Pseudo-header fields must precede regular header fields.
+            if !headers.is_empty() {
+                return Err(Error::MessageError);
+            }
+
+            let field = match name {
+                b":method" => &mut method,
+                b":scheme" => &mut scheme,
+                b":authority" => &mut authority,
+                b":path" => &mut path,
+                b":protocol" => &mut protocol,
+
+                _ => return Err(Error::MessageError),
+            };
+
+            if field.is_some() {
+                return Err(Error::MessageError);
+            }
+
+            let value = String::from_utf8(h.value().to_vec())
+                .map_err(|_| Error::MessageError)?;
+
+            *field = Some(value);
+        }
+
+        let method = method.ok_or(Error::MessageError)?;
+
+        match (method.as_str(), &protocol) {
+            // A CONNECT request only carries the authority to connect to.
+            ("CONNECT", None) =>
+                if scheme.is_some() || path.is_some() || authority.is_none() {
+                    return Err(Error::MessageError);
+                },
+
+            // Extended CONNECT, see RFC 9220.
+            ("CONNECT", Some(_)) =>
+                if scheme.is_none() || path.is_none() || authority.is_none() {
+                    return Err(Error::MessageError);
+                },
+
+            (_, Some(_)) => return Err(Error::MessageError),
+
+            (_, None) =>
+                if scheme.is_none() || path.as_deref().unwrap_or("").is_empty() {
+                    return Err(Error::MessageError);
+                },
+        }
+
+        Ok(Request {
+            stream_id,
+            method,
+            scheme,
+            authority,
+            path,
+            protocol,
+            headers,
+            has_body,
+        })
+    }
+
+    /// Returns the ID of the stream the request was received on.
+    pub fn stream_id(&self) -> u64 {
+        self.stream_id
+    }
+
+    /// Returns the request method.
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+
+    /// Returns the request scheme. It is only `None` for CONNECT requests.
+    pub fn scheme(&self) -> Option<&str> {
+        self.scheme.as_deref()
+    }
+
+    /// Returns the request authority, if any.
+    pub fn authority(&self) -> Option<&str> {
+        self.authority.as_deref()
+    }
+
+    /// Returns the request path. It is only `None` for CONNECT requests.
+    pub fn path(&self) -> Option<&str> {
+        self.path.as_deref()
+    }
+
+    /// Returns the protocol of an extended CONNECT request, if any.
+    pub fn protocol(&self) -> Option<&str> {
+        self.protocol.as_deref()
+    }
+
+    /// Returns the regular header fields of the request, i.e. without the
+    /// pseudo-header fields.
+    pub fn headers(&self) -> &[Header] {
+        &self.headers
+    }
+
+    /// Returns the value of the first header field with the given name, if
+    /// any.
+    pub fn header(&self, name: &[u8]) -> Option<&[u8]> {
+        self.headers
+            .iter()
+            .find(|h| h.name() == name)
+            .map(|h| h.value())
+    }
+
+    /// Returns whether the request has a body or trailers.
+    pub fn has_body(&self) -> bool {
+        self.has_body
+    }
+
+    /// Reads request body data into the provided buffer.
+    ///
+    /// See [`Connection::recv_body()`].
+    ///
+    /// [`Connection::recv_body()`]: ../struct.Connection.html#method.recv_body
+    pub fn recv_body<F: BufFactory>(
+        &self, conn: &mut crate::Connection<F>, h3: &mut Connection,
+        out: &mut [u8],
+    ) -> Result<usize> {
+        h3.recv_body(conn, self.stream_id, out)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ResponseState {
+    /// Nothing was sent yet.
+    Initial,
+
+    /// One or more informational responses were sent.
+    Informational,
+
+    /// The final response headers were sent, the body may follow.
+    Body,
+
+    /// The response was fully sent.
+    Finished,
+}
+
+/// A handle to respond to a [`Request`].
+///
+/// It makes sure that informational responses, the final response, the body
+/// and the trailers are sent in that order, and that the `:status`
+/// pseudo-header comes first. Methods called out of order return the
+/// [`FrameUnexpected`] error without sending anything.
+///
+/// As with the underlying [`Connection`] methods, the [`StreamBlocked`] and
+/// [`Done`] errors mean that the stream doesn't have enough capacity, and the
+/// operation should be retried once it is writable again.
+///
+/// [`Request`]: struct.Request.html
+/// [`Connection`]: ../struct.Connection.html
+/// [`FrameUnexpected`]: ../enum.Error.html#variant.FrameUnexpected
+/// [`StreamBlocked`]: ../enum.Error.html#variant.StreamBlocked
+/// [`Done`]: ../enum.Error.html#variant.Done
+#[derive(Debug, PartialEq, Eq)]
+pub struct Responder {
+    stream_id: u64,
+    state: ResponseState,
+}
+
+impl Responder {
+    fn new(stream_id: u64) -> Self {
+        Responder {
+            stream_id,
+            state: ResponseState::Initial,
+        }
+    }
+
+    /// Returns the ID of the stream the response is sent on.
+    pub fn stream_id(&self) -> u64 {
+        self.stream_id
+    }
+
+    /// Returns whether the response was fully sent.
+    pub fn is_finished(&self) -> bool {
+        self.state == ResponseState::Finished
+    }
+
+    /// Sends response headers with the given status code.
+    ///
+    /// Informational (1xx) responses can be sent any number of times before
+    /// the final response, and can't have `fin` set. The `headers` must not
+    /// contain pseudo-header fields, otherwise the [`MessageError`] error is
+    /// returned.
+    ///
+    /// [`MessageError`]: ../enum.Error.html#variant.MessageError
+    pub fn send_response<T: NameValue, F: BufFactory>(
+        &mut self, conn: &mut crate::Connection<F>, h3: &mut Connection,
+        status: u16, headers: &[T], fin: bool,
+    ) -> Result<()> {
+        let informational = (100..200).contains(&status);
+
+        // 101 (Switching Protocols) is not allowed in HTTP/3.
+        if !(100..1000).contains(&status) ||
+            status == 101 ||
+            (informational && fin)
+        {
+            return Err(Error::FrameUnexpected);
+        }
+
+        if headers.iter().any(|h| h.name().starts_with(b":")) {
+            return Err(Error::MessageError);
+        }
+
+        let status = status.to_string();
+
+        let mut list = Vec::with_capacity(headers.len() + 1);
+        list.push(Header::new(b":status", status.as_bytes()));
+        list.extend(headers.iter().map(|h| Header::new(h.name(), h.value())));
+
+        match self.state {
+            ResponseState::Initial =>
+                h3.send_response(conn, self.stream_id, &list, fin)?,
+
+            ResponseState::Informational => h3.send_additional_headers(
+                conn,
+                self.stream_id,
+                &list,
+                false,
+                fin,
+            )?,
+
+            _ => return Err(Error::FrameUnexpected),
+        }
+
+        self.state = match (informational, fin) {
+            (true, _) => ResponseState::Informational,
+
+            (false, false) => ResponseState::Body,
+
+            (false, true) => ResponseState::Finished,
+        };
+
+        Ok(())
+    }
+
+    /// Sends response body data, returning how much of it was written.
+    ///
+    /// The response is only finished if `fin` is set and all of the data was
+    /// written. See [`Connection::send_body()`].
+    ///
+    /// [`Connection::send_body()`]: ../struct.Connection.html#method.send_body
+    pub fn send_body<F: BufFactory>(
+        &mut self, conn: &mut crate::Connection<F>, h3: &mut Connection,
+        body: &[u8], fin: bool,
+    ) -> Result<usize> {
+        if self.state != ResponseState::Body {
+            return Err(Error::FrameUnexpected);
+        }
+
+        let written = h3.send_body(conn, self.stream_id, body, fin)?;
+
+        if fin && written == body.len() {
+            self.state = ResponseState::Finished;
+        }
+
+        Ok(written)
+    }
+
+    /// Sends trailers, finishing the response.
+    pub fn send_trailers<T: NameValue, F: BufFactory>(
+        &mut self, conn: &mut crate::Connection<F>, h3: &mut Connection,
+        trailers: &[T],
+    ) -> Result<()> {
+        if self.state != ResponseState::Body {
+            return Err(Error::FrameUnexpected);
+        }
+
+        if trailers.iter().any(|h| h.name().starts_with(b":")) {
+            return Err(Error::MessageError);
+        }
+
+        h3.send_additional_headers(conn, self.stream_id, trailers, true, true)?;
+
+        self.state = ResponseState::Finished;
+
+        Ok(())
+    }
+}
+
+/// Assembles requests from the events of an HTTP/3 server connection.
+#[derive(Default)]
+pub struct Server {
+    /// The streams on which a request was received and is still being read.
+    requests: HashSet<u64>,
+}
+
+impl Server {
+    /// Creates a new server.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Processes HTTP/3 data received from the peer, like
+    /// [`Connection::poll()`].
+    ///
+    /// Malformed requests are rejected with the `H3_MESSAGE_ERROR` error code,
+    /// and no event is returned for them.
+    ///
+    /// [`Connection::poll()`]: ../struct.Connection.html#method.poll
+    pub fn poll<F: BufFactory>(
+        &mut self, conn: &mut crate::Connection<F>, h3: &mut Connection,
+    ) -> Result<Event> {
+        loop {
+            let (stream_id, event) = h3.poll(conn)?;
+
+            match event {
+                super::Event::Headers { list, .. }
+                    if self.requests.contains(&stream_id) =>
+                    return Ok(Event::Trailers(stream_id, list)),
+
+                super::Event::Headers { list, more_frames } =>
+                    match Request::from_headers(stream_id, list, more_frames) {
+                        Ok(request) => {
+                            self.requests.insert(stream_id);
+
+                            return Ok(Event::Request(
+                                request,
+                                Responder::new(stream_id),
+                            ));
+                        },
+
+                        Err(e) => {
+                            trace!(
+                                "{} rejected malformed request on stream {}",
+                                conn.trace_id(),
+                                stream_id
+                            );
+
+                            let code = e.to_wire();
+
+                            conn.stream_shutdown(
+                                stream_id,
+                                crate::Shutdown::Read,
+                                code,
+                            )
+                            .ok();
+                            conn.stream_shutdown(
+                                stream_id,
+                                crate::Shutdown::Write,
+                                code,
+                            )
+                            .ok();
+                        },
+                    },
+
+                // Ignore events on streams whose request was rejected.
+                super::Event::Data | super::Event::Finished
+                    if !self.requests.contains(&stream_id) =>
+                    (),
+
+                super::Event::Data => return Ok(Event::Data(stream_id)),
+
+                super::Event::Finished => {
+                    self.requests.remove(&stream_id);
+
+                    return Ok(Event::Finished(stream_id));
+                },
+
+                super::Event::Reset(_) | super::Event::Cancelled(_) => {
+                    self.requests.remove(&stream_id);
+
+                    return Ok(Event::Other(stream_id, event));
+                },
+
+                _ => return Ok(Event::Other(stream_id, event)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::h3::testing::Session;
+
+    fn request(headers: &[(&str, &str)]) -> Result<Request> {
+        let list = headers
+            .iter()
+            .map(|(n, v)| Header::new(n.as_bytes(), v.as_bytes()))
+            .collect();
+
+        Request::from_headers(0, list, false)
+    }
+
+    #[test]
+    fn well_formed_requests() {
+        let req = request(&[
+            (":method", "GET"),
+            (":scheme", "https"),
+            (":authority", "quic.tech"),
+            (":path", "/test"),
+            ("user-agent", "quiche-test"),
+            ("te", "trailers"),
+        ])
+        .unwrap();
+
+        assert_eq!(req.method(), "GET");
+        assert_eq!(req.scheme(), Some("https"));
+        assert_eq!(req.authority(), Some("quic.tech"));
+        assert_eq!(req.path(), Some("/test"));
+        assert_eq!(req.protocol(), None);
+        assert_eq!(req.headers().len(), 2);
+        assert_eq!(req.header(b"user-agent"), Some(&b"quiche-test"[..]));
+
+        let req =
+            request(&[(":method", "CONNECT"), (":authority", "quic.tech:443")])
+                .unwrap();
+        assert_eq!(req.path(), None);
+
+        let req = request(&[
+            (":method", "CONNECT"),
+            (":protocol", "webtransport"),
+            (":scheme", "https"),
+            (":authority", "quic.tech"),
+            (":path", "/wt"),
+        ])
+        .unwrap();
+        assert_eq!(req.protocol(), Some("webtransport"));
+    }
+
+    #[test]
+    fn malformed_requests() {
+        let malformed: &[&[(&str, &str)]] = &[
+            // Missing :method.
+            &[(":scheme", "https"), (":path", "/")],
+            // Missing :path.
+            &[(":method", "GET"), (":scheme", "https")],
+            // Empty :path.
+            &[(":method", "GET"), (":scheme", "https"), (":path", "")],
+            // Duplicate pseudo-header.
+            &[
+                (":method", "GET"),
+                (":method", "GET"),
+                (":scheme", "https"),
+                (":path", "/"),
+            ],
+            // Response pseudo-header.
+            &[
+                (":method", "GET"),
+                (":scheme", "https"),
+                (":path", "/"),
+                (":status", "200"),
+            ],
+            // Pseudo-header after regular header.
+            &[
+                (":method", "GET"),
+                (":scheme", "https"),
+                ("user-agent", "quiche-test"),
+                (":path", "/"),
+            ],
+            // Uppercase header name.
+            &[
+                (":method", "GET"),
+                (":scheme", "https"),
+                (":path", "/"),
+                ("User-Agent", "quiche-test"),
+            ],
+            // Connection-specific header.
+            &[
+                (":method", "GET"),
+                (":scheme", "https"),
+                (":path", "/"),
+                ("connection", "close"),
+            ],
+            &[
+                (":method", "GET"),
+                (":scheme", "https"),
+                (":path", "/"),
+                ("te", "gzip"),
+            ],
+            // CONNECT with a path.
+            &[
+                (":method", "CONNECT"),
+                (":authority", "quic.tech:443"),
+                (":path", "/"),
+            ],
+            // :protocol without CONNECT.
+            &[
+                (":method", "GET"),
+                (":protocol", "webtransport"),
+                (":scheme", "https"),
+                (":path", "/"),
+            ],
+        ];
+
+        for headers in malformed {
+            assert_eq!(request(headers), Err(Error::MessageError), "{headers:?}");
+        }
+    }
+
+    #[test]
+    fn request_response() {
+        let mut s = Session::new().unwrap();
+        s.handshake().unwrap();
+
+        let mut server = Server::new();
+
+        let (stream, _) = s.send_request(false).unwrap();
+        s.client
+            .send_body(&mut s.pipe.client, stream, b"hello", false)
+            .unwrap();
+        s.client
+            .send_additional_headers(
+                &mut s.pipe.client,
+                stream,
+                &[Header::new(b"trailer", b"value")],
+                true,
+                true,
+            )
+            .unwrap();
+        s.advance().ok();
+
+        let (req, mut responder) =
+            match server.poll(&mut s.pipe.server, &mut s.server) {
+                Ok(Event::Request(req, responder)) => (req, responder),
+
+                ev => panic!("unexpected event {ev:?}"),
+            };
+
+        assert_eq!(req.stream_id(), stream);
+        assert_eq!(req.path(), Some("/test"));
+        assert!(req.has_body());
+
+        assert_eq!(
+            server.poll(&mut s.pipe.server, &mut s.server),
+            Ok(Event::Data(stream))
+        );
+
+        let mut buf = [0; 16];
+        assert_eq!(
+            req.recv_body(&mut s.pipe.server, &mut s.server, &mut buf),
+            Ok(5)
+        );
+        assert_eq!(&buf[..5], b"hello");
+
+        assert_eq!(
+            server.poll(&mut s.pipe.server, &mut s.server),
+            Ok(Event::Trailers(stream, vec![Header::new(
+                b"trailer", b"value"
+            )]))
+        );
+        assert_eq!(
+            server.poll(&mut s.pipe.server, &mut s.server),
+            Ok(Event::Finished(stream))
+        );
+        assert_eq!(
+            server.poll(&mut s.pipe.server, &mut s.server),
+            Err(Error::Done)
+        );
+
+        let no_headers: &[Header] = &[];
+
+        // The body can't be sent before the response headers.
+        assert_eq!(
+            responder.send_body(&mut s.pipe.server, &mut s.server, b"x", true),
+            Err(Error::FrameUnexpected)
+        );
+
+        responder
+            .send_response(
+                &mut s.pipe.server,
+                &mut s.server,
+                103,
+                no_headers,
+                false,
+            )
+            .unwrap();
+        responder
+            .send_response(
+                &mut s.pipe.server,
+                &mut s.server,
+                200,
+                no_headers,
+                false,
+            )
+            .unwrap();
+
+        // Only one final response can be sent.
+        assert_eq!(
+            responder.send_response(
+                &mut s.pipe.server,
+                &mut s.server,
+                200,
+                no_headers,
+                false
+            ),
+            Err(Error::FrameUnexpected)
+        );
+
+        assert_eq!(
+            responder.send_body(
+                &mut s.pipe.server,
+                &mut s.server,
+                b"world",
+                false
+            ),
+            Ok(5)
+        );
+        responder
+            .send_trailers(&mut s.pipe.server, &mut s.server, &[Header::new(
+                b"trailer", b"value",
+            )])
+            .unwrap();
+        assert!(responder.is_finished());
+
+        s.advance().ok();
+
+        let ev_headers = crate::h3::Event::Headers {
+            list: vec![Header::new(b":status", b"103")],
+            more_frames: true,
+        };
+        assert_eq!(s.poll_client(), Ok((stream, ev_headers)));
+
+        let ev_headers = crate::h3::Event::Headers {
+            list: vec![Header::new(b":status", b"200")],
+            more_frames: true,
+        };
+        assert_eq!(s.poll_client(), Ok((stream, ev_headers)));
+        assert_eq!(s.poll_client(), Ok((stream, crate::h3::Event::Data)));
+    }
+
+    #[test]
+    fn malformed_request_rejected() {
+        let mut s = Session::new().unwrap();
+        s.handshake().unwrap();
+
+        let mut server = Server::new();
+
+        let req = vec![
+            Header::new(b":method", b"GET"),
+            Header::new(b":scheme", b"https"),
+            Header::new(b":authority", b"quic.tech"),
+        ];
+
+        let stream = s
+            .client
+            .send_request(&mut s.pipe.client, &req, true)
+            .unwrap();
+        s.advance().ok();
+
+        assert_eq!(
+            server.poll(&mut s.pipe.server, &mut s.server),
+            Err(Error::Done)
+        );
+
+        s.advance().ok();
+
+        assert_eq!(
+            s.poll_client(),
+            Ok((stream, crate::h3::Event::Reset(0x10e)))
+        );
+    }
+}
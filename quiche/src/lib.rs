@@ -122,7 +122,11 @@
 //! loop {
 //!     let (read, from) = socket.recv_from(&mut buf).unwrap();
 //!
-//!     let recv_info = quiche::RecvInfo { from, to };
+//!     let recv_info = quiche::RecvInfo {
+//!         from,
+//!         to,
+//!         ecn: quiche::Ecn::NotEct,
+//!     };
 //!
 //!     let read = match conn.recv(&mut buf[..read], recv_info) {
 //!         Ok(v) => v,
@@ -247,10 +251,21 @@
 //! Applications can use these hints by artificially delaying the sending of
 //! packets through platform-specific mechanisms (such as the [`SO_TXTIME`]
 //! socket option on Linux), or custom methods (for example by using user-space
-//! timers).
+//! timers). The [`txtime()`] method returns the same hint in the format
+//! expected by `SO_TXTIME`, and the [`pacing_rate()`] method exposes the
+//! current pacing rate of the path.
+//!
+//! The way these hints are computed can be tuned with
+//! [`set_pacer_algorithm()`], [`set_max_pacing_burst()`] and
+//! [`set_pacing_gain()`].
 //!
 //! [pace]: https://datatracker.ietf.org/doc/html/rfc9002#section-7.7
 //! [`SO_TXTIME`]: https://man7.org/linux/man-pages/man8/tc-etf.8.html
+//! [`txtime()`]: struct.SendInfo.html#method.txtime
+//! [`pacing_rate()`]: struct.SendInfo.html#method.pacing_rate
+//! [`set_pacer_algorithm()`]: struct.Config.html#method.set_pacer_algorithm
+//! [`set_max_pacing_burst()`]: struct.Config.html#method.set_max_pacing_burst
+//! [`set_pacing_gain()`]: struct.Config.html#method.set_pacing_gain
 //!
 //! ## Sending and receiving stream data
 //!
@@ -427,8 +442,14 @@ use crate::recovery::ReleaseDecision;
 /// The current QUIC wire version.
 pub const PROTOCOL_VERSION: u32 = PROTOCOL_VERSION_V1;
 
-/// Supported QUIC versions.
-const PROTOCOL_VERSION_V1: u32 = 0x0000_0001;
+/// QUIC version 1 (RFC 9000).
+pub const PROTOCOL_VERSION_V1: u32 = 0x0000_0001;
+
+/// QUIC version 2 (RFC 9369).
+pub const PROTOCOL_VERSION_V2: u32 = 0x6b33_43cf;
+
+// Supported QUIC versions, in order of preference.
+const SUPPORTED_VERSIONS: [u32; 2] = [PROTOCOL_VERSION_V1, PROTOCOL_VERSION_V2];
 
 /// The maximum length of a connection ID.
 pub const MAX_CONN_ID_LEN: usize = crate::packet::MAX_CID_LEN as usize;
@@ -499,6 +520,18 @@ const DEFAULT_INITIAL_CONGESTION_WINDOW_PACKETS: usize = 10;
 // The maximum data offset that can be stored in a crypto stream.
 const MAX_CRYPTO_STREAM_OFFSET: u64 = 1 << 16;
 
+// The transport parameter advertising support for the multipath extension.
+const MULTIPATH_TRANSPORT_PARAM: u64 = 0x0f73_9bbc_1b66_6d05;
+
+// The transport parameter advertising support for the ACK frequency extension.
+const MIN_ACK_DELAY_TRANSPORT_PARAM: u64 = 0xff04_de1b;
+
+// The transport parameter used for compatible version negotiation.
+const VERSION_INFORMATION_TRANSPORT_PARAM: u64 = 0x0011;
+
+// The maximum number of segments the kernel accepts in a single UDP GSO send.
+const MAX_GSO_SEGMENTS: usize = 64;
+
 /// A specialized [`Result`] type for quiche operations.
 ///
 /// This type is used throughout quiche's public API for any operation that
@@ -580,6 +613,10 @@ pub enum Error {
 
     /// The peer sent more data in CRYPTO frames than we can buffer.
     CryptoBufferExceeded,
+
+    /// The peer's version information doesn't match the negotiated version,
+    /// e.g. because of a version downgrade attempt.
+    VersionNegotiation,
 }
 
 /// QUIC error codes sent on the wire.
@@ -646,6 +683,9 @@ pub enum WireErrorCode {
     /// CONNECTION_CLOSE frame carrying this code except when the path does
     /// not support a large enough MTU.
     NoViablePath         = 0x10,
+    /// An endpoint detected an attempt to downgrade the negotiated version,
+    /// or the version information sent by the peer was inconsistent.
+    VersionNegotiationError = 0x11,
 }
 
 impl Error {
@@ -664,6 +704,8 @@ impl Error {
             Error::CryptoBufferExceeded =>
                 WireErrorCode::CryptoBufferExceeded as u64,
             Error::KeyUpdate => WireErrorCode::KeyUpdateError as u64,
+            Error::VersionNegotiation =>
+                WireErrorCode::VersionNegotiationError as u64,
             _ => WireErrorCode::ProtocolViolation as u64,
         }
     }
@@ -691,6 +733,7 @@ impl Error {
             Error::OutOfIdentifiers => -18,
             Error::KeyUpdate => -19,
             Error::CryptoBufferExceeded => -20,
+            Error::VersionNegotiation => -21,
         }
     }
 }
@@ -721,6 +764,12 @@ pub struct RecvInfo {
 
     /// The local address the packet was received on.
     pub to: SocketAddr,
+
+    /// The ECN codepoint of the IP header of the packet.
+    ///
+    /// Applications that can't read it from the socket should use
+    /// [`Ecn::NotEct`].
+    pub ecn: Ecn,
 }
 
 /// Ancillary information about outgoing packets.
@@ -738,6 +787,53 @@ pub struct SendInfo {
     ///
     /// [Pacing]: index.html#pacing
     pub at: time::Instant,
+
+    /// The ECN codepoint the IP header of the packet should be marked with.
+    ///
+    /// See [`Config::set_ecn()`] for more details.
+    ///
+    /// [`Config::set_ecn()`]: struct.Config.html#method.set_ecn
+    pub ecn: Ecn,
+
+    pacing_rate: u64,
+}
+
+impl SendInfo {
+    /// Returns the pacing rate of the path the packet is sent on, in bytes/s.
+    ///
+    /// This can be used to configure kernel-level rate limiting (e.g. the
+    /// `SO_MAX_PACING_RATE` socket option on Linux).
+    pub fn pacing_rate(&self) -> u64 {
+        self.pacing_rate
+    }
+
+    /// Returns the time to send the packet out as nanoseconds of the
+    /// `CLOCK_MONOTONIC` clock.
+    ///
+    /// This is the format expected by the `SCM_TXTIME` control message on a
+    /// socket configured with the [`SO_TXTIME`] option and `CLOCK_MONOTONIC`
+    /// as clock, so that the kernel (e.g. the ETF qdisc) delays the packet
+    /// until [`at`] instead of the application.
+    ///
+    /// [`SO_TXTIME`]: https://man7.org/linux/man-pages/man8/tc-etf.8.html
+    /// [`at`]: struct.SendInfo.html#structfield.at
+    #[cfg(target_os = "linux")]
+    pub fn txtime(&self) -> u64 {
+        let mut now = libc::timespec {
+            tv_sec: 0,
+            tv_nsec: 0,
+        };
+
+        // `CLOCK_MONOTONIC` is always supported on Linux, so this can't fail.
+        unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut now) };
+
+        let now = time::Duration::new(now.tv_sec as u64, now.tv_nsec as u32);
+
+        // Packets that should have been sent already are sent right away.
+        let delay = self.at.saturating_duration_since(time::Instant::now());
+
+        (now + delay).as_nanos() as u64
+    }
 }
 
 /// Represents information carried by `CONNECTION_CLOSE` frames.
@@ -789,6 +885,8 @@ pub struct Config {
 
     version: u32,
 
+    versions: Vec<u32>,
+
     tls_ctx: tls::Context,
 
     application_protos: Vec<Vec<u8>>,
@@ -797,21 +895,30 @@ pub struct Config {
 
     cc_algorithm: CongestionControlAlgorithm,
     custom_bbr_params: Option<BbrParams>,
+    custom_cc: Option<recovery::CustomCongestionControl>,
+    stream_scheduler: Option<Arc<dyn StreamScheduler>>,
     initial_congestion_window_packets: usize,
 
     pmtud: bool,
 
+    ecn: Ecn,
+
     hystart: bool,
 
     pacing: bool,
     /// Send rate limit in Mbps
     max_pacing_rate: Option<u64>,
+    pacer_algorithm: PacerAlgorithm,
+    max_pacing_burst: Option<usize>,
+    pacing_gain: f64,
 
     dgram_recv_max_queue_len: usize,
     dgram_send_max_queue_len: usize,
 
     path_challenge_recv_max_queue_len: usize,
 
+    path_validation: path::ValidationConfig,
+
     max_send_udp_payload_size: usize,
 
     max_connection_window: u64,
@@ -822,6 +929,9 @@ pub struct Config {
     disable_dcid_reuse: bool,
 
     track_unknown_transport_params: Option<usize>,
+
+    ack_eliciting_threshold: Option<u64>,
+    requested_max_ack_delay: Option<time::Duration>,
 }
 
 // See https://quicwg.org/base-drafts/rfc9000.html#section-15
@@ -865,17 +975,24 @@ impl Config {
         Ok(Config {
             local_transport_params: TransportParams::default(),
             version,
+            versions: SUPPORTED_VERSIONS.to_vec(),
             tls_ctx,
             application_protos: Vec::new(),
             grease: true,
             cc_algorithm: CongestionControlAlgorithm::CUBIC,
             custom_bbr_params: None,
+            custom_cc: None,
+            stream_scheduler: None,
             initial_congestion_window_packets:
                 DEFAULT_INITIAL_CONGESTION_WINDOW_PACKETS,
             pmtud: false,
+            ecn: Ecn::NotEct,
             hystart: true,
             pacing: true,
             max_pacing_rate: None,
+            pacer_algorithm: PacerAlgorithm::LeakyBucket,
+            max_pacing_burst: None,
+            pacing_gain: recovery::PACING_MULTIPLIER,
 
             dgram_recv_max_queue_len: DEFAULT_MAX_DGRAM_QUEUE_LEN,
             dgram_send_max_queue_len: DEFAULT_MAX_DGRAM_QUEUE_LEN,
@@ -883,6 +1000,8 @@ impl Config {
             path_challenge_recv_max_queue_len:
                 DEFAULT_MAX_PATH_CHALLENGE_RX_QUEUE_LEN,
 
+            path_validation: path::ValidationConfig::default(),
+
             max_send_udp_payload_size: MAX_SEND_UDP_PAYLOAD_SIZE,
 
             max_connection_window: MAX_CONNECTION_WINDOW,
@@ -893,6 +1012,9 @@ impl Config {
             disable_dcid_reuse: false,
 
             track_unknown_transport_params: None,
+
+            ack_eliciting_threshold: None,
+            requested_max_ack_delay: None,
         })
     }
 
@@ -982,11 +1104,40 @@ impl Config {
 
     /// Configures whether to do path MTU discovery.
     ///
+    /// When enabled, each path starts with the minimum datagram size and
+    /// sends probes of increasing size, up to the configured maximum send
+    /// UDP payload size (see [`set_max_send_udp_payload_size()`]), only
+    /// using larger datagrams once they have been acknowledged.
+    ///
     /// The default value is `false`.
+    ///
+    /// [`set_max_send_udp_payload_size()`]: struct.Config.html#method.set_max_send_udp_payload_size
     pub fn discover_pmtu(&mut self, discover: bool) {
         self.pmtud = discover;
     }
 
+    /// Sets the ECN codepoint used to mark outgoing packets.
+    ///
+    /// When set to [`Ecn::Ect0`] or [`Ecn::Ect1`], packets are marked with
+    /// the given codepoint (see [`SendInfo.ecn`]) and ECN is validated on
+    /// each path, using the ECN counts reported by the peer in ACK frames.
+    /// Marking stops on paths where validation fails. Any other value
+    /// disables marking.
+    ///
+    /// Increases of the ECN-CE count reported by the peer are treated as
+    /// congestion signals.
+    ///
+    /// Regardless of this setting, the ECN codepoint of incoming packets
+    /// (see [`RecvInfo.ecn`]) is reported to the peer.
+    ///
+    /// The default value is [`Ecn::NotEct`].
+    ///
+    /// [`SendInfo.ecn`]: struct.SendInfo.html#structfield.ecn
+    /// [`RecvInfo.ecn`]: struct.RecvInfo.html#structfield.ecn
+    pub fn set_ecn(&mut self, ecn: Ecn) {
+        self.ecn = ecn;
+    }
+
     /// Configures whether to send GREASE values.
     ///
     /// The default value is `true`.
@@ -1025,6 +1176,45 @@ impl Config {
         self.tls_ctx.set_early_data_enabled(true);
     }
 
+    /// Configures the QUIC versions that can be used, in order of preference.
+    ///
+    /// On the client the first version is used for the initial packets, and
+    /// the others are candidates when the server answers with a version
+    /// negotiation packet.
+    ///
+    /// On the server only connections using one of these versions are
+    /// accepted.
+    ///
+    /// [`UnknownVersion`] is returned if the list is empty or includes an
+    /// unsupported version. The default is every supported version, starting
+    /// with [`PROTOCOL_VERSION_V1`].
+    ///
+    /// ## Examples:
+    ///
+    /// ```
+    /// # let mut config = quiche::Config::new(quiche::PROTOCOL_VERSION)?;
+    /// config.set_versions(&[
+    ///     quiche::PROTOCOL_VERSION_V2,
+    ///     quiche::PROTOCOL_VERSION_V1,
+    /// ])?;
+    /// # Ok::<(), quiche::Error>(())
+    /// ```
+    ///
+    /// [`UnknownVersion`]: enum.Error.html#variant.UnknownVersion
+    /// [`PROTOCOL_VERSION_V1`]: constant.PROTOCOL_VERSION_V1.html
+    pub fn set_versions(&mut self, versions: &[u32]) -> Result<()> {
+        if versions.is_empty() ||
+            !versions.iter().all(|&v| version_is_supported(v))
+        {
+            return Err(Error::UnknownVersion);
+        }
+
+        self.version = versions[0];
+        self.versions = versions.to_vec();
+
+        Ok(())
+    }
+
     /// Configures the list of supported application protocols.
     ///
     /// On the client this configures the list of protocols to send to the
@@ -1143,6 +1333,16 @@ impl Config {
             .initial_max_stream_data_bidi_local = v;
     }
 
+    /// Returns the `initial_max_stream_data_bidi_local` transport parameter.
+    ///
+    /// See [`set_initial_max_stream_data_bidi_local()`].
+    ///
+    /// [`set_initial_max_stream_data_bidi_local()`]: struct.Config.html#method.set_initial_max_stream_data_bidi_local
+    pub fn initial_max_stream_data_bidi_local(&self) -> u64 {
+        self.local_transport_params
+            .initial_max_stream_data_bidi_local
+    }
+
     /// Sets the `initial_max_stream_data_bidi_remote` transport parameter.
     ///
     /// When set to a non-zero value quiche will only allow at most `v` bytes
@@ -1161,6 +1361,16 @@ impl Config {
             .initial_max_stream_data_bidi_remote = v;
     }
 
+    /// Returns the `initial_max_stream_data_bidi_remote` transport parameter.
+    ///
+    /// See [`set_initial_max_stream_data_bidi_remote()`].
+    ///
+    /// [`set_initial_max_stream_data_bidi_remote()`]: struct.Config.html#method.set_initial_max_stream_data_bidi_remote
+    pub fn initial_max_stream_data_bidi_remote(&self) -> u64 {
+        self.local_transport_params
+            .initial_max_stream_data_bidi_remote
+    }
+
     /// Sets the `initial_max_stream_data_uni` transport parameter.
     ///
     /// When set to a non-zero value quiche will only allow at most `v` bytes
@@ -1177,6 +1387,15 @@ impl Config {
         self.local_transport_params.initial_max_stream_data_uni = v;
     }
 
+    /// Returns the `initial_max_stream_data_uni` transport parameter.
+    ///
+    /// See [`set_initial_max_stream_data_uni()`].
+    ///
+    /// [`set_initial_max_stream_data_uni()`]: struct.Config.html#method.set_initial_max_stream_data_uni
+    pub fn initial_max_stream_data_uni(&self) -> u64 {
+        self.local_transport_params.initial_max_stream_data_uni
+    }
+
     /// Sets the `initial_max_streams_bidi` transport parameter.
     ///
     /// When set to a non-zero value quiche will only allow `v` number of
@@ -1198,6 +1417,15 @@ impl Config {
         self.local_transport_params.initial_max_streams_bidi = v;
     }
 
+    /// Returns the `initial_max_streams_bidi` transport parameter.
+    ///
+    /// See [`set_initial_max_streams_bidi()`].
+    ///
+    /// [`set_initial_max_streams_bidi()`]: struct.Config.html#method.set_initial_max_streams_bidi
+    pub fn initial_max_streams_bidi(&self) -> u64 {
+        self.local_transport_params.initial_max_streams_bidi
+    }
+
     /// Sets the `initial_max_streams_uni` transport parameter.
     ///
     /// When set to a non-zero value quiche will only allow `v` number of
@@ -1217,6 +1445,15 @@ impl Config {
         self.local_transport_params.initial_max_streams_uni = v;
     }
 
+    /// Returns the `initial_max_streams_uni` transport parameter.
+    ///
+    /// See [`set_initial_max_streams_uni()`].
+    ///
+    /// [`set_initial_max_streams_uni()`]: struct.Config.html#method.set_initial_max_streams_uni
+    pub fn initial_max_streams_uni(&self) -> u64 {
+        self.local_transport_params.initial_max_streams_uni
+    }
+
     /// Sets the `ack_delay_exponent` transport parameter.
     ///
     /// The default value is `3`.
@@ -1247,6 +1484,56 @@ impl Config {
         self.local_transport_params.disable_active_migration = v;
     }
 
+    /// Configures whether to negotiate the multipath extension.
+    ///
+    /// When both endpoints enable it and neither uses zero-length connection
+    /// IDs, every path validated with [`probe_path()`] carries non-probing
+    /// packets alongside the others instead of only being a migration target.
+    ///
+    /// The default value is `false`.
+    ///
+    /// [`probe_path()`]: struct.Connection.html#method.probe_path
+    pub fn enable_multipath(&mut self, v: bool) {
+        self.local_transport_params.enable_multipath = v;
+    }
+
+    /// Sets the `min_ack_delay` transport parameter, in microseconds.
+    ///
+    /// Advertising it lets the peer control how often acknowledgements are
+    /// sent using ACK_FREQUENCY and IMMEDIATE_ACK frames. It must not be
+    /// larger than the `max_ack_delay` transport parameter.
+    ///
+    /// The default is not to advertise it.
+    pub fn set_min_ack_delay(&mut self, v: u64) {
+        self.local_transport_params.min_ack_delay = Some(v);
+    }
+
+    /// Sets the number of ack-eliciting packets the peer is asked to receive
+    /// before sending an acknowledgement immediately.
+    ///
+    /// The request is only sent if the peer advertises the `min_ack_delay`
+    /// transport parameter. By default no request is sent, unless
+    /// [`set_requested_max_ack_delay()`] is used, in which case the threshold
+    /// requested is `1`.
+    ///
+    /// [`set_requested_max_ack_delay()`]: struct.Config.html#method.set_requested_max_ack_delay
+    pub fn set_ack_eliciting_threshold(&mut self, v: u64) {
+        self.ack_eliciting_threshold = Some(v);
+    }
+
+    /// Sets the maximum delay the peer is asked to wait before acknowledging
+    /// packets.
+    ///
+    /// The request is only sent if the peer advertises the `min_ack_delay`
+    /// transport parameter, and the delay is raised to it if lower. By default
+    /// no request is sent, unless [`set_ack_eliciting_threshold()`] is used, in
+    /// which case the peer's own `max_ack_delay` is requested.
+    ///
+    /// [`set_ack_eliciting_threshold()`]: struct.Config.html#method.set_ack_eliciting_threshold
+    pub fn set_requested_max_ack_delay(&mut self, v: time::Duration) {
+        self.requested_max_ack_delay = Some(v);
+    }
+
     /// Sets the congestion control algorithm used.
     ///
     /// The default value is `CongestionControlAlgorithm::CUBIC`.
@@ -1268,6 +1555,37 @@ impl Config {
         self.custom_bbr_params = Some(custom_bbr_settings);
     }
 
+    /// Sets a congestion controller implemented outside of quiche.
+    ///
+    /// The custom controller takes precedence over the algorithm selected
+    /// with [`set_cc_algorithm()`]. `cc` is only used as a template, each path
+    /// of each connection gets its own controller created with
+    /// [`CongestionControl::new_instance()`].
+    ///
+    /// The default value is `None`.
+    ///
+    /// [`set_cc_algorithm()`]: struct.Config.html#method.set_cc_algorithm
+    /// [`CongestionControl::new_instance()`]: trait.CongestionControl.html#tymethod.new_instance
+    pub fn set_custom_cc(&mut self, cc: Box<dyn CongestionControl>) {
+        self.custom_cc = Some(recovery::CustomCongestionControl::new(cc));
+    }
+
+    /// Sets a scheduler implemented outside of quiche to decide which stream
+    /// sends data next.
+    ///
+    /// The custom scheduler replaces the default order, which is based on the
+    /// priorities set with [`Connection::stream_priority()`]. `scheduler` is
+    /// only used as a template, each connection gets its own scheduler created
+    /// with [`StreamScheduler::new_instance()`].
+    ///
+    /// The default value is `None`.
+    ///
+    /// [`Connection::stream_priority()`]: struct.Connection.html#method.stream_priority
+    /// [`StreamScheduler::new_instance()`]: trait.StreamScheduler.html#tymethod.new_instance
+    pub fn set_stream_scheduler(&mut self, scheduler: Box<dyn StreamScheduler>) {
+        self.stream_scheduler = Some(Arc::from(scheduler));
+    }
+
     /// Sets the congestion control algorithm used by string.
     ///
     /// The default value is `cubic`. On error `Error::CongestionControl`
@@ -1314,6 +1632,50 @@ impl Config {
         self.max_pacing_rate = Some(v);
     }
 
+    /// Sets the pacer implementation used.
+    ///
+    /// The default value is `PacerAlgorithm::LeakyBucket`. The pacer isn't
+    /// used by the `bbr2_gcongestion` congestion control algorithm.
+    pub fn set_pacer_algorithm(&mut self, algo: PacerAlgorithm) {
+        self.pacer_algorithm = algo;
+    }
+
+    /// Sets the pacer implementation used by string.
+    ///
+    /// The default value is `leaky_bucket`. On error `Error::CongestionControl`
+    /// will be returned.
+    ///
+    /// ## Examples:
+    ///
+    /// ```
+    /// # let mut config = quiche::Config::new(0xbabababa)?;
+    /// config.set_pacer_algorithm_name("token_bucket")?;
+    /// # Ok::<(), quiche::Error>(())
+    /// ```
+    pub fn set_pacer_algorithm_name(&mut self, name: &str) -> Result<()> {
+        self.pacer_algorithm = PacerAlgorithm::from_str(name)?;
+
+        Ok(())
+    }
+
+    /// Sets the max burst size of the pacer, in packets.
+    ///
+    /// By default the burst size is the send quantum of the congestion
+    /// control algorithm. Larger bursts allow a better utilization of links
+    /// with a large bandwidth-delay product, at the cost of burstier traffic.
+    pub fn set_max_pacing_burst(&mut self, packets: usize) {
+        self.max_pacing_burst = Some(packets.max(1));
+    }
+
+    /// Sets the pacing gain, i.e. the multiplier applied to the congestion
+    /// window over the smoothed RTT to compute the pacing rate.
+    ///
+    /// The default value is 1.25. The gain isn't used by congestion control
+    /// algorithms that compute their own pacing rate, such as BBR.
+    pub fn set_pacing_gain(&mut self, gain: f64) {
+        self.pacing_gain = gain;
+    }
+
     /// Configures whether to enable receiving DATAGRAM frames.
     ///
     /// When enabled, the `max_datagram_frame_size` transport parameter is set
@@ -1342,6 +1704,39 @@ impl Config {
         self.path_challenge_recv_max_queue_len = queue_len;
     }
 
+    /// Sets the number of PATH_CHALLENGE frames that can be lost before the
+    /// validation of a path is declared failed.
+    ///
+    /// The default value is 3.
+    pub fn set_max_path_challenges(&mut self, v: usize) {
+        self.path_validation.max_challenges = v;
+    }
+
+    /// Sets the time, in milliseconds, after which an unanswered
+    /// PATH_CHALLENGE frame is considered lost and a new one is sent.
+    ///
+    /// The default value is 0, meaning that PATH_CHALLENGE frames are only
+    /// considered lost when the loss detection timer fires.
+    pub fn set_path_challenge_retry_interval(&mut self, v: u64) {
+        self.path_validation.retry_interval = match v {
+            0 => None,
+            v => Some(Duration::from_millis(v)),
+        };
+    }
+
+    /// Sets the maximum duration, in milliseconds, of a path validation.
+    ///
+    /// Once it elapses, the validation is declared failed regardless of the
+    /// number of PATH_CHALLENGE frames that were sent.
+    ///
+    /// The default value is 0, meaning that there is no limit.
+    pub fn set_path_validation_timeout(&mut self, v: u64) {
+        self.path_validation.timeout = match v {
+            0 => None,
+            v => Some(Duration::from_millis(v)),
+        };
+    }
+
     /// Sets the maximum size of the connection window.
     ///
     /// The default value is MAX_CONNECTION_WINDOW (24MBytes).
@@ -1391,6 +1786,24 @@ impl Config {
     pub fn enable_track_unknown_transport_parameters(&mut self, size: usize) {
         self.track_unknown_transport_params = Some(size);
     }
+
+    /// Adds a custom transport parameter to the ones sent to the peer.
+    ///
+    /// The parameter is encoded after all the transport parameters known to
+    /// quiche, with `value` written as-is. No validation is performed, so
+    /// using an `id` that quiche already sends will cause the peer to see a
+    /// duplicate parameter. This is mostly useful for testing how peers react
+    /// to extensions they may not support.
+    pub fn add_custom_transport_parameter(&mut self, id: u64, value: &[u8]) {
+        self.local_transport_params
+            .unknown_params
+            .get_or_insert_with(Default::default)
+            .parameters
+            .push(UnknownTransportParameter {
+                id,
+                value: value.to_vec(),
+            });
+    }
 }
 
 /// A QUIC connection.
@@ -1401,6 +1814,21 @@ where
     /// QUIC wire version used for the connection.
     version: u32,
 
+    /// QUIC versions that can be used, in order of preference.
+    versions: Vec<u32>,
+
+    /// QUIC version of the client's first Initial packet, before any
+    /// compatible version negotiation took place.
+    original_version: u32,
+
+    /// The peer's ClientHello, buffered by the server until it can decide
+    /// whether to switch to a compatible version.
+    client_hello: Option<Vec<u8>>,
+
+    /// Initial packet protection keys of the original version, used by the
+    /// server after switching to a compatible version.
+    original_initial_open: Option<crypto::Open>,
+
     /// Connection Identifiers.
     ids: cid::ConnectionIdentifiers,
 
@@ -1438,12 +1866,38 @@ where
     /// The path manager.
     paths: path::PathMap,
 
+    /// Multipath extension state.
+    multipath: multipath::Multipath,
+
+    /// ACK frequency extension state.
+    ack_freq: ack_frequency::AckFrequency,
+
     /// PATH_CHALLENGE receive queue max length.
     path_challenge_recv_max_queue_len: usize,
 
+    /// Parameters of the path validation process.
+    path_validation: path::ValidationConfig,
+
     /// Total number of received PATH_CHALLENGE frames.
     path_challenge_rx_count: u64,
 
+    /// Total number of received DATA_BLOCKED frames.
+    data_blocked_rx_count: u64,
+
+    /// Total number of received STREAM_DATA_BLOCKED frames.
+    stream_data_blocked_rx_count: u64,
+
+    /// Total number of received STREAMS_BLOCKED frames for bidirectional
+    /// streams.
+    streams_blocked_bidi_rx_count: u64,
+
+    /// Total number of received STREAMS_BLOCKED frames for unidirectional
+    /// streams.
+    streams_blocked_uni_rx_count: u64,
+
+    /// Total number of sent packets the peer reported as CE-marked.
+    ecn_ce_count: u64,
+
     /// List of supported application protocols.
     application_protos: Vec<Vec<u8>>,
 
@@ -1474,6 +1928,12 @@ where
     /// Whether we send MAX_DATA frame.
     almost_full: bool,
 
+    /// Whether MAX_DATA, MAX_STREAM_DATA and MAX_STREAMS updates are withheld.
+    withhold_flow_control_updates: bool,
+
+    /// Frames queued by the application, sent as they are.
+    injected_frames: VecDeque<frame::Frame>,
+
     /// Number of stream data bytes that can be buffered.
     tx_cap: usize,
 
@@ -1820,7 +2280,7 @@ pub fn retry(
 /// Returns true if the given protocol version is supported.
 #[inline]
 pub fn version_is_supported(version: u32) -> bool {
-    matches!(version, PROTOCOL_VERSION_V1)
+    matches!(version, PROTOCOL_VERSION_V1 | PROTOCOL_VERSION_V2)
 }
 
 /// Pushes a frame to the output packet if there is enough space.
@@ -1943,7 +2403,7 @@ impl<F: BufFactory> Connection<F> {
         path.peer_verified_local_address = is_server;
 
         // Do not allocate more than the number of active CIDs.
-        let paths = path::PathMap::new(
+        let mut paths = path::PathMap::new(
             path,
             config.local_transport_params.active_conn_id_limit as usize,
             is_server,
@@ -1951,6 +2411,8 @@ impl<F: BufFactory> Connection<F> {
             config.max_send_udp_payload_size,
         );
 
+        paths.set_ecn(config.ecn);
+
         let active_path_id = paths.get_active_path_id()?;
 
         let ids = cid::ConnectionIdentifiers::new(
@@ -1963,6 +2425,14 @@ impl<F: BufFactory> Connection<F> {
         let mut conn = Connection {
             version: config.version,
 
+            versions: config.versions.clone(),
+
+            original_version: config.version,
+
+            client_hello: None,
+
+            original_initial_open: None,
+
             ids,
 
             trace_id: scid_as_hex.join(""),
@@ -1989,9 +2459,20 @@ impl<F: BufFactory> Connection<F> {
             recovery_config,
 
             paths,
+            multipath: multipath::Multipath::default(),
+            ack_freq: ack_frequency::AckFrequency::new(
+                config.ack_eliciting_threshold,
+                config.requested_max_ack_delay,
+            ),
             path_challenge_recv_max_queue_len: config
                 .path_challenge_recv_max_queue_len,
+            path_validation: config.path_validation,
             path_challenge_rx_count: 0,
+            data_blocked_rx_count: 0,
+            stream_data_blocked_rx_count: 0,
+            streams_blocked_bidi_rx_count: 0,
+            streams_blocked_uni_rx_count: 0,
+            ecn_ce_count: 0,
 
             application_protos: config.application_protos.clone(),
 
@@ -2014,6 +2495,10 @@ impl<F: BufFactory> Connection<F> {
             ),
             almost_full: false,
 
+            withhold_flow_control_updates: false,
+
+            injected_frames: VecDeque::new(),
+
             tx_cap: 0,
 
             tx_buffered: 0,
@@ -2117,13 +2602,17 @@ impl<F: BufFactory> Connection<F> {
             conn.did_retry = true;
         }
 
+        if let Some(scheduler) = &config.stream_scheduler {
+            conn.streams.set_scheduler(scheduler.new_instance());
+        }
+
         conn.local_transport_params.initial_source_connection_id =
             Some(conn.ids.get_scid(0)?.cid.to_vec().into());
 
         conn.handshake.init(is_server)?;
 
         conn.handshake
-            .use_legacy_codepoint(config.version != PROTOCOL_VERSION_V1);
+            .use_legacy_codepoint(!version_is_supported(config.version));
 
         conn.encode_transport_params()?;
 
@@ -2508,6 +2997,7 @@ impl<F: BufFactory> Connection<F> {
     ///     let recv_info = quiche::RecvInfo {
     ///         from,
     ///         to: local,
+    ///         ecn: quiche::Ecn::NotEct,
     ///     };
     ///
     ///     let read = match conn.recv(&mut buf[..read], recv_info) {
@@ -2522,6 +3012,90 @@ impl<F: BufFactory> Connection<F> {
     /// # Ok::<(), quiche::Error>(())
     /// ```
     pub fn recv(&mut self, buf: &mut [u8], info: RecvInfo) -> Result<usize> {
+        let done = self.recv_datagram(buf, info, time::Instant::now())?;
+
+        // Even though the packet was previously "accepted", it
+        // should be safe to forward the error, as it also comes
+        // from the `recv()` method.
+        self.process_undecrypted_0rtt_packets()?;
+
+        Ok(done)
+    }
+
+    /// Processes a batch of UDP datagrams received from the peer.
+    ///
+    /// This is equivalent to calling [`recv()`] for each buffer and
+    /// [`RecvInfo`] pair in `batch`, in order, but the per-call work is only
+    /// done once for the whole batch. This is useful when multiple datagrams
+    /// are read from the socket at once (e.g. with `recvmmsg()` or UDP Generic
+    /// Receive Offload).
+    ///
+    /// As outgoing packets are only generated by [`send()`], calling it after
+    /// processing the whole batch allows acknowledging all of the received
+    /// packets with a single ACK frame.
+    ///
+    /// Empty buffers in the batch are skipped. On success the total number of
+    /// bytes processed from the input buffers is returned. On error the
+    /// connection will be closed by calling [`close()`] with the appropriate
+    /// error code, and the remaining datagrams in the batch are not processed.
+    ///
+    /// [`recv()`]: struct.Connection.html#method.recv
+    /// [`RecvInfo`]: struct.RecvInfo.html
+    /// [`send()`]: struct.Connection.html#method.send
+    /// [`close()`]: struct.Connection.html#method.close
+    ///
+    /// ## Examples:
+    ///
+    /// ```no_run
+    /// # let mut bufs = [[0; 1500]; 2];
+    /// # let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    /// # let mut config = quiche::Config::new(quiche::PROTOCOL_VERSION)?;
+    /// # let scid = quiche::ConnectionId::from_ref(&[0xba; 16]);
+    /// # let peer = "127.0.0.1:1234".parse().unwrap();
+    /// # let local = socket.local_addr().unwrap();
+    /// # let mut conn = quiche::accept(&scid, None, local, peer, &mut config)?;
+    /// let mut batch = Vec::new();
+    ///
+    /// for buf in bufs.iter_mut() {
+    ///     let (read, from) = socket.recv_from(buf).unwrap();
+    ///
+    ///     let recv_info = quiche::RecvInfo {
+    ///         from,
+    ///         to: local,
+    ///         ecn: quiche::Ecn::NotEct,
+    ///     };
+    ///
+    ///     batch.push((&mut buf[..read], recv_info));
+    /// }
+    ///
+    /// conn.recv_batch(&mut batch)?;
+    /// # Ok::<(), quiche::Error>(())
+    /// ```
+    pub fn recv_batch(
+        &mut self, batch: &mut [(&mut [u8], RecvInfo)],
+    ) -> Result<usize> {
+        let now = time::Instant::now();
+
+        let mut done = 0;
+
+        for (buf, info) in batch.iter_mut() {
+            // Unlike `recv()`, don't fail the whole batch because of a single
+            // empty buffer.
+            if buf.is_empty() {
+                continue;
+            }
+
+            done += self.recv_datagram(buf, *info, now)?;
+        }
+
+        self.process_undecrypted_0rtt_packets()?;
+
+        Ok(done)
+    }
+
+    fn recv_datagram(
+        &mut self, buf: &mut [u8], info: RecvInfo, now: time::Instant,
+    ) -> Result<usize> {
         let len = buf.len();
 
         if len == 0 {
@@ -2568,6 +3142,7 @@ impl<F: BufFactory> Connection<F> {
                 &mut buf[len - left..len],
                 &info,
                 recv_pid,
+                now,
             ) {
                 Ok(v) => v,
 
@@ -2595,11 +3170,6 @@ impl<F: BufFactory> Connection<F> {
             left -= read;
         }
 
-        // Even though the packet was previously "accepted", it
-        // should be safe to forward the error, as it also comes
-        // from the `recv()` method.
-        self.process_undecrypted_0rtt_packets()?;
-
         Ok(done)
     }
 
@@ -2663,9 +3233,8 @@ impl<F: BufFactory> Connection<F> {
     /// [`Done`]: enum.Error.html#variant.Done
     fn recv_single(
         &mut self, buf: &mut [u8], info: &RecvInfo, recv_pid: Option<usize>,
+        now: time::Instant,
     ) -> Result<usize> {
-        let now = time::Instant::now();
-
         if buf.is_empty() {
             return Err(Error::Done);
         }
@@ -2729,24 +3298,14 @@ impl<F: BufFactory> Connection<F> {
                 return Err(Error::Done);
             }
 
-            let supported_versions =
-                versions.iter().filter(|&&v| version_is_supported(v));
-
-            let mut found_version = false;
+            // Pick the most preferred of our versions the server supports.
+            let found_version =
+                self.versions.iter().find(|v| versions.contains(v));
 
-            for &v in supported_versions {
-                found_version = true;
-
-                // The final version takes precedence over draft ones.
-                if v == PROTOCOL_VERSION_V1 {
-                    self.version = v;
-                    break;
-                }
-
-                self.version = cmp::max(self.version, v);
-            }
-
-            if !found_version {
+            if let Some(&v) = found_version {
+                self.version = v;
+                self.original_version = v;
+            } else {
                 // We don't support any of the versions offered.
                 //
                 // While a man-in-the-middle attacker might be able to
@@ -2778,7 +3337,7 @@ impl<F: BufFactory> Connection<F> {
                 Some(aead_seal);
 
             self.handshake
-                .use_legacy_codepoint(self.version != PROTOCOL_VERSION_V1);
+                .use_legacy_codepoint(!version_is_supported(self.version));
 
             // Encode transport parameters again, as the new version might be
             // using a different format.
@@ -2847,15 +3406,23 @@ impl<F: BufFactory> Connection<F> {
         }
 
         if self.is_server && !self.did_version_negotiation {
-            if !version_is_supported(hdr.version) {
+            if !self.versions.contains(&hdr.version) {
                 return Err(Error::UnknownVersion);
             }
 
             self.version = hdr.version;
+            self.original_version = hdr.version;
             self.did_version_negotiation = true;
 
+            // All supported versions are compatible with each other, so if
+            // a more preferred one exists, wait for the ClientHello to find
+            // out whether the client supports it as well.
+            if self.versions[0] != self.version {
+                self.client_hello = Some(Vec::new());
+            }
+
             self.handshake
-                .use_legacy_codepoint(self.version != PROTOCOL_VERSION_V1);
+                .use_legacy_codepoint(!version_is_supported(self.version));
 
             // Encode transport parameters again, as the new version might be
             // using a different format.
@@ -2863,15 +3430,51 @@ impl<F: BufFactory> Connection<F> {
         }
 
         if hdr.ty != packet::Type::Short && hdr.version != self.version {
-            // At this point version negotiation was already performed, so
-            // ignore packets that don't match the connection's version.
-            return Err(Error::Done);
-        }
-
-        // Long header packets have an explicit payload length, but short
-        // packets don't so just use the remaining capacity in the buffer.
-        let payload_len = if hdr.ty == packet::Type::Short {
-            b.cap()
+            if self.is_server &&
+                hdr.ty == packet::Type::Initial &&
+                hdr.version == self.original_version &&
+                self.original_initial_open.is_some()
+            {
+                // The client keeps using the original version until it
+                // receives our first packet.
+            } else if !self.is_server &&
+                hdr.ty == packet::Type::Initial &&
+                !self.got_peer_conn_id &&
+                self.versions.contains(&hdr.version)
+            {
+                // The server switched to a compatible version, follow it.
+                trace!(
+                    "{} compatible version negotiation {:#x} -> {:#x}",
+                    self.trace_id,
+                    self.version,
+                    hdr.version
+                );
+
+                let (aead_open, aead_seal) = crypto::derive_initial_key_material(
+                    &self.destination_id(),
+                    hdr.version,
+                    self.is_server,
+                    true,
+                )?;
+
+                self.pkt_num_spaces[packet::Epoch::Initial].crypto_open =
+                    Some(aead_open);
+                self.pkt_num_spaces[packet::Epoch::Initial].crypto_seal =
+                    Some(aead_seal);
+
+                self.version = hdr.version;
+            } else {
+                // At this point version negotiation was already performed,
+                // so ignore packets that don't match the connection's
+                // version.
+                return Err(Error::Done);
+            }
+        }
+
+        // Long header packets have an explicit payload length, but short
+        // packets don't so just use the remaining capacity in the buffer.
+        let payload_len = if hdr.ty == packet::Type::Short {
+            b.cap()
         } else {
             b.get_varint().map_err(|e| {
                 drop_pkt_on_err(
@@ -2918,6 +3521,9 @@ impl<F: BufFactory> Connection<F> {
         let aead = if hdr.ty == packet::Type::ZeroRTT {
             // Only use 0-RTT key if incoming packet is 0-RTT.
             self.pkt_num_spaces[epoch].crypto_0rtt_open.as_ref()
+        } else if hdr.version != self.version && hdr.ty != packet::Type::Short {
+            // Initial packets still using the original version.
+            self.original_initial_open.as_ref()
         } else {
             // Otherwise use the packet number space's main key.
             self.pkt_num_spaces[epoch].crypto_open.as_ref()
@@ -2961,8 +3567,43 @@ impl<F: BufFactory> Connection<F> {
             drop_pkt_on_err(e, self.recv_count, self.is_server, &self.trace_id)
         })?;
 
+        // With multipath, 1-RTT packets are numbered in the packet number space
+        // of their path, identified by the sequence number of the connection ID
+        // they were sent to.
+        let mp_path_id =
+            if self.multipath.enabled() && hdr.ty == packet::Type::Short {
+                self.ids.find_scid_seq(&hdr.dcid).map(|(seq, _)| seq)
+            } else {
+                None
+            };
+
+        let largest_rx_pkt_num = match mp_path_id {
+            Some(id) => match self.multipath.space(id) {
+                Some(space) => space.largest_rx_pkt_num,
+
+                // Path 0 continues the packet numbers used before the
+                // extension was negotiated, other paths start from 0.
+                None if id == 0 => self.pkt_num_spaces[epoch].largest_rx_pkt_num,
+
+                None => 0,
+            },
+
+            None => self.pkt_num_spaces[epoch].largest_rx_pkt_num,
+        };
+
+        // Path IDs are limited to 32 bits, as they are part of the nonce.
+        let nonce_path_id =
+            u32::try_from(mp_path_id.unwrap_or(0)).map_err(|_| {
+                drop_pkt_on_err(
+                    Error::InvalidPacket,
+                    self.recv_count,
+                    self.is_server,
+                    &self.trace_id,
+                )
+            })?;
+
         let pn = packet::decode_pkt_num(
-            self.pkt_num_spaces[epoch].largest_rx_pkt_num,
+            largest_rx_pkt_num,
             hdr.pkt_num,
             hdr.pkt_num_len,
         );
@@ -3021,6 +3662,7 @@ impl<F: BufFactory> Connection<F> {
 
         let mut payload = packet::decrypt_pkt(
             &mut b,
+            nonce_path_id,
             pn,
             pn_len,
             payload_len,
@@ -3030,7 +3672,13 @@ impl<F: BufFactory> Connection<F> {
             drop_pkt_on_err(e, self.recv_count, self.is_server, &self.trace_id)
         })?;
 
-        if self.pkt_num_spaces[epoch].recv_pkt_num.contains(pn) {
+        let duplicate = match mp_path_id {
+            Some(id) => self.multipath.space_mut(id).recv_pkt_num.contains(pn),
+
+            None => self.pkt_num_spaces[epoch].recv_pkt_num.contains(pn),
+        };
+
+        if duplicate {
             trace!("{} ignored duplicate packet {}", self.trace_id, pn);
             return Err(Error::Done);
         }
@@ -3109,6 +3757,19 @@ impl<F: BufFactory> Connection<F> {
             });
         }
 
+        // After a locally initiated key update, the first packet the peer
+        // protects with the new keys marks the end of the previous key phase.
+        if self.handshake_confirmed &&
+            hdr.ty == Type::Short &&
+            hdr.key_phase == self.key_phase
+        {
+            if let Some(key_update) =
+                self.pkt_num_spaces[epoch].key_update.as_mut()
+            {
+                key_update.pn_on_update = key_update.pn_on_update.min(pn);
+            }
+        }
+
         if !self.is_server && !self.got_peer_conn_id {
             if self.odcid.is_none() {
                 self.odcid = Some(self.destination_id().into_owned());
@@ -3232,22 +3893,23 @@ impl<F: BufFactory> Connection<F> {
             });
         }
 
-        // Following flag used to upgrade datagram size, if probe is successful.
-        let mut pmtud_probe = false;
+        // The maximum ACK delay used by the peer, once it acknowledged our
+        // ACK_FREQUENCY frame.
+        let mut ack_freq_max_ack_delay = None;
 
         // Process acked frames. Note that several packets from several paths
         // might have been acked by the received packet.
         for (_, p) in self.paths.iter_mut() {
+            // Following flag used to upgrade datagram size, if probe is
+            // successful.
+            let mut pmtud_probe = false;
+
             for acked in p.recovery.get_acked_frames(epoch) {
                 match acked {
                     frame::Frame::Ping {
                         mtu_probe: Some(mtu_probe),
                     } => {
-                        let pmtud_next = p.pmtud.get_current();
-                        p.pmtud.set_current(cmp::max(pmtud_next, mtu_probe));
-
-                        // Stop sending path MTU probes after successful probe.
-                        p.pmtud.should_probe(false);
+                        p.pmtud.on_probe_acked(mtu_probe);
                         pmtud_probe = true;
 
                         trace!(
@@ -3268,6 +3930,19 @@ impl<F: BufFactory> Connection<F> {
                         }
                     },
 
+                    frame::Frame::PathAck {
+                        path_id, ranges, ..
+                    } =>
+                        if let Some(largest_acked) = ranges.last() {
+                            self.multipath
+                                .on_path_ack_acked(path_id, largest_acked);
+                        },
+
+                    frame::Frame::AckFrequency { seq_num, .. } => {
+                        ack_freq_max_ack_delay =
+                            self.ack_freq.on_request_acked(seq_num);
+                    },
+
                     frame::Frame::CryptoHeader { offset, length } => {
                         self.pkt_num_spaces[epoch]
                             .crypto_stream
@@ -3348,8 +4023,8 @@ impl<F: BufFactory> Connection<F> {
             if pmtud_probe {
                 trace!(
                     "{} updating pmtu {:?}",
-                    p.pmtud.get_current(),
-                    self.trace_id
+                    self.trace_id,
+                    p.pmtud.get_current()
                 );
 
                 qlog_with_type!(
@@ -3363,7 +4038,7 @@ impl<F: BufFactory> Connection<F> {
                             qlog::events::connectivity::MtuUpdated {
                                 old: Some(p.recovery.max_datagram_size() as u16),
                                 new: p.pmtud.get_current() as u16,
-                                done: Some(pmtud_probe),
+                                done: Some(!p.pmtud.get_probe_status()),
                             },
                         );
 
@@ -3376,6 +4051,14 @@ impl<F: BufFactory> Connection<F> {
             }
         }
 
+        if let Some(max_ack_delay) = ack_freq_max_ack_delay {
+            self.recovery_config.max_ack_delay = max_ack_delay;
+
+            for (_, p) in self.paths.iter_mut() {
+                p.recovery.update_max_ack_delay(max_ack_delay);
+            }
+        }
+
         // Now that we processed all the frames, if there is a path that has no
         // Destination CID, try to allocate one.
         let no_dcid = self
@@ -3399,21 +4082,45 @@ impl<F: BufFactory> Connection<F> {
             p.active_dcid_seq = Some(dcid_seq);
         }
 
-        // We only record the time of arrival of the largest packet number
-        // that still needs to be acked, to be used for ACK delay calculation.
-        if self.pkt_num_spaces[epoch].recv_pkt_need_ack.last() < Some(pn) {
-            self.pkt_num_spaces[epoch].largest_rx_pkt_time = now;
-        }
+        if let Some(id) = mp_path_id {
+            self.multipath.space_mut(id).on_packet_received(
+                pn,
+                ack_elicited,
+                info.ecn,
+                now,
+            );
+        } else {
+            // We only record the time of arrival of the largest packet number
+            // that still needs to be acked, to be used for ACK delay
+            // calculation.
+            if self.pkt_num_spaces[epoch].recv_pkt_need_ack.last() < Some(pn) {
+                self.pkt_num_spaces[epoch].largest_rx_pkt_time = now;
+            }
+
+            self.pkt_num_spaces[epoch].recv_pkt_num.insert(pn);
+
+            self.pkt_num_spaces[epoch].recv_pkt_need_ack.push_item(pn);
 
-        self.pkt_num_spaces[epoch].recv_pkt_num.insert(pn);
+            self.pkt_num_spaces[epoch]
+                .ecn_counts
+                .on_packet_received(info.ecn);
 
-        self.pkt_num_spaces[epoch].recv_pkt_need_ack.push_item(pn);
+            // Application data packets might not need to be acknowledged right
+            // away, depending on what the peer requested with ACK_FREQUENCY.
+            let ack_elicited = ack_elicited &&
+                (epoch != packet::Epoch::Application ||
+                    self.ack_freq.on_ack_eliciting_received(
+                        pn,
+                        self.pkt_num_spaces[epoch].largest_rx_pkt_num,
+                        now,
+                    ));
 
-        self.pkt_num_spaces[epoch].ack_elicited =
-            cmp::max(self.pkt_num_spaces[epoch].ack_elicited, ack_elicited);
+            self.pkt_num_spaces[epoch].ack_elicited =
+                cmp::max(self.pkt_num_spaces[epoch].ack_elicited, ack_elicited);
 
-        self.pkt_num_spaces[epoch].largest_rx_pkt_num =
-            cmp::max(self.pkt_num_spaces[epoch].largest_rx_pkt_num, pn);
+            self.pkt_num_spaces[epoch].largest_rx_pkt_num =
+                cmp::max(self.pkt_num_spaces[epoch].largest_rx_pkt_num, pn);
+        }
 
         if !probing {
             self.pkt_num_spaces[epoch].largest_rx_non_probing_pkt_num = cmp::max(
@@ -3424,7 +4131,10 @@ impl<F: BufFactory> Connection<F> {
             // Did the peer migrated to another path?
             let active_path_id = self.paths.get_active_path_id()?;
 
+            // With multipath, non-probing packets are expected on every path
+            // in use, so they don't denote a migration.
             if self.is_server &&
+                !self.multipath.enabled() &&
                 recv_pid != active_path_id &&
                 self.pkt_num_spaces[epoch].largest_rx_non_probing_pkt_num == pn
             {
@@ -3661,7 +4371,7 @@ impl<F: BufFactory> Connection<F> {
         let send_path = self.paths.get_mut(send_pid)?;
 
         // Update max datagram size to allow path MTU discovery probe to be sent.
-        if send_path.pmtud.get_probe_status() {
+        if send_path.pmtud.should_send_probe() {
             let size = if self.handshake_confirmed || self.handshake_done_sent {
                 send_path.pmtud.get_probe_size()
             } else {
@@ -3679,6 +4389,9 @@ impl<F: BufFactory> Connection<F> {
             left = cmp::min(left, send_path.max_send_bytes);
         }
 
+        // All packets coalesced in the datagram share its ECN codepoint.
+        let ecn = send_path.ecn.begin_datagram();
+
         // Generate coalesced packets.
         while left > 0 {
             let (ty, written) = match self.send_single(
@@ -3747,11 +4460,126 @@ impl<F: BufFactory> Connection<F> {
             to: send_path.peer_addr(),
 
             at: send_path.recovery.get_packet_send_time(),
+
+            ecn,
+
+            pacing_rate: send_path.recovery.pacing_rate(),
         };
 
         Ok((done, info))
     }
 
+    /// Writes multiple QUIC packets to be sent to the peer as a batch of
+    /// equally sized UDP datagrams.
+    ///
+    /// Datagrams are written back to back in the `out` buffer. All of them
+    /// are the same size, except for the last one that can be shorter, and
+    /// they share the same [`SendInfo`], so that the whole buffer can be sent
+    /// with a single system call using UDP Generic Segmentation Offload
+    /// (e.g. the `UDP_SEGMENT` socket option on Linux).
+    ///
+    /// On success the total number of bytes written, the segment size (i.e.
+    /// the size of each datagram) and the [`SendInfo`] of the batch are
+    /// returned. The [`at`] field of the [`SendInfo`] refers to the first
+    /// datagram, so applications should limit the size of `out` (for example
+    /// to the value returned by [`send_quantum()`]) when pacing.
+    ///
+    /// Writing stops when `out` is full, when a datagram shorter than the
+    /// segment size is written, or when the next datagram can't be part of
+    /// the same batch. At most 64 datagrams are written in a single call.
+    ///
+    /// Other than that, this method behaves like [`send()`].
+    ///
+    /// [`SendInfo`]: struct.SendInfo.html
+    /// [`at`]: struct.SendInfo.html#structfield.at
+    /// [`send_quantum()`]: struct.Connection.html#method.send_quantum
+    /// [`send()`]: struct.Connection.html#method.send
+    ///
+    /// ## Examples:
+    ///
+    /// ```no_run
+    /// # let mut out = [0; 65535];
+    /// # let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    /// # let mut config = quiche::Config::new(quiche::PROTOCOL_VERSION)?;
+    /// # let scid = quiche::ConnectionId::from_ref(&[0xba; 16]);
+    /// # let peer = "127.0.0.1:1234".parse().unwrap();
+    /// # let local = socket.local_addr().unwrap();
+    /// # let mut conn = quiche::accept(&scid, None, local, peer, &mut config)?;
+    /// loop {
+    ///     let (write, segment_size, send_info) =
+    ///         match conn.send_multiple(&mut out) {
+    ///             Ok(v) => v,
+    ///
+    ///             Err(quiche::Error::Done) => {
+    ///                 // Done writing.
+    ///                 break;
+    ///             },
+    ///
+    ///             Err(e) => {
+    ///                 // An error occurred, handle it.
+    ///                 break;
+    ///             },
+    ///         };
+    ///
+    ///     // Send `out[..write]` with GSO, or one segment at a time.
+    ///     for dgram in out[..write].chunks(segment_size) {
+    ///         socket.send_to(dgram, &send_info.to).unwrap();
+    ///     }
+    /// }
+    /// # Ok::<(), quiche::Error>(())
+    /// ```
+    pub fn send_multiple(
+        &mut self, out: &mut [u8],
+    ) -> Result<(usize, usize, SendInfo)> {
+        let (segment_size, info) = self.send(out)?;
+
+        let mut done = segment_size;
+        let mut segments = 1;
+
+        // Following datagrams can't be larger than the first one, so only
+        // keep going when it was as large as possible.
+        if segment_size < self.max_send_udp_payload_size() {
+            return Ok((done, segment_size, info));
+        }
+
+        let send_pid = self
+            .paths
+            .path_id_from_addrs(&(info.from, info.to))
+            .ok_or(Error::InvalidState)?;
+
+        while segments < MAX_GSO_SEGMENTS && done < out.len() {
+            // All datagrams in the batch are marked with the same ECN
+            // codepoint.
+            if self.paths.get(send_pid)?.ecn.next_codepoint() != info.ecn {
+                break;
+            }
+
+            let end = cmp::min(done + segment_size, out.len());
+
+            let written = match self.send_on_path(
+                &mut out[done..end],
+                Some(info.from),
+                Some(info.to),
+            ) {
+                Ok((v, _)) => v,
+
+                Err(Error::Done) => break,
+
+                Err(e) => return Err(e),
+            };
+
+            done += written;
+            segments += 1;
+
+            // Only the last datagram can be shorter than the segment size.
+            if written < segment_size {
+                break;
+            }
+        }
+
+        Ok((done, segment_size, info))
+    }
+
     fn send_single(
         &mut self, out: &mut [u8], send_pid: usize, has_initial: bool,
         now: time::Instant,
@@ -3837,6 +4665,28 @@ impl<F: BufFactory> Connection<F> {
                         pkt_space.ack_elicited = true;
                     },
 
+                    frame::Frame::PathAck { path_id, .. } => {
+                        self.multipath.on_path_ack_lost(path_id);
+                    },
+
+                    frame::Frame::PathAbandon {
+                        path_id,
+                        error_code,
+                        reason,
+                    } => {
+                        self.multipath.abandon(path_id, error_code, reason);
+                    },
+
+                    frame::Frame::PathStatus {
+                        path_id, seq_num, ..
+                    } => {
+                        self.multipath.on_status_lost(path_id, seq_num);
+                    },
+
+                    frame::Frame::AckFrequency { seq_num, .. } => {
+                        self.ack_freq.on_request_lost(seq_num);
+                    },
+
                     frame::Frame::ResetStream {
                         stream_id,
                         error_code,
@@ -3871,8 +4721,10 @@ impl<F: BufFactory> Connection<F> {
                         self.ids.mark_retire_dcid_seq(seq_num, true)?;
                     },
 
-                    frame::Frame::Ping { mtu_probe } if mtu_probe.is_some() => {
-                        p.pmtud.pmtu_probe_lost();
+                    frame::Frame::Ping {
+                        mtu_probe: Some(mtu_probe),
+                    } => {
+                        p.pmtud.on_probe_lost(mtu_probe);
                     },
 
                     _ => (),
@@ -3893,7 +4745,26 @@ impl<F: BufFactory> Connection<F> {
             b.cap()
         };
 
-        let pn = self.next_pkt_num;
+        let dcid_seq = path.active_dcid_seq.ok_or(Error::OutOfIdentifiers)?;
+
+        // With multipath, 1-RTT packets are numbered in the packet number space
+        // of their path, identified by the sequence number of the connection ID
+        // they are sent to. Path 0 keeps using the connection-wide counter.
+        let mp_path_id = (self.multipath.enabled() &&
+            pkt_type == packet::Type::Short &&
+            dcid_seq != 0)
+            .then_some(dcid_seq);
+
+        // The path ID is part of the nonce, so it must fit in 32 bits.
+        let nonce_path_id = u32::try_from(mp_path_id.unwrap_or(0))
+            .map_err(|_| Error::InvalidState)?;
+
+        let pn = match mp_path_id {
+            Some(id) => self.multipath.next_pkt_num(id),
+
+            None => self.next_pkt_num,
+        };
+
         let largest_acked_pkt =
             path.recovery.get_largest_acked_on_epoch(epoch).unwrap_or(0);
         let pn_len = packet::pkt_num_len(pn, largest_acked_pkt);
@@ -3901,8 +4772,6 @@ impl<F: BufFactory> Connection<F> {
         // The AEAD overhead at the current encryption level.
         let crypto_overhead = pkt_space.crypto_overhead().ok_or(Error::Done)?;
 
-        let dcid_seq = path.active_dcid_seq.ok_or(Error::OutOfIdentifiers)?;
-
         let dcid =
             ConnectionId::from_ref(self.ids.get_dcid(dcid_seq)?.cid.as_ref());
 
@@ -4046,7 +4915,10 @@ impl<F: BufFactory> Connection<F> {
             let frame = frame::Frame::ACK {
                 ack_delay,
                 ranges: pkt_space.recv_pkt_need_ack.clone(),
-                ecn_counts: None, // sending ECN is not supported at this time
+                ecn_counts: pkt_space
+                    .ecn_counts
+                    .any()
+                    .then(|| pkt_space.ecn_counts.clone()),
             };
 
             // When a PING frame needs to be sent, avoid sending the ACK if
@@ -4059,6 +4931,47 @@ impl<F: BufFactory> Connection<F> {
                 // available cwnd.
                 if push_frame_to_pkt!(b, frames, frame, left) {
                     pkt_space.ack_elicited = false;
+
+                    if epoch == packet::Epoch::Application {
+                        self.ack_freq.on_ack_sent();
+                    }
+                }
+            }
+        }
+
+        // Create PATH_ACK frames, one for each path packet number space that
+        // has packets to acknowledge. They can be sent on any path.
+        if pkt_type == packet::Type::Short && !is_closing && path.active() {
+            for (&path_id, space) in self.multipath.spaces_mut() {
+                if space.recv_pkt_need_ack.len() == 0 ||
+                    !(space.ack_elicited || ack_elicit_required)
+                {
+                    continue;
+                }
+
+                let ack_delay = space.largest_rx_pkt_time.elapsed();
+
+                let ack_delay = ack_delay.as_micros() as u64 /
+                    2_u64.pow(
+                        self.local_transport_params.ack_delay_exponent as u32,
+                    );
+
+                let frame = frame::Frame::PathAck {
+                    path_id,
+                    ack_delay,
+                    ranges: space.recv_pkt_need_ack.clone(),
+                    ecn_counts: space
+                        .ecn_counts
+                        .any()
+                        .then(|| space.ecn_counts.clone()),
+                };
+
+                if space.ack_elicited || frame.wire_len() < cwnd_available {
+                    if push_frame_to_pkt!(b, frames, frame, left) {
+                        space.ack_elicited = false;
+                    } else {
+                        break;
+                    }
                 }
             }
         }
@@ -4072,7 +4985,7 @@ impl<F: BufFactory> Connection<F> {
 
         let mut challenge_data = None;
 
-        let active_path = self.paths.get_active_mut()?;
+        let send_path = self.paths.get_mut(send_pid)?;
 
         if pkt_type == packet::Type::Short {
             // Create PMTUD probe.
@@ -4087,27 +5000,27 @@ impl<F: BufFactory> Connection<F> {
             // is confirmed, to avoid interfering with the handshake
             // (e.g. due to the anti-amplification limits).
 
-            let pmtu_probe = active_path.should_send_pmtu_probe(
+            let pmtu_probe = send_path.should_send_pmtu_probe(
                 self.handshake_confirmed,
-                self.handshake_done_sent,
+                self.handshake_done_sent || !self.is_server,
                 out_len,
                 is_closing,
                 frames.is_empty(),
             );
 
             trace!("{} pmtud probe status {} hs_con={} hs_sent={} cwnd_avail={} out_len={} left={}", self.trace_id, pmtu_probe, self.handshake_confirmed, self.handshake_done_sent,
-                    active_path.recovery.cwnd_available(), out_len, left);
+                    send_path.recovery.cwnd_available(), out_len, left);
 
             if pmtu_probe {
                 trace!(
                     "{} sending pmtud probe pmtu_probe={} next_size={} pmtu={}",
                     self.trace_id,
-                    active_path.pmtud.get_probe_size(),
-                    active_path.pmtud.get_probe_status(),
-                    active_path.pmtud.get_current(),
+                    send_path.pmtud.get_probe_size(),
+                    send_path.pmtud.get_probe_status(),
+                    send_path.pmtud.get_current(),
                 );
 
-                left = active_path.pmtud.get_probe_size();
+                left = send_path.pmtud.get_probe_size();
 
                 match left.checked_sub(overhead) {
                     Some(v) => left = v,
@@ -4121,18 +5034,18 @@ impl<F: BufFactory> Connection<F> {
                         //
                         // In such case app_limited is set to false here to make
                         // cwnd grow when ACK is received.
-                        active_path.recovery.update_app_limited(false);
+                        send_path.recovery.update_app_limited(false);
                         return Err(Error::Done);
                     },
                 }
 
                 let frame = frame::Frame::Padding {
-                    len: active_path.pmtud.get_probe_size() - overhead - 1,
+                    len: send_path.pmtud.get_probe_size() - overhead - 1,
                 };
 
                 if push_frame_to_pkt!(b, frames, frame, left) {
                     let frame = frame::Frame::Ping {
-                        mtu_probe: Some(active_path.pmtud.get_probe_size()),
+                        mtu_probe: Some(send_path.pmtud.get_probe_size()),
                     };
 
                     if push_frame_to_pkt!(b, frames, frame, left) {
@@ -4141,6 +5054,8 @@ impl<F: BufFactory> Connection<F> {
                     }
                 }
 
+                send_path.pmtud.on_probe_sent();
+
                 pmtud_probe = true;
             }
 
@@ -4217,7 +5132,9 @@ impl<F: BufFactory> Connection<F> {
             }
 
             // Create MAX_STREAMS_BIDI frame.
-            if self.streams.should_update_max_streams_bidi() {
+            if self.streams.should_update_max_streams_bidi() &&
+                !self.withhold_flow_control_updates
+            {
                 let frame = frame::Frame::MaxStreamsBidi {
                     max: self.streams.max_streams_bidi_next(),
                 };
@@ -4231,7 +5148,9 @@ impl<F: BufFactory> Connection<F> {
             }
 
             // Create MAX_STREAMS_UNI frame.
-            if self.streams.should_update_max_streams_uni() {
+            if self.streams.should_update_max_streams_uni() &&
+                !self.withhold_flow_control_updates
+            {
                 let frame = frame::Frame::MaxStreamsUni {
                     max: self.streams.max_streams_uni_next(),
                 };
@@ -4257,7 +5176,11 @@ impl<F: BufFactory> Connection<F> {
             }
 
             // Create MAX_STREAM_DATA frames as needed.
-            for stream_id in self.streams.almost_full() {
+            for stream_id in self
+                .streams
+                .almost_full()
+                .filter(|_| !self.withhold_flow_control_updates)
+            {
                 let stream = match self.streams.get_mut(stream_id) {
                     Some(v) => v,
 
@@ -4301,6 +5224,7 @@ impl<F: BufFactory> Connection<F> {
 
             // Create MAX_DATA frame as needed.
             if self.almost_full &&
+                !self.withhold_flow_control_updates &&
                 flow_control.max_data() < flow_control.max_data_next()
             {
                 // Autotune the connection window size.
@@ -4321,6 +5245,18 @@ impl<F: BufFactory> Connection<F> {
                 }
             }
 
+            // Create frames queued by the application.
+            while let Some(frame) = self.injected_frames.front() {
+                if !push_frame_to_pkt!(b, frames, frame.clone(), left) {
+                    break;
+                }
+
+                self.injected_frames.pop_front();
+
+                ack_eliciting = true;
+                in_flight = true;
+            }
+
             // Create STOP_SENDING frames as needed.
             for (stream_id, error_code) in self
                 .streams
@@ -4401,6 +5337,74 @@ impl<F: BufFactory> Connection<F> {
                     break;
                 }
             }
+
+            // Create PATH_ABANDON frames as needed.
+            while let Some((path_id, abandon)) = self.multipath.next_abandon() {
+                let frame = frame::Frame::PathAbandon {
+                    path_id,
+                    error_code: abandon.error_code,
+                    reason: abandon.reason,
+                };
+
+                if push_frame_to_pkt!(b, frames, frame, left) {
+                    self.multipath.on_abandon_sent(path_id);
+
+                    ack_eliciting = true;
+                    in_flight = true;
+                } else {
+                    break;
+                }
+            }
+
+            // Create PATH_STATUS frames as needed.
+            while let Some((path_id, seq_num, status)) =
+                self.multipath.next_status()
+            {
+                let frame = frame::Frame::PathStatus {
+                    path_id,
+                    seq_num,
+                    status: status.to_wire(),
+                };
+
+                if push_frame_to_pkt!(b, frames, frame, left) {
+                    self.multipath.on_status_sent(path_id);
+
+                    ack_eliciting = true;
+                    in_flight = true;
+                } else {
+                    break;
+                }
+            }
+
+            // Create ACK_FREQUENCY frame.
+            if let Some(request) = self.ack_freq.next_request() {
+                let frame = frame::Frame::AckFrequency {
+                    seq_num: request.seq_num,
+                    ack_eliciting_threshold: request.ack_eliciting_threshold,
+                    request_max_ack_delay: request.max_ack_delay.as_micros()
+                        as u64,
+                    reordering_threshold: request.reordering_threshold,
+                };
+
+                if push_frame_to_pkt!(b, frames, frame, left) {
+                    self.ack_freq.on_request_sent();
+
+                    ack_eliciting = true;
+                    in_flight = true;
+                }
+            }
+
+            // Create IMMEDIATE_ACK frame.
+            if self.ack_freq.immediate_ack_pending() {
+                let frame = frame::Frame::ImmediateAck;
+
+                if push_frame_to_pkt!(b, frames, frame, left) {
+                    self.ack_freq.on_immediate_ack_sent();
+
+                    ack_eliciting = true;
+                    in_flight = true;
+                }
+            }
         }
 
         // Create CONNECTION_CLOSE frame. Try to send this only on the active
@@ -4698,14 +5702,28 @@ impl<F: BufFactory> Connection<F> {
                 }
 
                 let priority_key = Arc::clone(&stream.priority_key);
+
+                qlog_with_type!(QLOG_DATA_MV, self.qlog, q, {
+                    let ev_data =
+                        EventData::DataMoved(qlog::events::quic::DataMoved {
+                            stream_id: Some(stream_id),
+                            offset: Some(stream_off),
+                            length: Some(len as u64),
+                            from: Some(DataRecipient::Transport),
+                            to: Some(DataRecipient::Network),
+                            urgency: Some(priority_key.urgency),
+                            incremental: Some(priority_key.incremental),
+                            ..Default::default()
+                        });
+
+                    q.add_event_data_with_instant(ev_data, now).ok();
+                });
+
                 // If the stream is no longer flushable, remove it from the queue
                 if !stream.is_flushable() {
                     self.streams.remove_flushable(&priority_key);
-                } else if stream.incremental {
-                    // Shuffle the incremental stream to the back of the
-                    // queue.
-                    self.streams.remove_flushable(&priority_key);
-                    self.streams.insert_flushable(&priority_key);
+                } else {
+                    self.streams.on_flushed(&priority_key, len);
                 }
 
                 #[cfg(feature = "fuzzing")]
@@ -4854,12 +5872,13 @@ impl<F: BufFactory> Connection<F> {
         });
 
         let aead = match pkt_space.crypto_seal {
-            Some(ref v) => v,
+            Some(ref mut v) => v,
             None => return Err(Error::InvalidState),
         };
 
         let written = packet::encrypt_pkt(
             &mut b,
+            nonce_path_id,
             pn,
             pn_len,
             payload_len,
@@ -4897,7 +5916,19 @@ impl<F: BufFactory> Connection<F> {
             path.recovery.delivery_rate_update_app_limited(true);
         }
 
-        self.next_pkt_num += 1;
+        match mp_path_id {
+            Some(id) => self.multipath.on_packet_sent(id),
+
+            None => self.next_pkt_num += 1,
+        }
+
+        // Remember which path carries each packet number space, so that
+        // acknowledgements can be matched even after the connection ID the
+        // packets were sent to was retired.
+        if epoch == packet::Epoch::Application {
+            self.multipath
+                .set_sent_path(mp_path_id.unwrap_or(0), send_pid);
+        }
 
         let handshake_status = recovery::HandshakeStatus {
             has_handshake_keys: self.pkt_num_spaces[packet::Epoch::Handshake]
@@ -4914,6 +5945,8 @@ impl<F: BufFactory> Connection<F> {
             &self.trace_id,
         );
 
+        path.ecn.on_packet_sent(epoch, pn);
+
         qlog_with_type!(QLOG_METRICS, self.qlog, q, {
             if let Some(ev_data) = path.recovery.maybe_qlog() {
                 q.add_event_data_with_instant(ev_data, now).ok();
@@ -4921,8 +5954,12 @@ impl<F: BufFactory> Connection<F> {
         });
 
         // Record sent packet size if we probe the path.
+        let mut validation_started = None;
+
         if let Some(data) = challenge_data {
-            path.add_challenge_sent(data, written, now);
+            if path.add_challenge_sent(data, written, now) {
+                validation_started = Some((path.local_addr(), path.peer_addr()));
+            }
         }
 
         self.sent_count += 1;
@@ -4936,6 +5973,12 @@ impl<F: BufFactory> Connection<F> {
 
         path.max_send_bytes = path.max_send_bytes.saturating_sub(written);
 
+        if let Some((local_addr, peer_addr)) = validation_started {
+            self.paths.notify_event(path::PathEvent::ValidationStarted(
+                local_addr, peer_addr,
+            ));
+        }
+
         // On the client, drop initial state after sending an Handshake packet.
         if !self.is_server && hdr_ty == packet::Type::Handshake {
             self.drop_epoch_state(packet::Epoch::Initial, now);
@@ -4953,11 +5996,13 @@ impl<F: BufFactory> Connection<F> {
             self.ack_eliciting_sent = true;
         }
 
-        let active_path = self.paths.get_active_mut()?;
-        if active_path.pmtud.is_enabled() {
-            active_path
+        // Restore the max datagram size to the validated path MTU, after it
+        // might have been raised to send a probe.
+        let send_path = self.paths.get_mut(send_pid)?;
+        if send_path.pmtud.is_enabled() {
+            send_path
                 .recovery
-                .pmtud_update_max_datagram_size(active_path.pmtud.get_current());
+                .pmtud_update_max_datagram_size(send_path.pmtud.get_current());
         }
 
         Ok((pkt_type, written))
@@ -5068,17 +6113,68 @@ impl<F: BufFactory> Connection<F> {
     pub fn stream_recv(
         &mut self, stream_id: u64, out: &mut [u8],
     ) -> Result<(usize, bool)> {
-        // We can't read on our own unidirectional streams.
-        if !stream::is_bidi(stream_id) &&
-            stream::is_local(stream_id, self.is_server)
-        {
-            return Err(Error::InvalidStreamState(stream_id));
-        }
-
-        let stream = self
-            .streams
-            .get_mut(stream_id)
-            .ok_or(Error::InvalidStreamState(stream_id))?;
+        self.stream_do_recv(stream_id, |stream: &mut stream::Stream<F>| {
+            stream.recv.emit(out)
+        })
+    }
+
+    /// Reads contiguous data from a stream into the provided slices.
+    ///
+    /// This is the same as [`stream_recv()`], except that data is scattered
+    /// across the given buffers, in order, moving to the next one once the
+    /// current one is full. This allows applications to read directly into,
+    /// for example, the buffers later passed to a vectored write to a file or
+    /// socket, without an intermediate copy.
+    ///
+    /// [`stream_recv()`]: struct.Connection.html#method.stream_recv
+    ///
+    /// ## Examples:
+    ///
+    /// ```no_run
+    /// # let mut header = [0; 16];
+    /// # let mut body = [0; 512];
+    /// # let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    /// # let mut config = quiche::Config::new(quiche::PROTOCOL_VERSION)?;
+    /// # let scid = quiche::ConnectionId::from_ref(&[0xba; 16]);
+    /// # let peer = "127.0.0.1:1234".parse().unwrap();
+    /// # let local = socket.local_addr().unwrap();
+    /// # let mut conn = quiche::accept(&scid, None, local, peer, &mut config)?;
+    /// # let stream_id = 0;
+    /// let mut bufs = [
+    ///     std::io::IoSliceMut::new(&mut header),
+    ///     std::io::IoSliceMut::new(&mut body),
+    /// ];
+    ///
+    /// while let Ok((read, fin)) = conn.stream_recv_v(stream_id, &mut bufs) {
+    ///     println!("Got {} bytes on stream {}", read, stream_id);
+    /// }
+    /// # Ok::<(), quiche::Error>(())
+    /// ```
+    pub fn stream_recv_v(
+        &mut self, stream_id: u64, out: &mut [std::io::IoSliceMut],
+    ) -> Result<(usize, bool)> {
+        self.stream_do_recv(stream_id, |stream: &mut stream::Stream<F>| {
+            stream.recv.emit_vectored(out)
+        })
+    }
+
+    fn stream_do_recv<RCV>(
+        &mut self, stream_id: u64, read_fn: RCV,
+    ) -> Result<(usize, bool)>
+    where
+        RCV: FnOnce(&mut stream::Stream<F>) -> Result<(usize, bool)>,
+    {
+        // We can't read on our own unidirectional streams.
+        if !stream::is_bidi(stream_id) &&
+            stream::is_local(stream_id, self.is_server)
+        {
+            return Err(Error::InvalidStreamState(stream_id));
+        }
+
+        let stream = self
+            .streams
+            .get_mut(stream_id)
+            .ok_or(Error::InvalidStreamState(stream_id))?;
 
         if !stream.is_readable() {
             return Err(Error::Done);
@@ -5090,7 +6186,7 @@ impl<F: BufFactory> Connection<F> {
         #[cfg(feature = "qlog")]
         let offset = stream.recv.off_front();
 
-        let (read, fin) = match stream.recv.emit(out) {
+        let (read, fin) = match read_fn(stream) {
             Ok(v) => v,
 
             Err(e) => {
@@ -5244,6 +6340,60 @@ impl<F: BufFactory> Connection<F> {
         )
     }
 
+    /// Writes an owned buffer to a stream without copying it.
+    ///
+    /// The connection takes ownership of `buf` and appends it directly to the
+    /// stream's send queue, instead of copying its contents like
+    /// [`stream_send()`] does. With the default buffer type, `buf` can be a
+    /// `Vec<u8>`.
+    ///
+    /// When a partial write happens the unwritten part of the buffer is
+    /// returned, so that the application can retry the operation with it once
+    /// the stream is reported as writable again. Unlike [`stream_send()`],
+    /// when the stream has no capacity at all the whole buffer is returned
+    /// along with a written length of 0, instead of [`Done`], so that it isn't
+    /// lost.
+    ///
+    /// [`stream_send()`]: struct.Connection.html#method.stream_send
+    /// [`Done`]: enum.Error.html#variant.Done
+    ///
+    /// ## Examples:
+    ///
+    /// ```no_run
+    /// # let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    /// # let mut config = quiche::Config::new(quiche::PROTOCOL_VERSION)?;
+    /// # let scid = quiche::ConnectionId::from_ref(&[0xba; 16]);
+    /// # let peer = "127.0.0.1:1234".parse().unwrap();
+    /// # let local = "127.0.0.1:4321".parse().unwrap();
+    /// # let mut conn = quiche::accept(&scid, None, local, peer, &mut config)?;
+    /// # let stream_id = 0;
+    /// let body = vec![0; 1_000_000];
+    ///
+    /// let (written, remaining) = conn.stream_send_owned(stream_id, body, true)?;
+    /// # Ok::<(), quiche::Error>(())
+    /// ```
+    pub fn stream_send_owned<B>(
+        &mut self, stream_id: u64, buf: B, fin: bool,
+    ) -> Result<(usize, Option<F::Buf>)>
+    where
+        B: Into<F::Buf>,
+        F::Buf: BufSplit,
+    {
+        let buf = buf.into();
+
+        if buf.as_ref().is_empty() {
+            return self.stream_send_zc(stream_id, buf, None, fin);
+        }
+
+        // Keep a reference to the buffer, so it can be handed back if none of
+        // it could be written.
+        match self.stream_send_zc(stream_id, buf.clone(), None, fin) {
+            Err(Error::Done) => Ok((0, Some(buf))),
+
+            v => v,
+        }
+    }
+
     fn stream_do_send<B, R, SND>(
         &mut self, stream_id: u64, buf: B, fin: bool, write_fn: SND,
     ) -> Result<R>
@@ -5398,6 +6548,17 @@ impl<F: BufFactory> Connection<F> {
     /// on the wire (streams with lower priority are sent first). Streams are
     /// created with a default priority of `127`.
     ///
+    /// Among streams with the same `urgency`, non-incremental streams are sent
+    /// first, one at a time and in increasing stream ID order. Incremental
+    /// streams are then interleaved: after a packet carries data for one of
+    /// them, it is moved behind the other incremental streams of the same
+    /// urgency. A custom order can be configured with
+    /// [`Config::set_stream_scheduler()`].
+    ///
+    /// When qlog is enabled, the order in which data is actually sent is
+    /// recorded by `data_moved` events, which include the stream's urgency
+    /// and incremental flag.
+    ///
     /// The target stream is created if it did not exist before calling this
     /// method.
     pub fn stream_priority(
@@ -5734,6 +6895,34 @@ impl<F: BufFactory> Connection<F> {
         self.streams.peer_streams_left_uni()
     }
 
+    /// Returns the peer's limit on the number of bidirectional streams that
+    /// can be created, as advertised in its transport parameters or in the
+    /// latest MAX_STREAMS frame.
+    ///
+    /// This includes the streams that were already created. See
+    /// [`peer_streams_left_bidi()`] for the number of streams that can still
+    /// be created.
+    ///
+    /// [`peer_streams_left_bidi()`]: struct.Connection.html#method.peer_streams_left_bidi
+    #[inline]
+    pub fn peer_max_streams_bidi(&self) -> u64 {
+        self.streams.peer_max_streams_bidi()
+    }
+
+    /// Returns the peer's limit on the number of unidirectional streams that
+    /// can be created, as advertised in its transport parameters or in the
+    /// latest MAX_STREAMS frame.
+    ///
+    /// This includes the streams that were already created. See
+    /// [`peer_streams_left_uni()`] for the number of streams that can still
+    /// be created.
+    ///
+    /// [`peer_streams_left_uni()`]: struct.Connection.html#method.peer_streams_left_uni
+    #[inline]
+    pub fn peer_max_streams_uni(&self) -> u64 {
+        self.streams.peer_max_streams_uni()
+    }
+
     /// Returns an iterator over streams that have outstanding data to read.
     ///
     /// Note that the iterator will only include streams that were readable at
@@ -5887,6 +7076,197 @@ impl<F: BufFactory> Connection<F> {
         Ok(())
     }
 
+    /// Asks the peer to acknowledge packets immediately.
+    ///
+    /// An IMMEDIATE_ACK frame is sent with the next packet, overriding any
+    /// delay previously requested with [`set_ack_eliciting_threshold()`] or
+    /// [`set_requested_max_ack_delay()`]. [`InvalidState`] is returned if the
+    /// peer didn't advertise the `min_ack_delay` transport parameter.
+    ///
+    /// [`set_ack_eliciting_threshold()`]: struct.Config.html#method.set_ack_eliciting_threshold
+    /// [`set_requested_max_ack_delay()`]: struct.Config.html#method.set_requested_max_ack_delay
+    /// [`InvalidState`]: enum.Error.html#variant.InvalidState
+    pub fn send_immediate_ack(&mut self) -> Result<()> {
+        if !self.ack_freq.peer_enabled() {
+            return Err(Error::InvalidState);
+        }
+
+        self.ack_freq.send_immediate_ack();
+
+        Ok(())
+    }
+
+    /// Queues a flow control frame to be sent as is.
+    ///
+    /// Unlike the flow control updates quiche sends on its own, the frame's
+    /// values are not checked against the local flow control state, so they
+    /// can be lower than previously advertised limits, or refer to streams
+    /// that don't exist. The local limits are not updated either, and the
+    /// frame is not retransmitted if lost.
+    ///
+    /// This is meant for testing how peers react to unusual flow control
+    /// behavior. [`InvalidFrame`] is returned if a value can't be encoded as a
+    /// variable-length integer.
+    ///
+    /// [`InvalidFrame`]: enum.Error.html#variant.InvalidFrame
+    #[cfg(feature = "internal")]
+    #[doc(hidden)]
+    pub fn send_flow_control_frame(
+        &mut self, frame: FlowControlFrame,
+    ) -> Result<()> {
+        let (frame, max) = match frame {
+            FlowControlFrame::MaxData { max } =>
+                (frame::Frame::MaxData { max }, max),
+
+            FlowControlFrame::MaxStreamData { stream_id, max } => {
+                if stream_id >= frame::MAX_STREAM_SIZE {
+                    return Err(Error::InvalidFrame);
+                }
+
+                (frame::Frame::MaxStreamData { stream_id, max }, max)
+            },
+
+            FlowControlFrame::MaxStreamsBidi { max } =>
+                (frame::Frame::MaxStreamsBidi { max }, max),
+
+            FlowControlFrame::MaxStreamsUni { max } =>
+                (frame::Frame::MaxStreamsUni { max }, max),
+        };
+
+        // Values are encoded as variable-length integers.
+        if max >= frame::MAX_STREAM_SIZE {
+            return Err(Error::InvalidFrame);
+        }
+
+        self.injected_frames.push_back(frame);
+
+        Ok(())
+    }
+
+    /// Queues bytes to be written as is in the payload of a 1-RTT packet.
+    ///
+    /// The bytes can encode any number of frames, including malformed ones or
+    /// frames quiche would never send, and are not validated in any way. They
+    /// are never retransmitted, and the packet carrying them is always
+    /// considered ack-eliciting.
+    ///
+    /// This is meant for testing how peers react to unusual transport frames.
+    /// [`InvalidFrame`] is returned if `data` is empty, and [`BufferTooShort`]
+    /// if it can't fit in a packet.
+    ///
+    /// [`InvalidFrame`]: enum.Error.html#variant.InvalidFrame
+    /// [`BufferTooShort`]: enum.Error.html#variant.BufferTooShort
+    #[cfg(feature = "internal")]
+    #[doc(hidden)]
+    pub fn send_raw_frame(&mut self, data: &[u8]) -> Result<()> {
+        if data.is_empty() {
+            return Err(Error::InvalidFrame);
+        }
+
+        // Leave room for the largest short header, and for the AEAD tag.
+        let overhead = 1 + MAX_CONN_ID_LEN + packet::MAX_PKT_NUM_LEN + 16;
+
+        if data.len() > self.max_send_udp_payload_size().saturating_sub(overhead)
+        {
+            return Err(Error::BufferTooShort);
+        }
+
+        self.injected_frames.push_back(frame::Frame::Raw {
+            data: data.to_vec(),
+        });
+
+        Ok(())
+    }
+
+    /// Initiates a key update.
+    ///
+    /// The next 1-RTT packets are protected with the next generation of packet
+    /// protection keys, using the flipped key phase. The previous keys are
+    /// kept to decrypt the packets the peer sends before it responds to the
+    /// key update.
+    ///
+    /// This is meant for testing how peers handle key phase changes.
+    /// [`InvalidState`] is returned if the handshake is not confirmed yet, or
+    /// if the keys of the previous key update are not discarded yet.
+    ///
+    /// [`InvalidState`]: enum.Error.html#variant.InvalidState
+    #[cfg(feature = "internal")]
+    #[doc(hidden)]
+    pub fn update_key(&mut self) -> Result<()> {
+        let now = time::Instant::now();
+
+        let space = &mut self.pkt_num_spaces[packet::Epoch::Application];
+
+        if !self.handshake_confirmed || space.key_update.is_some() {
+            return Err(Error::InvalidState);
+        }
+
+        let (Some(open), Some(seal)) =
+            (space.crypto_open.as_ref(), space.crypto_seal.as_ref())
+        else {
+            return Err(Error::InvalidState);
+        };
+
+        let open_next = open.derive_next_packet_key()?;
+        let seal_next = seal.derive_next_packet_key()?;
+
+        let open_prev = space.crypto_open.replace(open_next).unwrap();
+        space.crypto_seal.replace(seal_next);
+
+        let pto = self.paths.get_active()?.recovery.pto();
+
+        // The peer keeps using the previous keys until it sees the update, so
+        // which packet it first protects with the new keys is not known yet.
+        self.pkt_num_spaces[packet::Epoch::Application].key_update =
+            Some(packet::KeyUpdate {
+                crypto_open: open_prev,
+                pn_on_update: u64::MAX,
+                update_acked: false,
+                timer: now + (pto * 3),
+            });
+
+        self.key_phase = !self.key_phase;
+
+        trace!("{} local key update", self.trace_id);
+
+        qlog_with_type!(QLOG_PACKET_TX, self.qlog, q, {
+            let trigger = Some(
+                qlog::events::security::KeyUpdateOrRetiredTrigger::LocalUpdate,
+            );
+
+            for key_type in [
+                qlog::events::security::KeyType::Client1RttSecret,
+                qlog::events::security::KeyType::Server1RttSecret,
+            ] {
+                let ev_data =
+                    EventData::KeyUpdated(qlog::events::security::KeyUpdated {
+                        key_type,
+                        trigger: trigger.clone(),
+                        ..Default::default()
+                    });
+
+                q.add_event_data_with_instant(ev_data, now).ok();
+            }
+        });
+
+        Ok(())
+    }
+
+    /// Configures whether MAX_DATA, MAX_STREAM_DATA and MAX_STREAMS updates
+    /// are withheld.
+    ///
+    /// While updates are withheld, the peer is eventually blocked by the
+    /// limits previously advertised. Pending updates are sent once they stop
+    /// being withheld. Frames queued with [`send_flow_control_frame()`] are
+    /// sent regardless.
+    ///
+    /// [`send_flow_control_frame()`]: struct.Connection.html#method.send_flow_control_frame
+    #[cfg(feature = "internal")]
+    #[doc(hidden)]
+    pub fn withhold_flow_control_updates(&mut self, withhold: bool) {
+        self.withhold_flow_control_updates = withhold;
+    }
+
     /// Reads the first received DATAGRAM.
     ///
     /// On success the DATAGRAM's data is returned along with its size.
@@ -6193,7 +7573,13 @@ impl<F: BufFactory> Connection<F> {
             let path_timer = self
                 .paths
                 .iter()
-                .filter_map(|(_, p)| p.recovery.loss_detection_timer())
+                .flat_map(|(_, p)| {
+                    [
+                        p.recovery.loss_detection_timer(),
+                        p.validation_timer(&self.path_validation),
+                    ]
+                })
+                .flatten()
                 .min();
 
             let key_update_timer = self.pkt_num_spaces
@@ -6202,7 +7588,12 @@ impl<F: BufFactory> Connection<F> {
                 .as_ref()
                 .map(|key_update| key_update.timer);
 
-            let timers = [self.idle_timer, path_timer, key_update_timer];
+            let timers = [
+                self.idle_timer,
+                path_timer,
+                key_update_timer,
+                self.ack_freq.ack_timer(),
+            ];
 
             timers.iter().filter_map(|&x| x).min()
         }
@@ -6268,6 +7659,18 @@ impl<F: BufFactory> Connection<F> {
             }
         }
 
+        if let Some(timer) = self.ack_freq.ack_timer() {
+            if timer <= now {
+                trace!("{} delayed ack timeout expired", self.trace_id);
+
+                // Acknowledge the packets that were held back.
+                self.pkt_num_spaces[packet::Epoch::Application].ack_elicited =
+                    true;
+
+                self.ack_freq.on_ack_timeout();
+            }
+        }
+
         let handshake_status = self.handshake_status();
 
         for (_, p) in self.paths.iter_mut() {
@@ -6279,6 +7682,7 @@ impl<F: BufFactory> Connection<F> {
                         handshake_status,
                         now,
                         self.is_server,
+                        &self.path_validation,
                         &self.trace_id,
                     );
 
@@ -6292,6 +7696,18 @@ impl<F: BufFactory> Connection<F> {
                     });
                 }
             }
+
+            if let Some(timer) = p.validation_timer(&self.path_validation) {
+                if timer <= now {
+                    trace!("{} path validation timer expired", self.trace_id);
+
+                    p.on_validation_timeout(
+                        &self.path_validation,
+                        self.is_server,
+                        now,
+                    );
+                }
+            }
         }
 
         // Notify timeout events to the application.
@@ -6449,6 +7865,101 @@ impl<F: BufFactory> Connection<F> {
         Ok(dcid_seq)
     }
 
+    /// Returns whether the multipath extension was negotiated.
+    ///
+    /// See [`Config::enable_multipath()`] for details.
+    ///
+    /// [`Config::enable_multipath()`]: struct.Config.html#method.enable_multipath
+    pub fn is_multipath_enabled(&self) -> bool {
+        self.multipath.enabled()
+    }
+
+    /// Abandons the network path between `local_addr` and `peer_addr`.
+    ///
+    /// A PATH_ABANDON frame carrying `error_code` and `reason` is sent on one
+    /// of the remaining paths, and the abandoned path stops carrying
+    /// non-probing packets. A [`PathEvent::Closed`] event is raised for it.
+    ///
+    /// This requires the multipath extension to be negotiated. If it is not
+    /// the case, if the path is unknown, or if it is the last path in use (the
+    /// connection should be closed instead), this returns [`InvalidState`].
+    ///
+    /// [`PathEvent::Closed`]: enum.PathEvent.html#variant.Closed
+    /// [`InvalidState`]: enum.Error.html#InvalidState
+    pub fn abandon_path(
+        &mut self, local_addr: SocketAddr, peer_addr: SocketAddr,
+        error_code: u64, reason: &[u8],
+    ) -> Result<()> {
+        if !self.multipath.enabled() {
+            return Err(Error::InvalidState);
+        }
+
+        let pid = self
+            .paths
+            .path_id_from_addrs(&(local_addr, peer_addr))
+            .ok_or(Error::InvalidState)?;
+
+        if !self.paths.iter().any(|(id, p)| id != pid && p.active()) {
+            return Err(Error::InvalidState);
+        }
+
+        let path = self.paths.get_mut(pid)?;
+
+        if path.abandoned() {
+            return Ok(());
+        }
+
+        let path_id = path.active_dcid_seq.ok_or(Error::InvalidState)?;
+
+        path.abandon();
+
+        self.multipath.abandon(path_id, error_code, reason.to_vec());
+
+        self.paths
+            .notify_event(PathEvent::Closed(local_addr, peer_addr));
+
+        Ok(())
+    }
+
+    /// Sets the status of the network path between `local_addr` and
+    /// `peer_addr`.
+    ///
+    /// A PATH_STATUS frame is sent to let the peer know whether it should use
+    /// the path. Locally, a path marked as [`PathStatus::Standby`] by either
+    /// endpoint only carries packets when no available path has room left in
+    /// its congestion window. Applications that need finer control can steer
+    /// packets to a given path with [`send_on_path()`].
+    ///
+    /// This requires the multipath extension to be negotiated. If it is not
+    /// the case, or if the path is unknown, this returns [`InvalidState`].
+    ///
+    /// [`PathStatus::Standby`]: enum.PathStatus.html#variant.Standby
+    /// [`send_on_path()`]: struct.Connection.html#method.send_on_path
+    /// [`InvalidState`]: enum.Error.html#InvalidState
+    pub fn set_path_status(
+        &mut self, local_addr: SocketAddr, peer_addr: SocketAddr,
+        status: PathStatus,
+    ) -> Result<()> {
+        if !self.multipath.enabled() {
+            return Err(Error::InvalidState);
+        }
+
+        let pid = self
+            .paths
+            .path_id_from_addrs(&(local_addr, peer_addr))
+            .ok_or(Error::InvalidState)?;
+
+        let path = self.paths.get_mut(pid)?;
+
+        let path_id = path.active_dcid_seq.ok_or(Error::InvalidState)?;
+
+        path.set_local_status(status);
+
+        self.multipath.set_status(path_id, status);
+
+        Ok(())
+    }
+
     /// Provides additional source Connection IDs that the peer can use to reach
     /// this host.
     ///
@@ -6846,6 +8357,15 @@ impl<F: BufFactory> Connection<F> {
         self.handshake_completed
     }
 
+    /// Returns true if the connection handshake is confirmed.
+    ///
+    /// The server confirms the handshake as soon as it completes, while the
+    /// client confirms it once it receives a HANDSHAKE_DONE frame.
+    #[inline]
+    pub fn is_handshake_confirmed(&self) -> bool {
+        self.handshake_confirmed
+    }
+
     /// Returns true if the connection is resumed.
     #[inline]
     pub fn is_resumed(&self) -> bool {
@@ -6859,6 +8379,15 @@ impl<F: BufFactory> Connection<F> {
         self.handshake.is_in_early_data()
     }
 
+    /// Returns true if the early data sent by the client was accepted by the
+    /// server.
+    ///
+    /// This is only meaningful once the handshake is complete.
+    #[inline]
+    pub fn is_early_data_accepted(&self) -> bool {
+        self.handshake.is_early_data_accepted()
+    }
+
     /// Returns whether there is stream or DATAGRAM data available to read.
     #[inline]
     pub fn is_readable(&self) -> bool {
@@ -6964,6 +8493,11 @@ impl<F: BufFactory> Connection<F> {
             reset_stream_count_remote: self.reset_stream_remote_count,
             stopped_stream_count_remote: self.stopped_stream_remote_count,
             path_challenge_rx_count: self.path_challenge_rx_count,
+            data_blocked_rx_count: self.data_blocked_rx_count,
+            stream_data_blocked_rx_count: self.stream_data_blocked_rx_count,
+            streams_blocked_bidi_rx_count: self.streams_blocked_bidi_rx_count,
+            streams_blocked_uni_rx_count: self.streams_blocked_uni_rx_count,
+            ecn_ce_count: self.ecn_ce_count,
         }
     }
 
@@ -6983,13 +8517,44 @@ impl<F: BufFactory> Connection<F> {
         self.paths.iter().map(|(_, p)| p.stats())
     }
 
-    /// Returns whether or not this is a server-side connection.
-    pub fn is_server(&self) -> bool {
+    /// Returns the QUIC version used by the connection.
+    ///
+    /// This might change during the handshake as a result of version
+    /// negotiation.
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Returns whether or not this is a server-side connection.
+    pub fn is_server(&self) -> bool {
         self.is_server
     }
 
     fn encode_transport_params(&mut self) -> Result<()> {
-        let mut raw_params = [0; 128];
+        self.local_transport_params.version_information =
+            Some(VersionInformation {
+                chosen_version: self.version,
+                available_versions: self.versions.clone(),
+            });
+
+        let version_info_len = 4 * (1 + self.versions.len());
+
+        let unknown_len = self
+            .local_transport_params
+            .unknown_params
+            .as_ref()
+            .map_or(0, |unknown_params| {
+                unknown_params
+                    .into_iter()
+                    .map(|p| {
+                        octets::varint_len(p.id) +
+                            octets::varint_len(p.value.len() as u64) +
+                            p.value.len()
+                    })
+                    .sum()
+            });
+
+        let mut raw_params = vec![0; 128 + version_info_len + unknown_len];
 
         let raw_params = TransportParams::encode(
             &self.local_transport_params,
@@ -7002,6 +8567,76 @@ impl<F: BufFactory> Connection<F> {
         Ok(())
     }
 
+    /// Switches the server to a more preferred version compatible with the one
+    /// used by the client, as long as the client's ClientHello advertises it.
+    fn negotiate_compatible_version(&mut self, dcid: &[u8]) -> Result<()> {
+        let client_hello = match self.client_hello.take() {
+            Some(v) => v,
+
+            None => return Ok(()),
+        };
+
+        let params = match tls::client_hello_transport_params(&client_hello) {
+            Ok(v) => v,
+
+            // Wait for the rest of the ClientHello.
+            Err(Error::BufferTooShort) => {
+                self.client_hello = Some(client_hello);
+                return Ok(());
+            },
+
+            // Let the TLS stack deal with the malformed message.
+            Err(_) => None,
+        };
+
+        let info = match params
+            .and_then(|p| TransportParams::decode(p, true, None).ok())
+            .and_then(|tp| tp.version_information)
+        {
+            Some(v) => v,
+
+            None => return Ok(()),
+        };
+
+        // Pick the most preferred of our versions the client also supports.
+        let found_version = self
+            .versions
+            .iter()
+            .take_while(|&&v| v != self.version)
+            .find(|v| info.available_versions.contains(v));
+
+        let version = match found_version {
+            Some(&v) => v,
+
+            None => return Ok(()),
+        };
+
+        trace!(
+            "{} compatible version negotiation {:#x} -> {:#x}",
+            self.trace_id,
+            self.version,
+            version
+        );
+
+        let (aead_open, aead_seal) = crypto::derive_initial_key_material(
+            dcid,
+            version,
+            self.is_server,
+            true,
+        )?;
+
+        // Keep the original keys around, as the client will keep using them
+        // until it receives our first packet.
+        self.original_initial_open = self.pkt_num_spaces[packet::Epoch::Initial]
+            .crypto_open
+            .replace(aead_open);
+        self.pkt_num_spaces[packet::Epoch::Initial].crypto_seal = Some(aead_seal);
+
+        self.version = version;
+
+        self.encode_transport_params()
+    }
+
     fn parse_peer_transport_params(
         &mut self, peer_params: TransportParams,
     ) -> Result<()> {
@@ -7048,6 +8683,44 @@ impl<F: BufFactory> Connection<F> {
             }
         }
 
+        // Validate version_information.
+        match &peer_params.version_information {
+            Some(info) => {
+                // The client's chosen version is the one it sent its first
+                // Initial with, the server's is the negotiated one.
+                let chosen_version = if self.is_server {
+                    self.original_version
+                } else {
+                    self.version
+                };
+
+                if info.chosen_version != chosen_version {
+                    return Err(Error::VersionNegotiation);
+                }
+
+                // After incompatible version negotiation, make sure the
+                // client would have picked the same version had it known the
+                // server's full list, to prevent downgrade attacks.
+                if !self.is_server && self.did_version_negotiation {
+                    let expected_version = self
+                        .versions
+                        .iter()
+                        .find(|v| info.available_versions.contains(v));
+
+                    if expected_version != Some(&self.version) {
+                        return Err(Error::VersionNegotiation);
+                    }
+                }
+            },
+
+            // The server's version information is needed to validate
+            // incompatible version negotiation.
+            None if !self.is_server && self.did_version_negotiation =>
+                return Err(Error::VersionNegotiation),
+
+            None => (),
+        }
+
         self.process_peer_transport_params(peer_params)?;
 
         self.parsed_peer_transport_params = true;
@@ -7068,22 +8741,44 @@ impl<F: BufFactory> Connection<F> {
         self.streams
             .update_peer_max_streams_uni(peer_params.initial_max_streams_uni);
 
-        let max_ack_delay =
+        let mut max_ack_delay =
             time::Duration::from_millis(peer_params.max_ack_delay);
 
+        if let Some(min_ack_delay) = peer_params.min_ack_delay {
+            // The minimum ACK delay can't exceed the maximum one.
+            if min_ack_delay > peer_params.max_ack_delay * 1000 {
+                return Err(Error::InvalidTransportParam);
+            }
+
+            self.ack_freq.on_peer_params(
+                time::Duration::from_micros(min_ack_delay),
+                max_ack_delay,
+            );
+
+            // Until the ACK_FREQUENCY frame is acknowledged the peer might use
+            // either delay, so account for the larger one.
+            if let Some(request) = self.ack_freq.next_request() {
+                max_ack_delay = cmp::max(max_ack_delay, request.max_ack_delay);
+            }
+        }
+
         self.recovery_config.max_ack_delay = max_ack_delay;
 
         let active_path = self.paths.get_active_mut()?;
 
         active_path.recovery.update_max_ack_delay(max_ack_delay);
 
-        if active_path.pmtud.get_probe_status() {
-            active_path.recovery.pmtud_update_max_datagram_size(
-                active_path
-                    .pmtud
-                    .get_probe_size()
-                    .min(peer_params.max_udp_payload_size as usize),
-            );
+        if active_path.pmtud.is_enabled() {
+            // Path MTU discovery must not probe beyond what the peer is
+            // willing to receive.
+            self.paths
+                .set_pmtud_max_size(peer_params.max_udp_payload_size as usize);
+
+            let active_path = self.paths.get_active_mut()?;
+
+            active_path
+                .recovery
+                .pmtud_update_max_datagram_size(active_path.pmtud.get_current());
         } else {
             active_path.recovery.update_max_datagram_size(
                 peer_params.max_udp_payload_size as usize,
@@ -7094,6 +8789,17 @@ impl<F: BufFactory> Connection<F> {
         self.ids
             .set_source_conn_id_limit(peer_params.active_conn_id_limit);
 
+        // Path identifiers are derived from connection ID sequence numbers, so
+        // multipath can't be used with zero-length connection IDs.
+        if self.local_transport_params.enable_multipath &&
+            peer_params.enable_multipath &&
+            !self.ids.zero_length_scid() &&
+            !self.ids.zero_length_dcid()
+        {
+            self.multipath.enable();
+            self.paths.enable_multipath();
+        }
+
         self.peer_transport_params = peer_params;
 
         Ok(())
@@ -7116,9 +8822,11 @@ impl<F: BufFactory> Connection<F> {
 
             trace_id: &self.trace_id,
 
-            recovery_config: self.recovery_config,
+            recovery_config: self.recovery_config.clone(),
 
             is_server: self.is_server,
+
+            version: self.version,
         };
 
         if self.handshake_completed {
@@ -7275,22 +8983,20 @@ impl<F: BufFactory> Connection<F> {
         let send_path = self.paths.get(send_pid)?;
         if (self.is_established() || self.is_in_early_data()) &&
             (self.should_send_handshake_done() ||
-                self.almost_full ||
+                self.should_send_flow_control_updates() ||
+                !self.injected_frames.is_empty() ||
                 self.blocked_limit.is_some() ||
                 self.dgram_send_queue.has_pending() ||
                 self.local_error
                     .as_ref()
                     .is_some_and(|conn_err| conn_err.is_app) ||
-                self.streams.should_update_max_streams_bidi() ||
-                self.streams.should_update_max_streams_uni() ||
                 self.streams.has_flushable() ||
-                self.streams.has_almost_full() ||
                 self.streams.has_blocked() ||
                 self.streams.has_reset() ||
                 self.streams.has_stopped() ||
                 self.ids.has_new_scids() ||
                 self.ids.has_retire_dcids() ||
-                send_path.pmtud.get_probe_status() ||
+                send_path.pmtud.should_send_probe() ||
                 send_path.needs_ack_eliciting ||
                 send_path.probing_required())
         {
@@ -7332,7 +9038,9 @@ impl<F: BufFactory> Connection<F> {
             frame::Frame::Ping { .. } => (),
 
             frame::Frame::ACK {
-                ranges, ack_delay, ..
+                ranges,
+                ack_delay,
+                ecn_counts,
             } => {
                 let ack_delay = ack_delay
                     .checked_mul(2_u64.pow(
@@ -7347,29 +9055,173 @@ impl<F: BufFactory> Connection<F> {
                     self.peer_verified_initial_address = true;
                 }
 
-                let handshake_status = self.handshake_status();
-
-                let is_app_limited = self.delivery_rate_check_if_app_limited();
+                // With multipath, ACK frames refer to path 0, whose packets are
+                // numbered by the connection-wide counter, so they can't match
+                // packets sent on other paths.
+                let pid = if self.multipath.enabled() &&
+                    epoch == packet::Epoch::Application
+                {
+                    match self.multipath.sent_path(0) {
+                        Some(v) => Some(v),
 
-                for (_, p) in self.paths.iter_mut() {
-                    if is_app_limited {
-                        p.recovery.delivery_rate_update_app_limited(true);
+                        None => return Ok(()),
                     }
+                } else {
+                    None
+                };
 
-                    let (lost_packets, lost_bytes, acked_bytes) =
-                        p.recovery.on_ack_received(
-                            &ranges,
-                            ack_delay,
-                            epoch,
-                            handshake_status,
-                            now,
-                            &self.trace_id,
-                        );
+                self.on_ack_ranges_received(
+                    &ranges,
+                    ack_delay,
+                    epoch,
+                    pid,
+                    None,
+                    ecn_counts.as_ref(),
+                    now,
+                );
+            },
 
-                    self.lost_count += lost_packets;
-                    self.lost_bytes += lost_bytes as u64;
-                    self.acked_bytes += acked_bytes as u64;
+            frame::Frame::PathAck {
+                path_id,
+                ranges,
+                ack_delay,
+                ecn_counts,
+            } => {
+                if !self.multipath.enabled() {
+                    return Err(Error::InvalidState);
+                }
+
+                let ack_delay = ack_delay
+                    .checked_mul(2_u64.pow(
+                        self.peer_transport_params.ack_delay_exponent as u32,
+                    ))
+                    .ok_or(Error::InvalidFrame)?;
+
+                // Packet numbers are reused across paths, so the ranges can
+                // only match packets sent on the path the PATH_ACK refers to.
+                let pid = match self.multipath.sent_path(path_id) {
+                    Some(v) => v,
+
+                    None => return Ok(()),
+                };
+
+                self.on_ack_ranges_received(
+                    &ranges,
+                    ack_delay,
+                    epoch,
+                    Some(pid),
+                    Some(path_id),
+                    ecn_counts.as_ref(),
+                    now,
+                );
+            },
+
+            frame::Frame::PathAbandon {
+                path_id,
+                error_code,
+                ..
+            } => {
+                if !self.multipath.enabled() {
+                    return Err(Error::InvalidState);
+                }
+
+                let pid =
+                    match self.ids.get_scid(path_id).ok().and_then(|e| e.path_id)
+                    {
+                        Some(v) => v,
+
+                        // The path is unknown or already gone.
+                        None => return Ok(()),
+                    };
+
+                if self.paths.get(pid)?.abandoned() {
+                    return Ok(());
+                }
+
+                // The last path can't be abandoned, the connection must be
+                // closed instead.
+                if !self.paths.iter().any(|(id, p)| id != pid && p.active()) {
+                    return Err(Error::InvalidState);
+                }
+
+                let path = self.paths.get_mut(pid)?;
+
+                trace!(
+                    "{} peer abandoned path {} err={:x}",
+                    self.trace_id,
+                    pid,
+                    error_code
+                );
+
+                path.abandon();
+
+                let (local_addr, peer_addr) =
+                    (path.local_addr(), path.peer_addr());
+
+                // Abandon the path on our side too.
+                if let Some(dcid_seq) = path.active_dcid_seq {
+                    self.multipath.abandon(dcid_seq, 0, Vec::new());
+                }
+
+                self.paths
+                    .notify_event(PathEvent::Closed(local_addr, peer_addr));
+            },
+
+            frame::Frame::PathStatus {
+                path_id,
+                seq_num,
+                status,
+            } => {
+                if !self.multipath.enabled() {
+                    return Err(Error::InvalidState);
+                }
+
+                let status = path::PathStatus::from_wire(status)?;
+
+                if let Some(pid) =
+                    self.ids.get_scid(path_id).ok().and_then(|e| e.path_id)
+                {
+                    self.paths.get_mut(pid)?.on_peer_status(seq_num, status);
+                }
+            },
+
+            frame::Frame::AckFrequency {
+                seq_num,
+                ack_eliciting_threshold,
+                request_max_ack_delay,
+                reordering_threshold,
+            } => {
+                // The frame is only allowed if we advertised `min_ack_delay`,
+                // and the requested delay can't be lower than it.
+                let min_ack_delay =
+                    match self.local_transport_params.min_ack_delay {
+                        Some(v) => v,
+
+                        None => return Err(Error::InvalidFrame),
+                    };
+
+                if request_max_ack_delay < min_ack_delay {
+                    return Err(Error::InvalidFrame);
+                }
+
+                self.ack_freq.on_peer_request(
+                    ack_frequency::AckFrequencyRequest {
+                        seq_num,
+                        ack_eliciting_threshold,
+                        max_ack_delay: time::Duration::from_micros(
+                            request_max_ack_delay,
+                        ),
+                        reordering_threshold,
+                    },
+                );
+            },
+
+            frame::Frame::ImmediateAck => {
+                if self.local_transport_params.min_ack_delay.is_none() {
+                    return Err(Error::InvalidFrame);
                 }
+
+                self.pkt_num_spaces[epoch].ack_elicited = true;
             },
 
             frame::Frame::ResetStream {
@@ -7501,9 +9353,21 @@ impl<F: BufFactory> Connection<F> {
 
                 while let Ok((read, _)) = stream.recv.emit(&mut crypto_buf) {
                     let recv_buf = &crypto_buf[..read];
+
+                    if let Some(client_hello) = &mut self.client_hello {
+                        client_hello.extend_from_slice(recv_buf);
+                    }
+
                     self.handshake.provide_data(level, recv_buf)?;
                 }
 
+                // The version needs to be settled before the TLS stack
+                // processes the ClientHello and sends our transport
+                // parameters.
+                if self.client_hello.is_some() {
+                    self.negotiate_compatible_version(&hdr.dcid)?;
+                }
+
                 self.do_handshake(now)?;
             },
 
@@ -7645,20 +9509,32 @@ impl<F: BufFactory> Connection<F> {
                 self.streams.update_peer_max_streams_uni(max);
             },
 
-            frame::Frame::DataBlocked { .. } => (),
+            frame::Frame::DataBlocked { .. } => {
+                self.data_blocked_rx_count =
+                    self.data_blocked_rx_count.saturating_add(1);
+            },
 
-            frame::Frame::StreamDataBlocked { .. } => (),
+            frame::Frame::StreamDataBlocked { .. } => {
+                self.stream_data_blocked_rx_count =
+                    self.stream_data_blocked_rx_count.saturating_add(1);
+            },
 
             frame::Frame::StreamsBlockedBidi { limit } => {
                 if limit > MAX_STREAM_ID {
                     return Err(Error::InvalidFrame);
                 }
+
+                self.streams_blocked_bidi_rx_count =
+                    self.streams_blocked_bidi_rx_count.saturating_add(1);
             },
 
             frame::Frame::StreamsBlockedUni { limit } => {
                 if limit > MAX_STREAM_ID {
                     return Err(Error::InvalidFrame);
                 }
+
+                self.streams_blocked_uni_rx_count =
+                    self.streams_blocked_uni_rx_count.saturating_add(1);
             },
 
             frame::Frame::NewConnectionId {
@@ -7722,6 +9598,10 @@ impl<F: BufFactory> Connection<F> {
                     return Err(Error::InvalidState);
                 }
 
+                // Packets sent to that Source Connection ID can no longer
+                // arrive, so its packet number space can go.
+                self.multipath.drop_space(seq_num);
+
                 if let Some(pid) = self.ids.retire_scid(seq_num, &hdr.dcid)? {
                     let path = self.paths.get_mut(pid)?;
 
@@ -7744,7 +9624,7 @@ impl<F: BufFactory> Connection<F> {
             },
 
             frame::Frame::PathResponse { data } => {
-                self.paths.on_response_received(data)?;
+                self.paths.on_response_received(data, now)?;
             },
 
             frame::Frame::ConnectionClose {
@@ -7809,6 +9689,9 @@ impl<F: BufFactory> Connection<F> {
             },
 
             frame::Frame::DatagramHeader { .. } => unreachable!(),
+
+            #[cfg(feature = "internal")]
+            frame::Frame::Raw { .. } => unreachable!(),
         }
 
         Ok(())
@@ -7824,6 +9707,10 @@ impl<F: BufFactory> Connection<F> {
         self.pkt_num_spaces[epoch].crypto_seal = None;
         self.pkt_num_spaces[epoch].clear();
 
+        if epoch == packet::Epoch::Initial {
+            self.original_initial_open = None;
+        }
+
         let handshake_status = self.handshake_status();
         for (_, p) in self.paths.iter_mut() {
             p.recovery
@@ -7851,10 +9738,26 @@ impl<F: BufFactory> Connection<F> {
         self.is_established() && !self.handshake_done_sent && self.is_server
     }
 
-    /// Returns the idle timeout value.
+    /// Returns true if MAX_DATA, MAX_STREAM_DATA or MAX_STREAMS frames need
+    /// to be sent.
+    fn should_send_flow_control_updates(&self) -> bool {
+        !self.withhold_flow_control_updates &&
+            (self.almost_full ||
+                self.streams.should_update_max_streams_bidi() ||
+                self.streams.should_update_max_streams_uni() ||
+                self.streams.has_almost_full())
+    }
+
+    /// Returns the effective idle timeout of the connection.
+    ///
+    /// This is the smaller of the `max_idle_timeout` transport parameters of
+    /// both endpoints, ignoring the ones set to 0, but no less than three
+    /// times the current PTO. Before the handshake completes, only the local
+    /// value is known.
     ///
     /// `None` is returned if both end-points disabled the idle timeout.
-    fn idle_timeout(&mut self) -> Option<time::Duration> {
+    #[inline]
+    pub fn idle_timeout(&self) -> Option<time::Duration> {
         // If the transport parameter is set to 0, then the respective endpoint
         // decided to disable the idle timeout. If both are disabled we should
         // not set any timeout.
@@ -8062,6 +9965,62 @@ impl<F: BufFactory> Connection<F> {
         Ok(pid)
     }
 
+    /// Processes the packet number ranges acknowledged by an ACK or PATH_ACK
+    /// frame, along with the ECN counts it carries. If `pid` is `None`, the
+    /// ranges can match packets sent on any path, otherwise only those sent on
+    /// that path. `mp_path_id` holds the multipath path ID a PATH_ACK refers
+    /// to.
+    #[allow(clippy::too_many_arguments)]
+    fn on_ack_ranges_received(
+        &mut self, ranges: &ranges::RangeSet, ack_delay: u64,
+        epoch: packet::Epoch, pid: Option<usize>, mp_path_id: Option<u64>,
+        ecn_counts: Option<&frame::EcnCounts>, now: time::Instant,
+    ) {
+        let handshake_status = self.handshake_status();
+
+        let is_app_limited = self.delivery_rate_check_if_app_limited();
+
+        let paths = self
+            .paths
+            .iter_mut()
+            .filter(|(id, _)| pid.is_none() || pid == Some(*id));
+
+        for (_, p) in paths {
+            if is_app_limited {
+                p.recovery.delivery_rate_update_app_limited(true);
+            }
+
+            let (lost_packets, lost_bytes, acked_bytes) =
+                p.recovery.on_ack_received(
+                    ranges,
+                    ack_delay,
+                    epoch,
+                    handshake_status,
+                    now,
+                    &self.trace_id,
+                );
+
+            self.lost_count += lost_packets;
+            self.lost_bytes += lost_bytes as u64;
+            self.acked_bytes += acked_bytes as u64;
+
+            let ce_count =
+                p.ecn.on_ack_received(epoch, mp_path_id, ranges, ecn_counts);
+
+            if ce_count > 0 {
+                trace!(
+                    "{} peer reported {} new ECN-CE marks",
+                    self.trace_id,
+                    ce_count
+                );
+
+                p.recovery.on_ecn_ce(epoch, now);
+
+                self.ecn_ce_count += ce_count;
+            }
+        }
+    }
+
     /// Selects the path on which the next packet must be sent.
     fn get_send_path_id(
         &self, from: Option<SocketAddr>, to: Option<SocketAddr>,
@@ -8083,6 +10042,14 @@ impl<F: BufFactory> Connection<F> {
             }
         }
 
+        // With multipath, non-probing packets are spread over all the paths in
+        // use.
+        if self.multipath.enabled() {
+            if let Some(pid) = self.paths.select_multipath_send_path(from, to) {
+                return Ok(pid);
+            }
+        }
+
         if let Some((pid, p)) = self.paths.get_active_with_pid() {
             if from.is_some() && Some(p.local_addr()) != from {
                 return Err(Error::Done);
@@ -8388,6 +10355,24 @@ pub struct Stats {
 
     /// The total number of PATH_CHALLENGE frames that were received.
     pub path_challenge_rx_count: u64,
+
+    /// The total number of DATA_BLOCKED frames that were received.
+    pub data_blocked_rx_count: u64,
+
+    /// The total number of STREAM_DATA_BLOCKED frames that were received.
+    pub stream_data_blocked_rx_count: u64,
+
+    /// The total number of STREAMS_BLOCKED frames for bidirectional streams
+    /// that were received.
+    pub streams_blocked_bidi_rx_count: u64,
+
+    /// The total number of STREAMS_BLOCKED frames for unidirectional streams
+    /// that were received.
+    pub streams_blocked_uni_rx_count: u64,
+
+    /// The number of sent QUIC packets that the peer reported as marked with
+    /// ECN Congestion Experienced.
+    pub ecn_ce_count: u64,
 }
 
 impl std::fmt::Debug for Stats {
@@ -8409,6 +10394,25 @@ impl std::fmt::Debug for Stats {
     }
 }
 
+/// A flow control frame that can be sent with
+/// [`Connection::send_flow_control_frame()`].
+#[cfg(feature = "internal")]
+#[doc(hidden)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FlowControlFrame {
+    /// A MAX_DATA frame.
+    MaxData { max: u64 },
+
+    /// A MAX_STREAM_DATA frame.
+    MaxStreamData { stream_id: u64, max: u64 },
+
+    /// A MAX_STREAMS frame for bidirectional streams.
+    MaxStreamsBidi { max: u64 },
+
+    /// A MAX_STREAMS frame for unidirectional streams.
+    MaxStreamsUni { max: u64 },
+}
+
 /// QUIC Unknown Transport Parameter.
 ///
 /// A QUIC transport parameter that is not specifically recognized
@@ -8513,6 +10517,17 @@ impl<'a> Iterator for UnknownTransportParameterIterator<'a> {
     }
 }
 
+/// The contents of the `version_information` transport parameter.
+///
+/// As defined in [RFC9368](https://www.rfc-editor.org/rfc/rfc9368.html#name-version-information).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct VersionInformation {
+    /// The version in use by the endpoint.
+    pub chosen_version: u32,
+    /// The versions supported by the endpoint, in order of preference.
+    pub available_versions: Vec<u32>,
+}
+
 /// QUIC Transport Parameters
 #[derive(Clone, Debug, PartialEq)]
 pub struct TransportParams {
@@ -8552,6 +10567,12 @@ pub struct TransportParams {
     pub retry_source_connection_id: Option<ConnectionId<'static>>,
     /// DATAGRAM frame extension parameter, if any.
     pub max_datagram_frame_size: Option<u64>,
+    /// Whether the multipath extension is supported.
+    pub enable_multipath: bool,
+    /// ACK frequency extension parameter, in microseconds, if any.
+    pub min_ack_delay: Option<u64>,
+    /// Compatible version negotiation parameter, if any.
+    pub version_information: Option<VersionInformation>,
     /// Unknown peer transport parameters and values, if any.
     pub unknown_params: Option<UnknownTransportParameters>,
     // pub preferred_address: ...,
@@ -8577,6 +10598,9 @@ impl Default for TransportParams {
             initial_source_connection_id: None,
             retry_source_connection_id: None,
             max_datagram_frame_size: None,
+            enable_multipath: false,
+            min_ack_delay: None,
+            version_information: None,
             unknown_params: Default::default(),
         }
     }
@@ -8737,6 +10761,39 @@ impl TransportParams {
                     tp.max_datagram_frame_size = Some(val.get_varint()?);
                 },
 
+                MULTIPATH_TRANSPORT_PARAM => {
+                    tp.enable_multipath = true;
+                },
+
+                MIN_ACK_DELAY_TRANSPORT_PARAM => {
+                    tp.min_ack_delay = Some(val.get_varint()?);
+                },
+
+                VERSION_INFORMATION_TRANSPORT_PARAM => {
+                    if val.cap() == 0 || val.cap() % 4 != 0 {
+                        return Err(Error::InvalidTransportParam);
+                    }
+
+                    let chosen_version = val.get_u32()?;
+
+                    let mut available_versions = Vec::new();
+
+                    while val.cap() > 0 {
+                        available_versions.push(val.get_u32()?);
+                    }
+
+                    // Version 0 is reserved for version negotiation and can't
+                    // be used by either field.
+                    if chosen_version == 0 || available_versions.contains(&0) {
+                        return Err(Error::InvalidTransportParam);
+                    }
+
+                    tp.version_information = Some(VersionInformation {
+                        chosen_version,
+                        available_versions,
+                    });
+                },
+
                 // Track unknown transport parameters specially.
                 unknown_tp_id => {
                     if let Some(unknown_params) = &mut tp.unknown_params {
@@ -8908,17 +10965,54 @@ impl TransportParams {
             b.put_varint(max_datagram_frame_size)?;
         }
 
-        let out_len = b.off();
+        if tp.enable_multipath {
+            TransportParams::encode_param(&mut b, MULTIPATH_TRANSPORT_PARAM, 0)?;
+        }
 
-        Ok(&mut out[..out_len])
-    }
+        if let Some(min_ack_delay) = tp.min_ack_delay {
+            TransportParams::encode_param(
+                &mut b,
+                MIN_ACK_DELAY_TRANSPORT_PARAM,
+                octets::varint_len(min_ack_delay),
+            )?;
+            b.put_varint(min_ack_delay)?;
+        }
 
-    /// Creates a qlog event for connection transport parameters and TLS fields
-    #[cfg(feature = "qlog")]
-    pub fn to_qlog(
-        &self, owner: TransportOwner, cipher: Option<crypto::Algorithm>,
-    ) -> EventData {
-        let original_destination_connection_id = qlog::HexSlice::maybe_string(
+        if let Some(info) = &tp.version_information {
+            TransportParams::encode_param(
+                &mut b,
+                VERSION_INFORMATION_TRANSPORT_PARAM,
+                4 * (1 + info.available_versions.len()),
+            )?;
+            b.put_u32(info.chosen_version)?;
+
+            for &v in &info.available_versions {
+                b.put_u32(v)?;
+            }
+        }
+
+        if let Some(unknown_params) = &tp.unknown_params {
+            for param in unknown_params {
+                TransportParams::encode_param(
+                    &mut b,
+                    param.id,
+                    param.value.len(),
+                )?;
+                b.put_bytes(&param.value)?;
+            }
+        }
+
+        let out_len = b.off();
+
+        Ok(&mut out[..out_len])
+    }
+
+    /// Creates a qlog event for connection transport parameters and TLS fields
+    #[cfg(feature = "qlog")]
+    pub fn to_qlog(
+        &self, owner: TransportOwner, cipher: Option<crypto::Algorithm>,
+    ) -> EventData {
+        let original_destination_connection_id = qlog::HexSlice::maybe_string(
             self.original_destination_connection_id.as_ref(),
         );
 
@@ -9226,6 +11320,7 @@ pub mod testing {
             let info = RecvInfo {
                 to: server_path.peer_addr(),
                 from: server_path.local_addr(),
+                ecn: Ecn::NotEct,
             };
 
             self.client.recv(buf, info)
@@ -9236,6 +11331,7 @@ pub mod testing {
             let info = RecvInfo {
                 to: client_path.peer_addr(),
                 from: client_path.local_addr(),
+                ecn: Ecn::NotEct,
             };
 
             self.server.recv(buf, info)
@@ -9289,6 +11385,7 @@ pub mod testing {
         let info = RecvInfo {
             to: active_path.local_addr(),
             from: active_path.peer_addr(),
+            ecn: Ecn::NotEct,
         };
 
         conn.recv(&mut buf[..len], info)?;
@@ -9313,6 +11410,7 @@ pub mod testing {
             let info = RecvInfo {
                 to: si.to,
                 from: si.from,
+                ecn: si.ecn,
             };
 
             conn.recv(&mut pkt, info)?;
@@ -9422,12 +11520,13 @@ pub mod testing {
         }
 
         let aead = match space.crypto_seal {
-            Some(ref v) => v,
+            Some(ref mut v) => v,
             None => return Err(Error::InvalidState),
         };
 
         let written = packet::encrypt_pkt(
             &mut b,
+            0,
             pn,
             pn_len,
             payload_len,
@@ -9462,9 +11561,15 @@ pub mod testing {
             hdr.pkt_num_len,
         );
 
-        let mut payload =
-            packet::decrypt_pkt(&mut b, pn, hdr.pkt_num_len, payload_len, aead)
-                .unwrap();
+        let mut payload = packet::decrypt_pkt(
+            &mut b,
+            0,
+            pn,
+            hdr.pkt_num_len,
+            payload_len,
+            aead,
+        )
+        .unwrap();
 
         let mut frames = Vec::new();
 
@@ -9519,6 +11624,9 @@ mod tests {
             initial_source_connection_id: Some(b"woot woot".to_vec().into()),
             retry_source_connection_id: Some(b"retry".to_vec().into()),
             max_datagram_frame_size: Some(32),
+            enable_multipath: false,
+            min_ack_delay: None,
+            version_information: None,
             unknown_params: Default::default(),
         };
 
@@ -9550,6 +11658,9 @@ mod tests {
             initial_source_connection_id: Some(b"woot woot".to_vec().into()),
             retry_source_connection_id: None,
             max_datagram_frame_size: Some(32),
+            enable_multipath: false,
+            min_ack_delay: None,
+            version_information: None,
             unknown_params: Default::default(),
         };
 
@@ -9563,6 +11674,76 @@ mod tests {
         assert_eq!(new_tp, tp);
     }
 
+    #[test]
+    fn transport_params_multipath() {
+        let tp = TransportParams {
+            enable_multipath: true,
+            ..Default::default()
+        };
+
+        let mut raw_params = [42; 256];
+        let raw_params =
+            TransportParams::encode(&tp, false, &mut raw_params).unwrap();
+
+        let new_tp = TransportParams::decode(raw_params, true, None).unwrap();
+
+        assert!(new_tp.enable_multipath);
+        assert_eq!(new_tp, tp);
+    }
+
+    #[test]
+    fn transport_params_min_ack_delay() {
+        let tp = TransportParams {
+            min_ack_delay: Some(1000),
+            ..Default::default()
+        };
+
+        let mut raw_params = [42; 256];
+        let raw_params =
+            TransportParams::encode(&tp, true, &mut raw_params).unwrap();
+
+        let new_tp = TransportParams::decode(raw_params, false, None).unwrap();
+
+        assert_eq!(new_tp.min_ack_delay, Some(1000));
+        assert_eq!(new_tp, tp);
+    }
+
+    #[test]
+    fn transport_params_version_information() {
+        let tp = TransportParams {
+            version_information: Some(VersionInformation {
+                chosen_version: PROTOCOL_VERSION_V1,
+                available_versions: vec![
+                    PROTOCOL_VERSION_V2,
+                    PROTOCOL_VERSION_V1,
+                ],
+            }),
+            ..Default::default()
+        };
+
+        let mut raw_params = [42; 256];
+        let raw_params =
+            TransportParams::encode(&tp, false, &mut raw_params).unwrap();
+
+        let new_tp = TransportParams::decode(raw_params, true, None).unwrap();
+
+        assert_eq!(new_tp, tp);
+
+        // A chosen version of 0 is invalid.
+        let raw_params = [0x11, 0x04, 0x00, 0x00, 0x00, 0x00];
+        assert_eq!(
+            TransportParams::decode(&raw_params, true, None),
+            Err(Error::InvalidTransportParam)
+        );
+
+        // The parameter length must be a multiple of 4.
+        let raw_params = [0x11, 0x03, 0x00, 0x00, 0x01];
+        assert_eq!(
+            TransportParams::decode(&raw_params, true, None),
+            Err(Error::InvalidTransportParam)
+        );
+    }
+
     #[test]
     fn transport_params_forbid_duplicates() {
         // Given an encoded param.
@@ -9683,6 +11864,47 @@ mod tests {
         assert!(reserved_unknown_param.is_reserved());
         assert!(!not_reserved_unknown_param.is_reserved());
     }
+
+    #[test]
+    fn custom_transport_params() {
+        let mut config = Config::new(crate::PROTOCOL_VERSION).unwrap();
+        config
+            .load_cert_chain_from_pem_file("examples/cert.crt")
+            .unwrap();
+        config
+            .load_priv_key_from_pem_file("examples/cert.key")
+            .unwrap();
+        config
+            .set_application_protos(&[b"proto1", b"proto2"])
+            .unwrap();
+        config.verify_peer(false);
+        config.enable_track_unknown_transport_parameters(1024);
+        config.add_custom_transport_parameter(31 * 5 + 27, b"");
+        config.add_custom_transport_parameter(0xff73db, &[0xab; 200]);
+
+        let mut pipe = testing::Pipe::with_config(&mut config).unwrap();
+        assert_eq!(pipe.handshake(), Ok(()));
+
+        for conn in [&pipe.client, &pipe.server] {
+            let unknown_params = conn
+                .peer_transport_params()
+                .unwrap()
+                .unknown_params
+                .as_ref()
+                .unwrap();
+
+            assert_eq!(unknown_params.parameters, vec![
+                UnknownTransportParameter {
+                    id: 31 * 5 + 27,
+                    value: vec![],
+                },
+                UnknownTransportParameter {
+                    id: 0xff73db,
+                    value: vec![0xab; 200],
+                },
+            ]);
+        }
+    }
     #[test]
     fn unknown_version() {
         let mut config = Config::new(0xbabababa).unwrap();
@@ -9709,6 +11931,43 @@ mod tests {
         );
     }
 
+    #[test]
+    fn config_stream_limits() {
+        let mut config = Config::new(crate::PROTOCOL_VERSION).unwrap();
+        config
+            .load_cert_chain_from_pem_file("examples/cert.crt")
+            .unwrap();
+        config
+            .load_priv_key_from_pem_file("examples/cert.key")
+            .unwrap();
+        config
+            .set_application_protos(&[b"proto1", b"proto2"])
+            .unwrap();
+        config.set_initial_max_data(100000);
+        config.set_initial_max_stream_data_bidi_local(1000);
+        config.set_initial_max_stream_data_bidi_remote(20000);
+        config.set_initial_max_stream_data_uni(100);
+        config.set_initial_max_streams_bidi(50);
+        config.set_initial_max_streams_uni(3);
+
+        assert_eq!(config.initial_max_stream_data_bidi_local(), 1000);
+        assert_eq!(config.initial_max_stream_data_bidi_remote(), 20000);
+        assert_eq!(config.initial_max_stream_data_uni(), 100);
+        assert_eq!(config.initial_max_streams_bidi(), 50);
+        assert_eq!(config.initial_max_streams_uni(), 3);
+
+        let mut pipe = testing::Pipe::with_server_config(&mut config).unwrap();
+        assert_eq!(pipe.handshake(), Ok(()));
+
+        let params = pipe.client.peer_transport_params().unwrap();
+        assert_eq!(params.initial_max_stream_data_bidi_local, 1000);
+        assert_eq!(params.initial_max_stream_data_bidi_remote, 20000);
+        assert_eq!(params.initial_max_stream_data_uni, 100);
+
+        assert_eq!(pipe.client.peer_max_streams_bidi(), 50);
+        assert_eq!(pipe.client.peer_max_streams_uni(), 3);
+    }
+
     #[test]
     fn version_negotiation() {
         let mut buf = [0; 65535];
@@ -9735,26 +11994,44 @@ mod tests {
     }
 
     #[test]
-    fn verify_custom_root() {
+    fn handshake_v2() {
         let mut config = Config::new(PROTOCOL_VERSION).unwrap();
-        config.verify_peer(true);
-        config
-            .load_verify_locations_from_file("examples/rootca.crt")
-            .unwrap();
+        assert_eq!(config.set_versions(&[PROTOCOL_VERSION_V2]), Ok(()));
         config
             .set_application_protos(&[b"proto1", b"proto2"])
             .unwrap();
+        config.set_initial_max_data(30);
+        config.set_initial_max_stream_data_bidi_local(15);
+        config.set_initial_max_stream_data_bidi_remote(15);
+        config.set_initial_max_streams_bidi(3);
+        config.verify_peer(false);
 
         let mut pipe = testing::Pipe::with_client_config(&mut config).unwrap();
         assert_eq!(pipe.handshake(), Ok(()));
+
+        assert_eq!(pipe.client.version, PROTOCOL_VERSION_V2);
+        assert_eq!(pipe.server.version, PROTOCOL_VERSION_V2);
+
+        // Application data flows using the v2 packet protection keys.
+        assert_eq!(pipe.client.stream_send(0, b"hello", true), Ok(5));
+        assert_eq!(pipe.advance(), Ok(()));
+
+        let mut r = pipe.server.readable();
+        assert_eq!(r.next(), Some(0));
+        assert_eq!(r.next(), None);
     }
 
-    // Disable this for openssl as it seems to fail for some reason. It could be
-    // because of the way the get_certs API differs from bssl.
-    #[cfg(not(feature = "openssl"))]
     #[test]
-    fn verify_client_invalid() {
-        let mut server_config = Config::new(crate::PROTOCOL_VERSION).unwrap();
+    fn handshake_version_disabled_on_server() {
+        let mut client_config = Config::new(PROTOCOL_VERSION).unwrap();
+        assert_eq!(client_config.set_versions(&[PROTOCOL_VERSION_V2]), Ok(()));
+        client_config
+            .set_application_protos(&[b"proto1", b"proto2"])
+            .unwrap();
+        client_config.verify_peer(false);
+
+        let mut server_config = Config::new(PROTOCOL_VERSION).unwrap();
+        assert_eq!(server_config.set_versions(&[PROTOCOL_VERSION_V1]), Ok(()));
         server_config
             .load_cert_chain_from_pem_file("examples/cert.crt")
             .unwrap();
@@ -9764,22 +12041,24 @@ mod tests {
         server_config
             .set_application_protos(&[b"proto1", b"proto2"])
             .unwrap();
-        server_config.set_initial_max_data(30);
-        server_config.set_initial_max_stream_data_bidi_local(15);
-        server_config.set_initial_max_stream_data_bidi_remote(15);
-        server_config.set_initial_max_streams_bidi(3);
 
-        // The server shouldn't be able to verify the client's certificate due
-        // to missing CA.
-        server_config.verify_peer(true);
+        let mut pipe = testing::Pipe::with_client_and_server_config(
+            &mut client_config,
+            &mut server_config,
+        )
+        .unwrap();
 
-        let mut client_config = Config::new(crate::PROTOCOL_VERSION).unwrap();
-        client_config
-            .load_cert_chain_from_pem_file("examples/cert.crt")
-            .unwrap();
-        client_config
-            .load_priv_key_from_pem_file("examples/cert.key")
-            .unwrap();
+        assert_eq!(pipe.handshake(), Err(Error::UnknownVersion));
+    }
+
+    #[test]
+    fn handshake_compatible_version_negotiation() {
+        let mut client_config = Config::new(PROTOCOL_VERSION_V1).unwrap();
+        assert_eq!(
+            client_config
+                .set_versions(&[PROTOCOL_VERSION_V1, PROTOCOL_VERSION_V2]),
+            Ok(())
+        );
         client_config
             .set_application_protos(&[b"proto1", b"proto2"])
             .unwrap();
@@ -9787,64 +12066,254 @@ mod tests {
         client_config.set_initial_max_stream_data_bidi_local(15);
         client_config.set_initial_max_stream_data_bidi_remote(15);
         client_config.set_initial_max_streams_bidi(3);
+        client_config.verify_peer(false);
 
-        // The client is able to verify the server's certificate with the
-        // appropriate CA.
-        client_config
-            .load_verify_locations_from_file("examples/rootca.crt")
+        let mut server_config = Config::new(PROTOCOL_VERSION_V1).unwrap();
+        assert_eq!(
+            server_config
+                .set_versions(&[PROTOCOL_VERSION_V2, PROTOCOL_VERSION_V1]),
+            Ok(())
+        );
+        server_config
+            .load_cert_chain_from_pem_file("examples/cert.crt")
             .unwrap();
-        client_config.verify_peer(true);
+        server_config
+            .load_priv_key_from_pem_file("examples/cert.key")
+            .unwrap();
+        server_config
+            .set_application_protos(&[b"proto1", b"proto2"])
+            .unwrap();
+        server_config.set_initial_max_data(30);
+        server_config.set_initial_max_stream_data_bidi_local(15);
+        server_config.set_initial_max_stream_data_bidi_remote(15);
+        server_config.set_initial_max_streams_bidi(3);
 
         let mut pipe = testing::Pipe::with_client_and_server_config(
             &mut client_config,
             &mut server_config,
         )
         .unwrap();
-        assert_eq!(pipe.handshake(), Err(Error::TlsFail));
 
-        // Client did send a certificate.
-        assert!(pipe.server.peer_cert().is_some());
+        assert_eq!(pipe.handshake(), Ok(()));
+
+        // Both endpoints upgraded to v2 without a version negotiation round
+        // trip.
+        assert_eq!(pipe.client.version(), PROTOCOL_VERSION_V2);
+        assert_eq!(pipe.server.version(), PROTOCOL_VERSION_V2);
+        assert!(!pipe.client.did_version_negotiation);
+
+        assert_eq!(pipe.client.stream_send(0, b"hello", true), Ok(5));
+        assert_eq!(pipe.advance(), Ok(()));
+
+        let mut r = pipe.server.readable();
+        assert_eq!(r.next(), Some(0));
+        assert_eq!(r.next(), None);
     }
 
     #[test]
-    fn verify_client_anonymous() {
-        let mut config = Config::new(crate::PROTOCOL_VERSION).unwrap();
-        config
+    fn handshake_compatible_version_not_offered_by_client() {
+        let mut client_config = Config::new(PROTOCOL_VERSION_V1).unwrap();
+        assert_eq!(client_config.set_versions(&[PROTOCOL_VERSION_V1]), Ok(()));
+        client_config
+            .set_application_protos(&[b"proto1", b"proto2"])
+            .unwrap();
+        client_config.verify_peer(false);
+
+        let mut server_config = Config::new(PROTOCOL_VERSION_V1).unwrap();
+        assert_eq!(
+            server_config
+                .set_versions(&[PROTOCOL_VERSION_V2, PROTOCOL_VERSION_V1]),
+            Ok(())
+        );
+        server_config
             .load_cert_chain_from_pem_file("examples/cert.crt")
             .unwrap();
-        config
+        server_config
             .load_priv_key_from_pem_file("examples/cert.key")
             .unwrap();
-        config
+        server_config
             .set_application_protos(&[b"proto1", b"proto2"])
             .unwrap();
-        config.set_initial_max_data(30);
-        config.set_initial_max_stream_data_bidi_local(15);
-        config.set_initial_max_stream_data_bidi_remote(15);
-        config.set_initial_max_streams_bidi(3);
 
-        // Try to validate client certificate.
-        config.verify_peer(true);
+        let mut pipe = testing::Pipe::with_client_and_server_config(
+            &mut client_config,
+            &mut server_config,
+        )
+        .unwrap();
 
-        let mut pipe = testing::Pipe::with_server_config(&mut config).unwrap();
         assert_eq!(pipe.handshake(), Ok(()));
 
-        // Client didn't send a certificate.
-        assert!(pipe.server.peer_cert().is_none());
+        assert_eq!(pipe.client.version(), PROTOCOL_VERSION_V1);
+        assert_eq!(pipe.server.version(), PROTOCOL_VERSION_V1);
     }
 
-    #[rstest]
-    fn missing_initial_source_connection_id(
-        #[values("cubic", "bbr2", "bbr2_gcongestion")] cc_algorithm_name: &str,
-    ) {
+    #[test]
+    fn version_negotiation_downgrade() {
         let mut buf = [0; 65535];
 
-        let mut pipe = testing::Pipe::new(cc_algorithm_name).unwrap();
-
-        // Reset initial_source_connection_id.
-        pipe.client
-            .local_transport_params
-            .initial_source_connection_id = None;
+        let mut config = Config::new(0xbabababa).unwrap();
+        assert_eq!(
+            config.set_versions(&[PROTOCOL_VERSION_V2, PROTOCOL_VERSION_V1]),
+            Ok(())
+        );
+        config
+            .set_application_protos(&[b"proto1", b"proto2"])
+            .unwrap();
+        config.verify_peer(false);
+
+        let mut pipe = testing::Pipe::with_client_config(&mut config).unwrap();
+
+        let (mut len, _) = pipe.client.send(&mut buf).unwrap();
+
+        // Forge a Version Negotiation packet that omits the client's preferred
+        // version, even though the server supports it.
+        let hdr = packet::Header::from_slice(&mut buf[..len], 0).unwrap();
+        len = crate::negotiate_version(&hdr.scid, &hdr.dcid, &mut buf).unwrap();
+        len -= 4;
+
+        assert_eq!(pipe.client_recv(&mut buf[..len]), Ok(len));
+
+        // The server's version information reveals the downgrade.
+        assert_eq!(pipe.handshake(), Err(Error::VersionNegotiation));
+
+        assert_eq!(
+            pipe.client.local_error(),
+            Some(&ConnectionError {
+                is_app: false,
+                error_code: WireErrorCode::VersionNegotiationError as u64,
+                reason: vec![],
+            })
+        );
+    }
+
+    #[test]
+    fn set_versions() {
+        let mut config = Config::new(PROTOCOL_VERSION).unwrap();
+
+        assert_eq!(config.set_versions(&[]), Err(Error::UnknownVersion));
+        assert_eq!(
+            config.set_versions(&[PROTOCOL_VERSION_V1, 0xbabababa]),
+            Err(Error::UnknownVersion)
+        );
+
+        assert_eq!(
+            config.set_versions(&[PROTOCOL_VERSION_V2, PROTOCOL_VERSION_V1]),
+            Ok(())
+        );
+        assert_eq!(config.version, PROTOCOL_VERSION_V2);
+    }
+
+    #[test]
+    fn verify_custom_root() {
+        let mut config = Config::new(PROTOCOL_VERSION).unwrap();
+        config.verify_peer(true);
+        config
+            .load_verify_locations_from_file("examples/rootca.crt")
+            .unwrap();
+        config
+            .set_application_protos(&[b"proto1", b"proto2"])
+            .unwrap();
+
+        let mut pipe = testing::Pipe::with_client_config(&mut config).unwrap();
+        assert_eq!(pipe.handshake(), Ok(()));
+    }
+
+    // Disable this for openssl as it seems to fail for some reason. It could be
+    // because of the way the get_certs API differs from bssl.
+    #[cfg(not(feature = "openssl"))]
+    #[test]
+    fn verify_client_invalid() {
+        let mut server_config = Config::new(crate::PROTOCOL_VERSION).unwrap();
+        server_config
+            .load_cert_chain_from_pem_file("examples/cert.crt")
+            .unwrap();
+        server_config
+            .load_priv_key_from_pem_file("examples/cert.key")
+            .unwrap();
+        server_config
+            .set_application_protos(&[b"proto1", b"proto2"])
+            .unwrap();
+        server_config.set_initial_max_data(30);
+        server_config.set_initial_max_stream_data_bidi_local(15);
+        server_config.set_initial_max_stream_data_bidi_remote(15);
+        server_config.set_initial_max_streams_bidi(3);
+
+        // The server shouldn't be able to verify the client's certificate due
+        // to missing CA.
+        server_config.verify_peer(true);
+
+        let mut client_config = Config::new(crate::PROTOCOL_VERSION).unwrap();
+        client_config
+            .load_cert_chain_from_pem_file("examples/cert.crt")
+            .unwrap();
+        client_config
+            .load_priv_key_from_pem_file("examples/cert.key")
+            .unwrap();
+        client_config
+            .set_application_protos(&[b"proto1", b"proto2"])
+            .unwrap();
+        client_config.set_initial_max_data(30);
+        client_config.set_initial_max_stream_data_bidi_local(15);
+        client_config.set_initial_max_stream_data_bidi_remote(15);
+        client_config.set_initial_max_streams_bidi(3);
+
+        // The client is able to verify the server's certificate with the
+        // appropriate CA.
+        client_config
+            .load_verify_locations_from_file("examples/rootca.crt")
+            .unwrap();
+        client_config.verify_peer(true);
+
+        let mut pipe = testing::Pipe::with_client_and_server_config(
+            &mut client_config,
+            &mut server_config,
+        )
+        .unwrap();
+        assert_eq!(pipe.handshake(), Err(Error::TlsFail));
+
+        // Client did send a certificate.
+        assert!(pipe.server.peer_cert().is_some());
+    }
+
+    #[test]
+    fn verify_client_anonymous() {
+        let mut config = Config::new(crate::PROTOCOL_VERSION).unwrap();
+        config
+            .load_cert_chain_from_pem_file("examples/cert.crt")
+            .unwrap();
+        config
+            .load_priv_key_from_pem_file("examples/cert.key")
+            .unwrap();
+        config
+            .set_application_protos(&[b"proto1", b"proto2"])
+            .unwrap();
+        config.set_initial_max_data(30);
+        config.set_initial_max_stream_data_bidi_local(15);
+        config.set_initial_max_stream_data_bidi_remote(15);
+        config.set_initial_max_streams_bidi(3);
+
+        // Try to validate client certificate.
+        config.verify_peer(true);
+
+        let mut pipe = testing::Pipe::with_server_config(&mut config).unwrap();
+        assert_eq!(pipe.handshake(), Ok(()));
+
+        // Client didn't send a certificate.
+        assert!(pipe.server.peer_cert().is_none());
+    }
+
+    #[rstest]
+    fn missing_initial_source_connection_id(
+        #[values("cubic", "bbr2", "bbr2_gcongestion")] cc_algorithm_name: &str,
+    ) {
+        let mut buf = [0; 65535];
+
+        let mut pipe = testing::Pipe::new(cc_algorithm_name).unwrap();
+
+        // Reset initial_source_connection_id.
+        pipe.client
+            .local_transport_params
+            .initial_source_connection_id = None;
         assert_eq!(pipe.client.encode_transport_params(), Ok(()));
 
         // Client sends initial flight.
@@ -9985,6 +12454,7 @@ mod tests {
 
         assert!(pipe.client.is_established());
         assert!(!pipe.client.handshake_confirmed);
+        assert!(!pipe.client.is_handshake_confirmed());
 
         assert!(pipe.server.is_established());
         assert!(pipe.server.handshake_confirmed);
@@ -9996,6 +12466,7 @@ mod tests {
 
         assert!(pipe.client.is_established());
         assert!(pipe.client.handshake_confirmed);
+        assert!(pipe.client.is_handshake_confirmed());
 
         assert!(pipe.server.is_established());
         assert!(pipe.server.handshake_confirmed);
@@ -10172,6 +12643,79 @@ mod tests {
         let mut b = [0; 15];
         assert_eq!(pipe.server.stream_recv(4, &mut b), Ok((5, true)));
         assert_eq!(&b[..5], b"aaaaa");
+
+        assert_eq!(pipe.advance(), Ok(()));
+        assert!(pipe.client.is_established());
+        assert!(pipe.client.is_early_data_accepted());
+        assert!(pipe.server.is_early_data_accepted());
+    }
+
+    #[cfg(not(feature = "openssl"))] // 0-RTT not supported when using openssl/quictls
+    #[rstest]
+    fn handshake_0rtt_rejected(
+        #[values("cubic", "bbr2", "bbr2_gcongestion")] cc_algorithm_name: &str,
+    ) {
+        let mut config = Config::new(crate::PROTOCOL_VERSION).unwrap();
+        assert_eq!(config.set_cc_algorithm_name(cc_algorithm_name), Ok(()));
+        config
+            .load_cert_chain_from_pem_file("examples/cert.crt")
+            .unwrap();
+        config
+            .load_priv_key_from_pem_file("examples/cert.key")
+            .unwrap();
+        config
+            .set_application_protos(&[b"proto1", b"proto2"])
+            .unwrap();
+        config.set_initial_max_data(30);
+        config.set_initial_max_stream_data_bidi_local(15);
+        config.set_initial_max_stream_data_bidi_remote(15);
+        config.set_initial_max_streams_bidi(3);
+        config.enable_early_data();
+        config.verify_peer(false);
+
+        // Perform initial handshake.
+        let mut pipe = testing::Pipe::with_config(&mut config).unwrap();
+        assert_eq!(pipe.handshake(), Ok(()));
+
+        let session = pipe.client.session().unwrap();
+
+        // The server no longer accepts early data.
+        let mut server_config = Config::new(crate::PROTOCOL_VERSION).unwrap();
+        assert_eq!(
+            server_config.set_cc_algorithm_name(cc_algorithm_name),
+            Ok(())
+        );
+        server_config
+            .load_cert_chain_from_pem_file("examples/cert.crt")
+            .unwrap();
+        server_config
+            .load_priv_key_from_pem_file("examples/cert.key")
+            .unwrap();
+        server_config
+            .set_application_protos(&[b"proto1", b"proto2"])
+            .unwrap();
+        server_config.set_initial_max_data(30);
+        server_config.set_initial_max_stream_data_bidi_local(15);
+        server_config.set_initial_max_stream_data_bidi_remote(15);
+        server_config.set_initial_max_streams_bidi(3);
+
+        let mut pipe = testing::Pipe::with_client_and_server_config(
+            &mut config,
+            &mut server_config,
+        )
+        .unwrap();
+        assert_eq!(pipe.client.set_session(session), Ok(()));
+
+        // Client sends early data along with its initial flight.
+        let mut buf = [0; 65535];
+        let (len, _) = pipe.client.send(&mut buf).unwrap();
+        assert!(pipe.client.is_in_early_data());
+        assert_eq!(pipe.client.stream_send(4, b"aaaaa", true), Ok(5));
+        assert_eq!(pipe.server_recv(&mut buf[..len]), Ok(len));
+
+        assert_eq!(pipe.handshake(), Ok(()));
+        assert!(!pipe.client.is_early_data_accepted());
+        assert!(!pipe.server.is_early_data_accepted());
     }
 
     #[cfg(not(feature = "openssl"))] // 0-RTT not supported when using openssl/quictls
@@ -10355,6 +12899,7 @@ mod tests {
         let info = RecvInfo {
             to: active_path.local_addr(),
             from: active_path.peer_addr(),
+            ecn: Ecn::NotEct,
         };
 
         assert_eq!(
@@ -11059,6 +13604,7 @@ mod tests {
 
         pipe.server.stream_send(9, b"a", false).ok();
         assert_eq!(0, pipe.server.peer_streams_left_bidi());
+        assert_eq!(3, pipe.server.peer_max_streams_bidi());
 
         let frames = [frame::Frame::MaxStreamsBidi { max: MAX_STREAM_ID }];
 
@@ -11066,6 +13612,7 @@ mod tests {
         assert!(pipe.send_pkt_to_server(pkt_type, &frames, &mut buf).is_ok());
 
         assert_eq!(MAX_STREAM_ID - 3, pipe.server.peer_streams_left_bidi());
+        assert_eq!(MAX_STREAM_ID, pipe.server.peer_max_streams_bidi());
     }
 
     #[rstest]
@@ -11087,6 +13634,7 @@ mod tests {
 
         pipe.server.stream_send(11, b"a", false).ok();
         assert_eq!(0, pipe.server.peer_streams_left_uni());
+        assert_eq!(3, pipe.server.peer_max_streams_uni());
 
         let frames = [frame::Frame::MaxStreamsUni { max: MAX_STREAM_ID }];
 
@@ -11094,6 +13642,7 @@ mod tests {
         assert!(pipe.send_pkt_to_server(pkt_type, &frames, &mut buf).is_ok());
 
         assert_eq!(MAX_STREAM_ID - 3, pipe.server.peer_streams_left_uni());
+        assert_eq!(MAX_STREAM_ID, pipe.server.peer_max_streams_uni());
     }
 
     #[rstest]
@@ -12943,10 +15492,11 @@ mod tests {
         // Use correct payload length when encrypting the packet.
         let payload_len = frames.iter().fold(0, |acc, x| acc + x.wire_len());
 
-        let aead = space.crypto_seal.as_ref().unwrap();
+        let aead = space.crypto_seal.as_mut().unwrap();
 
         let written = packet::encrypt_pkt(
             &mut b,
+            0,
             pn,
             pn_len,
             payload_len,
@@ -14035,41 +16585,219 @@ mod tests {
     }
 
     #[rstest]
-    fn stream_data_blocked_unblocked_flow_control(
+    fn blocked_frames_stats(
         #[values("cubic", "bbr2", "bbr2_gcongestion")] cc_algorithm_name: &str,
     ) {
-        let mut buf = [0; 65535];
         let mut pipe = testing::Pipe::new(cc_algorithm_name).unwrap();
         assert_eq!(pipe.handshake(), Ok(()));
 
-        assert_eq!(
-            pipe.client.stream_send(0, b"aaaaaaaaaaaaaaah", false),
-            Ok(15)
-        );
-        assert_eq!(pipe.client.streams.blocked().len(), 1);
+        assert_eq!(pipe.client.stream_send(0, b"aaaaaaaaaa", false), Ok(10));
+        assert_eq!(pipe.client.stream_send(4, b"aaaaaaaaaa", false), Ok(10));
+        assert_eq!(pipe.client.stream_send(8, b"aaaaaaaaaaa", false), Ok(10));
         assert_eq!(pipe.advance(), Ok(()));
-        assert_eq!(pipe.client.streams.blocked().len(), 0);
-
-        // Send again on blocked stream. It's blocked at the same offset as
-        // previously, so it should not be marked as blocked again.
-        assert_eq!(pipe.client.stream_send(0, b"h", false), Err(Error::Done));
-        assert_eq!(pipe.client.streams.blocked().len(), 0);
-
-        // No matter how many times we try to write stream data tried, no
-        // packets containing STREAM_BLOCKED should be emitted.
-        assert_eq!(pipe.client.stream_send(0, b"h", false), Err(Error::Done));
-        assert_eq!(pipe.client.send(&mut buf), Err(Error::Done));
 
-        assert_eq!(pipe.client.stream_send(0, b"h", false), Err(Error::Done));
-        assert_eq!(pipe.client.send(&mut buf), Err(Error::Done));
+        let stats = pipe.server.stats();
+        assert_eq!(stats.data_blocked_rx_count, 1);
+        assert_eq!(stats.stream_data_blocked_rx_count, 0);
+    }
 
-        assert_eq!(pipe.client.stream_send(0, b"h", false), Err(Error::Done));
-        assert_eq!(pipe.client.send(&mut buf), Err(Error::Done));
+    #[cfg(feature = "internal")]
+    #[rstest]
+    fn send_flow_control_frame(
+        #[values("cubic", "bbr2", "bbr2_gcongestion")] cc_algorithm_name: &str,
+    ) {
+        let mut buf = [0; 65535];
 
-        // Now read some data at the server to release flow control.
-        let mut r = pipe.server.readable();
-        assert_eq!(r.next(), Some(0));
-        assert_eq!(r.next(), None);
+        let mut pipe = testing::Pipe::new(cc_algorithm_name).unwrap();
+        assert_eq!(pipe.handshake(), Ok(()));
+        assert_eq!(pipe.advance(), Ok(()));
+
+        // Values lower than the ones previously advertised are sent as is.
+        assert_eq!(
+            pipe.client
+                .send_flow_control_frame(FlowControlFrame::MaxData { max: 5 }),
+            Ok(())
+        );
+        assert_eq!(
+            pipe.client.send_flow_control_frame(
+                FlowControlFrame::MaxStreamData {
+                    stream_id: 1,
+                    max: 1 << 62,
+                }
+            ),
+            Err(Error::InvalidFrame)
+        );
+        assert_eq!(
+            pipe.client.send_flow_control_frame(
+                FlowControlFrame::MaxStreamsBidi { max: 1000 }
+            ),
+            Ok(())
+        );
+
+        let (len, _) = pipe.client.send(&mut buf).unwrap();
+
+        let frames =
+            testing::decode_pkt(&mut pipe.server, &mut buf[..len]).unwrap();
+
+        let mut iter = frames.iter();
+
+        assert_eq!(iter.next(), Some(&frame::Frame::MaxData { max: 5 }));
+        assert_eq!(
+            iter.next(),
+            Some(&frame::Frame::MaxStreamsBidi { max: 1000 })
+        );
+        assert_eq!(iter.next(), None);
+
+        assert_eq!(pipe.client.send(&mut buf), Err(Error::Done));
+    }
+
+    #[cfg(feature = "internal")]
+    #[rstest]
+    fn send_raw_frame(
+        #[values("cubic", "bbr2", "bbr2_gcongestion")] cc_algorithm_name: &str,
+    ) {
+        let mut buf = [0; 65535];
+
+        let mut pipe = testing::Pipe::new(cc_algorithm_name).unwrap();
+        assert_eq!(pipe.handshake(), Ok(()));
+        assert_eq!(pipe.advance(), Ok(()));
+
+        assert_eq!(pipe.client.send_raw_frame(&[]), Err(Error::InvalidFrame));
+        assert_eq!(
+            pipe.client.send_raw_frame(&[0; 1500]),
+            Err(Error::BufferTooShort)
+        );
+
+        // PING followed by 3 bytes of PADDING.
+        assert_eq!(pipe.client.send_raw_frame(&[0x01, 0, 0, 0]), Ok(()));
+
+        let (len, _) = pipe.client.send(&mut buf).unwrap();
+
+        let frames =
+            testing::decode_pkt(&mut pipe.server, &mut buf[..len]).unwrap();
+
+        let mut iter = frames.iter();
+
+        assert_eq!(iter.next(), Some(&frame::Frame::Ping { mtu_probe: None }));
+        assert_eq!(iter.next(), Some(&frame::Frame::Padding { len: 3 }));
+        assert_eq!(iter.next(), None);
+
+        assert_eq!(pipe.client.send(&mut buf), Err(Error::Done));
+
+        // Unknown frame types are rejected by the peer.
+        assert_eq!(pipe.client.send_raw_frame(&[0x1f]), Ok(()));
+        assert_eq!(pipe.advance(), Err(Error::InvalidFrame));
+    }
+
+    #[cfg(feature = "internal")]
+    #[rstest]
+    fn update_key(
+        #[values("cubic", "bbr2", "bbr2_gcongestion")] cc_algorithm_name: &str,
+    ) {
+        let mut buf = [0; 65535];
+
+        let mut pipe = testing::Pipe::new(cc_algorithm_name).unwrap();
+        assert_eq!(pipe.client.update_key(), Err(Error::InvalidState));
+
+        assert_eq!(pipe.handshake(), Ok(()));
+        assert_eq!(pipe.advance(), Ok(()));
+
+        // The server sends a packet with the initial keys, which is delayed.
+        assert_eq!(pipe.server.stream_send(1, b"a", false), Ok(1));
+        let (len, _) = pipe.server.send(&mut buf).unwrap();
+        let mut delayed = buf[..len].to_vec();
+
+        assert_eq!(pipe.client.update_key(), Ok(()));
+        assert!(pipe.client.key_phase);
+
+        // Another key update can't be initiated until the previous keys are
+        // discarded.
+        assert_eq!(pipe.client.update_key(), Err(Error::InvalidState));
+
+        assert_eq!(pipe.client.stream_send(4, b"b", false), Ok(1));
+        assert_eq!(pipe.advance(), Ok(()));
+        assert!(pipe.server.key_phase);
+
+        // The delayed packet is still decrypted with the previous keys.
+        assert_eq!(pipe.client_recv(&mut delayed), Ok(delayed.len()));
+
+        let mut r = pipe.client.readable();
+        assert_eq!(r.next(), Some(1));
+        assert_eq!(r.next(), None);
+
+        assert_eq!(pipe.server.stream_send(1, b"c", true), Ok(1));
+        assert_eq!(pipe.advance(), Ok(()));
+
+        let mut b = [0; 15];
+        assert_eq!(pipe.client.stream_recv(1, &mut b), Ok((2, true)));
+        assert_eq!(&b[..2], b"ac");
+    }
+
+    #[cfg(feature = "internal")]
+    #[rstest]
+    fn withhold_flow_control_updates(
+        #[values("cubic", "bbr2", "bbr2_gcongestion")] cc_algorithm_name: &str,
+    ) {
+        let mut buf = [0; 65535];
+
+        let mut pipe = testing::Pipe::new(cc_algorithm_name).unwrap();
+        assert_eq!(pipe.handshake(), Ok(()));
+
+        pipe.server.withhold_flow_control_updates(true);
+
+        assert_eq!(
+            pipe.client.stream_send(0, b"aaaaaaaaaaaaaaa", false),
+            Ok(15)
+        );
+        assert_eq!(pipe.advance(), Ok(()));
+
+        assert_eq!(pipe.server.stream_recv(0, &mut buf), Ok((15, false)));
+        assert_eq!(pipe.advance(), Ok(()));
+
+        assert_eq!(pipe.client.stream_capacity(0), Ok(0));
+
+        pipe.server.withhold_flow_control_updates(false);
+        assert_eq!(pipe.advance(), Ok(()));
+
+        assert!(pipe.client.stream_capacity(0).unwrap() > 0);
+    }
+
+    #[rstest]
+    fn stream_data_blocked_unblocked_flow_control(
+        #[values("cubic", "bbr2", "bbr2_gcongestion")] cc_algorithm_name: &str,
+    ) {
+        let mut buf = [0; 65535];
+        let mut pipe = testing::Pipe::new(cc_algorithm_name).unwrap();
+        assert_eq!(pipe.handshake(), Ok(()));
+
+        assert_eq!(
+            pipe.client.stream_send(0, b"aaaaaaaaaaaaaaah", false),
+            Ok(15)
+        );
+        assert_eq!(pipe.client.streams.blocked().len(), 1);
+        assert_eq!(pipe.advance(), Ok(()));
+        assert_eq!(pipe.client.streams.blocked().len(), 0);
+
+        // Send again on blocked stream. It's blocked at the same offset as
+        // previously, so it should not be marked as blocked again.
+        assert_eq!(pipe.client.stream_send(0, b"h", false), Err(Error::Done));
+        assert_eq!(pipe.client.streams.blocked().len(), 0);
+
+        // No matter how many times we try to write stream data tried, no
+        // packets containing STREAM_BLOCKED should be emitted.
+        assert_eq!(pipe.client.stream_send(0, b"h", false), Err(Error::Done));
+        assert_eq!(pipe.client.send(&mut buf), Err(Error::Done));
+
+        assert_eq!(pipe.client.stream_send(0, b"h", false), Err(Error::Done));
+        assert_eq!(pipe.client.send(&mut buf), Err(Error::Done));
+
+        assert_eq!(pipe.client.stream_send(0, b"h", false), Err(Error::Done));
+        assert_eq!(pipe.client.send(&mut buf), Err(Error::Done));
+
+        // Now read some data at the server to release flow control.
+        let mut r = pipe.server.readable();
+        assert_eq!(r.next(), Some(0));
+        assert_eq!(r.next(), None);
 
         let mut b = [0; 10];
         assert_eq!(pipe.server.stream_recv(0, &mut b), Ok((10, false)));
@@ -14776,6 +17504,119 @@ mod tests {
         assert_eq!(pipe.server.send(&mut buf), Err(Error::Done));
     }
 
+    #[cfg(feature = "qlog")]
+    #[test]
+    /// Tests that the order in which stream data is sent is logged to qlog,
+    /// along with the streams' priority.
+    fn stream_priority_qlog() {
+        #[derive(Clone, Default)]
+        struct SharedBuf(Arc<std::sync::Mutex<Vec<u8>>>);
+
+        impl std::io::Write for SharedBuf {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().extend_from_slice(buf);
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut config = Config::new(crate::PROTOCOL_VERSION).unwrap();
+        config
+            .load_cert_chain_from_pem_file("examples/cert.crt")
+            .unwrap();
+        config
+            .load_priv_key_from_pem_file("examples/cert.key")
+            .unwrap();
+        config
+            .set_application_protos(&[b"proto1", b"proto2"])
+            .unwrap();
+        config.set_initial_max_data(100_000);
+        config.set_initial_max_stream_data_bidi_local(100_000);
+        config.set_initial_max_stream_data_bidi_remote(100_000);
+        config.set_initial_max_streams_bidi(5);
+        config.verify_peer(false);
+
+        let mut pipe = testing::Pipe::with_config(&mut config).unwrap();
+        assert_eq!(pipe.handshake(), Ok(()));
+
+        let qlog = SharedBuf::default();
+        pipe.client.set_qlog(
+            Box::new(qlog.clone()),
+            "title".to_string(),
+            "description".to_string(),
+        );
+
+        // Two incremental streams, each needing more than one packet.
+        assert_eq!(pipe.client.stream_priority(0, 3, true), Ok(()));
+        assert_eq!(pipe.client.stream_send(0, &[0; 2000], true), Ok(2000));
+
+        assert_eq!(pipe.client.stream_priority(4, 3, true), Ok(()));
+        assert_eq!(pipe.client.stream_send(4, &[0; 2000], true), Ok(2000));
+
+        // A non-incremental stream with the same urgency.
+        assert_eq!(pipe.client.stream_priority(8, 3, false), Ok(()));
+        assert_eq!(pipe.client.stream_send(8, b"b", true), Ok(1));
+
+        // A more urgent stream.
+        assert_eq!(pipe.client.stream_priority(12, 1, false), Ok(()));
+        assert_eq!(pipe.client.stream_send(12, b"a", true), Ok(1));
+
+        assert_eq!(pipe.advance(), Ok(()));
+
+        let log = qlog.0.lock().unwrap().clone();
+        let reader =
+            qlog::reader::QlogSeqReader::new(Box::new(std::io::Cursor::new(log)))
+                .unwrap();
+
+        let moved: Vec<_> = reader
+            .filter_map(|ev| match ev {
+                qlog::reader::Event::Qlog(Event {
+                    data: EventData::DataMoved(ev),
+                    ..
+                }) if ev.to == Some(DataRecipient::Network) => Some(ev),
+
+                _ => None,
+            })
+            .collect();
+
+        // The most urgent stream goes first, then the non-incremental one.
+        assert_eq!(moved[0].stream_id, Some(12));
+        assert_eq!(moved[0].urgency, Some(1));
+        assert_eq!(moved[0].incremental, Some(false));
+        assert_eq!(moved[0].length, Some(1));
+
+        assert_eq!(moved[1].stream_id, Some(8));
+        assert_eq!(moved[1].urgency, Some(3));
+        assert_eq!(moved[1].incremental, Some(false));
+        assert_eq!(moved[1].length, Some(1));
+
+        // The incremental streams are interleaved.
+        let mut order: Vec<u64> =
+            moved[2..].iter().map(|ev| ev.stream_id.unwrap()).collect();
+        order.dedup();
+        assert_eq!(order, [0, 4, 0, 4]);
+
+        for ev in &moved[2..] {
+            assert_eq!(ev.from, Some(DataRecipient::Transport));
+            assert_eq!(ev.urgency, Some(3));
+            assert_eq!(ev.incremental, Some(true));
+        }
+
+        // All of the data was logged.
+        for stream_id in [0, 4] {
+            let len: u64 = moved
+                .iter()
+                .filter(|ev| ev.stream_id == Some(stream_id))
+                .map(|ev| ev.length.unwrap())
+                .sum();
+
+            assert_eq!(len, 2000);
+        }
+    }
+
     #[rstest]
     /// Tests that changing a stream's priority is correctly propagated.
     fn stream_reprioritize(
@@ -16680,6 +19521,7 @@ mod tests {
         let info = RecvInfo {
             to: active_path.local_addr(),
             from: active_path.peer_addr(),
+            ecn: Ecn::NotEct,
         };
 
         assert_eq!(
@@ -16754,6 +19596,7 @@ mod tests {
         let info = RecvInfo {
             to: active_path.local_addr(),
             from: active_path.peer_addr(),
+            ecn: Ecn::NotEct,
         };
 
         assert_eq!(
@@ -17043,6 +19886,7 @@ mod tests {
         let info = RecvInfo {
             to: active_path.local_addr(),
             from: active_path.peer_addr(),
+            ecn: Ecn::NotEct,
         };
 
         assert_eq!(
@@ -17193,6 +20037,10 @@ mod tests {
         assert_eq!(pipe.advance(), Ok(()));
 
         // The path should be validated at some point.
+        assert_eq!(
+            pipe.client.path_event_next(),
+            Some(PathEvent::ValidationStarted(client_addr_2, server_addr)),
+        );
         assert_eq!(
             pipe.client.path_event_next(),
             Some(PathEvent::Validated(client_addr_2, server_addr)),
@@ -17204,6 +20052,10 @@ mod tests {
             pipe.server.path_event_next(),
             Some(PathEvent::New(server_addr, client_addr_2)),
         );
+        assert_eq!(
+            pipe.server.path_event_next(),
+            Some(PathEvent::ValidationStarted(server_addr, client_addr_2)),
+        );
         assert_eq!(
             pipe.server.path_event_next(),
             Some(PathEvent::Validated(server_addr, client_addr_2)),
@@ -17268,6 +20120,10 @@ mod tests {
         assert_eq!(pipe.advance(), Ok(()));
 
         // The path should be validated at some point.
+        assert_eq!(
+            pipe.client.path_event_next(),
+            Some(PathEvent::ValidationStarted(client_addr_2, server_addr))
+        );
         assert_eq!(
             pipe.client.path_event_next(),
             Some(PathEvent::Validated(client_addr_2, server_addr))
@@ -17278,6 +20134,10 @@ mod tests {
             pipe.server.path_event_next(),
             Some(PathEvent::New(server_addr, client_addr_2))
         );
+        assert_eq!(
+            pipe.server.path_event_next(),
+            Some(PathEvent::ValidationStarted(server_addr, client_addr_2))
+        );
         // The path should be validated at some point.
         assert_eq!(
             pipe.server.path_event_next(),
@@ -17335,6 +20195,10 @@ mod tests {
             pipe.client.on_timeout();
         }
 
+        assert_eq!(
+            pipe.client.path_event_next(),
+            Some(PathEvent::ValidationStarted(client_addr_2, server_addr)),
+        );
         assert_eq!(
             pipe.client.path_event_next(),
             Some(PathEvent::FailedValidation(client_addr_2, server_addr)),
@@ -17342,43 +20206,7 @@ mod tests {
     }
 
     #[rstest]
-    fn client_discard_unknown_address(
-        #[values("cubic", "bbr2", "bbr2_gcongestion")] cc_algorithm_name: &str,
-    ) {
-        let mut config = Config::new(crate::PROTOCOL_VERSION).unwrap();
-        assert_eq!(config.set_cc_algorithm_name(cc_algorithm_name), Ok(()));
-        config
-            .load_cert_chain_from_pem_file("examples/cert.crt")
-            .unwrap();
-        config
-            .load_priv_key_from_pem_file("examples/cert.key")
-            .unwrap();
-        config
-            .set_application_protos(&[b"proto1", b"proto2"])
-            .unwrap();
-        config.verify_peer(false);
-        config.set_initial_max_data(30);
-        config.set_initial_max_stream_data_uni(10);
-        config.set_initial_max_streams_uni(3);
-
-        let mut pipe = testing::Pipe::with_config(&mut config).unwrap();
-        assert_eq!(pipe.handshake(), Ok(()));
-
-        // Server sends stream data.
-        assert_eq!(pipe.server.stream_send(3, b"a", true), Ok(1));
-
-        let mut flight =
-            testing::emit_flight(&mut pipe.server).expect("no packet");
-        // Let's change the address info.
-        flight
-            .iter_mut()
-            .for_each(|(_, si)| si.from = "127.0.0.1:9292".parse().unwrap());
-        assert_eq!(testing::process_flight(&mut pipe.client, flight), Ok(()));
-        assert_eq!(pipe.client.paths.len(), 1);
-    }
-
-    #[rstest]
-    fn path_validation_limited_mtu(
+    fn path_challenge_retry_interval(
         #[values("cubic", "bbr2", "bbr2_gcongestion")] cc_algorithm_name: &str,
     ) {
         let mut config = Config::new(crate::PROTOCOL_VERSION).unwrap();
@@ -17394,28 +20222,241 @@ mod tests {
             .unwrap();
         config.verify_peer(false);
         config.set_active_connection_id_limit(2);
+        config.set_max_path_challenges(2);
+        config.set_path_challenge_retry_interval(5);
 
         let mut pipe = pipe_with_exchanged_cids(&mut config, 16, 16, 1);
 
         let server_addr = testing::Pipe::server_addr();
         let client_addr_2 = "127.0.0.1:5678".parse().unwrap();
         assert_eq!(pipe.client.probe_path(client_addr_2, server_addr), Ok(1));
-        // Limited MTU of 1199 bytes for some reason.
-        testing::process_flight(
-            &mut pipe.server,
-            testing::emit_flight_with_max_buffer(
-                &mut pipe.client,
-                1199,
-                None,
-                None,
-            )
-            .expect("no packet"),
-        )
-        .expect("error when processing client packets");
-        testing::process_flight(
-            &mut pipe.client,
-            testing::emit_flight(&mut pipe.server).expect("no packet"),
-        )
+
+        let probed_pid = pipe
+            .client
+            .paths
+            .path_id_from_addrs(&(client_addr_2, server_addr))
+            .unwrap();
+
+        for _ in 0..2 {
+            // The client creates the PATH CHALLENGE, but it is always lost.
+            testing::emit_flight(&mut pipe.client).unwrap();
+
+            // The challenge is retried once the interval elapsed.
+            let retry_instant = pipe
+                .client
+                .paths
+                .get(probed_pid)
+                .unwrap()
+                .validation_timer(&pipe.client.path_validation)
+                .unwrap();
+            assert!(pipe.client.timeout_instant().unwrap() <= retry_instant);
+
+            let timer = retry_instant.duration_since(time::Instant::now());
+            std::thread::sleep(timer + time::Duration::from_millis(1));
+
+            pipe.client.on_timeout();
+        }
+
+        assert_eq!(
+            pipe.client.path_event_next(),
+            Some(PathEvent::ValidationStarted(client_addr_2, server_addr)),
+        );
+        assert_eq!(
+            pipe.client.path_event_next(),
+            Some(PathEvent::FailedValidation(client_addr_2, server_addr)),
+        );
+        assert_eq!(pipe.client.path_event_next(), None);
+
+        let stats = pipe
+            .client
+            .path_stats()
+            .find(|s| s.local_addr == client_addr_2)
+            .unwrap();
+        assert_eq!(stats.validation_state, path::PathState::Failed);
+        assert!(
+            stats.validation_duration.unwrap() >= time::Duration::from_millis(5)
+        );
+    }
+
+    #[rstest]
+    fn path_validation_timeout(
+        #[values("cubic", "bbr2", "bbr2_gcongestion")] cc_algorithm_name: &str,
+    ) {
+        let mut config = Config::new(crate::PROTOCOL_VERSION).unwrap();
+        assert_eq!(config.set_cc_algorithm_name(cc_algorithm_name), Ok(()));
+        config
+            .load_cert_chain_from_pem_file("examples/cert.crt")
+            .unwrap();
+        config
+            .load_priv_key_from_pem_file("examples/cert.key")
+            .unwrap();
+        config
+            .set_application_protos(&[b"proto1", b"proto2"])
+            .unwrap();
+        config.verify_peer(false);
+        config.set_active_connection_id_limit(2);
+        config.set_max_path_challenges(usize::MAX);
+        config.set_path_validation_timeout(20);
+
+        let mut pipe = pipe_with_exchanged_cids(&mut config, 16, 16, 1);
+
+        let server_addr = testing::Pipe::server_addr();
+        let client_addr_2 = "127.0.0.1:5678".parse().unwrap();
+        assert_eq!(pipe.client.probe_path(client_addr_2, server_addr), Ok(1));
+
+        // Lose everything the client sends until the validation times out.
+        while pipe.client.path_event_next() !=
+            Some(PathEvent::FailedValidation(client_addr_2, server_addr))
+        {
+            testing::emit_flight(&mut pipe.client).ok();
+
+            let timer = pipe.client.timeout().unwrap();
+            std::thread::sleep(timer + time::Duration::from_millis(1));
+
+            pipe.client.on_timeout();
+        }
+
+        let stats = pipe
+            .client
+            .path_stats()
+            .find(|s| s.local_addr == client_addr_2)
+            .unwrap();
+        assert_eq!(stats.validation_state, path::PathState::Failed);
+        assert!(
+            stats.validation_duration.unwrap() >= time::Duration::from_millis(20)
+        );
+    }
+
+    #[rstest]
+    fn path_validation_duration(
+        #[values("cubic", "bbr2", "bbr2_gcongestion")] cc_algorithm_name: &str,
+    ) {
+        let mut config = Config::new(crate::PROTOCOL_VERSION).unwrap();
+        assert_eq!(config.set_cc_algorithm_name(cc_algorithm_name), Ok(()));
+        config
+            .load_cert_chain_from_pem_file("examples/cert.crt")
+            .unwrap();
+        config
+            .load_priv_key_from_pem_file("examples/cert.key")
+            .unwrap();
+        config
+            .set_application_protos(&[b"proto1", b"proto2"])
+            .unwrap();
+        config.verify_peer(false);
+        config.set_active_connection_id_limit(2);
+        config.set_path_validation_timeout(1000);
+
+        let mut pipe = pipe_with_exchanged_cids(&mut config, 16, 16, 1);
+
+        let server_addr = testing::Pipe::server_addr();
+        let client_addr_2 = "127.0.0.1:5678".parse().unwrap();
+        assert_eq!(pipe.client.probe_path(client_addr_2, server_addr), Ok(1));
+        assert_eq!(pipe.advance(), Ok(()));
+
+        assert_eq!(
+            pipe.client.is_path_validated(client_addr_2, server_addr),
+            Ok(true)
+        );
+
+        let stats = pipe
+            .client
+            .path_stats()
+            .find(|s| s.local_addr == client_addr_2)
+            .unwrap();
+        assert!(stats.validation_duration.is_some());
+
+        // Once validated, the path is not subject to the validation timeout
+        // anymore.
+        let pid = pipe
+            .client
+            .paths
+            .path_id_from_addrs(&(client_addr_2, server_addr))
+            .unwrap();
+        assert_eq!(
+            pipe.client
+                .paths
+                .get(pid)
+                .unwrap()
+                .validation_timer(&pipe.client.path_validation),
+            None
+        );
+    }
+
+    #[rstest]
+    fn client_discard_unknown_address(
+        #[values("cubic", "bbr2", "bbr2_gcongestion")] cc_algorithm_name: &str,
+    ) {
+        let mut config = Config::new(crate::PROTOCOL_VERSION).unwrap();
+        assert_eq!(config.set_cc_algorithm_name(cc_algorithm_name), Ok(()));
+        config
+            .load_cert_chain_from_pem_file("examples/cert.crt")
+            .unwrap();
+        config
+            .load_priv_key_from_pem_file("examples/cert.key")
+            .unwrap();
+        config
+            .set_application_protos(&[b"proto1", b"proto2"])
+            .unwrap();
+        config.verify_peer(false);
+        config.set_initial_max_data(30);
+        config.set_initial_max_stream_data_uni(10);
+        config.set_initial_max_streams_uni(3);
+
+        let mut pipe = testing::Pipe::with_config(&mut config).unwrap();
+        assert_eq!(pipe.handshake(), Ok(()));
+
+        // Server sends stream data.
+        assert_eq!(pipe.server.stream_send(3, b"a", true), Ok(1));
+
+        let mut flight =
+            testing::emit_flight(&mut pipe.server).expect("no packet");
+        // Let's change the address info.
+        flight
+            .iter_mut()
+            .for_each(|(_, si)| si.from = "127.0.0.1:9292".parse().unwrap());
+        assert_eq!(testing::process_flight(&mut pipe.client, flight), Ok(()));
+        assert_eq!(pipe.client.paths.len(), 1);
+    }
+
+    #[rstest]
+    fn path_validation_limited_mtu(
+        #[values("cubic", "bbr2", "bbr2_gcongestion")] cc_algorithm_name: &str,
+    ) {
+        let mut config = Config::new(crate::PROTOCOL_VERSION).unwrap();
+        assert_eq!(config.set_cc_algorithm_name(cc_algorithm_name), Ok(()));
+        config
+            .load_cert_chain_from_pem_file("examples/cert.crt")
+            .unwrap();
+        config
+            .load_priv_key_from_pem_file("examples/cert.key")
+            .unwrap();
+        config
+            .set_application_protos(&[b"proto1", b"proto2"])
+            .unwrap();
+        config.verify_peer(false);
+        config.set_active_connection_id_limit(2);
+
+        let mut pipe = pipe_with_exchanged_cids(&mut config, 16, 16, 1);
+
+        let server_addr = testing::Pipe::server_addr();
+        let client_addr_2 = "127.0.0.1:5678".parse().unwrap();
+        assert_eq!(pipe.client.probe_path(client_addr_2, server_addr), Ok(1));
+        // Limited MTU of 1199 bytes for some reason.
+        testing::process_flight(
+            &mut pipe.server,
+            testing::emit_flight_with_max_buffer(
+                &mut pipe.client,
+                1199,
+                None,
+                None,
+            )
+            .expect("no packet"),
+        )
+        .expect("error when processing client packets");
+        testing::process_flight(
+            &mut pipe.client,
+            testing::emit_flight(&mut pipe.server).expect("no packet"),
+        )
         .expect("error when processing client packets");
         let probed_pid = pipe
             .client
@@ -17423,6 +20464,10 @@ mod tests {
             .path_id_from_addrs(&(client_addr_2, server_addr))
             .unwrap();
         assert!(!pipe.client.paths.get(probed_pid).unwrap().validated(),);
+        assert_eq!(
+            pipe.client.path_event_next(),
+            Some(PathEvent::ValidationStarted(client_addr_2, server_addr))
+        );
         assert_eq!(pipe.client.path_event_next(), None);
         // Now let the client probe at its MTU.
         assert_eq!(pipe.advance(), Ok(()));
@@ -17460,6 +20505,10 @@ mod tests {
         assert_eq!(pipe.advance(), Ok(()));
 
         // The path should be validated at some point.
+        assert_eq!(
+            pipe.client.path_event_next(),
+            Some(PathEvent::ValidationStarted(client_addr_2, server_addr))
+        );
         assert_eq!(
             pipe.client.path_event_next(),
             Some(PathEvent::Validated(client_addr_2, server_addr))
@@ -17471,6 +20520,10 @@ mod tests {
             pipe.server.path_event_next(),
             Some(PathEvent::New(server_addr, client_addr_2))
         );
+        assert_eq!(
+            pipe.server.path_event_next(),
+            Some(PathEvent::ValidationStarted(server_addr, client_addr_2))
+        );
         assert_eq!(
             pipe.server.path_event_next(),
             Some(PathEvent::Validated(server_addr, client_addr_2))
@@ -17580,6 +20633,7 @@ mod tests {
         let ri = RecvInfo {
             to: si.to,
             from: si.from,
+            ecn: si.ecn,
         };
         assert_eq!(pipe.server.recv(&mut buf[..sent], ri), Ok(sent));
 
@@ -17624,6 +20678,7 @@ mod tests {
         let ri = RecvInfo {
             to: si.to,
             from: si.from,
+            ecn: si.ecn,
         };
         assert_eq!(pipe.server.recv(&mut buf[..sent], ri), Ok(sent));
 
@@ -17641,6 +20696,7 @@ mod tests {
         let ri = RecvInfo {
             to: si.to,
             from: si.from,
+            ecn: si.ecn,
         };
         assert_eq!(pipe.server.recv(&mut buf[..sent], ri), Ok(sent));
 
@@ -17659,6 +20715,7 @@ mod tests {
         let ri = RecvInfo {
             to: si.to,
             from: si.from,
+            ecn: si.ecn,
         };
         assert_eq!(pipe.server.recv(&mut buf[..sent], ri), Ok(sent));
 
@@ -17676,6 +20733,7 @@ mod tests {
         let ri = RecvInfo {
             to: si.to,
             from: si.from,
+            ecn: si.ecn,
         };
         assert_eq!(pipe.server.recv(&mut buf[..sent], ri), Ok(sent));
 
@@ -17719,12 +20777,9 @@ mod tests {
         assert_eq!(stats.path_challenge_rx_count, 3);
     }
 
-    #[rstest]
-    fn connection_migration(
-        #[values("cubic", "bbr2", "bbr2_gcongestion")] cc_algorithm_name: &str,
-    ) {
+    #[test]
+    fn multipath() {
         let mut config = Config::new(crate::PROTOCOL_VERSION).unwrap();
-        assert_eq!(config.set_cc_algorithm_name(cc_algorithm_name), Ok(()));
         config
             .load_cert_chain_from_pem_file("examples/cert.crt")
             .unwrap();
@@ -17736,42 +20791,276 @@ mod tests {
             .unwrap();
         config.verify_peer(false);
         config.set_active_connection_id_limit(3);
-        config.set_initial_max_data(30);
-        config.set_initial_max_stream_data_bidi_local(15);
-        config.set_initial_max_stream_data_bidi_remote(15);
-        config.set_initial_max_stream_data_uni(10);
+        config.set_initial_max_data(100_000);
+        config.set_initial_max_stream_data_bidi_local(100_000);
+        config.set_initial_max_stream_data_bidi_remote(100_000);
         config.set_initial_max_streams_bidi(3);
+        config.enable_multipath(true);
 
         let mut pipe = pipe_with_exchanged_cids(&mut config, 16, 16, 2);
+        assert!(pipe.client.is_multipath_enabled());
+        assert!(pipe.server.is_multipath_enabled());
 
+        let client_addr = testing::Pipe::client_addr();
         let server_addr = testing::Pipe::server_addr();
         let client_addr_2 = "127.0.0.1:5678".parse().unwrap();
-        let client_addr_3 = "127.0.0.1:9012".parse().unwrap();
-        let client_addr_4 = "127.0.0.1:8908".parse().unwrap();
 
-        // Case 1: the client first probes the new address, the server too, and
-        // then migrates.
-        assert_eq!(pipe.client.probe_path(client_addr_2, server_addr), Ok(1));
-        assert_eq!(pipe.advance(), Ok(()));
+        // Abandoning the only path is not allowed.
         assert_eq!(
-            pipe.client.path_event_next(),
-            Some(PathEvent::Validated(client_addr_2, server_addr))
+            pipe.client.abandon_path(client_addr, server_addr, 0, b""),
+            Err(Error::InvalidState)
         );
-        assert_eq!(pipe.client.path_event_next(), None);
+
+        assert_eq!(pipe.client.probe_path(client_addr_2, server_addr), Ok(1));
+        assert_eq!(pipe.advance(), Ok(()));
+
+        // Once validated, the new path is used alongside the first one.
+        let active_paths =
+            |c: &Connection| c.paths.iter().filter(|(_, p)| p.active()).count();
+        assert_eq!(active_paths(&pipe.client), 2);
+        assert_eq!(active_paths(&pipe.server), 2);
+
+        // Steer the traffic to the new path.
         assert_eq!(
-            pipe.server.path_event_next(),
-            Some(PathEvent::New(server_addr, client_addr_2))
+            pipe.client.set_path_status(
+                client_addr,
+                server_addr,
+                PathStatus::Standby
+            ),
+            Ok(())
         );
+
+        let pid = pipe
+            .client
+            .paths
+            .path_id_from_addrs(&(client_addr_2, server_addr))
+            .unwrap();
+        let sent_before = pipe.client.paths.get(pid).unwrap().sent_count;
+
+        assert_eq!(pipe.client.stream_send(0, &[0; 5000], true), Ok(5000));
+        assert_eq!(pipe.advance(), Ok(()));
+
+        assert!(pipe.client.paths.get(pid).unwrap().sent_count > sent_before);
+
+        let mut buf = [0; 5000];
+        assert_eq!(pipe.server.stream_recv(0, &mut buf), Ok((5000, true)));
+
+        // The server learnt about the standby path, and acknowledged packets of
+        // both paths in their own packet number space.
+        let server_pid = pipe
+            .server
+            .paths
+            .path_id_from_addrs(&(server_addr, client_addr))
+            .unwrap();
+        assert!(pipe.server.paths.get(server_pid).unwrap().standby());
+        assert!(pipe.server.multipath.space(0).is_some());
+        assert!(pipe.server.multipath.space(1).is_some());
+
+        // Abandon the new path.
         assert_eq!(
-            pipe.server.path_event_next(),
-            Some(PathEvent::Validated(server_addr, client_addr_2))
+            pipe.client.abandon_path(client_addr_2, server_addr, 0, b""),
+            Ok(())
         );
         assert_eq!(
-            pipe.client.is_path_validated(client_addr_2, server_addr),
-            Ok(true)
+            pipe.client.path_event_next(),
+            Some(PathEvent::Closed(client_addr_2, server_addr))
         );
-        assert_eq!(
-            pipe.server.is_path_validated(server_addr, client_addr_2),
+        assert_eq!(pipe.advance(), Ok(()));
+
+        let server_pid = pipe
+            .server
+            .paths
+            .path_id_from_addrs(&(server_addr, client_addr_2))
+            .unwrap();
+        assert!(pipe.server.paths.get(server_pid).unwrap().abandoned());
+        assert!(std::iter::from_fn(|| pipe.server.path_event_next())
+            .any(|e| e == PathEvent::Closed(server_addr, client_addr_2)));
+
+        assert_eq!(active_paths(&pipe.client), 1);
+        assert_eq!(active_paths(&pipe.server), 1);
+    }
+
+    #[test]
+    fn multipath_uneven_traffic() {
+        let mut config = Config::new(crate::PROTOCOL_VERSION).unwrap();
+        config
+            .load_cert_chain_from_pem_file("examples/cert.crt")
+            .unwrap();
+        config
+            .load_priv_key_from_pem_file("examples/cert.key")
+            .unwrap();
+        config
+            .set_application_protos(&[b"proto1", b"proto2"])
+            .unwrap();
+        config.verify_peer(false);
+        config.set_active_connection_id_limit(3);
+        config.set_initial_max_data(1_000_000);
+        config.set_initial_max_stream_data_bidi_local(1_000_000);
+        config.set_initial_max_stream_data_bidi_remote(1_000_000);
+        config.set_initial_max_streams_bidi(10);
+        config.enable_multipath(true);
+
+        let mut pipe = pipe_with_exchanged_cids(&mut config, 16, 16, 2);
+
+        let client_addr = testing::Pipe::client_addr();
+        let server_addr = testing::Pipe::server_addr();
+        let client_addr_2 = "127.0.0.1:5678".parse().unwrap();
+
+        assert_eq!(pipe.client.probe_path(client_addr_2, server_addr), Ok(1));
+        assert_eq!(pipe.advance(), Ok(()));
+
+        // Alternate between the paths, leaving one idle while the other sends
+        // a lot more packets, and finally use both at once.
+        let rounds = [
+            (0, PathStatus::Standby, PathStatus::Available, 50_000),
+            (4, PathStatus::Available, PathStatus::Standby, 2_000),
+            (8, PathStatus::Standby, PathStatus::Available, 20_000),
+            (12, PathStatus::Available, PathStatus::Standby, 500),
+            (16, PathStatus::Available, PathStatus::Available, 30_000),
+        ];
+
+        let mut buf = vec![0; 50_000];
+
+        for (stream_id, status, status_2, len) in rounds {
+            assert_eq!(
+                pipe.client
+                    .set_path_status(client_addr, server_addr, status),
+                Ok(())
+            );
+            assert_eq!(
+                pipe.client
+                    .set_path_status(client_addr_2, server_addr, status_2),
+                Ok(())
+            );
+
+            assert_eq!(
+                pipe.client.stream_send(stream_id, &buf[..len], true),
+                Ok(len)
+            );
+            assert_eq!(pipe.advance(), Ok(()));
+
+            assert_eq!(
+                pipe.server.stream_recv(stream_id, &mut buf),
+                Ok((len, true))
+            );
+        }
+
+        // Every packet was decrypted in the packet number space of its path.
+        assert_eq!(pipe.client.stats().lost, 0);
+        assert_eq!(
+            pipe.server.multipath.space(0).unwrap().largest_rx_pkt_num + 1,
+            pipe.client.next_pkt_num
+        );
+        assert_eq!(
+            pipe.server.multipath.space(1).unwrap().largest_rx_pkt_num + 1,
+            pipe.client.multipath.next_pkt_num(1)
+        );
+    }
+
+    #[test]
+    fn multipath_ack_after_retiring_initial_dcid() {
+        let mut config = Config::new(crate::PROTOCOL_VERSION).unwrap();
+        config
+            .load_cert_chain_from_pem_file("examples/cert.crt")
+            .unwrap();
+        config
+            .load_priv_key_from_pem_file("examples/cert.key")
+            .unwrap();
+        config
+            .set_application_protos(&[b"proto1", b"proto2"])
+            .unwrap();
+        config.verify_peer(false);
+        config.set_active_connection_id_limit(3);
+        config.set_initial_max_data(100_000);
+        config.set_initial_max_stream_data_bidi_local(100_000);
+        config.set_initial_max_stream_data_bidi_remote(100_000);
+        config.set_initial_max_streams_bidi(3);
+        config.enable_multipath(true);
+
+        let mut pipe = pipe_with_exchanged_cids(&mut config, 16, 16, 2);
+
+        assert_eq!(pipe.client.stream_send(0, &[0; 5000], true), Ok(5000));
+
+        let flight = testing::emit_flight(&mut pipe.client).unwrap();
+        testing::process_flight(&mut pipe.server, flight).unwrap();
+
+        // Retire the connection ID the packets were sent to before they are
+        // acknowledged.
+        assert_eq!(pipe.client.retire_dcid(0), Ok(()));
+        assert_eq!(pipe.advance(), Ok(()));
+
+        let mut buf = [0; 5000];
+        assert_eq!(pipe.server.stream_recv(0, &mut buf), Ok((5000, true)));
+
+        // The acknowledgements still reached the path the packets were sent
+        // on.
+        let path = pipe.client.paths.get_active().unwrap();
+        assert_ne!(path.active_dcid_seq, Some(0));
+        assert_eq!(path.recovery.bytes_in_flight(), 0);
+        assert_eq!(pipe.client.stats().lost, 0);
+    }
+
+    #[rstest]
+    fn connection_migration(
+        #[values("cubic", "bbr2", "bbr2_gcongestion")] cc_algorithm_name: &str,
+    ) {
+        let mut config = Config::new(crate::PROTOCOL_VERSION).unwrap();
+        assert_eq!(config.set_cc_algorithm_name(cc_algorithm_name), Ok(()));
+        config
+            .load_cert_chain_from_pem_file("examples/cert.crt")
+            .unwrap();
+        config
+            .load_priv_key_from_pem_file("examples/cert.key")
+            .unwrap();
+        config
+            .set_application_protos(&[b"proto1", b"proto2"])
+            .unwrap();
+        config.verify_peer(false);
+        config.set_active_connection_id_limit(3);
+        config.set_initial_max_data(30);
+        config.set_initial_max_stream_data_bidi_local(15);
+        config.set_initial_max_stream_data_bidi_remote(15);
+        config.set_initial_max_stream_data_uni(10);
+        config.set_initial_max_streams_bidi(3);
+
+        let mut pipe = pipe_with_exchanged_cids(&mut config, 16, 16, 2);
+
+        let server_addr = testing::Pipe::server_addr();
+        let client_addr_2 = "127.0.0.1:5678".parse().unwrap();
+        let client_addr_3 = "127.0.0.1:9012".parse().unwrap();
+        let client_addr_4 = "127.0.0.1:8908".parse().unwrap();
+
+        // Case 1: the client first probes the new address, the server too, and
+        // then migrates.
+        assert_eq!(pipe.client.probe_path(client_addr_2, server_addr), Ok(1));
+        assert_eq!(pipe.advance(), Ok(()));
+        assert_eq!(
+            pipe.client.path_event_next(),
+            Some(PathEvent::ValidationStarted(client_addr_2, server_addr))
+        );
+        assert_eq!(
+            pipe.client.path_event_next(),
+            Some(PathEvent::Validated(client_addr_2, server_addr))
+        );
+        assert_eq!(pipe.client.path_event_next(), None);
+        assert_eq!(
+            pipe.server.path_event_next(),
+            Some(PathEvent::New(server_addr, client_addr_2))
+        );
+        assert_eq!(
+            pipe.server.path_event_next(),
+            Some(PathEvent::ValidationStarted(server_addr, client_addr_2))
+        );
+        assert_eq!(
+            pipe.server.path_event_next(),
+            Some(PathEvent::Validated(server_addr, client_addr_2))
+        );
+        assert_eq!(
+            pipe.client.is_path_validated(client_addr_2, server_addr),
+            Ok(true)
+        );
+        assert_eq!(
+            pipe.server.is_path_validated(server_addr, client_addr_2),
             Ok(true)
         );
         // The server can never initiates the connection migration.
@@ -17845,6 +21134,10 @@ mod tests {
             pipe.server.path_event_next(),
             Some(PathEvent::New(server_addr, client_addr_3))
         );
+        assert_eq!(
+            pipe.server.path_event_next(),
+            Some(PathEvent::ValidationStarted(server_addr, client_addr_3))
+        );
         assert_eq!(
             pipe.server.path_event_next(),
             Some(PathEvent::Validated(server_addr, client_addr_3))
@@ -17988,6 +21281,10 @@ mod tests {
             pipe.server.path_event_next(),
             Some(PathEvent::New(server_addr, client_addr_2))
         );
+        assert_eq!(
+            pipe.server.path_event_next(),
+            Some(PathEvent::ValidationStarted(server_addr, client_addr_2))
+        );
         assert_eq!(
             pipe.server.path_event_next(),
             Some(PathEvent::Validated(server_addr, client_addr_2))
@@ -18046,6 +21343,10 @@ mod tests {
 
         assert_eq!(pipe.client.probe_path(client_addr_2, server_addr), Ok(1));
         assert_eq!(pipe.advance(), Ok(()));
+        assert_eq!(
+            pipe.client.path_event_next(),
+            Some(PathEvent::ValidationStarted(client_addr_2, server_addr))
+        );
         assert_eq!(
             pipe.client.path_event_next(),
             Some(PathEvent::Validated(client_addr_2, server_addr))
@@ -18055,6 +21356,10 @@ mod tests {
             pipe.server.path_event_next(),
             Some(PathEvent::New(server_addr, client_addr_2))
         );
+        assert_eq!(
+            pipe.server.path_event_next(),
+            Some(PathEvent::ValidationStarted(server_addr, client_addr_2))
+        );
         assert_eq!(
             pipe.server.path_event_next(),
             Some(PathEvent::Validated(server_addr, client_addr_2))
@@ -18199,6 +21504,13 @@ mod tests {
 
         // Because of the small ACK size, the server cannot send more to the
         // client. Fallback on the previous active path.
+        assert_eq!(
+            pipe.server.path_event_next(),
+            Some(PathEvent::ValidationStarted(
+                server_addr,
+                spoofed_client_addr
+            ))
+        );
         assert_eq!(
             pipe.server.path_event_next(),
             Some(PathEvent::FailedValidation(
@@ -18329,6 +21641,118 @@ mod tests {
         assert!(iter.next().is_none());
     }
 
+    #[test]
+    fn ack_frequency() {
+        let mut buf = [0; 65535];
+
+        let mut config = Config::new(crate::PROTOCOL_VERSION).unwrap();
+        config
+            .load_cert_chain_from_pem_file("examples/cert.crt")
+            .unwrap();
+        config
+            .load_priv_key_from_pem_file("examples/cert.key")
+            .unwrap();
+        config
+            .set_application_protos(&[b"proto1", b"proto2"])
+            .unwrap();
+        config.verify_peer(false);
+        config.set_initial_max_data(100_000);
+        config.set_initial_max_stream_data_bidi_local(100_000);
+        config.set_initial_max_stream_data_bidi_remote(100_000);
+        config.set_initial_max_streams_bidi(3);
+        config.set_min_ack_delay(1_000);
+        config.set_ack_eliciting_threshold(10);
+        config.set_requested_max_ack_delay(time::Duration::from_millis(20));
+
+        let mut pipe = testing::Pipe::with_config(&mut config).unwrap();
+        assert_eq!(pipe.handshake(), Ok(()));
+        assert_eq!(pipe.advance(), Ok(()));
+
+        // Until the request is acknowledged, the larger delay is assumed.
+        assert_eq!(
+            pipe.client.recovery_config.max_ack_delay,
+            time::Duration::from_millis(25)
+        );
+
+        // Ack-eliciting packets are no longer acknowledged right away.
+        assert_eq!(pipe.client.stream_send(0, b"a", false), Ok(1));
+        assert_eq!(pipe.advance(), Ok(()));
+
+        assert!(
+            !pipe.server.pkt_num_spaces[packet::Epoch::Application].ack_elicited
+        );
+        assert!(pipe.server.ack_freq.ack_timer().is_some());
+
+        // Unless an immediate acknowledgement is requested.
+        assert_eq!(pipe.client.send_immediate_ack(), Ok(()));
+
+        let flight = testing::emit_flight(&mut pipe.client).unwrap();
+        testing::process_flight(&mut pipe.server, flight).unwrap();
+
+        let (len, _) = pipe.server.send(&mut buf).unwrap();
+        assert!(pipe.server.ack_freq.ack_timer().is_none());
+
+        assert_eq!(pipe.client_recv(&mut buf[..len]), Ok(len));
+
+        // The peer acknowledged the request, so the requested delay is used.
+        assert_eq!(
+            pipe.client.recovery_config.max_ack_delay,
+            time::Duration::from_millis(20)
+        );
+    }
+
+    #[test]
+    fn immediate_ack_not_negotiated() {
+        let mut pipe = testing::Pipe::new("cubic").unwrap();
+        assert_eq!(pipe.handshake(), Ok(()));
+
+        assert_eq!(pipe.client.send_immediate_ack(), Err(Error::InvalidState));
+    }
+
+    #[test]
+    fn ack_frequency_invalid_max_ack_delay() {
+        let mut buf = [0; 65535];
+
+        let mut config = Config::new(crate::PROTOCOL_VERSION).unwrap();
+        config
+            .load_cert_chain_from_pem_file("examples/cert.crt")
+            .unwrap();
+        config
+            .load_priv_key_from_pem_file("examples/cert.key")
+            .unwrap();
+        config
+            .set_application_protos(&[b"proto1", b"proto2"])
+            .unwrap();
+        config.verify_peer(false);
+        config.set_min_ack_delay(1_000);
+
+        let mut pipe = testing::Pipe::with_config(&mut config).unwrap();
+        assert_eq!(pipe.handshake(), Ok(()));
+
+        // The requested delay is lower than the advertised `min_ack_delay`.
+        let frames = [frame::Frame::AckFrequency {
+            seq_num: 0,
+            ack_eliciting_threshold: 1,
+            request_max_ack_delay: 999,
+            reordering_threshold: 1,
+        }];
+
+        let pkt_type = packet::Type::Short;
+        assert_eq!(
+            pipe.send_pkt_to_server(pkt_type, &frames, &mut buf),
+            Err(Error::InvalidFrame)
+        );
+
+        assert_eq!(
+            pipe.server.local_error(),
+            Some(&ConnectionError {
+                is_app: false,
+                error_code: WireErrorCode::FrameEncodingError as u64,
+                reason: vec![],
+            })
+        );
+    }
+
     /// Tests that streams do not keep being "writable" after being collected
     /// on reset.
     #[rstest]
@@ -18595,10 +22019,11 @@ mod tests {
             frame.to_bytes(&mut b).expect("encode frames");
         }
 
-        let aead = space.crypto_seal.as_ref().expect("crypto seal");
+        let aead = space.crypto_seal.as_mut().expect("crypto seal");
 
         let written = packet::encrypt_pkt(
             &mut b,
+            0,
             pn,
             pn_len,
             payload_len,
@@ -18613,6 +22038,7 @@ mod tests {
             .recv(&mut pkt_buf[..written], RecvInfo {
                 to: server_addr,
                 from: client_addr_2,
+                ecn: Ecn::NotEct,
             })
             .expect("server receive path challenge");
 
@@ -18709,10 +22135,11 @@ mod tests {
             .path_id_from_addrs(&(server_addr, client_addr))
             .expect("no such path");
 
-        // Check that PMTU params are configured correctly
+        // Check that PMTU params are configured correctly, probes being capped
+        // by the peer's max_udp_payload_size.
         let pmtu_param = &mut pipe.server.paths.get_mut(pid_1).unwrap().pmtud;
         assert!(pmtu_param.get_probe_status());
-        assert_eq!(pmtu_param.get_probe_size(), 1350);
+        assert_eq!(pmtu_param.get_probe_size(), 1250);
         std::thread::sleep(
             pipe.server.paths.get_mut(pid_1).unwrap().recovery.rtt() +
                 time::Duration::from_millis(1),
@@ -18727,35 +22154,689 @@ mod tests {
         // Continue searching for PMTU
         assert!(pmtu_param.get_probe_status());
     }
-}
-
-pub use crate::packet::ConnectionId;
-pub use crate::packet::Header;
-pub use crate::packet::Type;
-
-pub use crate::path::PathEvent;
-pub use crate::path::PathStats;
-pub use crate::path::SocketAddrIter;
 
-pub use crate::recovery::BbrBwLoReductionStrategy;
-pub use crate::recovery::BbrParams;
-pub use crate::recovery::CongestionControlAlgorithm;
+    #[test]
+    fn pmtud_new_path() {
+        let mut config = Config::new(crate::PROTOCOL_VERSION).unwrap();
+        config
+            .load_cert_chain_from_pem_file("examples/cert.crt")
+            .unwrap();
+        config
+            .load_priv_key_from_pem_file("examples/cert.key")
+            .unwrap();
+        config
+            .set_application_protos(&[b"proto1", b"proto2"])
+            .unwrap();
+        config.verify_peer(false);
+        config.set_active_connection_id_limit(2);
+        config.set_max_send_udp_payload_size(1350);
+        config.set_max_recv_udp_payload_size(1350);
+        config.discover_pmtu(true);
+
+        let mut pipe = pipe_with_exchanged_cids(&mut config, 16, 16, 1);
+
+        let server_addr = testing::Pipe::server_addr();
+        let client_addr_2 = "127.0.0.1:5678".parse().unwrap();
+        assert_eq!(pipe.client.probe_path(client_addr_2, server_addr), Ok(1));
+        assert_eq!(pipe.advance(), Ok(()));
+
+        let pid = pipe
+            .server
+            .paths
+            .path_id_from_addrs(&(server_addr, client_addr_2))
+            .expect("no such path");
+
+        // The new path runs its own discovery, starting from the base size.
+        let path = pipe.server.paths.get(pid).unwrap();
+        assert!(path.validated());
+        assert!(path.pmtud.is_enabled());
+
+        // Exchange packets on the new path until the probe is acknowledged.
+        loop {
+            let server_flight = testing::emit_flight_on_path(
+                &mut pipe.server,
+                Some(server_addr),
+                Some(client_addr_2),
+            );
+
+            if let Ok(flight) = &server_flight {
+                testing::process_flight(&mut pipe.client, flight.clone())
+                    .unwrap();
+            }
+
+            let client_flight = testing::emit_flight_on_path(
+                &mut pipe.client,
+                Some(client_addr_2),
+                Some(server_addr),
+            );
+
+            if let Ok(flight) = &client_flight {
+                testing::process_flight(&mut pipe.server, flight.clone())
+                    .unwrap();
+            }
+
+            if server_flight.is_err() && client_flight.is_err() {
+                break;
+            }
+        }
+
+        let path = pipe.server.paths.get(pid).unwrap();
+        assert_eq!(path.pmtud.get_current(), 1350);
+        assert!(!path.pmtud.get_probe_status());
+        assert_eq!(path.recovery.max_datagram_size(), 1350);
+    }
+
+    fn ecn_config(ecn: Ecn) -> Config {
+        let mut config = Config::new(crate::PROTOCOL_VERSION).unwrap();
+        config
+            .load_cert_chain_from_pem_file("examples/cert.crt")
+            .unwrap();
+        config
+            .load_priv_key_from_pem_file("examples/cert.key")
+            .unwrap();
+        config
+            .set_application_protos(&[b"proto1", b"proto2"])
+            .unwrap();
+        config.set_initial_max_data(30);
+        config.set_initial_max_stream_data_bidi_local(15);
+        config.set_initial_max_stream_data_bidi_remote(15);
+        config.set_initial_max_streams_bidi(3);
+        config.set_cc_algorithm(CongestionControlAlgorithm::CUBIC);
+        config.verify_peer(false);
+        config.set_ecn(ecn);
+        config
+    }
+
+    #[test]
+    fn ecn_validation() {
+        let mut config = ecn_config(Ecn::Ect0);
+
+        let mut pipe = testing::Pipe::with_config(&mut config).unwrap();
+
+        let flight = testing::emit_flight(&mut pipe.client).unwrap();
+        assert!(flight.iter().all(|(_, si)| si.ecn == Ecn::Ect0));
+
+        testing::process_flight(&mut pipe.server, flight).unwrap();
+
+        assert_eq!(pipe.handshake(), Ok(()));
+        assert_eq!(pipe.advance(), Ok(()));
+
+        assert_eq!(pipe.client.stream_send(0, b"hello", true), Ok(5));
+        assert_eq!(pipe.advance(), Ok(()));
+
+        let client_path = pipe.client.paths.get_active().unwrap();
+        assert!(client_path.ecn.is_capable());
+        assert!(pipe.client.path_stats().all(|s| s.ecn_capable));
+
+        let server_path = pipe.server.paths.get_active().unwrap();
+        assert!(server_path.ecn.is_capable());
+
+        assert_eq!(pipe.client.stats().ecn_ce_count, 0);
+        assert_eq!(pipe.server.stats().ecn_ce_count, 0);
+
+        // Packets keep being marked once validated.
+        assert_eq!(pipe.client.stream_send(0, b"world", false), Ok(5));
+
+        let flight = testing::emit_flight(&mut pipe.client).unwrap();
+        assert!(flight.iter().all(|(_, si)| si.ecn == Ecn::Ect0));
+    }
+
+    #[test]
+    fn ecn_disabled() {
+        let mut config = ecn_config(Ecn::NotEct);
+
+        let mut pipe = testing::Pipe::with_config(&mut config).unwrap();
+        assert_eq!(pipe.handshake(), Ok(()));
+
+        assert_eq!(pipe.client.stream_send(0, b"hello", true), Ok(5));
+
+        let flight = testing::emit_flight(&mut pipe.client).unwrap();
+        assert!(flight.iter().all(|(_, si)| si.ecn == Ecn::NotEct));
+
+        testing::process_flight(&mut pipe.server, flight).unwrap();
+        assert_eq!(pipe.advance(), Ok(()));
+
+        assert!(!pipe.client.paths.get_active().unwrap().ecn.is_capable());
+    }
+
+    #[test]
+    fn ecn_marks_cleared() {
+        let mut config = ecn_config(Ecn::Ect0);
+
+        let mut pipe = testing::Pipe::with_config(&mut config).unwrap();
+
+        // The network clears the marks of the client's packets.
+        while !pipe.client.is_established() || !pipe.server.is_established() {
+            let mut flight = testing::emit_flight(&mut pipe.client).unwrap();
+
+            for (_, si) in flight.iter_mut() {
+                si.ecn = Ecn::NotEct;
+            }
+
+            testing::process_flight(&mut pipe.server, flight).unwrap();
+
+            let flight = testing::emit_flight(&mut pipe.server).unwrap();
+            testing::process_flight(&mut pipe.client, flight).unwrap();
+        }
+
+        // Validation failed on the client, so it stops marking packets.
+        assert!(!pipe.client.paths.get_active().unwrap().ecn.is_capable());
+
+        assert_eq!(pipe.client.stream_send(0, b"hello", true), Ok(5));
+
+        let flight = testing::emit_flight(&mut pipe.client).unwrap();
+        assert!(flight.iter().all(|(_, si)| si.ecn == Ecn::NotEct));
+
+        testing::process_flight(&mut pipe.server, flight).unwrap();
+        assert_eq!(pipe.advance(), Ok(()));
+
+        // The server's packets weren't affected.
+        assert!(pipe.server.paths.get_active().unwrap().ecn.is_capable());
+    }
+
+    #[test]
+    fn ecn_congestion_experienced() {
+        let mut config = ecn_config(Ecn::Ect0);
+
+        let mut pipe = testing::Pipe::with_config(&mut config).unwrap();
+        assert_eq!(pipe.handshake(), Ok(()));
+        assert_eq!(pipe.advance(), Ok(()));
+
+        assert!(pipe.client.paths.get_active().unwrap().ecn.is_capable());
+
+        let cwnd = pipe.client.paths.get_active().unwrap().recovery.cwnd();
+
+        // The network marks the client's packets with CE.
+        assert_eq!(pipe.client.stream_send(0, b"hello", true), Ok(5));
+
+        let mut flight = testing::emit_flight(&mut pipe.client).unwrap();
+        let marked = flight.len() as u64;
+
+        for (_, si) in flight.iter_mut() {
+            si.ecn = Ecn::Ce;
+        }
+
+        testing::process_flight(&mut pipe.server, flight).unwrap();
+        assert_eq!(pipe.advance(), Ok(()));
+
+        assert_eq!(pipe.client.stats().ecn_ce_count, marked);
+
+        let client_path = pipe.client.paths.get_active().unwrap();
+        assert!(client_path.ecn.is_capable());
+        assert_eq!(client_path.ecn.ce_count(), marked);
+        assert!(client_path.recovery.cwnd() < cwnd);
+    }
+
+    #[derive(Default)]
+    struct FixedWindow {
+        sent: Arc<std::sync::atomic::AtomicUsize>,
+        acked: Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    impl CongestionControl for FixedWindow {
+        fn new_instance(&self) -> Box<dyn CongestionControl> {
+            Box::new(FixedWindow {
+                sent: self.sent.clone(),
+                acked: self.acked.clone(),
+            })
+        }
+
+        fn on_packet_sent(
+            &mut self, sent_bytes: usize, _bytes_in_flight: usize,
+            _now: time::Instant,
+        ) {
+            self.sent
+                .fetch_add(sent_bytes, std::sync::atomic::Ordering::Relaxed);
+        }
+
+        fn on_packet_acked(
+            &mut self, acked_bytes: usize, _time_sent: time::Instant,
+            _bytes_in_flight: usize, _smoothed_rtt: Duration, _min_rtt: Duration,
+            _now: time::Instant,
+        ) {
+            self.acked
+                .fetch_add(acked_bytes, std::sync::atomic::Ordering::Relaxed);
+        }
+
+        fn on_packet_lost(
+            &mut self, _lost_bytes: usize, _time_sent: time::Instant,
+            _bytes_in_flight: usize, _now: time::Instant,
+        ) {
+        }
+
+        fn cwnd(&self) -> usize {
+            20_000
+        }
+    }
+
+    #[test]
+    fn custom_congestion_control() {
+        let cc = FixedWindow::default();
+        let sent = cc.sent.clone();
+        let acked = cc.acked.clone();
+
+        let mut config = Config::new(crate::PROTOCOL_VERSION).unwrap();
+        config
+            .load_cert_chain_from_pem_file("examples/cert.crt")
+            .unwrap();
+        config
+            .load_priv_key_from_pem_file("examples/cert.key")
+            .unwrap();
+        config
+            .set_application_protos(&[b"proto1", b"proto2"])
+            .unwrap();
+        config.set_initial_max_data(30);
+        config.set_initial_max_stream_data_bidi_local(15);
+        config.set_initial_max_stream_data_bidi_remote(15);
+        config.set_initial_max_streams_bidi(3);
+        config.set_cc_algorithm(CongestionControlAlgorithm::Bbr2Gcongestion);
+        config.set_custom_cc(Box::new(cc));
+        config.verify_peer(false);
+
+        let mut pipe = testing::Pipe::with_config(&mut config).unwrap();
+
+        // The custom controller takes precedence, even over gcongestion.
+        let path = pipe.client.paths.get_active().unwrap();
+        assert!(!path.recovery.gcongestion_enabled());
+        assert_eq!(path.recovery.cwnd(), 20_000);
+
+        assert_eq!(pipe.handshake(), Ok(()));
+
+        assert_eq!(pipe.client.stream_send(0, b"hello", true), Ok(5));
+        assert_eq!(pipe.advance(), Ok(()));
+
+        // Each endpoint reports to its own instance of the controller.
+        assert!(sent.load(std::sync::atomic::Ordering::Relaxed) > 0);
+        assert!(acked.load(std::sync::atomic::Ordering::Relaxed) > 0);
+
+        let path = pipe.client.paths.get_active().unwrap();
+        assert_eq!(path.recovery.cwnd(), 20_000);
+    }
+
+    #[test]
+    fn send_info_pacing() {
+        let mut buf = [0; 65535];
+
+        let mut pipe = testing::Pipe::new("cubic").unwrap();
+        assert_eq!(pipe.handshake(), Ok(()));
+
+        assert_eq!(pipe.client.stream_send(0, b"hello", true), Ok(5));
+
+        let (_, info) = pipe.client.send(&mut buf).unwrap();
+
+        let path = pipe.client.paths.get_active().unwrap();
+        assert!(info.pacing_rate() > 0);
+        assert_eq!(info.pacing_rate(), path.recovery.pacing_rate());
+
+        let stats = pipe.client.path_stats().next().unwrap();
+        assert_eq!(stats.pacing_rate, info.pacing_rate());
+
+        #[cfg(target_os = "linux")]
+        {
+            let later = SendInfo {
+                at: time::Instant::now() + Duration::from_secs(10),
+                ..info
+            };
+
+            let diff = later.txtime() - info.txtime();
+            assert!(diff > Duration::from_secs(9).as_nanos() as u64);
+            assert!(diff <= Duration::from_secs(10).as_nanos() as u64);
+        }
+    }
+
+    #[test]
+    fn send_multiple() {
+        let mut buf = [0; 65535];
+
+        let mut config = Config::new(crate::PROTOCOL_VERSION).unwrap();
+        config
+            .load_cert_chain_from_pem_file("examples/cert.crt")
+            .unwrap();
+        config
+            .load_priv_key_from_pem_file("examples/cert.key")
+            .unwrap();
+        config
+            .set_application_protos(&[b"proto1", b"proto2"])
+            .unwrap();
+        config.set_initial_max_data(100_000);
+        config.set_initial_max_stream_data_bidi_local(100_000);
+        config.set_initial_max_stream_data_bidi_remote(100_000);
+        config.set_initial_max_streams_bidi(3);
+        config.verify_peer(false);
+
+        let mut pipe = testing::Pipe::with_config(&mut config).unwrap();
+        assert_eq!(pipe.handshake(), Ok(()));
+
+        assert_eq!(pipe.client.stream_send(0, &[0; 5000], true), Ok(5000));
+
+        let (written, segment_size, info) =
+            pipe.client.send_multiple(&mut buf).unwrap();
+
+        // All datagrams are full sized, except for the last one.
+        assert_eq!(segment_size, pipe.client.max_send_udp_payload_size());
+        assert!(written > 4 * segment_size);
+        assert!(written < 6 * segment_size);
+
+        assert_eq!(info.from, testing::Pipe::client_addr());
+        assert_eq!(info.to, testing::Pipe::server_addr());
+
+        for dgram in buf[..written].chunks_mut(segment_size) {
+            let recv_info = RecvInfo {
+                to: info.to,
+                from: info.from,
+                ecn: info.ecn,
+            };
+
+            assert_eq!(pipe.server.recv(dgram, recv_info), Ok(dgram.len()));
+        }
+
+        let mut b = [0; 5000];
+        assert_eq!(pipe.server.stream_recv(0, &mut b), Ok((5000, true)));
+
+        assert_eq!(pipe.client.send_multiple(&mut buf), Err(Error::Done));
+    }
+
+    #[test]
+    fn recv_batch() {
+        let mut buf = [0; 65535];
+
+        let mut config = Config::new(crate::PROTOCOL_VERSION).unwrap();
+        config
+            .load_cert_chain_from_pem_file("examples/cert.crt")
+            .unwrap();
+        config
+            .load_priv_key_from_pem_file("examples/cert.key")
+            .unwrap();
+        config
+            .set_application_protos(&[b"proto1", b"proto2"])
+            .unwrap();
+        config.set_initial_max_data(100_000);
+        config.set_initial_max_stream_data_bidi_local(100_000);
+        config.set_initial_max_stream_data_bidi_remote(100_000);
+        config.set_initial_max_streams_bidi(3);
+        config.verify_peer(false);
+
+        let mut pipe = testing::Pipe::with_config(&mut config).unwrap();
+        assert_eq!(pipe.handshake(), Ok(()));
+
+        assert_eq!(pipe.client.stream_send(0, &[0; 5000], true), Ok(5000));
+
+        let (written, segment_size, info) =
+            pipe.client.send_multiple(&mut buf).unwrap();
+
+        let recv_info = RecvInfo {
+            to: info.to,
+            from: info.from,
+            ecn: info.ecn,
+        };
+
+        let mut batch: Vec<_> = buf[..written]
+            .chunks_mut(segment_size)
+            .map(|dgram| (dgram, recv_info))
+            .collect();
+        assert!(batch.len() > 1);
+
+        assert_eq!(pipe.server.recv_batch(&mut batch), Ok(written));
+
+        let mut b = [0; 5000];
+        assert_eq!(pipe.server.stream_recv(0, &mut b), Ok((5000, true)));
+
+        // All received packets are acknowledged at once.
+        let (len, _) = pipe.server.send(&mut buf).unwrap();
+        assert_eq!(pipe.client_recv(&mut buf[..len]), Ok(len));
+
+        let path = pipe.client.paths.get_active().unwrap();
+        assert_eq!(path.recovery.bytes_in_flight(), 0);
+
+        // An empty batch is a no-op.
+        assert_eq!(pipe.server.recv_batch(&mut []), Ok(0));
+    }
+
+    #[test]
+    fn recv_batch_empty_datagram() {
+        let mut buf = [0; 65535];
+
+        let mut config = Config::new(crate::PROTOCOL_VERSION).unwrap();
+        config
+            .load_cert_chain_from_pem_file("examples/cert.crt")
+            .unwrap();
+        config
+            .load_priv_key_from_pem_file("examples/cert.key")
+            .unwrap();
+        config
+            .set_application_protos(&[b"proto1", b"proto2"])
+            .unwrap();
+        config.set_initial_max_data(100_000);
+        config.set_initial_max_stream_data_bidi_local(100_000);
+        config.set_initial_max_stream_data_bidi_remote(100_000);
+        config.set_initial_max_streams_bidi(3);
+        config.verify_peer(false);
+
+        let mut pipe = testing::Pipe::with_config(&mut config).unwrap();
+        assert_eq!(pipe.handshake(), Ok(()));
+
+        assert_eq!(pipe.client.stream_send(0, &[0; 5000], true), Ok(5000));
+
+        let (written, segment_size, info) =
+            pipe.client.send_multiple(&mut buf).unwrap();
+
+        let recv_info = RecvInfo {
+            to: info.to,
+            from: info.from,
+            ecn: info.ecn,
+        };
+
+        let mut empty: [u8; 0] = [];
+
+        let mut batch: Vec<_> = buf[..written]
+            .chunks_mut(segment_size)
+            .map(|dgram| (dgram, recv_info))
+            .collect();
+        assert!(batch.len() > 1);
+
+        // The empty datagram in the middle of the batch is skipped, and the
+        // ones following it are still processed.
+        batch.insert(1, (&mut empty[..], recv_info));
+
+        assert_eq!(pipe.server.recv_batch(&mut batch), Ok(written));
+        assert!(!pipe.server.is_closed());
+
+        let mut b = [0; 5000];
+        assert_eq!(pipe.server.stream_recv(0, &mut b), Ok((5000, true)));
+    }
+
+    #[test]
+    fn stream_send_owned() {
+        let mut pipe = testing::Pipe::new("cubic").unwrap();
+        assert_eq!(pipe.handshake(), Ok(()));
+
+        // Only part of the buffer fits in the stream's flow control window.
+        let (written, rem) = pipe
+            .client
+            .stream_send_owned(0, b"hello, world!!!!!!!!".to_vec(), true)
+            .unwrap();
+        assert_eq!(written, 15);
+
+        let rem = rem.unwrap();
+        assert_eq!(rem.as_ref(), b"!!!!!");
+
+        // The buffer is handed back whole when nothing can be written.
+        let (written, rem) = pipe.client.stream_send_owned(0, rem, true).unwrap();
+        assert_eq!(written, 0);
+
+        let rem = rem.unwrap();
+        assert_eq!(rem.as_ref(), b"!!!!!");
+
+        assert_eq!(pipe.advance(), Ok(()));
+
+        let mut b = [0; 15];
+        assert_eq!(pipe.server.stream_recv(0, &mut b), Ok((15, false)));
+        assert_eq!(&b, b"hello, world!!!");
+
+        assert_eq!(pipe.advance(), Ok(()));
+
+        let (written, rem) = pipe.client.stream_send_owned(0, rem, true).unwrap();
+        assert_eq!(written, 5);
+        assert!(rem.is_none());
+
+        assert_eq!(pipe.advance(), Ok(()));
+
+        assert_eq!(pipe.server.stream_recv(0, &mut b), Ok((5, true)));
+        assert_eq!(&b[..5], b"!!!!!");
+    }
+
+    #[test]
+    fn stream_recv_v() {
+        let mut pipe = testing::Pipe::new("cubic").unwrap();
+        assert_eq!(pipe.handshake(), Ok(()));
+
+        assert_eq!(pipe.client.stream_send(4, b"hello, world", true), Ok(12));
+        assert_eq!(pipe.advance(), Ok(()));
+
+        let mut header = [0; 5];
+        let mut body = [0; 20];
+
+        let mut bufs = [
+            std::io::IoSliceMut::new(&mut header),
+            std::io::IoSliceMut::new(&mut body),
+        ];
+
+        assert_eq!(pipe.server.stream_recv_v(4, &mut bufs), Ok((12, true)));
+
+        assert!(!pipe.server.stream_readable(4));
+        assert_eq!(pipe.server.stream_recv_v(4, &mut bufs), Err(Error::Done));
+
+        assert_eq!(&header, b"hello");
+        assert_eq!(&body[..7], b", world");
+    }
+
+    /// A stream scheduler that always serves the stream with the highest ID.
+    #[derive(Default)]
+    struct HighestIdFirst {
+        streams: std::collections::BTreeSet<u64>,
+    }
+
+    impl StreamScheduler for HighestIdFirst {
+        fn new_instance(&self) -> Box<dyn StreamScheduler> {
+            Box::<HighestIdFirst>::default()
+        }
+
+        fn insert(&mut self, stream_id: u64, _urgency: u8, _incremental: bool) {
+            self.streams.insert(stream_id);
+        }
+
+        fn remove(&mut self, stream_id: u64) {
+            self.streams.remove(&stream_id);
+        }
+
+        fn next(&mut self) -> Option<u64> {
+            self.streams.last().copied()
+        }
+    }
+
+    #[test]
+    fn custom_stream_scheduler() {
+        let mut buf = [0; 65535];
+
+        let mut config = Config::new(crate::PROTOCOL_VERSION).unwrap();
+        config
+            .load_cert_chain_from_pem_file("examples/cert.crt")
+            .unwrap();
+        config
+            .load_priv_key_from_pem_file("examples/cert.key")
+            .unwrap();
+        config
+            .set_application_protos(&[b"proto1", b"proto2"])
+            .unwrap();
+        config.set_initial_max_data(30);
+        config.set_initial_max_stream_data_bidi_local(15);
+        config.set_initial_max_stream_data_bidi_remote(15);
+        config.set_initial_max_streams_bidi(3);
+        config.set_stream_scheduler(Box::<HighestIdFirst>::default());
+        config.verify_peer(false);
+
+        let mut pipe = testing::Pipe::with_config(&mut config).unwrap();
+        assert_eq!(pipe.handshake(), Ok(()));
+
+        assert_eq!(pipe.client.stream_send(0, b"aaaaa", false), Ok(5));
+        assert_eq!(pipe.client.stream_send(4, b"aaaaa", false), Ok(5));
+        assert_eq!(pipe.client.stream_send(8, b"aaaaa", false), Ok(5));
+
+        // Streams are sent in the order picked by the scheduler, rather than
+        // in the order they were written to.
+        for stream_id in [8, 4, 0] {
+            let (len, _) = pipe.client.send(&mut buf).unwrap();
+
+            let frames =
+                testing::decode_pkt(&mut pipe.server, &mut buf[..len]).unwrap();
+
+            assert!(frames.contains(&frame::Frame::Stream {
+                stream_id,
+                data: <RangeBuf>::from(b"aaaaa", 0, false),
+            }));
+        }
+
+        assert_eq!(pipe.client.send(&mut buf), Err(Error::Done));
+    }
+}
+
+pub use crate::ecn::Ecn;
+
+pub use crate::packet::ConnectionId;
+pub use crate::packet::Header;
+pub use crate::packet::Type;
+
+pub use crate::path::PathEvent;
+pub use crate::path::PathStats;
+pub use crate::path::PathStatus;
+pub use crate::path::SocketAddrIter;
+
+pub use crate::recovery::BbrBwLoReductionStrategy;
+pub use crate::recovery::BbrParams;
+pub use crate::recovery::CongestionControl;
+pub use crate::recovery::CongestionControlAlgorithm;
+pub use crate::recovery::PacerAlgorithm;
 use crate::recovery::RecoveryOps;
 
+pub use crate::stream::scheduler::StreamScheduler;
 pub use crate::stream::StreamIter;
 
 pub use crate::range_buf::BufFactory;
 pub use crate::range_buf::BufSplit;
+pub use crate::range_buf::DefaultBuf;
+
+#[cfg(feature = "internal")]
+#[doc(hidden)]
+pub use crate::rand::rand_bytes;
+#[cfg(feature = "internal")]
+#[doc(hidden)]
+pub use crate::rand::scoped_thread_rng;
+#[cfg(feature = "internal")]
+#[doc(hidden)]
+pub use crate::rand::set_thread_rng;
+#[cfg(feature = "internal")]
+#[doc(hidden)]
+pub use crate::rand::Rng;
+#[cfg(feature = "internal")]
+#[doc(hidden)]
+pub use crate::rand::SeededRng;
+#[cfg(feature = "internal")]
+#[doc(hidden)]
+pub use crate::rand::ThreadRngGuard;
 
+mod ack_frequency;
 mod cid;
 mod crypto;
 mod dgram;
+pub mod dispatcher;
+mod ecn;
 #[cfg(feature = "ffi")]
 mod ffi;
 mod flowcontrol;
 mod frame;
 pub mod h3;
 mod minmax;
+mod multipath;
 mod packet;
 mod path;
 mod pmtud;
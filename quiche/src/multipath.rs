@@ -0,0 +1,349 @@
+// Copyright (C) 2025, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Multipath extension state.
+//!
+//! Once both endpoints negotiated the multipath extension, every validated
+//! path can carry non-probing packets concurrently. Each path has its own
+//! packet number space, identified by the sequence number of the connection ID
+//! the packets are sent to, and packets received on a path are acknowledged
+//! with PATH_ACK frames referring to that space.
+//!
+//! As packet numbers are reused across paths, the path identifier is mixed
+//! into the packet protection nonce. Path 0 keeps drawing its packet numbers
+//! from the connection-wide counter used before the extension was negotiated,
+//! so its packets are protected exactly as without multipath.
+
+use std::cmp;
+use std::collections::BTreeMap;
+use std::time;
+
+use crate::ecn::Ecn;
+use crate::frame;
+use crate::packet;
+use crate::path::PathStatus;
+use crate::ranges;
+
+/// Receive-side packet number space of a single path.
+pub struct PathPktNumSpace {
+    pub largest_rx_pkt_num: u64,
+
+    pub largest_rx_pkt_time: time::Instant,
+
+    pub recv_pkt_need_ack: ranges::RangeSet,
+
+    pub recv_pkt_num: packet::PktNumWindow,
+
+    pub ack_elicited: bool,
+
+    pub ecn_counts: frame::EcnCounts,
+}
+
+impl PathPktNumSpace {
+    pub fn new() -> PathPktNumSpace {
+        PathPktNumSpace {
+            largest_rx_pkt_num: 0,
+
+            largest_rx_pkt_time: time::Instant::now(),
+
+            recv_pkt_need_ack: ranges::RangeSet::new(crate::MAX_ACK_RANGES),
+
+            recv_pkt_num: packet::PktNumWindow::default(),
+
+            ack_elicited: false,
+
+            ecn_counts: frame::EcnCounts::default(),
+        }
+    }
+
+    /// Records a successfully processed packet.
+    pub fn on_packet_received(
+        &mut self, pn: u64, ack_elicited: bool, ecn: Ecn, now: time::Instant,
+    ) {
+        // We only record the time of arrival of the largest packet number
+        // that still needs to be acked, to be used for ACK delay calculation.
+        if self.recv_pkt_need_ack.last() < Some(pn) {
+            self.largest_rx_pkt_time = now;
+        }
+
+        self.recv_pkt_num.insert(pn);
+
+        self.recv_pkt_need_ack.push_item(pn);
+
+        self.ack_elicited = cmp::max(self.ack_elicited, ack_elicited);
+
+        self.ecn_counts.on_packet_received(ecn);
+
+        self.largest_rx_pkt_num = cmp::max(self.largest_rx_pkt_num, pn);
+    }
+}
+
+/// A PATH_ABANDON frame waiting to be sent.
+#[derive(Clone)]
+pub struct PendingAbandon {
+    pub error_code: u64,
+
+    pub reason: Vec<u8>,
+}
+
+/// Multipath state of a connection.
+#[derive(Default)]
+pub struct Multipath {
+    /// Whether the extension was negotiated by both endpoints.
+    enabled: bool,
+
+    /// Receive-side packet number spaces, keyed by path identifier.
+    spaces: BTreeMap<u64, PathPktNumSpace>,
+
+    /// Next packet number to send on each path other than path 0, keyed by
+    /// path identifier.
+    next_pkt_nums: BTreeMap<u64, u64>,
+
+    /// Local path the latest 1-RTT packet of each path was sent on, keyed by
+    /// path identifier.
+    sent_paths: BTreeMap<u64, usize>,
+
+    /// PATH_ABANDON frames to send, keyed by path identifier.
+    abandons: BTreeMap<u64, PendingAbandon>,
+
+    /// Latest status advertised for each path, with its sequence number and
+    /// whether a PATH_STATUS frame still needs to be sent.
+    statuses: BTreeMap<u64, (u64, PathStatus, bool)>,
+
+    /// Sequence number of the next PATH_STATUS frame.
+    next_status_seq: u64,
+}
+
+impl Multipath {
+    /// Marks the extension as negotiated.
+    pub fn enable(&mut self) {
+        self.enabled = true;
+    }
+
+    /// Returns whether the extension was negotiated.
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Returns the next packet number to send on the given path.
+    pub fn next_pkt_num(&self, path_id: u64) -> u64 {
+        self.next_pkt_nums.get(&path_id).copied().unwrap_or(0)
+    }
+
+    /// Advances the packet number of the given path after sending a packet.
+    pub fn on_packet_sent(&mut self, path_id: u64) {
+        *self.next_pkt_nums.entry(path_id).or_insert(0) += 1;
+    }
+
+    /// Records the local path a 1-RTT packet of the given path was sent on.
+    pub fn set_sent_path(&mut self, path_id: u64, pid: usize) {
+        self.sent_paths.insert(path_id, pid);
+    }
+
+    /// Returns the local path the packets of the given path were sent on, if
+    /// any was sent.
+    pub fn sent_path(&self, path_id: u64) -> Option<usize> {
+        self.sent_paths.get(&path_id).copied()
+    }
+
+    /// Returns the packet number space of the given path, if any packet was
+    /// received on it.
+    pub fn space(&self, path_id: u64) -> Option<&PathPktNumSpace> {
+        self.spaces.get(&path_id)
+    }
+
+    /// Returns the packet number space of the given path, creating it if
+    /// needed.
+    pub fn space_mut(&mut self, path_id: u64) -> &mut PathPktNumSpace {
+        self.spaces
+            .entry(path_id)
+            .or_insert_with(PathPktNumSpace::new)
+    }
+
+    /// Returns an iterator over all the packet number spaces.
+    pub fn spaces_mut(
+        &mut self,
+    ) -> impl Iterator<Item = (&u64, &mut PathPktNumSpace)> {
+        self.spaces.iter_mut()
+    }
+
+    /// Drops the packet number space of the given path, once the connection ID
+    /// identifying it has been retired.
+    pub fn drop_space(&mut self, path_id: u64) {
+        self.spaces.remove(&path_id);
+    }
+
+    /// Stops acknowledging packets of the given path up to `largest`, after a
+    /// PATH_ACK frame covering them got acknowledged.
+    pub fn on_path_ack_acked(&mut self, path_id: u64, largest: u64) {
+        if let Some(space) = self.spaces.get_mut(&path_id) {
+            space.recv_pkt_need_ack.remove_until(largest);
+        }
+    }
+
+    /// Makes sure a PATH_ACK frame is sent again for the given path.
+    pub fn on_path_ack_lost(&mut self, path_id: u64) {
+        if let Some(space) = self.spaces.get_mut(&path_id) {
+            space.ack_elicited = true;
+        }
+    }
+
+    /// Schedules a PATH_ABANDON frame for the given path.
+    pub fn abandon(&mut self, path_id: u64, error_code: u64, reason: Vec<u8>) {
+        self.abandons
+            .insert(path_id, PendingAbandon { error_code, reason });
+    }
+
+    /// Returns the next PATH_ABANDON frame to send, if any.
+    pub fn next_abandon(&self) -> Option<(u64, PendingAbandon)> {
+        self.abandons
+            .iter()
+            .next()
+            .map(|(&path_id, abandon)| (path_id, abandon.clone()))
+    }
+
+    /// Removes the PATH_ABANDON frame of the given path from the send queue.
+    pub fn on_abandon_sent(&mut self, path_id: u64) {
+        self.abandons.remove(&path_id);
+    }
+
+    /// Schedules a PATH_STATUS frame advertising `status` for the given path.
+    pub fn set_status(&mut self, path_id: u64, status: PathStatus) {
+        let seq_num = self.next_status_seq;
+        self.next_status_seq += 1;
+
+        self.statuses.insert(path_id, (seq_num, status, true));
+    }
+
+    /// Returns the next PATH_STATUS frame to send, as a path identifier,
+    /// sequence number and status.
+    pub fn next_status(&self) -> Option<(u64, u64, PathStatus)> {
+        self.statuses
+            .iter()
+            .find(|(_, (_, _, pending))| *pending)
+            .map(|(&path_id, &(seq_num, status, _))| (path_id, seq_num, status))
+    }
+
+    /// Removes the PATH_STATUS frame of the given path from the send queue.
+    pub fn on_status_sent(&mut self, path_id: u64) {
+        if let Some((_, _, pending)) = self.statuses.get_mut(&path_id) {
+            *pending = false;
+        }
+    }
+
+    /// Schedules a lost PATH_STATUS frame again, unless a newer status was
+    /// advertised for that path since.
+    pub fn on_status_lost(&mut self, path_id: u64, seq_num: u64) {
+        if let Some((latest, _, pending)) = self.statuses.get_mut(&path_id) {
+            if *latest == seq_num {
+                *pending = true;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn path_spaces() {
+        let mut mp = Multipath::default();
+        let now = time::Instant::now();
+
+        mp.space_mut(1)
+            .on_packet_received(7, true, Ecn::NotEct, now);
+        mp.space_mut(1)
+            .on_packet_received(5, false, Ecn::NotEct, now);
+        mp.space_mut(2)
+            .on_packet_received(3, false, Ecn::NotEct, now);
+
+        let space = mp.space(1).unwrap();
+        assert_eq!(space.largest_rx_pkt_num, 7);
+        assert!(space.ack_elicited);
+        assert_eq!(space.recv_pkt_need_ack.len(), 2);
+
+        assert!(!mp.space(2).unwrap().ack_elicited);
+        assert!(mp.space(3).is_none());
+
+        mp.on_path_ack_acked(1, 5);
+        assert_eq!(mp.space(1).unwrap().recv_pkt_need_ack.len(), 1);
+
+        mp.drop_space(1);
+        assert!(mp.space(1).is_none());
+    }
+
+    #[test]
+    fn path_pkt_nums() {
+        let mut mp = Multipath::default();
+
+        assert_eq!(mp.next_pkt_num(1), 0);
+
+        mp.on_packet_sent(1);
+        mp.on_packet_sent(1);
+        mp.on_packet_sent(2);
+
+        assert_eq!(mp.next_pkt_num(1), 2);
+        assert_eq!(mp.next_pkt_num(2), 1);
+        assert_eq!(mp.next_pkt_num(3), 0);
+
+        assert_eq!(mp.sent_path(0), None);
+
+        mp.set_sent_path(0, 2);
+        mp.set_sent_path(1, 3);
+
+        assert_eq!(mp.sent_path(0), Some(2));
+        assert_eq!(mp.sent_path(1), Some(3));
+    }
+
+    #[test]
+    fn pending_frames() {
+        let mut mp = Multipath::default();
+
+        mp.abandon(4, 7, b"bye".to_vec());
+        let (path_id, abandon) = mp.next_abandon().unwrap();
+        assert_eq!(path_id, 4);
+        assert_eq!(abandon.error_code, 7);
+
+        mp.on_abandon_sent(4);
+        assert!(mp.next_abandon().is_none());
+
+        mp.set_status(2, PathStatus::Standby);
+        assert_eq!(mp.next_status(), Some((2, 0, PathStatus::Standby)));
+
+        mp.on_status_sent(2);
+        assert_eq!(mp.next_status(), None);
+
+        // A newer status supersedes the lost one.
+        mp.set_status(2, PathStatus::Available);
+        mp.on_status_sent(2);
+        mp.on_status_lost(2, 0);
+        assert_eq!(mp.next_status(), None);
+
+        mp.on_status_lost(2, 1);
+        assert_eq!(mp.next_status(), Some((2, 1, PathStatus::Available)));
+    }
+}
@@ -34,6 +34,7 @@ use crate::Error;
 use crate::Result;
 
 use crate::crypto;
+use crate::frame;
 use crate::rand;
 use crate::ranges;
 use crate::stream;
@@ -42,7 +43,7 @@ const FORM_BIT: u8 = 0x80;
 const FIXED_BIT: u8 = 0x40;
 const KEY_PHASE_BIT: u8 = 0x04;
 
-const TYPE_MASK: u8 = 0x30;
+pub(crate) const TYPE_MASK: u8 = 0x30;
 const PKT_NUM_MASK: u8 = 0x03;
 
 pub const MAX_CID_LEN: u8 = 20;
@@ -154,6 +155,54 @@ impl Type {
         }
     }
 
+    /// Decodes the type of a long header packet from the first byte, as the
+    /// mapping depends on the version (RFC 9369, Section 3.2).
+    pub(crate) fn from_long_header(first: u8, version: u32) -> Result<Type> {
+        let bits = (first & TYPE_MASK) >> 4;
+
+        let ty = if version == crate::PROTOCOL_VERSION_V2 {
+            match bits {
+                0x00 => Type::Retry,
+                0x01 => Type::Initial,
+                0x02 => Type::ZeroRTT,
+                0x03 => Type::Handshake,
+                _ => return Err(Error::InvalidPacket),
+            }
+        } else {
+            match bits {
+                0x00 => Type::Initial,
+                0x01 => Type::ZeroRTT,
+                0x02 => Type::Handshake,
+                0x03 => Type::Retry,
+                _ => return Err(Error::InvalidPacket),
+            }
+        };
+
+        Ok(ty)
+    }
+
+    fn to_long_header(self, version: u32) -> Result<u8> {
+        let bits = if version == crate::PROTOCOL_VERSION_V2 {
+            match self {
+                Type::Retry => 0x00,
+                Type::Initial => 0x01,
+                Type::ZeroRTT => 0x02,
+                Type::Handshake => 0x03,
+                _ => return Err(Error::InvalidPacket),
+            }
+        } else {
+            match self {
+                Type::Initial => 0x00,
+                Type::ZeroRTT => 0x01,
+                Type::Handshake => 0x02,
+                Type::Retry => 0x03,
+                _ => return Err(Error::InvalidPacket),
+            }
+        };
+
+        Ok(bits << 4)
+    }
+
     #[cfg(feature = "qlog")]
     pub(crate) fn to_qlog(self) -> qlog::events::quic::PacketType {
         match self {
@@ -371,13 +420,7 @@ impl<'a> Header<'a> {
         let ty = if version == 0 {
             Type::VersionNegotiation
         } else {
-            match (first & TYPE_MASK) >> 4 {
-                0x00 => Type::Initial,
-                0x01 => Type::ZeroRTT,
-                0x02 => Type::Handshake,
-                0x03 => Type::Retry,
-                _ => return Err(Error::InvalidPacket),
-            }
+            Type::from_long_header(first, version)?
         };
 
         let dcid_len = b.get_u8()?;
@@ -469,15 +512,9 @@ impl<'a> Header<'a> {
         }
 
         // Encode long header.
-        let ty: u8 = match self.ty {
-            Type::Initial => 0x00,
-            Type::ZeroRTT => 0x01,
-            Type::Handshake => 0x02,
-            Type::Retry => 0x03,
-            _ => return Err(Error::InvalidPacket),
-        };
+        let ty = self.ty.to_long_header(self.version)?;
 
-        first |= FORM_BIT | FIXED_BIT | (ty << 4);
+        first |= FORM_BIT | FIXED_BIT | ty;
 
         out.put_u8(first)?;
 
@@ -519,7 +556,7 @@ impl<'a> Header<'a> {
     /// Returns true if the packet has a long header.
     ///
     /// The `b` parameter represents the first byte of the QUIC header.
-    fn is_long(b: u8) -> bool {
+    pub(crate) fn is_long(b: u8) -> bool {
         b & FORM_BIT != 0
     }
 }
@@ -643,8 +680,8 @@ pub fn decode_pkt_num(largest_pn: u64, truncated_pn: u64, pn_len: usize) -> u64
 }
 
 pub fn decrypt_pkt<'a>(
-    b: &'a mut octets::OctetsMut, pn: u64, pn_len: usize, payload_len: usize,
-    aead: &crypto::Open,
+    b: &'a mut octets::OctetsMut, path_id: u32, pn: u64, pn_len: usize,
+    payload_len: usize, aead: &crypto::Open,
 ) -> Result<octets::Octets<'a>> {
     let payload_offset = b.off();
 
@@ -656,8 +693,12 @@ pub fn decrypt_pkt<'a>(
 
     let mut ciphertext = payload.peek_bytes_mut(payload_len)?;
 
-    let payload_len =
-        aead.open_with_u64_counter(pn, header.as_ref(), ciphertext.as_mut())?;
+    let payload_len = aead.open_with_u64_counter(
+        path_id,
+        pn,
+        header.as_ref(),
+        ciphertext.as_mut(),
+    )?;
 
     Ok(b.get_bytes(payload_len)?)
 }
@@ -688,13 +729,16 @@ pub fn encrypt_hdr(
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn encrypt_pkt(
-    b: &mut octets::OctetsMut, pn: u64, pn_len: usize, payload_len: usize,
-    payload_offset: usize, extra_in: Option<&[u8]>, aead: &crypto::Seal,
+    b: &mut octets::OctetsMut, path_id: u32, pn: u64, pn_len: usize,
+    payload_len: usize, payload_offset: usize, extra_in: Option<&[u8]>,
+    aead: &mut crypto::Seal,
 ) -> Result<usize> {
     let (mut header, mut payload) = b.split_at(payload_offset)?;
 
     let ciphertext_len = aead.seal_with_u64_counter(
+        path_id,
         pn,
         header.as_ref(),
         payload.as_mut(),
@@ -740,6 +784,7 @@ pub fn negotiate_version(
     b.put_u8(dcid.len() as u8)?;
     b.put_bytes(dcid)?;
     b.put_u32(crate::PROTOCOL_VERSION_V1)?;
+    b.put_u32(crate::PROTOCOL_VERSION_V2)?;
 
     Ok(b.off())
 }
@@ -800,10 +845,22 @@ fn compute_retry_integrity_tag(
         0x46, 0x15, 0x99, 0xd3, 0x5d, 0x63, 0x2b, 0xf2, 0x23, 0x98, 0x25, 0xbb,
     ];
 
+    const RETRY_INTEGRITY_KEY_V2: [u8; KEY_LEN] = [
+        0x8f, 0xb4, 0xb0, 0x1b, 0x56, 0xac, 0x48, 0xe2, 0x60, 0xfb, 0xcb, 0xce,
+        0xad, 0x7c, 0xcc, 0x92,
+    ];
+
+    const RETRY_INTEGRITY_NONCE_V2: [u8; crypto::MAX_NONCE_LEN] = [
+        0xd8, 0x69, 0x69, 0xbc, 0x2d, 0x7c, 0x6d, 0x99, 0x90, 0xef, 0xb0, 0x4a,
+    ];
+
     let (key, nonce) = match version {
         crate::PROTOCOL_VERSION_V1 =>
             (&RETRY_INTEGRITY_KEY_V1, RETRY_INTEGRITY_NONCE_V1),
 
+        crate::PROTOCOL_VERSION_V2 =>
+            (&RETRY_INTEGRITY_KEY_V2, RETRY_INTEGRITY_NONCE_V2),
+
         _ => (&RETRY_INTEGRITY_KEY_V1, RETRY_INTEGRITY_NONCE_V1),
     };
 
@@ -826,7 +883,8 @@ fn compute_retry_integrity_tag(
 
     let mut out_tag = vec![0_u8; TAG_LEN];
 
-    let out_len = key.seal_with_u64_counter(0, &pseudo, &mut out_tag, 0, None)?;
+    let out_len =
+        key.seal_with_u64_counter(0, 0, &pseudo, &mut out_tag, 0, None)?;
 
     // Ensure that the output only contains the AEAD tag.
     if out_len != out_tag.len() {
@@ -865,6 +923,8 @@ pub struct PktNumSpace {
 
     pub ack_elicited: bool,
 
+    pub ecn_counts: frame::EcnCounts,
+
     pub key_update: Option<KeyUpdate>,
 
     pub crypto_open: Option<crypto::Open>,
@@ -890,6 +950,8 @@ impl PktNumSpace {
 
             ack_elicited: false,
 
+            ecn_counts: frame::EcnCounts::default(),
+
             key_update: None,
 
             crypto_open: None,
@@ -1012,6 +1074,78 @@ mod tests {
         assert_eq!(Header::from_bytes(&mut b, 9).unwrap(), hdr);
     }
 
+    #[test]
+    fn long_header_types_v2() {
+        let types = [
+            (Type::Retry, 0x00),
+            (Type::Initial, 0x10),
+            (Type::ZeroRTT, 0x20),
+            (Type::Handshake, 0x30),
+        ];
+
+        for (ty, bits) in types {
+            let hdr = Header {
+                ty,
+                version: crate::PROTOCOL_VERSION_V2,
+                dcid: vec![0xba; 9].into(),
+                scid: vec![0xbb; 7].into(),
+                pkt_num: 0,
+                pkt_num_len: 0,
+                token: None,
+                versions: None,
+                key_phase: false,
+            };
+
+            let mut d = [0; 50];
+
+            let mut b = octets::OctetsMut::with_slice(&mut d);
+            assert!(hdr.to_bytes(&mut b).is_ok());
+
+            assert_eq!(d[0] & TYPE_MASK, bits);
+
+            assert_eq!(
+                Type::from_long_header(d[0], crate::PROTOCOL_VERSION_V2),
+                Ok(ty)
+            );
+            assert_ne!(
+                Type::from_long_header(d[0], crate::PROTOCOL_VERSION_V1),
+                Ok(ty)
+            );
+        }
+    }
+
+    #[test]
+    fn retry_integrity_v2() {
+        let mut d = [0; 128];
+
+        let scid = [0xba; 8];
+        let dcid = [0xbb; 8];
+        let new_scid = [0xbc; 8];
+
+        let len = super::retry(
+            &scid,
+            &dcid,
+            &new_scid,
+            b"token",
+            crate::PROTOCOL_VERSION_V2,
+            &mut d,
+        )
+        .unwrap();
+
+        let mut b = octets::OctetsMut::with_slice(&mut d[..len]);
+        let hdr = Header::from_bytes(&mut b, 8).unwrap();
+        assert_eq!(hdr.ty, Type::Retry);
+        assert_eq!(hdr.token, Some(b"token".to_vec()));
+
+        assert!(
+            verify_retry_integrity(&b, &dcid, crate::PROTOCOL_VERSION_V2).is_ok()
+        );
+        assert!(
+            verify_retry_integrity(&b, &dcid, crate::PROTOCOL_VERSION_V1)
+                .is_err()
+        );
+    }
+
     #[test]
     fn initial() {
         let hdr = Header {
@@ -1327,7 +1461,8 @@ mod tests {
         assert_eq!(pn, expected_pn);
 
         let payload =
-            decrypt_pkt(&mut b, pn, hdr.pkt_num_len, payload_len, &aead).unwrap();
+            decrypt_pkt(&mut b, 0, pn, hdr.pkt_num_len, payload_len, &aead)
+                .unwrap();
 
         let payload = payload.as_ref();
         assert_eq!(&payload[..expected_frames.len()], expected_frames);
@@ -1531,7 +1666,9 @@ mod tests {
 
         let alg = crypto::Algorithm::ChaCha20_Poly1305;
 
-        let aead = crypto::Open::from_secret(alg, &secret).unwrap();
+        let aead =
+            crypto::Open::from_secret(alg, &secret, crate::PROTOCOL_VERSION)
+                .unwrap();
 
         let mut hdr = Header::from_bytes(&mut b, 0).unwrap();
         assert_eq!(hdr.ty, Type::Short);
@@ -1545,7 +1682,8 @@ mod tests {
         assert_eq!(pn, 654_360_564);
 
         let payload =
-            decrypt_pkt(&mut b, pn, hdr.pkt_num_len, payload_len, &aead).unwrap();
+            decrypt_pkt(&mut b, 0, pn, hdr.pkt_num_len, payload_len, &aead)
+                .unwrap();
 
         let payload = payload.as_ref();
         assert_eq!(&payload, &[0x01]);
@@ -1565,7 +1703,7 @@ mod tests {
 
         b.put_bytes(header).unwrap();
 
-        let (_, aead) = crypto::derive_initial_key_material(
+        let (_, mut aead) = crypto::derive_initial_key_material(
             dcid,
             hdr.version,
             is_server,
@@ -1581,12 +1719,13 @@ mod tests {
 
         let written = encrypt_pkt(
             &mut b,
+            0,
             pn,
             pn_len,
             payload_len,
             payload_offset,
             None,
-            &aead,
+            &mut aead,
         )
         .unwrap();
 
@@ -1903,7 +2042,9 @@ mod tests {
 
         let alg = crypto::Algorithm::ChaCha20_Poly1305;
 
-        let aead = crypto::Seal::from_secret(alg, &secret).unwrap();
+        let mut aead =
+            crypto::Seal::from_secret(alg, &secret, crate::PROTOCOL_VERSION)
+                .unwrap();
 
         let pn = 654_360_564;
         let pn_len = 3;
@@ -1918,12 +2059,13 @@ mod tests {
 
         let written = encrypt_pkt(
             &mut b,
+            0,
             pn,
             pn_len,
             payload_len,
             payload_offset,
             None,
-            &aead,
+            &mut aead,
         )
         .unwrap();
 
@@ -1959,7 +2101,7 @@ mod tests {
                 .unwrap();
 
         assert_eq!(
-            decrypt_pkt(&mut b, 0, 1, payload_len, &aead),
+            decrypt_pkt(&mut b, 0, 0, 1, payload_len, &aead),
             Err(Error::InvalidPacket)
         );
     }
@@ -1993,7 +2135,7 @@ mod tests {
                 .unwrap();
 
         assert_eq!(
-            decrypt_pkt(&mut b, 0, 1, payload_len, &aead),
+            decrypt_pkt(&mut b, 0, 0, 1, payload_len, &aead),
             Err(Error::CryptoFail)
         );
     }
@@ -24,6 +24,7 @@
 // NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
 // SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
 
+use std::cmp;
 use std::time;
 
 use std::collections::BTreeMap;
@@ -37,6 +38,7 @@ use slab::Slab;
 use crate::Error;
 use crate::Result;
 
+use crate::ecn;
 use crate::pmtud;
 use crate::recovery;
 use crate::recovery::HandshakeStatus;
@@ -74,6 +76,38 @@ impl PathState {
     }
 }
 
+/// The status of a path advertised with the multipath extension.
+///
+/// A path marked as standby is only used when no available path is left.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum PathStatus {
+    /// The path can be used to send packets.
+    #[default]
+    Available,
+
+    /// The path should only be used if no available path is left.
+    Standby,
+}
+
+impl PathStatus {
+    /// Returns the value carried by PATH_STATUS frames.
+    pub(crate) fn to_wire(self) -> u64 {
+        match self {
+            PathStatus::Standby => 1,
+            PathStatus::Available => 2,
+        }
+    }
+
+    /// Parses the value carried by PATH_STATUS frames.
+    pub(crate) fn from_wire(v: u64) -> Result<Self> {
+        match v {
+            1 => Ok(PathStatus::Standby),
+            2 => Ok(PathStatus::Available),
+            _ => Err(Error::InvalidFrame),
+        }
+    }
+}
+
 /// A path-specific event.
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum PathEvent {
@@ -83,6 +117,11 @@ pub enum PathEvent {
     /// then probe this new path, if desired.
     New(SocketAddr, SocketAddr),
 
+    /// The validation of the related network path between local `SocketAddr`
+    /// and peer `SocketAddr` started, i.e., its first PATH_CHALLENGE frame has
+    /// been sent.
+    ValidationStarted(SocketAddr, SocketAddr),
+
     /// The related network path between local `SocketAddr` and peer
     /// `SocketAddr` has been validated.
     Validated(SocketAddr, SocketAddr),
@@ -113,6 +152,32 @@ pub enum PathEvent {
     PeerMigrated(SocketAddr, SocketAddr),
 }
 
+/// Parameters of the path validation process.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ValidationConfig {
+    /// The number of lost PATH_CHALLENGE frames after which the validation
+    /// fails.
+    pub max_challenges: usize,
+
+    /// The time after which an unanswered PATH_CHALLENGE frame is considered
+    /// lost. If `None`, PATH_CHALLENGE frames are only declared lost by the
+    /// loss detection timer.
+    pub retry_interval: Option<time::Duration>,
+
+    /// The maximum duration of a path validation, if any.
+    pub timeout: Option<time::Duration>,
+}
+
+impl Default for ValidationConfig {
+    fn default() -> Self {
+        Self {
+            max_challenges: crate::MAX_PROBING_TIMEOUTS,
+            retry_interval: None,
+            timeout: None,
+        }
+    }
+}
+
 /// A network path on which QUIC packets can be sent.
 #[derive(Debug)]
 pub struct Path {
@@ -140,6 +205,9 @@ pub struct Path {
     /// Path MTU discovery state.
     pub pmtud: pmtud::Pmtud,
 
+    /// ECN marking and validation state.
+    pub ecn: ecn::EcnState,
+
     /// Pending challenge data with the size of the packet containing them and
     /// when they were sent.
     in_flight_challenges: VecDeque<([u8; 8], usize, time::Instant)>,
@@ -153,6 +221,12 @@ pub struct Path {
     /// Last instant when a probing packet got lost.
     last_probe_lost_time: Option<time::Instant>,
 
+    /// When the ongoing path validation started.
+    validation_start: Option<time::Instant>,
+
+    /// How long the last path validation took to succeed or fail.
+    validation_duration: Option<time::Duration>,
+
     /// Received challenge data.
     received_challenges: VecDeque<[u8; 8]>,
 
@@ -206,6 +280,16 @@ pub struct Path {
 
     /// Whether or not we should force eliciting of an ACK (e.g. via PING frame)
     pub needs_ack_eliciting: bool,
+
+    /// Whether the path was abandoned by either endpoint.
+    abandoned: bool,
+
+    /// The status this endpoint advertised for the path.
+    local_status: PathStatus,
+
+    /// The status the peer advertised for the path, with the sequence number
+    /// of the PATH_STATUS frame that carried it.
+    peer_status: (PathStatus, Option<u64>),
 }
 
 impl Path {
@@ -232,10 +316,13 @@ impl Path {
             active: false,
             recovery: recovery::Recovery::new_with_config(recovery_config),
             pmtud: pmtud::Pmtud::new(pmtud_init),
+            ecn: ecn::EcnState::default(),
             in_flight_challenges: VecDeque::new(),
             max_challenge_size: 0,
             probing_lost: 0,
             last_probe_lost_time: None,
+            validation_start: None,
+            validation_duration: None,
             received_challenges: VecDeque::with_capacity(
                 path_challenge_recv_max_queue_len,
             ),
@@ -255,6 +342,9 @@ impl Path {
             failure_notified: false,
             migrating: false,
             needs_ack_eliciting: false,
+            abandoned: false,
+            local_status: PathStatus::Available,
+            peer_status: (PathStatus::Available, None),
         }
     }
 
@@ -290,6 +380,40 @@ impl Path {
                 self.active_dcid_seq.is_some())
     }
 
+    /// Returns whether the path was abandoned.
+    #[inline]
+    pub fn abandoned(&self) -> bool {
+        self.abandoned
+    }
+
+    /// Abandons the path, which can no longer carry non-probing packets.
+    pub fn abandon(&mut self) {
+        self.abandoned = true;
+        self.active = false;
+    }
+
+    /// Returns whether either endpoint marked the path as standby.
+    #[inline]
+    pub fn standby(&self) -> bool {
+        self.local_status == PathStatus::Standby ||
+            self.peer_status.0 == PathStatus::Standby
+    }
+
+    /// Sets the status this endpoint advertises for the path.
+    pub fn set_local_status(&mut self, status: PathStatus) {
+        self.local_status = status;
+    }
+
+    /// Handles a PATH_STATUS frame, ignoring it if a more recent one was
+    /// already received.
+    pub fn on_peer_status(&mut self, seq_num: u64, status: PathStatus) {
+        if self.peer_status.1.is_some_and(|latest| latest >= seq_num) {
+            return;
+        }
+
+        self.peer_status = (status, Some(seq_num));
+    }
+
     /// Returns whether the path is unused.
     #[inline]
     fn unused(&self) -> bool {
@@ -346,10 +470,11 @@ impl Path {
         is_closing: bool, frames_empty: bool,
     ) -> bool {
         (hs_confirmed && hs_done) &&
+            self.validated() &&
+            self.pmtud.should_send_probe() &&
             self.pmtud.get_probe_size() > self.pmtud.get_current() &&
             self.recovery.cwnd_available() > self.pmtud.get_probe_size() &&
             out_len >= self.pmtud.get_probe_size() &&
-            self.pmtud.get_probe_status() &&
             !is_closing &&
             frames_empty
     }
@@ -360,12 +485,21 @@ impl Path {
     }
 
     /// Handles the sending of PATH_CHALLENGE.
+    ///
+    /// Returns whether this starts a new path validation.
     pub fn add_challenge_sent(
         &mut self, data: [u8; 8], pkt_size: usize, sent_time: time::Instant,
-    ) {
+    ) -> bool {
         self.on_challenge_sent();
         self.in_flight_challenges
             .push_back((data, pkt_size, sent_time));
+
+        if self.validation_start.is_some() {
+            return false;
+        }
+
+        self.validation_start = Some(sent_time);
+        true
     }
 
     pub fn on_challenge_received(&mut self, data: [u8; 8]) {
@@ -383,7 +517,9 @@ impl Path {
     }
 
     /// Returns whether the path is now validated.
-    pub fn on_response_received(&mut self, data: [u8; 8]) -> bool {
+    pub fn on_response_received(
+        &mut self, data: [u8; 8], now: time::Instant,
+    ) -> bool {
         self.verified_peer_address = true;
         self.probing_lost = 0;
 
@@ -407,19 +543,101 @@ impl Path {
             if self.max_challenge_size >= crate::MIN_CLIENT_INITIAL_LEN {
                 // Path MTU is sufficient for QUIC traffic.
                 self.promote_to(PathState::Validated);
+                self.on_validation_done(now);
                 return true;
             }
 
             // If the MTU was not validated, probe again.
             self.request_validation();
+        } else {
+            // An already validated path was probed again.
+            self.on_validation_done(now);
         }
 
         false
     }
 
-    fn on_failed_validation(&mut self) {
+    fn on_failed_validation(&mut self, now: time::Instant) {
         self.state = PathState::Failed;
         self.active = false;
+        self.on_validation_done(now);
+    }
+
+    fn on_validation_done(&mut self, now: time::Instant) {
+        if let Some(start) = self.validation_start.take() {
+            self.validation_duration = Some(now.saturating_duration_since(start));
+        }
+    }
+
+    /// Handles the loss of PATH_CHALLENGE frames, either probing again or
+    /// declaring the validation failed.
+    fn on_challenge_lost(
+        &mut self, config: &ValidationConfig, is_server: bool, now: time::Instant,
+    ) {
+        // As a server, if requesting a challenge is not possible due to the
+        // amplification attack, declare the validation as failed.
+        if self.probing_lost >= config.max_challenges ||
+            (is_server && self.max_send_bytes < crate::MIN_PROBING_SIZE)
+        {
+            self.on_failed_validation(now);
+        } else {
+            self.request_validation();
+        }
+    }
+
+    /// Returns when the next PATH_CHALLENGE retry or validation timeout will
+    /// occur, if any.
+    pub fn validation_timer(
+        &self, config: &ValidationConfig,
+    ) -> Option<time::Instant> {
+        // Only the challenges of an ongoing validation are retried.
+        let start = self.validation_start?;
+
+        let timeout = config.timeout.map(|timeout| start + timeout);
+
+        let retry =
+            match (self.in_flight_challenges.front(), config.retry_interval) {
+                (Some((_, _, sent_time)), Some(interval)) =>
+                    Some(*sent_time + interval),
+
+                _ => None,
+            };
+
+        [timeout, retry].into_iter().flatten().min()
+    }
+
+    /// Handles the expiration of the timer returned by [`validation_timer()`].
+    ///
+    /// [`validation_timer()`]: struct.Path.html#method.validation_timer
+    pub fn on_validation_timeout(
+        &mut self, config: &ValidationConfig, is_server: bool, now: time::Instant,
+    ) {
+        let Some(start) = self.validation_start else {
+            return;
+        };
+
+        if let Some(timeout) = config.timeout {
+            if start + timeout <= now {
+                self.on_failed_validation(now);
+                return;
+            }
+        }
+
+        let Some(interval) = config.retry_interval else {
+            return;
+        };
+
+        let in_flight = self.in_flight_challenges.len();
+
+        self.in_flight_challenges
+            .retain(|(_, _, sent_time)| *sent_time + interval > now);
+
+        if self.in_flight_challenges.len() < in_flight {
+            self.probing_lost += 1;
+            self.last_probe_lost_time = Some(now);
+
+            self.on_challenge_lost(config, is_server, now);
+        }
     }
 
     #[inline]
@@ -429,7 +647,7 @@ impl Path {
 
     pub fn on_loss_detection_timeout(
         &mut self, handshake_status: HandshakeStatus, now: time::Instant,
-        is_server: bool, trace_id: &str,
+        is_server: bool, validation_config: &ValidationConfig, trace_id: &str,
     ) -> (usize, usize) {
         let (lost_packets, lost_bytes) = self.recovery.on_loss_detection_timeout(
             handshake_status,
@@ -467,16 +685,8 @@ impl Path {
                     Some(lost_probe_time)
                 },
             };
-            // As a server, if requesting a challenge is not
-            // possible due to the amplification attack, declare the
-            // validation as failed.
-            if self.probing_lost >= crate::MAX_PROBING_TIMEOUTS ||
-                (is_server && self.max_send_bytes < crate::MIN_PROBING_SIZE)
-            {
-                self.on_failed_validation();
-            } else {
-                self.request_validation();
-            }
+
+            self.on_challenge_lost(validation_config, is_server, now);
         }
 
         (lost_packets, lost_bytes)
@@ -493,6 +703,7 @@ impl Path {
             local_addr: self.local_addr,
             peer_addr: self.peer_addr,
             validation_state: self.state,
+            validation_duration: self.validation_duration,
             active: self.active,
             recv: self.recv_count,
             sent: self.sent_count,
@@ -510,6 +721,9 @@ impl Path {
             stream_retrans_bytes: self.stream_retrans_bytes,
             pmtu: self.recovery.max_datagram_size(),
             delivery_rate: self.recovery.delivery_rate(),
+            pacing_rate: self.recovery.pacing_rate(),
+            ecn_capable: self.ecn.is_capable(),
+            ecn_ce_count: self.ecn.ce_count(),
         }
     }
 }
@@ -557,6 +771,15 @@ pub struct PathMap {
 
     /// Whether this manager serves a connection as a server.
     is_server: bool,
+
+    /// Whether several paths can be active at the same time.
+    multipath: bool,
+
+    /// The largest datagram size probed by path MTU discovery, if enabled.
+    pmtud_max_size: Option<usize>,
+
+    /// The ECN codepoint used to mark packets sent on new paths.
+    ecn: ecn::Ecn,
 }
 
 impl PathMap {
@@ -577,10 +800,10 @@ impl PathMap {
 
         // Enable path MTU Discovery and start probing with the largest datagram
         // size.
-        if enable_pmtud {
-            initial_path.pmtud.should_probe(enable_pmtud);
-            initial_path.pmtud.set_probe_size(max_send_udp_payload_size);
-            initial_path.pmtud.enable(enable_pmtud);
+        let pmtud_max_size = enable_pmtud.then_some(max_send_udp_payload_size);
+
+        if let Some(max_size) = pmtud_max_size {
+            initial_path.pmtud.enable(max_size);
         }
 
         let active_path_id = paths.insert(initial_path);
@@ -592,9 +815,43 @@ impl PathMap {
             addrs_to_paths,
             events: VecDeque::new(),
             is_server,
+            multipath: false,
+            pmtud_max_size,
+            ecn: ecn::Ecn::NotEct,
         }
     }
 
+    /// Sets the ECN codepoint used to mark packets, resetting the ECN state
+    /// of all paths.
+    pub fn set_ecn(&mut self, codepoint: ecn::Ecn) {
+        self.ecn = codepoint;
+
+        for (_, p) in self.paths.iter_mut() {
+            p.ecn = ecn::EcnState::new(codepoint);
+        }
+    }
+
+    /// Lowers the largest datagram size probed by path MTU discovery on all
+    /// paths, e.g. following the peer's `max_udp_payload_size` transport
+    /// parameter.
+    pub fn set_pmtud_max_size(&mut self, max_size: usize) {
+        if let Some(cur) = self.pmtud_max_size {
+            let max_size = cmp::min(cur, max_size);
+
+            self.pmtud_max_size = Some(max_size);
+
+            for (_, p) in self.paths.iter_mut() {
+                p.pmtud.set_max_size(max_size);
+            }
+        }
+    }
+
+    /// Allows several paths to be active at the same time, once the multipath
+    /// extension has been negotiated.
+    pub fn enable_multipath(&mut self) {
+        self.multipath = true;
+    }
+
     /// Gets an immutable reference to the path identified by `path_id`. If the
     /// provided `path_id` does not identify any current `Path`, returns an
     /// [`InvalidState`].
@@ -718,9 +975,19 @@ impl PathMap {
     /// it returns [`Done`].
     ///
     /// [`Done`]: enum.Error.html#variant.Done
-    pub fn insert_path(&mut self, path: Path, is_server: bool) -> Result<usize> {
+    pub fn insert_path(
+        &mut self, mut path: Path, is_server: bool,
+    ) -> Result<usize> {
         self.make_room_for_new_path()?;
 
+        // Each path runs its own path MTU discovery.
+        if let Some(max_size) = self.pmtud_max_size {
+            path.pmtud.enable(max_size);
+        }
+
+        // ECN is validated separately on each path.
+        path.ecn = ecn::EcnState::new(self.ecn);
+
         let local_addr = path.local_addr;
         let peer_addr = path.peer_addr;
 
@@ -771,21 +1038,54 @@ impl PathMap {
             .map(|(pid, _)| pid)
     }
 
+    /// Selects the active path on which the next non-probing packet should be
+    /// sent when several of them are in use.
+    ///
+    /// Paths with congestion window space left come first, then paths that
+    /// neither endpoint marked as standby, and finally the path with the lowest
+    /// RTT is picked.
+    pub fn select_multipath_send_path(
+        &self, from: Option<SocketAddr>, to: Option<SocketAddr>,
+    ) -> Option<usize> {
+        self.paths
+            .iter()
+            .filter(|(_, p)| p.active())
+            .filter(|(_, p)| from.is_none() || Some(p.local_addr) == from)
+            .filter(|(_, p)| to.is_none() || Some(p.peer_addr) == to)
+            .min_by_key(|(_, p)| {
+                (
+                    p.recovery.cwnd_available() == 0,
+                    p.standby(),
+                    p.recovery.rtt(),
+                )
+            })
+            .map(|(pid, _)| pid)
+    }
+
     /// Handles incoming PATH_RESPONSE data.
-    pub fn on_response_received(&mut self, data: [u8; 8]) -> Result<()> {
+    pub fn on_response_received(
+        &mut self, data: [u8; 8], now: time::Instant,
+    ) -> Result<()> {
         let active_pid = self.get_active_path_id()?;
+        let multipath = self.multipath;
 
         let challenge_pending =
             self.iter_mut().find(|(_, p)| p.has_pending_challenge(data));
 
         if let Some((pid, p)) = challenge_pending {
-            if p.on_response_received(data) {
+            if p.on_response_received(data, now) {
                 let local_addr = p.local_addr;
                 let peer_addr = p.peer_addr;
                 let was_migrating = p.migrating;
 
                 p.migrating = false;
 
+                // With multipath, a validated path is used alongside the
+                // others right away.
+                if multipath && !p.abandoned {
+                    p.active = true;
+                }
+
                 // Notifies the application.
                 self.notify_event(PathEvent::Validated(local_addr, peer_addr));
 
@@ -803,9 +1103,9 @@ impl PathMap {
 
     /// Sets the path with identifier 'path_id' to be active.
     ///
-    /// There can be exactly one active path on which non-probing packets can be
-    /// sent. If another path is marked as active, it will be superseded by the
-    /// one having `path_id` as identifier.
+    /// Without multipath, there can be exactly one active path on which
+    /// non-probing packets can be sent. If another path is marked as active, it
+    /// will be superseded by the one having `path_id` as identifier.
     ///
     /// A server should always ensure that the active path is validated. If it
     /// is already the case, it notifies the application that the connection
@@ -814,8 +1114,10 @@ impl PathMap {
     pub fn set_active_path(&mut self, path_id: usize) -> Result<()> {
         let is_server = self.is_server;
 
-        if let Ok(old_active_path) = self.get_active_mut() {
-            old_active_path.active = false;
+        if !self.multipath {
+            if let Ok(old_active_path) = self.get_active_mut() {
+                old_active_path.active = false;
+            }
         }
 
         let new_active_path = self.get_mut(path_id)?;
@@ -858,6 +1160,9 @@ pub struct PathStats {
     /// The path validation state.
     pub validation_state: PathState,
 
+    /// How long the last validation of the path took to succeed or fail.
+    pub validation_duration: Option<time::Duration>,
+
     /// Whether the path is marked as active.
     pub active: bool,
 
@@ -916,6 +1221,16 @@ pub struct PathStats {
     /// [`SendInfo.at`]: struct.SendInfo.html#structfield.at
     /// [Pacing]: index.html#pacing
     pub delivery_rate: u64,
+
+    /// The current pacing rate in bytes/s.
+    pub pacing_rate: u64,
+
+    /// Whether ECN was validated on the path.
+    pub ecn_capable: bool,
+
+    /// The number of packets sent on the path that the peer reported as
+    /// marked with ECN Congestion Experienced.
+    pub ecn_ce_count: u64,
 }
 
 impl std::fmt::Debug for PathStats {
@@ -928,8 +1243,8 @@ impl std::fmt::Debug for PathStats {
         )?;
         write!(
             f,
-            "validation_state={:?} active={} ",
-            self.validation_state, self.active,
+            "validation_state={:?} validation_duration={:?} active={} ",
+            self.validation_state, self.validation_duration, self.active,
         )?;
         write!(
             f,
@@ -945,8 +1260,17 @@ impl std::fmt::Debug for PathStats {
 
         write!(
             f,
-            " stream_retrans_bytes={} pmtu={} delivery_rate={}",
-            self.stream_retrans_bytes, self.pmtu, self.delivery_rate,
+            " stream_retrans_bytes={} pmtu={} delivery_rate={} pacing_rate={}",
+            self.stream_retrans_bytes,
+            self.pmtu,
+            self.delivery_rate,
+            self.pacing_rate,
+        )?;
+
+        write!(
+            f,
+            " ecn_capable={} ecn_ce_count={}",
+            self.ecn_capable, self.ecn_ce_count,
         )
     }
 }
@@ -1015,7 +1339,9 @@ mod tests {
 
         // Receives the response. The path is reachable, but the MTU is not
         // validated yet.
-        path_mgr.on_response_received(data).unwrap();
+        path_mgr
+            .on_response_received(data, time::Instant::now())
+            .unwrap();
 
         assert!(path_mgr.get_mut(pid).unwrap().validation_requested());
         assert!(path_mgr.get_mut(pid).unwrap().probing_required());
@@ -1036,7 +1362,9 @@ mod tests {
             time::Instant::now(),
         );
 
-        path_mgr.on_response_received(data).unwrap();
+        path_mgr
+            .on_response_received(data, time::Instant::now())
+            .unwrap();
 
         assert!(!path_mgr.get_mut(pid).unwrap().validation_requested());
         assert!(!path_mgr.get_mut(pid).unwrap().probing_required());
@@ -1118,7 +1446,9 @@ mod tests {
         assert_eq!(server_path.received_challenges.len(), 2);
 
         // Response for first probe.
-        client_path_mgr.on_response_received(data).unwrap();
+        client_path_mgr
+            .on_response_received(data, time::Instant::now())
+            .unwrap();
         assert_eq!(
             client_path_mgr
                 .get(client_pid)
@@ -1129,7 +1459,9 @@ mod tests {
         );
 
         // Response for second probe.
-        client_path_mgr.on_response_received(data_2).unwrap();
+        client_path_mgr
+            .on_response_received(data_2, time::Instant::now())
+            .unwrap();
         assert_eq!(
             client_path_mgr
                 .get(client_pid)
@@ -1255,7 +1587,9 @@ mod tests {
         assert_eq!(server_path.received_challenges.len(), 3);
 
         // Response for first probe.
-        client_path_mgr.on_response_received(data).unwrap();
+        client_path_mgr
+            .on_response_received(data, time::Instant::now())
+            .unwrap();
         assert_eq!(
             client_path_mgr
                 .get(client_pid)
@@ -1266,7 +1600,9 @@ mod tests {
         );
 
         // Response for second probe.
-        client_path_mgr.on_response_received(data_2).unwrap();
+        client_path_mgr
+            .on_response_received(data_2, time::Instant::now())
+            .unwrap();
         assert_eq!(
             client_path_mgr
                 .get(client_pid)
@@ -1277,7 +1613,9 @@ mod tests {
         );
 
         // Response for third probe.
-        client_path_mgr.on_response_received(data_3).unwrap();
+        client_path_mgr
+            .on_response_received(data_3, time::Instant::now())
+            .unwrap();
         assert_eq!(
             client_path_mgr
                 .get(client_pid)
@@ -1,3 +1,13 @@
+use std::cmp;
+
+// The number of consecutive lost probes of the same size after which that
+// size is considered to be larger than the path MTU.
+const MAX_PROBES: u8 = 3;
+
+// Stop searching once the difference between the largest validated size and
+// the smallest failed size is below this value.
+const SEARCH_GRANULARITY: usize = 16;
+
 #[derive(Default)]
 pub struct Pmtud {
     /// The current path MTU estimate.
@@ -6,6 +16,16 @@ pub struct Pmtud {
     /// The last MTU probe size that was attempted.
     probe: usize,
 
+    /// The upper bound of the search, i.e. the largest size that hasn't
+    /// failed yet.
+    max_size: usize,
+
+    /// The number of consecutive probes of the current size that were lost.
+    probe_count: u8,
+
+    /// Whether a probe is currently in flight.
+    in_flight: bool,
+
     /// Indicated if Path MTU probe needs to be generated.
     next_size: bool,
 
@@ -22,62 +42,116 @@ impl Pmtud {
         }
     }
 
-    /// Enables Path MTU Discovery for the connection.
-    pub fn enable(&mut self, enable: bool) {
-        self.enable = enable;
+    /// Enables Path MTU Discovery for the path, searching up to `max_size`.
+    ///
+    /// The first probe optimistically uses the largest size, as that's
+    /// supported by most paths.
+    pub fn enable(&mut self, max_size: usize) {
+        self.enable = true;
+        self.max_size = cmp::max(max_size, self.cur_size);
+        self.probe = self.max_size;
+        self.probe_count = 0;
+        self.next_size = self.max_size > self.cur_size;
     }
 
     /// Returns enable status for Path MTU Discovery for the connection.
-    pub fn is_enabled(&mut self) -> bool {
+    pub fn is_enabled(&self) -> bool {
         self.enable
     }
 
-    /// Specifies whether Path MTU Discovery should be performed at the next
-    /// opportunity, i.e., when the next packet is sent out if possible.
-    ///
-    /// Once Path MTU has been discovered, this maybe set to false.
-    pub fn should_probe(&mut self, pmtu_next: bool) {
-        self.next_size = pmtu_next;
+    /// Lowers the upper bound of the search, e.g. after the peer advertised
+    /// a smaller `max_udp_payload_size` transport parameter.
+    pub fn set_max_size(&mut self, max_size: usize) {
+        if !self.enable {
+            return;
+        }
+
+        self.cur_size = cmp::min(self.cur_size, max_size);
+        self.max_size = cmp::min(self.max_size, max_size);
+
+        if self.probe > self.max_size {
+            self.probe = self.max_size;
+            self.probe_count = 0;
+        }
+
+        self.next_size = self.next_size && self.max_size > self.cur_size;
     }
 
-    /// Returns the value of the Path MTU Discovery flag.
+    /// Returns whether Path MTU Discovery is still searching for a larger
+    /// size.
     pub fn get_probe_status(&self) -> bool {
         self.next_size
     }
 
-    /// Sets the next Path MTU Discovery probe size.
-    pub fn set_probe_size(&mut self, pmtu_probe: usize) {
-        self.probe = pmtu_probe;
+    /// Returns whether a probe should be sent at the next opportunity.
+    pub fn should_send_probe(&self) -> bool {
+        self.enable && self.next_size && !self.in_flight
     }
 
     /// Returns the next Path MTU Discovery probe size.
-    pub fn get_probe_size(&mut self) -> usize {
+    pub fn get_probe_size(&self) -> usize {
         self.probe
     }
 
-    /// Sets the current Path MTU Discovery size after a successful probe has
-    /// been performed.
-    pub fn set_current(&mut self, pmtu: usize) {
-        self.cur_size = pmtu;
-    }
-
     /// Returns the discovered PATH MTU size.
-    pub fn get_current(&mut self) -> usize {
+    pub fn get_current(&self) -> usize {
         self.cur_size
     }
 
-    /// Selects path MTU probe based on the binary search algorithm.
-    ///
-    /// Based on the Optimistic Binary algorithm defined in:
-    /// Ref: <https://www.hb.fh-muenster.de/opus4/frontdoor/deliver/index/docId/14965/file/dplpmtudQuicPaper.pdf>
-    pub fn update_probe_size(&mut self) {
-        self.probe = self.cur_size + ((self.probe - self.cur_size) / 2);
+    /// Selects the next probe size between the validated size and the upper
+    /// bound, using binary search.
+    fn update_probe_size(&mut self) {
+        if self.max_size - self.cur_size < SEARCH_GRANULARITY {
+            self.probe = self.cur_size;
+            self.next_size = false;
+            return;
+        }
+
+        self.probe = self.cur_size + (self.max_size - self.cur_size).div_ceil(2);
+        self.next_size = true;
+    }
+
+    /// Updates state when a probe is sent.
+    pub fn on_probe_sent(&mut self) {
+        self.in_flight = true;
+    }
+
+    /// Updates state when a probe of the given size is acknowledged.
+    pub fn on_probe_acked(&mut self, size: usize) {
+        self.in_flight = false;
+
+        let size = cmp::min(size, self.max_size);
+
+        if size <= self.cur_size {
+            return;
+        }
+
+        self.cur_size = size;
+        self.probe_count = 0;
+
+        self.update_probe_size();
     }
 
     /// Updates probe value when the Path MTU Discovery probe is lost.
-    pub fn pmtu_probe_lost(&mut self) {
+    pub fn on_probe_lost(&mut self, size: usize) {
+        self.in_flight = false;
+
+        // Ignore probes of a size that is not being searched anymore.
+        if size != self.probe || !self.next_size {
+            return;
+        }
+
+        self.probe_count += 1;
+
+        if self.probe_count < MAX_PROBES {
+            return;
+        }
+
+        // The probed size doesn't fit the path, so search below it.
+        self.max_size = cmp::max(size - 1, self.cur_size);
+        self.probe_count = 0;
+
         self.update_probe_size();
-        self.should_probe(true);
     }
 }
 
@@ -85,8 +159,95 @@ impl std::fmt::Debug for Pmtud {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(f, "current={:?} ", self.cur_size)?;
         write!(f, "probe_size={:?} ", self.probe)?;
+        write!(f, "max_size={:?} ", self.max_size)?;
         write!(f, "continue_probing={:?} ", self.next_size)?;
         write!(f, "enable={:?} ", self.enable)?;
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn largest_size_succeeds() {
+        let mut pmtud = Pmtud::new(1200);
+        pmtud.enable(1500);
+
+        assert!(pmtud.should_send_probe());
+        assert_eq!(pmtud.get_probe_size(), 1500);
+
+        pmtud.on_probe_sent();
+        assert!(!pmtud.should_send_probe());
+
+        pmtud.on_probe_acked(1500);
+        assert_eq!(pmtud.get_current(), 1500);
+        assert!(!pmtud.get_probe_status());
+        assert!(!pmtud.should_send_probe());
+    }
+
+    #[test]
+    fn search_increasing_sizes() {
+        let mut pmtud = Pmtud::new(1200);
+        pmtud.enable(1500);
+
+        // The largest size is retried before giving up on it.
+        for _ in 0..MAX_PROBES {
+            assert_eq!(pmtud.get_probe_size(), 1500);
+            pmtud.on_probe_sent();
+            pmtud.on_probe_lost(1500);
+        }
+
+        assert_eq!(pmtud.get_current(), 1200);
+        assert_eq!(pmtud.get_probe_size(), 1350);
+        assert!(pmtud.should_send_probe());
+
+        pmtud.on_probe_sent();
+        pmtud.on_probe_acked(1350);
+        assert_eq!(pmtud.get_current(), 1350);
+
+        // Following probes keep growing towards the upper bound.
+        assert_eq!(pmtud.get_probe_size(), 1425);
+
+        pmtud.on_probe_sent();
+        pmtud.on_probe_acked(1425);
+        assert_eq!(pmtud.get_current(), 1425);
+        assert_eq!(pmtud.get_probe_size(), 1462);
+
+        pmtud.on_probe_sent();
+        pmtud.on_probe_acked(1462);
+        assert_eq!(pmtud.get_probe_size(), 1481);
+
+        pmtud.on_probe_sent();
+        pmtud.on_probe_acked(1481);
+        assert_eq!(pmtud.get_probe_size(), 1490);
+
+        pmtud.on_probe_sent();
+        pmtud.on_probe_acked(1490);
+
+        // The remaining range is too small to keep searching.
+        assert_eq!(pmtud.get_current(), 1490);
+        assert!(!pmtud.get_probe_status());
+    }
+
+    #[test]
+    fn max_size_lowered() {
+        let mut pmtud = Pmtud::new(1200);
+        pmtud.enable(1500);
+
+        pmtud.set_max_size(1350);
+        assert_eq!(pmtud.get_probe_size(), 1350);
+
+        pmtud.on_probe_sent();
+        pmtud.on_probe_acked(1350);
+        assert_eq!(pmtud.get_current(), 1350);
+        assert!(!pmtud.get_probe_status());
+
+        // A limit below the base size stops the search altogether.
+        let mut pmtud = Pmtud::new(1200);
+        pmtud.enable(1500);
+        pmtud.set_max_size(1200);
+        assert!(!pmtud.should_send_probe());
+    }
+}
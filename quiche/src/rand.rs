@@ -24,7 +24,118 @@
 // NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
 // SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
 
+#[cfg(any(test, feature = "internal"))]
+use std::cell::RefCell;
+
+/// A source of randomness that can replace the system's CSPRNG, see
+/// `set_thread_rng()`.
+#[cfg(any(test, feature = "internal"))]
+pub trait Rng {
+    /// Fills `buf` with random bytes.
+    fn fill_bytes(&mut self, buf: &mut [u8]);
+}
+
+/// A deterministic, and thus insecure, [`Rng`] generating bytes from a seed.
+#[cfg(any(test, feature = "internal"))]
+pub struct SeededRng {
+    state: u64,
+}
+
+#[cfg(any(test, feature = "internal"))]
+impl SeededRng {
+    /// Creates a generator from the given seed.
+    pub fn new(seed: u64) -> Self {
+        SeededRng { state: seed }
+    }
+
+    // SplitMix64.
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+
+        z ^ (z >> 31)
+    }
+}
+
+#[cfg(any(test, feature = "internal"))]
+impl Rng for SeededRng {
+    fn fill_bytes(&mut self, buf: &mut [u8]) {
+        for chunk in buf.chunks_mut(8) {
+            let v = self.next_u64().to_le_bytes();
+
+            chunk.copy_from_slice(&v[..chunk.len()]);
+        }
+    }
+}
+
+#[cfg(any(test, feature = "internal"))]
+thread_local! {
+    static THREAD_RNG: RefCell<Option<Box<dyn Rng>>> = const { RefCell::new(None) };
+}
+
+/// Replaces the randomness used by quiche on the current thread, returning
+/// the previously installed [`Rng`], if any. Passing `None` restores the
+/// system's CSPRNG.
+///
+/// This covers connection IDs generated by quiche, PATH_CHALLENGE data,
+/// HTTP/3 GREASE values and the randomized decisions of congestion control,
+/// making connection traces reproducible in tests and fuzzing. The TLS
+/// handshake keeps using the TLS library's own randomness.
+///
+/// This must never be used in production.
+#[cfg(any(test, feature = "internal"))]
+pub fn set_thread_rng(rng: Option<Box<dyn Rng>>) -> Option<Box<dyn Rng>> {
+    THREAD_RNG.with(|r| r.replace(rng))
+}
+
+/// Restores the [`Rng`] that was installed on the thread before
+/// `scoped_thread_rng()` was called, once dropped.
+#[cfg(any(test, feature = "internal"))]
+pub struct ThreadRngGuard {
+    prev: Option<Box<dyn Rng>>,
+}
+
+#[cfg(any(test, feature = "internal"))]
+impl Drop for ThreadRngGuard {
+    fn drop(&mut self) {
+        set_thread_rng(self.prev.take());
+    }
+}
+
+/// Like `set_thread_rng()`, but `rng` is only used until the returned guard
+/// is dropped, so that it doesn't leak into later connections on the same
+/// thread.
+///
+/// This must never be used in production.
+#[cfg(any(test, feature = "internal"))]
+pub fn scoped_thread_rng(rng: Box<dyn Rng>) -> ThreadRngGuard {
+    ThreadRngGuard {
+        prev: set_thread_rng(Some(rng)),
+    }
+}
+
+/// Fills `buf` with random bytes, taken from the thread's `Rng` if one was
+/// installed with `set_thread_rng()`.
 pub fn rand_bytes(buf: &mut [u8]) {
+    #[cfg(any(test, feature = "internal"))]
+    {
+        let injected = THREAD_RNG.with(|r| match r.borrow_mut().as_mut() {
+            Some(rng) => {
+                rng.fill_bytes(buf);
+                true
+            },
+
+            None => false,
+        });
+
+        if injected {
+            return;
+        }
+    }
+
     unsafe {
         RAND_bytes(buf.as_mut_ptr(), buf.len());
     }
@@ -62,3 +173,55 @@ pub fn rand_u64_uniform(max: u64) -> u64 {
 extern "C" {
     fn RAND_bytes(buf: *mut u8, len: libc::size_t) -> libc::c_int;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seeded_rng() {
+        let mut a = [0; 21];
+        let mut b = [0; 21];
+
+        SeededRng::new(42).fill_bytes(&mut a);
+        SeededRng::new(42).fill_bytes(&mut b);
+        assert_eq!(a, b);
+
+        SeededRng::new(43).fill_bytes(&mut b);
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn thread_rng() {
+        set_thread_rng(Some(Box::new(SeededRng::new(42))));
+        let a = rand_u64();
+
+        set_thread_rng(Some(Box::new(SeededRng::new(42))));
+        assert_eq!(rand_u64(), a);
+
+        assert!(set_thread_rng(None).is_some());
+        assert!(set_thread_rng(None).is_none());
+    }
+
+    #[test]
+    fn thread_rng_guard() {
+        set_thread_rng(Some(Box::new(SeededRng::new(42))));
+        let a = rand_u64();
+
+        set_thread_rng(Some(Box::new(SeededRng::new(42))));
+
+        {
+            let _guard = scoped_thread_rng(Box::new(SeededRng::new(43)));
+            assert_ne!(rand_u64(), a);
+        }
+
+        // The previous generator is restored once the guard is dropped.
+        assert_eq!(rand_u64(), a);
+
+        assert!(set_thread_rng(None).is_some());
+
+        // Without a previous generator, the system's CSPRNG is restored.
+        drop(scoped_thread_rng(Box::new(SeededRng::new(43))));
+        assert!(set_thread_rng(None).is_none());
+    }
+}
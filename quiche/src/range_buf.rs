@@ -106,21 +106,66 @@ pub trait BufSplit {
 #[derive(Debug, Clone, Default)]
 pub struct DefaultBufFactory;
 
-/// The default [`BufFactory::Buf`] is a boxed slice wrapped in an [`Arc`].
+/// The buffer type used by connections that don't provide their own
+/// [`BufFactory`].
+///
+/// It can be created from a `Vec<u8>` without copying its contents, and
+/// splitting it only shares the underlying allocation, so it can be used to
+/// hand owned buffers over to [`stream_send_owned()`].
+///
+/// [`BufFactory`]: trait.BufFactory.html
+/// [`stream_send_owned()`]: struct.Connection.html#method.stream_send_owned
 #[derive(Debug, Clone, Default)]
-pub struct DefaultBuf(Arc<Box<[u8]>>);
+pub struct DefaultBuf {
+    data: Arc<Vec<u8>>,
+    start: usize,
+    end: usize,
+}
 
 impl BufFactory for DefaultBufFactory {
     type Buf = DefaultBuf;
 
     fn buf_from_slice(buf: &[u8]) -> Self::Buf {
-        DefaultBuf(Arc::new(buf.into()))
+        DefaultBuf::from(buf.to_vec())
+    }
+}
+
+impl From<Vec<u8>> for DefaultBuf {
+    fn from(data: Vec<u8>) -> Self {
+        DefaultBuf {
+            end: data.len(),
+            data: Arc::new(data),
+            start: 0,
+        }
     }
 }
 
 impl AsRef<[u8]> for DefaultBuf {
     fn as_ref(&self) -> &[u8] {
-        &self.0[..]
+        &self.data[self.start..self.end]
+    }
+}
+
+impl BufSplit for DefaultBuf {
+    fn split_at(&mut self, at: usize) -> Self {
+        assert!(
+            at <= self.end - self.start,
+            "`at` split index (is {}) should be <= len (is {})",
+            at,
+            self.end - self.start
+        );
+
+        let at = self.start + at;
+
+        let buf = DefaultBuf {
+            data: Arc::clone(&self.data),
+            start: at,
+            end: self.end,
+        };
+
+        self.end = at;
+
+        buf
     }
 }
 
@@ -333,7 +333,7 @@ fn rollback(_r: &mut Congestion) -> bool {
     false
 }
 
-fn has_custom_pacing() -> bool {
+fn has_custom_pacing(_r: &Congestion) -> bool {
     true
 }
 
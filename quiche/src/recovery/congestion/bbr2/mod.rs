@@ -607,7 +607,7 @@ fn rollback(_r: &mut Congestion) -> bool {
     false
 }
 
-fn has_custom_pacing() -> bool {
+fn has_custom_pacing(_r: &Congestion) -> bool {
     true
 }
 
@@ -0,0 +1,758 @@
+// Copyright (C) 2025, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use std::cmp;
+
+use super::*;
+use crate::rand;
+use crate::recovery::MINIMUM_WINDOW_PACKETS;
+
+/// 1.2Mbps in bytes/sec
+const PACING_RATE_1_2MBPS: u64 = 1200 * 1000 / 8;
+
+/// The minimal cwnd value BBR3 tries to target, in bytes
+#[inline]
+fn bbr3_min_pipe_cwnd(r: &mut Congestion) -> usize {
+    MIN_PIPE_CWND_PKTS * r.max_datagram_size
+}
+
+// BBR3 Functions when ACK is received.
+//
+pub fn bbr3_update_model_and_state(
+    r: &mut Congestion, packet: &Acked, in_flight: usize, now: Instant,
+) {
+    per_loss::bbr3_update_latest_delivery_signals(r);
+    per_loss::bbr3_update_congestion_signals(r, packet);
+    bbr3_update_ack_aggregation(r, packet, now);
+    bbr3_check_full_bw_reached(r);
+    bbr3_check_startup_done(r);
+    bbr3_check_drain(r, in_flight, now);
+    bbr3_update_probe_bw_cycle_phase(r, in_flight, now);
+    bbr3_update_min_rtt(r, now);
+    bbr3_check_probe_rtt(r, in_flight, now);
+    per_loss::bbr3_advance_latest_delivery_signals(r);
+    per_loss::bbr3_bound_bw_for_model(r);
+}
+
+pub fn bbr3_update_control_parameters(
+    r: &mut Congestion, in_flight: usize, now: Instant,
+) {
+    pacing::bbr3_set_pacing_rate(r);
+    bbr3_set_send_quantum(r);
+
+    // Set outgoing packet pacing rate
+    // It is called here because send_quantum may be updated too.
+    r.set_pacing_rate(r.bbr3_state.pacing_rate, now);
+
+    bbr3_set_cwnd(r, in_flight);
+}
+
+// BBR3 Functions while processing ACKs.
+//
+
+// Startup
+fn bbr3_check_startup_done(r: &mut Congestion) {
+    if r.bbr3_state.state == BBR3StateMachine::Startup && r.bbr3_state.filled_pipe
+    {
+        bbr3_enter_drain(r);
+    }
+}
+
+// Exiting Startup Based on Bandwidth Plateau
+//
+// Unlike BBRv2 this also runs outside of Startup, so that ProbeBW_UP can
+// tell when the bandwidth stopped growing.
+fn bbr3_check_full_bw_reached(r: &mut Congestion) {
+    if r.bbr3_state.full_bw_now || r.delivery_rate.sample_is_app_limited() {
+        // No need to check for a full pipe now.
+        return;
+    }
+
+    let rate = r.delivery_rate();
+
+    // Still growing?
+    if rate as f64 >= r.bbr3_state.full_bw as f64 * MAX_BW_GROWTH_THRESHOLD {
+        // Record new baseline level
+        init::bbr3_reset_full_bw(r);
+        r.bbr3_state.full_bw = rate;
+        return;
+    }
+
+    if !r.bbr3_state.round_start {
+        return;
+    }
+
+    // Another round w/o much growth
+    r.bbr3_state.full_bw_count += 1;
+    r.bbr3_state.full_bw_now = r.bbr3_state.full_bw_count >= MAX_BW_COUNT;
+
+    if r.bbr3_state.full_bw_now {
+        r.bbr3_state.filled_pipe = true;
+    }
+}
+
+// Drain
+fn bbr3_enter_drain(r: &mut Congestion) {
+    let bbr = &mut r.bbr3_state;
+
+    bbr.state = BBR3StateMachine::Drain;
+
+    // pace slowly
+    bbr.pacing_gain = DRAIN_PACING_GAIN;
+
+    // maintain cwnd
+    bbr.cwnd_gain = STARTUP_CWND_GAIN;
+}
+
+fn bbr3_check_drain(r: &mut Congestion, in_flight: usize, now: Instant) {
+    if r.bbr3_state.state == BBR3StateMachine::Drain &&
+        in_flight <= bbr3_inflight(r, r.bbr3_state.max_bw, 1.0)
+    {
+        // BBR estimates the queue was drained
+        bbr3_enter_probe_bw(r, now);
+    }
+}
+
+// ProbeBW
+fn bbr3_check_time_to_probe_bw(r: &mut Congestion, now: Instant) -> bool {
+    // Is it time to transition from DOWN or CRUISE to REFILL?
+    if bbr3_has_elapsed_in_phase(r, r.bbr3_state.bw_probe_wait, now) ||
+        bbr3_is_reno_coexistence_probe_time(r)
+    {
+        bbr3_start_probe_bw_refill(r);
+
+        return true;
+    }
+
+    false
+}
+
+// Randomized decision about how long to wait until
+// probing for bandwidth, using round count and wall clock.
+fn bbr3_pick_probe_wait(r: &mut Congestion) {
+    let bbr = &mut r.bbr3_state;
+
+    // Decide random round-trip bound for wait
+    bbr.rounds_since_probe = rand::rand_u8() as usize % 2;
+
+    // Decide the random wall clock bound for wait
+    bbr.bw_probe_wait = Duration::from_secs_f64(
+        2.0 + rand::rand_u64_uniform(1000000) as f64 / 1000000.0,
+    );
+}
+
+fn bbr3_is_reno_coexistence_probe_time(r: &mut Congestion) -> bool {
+    let reno_rounds = bbr3_target_inflight(r) / r.max_datagram_size;
+    let rounds = reno_rounds.min(63);
+
+    r.bbr3_state.rounds_since_probe >= rounds
+}
+
+// How much data do we want in flight?
+// Our estimated BDP, unless congestion cut cwnd.
+pub fn bbr3_target_inflight(r: &mut Congestion) -> usize {
+    r.bbr3_state.bdp.min(r.congestion_window)
+}
+
+// ProbeBW Algorithm Details
+fn bbr3_enter_probe_bw(r: &mut Congestion, now: Instant) {
+    bbr3_start_probe_bw_down(r, now);
+}
+
+pub fn bbr3_start_probe_bw_down(r: &mut Congestion, now: Instant) {
+    per_loss::bbr3_reset_congestion_signals(r);
+
+    // not growing inflight_hi
+    r.bbr3_state.probe_up_cnt = usize::MAX;
+
+    bbr3_pick_probe_wait(r);
+
+    // start wall clock
+    r.bbr3_state.cycle_stamp = now;
+    r.bbr3_state.ack_phase = BBR3AckPhase::ProbeStopping;
+
+    bbr3_start_round(r);
+
+    r.bbr3_state.state = BBR3StateMachine::ProbeBWDOWN;
+    r.bbr3_state.pacing_gain = PROBE_DOWN_PACING_GAIN;
+    r.bbr3_state.cwnd_gain = CWND_GAIN
+}
+
+fn bbr3_start_probe_bw_cruise(r: &mut Congestion) {
+    let bbr = &mut r.bbr3_state;
+
+    bbr.state = BBR3StateMachine::ProbeBWCRUISE;
+    bbr.pacing_gain = PACING_GAIN;
+    bbr.cwnd_gain = CWND_GAIN;
+}
+
+fn bbr3_start_probe_bw_refill(r: &mut Congestion) {
+    per_loss::bbr3_reset_lower_bounds(r);
+
+    r.bbr3_state.bw_probe_up_rounds = 0;
+    r.bbr3_state.bw_probe_up_acks = 0;
+    r.bbr3_state.ack_phase = BBR3AckPhase::Refilling;
+
+    bbr3_start_round(r);
+
+    r.bbr3_state.state = BBR3StateMachine::ProbeBWREFILL;
+    r.bbr3_state.pacing_gain = PACING_GAIN;
+    r.bbr3_state.cwnd_gain = CWND_GAIN;
+}
+
+fn bbr3_start_probe_bw_up(r: &mut Congestion, now: Instant) {
+    r.bbr3_state.ack_phase = BBR3AckPhase::ProbeStarting;
+
+    bbr3_start_round(r);
+
+    // Look for the bandwidth to plateau again while probing.
+    init::bbr3_reset_full_bw(r);
+    r.bbr3_state.full_bw = r.delivery_rate();
+
+    // Start wall clock.
+    r.bbr3_state.cycle_stamp = now;
+    r.bbr3_state.state = BBR3StateMachine::ProbeBWUP;
+    r.bbr3_state.pacing_gain = PROBE_UP_PACING_GAIN;
+    r.bbr3_state.cwnd_gain = PROBE_UP_CWND_GAIN;
+
+    bbr3_raise_inflight_hi_slope(r);
+}
+
+// The core state machine logic for ProbeBW
+fn bbr3_update_probe_bw_cycle_phase(
+    r: &mut Congestion, in_flight: usize, now: Instant,
+) {
+    if !r.bbr3_state.filled_pipe {
+        // only handling steady-state behavior here
+        return;
+    }
+
+    bbr3_adapt_upper_bounds(r);
+
+    if !bbr3_is_in_a_probe_bw_state(r) {
+        // only handling ProbeBW states here
+        return;
+    }
+
+    let state = r.bbr3_state.state;
+
+    match state {
+        BBR3StateMachine::ProbeBWDOWN => {
+            if bbr3_check_time_to_probe_bw(r, now) {
+                // Already decided state transition.
+                return;
+            }
+
+            if bbr3_check_time_to_cruise(r, in_flight) {
+                bbr3_start_probe_bw_cruise(r);
+            }
+        },
+
+        BBR3StateMachine::ProbeBWCRUISE => {
+            bbr3_check_time_to_probe_bw(r, now);
+        },
+
+        // After one round of REFILL, start UP.
+        BBR3StateMachine::ProbeBWREFILL if r.bbr3_state.round_start => {
+            r.bbr3_state.bw_probe_samples = true;
+
+            bbr3_start_probe_bw_up(r, now);
+        },
+
+        BBR3StateMachine::ProbeBWUP if bbr3_is_time_to_go_down(r) => {
+            bbr3_start_probe_bw_down(r, now);
+        },
+
+        _ => (),
+    }
+}
+
+pub fn bbr3_is_in_a_probe_bw_state(r: &mut Congestion) -> bool {
+    let state = r.bbr3_state.state;
+
+    state == BBR3StateMachine::ProbeBWDOWN ||
+        state == BBR3StateMachine::ProbeBWCRUISE ||
+        state == BBR3StateMachine::ProbeBWREFILL ||
+        state == BBR3StateMachine::ProbeBWUP
+}
+
+fn bbr3_check_time_to_cruise(r: &mut Congestion, in_flight: usize) -> bool {
+    if in_flight > bbr3_inflight_with_headroom(r) {
+        // Not enough headroom.
+        return false;
+    }
+
+    if in_flight <= bbr3_inflight(r, r.bbr3_state.max_bw, 1.0) {
+        // inflight <= estimated BDP
+        return true;
+    }
+
+    false
+}
+
+// Leave ProbeBW_UP once the bandwidth stopped growing, unless it's
+// inflight_hi rather than the path that is holding it back.
+fn bbr3_is_time_to_go_down(r: &mut Congestion) -> bool {
+    if !r.app_limited && r.congestion_window >= r.bbr3_state.inflight_hi {
+        // bw is limited by inflight_hi.
+        init::bbr3_reset_full_bw(r);
+        r.bbr3_state.full_bw = r.delivery_rate();
+    } else if r.bbr3_state.full_bw_now {
+        // We estimate we've fully used path bw.
+        return true;
+    }
+
+    false
+}
+
+fn bbr3_has_elapsed_in_phase(
+    r: &mut Congestion, interval: Duration, now: Instant,
+) -> bool {
+    now > r.bbr3_state.cycle_stamp + interval
+}
+
+// Return a volume of data that tries to leave free
+// headroom in the bottleneck buffer or link for
+// other flows, for fairness convergence and lower
+// RTTs and loss
+fn bbr3_inflight_with_headroom(r: &mut Congestion) -> usize {
+    let bbr = &mut r.bbr3_state;
+
+    if bbr.inflight_hi == usize::MAX {
+        return usize::MAX;
+    }
+
+    let headroom = ((HEADROOM * bbr.inflight_hi as f64) as usize).max(1);
+
+    bbr.inflight_hi
+        .saturating_sub(headroom)
+        .max(bbr3_min_pipe_cwnd(r))
+}
+
+// Raise inflight_hi slope if appropriate.
+fn bbr3_raise_inflight_hi_slope(r: &mut Congestion) {
+    let bbr = &mut r.bbr3_state;
+
+    let growth_this_round = (1 << bbr.bw_probe_up_rounds) * r.max_datagram_size;
+
+    bbr.bw_probe_up_rounds = (bbr.bw_probe_up_rounds + 1).min(30);
+    bbr.probe_up_cnt = (r.congestion_window / growth_this_round).max(1);
+}
+
+// Increase inflight_hi if appropriate.
+fn bbr3_probe_inflight_hi_upward(r: &mut Congestion) {
+    if r.app_limited || r.congestion_window < r.bbr3_state.inflight_hi {
+        // Not fully using inflight_hi, so don't grow it.
+        return;
+    }
+
+    let bbr = &mut r.bbr3_state;
+
+    // bw_probe_up_acks is a packet count.
+    bbr.bw_probe_up_acks += 1;
+
+    if bbr.bw_probe_up_acks >= bbr.probe_up_cnt {
+        let delta = bbr.bw_probe_up_acks / bbr.probe_up_cnt;
+
+        bbr.bw_probe_up_acks -= delta * bbr.probe_up_cnt;
+
+        bbr.inflight_hi += delta * r.max_datagram_size;
+    }
+
+    if bbr.round_start {
+        bbr3_raise_inflight_hi_slope(r);
+    }
+}
+
+// Track ACK state and update bbr.max_bw window and bbr.inflight_hi.
+//
+// Too much loss is handled as losses are detected, see
+// `per_loss::bbr3_handle_lost_packet()`, so only the upward adjustment is
+// done here.
+fn bbr3_adapt_upper_bounds(r: &mut Congestion) {
+    if r.bbr3_state.ack_phase == BBR3AckPhase::ProbeStarting &&
+        r.bbr3_state.round_start
+    {
+        // Starting to get bw probing samples.
+        r.bbr3_state.ack_phase = BBR3AckPhase::ProbeFeedback;
+    }
+
+    if r.bbr3_state.ack_phase == BBR3AckPhase::ProbeStopping &&
+        r.bbr3_state.round_start
+    {
+        r.bbr3_state.bw_probe_samples = false;
+        r.bbr3_state.ack_phase = BBR3AckPhase::Init;
+
+        // End of samples from bw probing phase.
+        if bbr3_is_in_a_probe_bw_state(r) &&
+            !r.delivery_rate.sample_is_app_limited()
+        {
+            bbr3_advance_max_bw_filter(r);
+        }
+    }
+
+    if r.bbr3_state.inflight_hi == usize::MAX {
+        // No upper bounds to raise.
+        return;
+    }
+
+    if r.bbr3_state.state == BBR3StateMachine::ProbeBWUP {
+        bbr3_probe_inflight_hi_upward(r);
+    }
+}
+
+// ProbeRTT
+fn bbr3_update_min_rtt(r: &mut Congestion, now: Instant) {
+    let bbr = &mut r.bbr3_state;
+
+    bbr.probe_rtt_expired = now > bbr.probe_rtt_min_stamp + PROBE_RTT_INTERVAL;
+
+    let rs_rtt = r.delivery_rate.sample_rtt();
+
+    if !rs_rtt.is_zero() &&
+        (rs_rtt < bbr.probe_rtt_min_delay || bbr.probe_rtt_expired)
+    {
+        bbr.probe_rtt_min_delay = rs_rtt;
+        bbr.probe_rtt_min_stamp = now;
+    }
+
+    let min_rtt_expired = now > bbr.min_rtt_stamp + MIN_RTT_FILTER_LEN;
+
+    if bbr.probe_rtt_min_delay < bbr.min_rtt || min_rtt_expired {
+        bbr.min_rtt = bbr.probe_rtt_min_delay;
+        bbr.min_rtt_stamp = bbr.probe_rtt_min_stamp;
+    }
+}
+
+fn bbr3_check_probe_rtt(r: &mut Congestion, in_flight: usize, now: Instant) {
+    if r.bbr3_state.state != BBR3StateMachine::ProbeRTT &&
+        r.bbr3_state.probe_rtt_expired &&
+        !r.bbr3_state.idle_restart
+    {
+        bbr3_enter_probe_rtt(r);
+
+        r.bbr3_state.prior_cwnd = bbr3_save_cwnd(r);
+        r.bbr3_state.probe_rtt_done_stamp = None;
+        r.bbr3_state.ack_phase = BBR3AckPhase::ProbeStopping;
+
+        bbr3_start_round(r);
+    }
+
+    if r.bbr3_state.state == BBR3StateMachine::ProbeRTT {
+        bbr3_handle_probe_rtt(r, in_flight, now);
+    }
+
+    if r.delivery_rate.sample_delivered() > 0 {
+        r.bbr3_state.idle_restart = false;
+    }
+}
+
+fn bbr3_enter_probe_rtt(r: &mut Congestion) {
+    let bbr = &mut r.bbr3_state;
+
+    bbr.state = BBR3StateMachine::ProbeRTT;
+    bbr.pacing_gain = PACING_GAIN;
+    bbr.cwnd_gain = PROBE_RTT_CWND_GAIN;
+}
+
+fn bbr3_handle_probe_rtt(r: &mut Congestion, in_flight: usize, now: Instant) {
+    // Ignore low rate samples during ProbeRTT.
+    r.delivery_rate.update_app_limited(true);
+
+    if r.bbr3_state.probe_rtt_done_stamp.is_some() {
+        if r.bbr3_state.round_start {
+            r.bbr3_state.probe_rtt_round_done = true;
+        }
+
+        if r.bbr3_state.probe_rtt_round_done {
+            bbr3_check_probe_rtt_done(r, now);
+        }
+    } else if in_flight <= bbr3_probe_rtt_cwnd(r) {
+        // Wait for at least ProbeRTTDuration to elapse.
+        r.bbr3_state.probe_rtt_done_stamp = Some(now + PROBE_RTT_DURATION);
+
+        // Wait for at lease one round to elapse.
+        r.bbr3_state.probe_rtt_round_done = false;
+
+        bbr3_start_round(r);
+    }
+}
+
+pub fn bbr3_check_probe_rtt_done(r: &mut Congestion, now: Instant) {
+    let bbr = &mut r.bbr3_state;
+
+    if let Some(probe_rtt_done_stamp) = bbr.probe_rtt_done_stamp {
+        if now > probe_rtt_done_stamp {
+            // Schedule next ProbeRTT.
+            bbr.probe_rtt_min_stamp = now;
+
+            bbr3_restore_cwnd(r);
+            bbr3_exit_probe_rtt(r, now);
+        }
+    }
+}
+
+// Exiting ProbeRTT
+fn bbr3_exit_probe_rtt(r: &mut Congestion, now: Instant) {
+    per_loss::bbr3_reset_lower_bounds(r);
+
+    if r.bbr3_state.filled_pipe {
+        bbr3_start_probe_bw_down(r, now);
+        bbr3_start_probe_bw_cruise(r);
+    } else {
+        init::bbr3_enter_startup(r);
+    }
+}
+
+// BBR.round_count: Tracking Packet-Timed Round Trips
+fn bbr3_update_round(r: &mut Congestion, packet: &Acked) {
+    if packet.delivered >= r.bbr3_state.next_round_delivered {
+        bbr3_start_round(r);
+
+        r.bbr3_state.round_count += 1;
+        r.bbr3_state.rounds_since_probe += 1;
+        r.bbr3_state.round_start = true;
+    } else {
+        r.bbr3_state.round_start = false;
+    }
+}
+
+fn bbr3_start_round(r: &mut Congestion) {
+    r.bbr3_state.next_round_delivered = r.delivery_rate.delivered();
+}
+
+// Updating the BBR.max_bw Max Filter
+pub fn bbr3_update_max_bw(r: &mut Congestion, packet: &Acked) {
+    bbr3_update_round(r, packet);
+
+    let rate = r.delivery_rate();
+
+    if rate >= r.bbr3_state.max_bw || !r.delivery_rate.sample_is_app_limited() {
+        let bbr = &mut r.bbr3_state;
+
+        let slot = (bbr.cycle_count % 2) as usize;
+
+        bbr.max_bw_filter[slot] = bbr.max_bw_filter[slot].max(rate);
+        bbr.max_bw = bbr.max_bw_filter[0].max(bbr.max_bw_filter[1]);
+    }
+}
+
+// Tracking Time for the BBR.max_bw Max Filter
+fn bbr3_advance_max_bw_filter(r: &mut Congestion) {
+    let bbr = &mut r.bbr3_state;
+
+    bbr.cycle_count += 1;
+
+    // Expire the samples from two cycles ago.
+    let slot = (bbr.cycle_count % 2) as usize;
+
+    bbr.max_bw_filter[slot] = 0;
+    bbr.max_bw = bbr.max_bw_filter[0].max(bbr.max_bw_filter[1]);
+}
+
+// BBR.offload_budget
+fn bbr3_update_offload_budget(r: &mut Congestion) {
+    r.bbr3_state.offload_budget = 3 * r.send_quantum;
+}
+
+// BBR.extra_acked
+fn bbr3_update_ack_aggregation(r: &mut Congestion, packet: &Acked, now: Instant) {
+    let bbr = &mut r.bbr3_state;
+
+    // Find excess ACKed beyond expected amount over this interval.
+    let interval = now - bbr.extra_acked_interval_start;
+    let mut expected_delivered =
+        (bbr.bw as f64 * interval.as_secs_f64()) as usize;
+
+    // Reset interval if ACK rate is below expected rate.
+    if bbr.extra_acked_delivered <= expected_delivered {
+        bbr.extra_acked_delivered = 0;
+        bbr.extra_acked_interval_start = now;
+        expected_delivered = 0;
+    }
+
+    bbr.extra_acked_delivered += packet.size;
+
+    let extra = bbr.extra_acked_delivered.saturating_sub(expected_delivered);
+    let extra = extra.min(r.congestion_window);
+
+    // The filter window is in rounds, so use the round count as virtual time.
+    bbr.extra_acked = bbr.extra_acked_filter.running_max(
+        Duration::from_secs(EXTRA_ACKED_FILTER_LEN),
+        bbr.start_time + Duration::from_secs(bbr.round_count),
+        extra,
+    );
+}
+
+// Send Quantum: BBR.send_quantum
+fn bbr3_set_send_quantum(r: &mut Congestion) {
+    let bbr = &mut r.bbr3_state;
+
+    let rate = bbr.pacing_rate;
+    let floor = if rate < PACING_RATE_1_2MBPS {
+        r.max_datagram_size
+    } else {
+        2 * r.max_datagram_size
+    };
+
+    r.send_quantum = cmp::min((rate / 1000_u64) as usize, 64 * 1024); // Assumes send buffer is limited to 64KB
+    r.send_quantum = r.send_quantum.max(floor);
+}
+
+// Initial cwnd
+// Computing BBR.max_inflight
+pub fn bbr3_bdp_multiple(r: &mut Congestion, bw: u64, gain: f64) -> usize {
+    let bbr = &mut r.bbr3_state;
+
+    if bbr.min_rtt == Duration::MAX {
+        // No valid RTT samples yet.
+        return r.max_datagram_size * r.initial_congestion_window_packets;
+    }
+
+    bbr.bdp = (bw as f64 * bbr.min_rtt.as_secs_f64()) as usize;
+
+    (gain * bbr.bdp as f64) as usize
+}
+
+fn bbr3_quantization_budget(r: &mut Congestion, inflight: usize) -> usize {
+    bbr3_update_offload_budget(r);
+
+    let inflight = inflight.max(r.bbr3_state.offload_budget);
+    let inflight = inflight.max(bbr3_min_pipe_cwnd(r));
+
+    if r.bbr3_state.state == BBR3StateMachine::ProbeBWUP {
+        return inflight + 2 * r.max_datagram_size;
+    }
+
+    inflight
+}
+
+fn bbr3_inflight(r: &mut Congestion, bw: u64, gain: f64) -> usize {
+    let inflight = bbr3_bdp_multiple(r, bw, gain);
+
+    bbr3_quantization_budget(r, inflight)
+}
+
+fn bbr3_update_max_inflight(r: &mut Congestion) {
+    let inflight = bbr3_bdp_multiple(r, r.bbr3_state.bw, r.bbr3_state.cwnd_gain);
+    let inflight = inflight + r.bbr3_state.extra_acked;
+
+    r.bbr3_state.max_inflight = bbr3_quantization_budget(r, inflight);
+}
+
+// Modulating cwnd in Loss Recovery
+pub fn bbr3_save_cwnd(r: &mut Congestion) -> usize {
+    if !r.bbr3_state.in_recovery &&
+        r.bbr3_state.state != BBR3StateMachine::ProbeRTT
+    {
+        r.congestion_window
+    } else {
+        r.congestion_window.max(r.bbr3_state.prior_cwnd)
+    }
+}
+
+pub fn bbr3_restore_cwnd(r: &mut Congestion) {
+    r.congestion_window = r.congestion_window.max(r.bbr3_state.prior_cwnd);
+}
+
+fn bbr3_modulate_cwnd_for_recovery(r: &mut Congestion, in_flight: usize) {
+    let acked_bytes = r.bbr3_state.newly_acked_bytes;
+    let lost_bytes = r.bbr3_state.newly_lost_bytes;
+
+    if lost_bytes > 0 {
+        // QUIC mininum cwnd is 2 x MSS.
+        r.congestion_window = r
+            .congestion_window
+            .saturating_sub(lost_bytes)
+            .max(r.max_datagram_size * MINIMUM_WINDOW_PACKETS);
+    }
+
+    if r.bbr3_state.packet_conservation {
+        r.congestion_window = r.congestion_window.max(in_flight + acked_bytes);
+    }
+}
+
+// Modulating cwnd in ProbeRTT
+fn bbr3_probe_rtt_cwnd(r: &mut Congestion) -> usize {
+    let probe_rtt_cwnd =
+        bbr3_bdp_multiple(r, r.bbr3_state.bw, PROBE_RTT_CWND_GAIN);
+
+    probe_rtt_cwnd.max(bbr3_min_pipe_cwnd(r))
+}
+
+fn bbr3_bound_cwnd_for_probe_rtt(r: &mut Congestion) {
+    if r.bbr3_state.state == BBR3StateMachine::ProbeRTT {
+        r.congestion_window = r.congestion_window.min(bbr3_probe_rtt_cwnd(r));
+    }
+}
+
+// Core cwnd Adjustment Mechanism
+fn bbr3_set_cwnd(r: &mut Congestion, in_flight: usize) {
+    let acked_bytes = r.bbr3_state.newly_acked_bytes;
+
+    bbr3_update_max_inflight(r);
+    bbr3_modulate_cwnd_for_recovery(r, in_flight);
+
+    if !r.bbr3_state.packet_conservation {
+        if r.bbr3_state.filled_pipe {
+            r.congestion_window = cmp::min(
+                r.congestion_window + acked_bytes,
+                r.bbr3_state.max_inflight,
+            )
+        } else if r.congestion_window < r.bbr3_state.max_inflight ||
+            r.delivery_rate.delivered() <
+                r.max_datagram_size * r.initial_congestion_window_packets
+        {
+            r.congestion_window += acked_bytes;
+        }
+
+        r.congestion_window = r.congestion_window.max(bbr3_min_pipe_cwnd(r))
+    }
+
+    bbr3_bound_cwnd_for_probe_rtt(r);
+    bbr3_bound_cwnd_for_model(r);
+}
+
+// Bounding cwnd Based on Recent Congestion
+fn bbr3_bound_cwnd_for_model(r: &mut Congestion) {
+    let mut cap = usize::MAX;
+
+    if bbr3_is_in_a_probe_bw_state(r) &&
+        r.bbr3_state.state != BBR3StateMachine::ProbeBWCRUISE
+    {
+        cap = r.bbr3_state.inflight_hi;
+    } else if r.bbr3_state.state == BBR3StateMachine::ProbeRTT ||
+        r.bbr3_state.state == BBR3StateMachine::ProbeBWCRUISE
+    {
+        cap = bbr3_inflight_with_headroom(r);
+    }
+
+    // Apply inflight_lo (possibly infinite).
+    cap = cap.min(r.bbr3_state.inflight_lo);
+    cap = cap.max(bbr3_min_pipe_cwnd(r));
+
+    r.congestion_window = r.congestion_window.min(cap);
+}
@@ -0,0 +1,237 @@
+// Copyright (C) 2025, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use super::*;
+
+// BBR3 Functions on every packet loss event.
+//
+// Per-Loss Steps
+pub fn bbr3_update_on_loss(
+    r: &mut Congestion, packet: &Sent, lost_bytes: usize, now: Instant,
+) {
+    bbr3_note_loss(r, lost_bytes);
+    bbr3_handle_lost_packet(r, packet, lost_bytes, now);
+}
+
+// Record the loss event for the current loss round. A CE mark is reported
+// with no lost bytes, and only counts as congestion for the round.
+fn bbr3_note_loss(r: &mut Congestion, lost_bytes: usize) {
+    let bbr = &mut r.bbr3_state;
+
+    bbr.loss_in_round = true;
+
+    if lost_bytes > 0 {
+        bbr.loss_events_in_round += 1;
+        bbr.lost_in_round += lost_bytes;
+    }
+}
+
+// Updating the Model Upon Packet Loss
+// Probing for Bandwidth In ProbeBW
+fn bbr3_is_inflight_too_high(r: &mut Congestion) -> bool {
+    r.bbr3_state.lost > (r.bbr3_state.tx_in_flight as f64 * LOSS_THRESH) as usize
+}
+
+fn bbr3_handle_inflight_too_high(r: &mut Congestion, now: Instant) {
+    // Only react once per bw probe.
+    r.bbr3_state.bw_probe_samples = false;
+
+    if !r.delivery_rate.sample_is_app_limited() {
+        r.bbr3_state.inflight_hi = r
+            .bbr3_state
+            .tx_in_flight
+            .max((per_ack::bbr3_target_inflight(r) as f64 * BETA) as usize);
+    }
+
+    if r.bbr3_state.state == BBR3StateMachine::ProbeBWUP {
+        per_ack::bbr3_start_probe_bw_down(r, now);
+    }
+}
+
+fn bbr3_handle_lost_packet(
+    r: &mut Congestion, packet: &Sent, lost_bytes: usize, now: Instant,
+) {
+    if !r.bbr3_state.bw_probe_samples || lost_bytes == 0 {
+        return;
+    }
+
+    r.bbr3_state.tx_in_flight = packet.tx_in_flight;
+    r.bbr3_state.lost = lost_bytes;
+
+    r.delivery_rate.update_app_limited(packet.is_app_limited);
+
+    if bbr3_is_inflight_too_high(r) {
+        r.bbr3_state.tx_in_flight = bbr3_inflight_hi_from_lost_packet(r, packet);
+
+        bbr3_handle_inflight_too_high(r, now);
+    }
+}
+
+fn bbr3_inflight_hi_from_lost_packet(r: &mut Congestion, packet: &Sent) -> usize {
+    let size = packet.size;
+    let inflight_prev = r.bbr3_state.tx_in_flight.saturating_sub(size);
+    let lost_prev = r.bbr3_state.lost.saturating_sub(size);
+    let lost_prefix = (LOSS_THRESH * inflight_prev as f64 - lost_prev as f64) /
+        (1.0 - LOSS_THRESH);
+
+    inflight_prev + lost_prefix.max(0.0) as usize
+}
+
+// Exiting Startup Based on Packet Loss
+//
+// Startup is exited once a round trip has seen at least FULL_LOSS_COUNT loss
+// events and a loss rate above LOSS_THRESH, without waiting for the
+// bandwidth to plateau.
+fn bbr3_check_startup_high_loss(r: &mut Congestion) {
+    let bbr = &r.bbr3_state;
+
+    if bbr.filled_pipe || bbr.state != BBR3StateMachine::Startup {
+        return;
+    }
+
+    let round_bytes = bbr.loss_round_delivered_bytes + bbr.lost_in_round;
+
+    if bbr.loss_events_in_round >= FULL_LOSS_COUNT &&
+        bbr.lost_in_round as f64 > round_bytes as f64 * LOSS_THRESH
+    {
+        let inflight = per_ack::bbr3_bdp_multiple(r, r.bbr3_state.bw, 1.0);
+
+        r.bbr3_state.inflight_hi = inflight.max(r.bbr3_state.inflight_latest);
+        r.bbr3_state.filled_pipe = true;
+    }
+}
+
+// When not Probing for Bandwidth
+pub fn bbr3_update_latest_delivery_signals(r: &mut Congestion) {
+    let bbr = &mut r.bbr3_state;
+
+    // Near start of ACK processing.
+    bbr.loss_round_start = false;
+    bbr.bw_latest = bbr.bw_latest.max(r.delivery_rate.sample_delivery_rate());
+    bbr.inflight_latest =
+        bbr.inflight_latest.max(r.delivery_rate.sample_delivered());
+
+    if r.delivery_rate.sample_prior_delivered() >= bbr.loss_round_delivered {
+        bbr.loss_round_delivered_bytes =
+            r.delivery_rate.delivered() - bbr.loss_round_delivered;
+        bbr.loss_round_delivered = r.delivery_rate.delivered();
+        bbr.loss_round_start = true;
+    }
+}
+
+pub fn bbr3_advance_latest_delivery_signals(r: &mut Congestion) {
+    let bbr = &mut r.bbr3_state;
+
+    // Near end of ACK processing.
+    if bbr.loss_round_start {
+        bbr.bw_latest = r.delivery_rate.sample_delivery_rate();
+        bbr.inflight_latest = r.delivery_rate.sample_delivered();
+    }
+}
+
+pub fn bbr3_reset_congestion_signals(r: &mut Congestion) {
+    let bbr = &mut r.bbr3_state;
+
+    bbr.loss_in_round = false;
+    bbr.loss_events_in_round = 0;
+    bbr.lost_in_round = 0;
+    bbr.bw_latest = 0;
+    bbr.inflight_latest = 0;
+}
+
+pub fn bbr3_update_congestion_signals(r: &mut Congestion, packet: &Acked) {
+    // Update congestion state on every ACK.
+    per_ack::bbr3_update_max_bw(r, packet);
+
+    if !r.bbr3_state.loss_round_start {
+        // Wait until end of round trip.
+        return;
+    }
+
+    bbr3_check_startup_high_loss(r);
+    bbr3_adapt_lower_bounds_from_congestion(r);
+
+    r.bbr3_state.loss_in_round = false;
+    r.bbr3_state.loss_events_in_round = 0;
+    r.bbr3_state.lost_in_round = 0;
+}
+
+fn bbr3_adapt_lower_bounds_from_congestion(r: &mut Congestion) {
+    // Once per round-trip respond to congestion.
+    if bbr3_is_probing_bw(r) {
+        return;
+    }
+
+    if r.bbr3_state.loss_in_round {
+        bbr3_init_lower_bounds(r);
+        bbr3_loss_lower_bounds(r);
+    }
+}
+
+fn bbr3_init_lower_bounds(r: &mut Congestion) {
+    let bbr = &mut r.bbr3_state;
+
+    // Handle the first congestion episode in this cycle.
+    if bbr.bw_lo == u64::MAX {
+        bbr.bw_lo = bbr.max_bw;
+    }
+
+    if bbr.inflight_lo == usize::MAX {
+        bbr.inflight_lo = r.congestion_window;
+    }
+}
+
+fn bbr3_loss_lower_bounds(r: &mut Congestion) {
+    let bbr = &mut r.bbr3_state;
+
+    // Adjust model once per round based on loss.
+    bbr.bw_lo = bbr.bw_latest.max((bbr.bw_lo as f64 * BETA) as u64);
+    bbr.inflight_lo = bbr
+        .inflight_latest
+        .max((bbr.inflight_lo as f64 * BETA) as usize);
+}
+
+pub fn bbr3_reset_lower_bounds(r: &mut Congestion) {
+    let bbr = &mut r.bbr3_state;
+
+    bbr.bw_lo = u64::MAX;
+    bbr.inflight_lo = usize::MAX;
+}
+
+pub fn bbr3_bound_bw_for_model(r: &mut Congestion) {
+    let bbr = &mut r.bbr3_state;
+
+    bbr.bw = bbr.max_bw.min(bbr.bw_lo);
+}
+
+// This function is not defined in the draft but used.
+fn bbr3_is_probing_bw(r: &mut Congestion) -> bool {
+    let state = r.bbr3_state.state;
+
+    state == BBR3StateMachine::Startup ||
+        state == BBR3StateMachine::ProbeBWREFILL ||
+        state == BBR3StateMachine::ProbeBWUP
+}
@@ -401,7 +401,7 @@ fn rollback(r: &mut Congestion) -> bool {
     true
 }
 
-fn has_custom_pacing() -> bool {
+fn has_custom_pacing(_r: &Congestion) -> bool {
     false
 }
 
@@ -0,0 +1,188 @@
+// Copyright (C) 2025, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Adapter for congestion controllers implemented outside of quiche.
+
+use std::time::Duration;
+use std::time::Instant;
+
+use super::*;
+
+/// A congestion controller implemented outside of quiche.
+///
+/// Custom controllers are set with [`Config::set_custom_cc()`], and replace
+/// the algorithm selected with [`Config::set_cc_algorithm()`]. The instance
+/// passed to the configuration is only used as a template: each path of each
+/// connection gets its own controller, created with [`new_instance()`].
+///
+/// All sizes are in bytes. quiche keeps track of packet numbers, bytes in
+/// flight and loss detection, and only reports the outcome of each packet to
+/// the controller.
+///
+/// [`Config::set_custom_cc()`]: crate::Config::set_custom_cc
+/// [`Config::set_cc_algorithm()`]: crate::Config::set_cc_algorithm
+/// [`new_instance()`]: CongestionControl::new_instance
+pub trait CongestionControl: Send + Sync {
+    /// Returns a new controller in its initial state, for a new path.
+    fn new_instance(&self) -> Box<dyn CongestionControl>;
+
+    /// Called when a packet that counts towards bytes in flight is sent.
+    ///
+    /// `bytes_in_flight` doesn't include the packet being sent.
+    fn on_packet_sent(
+        &mut self, sent_bytes: usize, bytes_in_flight: usize, now: Instant,
+    );
+
+    /// Called for each newly acknowledged packet.
+    ///
+    /// `bytes_in_flight` still includes the packet being acknowledged.
+    fn on_packet_acked(
+        &mut self, acked_bytes: usize, time_sent: Instant,
+        bytes_in_flight: usize, smoothed_rtt: Duration, min_rtt: Duration,
+        now: Instant,
+    );
+
+    /// Called when packets are declared lost.
+    ///
+    /// This is called once per loss detection event, with `lost_bytes`
+    /// covering all the packets declared lost and `time_sent` being the send
+    /// time of the largest of them. A `lost_bytes` of zero signals that the
+    /// peer reported ECN Congestion Experienced marks instead.
+    fn on_packet_lost(
+        &mut self, lost_bytes: usize, time_sent: Instant, bytes_in_flight: usize,
+        now: Instant,
+    );
+
+    /// Returns the current congestion window.
+    fn cwnd(&self) -> usize;
+
+    /// Returns the current pacing rate, in bytes per second.
+    ///
+    /// When `None` is returned, quiche paces packets based on the congestion
+    /// window and the smoothed RTT, as for its own Reno and CUBIC
+    /// implementations.
+    fn pacing_rate(&self) -> Option<u64> {
+        None
+    }
+}
+
+pub(crate) static CUSTOM: CongestionControlOps = CongestionControlOps {
+    on_init,
+    on_packet_sent,
+    on_packets_acked,
+    congestion_event,
+    checkpoint,
+    rollback,
+    has_custom_pacing,
+    debug_fmt,
+};
+
+// Picks up the latest state of the controller after it handled an event.
+fn update(r: &mut Congestion, now: Instant) {
+    let cc = match r.custom_cc.as_ref() {
+        Some(cc) => cc,
+
+        None => return,
+    };
+
+    r.congestion_window = cc.cwnd();
+
+    if let Some(rate) = cc.pacing_rate() {
+        r.set_pacing_rate(rate, now);
+    }
+}
+
+fn on_init(r: &mut Congestion) {
+    if let Some(cc) = r.custom_cc.as_ref() {
+        r.congestion_window = cc.cwnd();
+    }
+}
+
+fn on_packet_sent(
+    r: &mut Congestion, sent_bytes: usize, bytes_in_flight: usize, now: Instant,
+) {
+    if let Some(cc) = r.custom_cc.as_mut() {
+        cc.on_packet_sent(sent_bytes, bytes_in_flight, now);
+    }
+
+    update(r, now);
+}
+
+fn on_packets_acked(
+    r: &mut Congestion, bytes_in_flight: usize, packets: &mut Vec<Acked>,
+    now: Instant, rtt_stats: &RttStats,
+) {
+    if let Some(cc) = r.custom_cc.as_mut() {
+        let mut bytes_in_flight = bytes_in_flight;
+
+        for pkt in packets.drain(..) {
+            cc.on_packet_acked(
+                pkt.size,
+                pkt.time_sent,
+                bytes_in_flight,
+                rtt_stats.smoothed_rtt,
+                *rtt_stats.min_rtt,
+                now,
+            );
+
+            bytes_in_flight = bytes_in_flight.saturating_sub(pkt.size);
+        }
+    }
+
+    update(r, now);
+}
+
+fn congestion_event(
+    r: &mut Congestion, bytes_in_flight: usize, lost_bytes: usize,
+    largest_lost_pkt: &Sent, now: Instant,
+) {
+    if let Some(cc) = r.custom_cc.as_mut() {
+        cc.on_packet_lost(
+            lost_bytes,
+            largest_lost_pkt.time_sent,
+            bytes_in_flight,
+            now,
+        );
+    }
+
+    update(r, now);
+}
+
+fn checkpoint(_r: &mut Congestion) {}
+
+fn rollback(_r: &mut Congestion) -> bool {
+    false
+}
+
+fn has_custom_pacing(r: &Congestion) -> bool {
+    r.custom_cc
+        .as_ref()
+        .is_some_and(|cc| cc.pacing_rate().is_some())
+}
+
+fn debug_fmt(_r: &Congestion, _f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    Ok(())
+}
@@ -33,7 +33,11 @@ use super::Sent;
 use crate::recovery::rtt;
 use crate::recovery::rtt::RttStats;
 use crate::recovery::CongestionControlAlgorithm;
+use crate::recovery::PacerAlgorithm;
+#[cfg(feature = "qlog")]
+use crate::recovery::QlogMetrics;
 
+/// The default pacing gain.
 pub const PACING_MULTIPLIER: f64 = 1.25;
 pub struct Congestion {
     // Congestion control.
@@ -60,6 +64,12 @@ pub struct Congestion {
     // BBRv2 state.
     bbr2_state: bbr2::State,
 
+    // BBRv3 state.
+    bbr3_state: bbr3::State,
+
+    // Congestion controller implemented outside of quiche, if any.
+    custom_cc: Option<Box<dyn custom::CongestionControl>>,
+
     pub(crate) congestion_window: usize,
 
     pub(crate) ssthresh: usize,
@@ -79,6 +89,10 @@ pub struct Congestion {
 
     max_datagram_size: usize,
 
+    /// Multiplier applied to cwnd / srtt to get the pacing rate, for
+    /// congestion control algorithms that don't do their own pacing.
+    pacing_gain: f64,
+
     pub(crate) lost_count: usize,
 }
 
@@ -98,7 +112,11 @@ impl Congestion {
 
             congestion_recovery_start_time: None,
 
-            cc_ops: recovery_config.cc_algorithm.into(),
+            cc_ops: match recovery_config.custom_cc {
+                Some(_) => &custom::CUSTOM,
+
+                None => recovery_config.cc_algorithm.into(),
+            },
 
             cubic_state: cubic::State::default(),
 
@@ -111,6 +129,8 @@ impl Congestion {
 
             max_datagram_size: recovery_config.max_send_udp_payload_size,
 
+            pacing_gain: recovery_config.pacing_gain,
+
             send_quantum: initial_congestion_window,
 
             delivery_rate: delivery_rate::Rate::default(),
@@ -119,6 +139,8 @@ impl Congestion {
 
             pacer: pacer::Pacer::new(
                 recovery_config.pacing,
+                recovery_config.pacer_algorithm,
+                recovery_config.max_pacing_burst,
                 initial_congestion_window,
                 0,
                 recovery_config.max_send_udp_payload_size,
@@ -130,6 +152,13 @@ impl Congestion {
             bbr_state: bbr::State::new(),
 
             bbr2_state: bbr2::State::new(),
+
+            bbr3_state: bbr3::State::new(),
+
+            custom_cc: recovery_config
+                .custom_cc
+                .as_ref()
+                .map(|cc| cc.new_instance()),
         };
 
         (cc.cc_ops.on_init)(&mut cc);
@@ -187,10 +216,10 @@ impl Congestion {
         }
 
         // Pacing: Set the pacing rate if CC doesn't do its own.
-        if !(self.cc_ops.has_custom_pacing)() &&
+        if !(self.cc_ops.has_custom_pacing)(self) &&
             rtt_stats.first_rtt_sample.is_some()
         {
-            let rate = PACING_MULTIPLIER * self.congestion_window as f64 /
+            let rate = self.pacing_gain * self.congestion_window as f64 /
                 rtt_stats.smoothed_rtt.as_secs_f64();
             self.set_pacing_rate(rate as u64, now);
         }
@@ -226,19 +255,24 @@ impl Congestion {
         );
     }
 
-    fn schedule_next_packet(&mut self, now: Instant, packet_size: usize) {
+    /// Returns whether outgoing packets are currently paced.
+    pub(crate) fn is_paced(&self) -> bool {
         // Don't pace in any of these cases:
-        //   * Packet contains no data.
         //   * The congestion window is within initcwnd.
+        //   * The hybrid pacer is in slow start.
 
         let in_initcwnd = self.congestion_window <
             self.max_datagram_size * self.initial_congestion_window_packets;
 
-        let sent_bytes = if !self.pacer.enabled() || in_initcwnd {
-            0
-        } else {
-            packet_size
-        };
+        let unpaced_slow_start = self.pacer.algorithm() == PacerAlgorithm::Hybrid &&
+            self.congestion_window < self.ssthresh;
+
+        self.pacer.enabled() && !in_initcwnd && !unpaced_slow_start
+    }
+
+    fn schedule_next_packet(&mut self, now: Instant, packet_size: usize) {
+        // Packets that aren't paced don't consume the pacer's budget.
+        let sent_bytes = if self.is_paced() { packet_size } else { 0 };
 
         self.pacer.send(sent_bytes, now);
     }
@@ -246,6 +280,14 @@ impl Congestion {
     pub(crate) fn get_packet_send_time(&self) -> Instant {
         self.pacer.next_time()
     }
+
+    /// Fills in the congestion controller specific recovery metrics.
+    #[cfg(feature = "qlog")]
+    pub(super) fn update_qlog_metrics(&self, metrics: &mut QlogMetrics) {
+        if std::ptr::eq(self.cc_ops, &bbr3::BBR3) {
+            bbr3::update_qlog_metrics(self, metrics);
+        }
+    }
 }
 
 pub(crate) struct CongestionControlOps {
@@ -278,7 +320,7 @@ pub(crate) struct CongestionControlOps {
 
     pub rollback: fn(r: &mut Congestion) -> bool,
 
-    pub has_custom_pacing: fn() -> bool,
+    pub has_custom_pacing: fn(r: &Congestion) -> bool,
 
     pub debug_fmt: fn(
         r: &Congestion,
@@ -293,6 +335,7 @@ impl From<CongestionControlAlgorithm> for &'static CongestionControlOps {
             CongestionControlAlgorithm::CUBIC => &cubic::CUBIC,
             CongestionControlAlgorithm::BBR => &bbr::BBR,
             CongestionControlAlgorithm::BBR2 => &bbr2::BBR2,
+            CongestionControlAlgorithm::BBR3 => &bbr3::BBR3,
             CongestionControlAlgorithm::Bbr2Gcongestion => {
                 debug_panic!("legacy implementation, not gcongestion");
                 &bbr2::BBR2
@@ -303,7 +346,9 @@ impl From<CongestionControlAlgorithm> for &'static CongestionControlOps {
 
 mod bbr;
 mod bbr2;
+mod bbr3;
 mod cubic;
+pub(crate) mod custom;
 mod delivery_rate;
 mod hystart;
 pub(crate) mod pacer;
@@ -37,15 +37,33 @@
 //! on the current pacing rate. It will make actual timestamp sent and recorded
 //! timestamp (Sent.time_sent) as close as possible. If GSO is not used, it will
 //! still try to provide close timestamp if the send burst is implemented.
+//!
+//! Alternatively, the pacer can be configured as a token bucket (see
+//! [`PacerAlgorithm`]), which is refilled at the pacing rate up to its
+//! capacity, and lets packets out immediately as long as there are enough
+//! tokens for them. This allows the sender to catch up after short idle
+//! periods, instead of restarting a new burst schedule.
 
 use std::time::Duration;
 use std::time::Instant;
 
+use crate::recovery::PacerAlgorithm;
+
 #[derive(Debug)]
 pub struct Pacer {
     /// Whether pacing is enabled.
     enabled: bool,
 
+    /// The pacing algorithm.
+    algorithm: PacerAlgorithm,
+
+    /// Max burst size (packets), overriding the capacity given by the
+    /// congestion control algorithm.
+    max_burst: Option<usize>,
+
+    /// Available tokens (bytes), only used by the token bucket.
+    tokens: usize,
+
     /// Bucket capacity (bytes).
     capacity: usize,
 
@@ -76,11 +94,11 @@ pub struct Pacer {
 
 impl Pacer {
     pub fn new(
-        enabled: bool, capacity: usize, rate: u64, max_datagram_size: usize,
+        enabled: bool, algorithm: PacerAlgorithm, max_burst: Option<usize>,
+        capacity: usize, rate: u64, max_datagram_size: usize,
         max_pacing_rate: Option<u64>,
     ) -> Self {
-        // Round capacity to MSS.
-        let capacity = capacity / max_datagram_size * max_datagram_size;
+        let capacity = burst_capacity(capacity, max_burst, max_datagram_size);
         let pacing_rate = if let Some(max_rate) = max_pacing_rate {
             max_rate.min(rate)
         } else {
@@ -90,6 +108,12 @@ impl Pacer {
         Pacer {
             enabled,
 
+            algorithm,
+
+            max_burst,
+
+            tokens: capacity,
+
             capacity,
 
             used: 0,
@@ -115,6 +139,22 @@ impl Pacer {
         self.enabled
     }
 
+    /// Returns the pacing algorithm.
+    pub fn algorithm(&self) -> PacerAlgorithm {
+        self.algorithm
+    }
+
+    /// Returns the configured max burst size, in packets.
+    pub fn max_burst(&self) -> Option<usize> {
+        self.max_burst
+    }
+
+    /// Returns the bucket capacity, i.e. the current max burst size in bytes.
+    #[cfg(any(test, feature = "qlog"))]
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
     /// Returns the current pacing rate.
     pub fn rate(&self) -> u64 {
         self.rate
@@ -127,7 +167,8 @@ impl Pacer {
 
     /// Updates the bucket capacity or pacing_rate.
     pub fn update(&mut self, capacity: usize, rate: u64, now: Instant) {
-        let capacity = capacity / self.max_datagram_size * self.max_datagram_size;
+        let capacity =
+            burst_capacity(capacity, self.max_burst, self.max_datagram_size);
 
         if self.capacity != capacity {
             self.reset(now);
@@ -135,6 +176,8 @@ impl Pacer {
 
         self.capacity = capacity;
 
+        self.tokens = self.tokens.min(capacity);
+
         self.rate = if let Some(max_rate) = self.max_pacing_rate {
             max_rate.min(rate)
         } else {
@@ -163,6 +206,53 @@ impl Pacer {
             return;
         }
 
+        match self.algorithm {
+            PacerAlgorithm::TokenBucket =>
+                self.send_token_bucket(packet_size, now),
+
+            PacerAlgorithm::LeakyBucket | PacerAlgorithm::Hybrid =>
+                self.send_leaky_bucket(packet_size, now),
+        }
+    }
+
+    fn send_token_bucket(&mut self, packet_size: usize, now: Instant) {
+        if packet_size == 0 {
+            self.next_time = self.next_time.max(now);
+
+            return;
+        }
+
+        let start = self.next_time.max(now);
+
+        // Refill the bucket with the tokens accumulated since the last update.
+        let elapsed = start.saturating_duration_since(self.last_update);
+        let refill = elapsed.as_nanos() * self.rate() as u128 / 1_000_000_000;
+        let refill = usize::try_from(refill).unwrap_or(usize::MAX);
+
+        self.tokens = self.tokens.saturating_add(refill).min(self.capacity);
+
+        self.last_update = start;
+
+        if self.tokens >= packet_size {
+            self.tokens -= packet_size;
+
+            self.next_time = start;
+
+            return;
+        }
+
+        // Wait for the missing tokens, which are then all consumed.
+        let missing = packet_size - self.tokens;
+
+        self.tokens = 0;
+
+        self.next_time =
+            start + Duration::from_secs_f64(missing as f64 / self.rate() as f64);
+
+        self.last_update = self.next_time;
+    }
+
+    fn send_leaky_bucket(&mut self, packet_size: usize, now: Instant) {
         if !self.iv.is_zero() {
             self.next_time = self.next_time.max(now) + self.iv;
 
@@ -207,6 +297,17 @@ impl Pacer {
     }
 }
 
+/// Returns the bucket capacity for the given burst size, rounded to MSS.
+fn burst_capacity(
+    capacity: usize, max_burst: Option<usize>, max_datagram_size: usize,
+) -> usize {
+    match max_burst {
+        Some(packets) => packets * max_datagram_size,
+
+        None => capacity / max_datagram_size * max_datagram_size,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -217,7 +318,15 @@ mod tests {
         let max_burst = datagram_size * 10;
         let pacing_rate = 100_000;
 
-        let mut p = Pacer::new(true, max_burst, pacing_rate, datagram_size, None);
+        let mut p = Pacer::new(
+            true,
+            PacerAlgorithm::LeakyBucket,
+            None,
+            max_burst,
+            pacing_rate,
+            datagram_size,
+            None,
+        );
 
         let now = Instant::now();
 
@@ -250,7 +359,15 @@ mod tests {
         let max_burst = datagram_size * 10;
         let pacing_rate = 100_000;
 
-        let mut p = Pacer::new(true, max_burst, pacing_rate, datagram_size, None);
+        let mut p = Pacer::new(
+            true,
+            PacerAlgorithm::LeakyBucket,
+            None,
+            max_burst,
+            pacing_rate,
+            datagram_size,
+            None,
+        );
 
         let now = Instant::now();
 
@@ -278,6 +395,8 @@ mod tests {
         // Use the max_pacing_rate.
         let mut p = Pacer::new(
             true,
+            PacerAlgorithm::LeakyBucket,
+            None,
             max_burst,
             pacing_rate,
             datagram_size,
@@ -324,4 +443,96 @@ mod tests {
             Duration::from_secs_f64(interval)
         );
     }
+
+    #[test]
+    fn pacer_max_burst() {
+        let datagram_size = 1200;
+        let pacing_rate = 100_000;
+
+        let mut p = Pacer::new(
+            true,
+            PacerAlgorithm::LeakyBucket,
+            Some(20),
+            datagram_size * 10,
+            pacing_rate,
+            datagram_size,
+            None,
+        );
+
+        assert_eq!(p.capacity(), datagram_size * 20);
+
+        // The capacity given by the congestion control is overridden.
+        let now = Instant::now();
+        p.update(datagram_size * 4, pacing_rate, now);
+
+        assert_eq!(p.capacity(), datagram_size * 20);
+
+        // Send 12000 bytes -> the burst isn't over yet.
+        p.send(12000, now);
+        p.send(12000, now);
+
+        assert!(now.duration_since(p.next_time()) < Duration::from_millis(1));
+
+        // Start of a new burst.
+        let now = now + Duration::from_millis(5);
+        p.send(1200, now);
+
+        let interval = (datagram_size * 20) as f64 / pacing_rate as f64;
+
+        assert_eq!(p.next_time() - now, Duration::from_secs_f64(interval));
+    }
+
+    #[test]
+    fn pacer_token_bucket() {
+        let datagram_size = 1200;
+        let max_burst = datagram_size * 10;
+        let pacing_rate = 120_000;
+
+        let mut p = Pacer::new(
+            true,
+            PacerAlgorithm::TokenBucket,
+            None,
+            max_burst,
+            pacing_rate,
+            datagram_size,
+            None,
+        );
+
+        let now = Instant::now();
+
+        // The bucket starts full, so a whole burst is sent right away.
+        for _ in 0..10 {
+            p.send(datagram_size, now);
+            assert_eq!(p.next_time(), now);
+        }
+
+        // The bucket is empty, the next packet waits for its tokens.
+        p.send(datagram_size, now);
+
+        let packet_interval = Duration::from_millis(10);
+
+        assert_eq!(p.next_time(), now + packet_interval);
+
+        // After some time, the accumulated tokens allow a smaller burst.
+        let now = now + packet_interval * 4;
+
+        for _ in 0..3 {
+            p.send(datagram_size, now);
+            assert_eq!(p.next_time(), now);
+        }
+
+        p.send(datagram_size, now);
+        assert_eq!(p.next_time(), now + packet_interval);
+
+        // Tokens don't accumulate beyond the bucket capacity.
+        let now = now + Duration::from_secs(10);
+
+        for _ in 0..10 {
+            p.send(datagram_size, now);
+            assert_eq!(p.next_time(), now);
+        }
+
+        p.send(datagram_size, now);
+        assert_eq!(p.next_time(), now + packet_interval);
+    }
 }
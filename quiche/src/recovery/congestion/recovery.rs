@@ -774,6 +774,50 @@ impl RecoveryOps for LegacyRecovery {
         self.detect_lost_packets(epoch, now, trace_id)
     }
 
+    fn on_ecn_ce(&mut self, _epoch: packet::Epoch, now: Instant) {
+        // The CE marks were reported by the ACK that was just processed, so
+        // the largest packet it newly acknowledged determines whether a new
+        // congestion event starts, as for lost packets.
+        let time_sent = match self.newly_acked.last() {
+            Some(pkt) => pkt.time_sent,
+
+            None => now,
+        };
+
+        if self.congestion.in_congestion_recovery(time_sent) {
+            return;
+        }
+
+        let pkt = Sent {
+            pkt_num: 0,
+            frames: Default::default(),
+            time_sent,
+            time_acked: None,
+            time_lost: None,
+            size: 0,
+            ack_eliciting: false,
+            in_flight: false,
+            delivered: 0,
+            delivered_time: time_sent,
+            first_sent_time: time_sent,
+            is_app_limited: false,
+            tx_in_flight: 0,
+            lost: 0,
+            has_data: false,
+            pmtud: false,
+        };
+
+        (self.congestion.cc_ops.checkpoint)(&mut self.congestion);
+
+        (self.congestion.cc_ops.congestion_event)(
+            &mut self.congestion,
+            self.bytes_in_flight,
+            0,
+            &pkt,
+            now,
+        );
+    }
+
     fn loss_detection_timer(&self) -> Option<Instant> {
         self.loss_timer.time
     }
@@ -830,6 +874,8 @@ impl RecoveryOps for LegacyRecovery {
 
         self.congestion.pacer = pacer::Pacer::new(
             self.congestion.pacer.enabled(),
+            self.congestion.pacer.algorithm(),
+            self.congestion.pacer.max_burst(),
             self.cwnd(),
             0,
             new_max_datagram_size,
@@ -860,7 +906,6 @@ impl RecoveryOps for LegacyRecovery {
         self.bytes_in_flight
     }
 
-    #[cfg(test)]
     fn pacing_rate(&self) -> u64 {
         self.congestion.pacer.rate()
     }
@@ -911,7 +956,7 @@ impl RecoveryOps for LegacyRecovery {
 
     #[cfg(feature = "qlog")]
     fn maybe_qlog(&mut self) -> Option<EventData> {
-        let qlog_metrics = QlogMetrics {
+        let mut qlog_metrics = QlogMetrics {
             min_rtt: *self.rtt_stats.min_rtt,
             smoothed_rtt: self.rtt(),
             latest_rtt: self.rtt_stats.latest_rtt,
@@ -920,8 +965,17 @@ impl RecoveryOps for LegacyRecovery {
             bytes_in_flight: self.bytes_in_flight as u64,
             ssthresh: Some(self.congestion.ssthresh as u64),
             pacing_rate: self.congestion.pacer.rate(),
+            pacing_burst: Some(self.congestion.pacer.capacity() as u64),
+            paced: Some(self.congestion.is_paced()),
+            bbr_state: None,
+            bbr_bw: None,
+            bbr_max_bw: None,
+            bbr_inflight_hi: None,
+            bbr_inflight_lo: None,
         };
 
+        self.congestion.update_qlog_metrics(&mut qlog_metrics);
+
         self.qlog_metrics.maybe_update(qlog_metrics)
     }
 
@@ -141,7 +141,7 @@ fn rollback(_r: &mut Congestion) -> bool {
     true
 }
 
-fn has_custom_pacing() -> bool {
+fn has_custom_pacing(_r: &Congestion) -> bool {
     false
 }
 
@@ -823,6 +823,11 @@ impl RecoveryOps for GRecovery {
         (lost_packets, lost_bytes)
     }
 
+    fn on_ecn_ce(&mut self, _epoch: packet::Epoch, _now: Instant) {
+        // The BBRv2 implementation doesn't model ECN, so CE marks are only
+        // reflected in the connection's statistics.
+    }
+
     fn loss_detection_timer(&self) -> Option<Instant> {
         self.loss_timer.time
     }
@@ -897,7 +902,6 @@ impl RecoveryOps for GRecovery {
         self.bytes_in_flight
     }
 
-    #[cfg(test)]
     fn pacing_rate(&self) -> u64 {
         self.pacer
             .pacing_rate(self.bytes_in_flight, &self.rtt_stats)
@@ -964,6 +968,13 @@ impl RecoveryOps for GRecovery {
             bytes_in_flight: self.bytes_in_flight as u64,
             ssthresh: self.pacer.ssthresh(),
             pacing_rate: self.delivery_rate(),
+            pacing_burst: None,
+            paced: None,
+            bbr_state: None,
+            bbr_bw: None,
+            bbr_max_bw: None,
+            bbr_inflight_hi: None,
+            bbr_inflight_lo: None,
         };
 
         self.qlog_metrics.maybe_update(qlog_metrics)
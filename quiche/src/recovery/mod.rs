@@ -25,6 +25,7 @@
 // SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
 
 use std::str::FromStr;
+use std::sync::Arc;
 use std::time::Duration;
 use std::time::Instant;
 
@@ -40,9 +41,12 @@ use smallvec::SmallVec;
 
 use self::congestion::recovery::LegacyRecovery;
 use self::gcongestion::GRecovery;
+pub(crate) use congestion::PACING_MULTIPLIER;
 pub use gcongestion::BbrBwLoReductionStrategy;
 pub use gcongestion::BbrParams;
 
+pub use congestion::custom::CongestionControl;
+
 // Loss Recovery
 const INITIAL_PACKET_THRESHOLD: u64 = 3;
 
@@ -94,7 +98,30 @@ impl std::fmt::Debug for LossDetectionTimer {
     }
 }
 
-#[derive(Clone, Copy, PartialEq)]
+/// A custom congestion controller, shared by all the paths using the same
+/// configuration.
+///
+/// Two instances are only equal if they share the same controller.
+#[derive(Clone)]
+pub(crate) struct CustomCongestionControl(Arc<dyn CongestionControl>);
+
+impl CustomCongestionControl {
+    pub fn new(cc: Box<dyn CongestionControl>) -> Self {
+        Self(Arc::from(cc))
+    }
+
+    pub fn new_instance(&self) -> Box<dyn CongestionControl> {
+        self.0.new_instance()
+    }
+}
+
+impl PartialEq for CustomCongestionControl {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+#[derive(Clone, PartialEq)]
 pub struct RecoveryConfig {
     pub max_send_udp_payload_size: usize,
     pub max_ack_delay: Duration,
@@ -103,7 +130,11 @@ pub struct RecoveryConfig {
     pub hystart: bool,
     pub pacing: bool,
     pub max_pacing_rate: Option<u64>,
+    pub pacer_algorithm: PacerAlgorithm,
+    pub max_pacing_burst: Option<usize>,
+    pub pacing_gain: f64,
     pub initial_congestion_window_packets: usize,
+    pub custom_cc: Option<CustomCongestionControl>,
 }
 
 impl RecoveryConfig {
@@ -116,8 +147,12 @@ impl RecoveryConfig {
             hystart: config.hystart,
             pacing: config.pacing,
             max_pacing_rate: config.max_pacing_rate,
+            pacer_algorithm: config.pacer_algorithm,
+            max_pacing_burst: config.max_pacing_burst,
+            pacing_gain: config.pacing_gain,
             initial_congestion_window_packets: config
                 .initial_congestion_window_packets,
+            custom_cc: config.custom_cc.clone(),
         }
     }
 }
@@ -174,6 +209,10 @@ pub trait RecoveryOps {
     fn on_path_change(
         &mut self, epoch: packet::Epoch, now: Instant, _trace_id: &str,
     ) -> (usize, usize);
+
+    /// Reacts to an increase of the ECN-CE count reported by the peer for
+    /// packets of the given epoch.
+    fn on_ecn_ce(&mut self, epoch: packet::Epoch, now: Instant);
     fn loss_detection_timer(&self) -> Option<Instant>;
     fn cwnd(&self) -> usize;
     fn cwnd_available(&self) -> usize;
@@ -187,6 +226,8 @@ pub trait RecoveryOps {
 
     fn delivery_rate(&self) -> u64;
 
+    fn pacing_rate(&self) -> u64;
+
     fn max_datagram_size(&self) -> usize;
 
     fn pmtud_update_max_datagram_size(&mut self, new_max_datagram_size: usize);
@@ -207,9 +248,6 @@ pub trait RecoveryOps {
     #[cfg(test)]
     fn in_flight_count(&self, epoch: packet::Epoch) -> usize;
 
-    #[cfg(test)]
-    fn pacing_rate(&self) -> u64;
-
     #[cfg(test)]
     fn pto_count(&self) -> u32;
 
@@ -241,7 +279,13 @@ pub trait RecoveryOps {
 
 impl Recovery {
     pub fn new_with_config(recovery_config: &RecoveryConfig) -> Self {
-        let grecovery = GRecovery::new(recovery_config);
+        // Custom congestion controllers are only supported by the legacy
+        // implementation.
+        let grecovery = match recovery_config.custom_cc {
+            Some(_) => None,
+
+            None => GRecovery::new(recovery_config),
+        };
         if let Some(grecovery) = grecovery {
             Recovery::from(grecovery)
         } else {
@@ -273,6 +317,8 @@ pub enum CongestionControlAlgorithm {
     /// BBRv2 congestion control algorithm implementation from gcongestion
     /// branch. `bbr2_gcongestion` in a string form.
     Bbr2Gcongestion = 4,
+    /// BBRv3 congestion control algorithm. `bbr3` in a string form.
+    BBR3            = 5,
 }
 
 impl FromStr for CongestionControlAlgorithm {
@@ -291,6 +337,42 @@ impl FromStr for CongestionControlAlgorithm {
             #[cfg(feature = "gcongestion")]
             "bbr2" => Ok(CongestionControlAlgorithm::Bbr2Gcongestion),
             "bbr2_gcongestion" => Ok(CongestionControlAlgorithm::Bbr2Gcongestion),
+            "bbr3" => Ok(CongestionControlAlgorithm::BBR3),
+            _ => Err(crate::Error::CongestionControl),
+        }
+    }
+}
+
+/// Available pacer implementations.
+///
+/// The pacer is only used by the Reno, CUBIC, BBR, BBRv2 and BBRv3 congestion
+/// control algorithms, `bbr2_gcongestion` always uses its own pacer.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(C)]
+pub enum PacerAlgorithm {
+    /// Leaky bucket releasing bursts of up to the send quantum at the pacing
+    /// rate (default). `leaky_bucket` in a string form.
+    LeakyBucket = 0,
+    /// Token bucket refilled at the pacing rate, allowing bursts of up to the
+    /// bucket capacity once enough tokens have accumulated. `token_bucket` in
+    /// a string form.
+    TokenBucket = 1,
+    /// Leaky bucket that doesn't pace packets during slow start. `hybrid` in a
+    /// string form.
+    Hybrid      = 2,
+}
+
+impl FromStr for PacerAlgorithm {
+    type Err = crate::Error;
+
+    /// Converts a string to `PacerAlgorithm`.
+    ///
+    /// If `name` is not valid, `Error::CongestionControl` is returned.
+    fn from_str(name: &str) -> std::result::Result<Self, Self::Err> {
+        match name {
+            "leaky_bucket" => Ok(PacerAlgorithm::LeakyBucket),
+            "token_bucket" => Ok(PacerAlgorithm::TokenBucket),
+            "hybrid" => Ok(PacerAlgorithm::Hybrid),
             _ => Err(crate::Error::CongestionControl),
         }
     }
@@ -386,6 +468,13 @@ struct QlogMetrics {
     bytes_in_flight: u64,
     ssthresh: Option<u64>,
     pacing_rate: u64,
+    pacing_burst: Option<u64>,
+    paced: Option<bool>,
+    bbr_state: Option<&'static str>,
+    bbr_bw: Option<u64>,
+    bbr_max_bw: Option<u64>,
+    bbr_inflight_hi: Option<u64>,
+    bbr_inflight_lo: Option<u64>,
 }
 
 #[cfg(feature = "qlog")]
@@ -463,6 +552,64 @@ impl QlogMetrics {
             None
         };
 
+        let new_pacing_burst = if self.pacing_burst != latest.pacing_burst {
+            self.pacing_burst = latest.pacing_burst;
+            emit_event = true;
+            latest.pacing_burst
+        } else {
+            None
+        };
+
+        let new_paced = if self.paced != latest.paced {
+            self.paced = latest.paced;
+            emit_event = true;
+            latest.paced
+        } else {
+            None
+        };
+
+        let new_bbr_state = if self.bbr_state != latest.bbr_state {
+            self.bbr_state = latest.bbr_state;
+            emit_event = true;
+            latest.bbr_state
+        } else {
+            None
+        };
+
+        let new_bbr_bw = if self.bbr_bw != latest.bbr_bw {
+            self.bbr_bw = latest.bbr_bw;
+            emit_event = true;
+            latest.bbr_bw
+        } else {
+            None
+        };
+
+        let new_bbr_max_bw = if self.bbr_max_bw != latest.bbr_max_bw {
+            self.bbr_max_bw = latest.bbr_max_bw;
+            emit_event = true;
+            latest.bbr_max_bw
+        } else {
+            None
+        };
+
+        let new_bbr_inflight_hi =
+            if self.bbr_inflight_hi != latest.bbr_inflight_hi {
+                self.bbr_inflight_hi = latest.bbr_inflight_hi;
+                emit_event = true;
+                latest.bbr_inflight_hi
+            } else {
+                None
+            };
+
+        let new_bbr_inflight_lo =
+            if self.bbr_inflight_lo != latest.bbr_inflight_lo {
+                self.bbr_inflight_lo = latest.bbr_inflight_lo;
+                emit_event = true;
+                latest.bbr_inflight_lo
+            } else {
+                None
+            };
+
         if emit_event {
             // QVis can't use all these fields and they can be large.
             return Some(EventData::MetricsUpdated(
@@ -475,6 +622,13 @@ impl QlogMetrics {
                     bytes_in_flight: new_bytes_in_flight,
                     ssthresh: new_ssthresh,
                     pacing_rate: new_pacing_rate,
+                    pacing_burst: new_pacing_burst,
+                    paced: new_paced,
+                    bbr_state: new_bbr_state.map(|s| s.to_string()),
+                    bbr_bw: new_bbr_bw,
+                    bbr_max_bw: new_bbr_max_bw,
+                    bbr_inflight_hi: new_bbr_inflight_hi,
+                    bbr_inflight_lo: new_bbr_inflight_lo,
                     ..Default::default()
                 },
             ));
@@ -561,6 +715,7 @@ mod tests {
     use crate::ranges;
     use crate::recovery::congestion::PACING_MULTIPLIER;
     use crate::CongestionControlAlgorithm;
+    use rstest::rstest;
     use smallvec::smallvec;
     use std::str::FromStr;
 
@@ -600,6 +755,10 @@ mod tests {
             CongestionControlAlgorithm::from_str("bbr2_gcongestion").unwrap();
         assert_eq!(algo, CongestionControlAlgorithm::Bbr2Gcongestion);
         assert!(recovery_for_alg(algo).gcongestion_enabled());
+
+        let algo = CongestionControlAlgorithm::from_str("bbr3").unwrap();
+        assert_eq!(algo, CongestionControlAlgorithm::BBR3);
+        assert!(!recovery_for_alg(algo).gcongestion_enabled());
     }
 
     #[test]
@@ -610,6 +769,26 @@ mod tests {
         );
     }
 
+    #[test]
+    fn lookup_pacer_algo() {
+        assert_eq!(
+            PacerAlgorithm::from_str("leaky_bucket"),
+            Ok(PacerAlgorithm::LeakyBucket)
+        );
+        assert_eq!(
+            PacerAlgorithm::from_str("token_bucket"),
+            Ok(PacerAlgorithm::TokenBucket)
+        );
+        assert_eq!(
+            PacerAlgorithm::from_str("hybrid"),
+            Ok(PacerAlgorithm::Hybrid)
+        );
+        assert_eq!(
+            PacerAlgorithm::from_str("???"),
+            Err(crate::Error::CongestionControl)
+        );
+    }
+
     #[test]
     fn loss_on_pto() {
         let mut cfg = crate::Config::new(crate::PROTOCOL_VERSION).unwrap();
@@ -1406,6 +1585,95 @@ mod tests {
         );
     }
 
+    #[rstest]
+    fn pacing_algorithm(
+        #[values("leaky_bucket", "token_bucket", "hybrid")] pacer: &str,
+    ) {
+        let mut cfg = crate::Config::new(crate::PROTOCOL_VERSION).unwrap();
+        cfg.set_cc_algorithm(CongestionControlAlgorithm::CUBIC);
+        assert_eq!(cfg.set_pacer_algorithm_name(pacer), Ok(()));
+        cfg.set_pacing_gain(2.0);
+
+        let mut r = Recovery::new(&cfg);
+
+        let mut now = Instant::now();
+
+        let sent = |pkt_num: u64, size: usize, now: Instant| Sent {
+            pkt_num,
+            frames: smallvec![],
+            time_sent: now,
+            time_acked: None,
+            time_lost: None,
+            size,
+            ack_eliciting: true,
+            in_flight: true,
+            delivered: 0,
+            delivered_time: now,
+            first_sent_time: now,
+            is_app_limited: false,
+            tx_in_flight: 0,
+            lost: 0,
+            has_data: false,
+            pmtud: false,
+        };
+
+        // Send out a full initcwnd and get it acked after 50ms.
+        r.on_packet_sent(
+            sent(0, 12000, now),
+            packet::Epoch::Application,
+            HandshakeStatus::default(),
+            now,
+            "",
+        );
+
+        now += Duration::from_millis(50);
+
+        let mut acked = ranges::RangeSet::default();
+        acked.insert(0..1);
+
+        r.on_ack_received(
+            &acked,
+            10,
+            packet::Epoch::Application,
+            HandshakeStatus::default(),
+            now,
+            "",
+        );
+
+        // Send out a full burst, followed by another packet.
+        for (pkt_num, size) in [(1, 6000), (2, 6000), (3, 1000)] {
+            r.on_packet_sent(
+                sent(pkt_num, size, now),
+                packet::Epoch::Application,
+                HandshakeStatus::default(),
+                now,
+                "",
+            );
+        }
+
+        // The pacing gain is applied to the pacing rate.
+        let pacing_rate = (r.cwnd() as f64 * 2.0 / 0.05) as u64;
+        assert_eq!(r.pacing_rate(), pacing_rate);
+
+        let expected = match pacer {
+            // The burst was sent, the next one is delayed.
+            "leaky_bucket" =>
+                now + Duration::from_secs_f64(12000.0 / pacing_rate as f64),
+
+            // The burst emptied the bucket, the last packet only waits for
+            // its own tokens.
+            "token_bucket" =>
+                now + Duration::from_secs_f64(1000.0 / pacing_rate as f64),
+
+            // Packets aren't paced during slow start.
+            "hybrid" => now,
+
+            _ => unreachable!(),
+        };
+
+        assert_eq!(r.get_packet_send_time(), expected);
+    }
+
     #[test]
     fn pmtud_loss_on_timer() {
         let mut cfg = crate::Config::new(crate::PROTOCOL_VERSION).unwrap();
@@ -43,6 +43,7 @@ use crate::range_buf::DefaultBufFactory;
 use crate::BufFactory;
 use crate::Error;
 use crate::Result;
+use crate::StreamScheduler;
 
 const DEFAULT_URGENCY: u8 = 127;
 
@@ -163,6 +164,9 @@ pub struct StreamMap<F: BufFactory = DefaultBufFactory> {
 
     /// The maximum size of a stream window.
     max_stream_window: u64,
+
+    /// The custom scheduler deciding the order of flushable streams, if any.
+    scheduler: Option<Box<dyn StreamScheduler>>,
 }
 
 impl<F: BufFactory> StreamMap<F> {
@@ -378,12 +382,25 @@ impl<F: BufFactory> StreamMap<F> {
         c.remove();
     }
 
+    /// Sets the custom scheduler deciding the order of flushable streams.
+    pub fn set_scheduler(&mut self, scheduler: Box<dyn StreamScheduler>) {
+        self.scheduler = Some(scheduler);
+    }
+
     /// Adds the stream ID to the flushable streams set.
     ///
     /// If the stream was already in the list, this does nothing.
     pub fn insert_flushable(&mut self, priority_key: &Arc<StreamPriorityKey>) {
         if !priority_key.flushable.is_linked() {
             self.flushable.insert(Arc::clone(priority_key));
+
+            if let Some(scheduler) = self.scheduler.as_mut() {
+                scheduler.insert(
+                    priority_key.id,
+                    priority_key.urgency,
+                    priority_key.incremental,
+                );
+            }
         }
     }
 
@@ -399,10 +416,40 @@ impl<F: BufFactory> StreamMap<F> {
         };
 
         c.remove();
+
+        if let Some(scheduler) = self.scheduler.as_mut() {
+            scheduler.remove(priority_key.id);
+        }
+    }
+
+    /// Returns the flushable stream that should send data next.
+    pub fn peek_flushable(&mut self) -> Option<Arc<StreamPriorityKey>> {
+        let next = self
+            .scheduler
+            .as_mut()
+            .and_then(|scheduler| scheduler.next())
+            .and_then(|id| self.streams.get(&id))
+            .filter(|stream| stream.priority_key.flushable.is_linked())
+            .map(|stream| Arc::clone(&stream.priority_key));
+
+        next.or_else(|| self.flushable.front().clone_pointer())
     }
 
-    pub fn peek_flushable(&self) -> Option<Arc<StreamPriorityKey>> {
-        self.flushable.front().clone_pointer()
+    /// Updates the flushable streams set after data was sent on a stream that
+    /// is still flushable.
+    pub fn on_flushed(
+        &mut self, priority_key: &Arc<StreamPriorityKey>, len: usize,
+    ) {
+        if let Some(scheduler) = self.scheduler.as_mut() {
+            scheduler.on_sent(priority_key.id, len);
+            return;
+        }
+
+        if priority_key.incremental {
+            // Shuffle the incremental stream to the back of the queue.
+            self.remove_flushable(priority_key);
+            self.insert_flushable(priority_key);
+        }
     }
 
     /// Updates the priorities of a stream.
@@ -421,7 +468,7 @@ impl<F: BufFactory> StreamMap<F> {
 
         if old.flushable.is_linked() {
             self.remove_flushable(old);
-            self.flushable.insert(Arc::clone(new));
+            self.insert_flushable(new);
         }
     }
 
@@ -513,6 +560,16 @@ impl<F: BufFactory> StreamMap<F> {
         self.local_max_streams_uni_next
     }
 
+    /// Returns the peer's bidirectional stream count limit.
+    pub fn peer_max_streams_bidi(&self) -> u64 {
+        self.peer_max_streams_bidi
+    }
+
+    /// Returns the peer's unidirectional stream count limit.
+    pub fn peer_max_streams_uni(&self) -> u64 {
+        self.peer_max_streams_uni
+    }
+
     /// Returns the number of bidirectional streams that can be created
     /// before the peer's stream count limit is reached.
     pub fn peer_streams_left_bidi(&self) -> u64 {
@@ -2110,4 +2167,5 @@ mod tests {
 }
 
 mod recv_buf;
+pub(crate) mod scheduler;
 mod send_buf;
@@ -25,6 +25,7 @@
 // SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
 
 use std::cmp;
+use std::io;
 use std::time;
 
 use std::collections::BTreeMap;
@@ -203,8 +204,20 @@ impl RecvBuf {
     /// On success the amount of data read, and a flag indicating if there is
     /// no more data in the buffer, are returned as a tuple.
     pub fn emit(&mut self, out: &mut [u8]) -> Result<(usize, bool)> {
+        self.emit_vectored(&mut [io::IoSliceMut::new(out)])
+    }
+
+    /// Writes data from the receive buffer into the given output buffers.
+    ///
+    /// This is the same as [`emit()`], except that data is written to each of
+    /// the output buffers in order, moving to the next one once the current
+    /// one is full.
+    ///
+    /// [`emit()`]: struct.RecvBuf.html#method.emit
+    pub fn emit_vectored(
+        &mut self, out: &mut [io::IoSliceMut],
+    ) -> Result<(usize, bool)> {
         let mut len = 0;
-        let mut cap = out.len();
 
         if !self.ready() {
             return Err(Error::Done);
@@ -217,31 +230,43 @@ impl RecvBuf {
             return Err(Error::StreamReset(e));
         }
 
-        while cap > 0 && self.ready() {
-            let mut entry = match self.data.first_entry() {
-                Some(entry) => entry,
-                None => break,
-            };
+        for out in out.iter_mut() {
+            let mut out_len = 0;
+            let mut cap = out.len();
 
-            let buf = entry.get_mut();
+            while cap > 0 && self.ready() {
+                let mut entry = match self.data.first_entry() {
+                    Some(entry) => entry,
+                    None => break,
+                };
 
-            let buf_len = cmp::min(buf.len(), cap);
+                let buf = entry.get_mut();
 
-            out[len..len + buf_len].copy_from_slice(&buf[..buf_len]);
+                let buf_len = cmp::min(buf.len(), cap);
 
-            self.off += buf_len as u64;
+                out[out_len..out_len + buf_len].copy_from_slice(&buf[..buf_len]);
 
-            len += buf_len;
-            cap -= buf_len;
+                self.off += buf_len as u64;
 
-            if buf_len < buf.len() {
-                buf.consume(buf_len);
+                out_len += buf_len;
+                cap -= buf_len;
 
-                // We reached the maximum capacity, so end here.
-                break;
+                if buf_len < buf.len() {
+                    buf.consume(buf_len);
+
+                    // We reached the maximum capacity, so move on to the next
+                    // output buffer.
+                    break;
+                }
+
+                entry.remove();
             }
 
-            entry.remove();
+            len += out_len;
+
+            if !self.ready() {
+                break;
+            }
         }
 
         // Update consumed bytes for flow control.
@@ -517,6 +542,48 @@ mod tests {
         assert_eq!(recv.off, 19);
     }
 
+    #[test]
+    fn vectored_read() {
+        let mut recv = RecvBuf::new(u64::MAX, DEFAULT_STREAM_WINDOW);
+        assert_eq!(recv.len, 0);
+
+        let mut buf1 = [0; 5];
+        let mut buf2 = [0; 10];
+        let mut buf3 = [0; 10];
+
+        let first = RangeBuf::from(b"something", 0, false);
+        let second = RangeBuf::from(b"helloworld", 9, true);
+
+        assert!(recv.write(first).is_ok());
+        assert!(recv.write(second).is_ok());
+        assert_eq!(recv.len, 19);
+        assert_eq!(recv.off, 0);
+
+        let (len, fin) = recv
+            .emit_vectored(&mut [
+                io::IoSliceMut::new(&mut buf1),
+                io::IoSliceMut::new(&mut buf2),
+            ])
+            .unwrap();
+        assert_eq!(len, 15);
+        assert!(!fin);
+        assert_eq!(&buf1, b"somet");
+        assert_eq!(&buf2, b"hinghellow");
+        assert_eq!(recv.off, 15);
+
+        let (len, fin) = recv
+            .emit_vectored(&mut [
+                io::IoSliceMut::new(&mut []),
+                io::IoSliceMut::new(&mut buf3),
+                io::IoSliceMut::new(&mut buf1),
+            ])
+            .unwrap();
+        assert_eq!(len, 4);
+        assert!(fin);
+        assert_eq!(&buf3[..len], b"orld");
+        assert_eq!(recv.off, 19);
+    }
+
     #[test]
     fn incomplete_read() {
         let mut recv = RecvBuf::new(u64::MAX, DEFAULT_STREAM_WINDOW);
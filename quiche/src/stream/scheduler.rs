@@ -0,0 +1,72 @@
+// Copyright (C) 2025, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Adapter for stream schedulers implemented outside of quiche.
+
+/// Decides which stream gets to send data next.
+///
+/// Custom schedulers are set with [`Config::set_stream_scheduler()`], and
+/// replace the default order of streams with data ready to be sent, which is
+/// based on the priorities set with [`Connection::stream_priority()`]. The
+/// instance passed to the configuration is only used as a template: each
+/// connection gets its own scheduler, created with [`new_instance()`].
+///
+/// quiche keeps track of which streams have data ready to be sent (taking
+/// flow control into account), notifies the scheduler when that changes, and
+/// asks it which stream to pick every time it creates a STREAM frame. This
+/// allows implementing policies such as strict priority, weighted fair
+/// queueing, round-robin or deadline-aware scheduling.
+///
+/// [`Config::set_stream_scheduler()`]: crate::Config::set_stream_scheduler
+/// [`Connection::stream_priority()`]: crate::Connection::stream_priority
+/// [`new_instance()`]: StreamScheduler::new_instance
+pub trait StreamScheduler: Send + Sync {
+    /// Returns a new scheduler with no scheduled streams, for a new
+    /// connection.
+    fn new_instance(&self) -> Box<dyn StreamScheduler>;
+
+    /// Called when a stream has data ready to be sent.
+    ///
+    /// This is also called when the priority of a scheduled stream is changed,
+    /// right after it's removed with the old priority.
+    fn insert(&mut self, stream_id: u64, urgency: u8, incremental: bool);
+
+    /// Called when a stream no longer has data ready to be sent.
+    fn remove(&mut self, stream_id: u64);
+
+    /// Returns the stream that should send data next.
+    ///
+    /// Picking a stream doesn't remove it: it stays scheduled until
+    /// [`remove()`] is called. If `None`, or a stream that isn't scheduled,
+    /// is returned, the default order is used instead.
+    ///
+    /// [`remove()`]: StreamScheduler::remove
+    fn next(&mut self) -> Option<u64>;
+
+    /// Called after `len` bytes of a stream were sent, if the stream still
+    /// has data ready to be sent.
+    fn on_sent(&mut self, _stream_id: u64, _len: usize) {}
+}
@@ -50,6 +50,9 @@ const TLS1_3_VERSION: u16 = 0x0304;
 const TLS_ALERT_ERROR: u64 = 0x100;
 const INTERNAL_ERROR: u64 = 0x01;
 
+const TLS_CLIENT_HELLO: u8 = 0x01;
+const TLS_QUIC_TRANSPORT_PARAMS_EXT: u16 = 0x0039;
+
 #[allow(non_camel_case_types)]
 #[repr(transparent)]
 struct SSL_METHOD {
@@ -696,6 +699,8 @@ pub struct ExData<'a> {
     pub recovery_config: crate::recovery::RecoveryConfig,
 
     pub is_server: bool,
+
+    pub version: u32,
 }
 
 impl<'a> ExData<'a> {
@@ -769,7 +774,8 @@ extern "C" fn set_read_secret(
     if level != crypto::Level::ZeroRTT || ex_data.is_server {
         let secret = unsafe { slice::from_raw_parts(secret, secret_len) };
 
-        let open = match crypto::Open::from_secret(aead, secret) {
+        let open = match crypto::Open::from_secret(aead, secret, ex_data.version)
+        {
             Ok(v) => v,
 
             Err(_) => return 0,
@@ -819,7 +825,8 @@ extern "C" fn set_write_secret(
     if level != crypto::Level::ZeroRTT || !ex_data.is_server {
         let secret = unsafe { slice::from_raw_parts(secret, secret_len) };
 
-        let seal = match crypto::Seal::from_secret(aead, secret) {
+        let seal = match crypto::Seal::from_secret(aead, secret, ex_data.version)
+        {
             Ok(v) => v,
 
             Err(_) => return 0,
@@ -1049,6 +1056,49 @@ pub fn map_result_ptr<'a, T>(bssl_result: *const T) -> Result<&'a T> {
     }
 }
 
+/// Extracts the QUIC transport parameters extension from a TLS ClientHello
+/// message without handing the message to the TLS library.
+///
+/// Returns `Err(Error::BufferTooShort)` if `buf` doesn't contain the whole
+/// message yet, and `Ok(None)` if the extension is not present.
+pub fn client_hello_transport_params(buf: &[u8]) -> Result<Option<&[u8]>> {
+    let mut b = octets::Octets::with_slice(buf);
+
+    if b.get_u8()? != TLS_CLIENT_HELLO {
+        return Err(Error::TlsFail);
+    }
+
+    let len = b.get_u24()? as usize;
+    let msg = b.get_bytes(len)?;
+
+    parse_client_hello_extensions(msg).map_err(|_| Error::TlsFail)
+}
+
+fn parse_client_hello_extensions<'a>(
+    mut msg: octets::Octets<'a>,
+) -> octets::Result<Option<&'a [u8]>> {
+    // Skip legacy_version and random.
+    msg.skip(2 + 32)?;
+
+    // Skip legacy_session_id, cipher_suites and legacy_compression_methods.
+    msg.get_bytes_with_u8_length()?;
+    msg.get_bytes_with_u16_length()?;
+    msg.get_bytes_with_u8_length()?;
+
+    let mut exts = msg.get_bytes_with_u16_length()?;
+
+    while exts.cap() > 0 {
+        let ty = exts.get_u16()?;
+        let data = exts.get_bytes_with_u16_length()?;
+
+        if ty == TLS_QUIC_TRANSPORT_PARAMS_EXT {
+            return Ok(Some(data.buf()));
+        }
+    }
+
+    Ok(None)
+}
+
 fn log_ssl_error() {
     let mut err = [0u8; 1024];
 
@@ -141,6 +141,10 @@ impl Handshake {
         false
     }
 
+    pub fn is_early_data_accepted(&self) -> bool {
+        false
+    }
+
     pub fn set_session(&mut self, session: &[u8]) -> Result<()> {
         unsafe {
             let ctx = SSL_get_SSL_CTX(self.as_ptr());
@@ -544,6 +544,22 @@ impl<H: DriverHooks> H3Driver<H> {
         self.process_h3_data(qconn, stream_id)
     }
 
+    /// Processes a stream reset, or a request cancellation, from the
+    /// [`quiche::h3::Connection`].
+    fn process_h3_reset(
+        &mut self, qconn: &mut QuicheConnection, stream_id: u64, code: u64,
+    ) -> H3ConnectionResult<()> {
+        if let Some(ctx) = self.stream_map.get(&stream_id) {
+            ctx.audit_stats.set_recvd_reset_stream_error_code(code as _);
+        }
+
+        self.h3_event_sender
+            .send(H3Event::ResetStream { stream_id }.into())
+            .map_err(|_| H3ConnectionError::ControllerWentAway)?;
+
+        self.finish_stream(qconn, stream_id, None, None)
+    }
+
     /// Processes a single [`quiche::h3::Event`] received from the underlying
     /// [`quiche::h3::Connection`]. Some events are dispatched to helper
     /// methods.
@@ -564,19 +580,14 @@ impl<H: DriverHooks> H3Driver<H> {
             h3::Event::Data => self.process_h3_data(qconn, stream_id),
             h3::Event::Finished => self.process_h3_fin(qconn, stream_id),
 
-            h3::Event::Reset(code) => {
-                if let Some(ctx) = self.stream_map.get(&stream_id) {
-                    ctx.audit_stats.set_recvd_reset_stream_error_code(code as _);
-                }
+            h3::Event::Reset(code) =>
+                self.process_h3_reset(qconn, stream_id, code),
 
-                self.h3_event_sender
-                    .send(H3Event::ResetStream { stream_id }.into())
-                    .map_err(|_| H3ConnectionError::ControllerWentAway)?;
-
-                self.finish_stream(qconn, stream_id, None, None)
-            },
+            h3::Event::Cancelled(cancellation) =>
+                self.process_h3_reset(qconn, stream_id, cancellation.error_code()),
 
             h3::Event::PriorityUpdate => Ok(()),
+            h3::Event::Metadata { .. } => Ok(()),
             h3::Event::GoAway => Err(H3ConnectionError::GoAway),
         }
     }
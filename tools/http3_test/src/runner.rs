@@ -187,6 +187,7 @@ pub fn run(
             let recv_info = quiche::RecvInfo {
                 from,
                 to: local_addr,
+                ecn: quiche::Ecn::NotEct,
             };
 
             // Process potentially coalesced packets.
@@ -329,7 +330,19 @@ pub fn run(
                         }
                     },
 
-                    Ok((stream_id, quiche::h3::Event::Reset(e))) => {
+                    Ok((
+                        stream_id,
+                        ev @ (quiche::h3::Event::Reset(_) |
+                        quiche::h3::Event::Cancelled(_)),
+                    )) => {
+                        let e = match ev {
+                            quiche::h3::Event::Cancelled(c) => c.error_code(),
+
+                            quiche::h3::Event::Reset(e) => e,
+
+                            _ => unreachable!(),
+                        };
+
                         reqs_complete += 1;
 
                         info!("request was reset by peer with {}", e);
@@ -364,6 +377,8 @@ pub fn run(
 
                     Ok((_goaway_id, quiche::h3::Event::GoAway)) => (),
 
+                    Ok((_, quiche::h3::Event::Metadata { .. })) => (),
+
                     Err(quiche::h3::Error::Done) => {
                         break;
                     },